@@ -0,0 +1,110 @@
+//! Compile-time evaluation of the values of `const` items, the initial values
+//! of `static` items and the lengths of arrays.
+//! The expressions are resolved before they are evaluated,
+//! so the operators are folded by the constant folding of the IR.
+
+use crate::analyser::scope::Scope;
+use crate::analyser::sym_resolver::VarKind;
+use crate::ast::expr::{Expr, ExprVisit};
+use crate::ir;
+use crate::ir::{IRType, Operand};
+use crate::rcc::RccError;
+
+/// Evaluate the constant expression `expr` in `scope`,
+/// `None` if it refers to a constant which has not been evaluated yet.
+pub fn eval_const_expr(expr: &Expr, scope: &Scope) -> Result<Option<Operand>, RccError> {
+    macro_rules! eval {
+        ($expr:expr) => {
+            match eval_const_expr($expr, scope)? {
+                Some(value) => value,
+                None => return Ok(None),
+            }
+        };
+    }
+    let value = match expr {
+        Expr::LitNum(lit_num_expr) => ir::lit_num_operand(lit_num_expr)?,
+        Expr::LitBool(b) => Operand::Bool(*b),
+        Expr::LitChar(c) => Operand::Char(*c),
+        Expr::Grouped(expr) => eval!(expr),
+        Expr::Path(path_expr) => {
+            let ident = path_expr.segments.join("::");
+            match scope.find_variable(&ident) {
+                Some((var_info, _)) if var_info.kind() == VarKind::Const => {
+                    match var_info.const_value() {
+                        Some(value) => value.clone(),
+                        None => return Ok(None),
+                    }
+                }
+                _ => {
+                    return Err(format!(
+                        "attempt to use a non-constant value `{}` in a constant",
+                        ident
+                    )
+                    .into())
+                }
+            }
+        }
+        Expr::Unary(unary_expr) => {
+            let src = eval!(&unary_expr.expr);
+            match ir::un_op_may_constant_fold(&unary_expr.op, &src)? {
+                Some(value) => value,
+                None => {
+                    return Err(format!(
+                        "unary operator `{:?}` is not supported in constants",
+                        unary_expr.op
+                    )
+                    .into())
+                }
+            }
+        }
+        Expr::BinOp(bin_op_expr) => {
+            let lhs = eval!(&bin_op_expr.lhs);
+            let rhs = eval!(&bin_op_expr.rhs);
+            match ir::bin_op_may_constant_fold(&bin_op_expr.bin_op, &lhs, &rhs)? {
+                Some(value) => value,
+                None => {
+                    return Err(format!(
+                        "binary operator `{:?}` is not supported in constants",
+                        bin_op_expr.bin_op
+                    )
+                    .into())
+                }
+            }
+        }
+        Expr::Cast(cast_expr) => {
+            let src = eval!(&cast_expr.expr);
+            let ir_type = IRType::from_type_info(&cast_expr.type_info().borrow())?;
+            match ir::cast_may_constant_fold(&src, ir_type) {
+                Some(value) => value,
+                None => {
+                    return Err(format!("casting `{:?}` is not supported in constants", src).into())
+                }
+            }
+        }
+        _ => return Err("unsupported expression in constants".into()),
+    };
+    Ok(Some(value))
+}
+
+/// Evaluate the initial value of a static, the elems of arrays are flattened in order.
+pub fn eval_static_init(expr: &Expr, scope: &Scope) -> Result<Vec<Operand>, RccError> {
+    match expr {
+        Expr::Grouped(expr) => eval_static_init(expr, scope),
+        Expr::Array(array_expr) if array_expr.is_repeat() => {
+            let elem = eval_static_init(&array_expr.elems[0], scope)?;
+            let len = *array_expr.len_expr.value().unwrap();
+            Ok((0..len).flat_map(|_| elem.iter().cloned()).collect())
+        }
+        Expr::Array(array_expr) => {
+            let mut values = vec![];
+            for elem in array_expr.elems.iter() {
+                values.extend(eval_static_init(elem, scope)?);
+            }
+            Ok(values)
+        }
+        expr => match eval_const_expr(expr, scope)? {
+            Some(value) => Ok(vec![value]),
+            None => Err("the constants used by statics must be evaluated first".into()),
+        },
+    }
+}
@@ -0,0 +1,333 @@
+//! Local type inference in the body of a function.
+//!
+//! The types of variables declared without types and of number literals without suffixes
+//! are type variables: `_`, `{integer}` and `{float}`.
+//! The exprs hold the cells of their types, the cells of the same type variable are
+//! unified by union-find, and all of them are updated once the type is known,
+//! so the uses of a variable before its type is known get the type as well.
+//! The `{integer}` and `{float}` left at the end of the function are `i32` and `f64`.
+//!
+//! Arrays, tuples and pointers of number literals like `[{integer}; 2]` are type variables
+//! as well, the types of their elems and of places like `a[i]` and `t.0` are the elem type
+//! variables of them, so the type inferred for an elem is the type of the same elem of
+//! all the others, e.g. `let a = [1, 2]; let x: u8 = a[0];` makes `a` a `[u8; 2]`.
+
+use crate::analyser::sym_resolver::TypeInfo;
+use crate::rcc::RccError;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+type TypeCell = Rc<RefCell<TypeInfo>>;
+
+struct TypeVar {
+    parent: usize,
+    /// cells of the type variable, kept by the root
+    cells: Vec<TypeCell>,
+    /// source line where the type variable is created or its type is inferred
+    line: u32,
+    /// type variables of the elems at the paths in the type, kept by the root
+    elems: Vec<(Vec<usize>, usize)>,
+    /// type variables of which the type variable is the elem at the path, kept by the root
+    owners: Vec<(usize, Vec<usize>)>,
+}
+
+/// Type variables of a function.
+#[derive(Default)]
+pub struct InferCtx {
+    vars: Vec<TypeVar>,
+    var_of: HashMap<*const RefCell<TypeInfo>, usize>,
+}
+
+fn is_type_var(type_info: &TypeInfo) -> bool {
+    type_info.is_unknown() || type_info.has_number_literal()
+}
+
+/// The type of the elem at `path` in `type_info`, the steps of the path are
+/// the pointee of pointers, the elem of arrays and the indexes of the elems of tuples.
+fn elem_type_of<'a>(type_info: &'a TypeInfo, path: &[usize]) -> Option<&'a TypeInfo> {
+    match (type_info, path.split_first()) {
+        (_, None) => Some(type_info),
+        (TypeInfo::Ptr { type_info, .. }, Some((0, rest)))
+        | (
+            TypeInfo::Array {
+                elem_type: type_info,
+                ..
+            },
+            Some((0, rest)),
+        ) => elem_type_of(type_info, rest),
+        (TypeInfo::Tuple(types), Some((&i, rest))) => elem_type_of(types.get(i)?, rest),
+        _ => None,
+    }
+}
+
+/// `type_info` with the elem at `path` replaced with `elem`.
+fn with_elem_type(type_info: &TypeInfo, path: &[usize], elem: &TypeInfo) -> TypeInfo {
+    let mut type_info = type_info.clone();
+    let mut t = &mut type_info;
+    for &i in path {
+        t = match t {
+            TypeInfo::Ptr { type_info, .. } => type_info.as_mut(),
+            TypeInfo::Array { elem_type, .. } => elem_type.as_mut(),
+            TypeInfo::Tuple(types) => &mut types[i],
+            _ => unreachable!("no elem at {:?} of `{:?}`", path, type_info),
+        };
+    }
+    *t = elem.clone();
+    type_info
+}
+
+/// The type of two type variables of `a` and `b` unified,
+/// `None` if they are of different types.
+fn merge_types(a: &TypeInfo, b: &TypeInfo) -> Option<TypeInfo> {
+    match (a, b) {
+        (TypeInfo::Unknown, t) | (t, TypeInfo::Unknown) => Some(t.clone()),
+        (t, other) if t == other => Some(t.clone()),
+        (t, lit_type) | (lit_type, t) if !t.has_number_literal() && t.can_determine(lit_type) => {
+            Some(t.clone())
+        }
+        (
+            TypeInfo::Ptr { kind, type_info },
+            TypeInfo::Ptr {
+                kind: other_kind,
+                type_info: other_type_info,
+            },
+        ) if kind == other_kind => Some(TypeInfo::Ptr {
+            kind: *kind,
+            type_info: Box::new(merge_types(type_info, other_type_info)?),
+        }),
+        (
+            TypeInfo::Array { elem_type, len },
+            TypeInfo::Array {
+                elem_type: other_elem_type,
+                len: other_len,
+            },
+        ) if len == other_len => Some(TypeInfo::Array {
+            elem_type: Box::new(merge_types(elem_type, other_elem_type)?),
+            len: *len,
+        }),
+        (TypeInfo::Tuple(types), TypeInfo::Tuple(other_types))
+            if types.len() == other_types.len() =>
+        {
+            let types = types.iter().zip(other_types.iter());
+            Some(TypeInfo::Tuple(
+                types
+                    .map(|(a, b)| merge_types(a, b))
+                    .collect::<Option<_>>()?,
+            ))
+        }
+        _ => None,
+    }
+}
+
+impl InferCtx {
+    pub fn new() -> InferCtx {
+        InferCtx::default()
+    }
+
+    fn find(&mut self, var: usize) -> usize {
+        let parent = self.vars[var].parent;
+        if parent == var {
+            return var;
+        }
+        let root = self.find(parent);
+        self.vars[var].parent = root;
+        root
+    }
+
+    /// The root of the type variable of `cell`, cells of unknown types are new type variables.
+    fn root(&mut self, cell: &TypeCell, line: u32) -> Option<usize> {
+        match self.var_of.get(&Rc::as_ptr(cell)) {
+            Some(&var) => Some(self.find(var)),
+            None if is_type_var(&cell.borrow()) => {
+                let var = self.vars.len();
+                self.vars.push(TypeVar {
+                    parent: var,
+                    cells: vec![cell.clone()],
+                    line,
+                    elems: vec![],
+                    owners: vec![],
+                });
+                self.var_of.insert(Rc::as_ptr(cell), var);
+                Some(var)
+            }
+            None => None,
+        }
+    }
+
+    /// Whether the type of `cell` is a type variable, or is inferred from other exprs.
+    pub fn is_inferred(&self, cell: &TypeCell) -> bool {
+        self.var_of.contains_key(&Rc::as_ptr(cell))
+    }
+
+    /// The source line where the type of `cell` comes from.
+    fn site(&mut self, cell: &TypeCell, line: u32) -> u32 {
+        match self.var_of.get(&Rc::as_ptr(cell)) {
+            Some(&var) => {
+                let root = self.find(var);
+                self.vars[root].line
+            }
+            None => line,
+        }
+    }
+
+    fn mismatch(&mut self, expected: &TypeCell, found: &TypeCell, line: u32) -> RccError {
+        let expected_line = self.site(expected, line);
+        let found_line = self.site(found, line);
+        format!(
+            "mismatched types: expected `{}` (line {}), found `{}` (line {})",
+            expected.borrow(),
+            expected_line,
+            found.borrow(),
+            found_line
+        )
+        .into()
+    }
+
+    fn type_of(&self, root: usize) -> TypeInfo {
+        self.vars[root].cells[0].borrow().clone()
+    }
+
+    /// The type of the type variable `root` is `type_info`, the elems and the owners
+    /// of it take the types of the same elems, which are only more specific.
+    fn set_type(&mut self, root: usize, type_info: &TypeInfo) {
+        for cell in self.vars[root].cells.iter() {
+            cell.replace(type_info.clone());
+        }
+        for (path, elem) in self.vars[root].elems.clone() {
+            let elem = self.find(elem);
+            let elem_type = self.type_of(elem);
+            let new_type =
+                elem_type_of(&self.type_of(root), &path).and_then(|t| merge_types(&elem_type, t));
+            match new_type {
+                Some(t) if t != elem_type => self.set_type(elem, &t),
+                _ => {}
+            }
+        }
+        for (owner, path) in self.vars[root].owners.clone() {
+            let owner = self.find(owner);
+            let owner_type = self.type_of(owner);
+            let new_type = elem_type_of(&owner_type, &path)
+                .and_then(|t| merge_types(t, &self.type_of(root)))
+                .map(|t| with_elem_type(&owner_type, &path, &t));
+            match new_type {
+                Some(t) if t != owner_type => self.set_type(owner, &t),
+                _ => {}
+            }
+        }
+    }
+
+    /// Unify the types of `expected` and `found` at source line `line`,
+    /// `!` can be unified with any type without changing it.
+    pub fn unify(
+        &mut self,
+        expected: &TypeCell,
+        found: &TypeCell,
+        line: u32,
+    ) -> Result<(), RccError> {
+        let expected_root = self.root(expected, line);
+        let found_root = self.root(found, line);
+        let expected_type = expected.borrow().clone();
+        let found_type = found.borrow().clone();
+        match (expected_root, found_root) {
+            (Some(e), Some(f)) if e == f => Ok(()),
+            (Some(e), Some(f)) if is_type_var(&expected_type) && is_type_var(&found_type) => {
+                let type_info = match merge_types(&expected_type, &found_type) {
+                    Some(t) => t,
+                    None => return Err(self.mismatch(expected, found, line)),
+                };
+                let cells = std::mem::take(&mut self.vars[f].cells);
+                self.vars[e].cells.extend(cells);
+                let elems = std::mem::take(&mut self.vars[f].elems);
+                self.vars[e].elems.extend(elems);
+                let owners = std::mem::take(&mut self.vars[f].owners);
+                self.vars[e].owners.extend(owners);
+                self.vars[f].parent = e;
+                // the type comes from `found`, or from this line if both are unknown
+                match (&expected_type, &found_type) {
+                    (TypeInfo::Unknown, TypeInfo::Unknown) => self.vars[e].line = line,
+                    (TypeInfo::Unknown, _) => self.vars[e].line = self.vars[f].line,
+                    _ => {}
+                }
+                self.set_type(e, &type_info);
+                Ok(())
+            }
+            (Some(e), _) if is_type_var(&expected_type) => self
+                .bind(e, &expected_type, &found_type, line)
+                .ok_or_else(|| self.mismatch(expected, found, line)),
+            (_, Some(f)) if is_type_var(&found_type) => self
+                .bind(f, &found_type, &expected_type, line)
+                .ok_or_else(|| self.mismatch(expected, found, line)),
+            _ if found_type.is(&expected_type) || expected_type.is_never() => Ok(()),
+            _ => Err(self.mismatch(expected, found, line)),
+        }
+    }
+
+    /// Bind the type variable `root` of `var_type` to the known type `type_info`,
+    /// return `None` if they can not be unified.
+    fn bind(
+        &mut self,
+        root: usize,
+        var_type: &TypeInfo,
+        type_info: &TypeInfo,
+        line: u32,
+    ) -> Option<()> {
+        if type_info.is_never() {
+            return Some(());
+        }
+        if !var_type.is_unknown() && !type_info.can_determine(var_type) {
+            return None;
+        }
+        self.set_type(root, type_info);
+        self.vars[root].line = line;
+        Some(())
+    }
+
+    /// The unknown type of `cell` is a type variable, which must be inferred in the function.
+    pub fn add_var(&mut self, cell: &TypeCell, line: u32) {
+        self.root(cell, line);
+    }
+
+    /// The type of `elem` is the type of the elem at `path` in the type of `owner`,
+    /// e.g. the elems of array exprs and tuple exprs, and the places `a[i]` and `t.0`.
+    pub fn add_elem(&mut self, owner: &TypeCell, path: &[usize], elem: &TypeCell, line: u32) {
+        if let (Some(owner), Some(elem)) = (self.root(owner, line), self.root(elem, line)) {
+            self.vars[owner].elems.push((path.to_vec(), elem));
+            self.vars[elem].owners.push((owner, path.to_vec()));
+        }
+    }
+
+    /// Bind the type variable of `cell` to `type_info`.
+    pub fn resolve(
+        &mut self,
+        cell: &TypeCell,
+        type_info: &TypeInfo,
+        line: u32,
+    ) -> Result<(), RccError> {
+        self.unify(&Rc::new(RefCell::new(type_info.clone())), cell, line)
+    }
+
+    /// `{integer}` and `{float}` which are still not known are `i32` and `f64`,
+    /// including those in the elems.
+    pub fn default_types(&mut self) -> Result<(), RccError> {
+        for var in 0..self.vars.len() {
+            if self.vars[var].parent != var {
+                continue;
+            }
+            let type_info = self.vars[var].cells[0].borrow().clone();
+            let default_type = match type_info {
+                TypeInfo::Unknown => {
+                    return Err(format!(
+                        "type annotations needed: cannot infer the type at line {}",
+                        self.vars[var].line
+                    )
+                    .into());
+                }
+                _ if type_info.has_number_literal() => type_info.default_number_type(),
+                _ => continue,
+            };
+            self.set_type(var, &default_type);
+        }
+        Ok(())
+    }
+}
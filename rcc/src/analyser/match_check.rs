@@ -0,0 +1,498 @@
+//! Type, reachability and exhaustiveness check of the patterns in `match`.
+//!
+//! Only integers, `bool` and `char` can be matched by literal and range patterns,
+//! so their values are treated as integers
+//! (`true` is 1 and chars are their code points),
+//! and split into disjoint intervals at the bounds of the ranges in the patterns.
+//! The values of enums are the indexes of their variants with the values of their fields,
+//! tuples and structs are matched by their fields like enums of one variant.
+//! A pattern is useful if it matches some values not matched by the previous arms,
+//! which is checked recursively on the fields, column by column of the patterns.
+
+use crate::analyser::sym_resolver::TypeInfo;
+use crate::ast::expr::RangeOp;
+use crate::ast::item::{Fields, TypeEnum};
+use crate::ast::pattern::{
+    is_variant_path, LitPattern, Pattern, RangePattern, StructPattern, TupleStructPattern,
+};
+use crate::ast::types::{PtrKind, TypeLitNum};
+use crate::rcc::RccError;
+
+/// Types of the fields of structs and enum variants, which are resolved in the current scope.
+pub type FieldTypes<'r> = dyn Fn(&Fields) -> Result<Vec<TypeInfo>, RccError> + 'r;
+
+/// Patterns of the previous arms, the values they do not match are not covered yet.
+/// The type is the scrutinee's type without references.
+pub struct ValueSpace<'r> {
+    type_info: TypeInfo,
+    /// alternatives of the arms without guards, each row has one column
+    rows: Vec<Vec<Pat>>,
+    field_types: &'r FieldTypes<'r>,
+}
+
+/// A pattern lowered to the values it matches.
+#[derive(Clone)]
+enum Pat {
+    /// wildcards, identifiers and other irrefutable patterns
+    Any,
+    /// integers, `bool` and `char` in `low..=high`
+    Range(i128, i128),
+    /// the `index`th variant of an enum with the patterns of all its fields,
+    /// tuples and structs have one variant
+    Fields(usize, Vec<Pat>),
+}
+
+/// Values of a type split at the bounds of the patterns,
+/// so that a pattern matches all of them or none.
+#[derive(Clone, Copy)]
+enum Ctor {
+    Range(i128, i128),
+    /// the `index`th variant
+    Fields(usize),
+}
+
+impl<'r> ValueSpace<'r> {
+    pub fn new(type_info: &TypeInfo, field_types: &'r FieldTypes<'r>) -> ValueSpace<'r> {
+        ValueSpace {
+            type_info: type_info.clone(),
+            rows: vec![],
+            field_types,
+        }
+    }
+
+    /// Every alternative of the pattern must match some values that are not matched yet.
+    /// Arms with guards may not match, so they do not cover any values.
+    pub fn cover(&mut self, pattern: &Pattern, has_guard: bool) -> Result<(), RccError> {
+        let alternatives = match pattern {
+            Pattern::Or(patterns) => patterns.iter().collect(),
+            p => vec![p],
+        };
+        let types = [self.type_info.clone()];
+        for p in alternatives {
+            let row = vec![self.lower(p, &self.type_info)?];
+            if !self.is_useful(&self.rows, &row, &types)? {
+                return Err(format!("unreachable pattern: `{}`", fmt_pattern(p)).into());
+            }
+            if !has_guard {
+                self.rows.push(row);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn check_exhaustive(&self) -> Result<(), RccError> {
+        let types = [self.type_info.clone()];
+        let mut not_covered: Vec<Ctor> = vec![];
+        for ctor in self.split(&Pat::Any, &self.rows, &self.type_info) {
+            let field_types = self.ctor_field_types(ctor, &self.type_info)?;
+            let rows = specialize(&self.rows, ctor, field_types.len());
+            if self.is_useful(&rows, &vec![Pat::Any; field_types.len()], &field_types)? {
+                match (not_covered.last_mut(), ctor) {
+                    // adjacent intervals are reported as one
+                    (Some(Ctor::Range(_, high)), Ctor::Range(low, h))
+                        if high.checked_add(1) == Some(low) =>
+                    {
+                        *high = h
+                    }
+                    _ => not_covered.push(ctor),
+                }
+            }
+        }
+        if not_covered.is_empty() {
+            return Ok(());
+        }
+        let not_covered: Vec<String> = match &self.type_info {
+            TypeInfo::Bool | TypeInfo::Char | TypeInfo::LitNum(_) | TypeInfo::Enum(_) => {
+                not_covered
+                    .iter()
+                    .flat_map(|ctor| self.fmt_ctor(*ctor))
+                    .collect()
+            }
+            _ => vec!["`_`".to_string()],
+        };
+        Err(format!(
+            "non-exhaustive patterns: {} not covered",
+            not_covered.join(", ")
+        )
+        .into())
+    }
+
+    /// Whether some values matched by `row` are not matched by any of the `rows`,
+    /// the columns of the rows have the types `types`.
+    fn is_useful(
+        &self,
+        rows: &[Vec<Pat>],
+        row: &[Pat],
+        types: &[TypeInfo],
+    ) -> Result<bool, RccError> {
+        if row.is_empty() {
+            return Ok(rows.is_empty());
+        }
+        for ctor in self.split(&row[0], rows, &types[0]) {
+            let mut field_types = self.ctor_field_types(ctor, &types[0])?;
+            let arity = field_types.len();
+            field_types.extend_from_slice(&types[1..]);
+            let row = specialize(&[row.to_vec()], ctor, arity).pop().unwrap();
+            if self.is_useful(&specialize(rows, ctor, arity), &row, &field_types)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Split the values matched by `head` into constructors at the bounds of the first column.
+    fn split(&self, head: &Pat, rows: &[Vec<Pat>], type_info: &TypeInfo) -> Vec<Ctor> {
+        match (head, type_info) {
+            (Pat::Fields(index, _), _) => return vec![Ctor::Fields(*index)],
+            (_, TypeInfo::Enum(type_enum)) => {
+                return (0..unsafe { type_enum.as_ref() }.variants().len())
+                    .map(Ctor::Fields)
+                    .collect()
+            }
+            (_, TypeInfo::Tuple(_) | TypeInfo::Struct { .. }) => return vec![Ctor::Fields(0)],
+            _ => {}
+        }
+        let (low, high) = match head {
+            Pat::Range(low, high) => (*low, *high),
+            _ => (i128::MIN, i128::MAX),
+        };
+        let mut cuts = vec![];
+        for row in rows.iter() {
+            if let Pat::Range(l, h) = row[0] {
+                cuts.push(l);
+                cuts.extend(h.checked_add(1));
+            }
+        }
+        cuts.sort_unstable();
+        cuts.dedup();
+        let mut ctors = vec![];
+        for (l, h) in value_domain(type_info) {
+            let (mut l, h) = (l.max(low), h.min(high));
+            if l > h {
+                continue;
+            }
+            for &cut in cuts.iter() {
+                if l < cut && cut <= h {
+                    ctors.push(Ctor::Range(l, cut - 1));
+                    l = cut;
+                }
+            }
+            ctors.push(Ctor::Range(l, h));
+        }
+        ctors
+    }
+
+    /// Fields of the struct or the enum variant, which are named.
+    fn ctor_fields<'t>(&self, ctor: Ctor, type_info: &'t TypeInfo) -> Option<&'t Fields> {
+        match (ctor, type_info) {
+            (Ctor::Fields(index), TypeInfo::Enum(type_enum)) => {
+                Some(unsafe { type_enum.as_ref() }.variants()[index].fields())
+            }
+            (Ctor::Fields(_), TypeInfo::Struct { fields, .. }) => Some(unsafe { fields.as_ref() }),
+            _ => None,
+        }
+    }
+
+    /// Types of the fields of the constructor without references.
+    fn ctor_field_types(
+        &self,
+        ctor: Ctor,
+        type_info: &TypeInfo,
+    ) -> Result<Vec<TypeInfo>, RccError> {
+        let field_types = match (ctor, type_info) {
+            (Ctor::Fields(_), TypeInfo::Tuple(types)) => types.clone(),
+            _ => match self.ctor_fields(ctor, type_info) {
+                Some(fields) => (self.field_types)(fields)?,
+                None => vec![],
+            },
+        };
+        Ok(field_types
+            .iter()
+            .map(|t| peel_ref_types(t).clone())
+            .collect())
+    }
+
+    /// Lower the pattern which matches the values of `type_info`.
+    fn lower(&self, pattern: &Pattern, type_info: &TypeInfo) -> Result<Pat, RccError> {
+        let pattern = peel_ref_patterns(pattern);
+        let (index, field_patterns) = match pattern {
+            p if p.is_irrefutable() => return Ok(Pat::Any),
+            Pattern::Literal(lit) => return Ok(Pat::Range(lit.value(), lit.value())),
+            Pattern::Range(range) => {
+                let (low, high) = range.bounds();
+                return Ok(Pat::Range(low, high));
+            }
+            Pattern::Tuple(patterns) => (0, patterns.iter().collect()),
+            Pattern::Path(path) | Pattern::Struct(StructPattern { path, .. }) => {
+                (self.variant_index(path, type_info)?, vec![])
+            }
+            Pattern::TupleStruct(TupleStructPattern { path, patterns }) => (
+                self.variant_index(path, type_info)?,
+                patterns.iter().collect(),
+            ),
+            p => return Err(format!("unsupported pattern in match: {:?}", p).into()),
+        };
+        let ctor = Ctor::Fields(index);
+        let field_types = self.ctor_field_types(ctor, type_info)?;
+        // fields not mentioned in the pattern match any values
+        let mut fields = vec![None; field_types.len()];
+        match (pattern, self.ctor_fields(ctor, type_info)) {
+            (Pattern::Struct(struct_pattern), Some(def_fields)) => {
+                for (i, (name, _)) in def_fields.types().iter().enumerate() {
+                    fields[i] = struct_pattern
+                        .fields
+                        .iter()
+                        .find(|f| &f.name == name)
+                        .map(|f| &f.pattern);
+                }
+            }
+            _ => {
+                let rest = field_patterns.iter().position(|p| p == &&Pattern::Rest);
+                let front = rest.unwrap_or(field_patterns.len());
+                let back = field_patterns.len() - rest.map_or(front, |i| i + 1);
+                for i in 0..front {
+                    fields[i] = Some(field_patterns[i]);
+                }
+                for i in 0..back {
+                    fields[field_types.len() - back + i] = Some(field_patterns[front + 1 + i]);
+                }
+            }
+        }
+        let fields = fields
+            .iter()
+            .zip(field_types.iter())
+            .map(|(p, t)| match p {
+                Some(p) => self.lower(p, t),
+                None => Ok(Pat::Any),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Pat::Fields(index, fields))
+    }
+
+    fn fmt_value(&self, value: i128) -> String {
+        match &self.type_info {
+            TypeInfo::Bool => (value != 0).to_string(),
+            TypeInfo::Char => format!("{:?}", char::from_u32(value as u32).unwrap()),
+            _ => value.to_string(),
+        }
+    }
+
+    /// Values of the constructor of the scrutinee's type, booleans are listed one by one.
+    fn fmt_ctor(&self, ctor: Ctor) -> Vec<String> {
+        match (ctor, &self.type_info) {
+            (Ctor::Range(low, high), TypeInfo::Bool) => (low..=high)
+                .map(|v| format!("`{}`", self.fmt_value(v)))
+                .collect(),
+            (Ctor::Range(low, high), _) if low == high => {
+                vec![format!("`{}`", self.fmt_value(low))]
+            }
+            (Ctor::Range(low, high), _) => vec![format!(
+                "`{}..={}`",
+                self.fmt_value(low),
+                self.fmt_value(high)
+            )],
+            (Ctor::Fields(index), TypeInfo::Enum(type_enum)) => {
+                vec![format!(
+                    "`{}`",
+                    fmt_variant(unsafe { type_enum.as_ref() }, index)
+                )]
+            }
+            (Ctor::Fields(_), _) => unreachable!(),
+        }
+    }
+
+    /// Index of the enum variant, structs have one variant.
+    fn variant_index(&self, path: &str, type_info: &TypeInfo) -> Result<usize, RccError> {
+        if !is_variant_path(path) {
+            return Ok(0);
+        }
+        let name = path.rsplit("::").next().unwrap();
+        match type_info {
+            TypeInfo::Enum(type_enum) => match unsafe { type_enum.as_ref() }.variant(name) {
+                Some((index, _)) => Ok(index),
+                None => Err(format!("no variant named `{}`", name).into()),
+            },
+            t => Err(format!(
+                "mismatched types in pattern: expected `{:?}`, found `{}`",
+                t, path
+            )
+            .into()),
+        }
+    }
+}
+
+fn fmt_pattern(pattern: &Pattern) -> String {
+    let fmt_patterns = |patterns: &[Pattern]| {
+        let patterns: Vec<String> = patterns.iter().map(fmt_pattern).collect();
+        patterns.join(", ")
+    };
+    match pattern {
+        Pattern::Identifier(ident_pattern) => ident_pattern.ident().to_string(),
+        Pattern::Wildcard => "_".to_string(),
+        Pattern::Rest => "..".to_string(),
+        Pattern::Path(path) => path.clone(),
+        Pattern::Literal(lit) => fmt_lit(lit),
+        Pattern::Range(range) => format!(
+            "{}{}{}",
+            fmt_lit(&range.lhs),
+            range.range_op,
+            fmt_lit(&range.rhs)
+        ),
+        Pattern::Ref(ref_pattern) => format!(
+            "&{}{}",
+            if ref_pattern.is_mut { "mut " } else { "" },
+            fmt_pattern(&ref_pattern.pattern)
+        ),
+        Pattern::Tuple(patterns) if patterns.len() == 1 => {
+            format!("({},)", fmt_pattern(&patterns[0]))
+        }
+        Pattern::Tuple(patterns) => format!("({})", fmt_patterns(patterns)),
+        Pattern::TupleStruct(tuple_struct_pattern) => format!(
+            "{}({})",
+            tuple_struct_pattern.path,
+            fmt_patterns(&tuple_struct_pattern.patterns)
+        ),
+        Pattern::Struct(struct_pattern) => {
+            let mut fields: Vec<String> = struct_pattern
+                .fields
+                .iter()
+                .map(|f| format!("{}: {}", f.name, fmt_pattern(&f.pattern)))
+                .collect();
+            if struct_pattern.has_rest {
+                fields.push("..".to_string());
+            }
+            format!("{} {{ {} }}", struct_pattern.path, fields.join(", "))
+        }
+        Pattern::Or(patterns) => {
+            let patterns: Vec<String> = patterns.iter().map(fmt_pattern).collect();
+            patterns.join(" | ")
+        }
+    }
+}
+
+fn fmt_lit(lit: &LitPattern) -> String {
+    match lit {
+        LitPattern::Bool(b) => b.to_string(),
+        LitPattern::Char(c) => format!("{:?}", c),
+        LitPattern::Int(value, _) => value.to_string(),
+    }
+}
+
+/// Rows matching the constructor, the first column is replaced by the `arity` fields of it.
+fn specialize(rows: &[Vec<Pat>], ctor: Ctor, arity: usize) -> Vec<Vec<Pat>> {
+    rows.iter()
+        .filter_map(|row| {
+            let fields = match (&row[0], ctor) {
+                (Pat::Any, _) => vec![Pat::Any; arity],
+                (Pat::Range(l, h), Ctor::Range(low, high)) if *l <= low && high <= *h => vec![],
+                (Pat::Fields(i, fields), Ctor::Fields(index)) if *i == index => fields.clone(),
+                _ => return None,
+            };
+            Some(fields.into_iter().chain(row[1..].iter().cloned()).collect())
+        })
+        .collect()
+}
+
+/// All values of the type.
+/// Types which can only be matched by wildcards and identifiers have one value.
+fn value_domain(type_info: &TypeInfo) -> Vec<(i128, i128)> {
+    match type_info {
+        TypeInfo::Bool => vec![(0, 1)],
+        TypeInfo::Char => vec![(0, 0xD7FF), (0xE000, 0x10FFFF)],
+        TypeInfo::LitNum(lit_num) if lit_num.is_integer() => vec![integer_bounds(*lit_num)],
+        TypeInfo::Enum(type_enum) => match unsafe { type_enum.as_ref() }.variants().len() {
+            0 => vec![],
+            n => vec![(0, n as i128 - 1)],
+        },
+        _ => vec![(0, 0)],
+    }
+}
+
+/// The smallest and the largest values of the integer type,
+/// `isize` and `usize` are 32 bits, and values of `u128` larger than `i128::MAX`
+/// are not considered.
+pub fn integer_bounds(lit_num: TypeLitNum) -> (i128, i128) {
+    match lit_num {
+        TypeLitNum::I8 => (i8::MIN as i128, i8::MAX as i128),
+        TypeLitNum::I16 => (i16::MIN as i128, i16::MAX as i128),
+        TypeLitNum::I32 | TypeLitNum::I | TypeLitNum::Isize => (i32::MIN as i128, i32::MAX as i128),
+        TypeLitNum::I64 => (i64::MIN as i128, i64::MAX as i128),
+        TypeLitNum::I128 => (i128::MIN, i128::MAX),
+        TypeLitNum::U8 => (0, u8::MAX as i128),
+        TypeLitNum::U16 => (0, u16::MAX as i128),
+        TypeLitNum::U32 | TypeLitNum::Usize => (0, u32::MAX as i128),
+        TypeLitNum::U64 => (0, u64::MAX as i128),
+        TypeLitNum::U128 => (0, i128::MAX),
+        _ => unreachable!("{:?} is not an integer type", lit_num),
+    }
+}
+
+/// `Enum::Variant`, `Enum::Variant(..)` or `Enum::Variant { .. }` of the `index`th variant
+fn fmt_variant(type_enum: &TypeEnum, index: usize) -> String {
+    let variant = &type_enum.variants()[index];
+    let fields = match variant.fields() {
+        Fields::None => "",
+        Fields::Tuple(_) => "(..)",
+        Fields::Struct(_) => " { .. }",
+    };
+    format!("{}::{}{}", type_enum.name(), variant.name(), fields)
+}
+
+/// Fields of references are matched by the patterns of the referenced values.
+fn peel_ref_types(type_info: &TypeInfo) -> &TypeInfo {
+    match type_info {
+        TypeInfo::Ptr {
+            kind: PtrKind::Ref | PtrKind::MutRef,
+            type_info,
+        } => peel_ref_types(type_info),
+        t => t,
+    }
+}
+
+/// Literal and range patterns match the values through references,
+/// `&1` in `match &x` is the same as `1` in `match x`.
+fn peel_ref_patterns(pattern: &Pattern) -> &Pattern {
+    match pattern {
+        Pattern::Ref(ref_pattern) => peel_ref_patterns(&ref_pattern.pattern),
+        p => p,
+    }
+}
+
+pub fn check_range_pattern_type(range: &RangePattern, type_info: &TypeInfo) -> Result<(), RccError> {
+    if type_info == &TypeInfo::Bool {
+        return Err("only `char` and numeric types are allowed in range patterns".into());
+    }
+    check_lit_pattern_type(&range.lhs, type_info)?;
+    check_lit_pattern_type(&range.rhs, type_info)?;
+    let (low, high) = (range.lhs.value(), range.rhs.value());
+    match range.range_op {
+        RangeOp::DotDotEq if low > high => {
+            Err("lower range bound must be less than or equal to upper".into())
+        }
+        RangeOp::DotDot if low >= high => Err("lower range bound must be less than upper".into()),
+        _ => Ok(()),
+    }
+}
+
+pub fn check_lit_pattern_type(lit: &LitPattern, type_info: &TypeInfo) -> Result<(), RccError> {
+    let is_matched = match (lit, type_info) {
+        (LitPattern::Bool(_), TypeInfo::Bool) | (LitPattern::Char(_), TypeInfo::Char) => true,
+        (LitPattern::Int(_, TypeLitNum::I), TypeInfo::LitNum(lit_num)) => lit_num.is_integer(),
+        (LitPattern::Int(_, t), TypeInfo::LitNum(lit_num)) => t == lit_num,
+        _ => false,
+    };
+    if !is_matched {
+        return Err(format!(
+            "mismatched types in pattern: expected `{:?}`, found `{:?}`",
+            type_info, lit
+        )
+        .into());
+    }
+    if let LitPattern::Int(value, _) = lit {
+        let domain = value_domain(type_info);
+        if *value < domain.first().unwrap().0 || *value > domain.last().unwrap().1 {
+            return Err(format!("literal out of range for `{:?}`: {}", type_info, value).into());
+        }
+    }
+    Ok(())
+}
@@ -1,10 +1,14 @@
 //! Semantic analysis includes:
 //! - Symbol resolving
 //! - Type check
+//! - Local type inference
 //! - Flow control check
 //!
 
 pub mod sym_resolver;
 pub mod scope;
+pub mod const_eval;
+mod infer;
+mod match_check;
 #[cfg(test)]
 mod tests;
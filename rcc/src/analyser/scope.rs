@@ -2,14 +2,17 @@ use crate::analyser::sym_resolver::TypeInfo::*;
 use crate::analyser::sym_resolver::{TypeInfo, VarInfo, VarKind};
 use crate::ast::expr::BlockExpr;
 use crate::ast::file::File;
-use crate::ast::item::{ExternalItem, FnSignature, Item, ItemStruct};
+use crate::ast::item::{
+    ExternalItem, FnParam, FnSignature, Item, ItemFn, ItemImpl, ItemStruct, ItemTrait, TypeEnum,
+};
+use crate::ast::types::{TypeAnnotation, TypeFnPtr};
 use crate::ast::types::TypeLitNum::*;
 use crate::ir::var_name::temp_local_var;
-use crate::rcc::RccError;
+use crate::ir::Operand;
 use lazy_static::lazy_static;
 use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
 use std::ptr::NonNull;
 use std::rc::Rc;
@@ -44,6 +47,15 @@ pub struct Scope {
     pub scope_id: ScopeID,
     father: Option<NonNull<Scope>>,
     pub(crate) types: HashMap<String, TypeInfo>,
+    traits: HashMap<String, NonNull<ItemTrait>>,
+    /// (type, trait) of `impl Trait for Type`
+    trait_impls: HashSet<(String, String)>,
+    generic_fns: HashMap<String, NonNull<ItemFn>>,
+    generic_structs: HashMap<String, NonNull<ItemStruct>>,
+    /// generic impls of the generic structs, `Pair` -> `impl<T> Pair<T>`
+    generic_impls: HashMap<String, Vec<NonNull<ItemImpl>>>,
+    /// type arguments of the instances of generic structs, `Pair<i32>` -> `[i32]`
+    instance_args: HashMap<String, Vec<TypeInfo>>,
     variables: HashMap<String, Vec<VarInfo>>,
     pub cur_stmt_id: u64,
     temp_count: u64,
@@ -57,6 +69,12 @@ impl Scope {
             scope_id,
             father: None,
             types: HashMap::new(),
+            traits: HashMap::new(),
+            trait_impls: HashSet::new(),
+            generic_fns: HashMap::new(),
+            generic_structs: HashMap::new(),
+            generic_impls: HashMap::new(),
+            instance_args: HashMap::new(),
             variables: HashMap::new(),
             cur_stmt_id: 0,
             temp_count: 0,
@@ -80,61 +98,10 @@ impl Scope {
         }
     }
 
-    /// ```
-    /// let mut a;
-    /// ...
-    /// a = 32i32;
-    /// ```
-    pub fn update_variable_type(
-        &self,
-        ident: &str,
-        new_type_info: Rc<RefCell<TypeInfo>>,
-    ) -> Result<(), RccError> {
-        match self.find_variable_mut(ident) {
-            Some((var_info, _)) => match var_info.type_info.partial_cmp(&new_type_info) {
-                Some(o) => match o {
-                    Ordering::Greater | Ordering::Equal => {
-                        if !new_type_info.borrow().is_never() {
-                            var_info.type_info = new_type_info;
-                        }
-                        Ok(())
-                    }
-                    Ordering::Less => Err(RccError::from("invalid type")),
-                },
-                None => Err(RccError::from("invalid type")),
-            },
-            None => Err(RccError::from(format!("variable `{}` not found", ident))),
-        }
-    }
-
-    // Return (var info, scope id)
-    fn find_variable_mut(&self, ident: &str) -> Option<(&mut VarInfo, ScopeID)> {
-        let mut cur_scope: *mut Scope = self as *const Scope as *mut _;
-        loop {
-            let s = unsafe { &mut *cur_scope };
-            if let Some(v) = s.variables.get_mut(ident) {
-                let mut left = 0;
-                let mut right = v.len();
-                if right == 1 {
-                    return Some((unsafe { v.get_unchecked_mut(0) }, s.scope_id));
-                }
-                while left < right {
-                    let mid = (left + right + 1) / 2;
-                    let stmt_id = unsafe { (*v.get_unchecked(mid)).stmt_id() };
-                    // Let stmt and variable using stmt is impossible to be the same.
-                    debug_assert_ne!(stmt_id, self.cur_stmt_id);
-                    if self.cur_stmt_id < stmt_id {
-                        right = mid - 1;
-                    } else {
-                        left = mid;
-                    }
-                }
-                return Some((unsafe { v.get_unchecked_mut(left) }, s.scope_id));
-            } else if let Some(f) = s.father {
-                cur_scope = f.as_ptr();
-            } else {
-                return None;
-            }
+    /// Set the value of the constant `ident` declared in this scope.
+    pub fn set_const_value(&mut self, ident: &str, value: Operand) {
+        if let Some(var_info) = self.variables.get_mut(ident).and_then(|v| v.last_mut()) {
+            var_info.set_const_value(value);
         }
     }
 
@@ -206,11 +173,122 @@ impl Scope {
         }
     }
 
+    pub fn find_trait(&self, ident: &str) -> Option<&ItemTrait> {
+        let mut cur_scope: *const Scope = self;
+        loop {
+            let s = unsafe { &*cur_scope };
+            if let Some(item_trait) = s.traits.get(ident) {
+                return Some(unsafe { item_trait.as_ref() });
+            }
+            cur_scope = s.father?.as_ptr();
+        }
+    }
+
+    pub fn find_generic_fn(&self, ident: &str) -> Option<&ItemFn> {
+        let mut cur_scope: *const Scope = self;
+        loop {
+            let s = unsafe { &*cur_scope };
+            if let Some(item_fn) = s.generic_fns.get(ident) {
+                return Some(unsafe { item_fn.as_ref() });
+            }
+            cur_scope = s.father?.as_ptr();
+        }
+    }
+
+    pub fn find_generic_struct(&self, ident: &str) -> Option<&ItemStruct> {
+        let mut cur_scope: *const Scope = self;
+        loop {
+            let s = unsafe { &*cur_scope };
+            if let Some(item_struct) = s.generic_structs.get(ident) {
+                return Some(unsafe { item_struct.as_ref() });
+            }
+            cur_scope = s.father?.as_ptr();
+        }
+    }
+
+    /// The generic impls of the generic struct `ident` in the scope and its fathers.
+    pub fn find_generic_impls(&self, ident: &str) -> Vec<&ItemImpl> {
+        let mut generic_impls = vec![];
+        let mut cur_scope: *const Scope = self;
+        loop {
+            let s = unsafe { &*cur_scope };
+            if let Some(item_impls) = s.generic_impls.get(ident) {
+                generic_impls.extend(item_impls.iter().map(|p| unsafe { p.as_ref() }));
+            }
+            match s.father {
+                Some(f) => cur_scope = f.as_ptr(),
+                None => return generic_impls,
+            }
+        }
+    }
+
+    pub fn find_instance_args(&self, ident: &str) -> Option<&[TypeInfo]> {
+        let mut cur_scope: *const Scope = self;
+        loop {
+            let s = unsafe { &*cur_scope };
+            if let Some(type_args) = s.instance_args.get(ident) {
+                return Some(type_args);
+            }
+            cur_scope = s.father?.as_ptr();
+        }
+    }
+
+    /// Whether there is an `impl Trait for Type` in the scope or its fathers.
+    pub fn implements(&self, type_name: &str, trait_name: &str) -> bool {
+        let key = (type_name.to_string(), trait_name.to_string());
+        let mut cur_scope: *const Scope = self;
+        loop {
+            let s = unsafe { &*cur_scope };
+            if s.trait_impls.contains(&key) {
+                return true;
+            }
+            if let Some(f) = s.father {
+                cur_scope = f.as_ptr();
+            } else {
+                return false;
+            }
+        }
+    }
+
     /// Add type definitions (functions, structs, etc.) to current scope.
     pub fn add_typedef(&mut self, item: &Item) {
         match item {
+            // generic functions are instantiated when they are called
+            Item::Fn(item_fn) if item_fn.is_generic() => {
+                self.generic_fns
+                    .insert(item_fn.name.clone(), NonNull::from(item_fn));
+            }
             Item::Fn(item_fn) => self.add_type_fn(item_fn),
+            // and so are generic structs when the type arguments are known
+            Item::Struct(item_struct) if item_struct.is_generic() => {
+                self.generic_structs
+                    .insert(item_struct.name().to_string(), NonNull::from(item_struct));
+            }
             Item::Struct(item_struct) => self.add_type_struct(item_struct),
+            Item::Enum(type_enum) => self.add_type_enum(type_enum),
+            // and so are generic impls with the instances of the structs
+            Item::Impl(item_impl) if item_impl.is_generic() => {
+                if let Some(name) = item_impl.generic_name() {
+                    self.generic_impls
+                        .entry(name.to_string())
+                        .or_default()
+                        .push(NonNull::from(item_impl));
+                }
+            }
+            // associated functions are named by their paths
+            Item::Impl(item_impl) => {
+                for item_fn in &item_impl.items {
+                    self.add_type_fn(item_fn);
+                }
+                if let Some(trait_name) = &item_impl.trait_name {
+                    self.trait_impls
+                        .insert((item_impl.self_type.clone(), trait_name.clone()));
+                }
+            }
+            Item::Trait(item_trait) => {
+                self.traits
+                    .insert(item_trait.name.clone(), NonNull::from(item_trait));
+            }
             Item::ExternalBlock(item_external_block) => {
                 for item in &item_external_block.external_items {
                     match item {
@@ -220,11 +298,13 @@ impl Scope {
                     }
                 }
             }
+            // constants and statics are variables, declared when they are resolved
+            Item::Const(_) | Item::Static(_) => {}
             _ => todo!(),
         }
     }
 
-    fn add_type_fn(&mut self, fn_sig: &impl FnSignature) {
+    pub fn add_type_fn(&mut self, fn_sig: &impl FnSignature) {
         let type_info = TypeInfo::from_fn_signature(fn_sig);
         self.types.insert(fn_sig.name(), type_info);
     }
@@ -234,6 +314,50 @@ impl Scope {
         self.types.insert(item_struct.name().to_string(), type_info);
     }
 
+    /// Add the type param of a generic function checked against its `bounds`,
+    /// the param is the fieldless struct `param` with the methods of the bound traits.
+    pub fn add_type_param(&mut self, param: &ItemStruct, bounds: &[String]) {
+        self.add_type_struct(param);
+        let name = param.name();
+        let mut methods = vec![];
+        for bound in bounds.iter() {
+            self.trait_impls.insert((name.to_string(), bound.clone()));
+            let item_trait = match self.find_trait(bound) {
+                Some(item_trait) => item_trait,
+                None => continue,
+            };
+            for trait_fn in item_trait.items.iter() {
+                let mut params = trait_fn.fn_params.type_annotations();
+                let self_type = TypeAnnotation::from(name);
+                params
+                    .iter_mut()
+                    .for_each(|t| t.replace_self_type(&self_type));
+                let mut ret_type = trait_fn.ret_type.clone();
+                ret_type.replace_self_type(&self_type);
+                let has_self = trait_fn.fn_params.params.first().is_some_and(FnParam::is_self);
+                let fn_type = Fn {
+                    vis: item_trait.vis(),
+                    inner: TypeFnPtr::new(params, ret_type),
+                    is_method: has_self,
+                };
+                methods.push((format!("{}::{}", name, trait_fn.name), fn_type));
+            }
+        }
+        self.types.extend(methods);
+    }
+
+    /// Add the instance of a generic struct with its type arguments.
+    pub fn add_struct_instance(&mut self, item_struct: &ItemStruct, type_args: Vec<TypeInfo>) {
+        self.add_type_struct(item_struct);
+        self.instance_args
+            .insert(item_struct.name().to_string(), type_args);
+    }
+
+    fn add_type_enum(&mut self, type_enum: &TypeEnum) {
+        let type_info = TypeInfo::from_type_enum(type_enum);
+        self.types.insert(type_enum.name().to_string(), type_info);
+    }
+
     pub fn set_father(&mut self, father: *mut Scope) {
         self.father = Some(unsafe { NonNull::new_unchecked(father) });
     }
@@ -246,6 +370,7 @@ impl Scope {
 pub struct ScopeStack {
     cur_scope: *mut Scope,
     file_scope: Option<NonNull<Scope>>,
+    file: Option<NonNull<File>>,
     scope_stack: Vec<*mut Scope>,
 }
 
@@ -254,14 +379,19 @@ impl ScopeStack {
         ScopeStack {
             cur_scope: std::ptr::null_mut(),
             file_scope: None,
+            file: None,
             scope_stack: vec![],
         }
     }
 
     pub fn enter_scope(&mut self, block_expr: &mut BlockExpr) {
-        block_expr.scope.set_father(self.cur_scope);
+        self.enter(&mut block_expr.scope);
+    }
+
+    pub fn enter(&mut self, scope: &mut Scope) {
+        scope.set_father(self.cur_scope);
         self.scope_stack.push(self.cur_scope);
-        self.cur_scope = &mut block_expr.scope;
+        self.cur_scope = scope;
     }
 
     pub fn exit_scope(&mut self) {
@@ -289,8 +419,18 @@ impl ScopeStack {
         unsafe { &mut *self.cur_scope }
     }
 
+    pub fn file_scope_mut(&mut self) -> &mut Scope {
+        unsafe { self.file_scope.expect("not in a file").as_mut() }
+    }
+
+    /// The file entered, where the instances of generic items are stored.
+    pub fn file_mut(&mut self) -> &mut File {
+        unsafe { self.file.expect("not in a file").as_mut() }
+    }
+
     pub fn enter_file(&mut self, file: &mut File) {
         self.cur_scope = &mut file.scope;
         self.file_scope = Some(NonNull::new(&mut file.scope).unwrap());
+        self.file = Some(NonNull::from(file));
     }
 }
@@ -1,25 +1,33 @@
+use crate::analyser::const_eval;
+use crate::analyser::infer::InferCtx;
+use crate::analyser::match_check::{
+    check_lit_pattern_type, check_range_pattern_type, integer_bounds, ValueSpace,
+};
 use crate::analyser::scope::{Scope, ScopeStack};
 use crate::analyser::sym_resolver::LoopKind::NotIn;
 use crate::analyser::sym_resolver::TypeInfo::Unknown;
 use crate::ast::expr::{
     ArrayExpr, ArrayIndexExpr, AssignExpr, AssignOp, BinOpExpr, BinOperator, BlockExpr, BreakExpr,
-    CallExpr, Expr, ExprKind, FieldAccessExpr, GroupedExpr, IfExpr, LhsExpr, LoopExpr, PathExpr,
-    RangeExpr, ReturnExpr, StructExpr, TupleExpr, TupleIndexExpr, UnAryExpr, UnOp, WhileExpr,
+    CallExpr, CastExpr, ContinueExpr, EnumVariantExpr, Expr, ExprKind, FieldAccessExpr, ForExpr,
+    GroupedExpr, IfExpr, LhsExpr, LoopExpr, MatchExpr, MethodCallExpr, PathExpr, RangeExpr, ReturnExpr,
+    StructExpr, StructExprField, TupleExpr, TupleIndexExpr, UnAryExpr, UnOp, WhileExpr,
 };
 use crate::ast::expr::{ExprVisit, TypeInfoSetter};
 use crate::ast::file::File;
 use crate::ast::item::{
-    ExternalItem, ExternalItemFn, Fields, FnSignature, Item, ItemExternalBlock, ItemFn, ItemStruct,
-    TypeEnum,
+    EnumVariant, ExternalItem, ExternalItemFn, Fields, FnParam, FnSignature, GenericParam, Item,
+    ItemConst, ItemExternalBlock, ItemFn, ItemImpl, ItemStruct, ItemTrait, TypeEnum,
 };
-use crate::ast::pattern::{IdentPattern, Pattern};
+use crate::ast::pattern::{IdentPattern, LitPattern, Pattern, StructPattern, TupleStructPattern};
 use crate::ast::stmt::{LetStmt, Stmt};
 use crate::ast::types::{PtrKind, TypeAnnotation, TypeFnPtr, TypeLitNum};
 use crate::ast::Visibility;
+use crate::ir::{self, IRType, Operand};
 use crate::rcc::RccError;
 use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
 use std::ops::Deref;
 use std::ptr::NonNull;
 use std::rc::Rc;
@@ -27,6 +35,7 @@ use std::rc::Rc;
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum VarKind {
     Static,
+    StaticMut,
     Const,
     LitConst,
     LocalMut,
@@ -38,6 +47,8 @@ pub struct VarInfo {
     stmt_id: u64,
     kind: VarKind,
     pub type_info: Rc<RefCell<TypeInfo>>,
+    /// value of a constant, known after it is evaluated
+    const_value: Option<Operand>,
 }
 
 impl VarInfo {
@@ -46,9 +57,18 @@ impl VarInfo {
             stmt_id,
             kind,
             type_info,
+            const_value: None,
         }
     }
 
+    pub fn const_value(&self) -> Option<&Operand> {
+        self.const_value.as_ref()
+    }
+
+    pub fn set_const_value(&mut self, value: Operand) {
+        self.const_value = Some(value);
+    }
+
     pub fn stmt_id(&self) -> u64 {
         self.stmt_id
     }
@@ -63,22 +83,33 @@ pub enum TypeInfo {
     Fn {
         vis: Visibility,
         inner: TypeFnPtr,
+        /// methods take `self` as the first param
+        is_method: bool,
     },
 
     FnPtr(TypeFnPtr),
 
     Struct {
         vis: Visibility,
+        name: String,
         fields: NonNull<Fields>,
     },
 
-    Enum(TypeEnum),
+    Enum(NonNull<TypeEnum>),
 
     Ptr {
         kind: PtrKind,
         type_info: Box<TypeInfo>,
     },
 
+    Array {
+        elem_type: Box<TypeInfo>,
+        len: u32,
+    },
+
+    /// `(i32, bool)`, the empty tuple is `Unit`
+    Tuple(Vec<TypeInfo>),
+
     /// primitive type
     /// !
     Never,
@@ -106,6 +137,29 @@ impl TypeInfo {
                 kind: tp.ptr_kind,
                 type_info: Box::new(TypeInfo::from_type_anno(&tp.type_anno, cur_scope)),
             },
+            TypeAnnotation::Array(ta) => TypeInfo::Array {
+                elem_type: Box::new(TypeInfo::from_type_anno(&ta._type, cur_scope)),
+                len: ta.len,
+            },
+            TypeAnnotation::Tuple(types) if types.is_empty() => TypeInfo::Unit,
+            TypeAnnotation::Tuple(types) => TypeInfo::Tuple(
+                types
+                    .iter()
+                    .map(|t| TypeInfo::from_type_anno(t, cur_scope))
+                    .collect(),
+            ),
+            TypeAnnotation::Generic(type_generic) => {
+                let type_args: Vec<TypeInfo> = type_generic
+                    .args
+                    .iter()
+                    .map(|t| TypeInfo::from_type_anno(t, cur_scope))
+                    .collect();
+                if type_args.iter().any(TypeInfo::is_unknown) {
+                    return TypeInfo::Unknown;
+                }
+                let name = format!("{}<{}>", type_generic.name, type_args_string(&type_args));
+                cur_scope.find_def_except_fn(&name)
+            }
             TypeAnnotation::Unknown => TypeInfo::Unknown,
             _ => todo!(),
         }
@@ -116,16 +170,31 @@ impl TypeInfo {
         Self::Fn {
             vis: item.vis(),
             inner: tp_fn_ptr,
+            is_method: item.has_self(),
         }
     }
 
     pub(crate) fn from_item_struct(item: &ItemStruct) -> Self {
         Self::Struct {
             vis: item.vis(),
+            name: item.name().to_string(),
             fields: NonNull::from(item.fields()),
         }
     }
 
+    pub(crate) fn from_type_enum(type_enum: &TypeEnum) -> Self {
+        Self::Enum(NonNull::from(type_enum))
+    }
+
+    /// Name of structs and enums, which are the types that can be implemented.
+    pub fn type_name(&self) -> Option<&str> {
+        match self {
+            TypeInfo::Struct { name, .. } => Some(name),
+            TypeInfo::Enum(type_enum) => Some(unsafe { type_enum.as_ref() }.name()),
+            _ => None,
+        }
+    }
+
     pub fn ref_str() -> TypeInfo {
         TypeInfo::Ptr {
             kind: PtrKind::Ref,
@@ -184,24 +253,136 @@ impl TypeInfo {
         }
     }
 
-    /// type `!` can be coerced into any other type.
+    /// type `!` can be coerced into any other type,
+    /// and pointers can be coerced into pointers to the same type with less permissions.
     pub fn is(&self, other: &Self) -> bool {
-        self == &Self::Never || self == other
+        match (self, other) {
+            (
+                TypeInfo::Ptr { kind, type_info },
+                TypeInfo::Ptr {
+                    kind: other_kind,
+                    type_info: other_type_info,
+                },
+            ) => type_info == other_type_info && kind.coerces_to(*other_kind),
+            _ => self == &Self::Never || self == other,
+        }
     }
 
-    pub fn eq_or_never(&self, other: &Self) -> bool {
-        self == other || self == &Self::Never || other == &Self::Never
+    /// Whether the type of the number literals `lit_type` can be determined as this type.
+    /// Arrays of number literals and empty arrays are determined by their elems,
+    /// tuples are determined if any of their elems is determined.
+    pub fn can_determine(&self, lit_type: &TypeInfo) -> bool {
+        match (self, lit_type) {
+            (
+                TypeInfo::Array { elem_type, len },
+                TypeInfo::Array {
+                    elem_type: lit_elem_type,
+                    len: lit_len,
+                },
+            ) => {
+                len == lit_len
+                    && (elem_type.can_determine(lit_elem_type)
+                        || *len == 0 && lit_elem_type.is_unknown())
+            }
+            (
+                TypeInfo::Ptr { kind, type_info },
+                TypeInfo::Ptr {
+                    kind: lit_kind,
+                    type_info: lit_type_info,
+                },
+            ) => lit_kind.coerces_to(*kind) && type_info.can_determine(lit_type_info),
+            (TypeInfo::Tuple(types), TypeInfo::Tuple(lit_types)) => {
+                types.len() == lit_types.len()
+                    && types != lit_types
+                    && types
+                        .iter()
+                        .zip(lit_types.iter())
+                        .all(|(t, lit_type)| t == lit_type || t.can_determine(lit_type))
+            }
+            _ => self.is_integer() && lit_type.is_i() || self.is_float() && lit_type.is_f(),
+        }
     }
 
     pub fn is_unknown(&self) -> bool {
         self == &TypeInfo::Unknown
     }
 
+    /// Whether the type contains number literals without suffixes.
+    pub fn has_number_literal(&self) -> bool {
+        match self {
+            TypeInfo::Ptr { type_info, .. } => type_info.has_number_literal(),
+            TypeInfo::Array { elem_type, .. } => elem_type.has_number_literal(),
+            TypeInfo::Tuple(types) => types.iter().any(TypeInfo::has_number_literal),
+            _ => self.is_i() || self.is_f(),
+        }
+    }
+
+    /// Number literals without suffixes are `i32` or `f64` by default.
+    pub fn default_number_type(&self) -> TypeInfo {
+        match self {
+            TypeInfo::Ptr { kind, type_info } => TypeInfo::Ptr {
+                kind: *kind,
+                type_info: Box::new(type_info.default_number_type()),
+            },
+            TypeInfo::Array { elem_type, len } => TypeInfo::Array {
+                elem_type: Box::new(elem_type.default_number_type()),
+                len: *len,
+            },
+            TypeInfo::Tuple(types) => {
+                TypeInfo::Tuple(types.iter().map(TypeInfo::default_number_type).collect())
+            }
+            _ if self.is_i() => TypeInfo::LitNum(TypeLitNum::I32),
+            _ if self.is_f() => TypeInfo::LitNum(TypeLitNum::F64),
+            _ => self.clone(),
+        }
+    }
+
     pub fn is_never(&self) -> bool {
         self == &TypeInfo::Never
     }
 }
 
+/// Types in the syntax of rust, which can be parsed as type annotations again.
+impl Display for TypeInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeInfo::Fn { inner, .. } | TypeInfo::FnPtr(inner) => {
+                write!(f, "fn({:?}) -> {:?}", inner.params, inner.ret_type)
+            }
+            TypeInfo::Struct { name, .. } => f.write_str(name),
+            TypeInfo::Enum(type_enum) => f.write_str(unsafe { type_enum.as_ref() }.name()),
+            TypeInfo::Ptr { kind, type_info } => {
+                let kind = match kind {
+                    PtrKind::Ref => "&",
+                    PtrKind::MutRef => "&mut ",
+                    PtrKind::ConstRawPtr => "*const ",
+                    PtrKind::MutRawPtr => "*mut ",
+                };
+                write!(f, "{}{}", kind, type_info)
+            }
+            TypeInfo::Array { elem_type, len } => write!(f, "[{}; {}]", elem_type, len),
+            TypeInfo::Tuple(types) => {
+                let types: Vec<String> = types.iter().map(|t| t.to_string()).collect();
+                match types.len() {
+                    1 => write!(f, "({},)", types[0]),
+                    _ => write!(f, "({})", types.join(", ")),
+                }
+            }
+            TypeInfo::Never => f.write_str("!"),
+            TypeInfo::Str => f.write_str("str"),
+            TypeInfo::Unit => f.write_str("()"),
+            TypeInfo::Bool => f.write_str("bool"),
+            TypeInfo::Char => f.write_str("char"),
+            TypeInfo::LitNum(TypeLitNum::I) => f.write_str("{integer}"),
+            TypeInfo::LitNum(TypeLitNum::F) => f.write_str("{float}"),
+            TypeInfo::LitNum(lit_num) => write!(f, "{}", lit_num),
+            TypeInfo::Unknown => f.write_str("_"),
+        }
+    }
+}
+
+const POINTEE_TYPE_NEEDED: &str = "type annotations needed for the pointee";
+
 impl PartialOrd for TypeInfo {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         if self == other {
@@ -258,6 +439,7 @@ impl PartialOrd for TypeInfo {
 enum LoopKind {
     NotIn,
     While,
+    For,
     Loop(*mut LoopExpr),
 }
 
@@ -272,13 +454,31 @@ pub struct SymbolResolver {
     scope_stack: ScopeStack,
 
     loop_kind: LoopKind,
-    loop_kind_stack: Vec<LoopKind>,
+    loop_label: Option<String>,
+    // (loop kind, label) of outer loops
+    loop_kind_stack: Vec<(LoopKind, Option<String>)>,
 
     cur_fn_ret_type: TypeInfo,
     cur_fn_ret_type_stack: Vec<TypeInfo>,
 
+    /// type variables of the current function
+    infer_ctx: InferCtx,
+    infer_ctx_stack: Vec<InferCtx>,
+    /// source line of the current statement, 0 if unknown
+    cur_line: u32,
+    /// whether the current expression is in an `unsafe` block
+    in_unsafe: bool,
+    /// whether a generic function is checked against the bounds of its type params,
+    /// the instances used by it are declared in the current scope but not resolved
+    in_generic_fn: bool,
+
     // TODO: Operator override tables
     pub override_bin_ops: HashSet<(BinOperator, TypeInfo, TypeInfo)>,
+
+    /// instances of generic functions to be resolved
+    instances: Vec<ItemFn>,
+    /// the next scope id of the instances
+    scope_count: u64,
 }
 
 impl SymbolResolver {
@@ -286,40 +486,71 @@ impl SymbolResolver {
         SymbolResolver {
             scope_stack: ScopeStack::new(),
             loop_kind: NotIn,
+            loop_label: None,
             loop_kind_stack: vec![],
             cur_fn_ret_type: TypeInfo::Unknown,
             cur_fn_ret_type_stack: vec![],
+            infer_ctx: InferCtx::new(),
+            infer_ctx_stack: vec![],
+            cur_line: 0,
+            in_unsafe: false,
+            in_generic_fn: false,
             override_bin_ops: HashSet::new(),
+            instances: vec![],
+            scope_count: 0,
         }
     }
 
-    fn may_update_variable_type(
-        &self,
-        place_expr: &Expr,
-        new_type: Rc<RefCell<TypeInfo>>,
+    /// Unify the types of two exprs at the current line.
+    fn unify(
+        &mut self,
+        expected: &Rc<RefCell<TypeInfo>>,
+        found: &Rc<RefCell<TypeInfo>>,
     ) -> Result<(), RccError> {
-        match place_expr {
-            Expr::Path(path_expr) => {
-                let ident = path_expr.segments.last().unwrap();
-                self.scope_stack
-                    .cur_scope()
-                    .update_variable_type(ident, new_type)?;
-            }
-            Expr::ArrayIndex(_) | Expr::TupleIndex(_) | Expr::FieldAccess(_) => todo!(),
-            _ => {}
+        self.infer_ctx.unify(expected, found, self.cur_line)
+    }
+
+    /// Unify the types of two operands.
+    fn unify_operands(&mut self, lhs: &Expr, rhs: &Expr) -> Result<(), RccError> {
+        self.unify(&lhs.type_info(), &rhs.type_info())
+    }
+
+    /// The blocks of `if` and the arms of `match` are of the same type,
+    /// number literals without suffixes take the type of the others.
+    fn unify_branches(
+        &mut self,
+        expected: &Rc<RefCell<TypeInfo>>,
+        found: &Rc<RefCell<TypeInfo>>,
+        msg: &str,
+    ) -> Result<(), RccError> {
+        if expected.partial_cmp(found).is_none()
+            && !self.infer_ctx.is_inferred(expected)
+            && !self.infer_ctx.is_inferred(found)
+        {
+            return Err(format!(
+                "{}: `{:?}`, `{:?}`",
+                msg,
+                expected.borrow().deref(),
+                found.borrow().deref()
+            )
+            .into());
         }
-        Ok(())
+        self.unify(expected, found)
     }
 
     /// return `TypeInfo::Unknown` if bin_op expr is invalid
     fn primitive_bin_ops(
-        &self,
+        &mut self,
         lhs: &mut Expr,
         bin_op: BinOperator,
         rhs: &mut Expr,
     ) -> Result<Rc<RefCell<TypeInfo>>, RccError> {
         let l_type: Rc<RefCell<TypeInfo>> = lhs.type_info();
         let r_type: Rc<RefCell<TypeInfo>> = rhs.type_info();
+        // number literals without suffixes take the type of the other operand
+        let is_numbers = l_type.borrow().is_number()
+            && r_type.borrow().is_number()
+            && l_type.partial_cmp(&r_type).is_some();
         match bin_op {
             // 3i64 << 2i32
             BinOperator::Shl | BinOperator::Shr => Ok(
@@ -331,19 +562,10 @@ impl SymbolResolver {
             ),
             BinOperator::Plus | BinOperator::Minus | BinOperator::Star | BinOperator::Slash => {
                 match l_type.partial_cmp(&r_type) {
-                    Some(o) => match o {
-                        Ordering::Equal => Ok(l_type),
-                        Ordering::Greater => {
-                            self.may_update_variable_type(lhs, r_type.clone())?;
-                            lhs.set_type_info_ref(r_type.clone());
-                            Ok(r_type)
-                        }
-                        Ordering::Less => {
-                            self.may_update_variable_type(rhs, l_type.clone())?;
-                            rhs.set_type_info_ref(l_type.clone());
-                            Ok(l_type)
-                        }
-                    },
+                    Some(o) => {
+                        self.unify_operands(lhs, rhs)?;
+                        Ok(if o == Ordering::Greater { r_type } else { l_type })
+                    }
                     None => Err(format!(
                         "invalid operand type `{:?}` and `{:?}` for `{:?}`",
                         l_type.borrow().deref(),
@@ -353,69 +575,27 @@ impl SymbolResolver {
                     .into()),
                 }
             }
-            BinOperator::Percent => match (l_type.borrow().deref(), r_type.borrow().deref()) {
-                (TypeInfo::LitNum(l_lit), TypeInfo::LitNum(r_lit)) => {
-                    if l_lit == &TypeLitNum::I && r_lit.is_integer() {
-                        lhs.set_type_info_ref(r_type.clone());
-                    } else if r_lit == &TypeLitNum::I && l_lit.is_integer() {
-                        rhs.set_type_info_ref(l_type.clone())
-                    } else if l_lit != r_lit || !l_lit.is_integer() {
-                        return Ok(Rc::new(RefCell::new(Unknown)));
-                    }
-                    Ok(lhs.type_info())
-                }
-                _ => Ok(Rc::new(RefCell::new(Unknown))),
-            },
+            BinOperator::Percent if is_numbers && l_type.borrow().is_integer() => {
+                self.unify_operands(lhs, rhs)?;
+                Ok(l_type)
+            }
+            BinOperator::Percent => Ok(Rc::new(RefCell::new(Unknown))),
             BinOperator::Lt
             | BinOperator::Gt
             | BinOperator::Le
             | BinOperator::Ge
             | BinOperator::EqEq
             | BinOperator::Ne => {
-                if let TypeInfo::LitNum(l_lit) = l_type.borrow().deref() {
-                    if let TypeInfo::LitNum(r_lit) = r_type.borrow().deref() {
-                        return Ok(if l_lit == r_lit {
-                            Rc::new(RefCell::new(TypeInfo::Bool))
-                        } else if l_lit == &TypeLitNum::I && r_lit.is_integer()
-                            || l_lit == &TypeLitNum::F && r_lit.is_float()
-                        {
-                            if let Expr::LitNum(expr) = lhs {
-                                expr.set_type_info_ref(r_type.clone());
-                            }
-                            Rc::new(RefCell::new(TypeInfo::Bool))
-                        } else if r_lit == &TypeLitNum::I && l_lit.is_integer()
-                            || r_lit == &TypeLitNum::F && l_lit.is_float()
-                        {
-                            if let Expr::LitNum(expr) = rhs {
-                                expr.set_type_info_ref(l_type.clone());
-                            }
-                            Rc::new(RefCell::new(TypeInfo::Bool))
-                        } else {
-                            Rc::new(RefCell::new(Unknown))
-                        });
-                    }
+                if is_numbers {
+                    self.unify_operands(lhs, rhs)?;
+                    return Ok(Rc::new(RefCell::new(TypeInfo::Bool)));
                 }
                 Ok(Rc::new(RefCell::new(Unknown)))
             }
             BinOperator::And | BinOperator::Or | BinOperator::Caret => {
-                if let TypeInfo::LitNum(l_lit) = l_type.borrow().deref() {
-                    if let TypeInfo::LitNum(r_lit) = r_type.borrow().deref() {
-                        return Ok(if l_lit == r_lit {
-                            l_type.clone()
-                        } else if l_lit == &TypeLitNum::I && r_lit.is_integer() {
-                            if let Expr::LitNum(expr) = lhs {
-                                expr.set_type_info_ref(l_type.clone());
-                            }
-                            r_type.clone()
-                        } else if r_lit == &TypeLitNum::I && l_lit.is_integer() {
-                            if let Expr::LitNum(expr) = rhs {
-                                expr.set_type_info_ref(l_type.clone());
-                            }
-                            l_type.clone()
-                        } else {
-                            Rc::new(RefCell::new(Unknown))
-                        });
-                    }
+                if is_numbers && (l_type == r_type || l_type.borrow().is_integer()) {
+                    self.unify_operands(lhs, rhs)?;
+                    return Ok(l_type);
                 } else if l_type.borrow().deref() == &TypeInfo::Bool
                     && r_type.borrow().deref() == &TypeInfo::Bool
                 {
@@ -438,23 +618,71 @@ impl SymbolResolver {
         }
     }
 
+    fn enter_loop(&mut self, loop_kind: LoopKind, label: Option<String>) {
+        let outer_label = std::mem::replace(&mut self.loop_label, label);
+        self.loop_kind_stack.push((self.loop_kind, outer_label));
+        self.loop_kind = loop_kind;
+    }
+
     fn exit_loop(&mut self) {
-        self.loop_kind = self.loop_kind_stack.pop().expect("empty loop kind stack!");
+        let (loop_kind, label) = self.loop_kind_stack.pop().expect("empty loop kind stack!");
+        self.loop_kind = loop_kind;
+        self.loop_label = label;
+    }
+
+    /// Find the loop which `break` or `continue` with `label` refers to
+    fn find_loop(&self, label: &Option<String>, expr_name: &str) -> Result<LoopKind, RccError> {
+        if !self.loop_kind.is_in_loop() {
+            return Err(format!("{} expr can not be out of loop block", expr_name).into());
+        }
+        match label {
+            None => Ok(self.loop_kind),
+            Some(_) if label == &self.loop_label => Ok(self.loop_kind),
+            Some(l) => self
+                .loop_kind_stack
+                .iter()
+                .rev()
+                .find(|(_, outer_label)| outer_label == label)
+                .map(|(loop_kind, _)| *loop_kind)
+                .ok_or_else(|| format!("use of undeclared label `{}`", l).into()),
+        }
     }
 
+    /// Number literals and the variables of their types are determined by the expected type,
+    /// so are the exprs sharing the type variables with them.
     fn try_determine_number_type(
+        &mut self,
         expected_num_type: &TypeInfo,
         expr: &mut (impl ExprVisit + TypeInfoSetter),
-    ) {
+    ) -> Result<(), RccError> {
         let type_info = expr.type_info();
-        let type_info = type_info.borrow();
-
-        if expected_num_type.is_integer() && type_info.is_i()
-            || expected_num_type.is_float() && type_info.is_f()
+        if !expected_num_type.can_determine(&type_info.borrow())
+            || expected_num_type.has_number_literal()
         {
-            std::mem::drop(type_info);
+            return Ok(());
+        }
+        if type_info.borrow().has_number_literal() {
+            self.infer_ctx.resolve(&type_info, expected_num_type, self.cur_line)
+        } else {
             expr.set_type_info(expected_num_type.clone());
+            Ok(())
+        }
+    }
+
+    /// The type of `expr` must be `expected_type`,
+    /// the error tells where the type of `expr` is inferred if it is.
+    fn expect_type(
+        &mut self,
+        expected_type: &TypeInfo,
+        expr: &mut (impl ExprVisit + TypeInfoSetter),
+        err_msg: &str,
+    ) -> Result<(), RccError> {
+        self.try_determine_number_type(expected_type, expr)?;
+        let type_info = expr.type_info();
+        if self.infer_ctx.is_inferred(&type_info) && !type_info.borrow().is(expected_type) {
+            return self.infer_ctx.resolve(&type_info, expected_type, self.cur_line);
         }
+        assert_type_is(expr, expected_type, err_msg)
     }
 
     fn validate_ret_type(&self, type_info: &TypeInfo) -> Result<(), RccError> {
@@ -473,22 +701,125 @@ impl SymbolResolver {
 impl SymbolResolver {
     pub(crate) fn visit_file(&mut self, file: &mut File) -> Result<(), RccError> {
         self.scope_stack.enter_file(file);
+        self.scope_count = file.scope_count;
+        self.add_default_methods(file.items.iter_mut())?;
+        check_duplicate_assoc_fns(&file.items)?;
+        self.instantiate_item_types(file.items.iter())?;
+        self.visit_consts_and_statics(&mut file.items)?;
         for item in file.items.iter_mut() {
             self.visit_item(item)?;
         }
+        // the instances are resolved in the file scope, and may instantiate more functions
+        while let Some(instance) = self.instances.pop() {
+            let mut instance = Box::pin(instance);
+            self.visit_item_fn(&mut instance)?;
+            file.instances.push(instance);
+        }
+        file.scope_count = self.scope_count;
         Ok(())
     }
 
     fn visit_item(&mut self, item: &mut Item) -> Result<(), RccError> {
         match item {
+            Item::Fn(item_fn) if item_fn.is_generic() => {
+                self.check_generics(&item_fn.generics)?;
+                self.check_generic_fn(item_fn)
+            }
+            Item::Struct(item_struct) if item_struct.is_generic() => {
+                self.visit_generic_struct(item_struct)
+            }
+            Item::Impl(item_impl) if item_impl.is_generic() => {
+                self.check_generics(&item_impl.generics)?;
+                self.check_generic_impl(item_impl)
+            }
             Item::Fn(item_fn) => self.visit_item_fn(item_fn),
+            Item::Trait(item_trait) => self.visit_item_trait(item_trait),
             Item::Struct(item_struct) => self.visit_item_struct(item_struct),
+            Item::Enum(type_enum) => self.visit_item_enum(type_enum),
             Item::ExternalBlock(external_block) => self.visit_item_external_block(external_block),
+            Item::Impl(item_impl) => self.visit_item_impl(item_impl),
+            // resolved before the other items, see `visit_consts_and_statics`
+            Item::Const(_) | Item::Static(_) => Ok(()),
             _ => unimplemented!(),
         }
     }
 
+    /// Constants and statics are declared in the file scope before the other items are resolved,
+    /// the constants are evaluated in the order of their dependencies.
+    fn visit_consts_and_statics(&mut self, items: &mut [Item]) -> Result<(), RccError> {
+        for item in items.iter() {
+            let (name, kind, type_anno) = match item {
+                Item::Const(c) => (&c.name, VarKind::Const, &c.type_anno),
+                Item::Static(s) if s.is_mut => (&s.name, VarKind::StaticMut, &s.type_anno),
+                Item::Static(s) => (&s.name, VarKind::Static, &s.type_anno),
+                _ => continue,
+            };
+            let cur_scope = self.scope_stack.cur_scope_mut();
+            if cur_scope.find_variable(name).is_some() {
+                return Err(format!("the name `{}` is defined multiple times", name).into());
+            }
+            let type_info = TypeInfo::from_type_anno(type_anno, cur_scope);
+            if type_info.is_unknown() {
+                return Err(format!("cannot find type `{:?}`", type_anno).into());
+            }
+            cur_scope.add_variable(name, kind, Rc::new(RefCell::new(type_info)));
+        }
+        for item in items.iter_mut() {
+            if let Item::Const(c) = item {
+                self.visit_const_or_static_expr(&c.name, &c.type_anno, &mut c.expr)?;
+            }
+        }
+
+        let mut pending: Vec<&ItemConst> = items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Const(item_const) => Some(item_const),
+                _ => None,
+            })
+            .collect();
+        while !pending.is_empty() {
+            let mut not_evaluated = vec![];
+            for item_const in pending.iter() {
+                let cur_scope = self.scope_stack.cur_scope_mut();
+                match const_eval::eval_const_expr(&item_const.expr, cur_scope)? {
+                    Some(value) => cur_scope.set_const_value(&item_const.name, value),
+                    None => not_evaluated.push(*item_const),
+                }
+            }
+            if not_evaluated.len() == pending.len() {
+                return Err(format!(
+                    "cycle detected when evaluating constant `{}`",
+                    pending[0].name
+                )
+                .into());
+            }
+            pending = not_evaluated;
+        }
+
+        // the initializers of statics may use the constants, like `[0; LEN]`
+        for item in items.iter_mut() {
+            if let Item::Static(s) = item {
+                self.visit_const_or_static_expr(&s.name, &s.type_anno, &mut s.expr)?;
+                const_eval::eval_static_init(&s.expr, self.scope_stack.cur_scope())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_const_or_static_expr(
+        &mut self,
+        name: &str,
+        type_anno: &TypeAnnotation,
+        expr: &mut Expr,
+    ) -> Result<(), RccError> {
+        let type_info = TypeInfo::from_type_anno(type_anno, self.scope_stack.cur_scope());
+        self.visit_expr(expr)?;
+        self.expect_type(&type_info, expr, &format!("mismatched types in `{}`", name))
+    }
+
     fn visit_expr(&mut self, expr: &mut Expr) -> Result<(), RccError> {
+        self.resolve_enum_variant(expr)?;
+        self.resolve_struct_ctor(expr)?;
         let result = match expr {
             Expr::Path(path_expr) => self.visit_path_expr(path_expr),
             Expr::LitNum(lit_num_expr) => Ok(()),
@@ -501,23 +832,28 @@ impl SymbolResolver {
             // Expr::Range(range_expr) => self.visit_range_expr(range_expr),
             Expr::BinOp(bin_op_expr) => self.visit_bin_op_expr(bin_op_expr),
             Expr::Grouped(grouped_expr) => self.visit_grouped_expr(grouped_expr),
-            // Expr::Array(array_expr) => self.visit_array_expr(array_expr),
-            // Expr::ArrayIndex(array_index_expr) => self.visit_array_index_expr(array_index_expr),
-            // Expr::Tuple(tuple_expr) => self.visit_tuple_expr(tuple_expr),
-            // Expr::TupleIndex(tuple_index_expr) => self.visit_tuple_index_expr(tuple_index_expr),
-            // Expr::Struct(struct_expr) => self.visit_struct_expr(struct_expr),
+            Expr::Array(array_expr) => self.visit_array_expr(array_expr),
+            Expr::ArrayIndex(array_index_expr) => self.visit_array_index_expr(array_index_expr),
+            Expr::Tuple(tuple_expr) => self.visit_tuple_expr(tuple_expr),
+            Expr::TupleIndex(tuple_index_expr) => self.visit_tuple_index_expr(tuple_index_expr),
+            Expr::Struct(struct_expr) => self.visit_struct_expr(struct_expr),
+            Expr::EnumVariant(enum_variant_expr) => self.visit_enum_variant_expr(enum_variant_expr),
             Expr::Call(call_expr) => self.visit_call_expr(call_expr),
-            // Expr::FieldAccess(field_access_expr) => self.visit_field_access_expr(field_access_expr),
+            Expr::MethodCall(_) => self.visit_method_call(expr),
+            Expr::FieldAccess(field_access_expr) => self.visit_field_access_expr(field_access_expr),
             Expr::While(while_expr) => self.visit_while_expr(while_expr),
             Expr::Loop(loop_expr) => self.visit_loop_expr(loop_expr),
+            Expr::For(for_expr) => self.visit_for_expr(for_expr),
             Expr::If(if_expr) => self.visit_if_expr(if_expr),
+            Expr::Match(match_expr) => self.visit_match_expr(match_expr),
             Expr::Return(return_expr) => self.visit_return_expr(return_expr),
             Expr::Break(break_expr) => self.visit_break_expr(break_expr),
+            Expr::Continue(continue_expr) => self.visit_continue_expr(continue_expr),
+            Expr::Cast(cast_expr) => self.visit_cast_expr(cast_expr),
             _ => unimplemented!(),
         };
-        debug_assert_ne!(
-            ExprKind::Unknown,
-            expr.kind(),
+        debug_assert!(
+            result.is_err() || expr.kind() != ExprKind::Unknown,
             "unknown expr kind: {:?}",
             expr
         );
@@ -527,7 +863,10 @@ impl SymbolResolver {
     fn visit_lhs_expr(&mut self, lhs_expr: &mut LhsExpr) -> Result<(), RccError> {
         let r = match lhs_expr {
             LhsExpr::Path(expr) => self.visit_path_expr(expr)?,
-            _ => todo!("visit lhs expr"),
+            LhsExpr::ArrayIndex(expr) => self.visit_array_index_expr(expr)?,
+            LhsExpr::TupleIndex(expr) => self.visit_tuple_index_expr(expr)?,
+            LhsExpr::FieldAccess(expr) => self.visit_field_access_expr(expr)?,
+            LhsExpr::Deref(expr) => self.visit_unary_expr(expr)?,
         };
         Ok(r)
     }
@@ -538,32 +877,38 @@ impl SymbolResolver {
 
     fn visit_item_fn(&mut self, item_fn: &mut ItemFn) -> Result<(), RccError> {
         // enter
+        let outer_unsafe = std::mem::replace(&mut self.in_unsafe, false);
         let mut temp_ret_type = Unknown;
         std::mem::swap(&mut self.cur_fn_ret_type, &mut temp_ret_type);
         self.cur_fn_ret_type_stack.push(temp_ret_type);
         self.cur_fn_ret_type =
             TypeInfo::from_type_anno(&item_fn.ret_type, self.scope_stack.cur_scope());
+        let outer_infer_ctx = std::mem::replace(&mut self.infer_ctx, InferCtx::new());
+        self.infer_ctx_stack.push(outer_infer_ctx);
 
         // visit params of function
         for param in item_fn.fn_params.params.iter() {
-            match &param.pattern {
-                Pattern::Identifier(ident_pattern) => item_fn.fn_block.scope.add_variable(
-                    ident_pattern.ident(),
-                    if ident_pattern.is_mut() {
-                        VarKind::LocalMut
-                    } else {
-                        VarKind::Local
-                    },
-                    Rc::new(RefCell::new(TypeInfo::from_type_anno(
-                        &param._type,
-                        self.scope_stack.cur_scope(),
-                    ))),
-                ),
+            if !param.pattern.is_irrefutable() {
+                return Err(format!(
+                    "refutable pattern in function argument: {:?}",
+                    param.pattern
+                )
+                .into());
+            }
+            let type_info = Rc::new(RefCell::new(TypeInfo::from_type_anno(
+                &param._type,
+                self.scope_stack.cur_scope(),
+            )));
+            let mut bindings = vec![];
+            self.visit_pattern(&param.pattern, type_info, BindingMode::Move, &mut bindings)?;
+            for (ident, kind, type_info) in bindings {
+                item_fn.fn_block.scope.add_variable(&ident, kind, type_info);
             }
         }
         self.visit_block_expr(&mut item_fn.fn_block)?;
         if item_fn.fn_block.last_expr.is_some() {
-            Self::try_determine_number_type(&self.cur_fn_ret_type, &mut item_fn.fn_block);
+            let ret_type = self.cur_fn_ret_type.clone();
+            self.try_determine_number_type(&ret_type, &mut item_fn.fn_block)?;
             let type_info = item_fn.fn_block.type_info();
             let t = type_info.borrow();
             let tp = t.deref();
@@ -582,117 +927,1193 @@ impl SymbolResolver {
             self.validate_ret_type(&type_info)?;
         }
 
+        self.infer_ctx.default_types()?;
+
         // restore
         self.cur_fn_ret_type = self
             .cur_fn_ret_type_stack
             .pop()
             .expect("empty cur_fn_ret_type_stack!");
+        self.infer_ctx = self.infer_ctx_stack.pop().expect("empty infer_ctx_stack!");
+        self.in_unsafe = outer_unsafe;
         Ok(())
     }
 
+    /// The types of the fields must exist,
+    /// and a struct containing itself without indirection has infinite size.
     fn visit_item_struct(&mut self, item_struct: &mut ItemStruct) -> Result<(), RccError> {
+        let field_types = self.field_types(item_struct.fields())?;
+        self.check_type_size(
+            item_struct.name(),
+            TypeInfo::from_item_struct(item_struct),
+            field_types,
+        )
+    }
+
+    /// Like structs, and the discriminants must be different and fit in the type of the discriminant.
+    /// Explicit discriminants of enums with fields need `#[repr(inttype)]`.
+    fn visit_item_enum(&mut self, type_enum: &mut TypeEnum) -> Result<(), RccError> {
+        let mut field_types = vec![];
+        for variant in type_enum.variants() {
+            field_types.extend(self.field_types(variant.fields())?);
+        }
+        self.check_type_size(
+            type_enum.name(),
+            TypeInfo::from_type_enum(type_enum),
+            field_types,
+        )?;
+
+        if !type_enum.is_fieldless()
+            && type_enum.repr().int_type.is_none()
+            && type_enum.variants().iter().any(|v| v.discriminant().is_some())
+        {
+            return Err(format!(
+                "`#[repr(inttype)]` must be specified for enum `{}` with fields and explicit discriminants",
+                type_enum.name()
+            )
+            .into());
+        }
+        let discriminant_type = type_enum.discriminant_type();
+        let (low, high) = integer_bounds(discriminant_type);
+        let mut discriminants = HashSet::new();
+        for d in type_enum.discriminants() {
+            if d < low || d > high {
+                return Err(format!(
+                    "discriminant value `{}` is out of range for `{:?}`",
+                    d, discriminant_type
+                )
+                .into());
+            }
+            if !discriminants.insert(d) {
+                return Err(format!("discriminant value `{}` assigned more than once", d).into());
+            }
+        }
         Ok(())
     }
 
-    fn visit_item_external_block(
-        &mut self,
-        external_block: &mut ItemExternalBlock,
+    fn field_types(&self, fields: &Fields) -> Result<Vec<TypeInfo>, RccError> {
+        let cur_scope = self.scope_stack.cur_scope();
+        let mut field_types = vec![];
+        for (_, type_anno) in fields.types() {
+            let type_info = TypeInfo::from_type_anno(type_anno, cur_scope);
+            if type_info.is_unknown() {
+                return Err(format!("cannot find type `{:?}`", type_anno).into());
+            }
+            field_types.push(type_info);
+        }
+        Ok(field_types)
+    }
+
+    /// A type `this` containing itself in the `field_types` without indirection has infinite size.
+    fn check_type_size(
+        &self,
+        name: &str,
+        this: TypeInfo,
+        mut field_types: Vec<TypeInfo>,
     ) -> Result<(), RccError> {
-        for item in &mut external_block.external_items {
-            match item {
-                ExternalItem::Fn(f) => {
-                    self.visit_external_item_fn(f)?;
+        let cur_scope = self.scope_stack.cur_scope();
+        let mut visited = HashSet::new();
+        while let Some(type_info) = field_types.pop() {
+            if type_info == this {
+                return Err(format!("recursive type `{}` has infinite size", name).into());
+            }
+            match type_info {
+                TypeInfo::Struct { fields, .. } => {
+                    if visited.insert(type_info) {
+                        for (_, type_anno) in unsafe { fields.as_ref() }.types() {
+                            field_types.push(TypeInfo::from_type_anno(type_anno, cur_scope));
+                        }
+                    }
                 }
+                TypeInfo::Enum(type_enum) => {
+                    if visited.insert(type_info) {
+                        for variant in unsafe { type_enum.as_ref() }.variants() {
+                            for (_, type_anno) in variant.fields().types() {
+                                field_types.push(TypeInfo::from_type_anno(type_anno, cur_scope));
+                            }
+                        }
+                    }
+                }
+                TypeInfo::Array { elem_type, .. } => field_types.push(*elem_type),
+                TypeInfo::Tuple(types) => field_types.extend(types),
+                _ => {}
             }
         }
         Ok(())
     }
 
-    fn visit_external_item_fn(
-        &mut self,
-        _external_item_fn: &mut ExternalItemFn,
-    ) -> Result<(), RccError> {
-        // do nothing
+    /// Only structs and enums have inherent impls, traits can be implemented for any type.
+    /// `Self` is the implemented type in the associated functions.
+    fn visit_item_impl(&mut self, item_impl: &mut ItemImpl) -> Result<(), RccError> {
+        let self_type = self
+            .scope_stack
+            .cur_scope()
+            .find_def_except_fn(&item_impl.self_type);
+        match self_type {
+            TypeInfo::Unknown => {
+                return Err(
+                    format!("cannot find type `{}` in this scope", item_impl.self_type).into(),
+                )
+            }
+            _ if item_impl.trait_name.is_some() => self.check_trait_impl(item_impl)?,
+            TypeInfo::Struct { .. } | TypeInfo::Enum(_) => {}
+            _ => {
+                return Err(format!(
+                    "cannot define inherent `impl` for the primitive type `{}`",
+                    item_impl.self_type
+                )
+                .into())
+            }
+        }
+        for item_fn in item_impl.items.iter_mut() {
+            item_fn
+                .fn_block
+                .scope
+                .types
+                .insert("Self".to_string(), self_type.clone());
+            self.visit_item_fn(item_fn)?;
+        }
         Ok(())
     }
 
-    fn visit_stmt(&mut self, stmt: &mut Stmt) -> Result<(), RccError> {
-        match stmt {
-            Stmt::Semi => Ok(()),
-            Stmt::Item(item) => self.visit_item(item),
-            Stmt::Let(let_stmt) => self.visit_let_stmt(let_stmt),
-            Stmt::ExprStmt(expr) => {
-                self.visit_expr(expr)?;
-                let t = expr.type_info();
-                let tp = t.borrow();
-                let type_info = tp.deref();
-                if expr.with_block() && type_info != &TypeInfo::Unit && !type_info.is_never() {
+    /// The methods must be declared in the trait with the same signatures,
+    /// and the methods without default bodies must be implemented.
+    fn check_trait_impl(&self, item_impl: &ItemImpl) -> Result<(), RccError> {
+        let trait_name = item_impl.trait_name.as_ref().unwrap();
+        let item_trait = match self.scope_stack.cur_scope().find_trait(trait_name) {
+            Some(item_trait) => item_trait,
+            None => return Err(format!("cannot find trait `{}` in this scope", trait_name).into()),
+        };
+        for item_fn in item_impl.items.iter() {
+            let fn_name = &item_fn.name[item_impl.self_type.len() + 2..];
+            let trait_fn = match item_trait.find_fn(fn_name) {
+                Some(trait_fn) => trait_fn,
+                None => {
                     return Err(format!(
-                        "invalid type for expr stmt: expected `()`, found {:?}",
-                        type_info
+                        "method `{}` is not a member of trait `{}`",
+                        fn_name, trait_name
                     )
-                    .into());
+                    .into())
                 }
-                Ok(())
+            };
+            let mut params = trait_fn.fn_params.type_annotations();
+            params
+                .iter_mut()
+                .for_each(|t| t.replace_self_type(&item_impl.self_type_anno));
+            let mut ret_type = trait_fn.ret_type.clone();
+            ret_type.replace_self_type(&item_impl.self_type_anno);
+            let has_self = trait_fn.fn_params.params.first().is_some_and(FnParam::is_self);
+            if params != item_fn.fn_params.type_annotations()
+                || ret_type != item_fn.ret_type
+                || has_self != item_fn.has_self()
+            {
+                return Err(format!(
+                    "method `{}` has an incompatible type for trait `{}`",
+                    fn_name, trait_name
+                )
+                .into());
             }
         }
+        let missing: Vec<&str> = item_trait
+            .items
+            .iter()
+            .filter(|trait_fn| trait_fn.default.is_none())
+            .map(|trait_fn| trait_fn.name.as_str())
+            .filter(|name| {
+                let path = format!("{}::{}", item_impl.self_type, name);
+                item_impl.items.iter().all(|item_fn| item_fn.name != path)
+            })
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "not all trait items implemented, missing: `{}`",
+                missing.join("`, `")
+            )
+            .into());
+        }
+        Ok(())
     }
 
-    fn visit_let_stmt(&mut self, let_stmt: &mut LetStmt) -> Result<(), RccError> {
-        let expr_type_info = if let Some(expr) = &mut let_stmt.rhs {
-            self.visit_expr(expr)?;
-            if let Some(type_anno) = &let_stmt._type {
-                let anno_type_info =
-                    TypeInfo::from_type_anno(type_anno, self.scope_stack.cur_scope());
-                Self::try_determine_number_type(&anno_type_info, expr);
-                let t = expr.type_info();
-                let tp = t.borrow();
-                let expr_type_info = tp.deref();
-                if !expr_type_info.is(&anno_type_info) {
-                    return Err(format!(
-                        "invalid type in let stmt: expected `{:?}`, found `{:?}`",
-                        anno_type_info, expr_type_info
-                    )
-                    .into());
-                }
+    /// Copy the default methods of traits into the impls without the methods,
+    /// before the items in the scope are resolved.
+    fn add_default_methods<'a>(
+        &mut self,
+        items: impl Iterator<Item = &'a mut Item>,
+    ) -> Result<(), RccError> {
+        for item in items {
+            let item_impl = match item {
+                // the instances of generic impls have their own default methods
+                Item::Impl(item_impl) if !item_impl.is_generic() => item_impl,
+                _ => continue,
+            };
+            for item_fn in self.default_methods(item_impl)? {
+                self.scope_stack.cur_scope_mut().add_type_fn(&item_fn);
+                item_impl.items.push(item_fn);
             }
-            expr.type_info()
-        } else {
-            Rc::new(RefCell::new(Unknown))
+        }
+        Ok(())
+    }
+
+    /// The default methods of the trait not implemented by the trait impl.
+    fn default_methods(&mut self, item_impl: &ItemImpl) -> Result<Vec<ItemFn>, RccError> {
+        let cur_scope = self.scope_stack.cur_scope();
+        let item_trait = match &item_impl.trait_name {
+            Some(trait_name) => match cur_scope.find_trait(trait_name) {
+                Some(item_trait) => item_trait,
+                None => return Ok(vec![]),
+            },
+            None => return Ok(vec![]),
         };
+        let mut default_methods = vec![];
+        for trait_fn in item_trait.items.iter() {
+            let path = format!("{}::{}", item_impl.self_type, trait_fn.name);
+            if let Some(template) = &trait_fn.default {
+                if item_impl.items.iter().all(|item_fn| item_fn.name != path) {
+                    let self_type = [("Self".to_string(), item_impl.self_type.clone())];
+                    let mut item_fn = ItemFn::instantiate(
+                        template,
+                        item_trait.vis(),
+                        path,
+                        &self_type,
+                        &mut self.scope_count,
+                    )?;
+                    // the type of `self` is not in the template
+                    for param in item_fn.fn_params.params.iter_mut() {
+                        param._type.replace_self_type(&item_impl.self_type_anno);
+                    }
+                    default_methods.push(item_fn);
+                }
+            }
+        }
+        Ok(default_methods)
+    }
 
-        match &let_stmt.pattern {
-            Pattern::Identifier(ident_pattern) => {
-                self.scope_stack.cur_scope_mut().add_variable(
-                    ident_pattern.ident(),
-                    if ident_pattern.is_mut() {
-                        VarKind::LocalMut
-                    } else {
-                        VarKind::Local
-                    },
-                    expr_type_info,
-                );
+    fn visit_item_trait(&mut self, item_trait: &mut ItemTrait) -> Result<(), RccError> {
+        for (i, trait_fn) in item_trait.items.iter().enumerate() {
+            if item_trait.items[..i].iter().any(|f| f.name == trait_fn.name) {
+                return Err(format!(
+                    "the name `{}` is defined multiple times in trait `{}`",
+                    trait_fn.name, item_trait.name
+                )
+                .into());
             }
         }
         Ok(())
     }
 
-    fn visit_pattern(&mut self, pattern: &mut Pattern) -> Result<(), RccError> {
+    /// Generic items are resolved when they are instantiated,
+    /// only the bounds of the type params are checked here.
+    fn check_generics(&self, generics: &[GenericParam]) -> Result<(), RccError> {
+        let cur_scope = self.scope_stack.cur_scope();
+        for param in generics.iter() {
+            for bound in param.bounds.iter() {
+                if cur_scope.find_trait(bound).is_none() {
+                    return Err(format!("cannot find trait `{}` in this scope", bound).into());
+                }
+            }
+        }
         Ok(())
     }
 
-    fn visit_ident_pattern(&mut self, ident_pattern: &mut IdentPattern) -> Result<(), RccError> {
+    /// The body of a generic function is resolved once with the type params as fieldless
+    /// structs, which only have the methods of the bound traits.
+    /// The instance checked is dropped, and so are its scopes.
+    fn check_generic_fn(&mut self, item_fn: &ItemFn) -> Result<(), RccError> {
+        let scope_count = self.scope_count;
+        let mut instance = ItemFn::instantiate(
+            item_fn.template.as_ref().unwrap(),
+            item_fn.vis(),
+            item_fn.name.clone(),
+            &[],
+            &mut self.scope_count,
+        )?;
+        let mut param_scope = Scope::new(self.scope_count);
+        self.scope_stack.enter(&mut param_scope);
+        self.add_type_params(&item_fn.generics);
+        let outer_generic_fn = std::mem::replace(&mut self.in_generic_fn, true);
+        self.instantiate_fn_types(&instance)?;
+        self.visit_item_fn(&mut instance)?;
+        self.in_generic_fn = outer_generic_fn;
+        self.scope_stack.exit_scope();
+        self.scope_count = scope_count;
         Ok(())
     }
 
-    fn visit_path_expr(&mut self, path_expr: &mut PathExpr) -> Result<(), RccError> {
-        if let Some(ident) = path_expr.segments.last() {
-            let cur_scope = self.scope_stack.cur_scope_mut();
-            if let Some((var_info, _scope_id)) = cur_scope.find_variable(ident) {
+    /// The methods of a generic impl are checked like generic functions,
+    /// with the methods of all the impls of the instance with the type params.
+    fn check_generic_impl(&mut self, item_impl: &ItemImpl) -> Result<(), RccError> {
+        for param in item_impl.generics.iter() {
+            if !item_impl.self_type_anno.mentions(&param.name) {
+                return Err(format!(
+                    "the type parameter `{}` is not constrained by the impl trait, self type, \
+                     or predicates",
+                    param.name
+                )
+                .into());
+            }
+        }
+        let scope_count = self.scope_count;
+        let mut instance = ItemImpl::instantiate(
+            item_impl.template.as_ref().unwrap(),
+            &[],
+            &mut self.scope_count,
+        )?;
+        let mut param_scope = Scope::new(self.scope_count);
+        self.scope_stack.enter(&mut param_scope);
+        self.add_type_params(&item_impl.generics);
+        let outer_generic_fn = std::mem::replace(&mut self.in_generic_fn, true);
+        self.instantiate_type(&instance.self_type_anno)?;
+        for item_fn in instance.items.iter() {
+            self.instantiate_fn_types(item_fn)?;
+        }
+        self.visit_item_impl(&mut instance)?;
+        self.in_generic_fn = outer_generic_fn;
+        self.scope_stack.exit_scope();
+        self.scope_count = scope_count;
+        Ok(())
+    }
+
+    /// Add the type params to the current scope as fieldless structs,
+    /// which only have the methods of the bound traits.
+    fn add_type_params(&mut self, generics: &[GenericParam]) {
+        for param in generics.iter() {
+            let type_param = Box::pin(ItemStruct::new(Visibility::Priv, param.name.clone()));
+            self.scope_stack
+                .cur_scope_mut()
+                .add_type_param(&type_param, &param.bounds);
+            self.scope_stack
+                .file_mut()
+                .struct_instances
+                .push(type_param);
+        }
+    }
+
+    /// The type params of generic structs must be used by the fields.
+    fn visit_generic_struct(&mut self, item_struct: &mut ItemStruct) -> Result<(), RccError> {
+        self.check_generics(&item_struct.generics)?;
+        let field_types = item_struct.fields().types();
+        for param in item_struct.generics.iter() {
+            if field_types.iter().all(|(_, t)| !t.mentions(&param.name)) {
+                return Err(format!("parameter `{}` is never used", param.name).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Instantiate the generic structs used in the signatures and the fields of the items,
+    /// before the items in the scope are resolved.
+    fn instantiate_item_types<'a>(
+        &mut self,
+        items: impl Iterator<Item = &'a Item>,
+    ) -> Result<(), RccError> {
+        for item in items {
+            match item {
+                Item::Fn(item_fn) if !item_fn.is_generic() => self.instantiate_fn_types(item_fn)?,
+                Item::Struct(item_struct) if !item_struct.is_generic() => {
+                    for (_, type_anno) in item_struct.fields().types() {
+                        self.instantiate_type(type_anno)?;
+                    }
+                }
+                Item::Enum(type_enum) => {
+                    for variant in type_enum.variants() {
+                        for (_, type_anno) in variant.fields().types() {
+                            self.instantiate_type(type_anno)?;
+                        }
+                    }
+                }
+                Item::Impl(item_impl) if !item_impl.is_generic() => {
+                    self.instantiate_type(&item_impl.self_type_anno)?;
+                    for item_fn in item_impl.items.iter() {
+                        self.instantiate_fn_types(item_fn)?;
+                    }
+                }
+                Item::ExternalBlock(external_block) => {
+                    for item in external_block.external_items.iter() {
+                        match item {
+                            ExternalItem::Fn(f) => self.instantiate_fn_types(f)?,
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn instantiate_fn_types(&mut self, fn_sig: &impl FnSignature) -> Result<(), RccError> {
+        for type_anno in fn_sig.params().iter() {
+            self.instantiate_type(type_anno)?;
+        }
+        self.instantiate_type(&fn_sig.ret_type())
+    }
+
+    /// Instantiate the generic structs in the type, e.g. `Pair<i32>` in `[Pair<i32>; 2]`.
+    fn instantiate_type(&mut self, type_anno: &TypeAnnotation) -> Result<(), RccError> {
+        match type_anno {
+            TypeAnnotation::Identifier(name) => {
+                if self.scope_stack.cur_scope().find_generic_struct(name).is_some() {
+                    return Err(format!("missing generics for struct `{}`", name).into());
+                }
+            }
+            TypeAnnotation::Tuple(types) => {
+                for t in types.iter() {
+                    self.instantiate_type(t)?;
+                }
+            }
+            TypeAnnotation::Array(type_array) => self.instantiate_type(&type_array._type)?,
+            TypeAnnotation::Slice(type_slice) => self.instantiate_type(type_slice)?,
+            TypeAnnotation::Ptr(type_ptr) => self.instantiate_type(&type_ptr.type_anno)?,
+            TypeAnnotation::FnPtr(type_fn_ptr) => {
+                for t in type_fn_ptr.params.iter() {
+                    self.instantiate_type(t)?;
+                }
+                self.instantiate_type(&type_fn_ptr.ret_type)?;
+            }
+            TypeAnnotation::Generic(type_generic) => {
+                let type_args = self.explicit_type_args(&type_generic.args)?;
+                self.instantiate_generic_struct(&type_generic.name, type_args)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The types of the type arguments `<T1, T2>` in the paths and types.
+    fn explicit_type_args(&mut self, args: &[TypeAnnotation]) -> Result<Vec<TypeInfo>, RccError> {
+        let mut type_args = vec![];
+        for arg in args.iter() {
+            self.instantiate_type(arg)?;
+            let type_arg = TypeInfo::from_type_anno(arg, self.scope_stack.cur_scope());
+            if type_arg.is_unknown() {
+                return Err(format!("cannot find type `{:?}` in this scope", arg).into());
+            }
+            type_args.push(type_arg);
+        }
+        Ok(type_args)
+    }
+
+    /// The instance `Pair<T1, T2>` of the generic struct `Pair` is created at the first use,
+    /// the type arguments must implement the traits bounding the type params.
+    fn instantiate_generic_struct(
+        &mut self,
+        name: &str,
+        type_args: Vec<TypeInfo>,
+    ) -> Result<TypeInfo, RccError> {
+        let cur_scope = self.scope_stack.cur_scope();
+        let generic_struct = match cur_scope.find_generic_struct(name) {
+            Some(generic_struct) => generic_struct,
+            None if cur_scope.find_def_except_fn(name).is_unknown() => {
+                return Err(format!("cannot find type `{}` in this scope", name).into())
+            }
+            None => {
+                return Err(format!(
+                    "type `{}` takes 0 generic arguments but {} were supplied",
+                    name,
+                    type_args.len()
+                )
+                .into())
+            }
+        };
+        if generic_struct.generics.len() != type_args.len() {
+            return Err(format!(
+                "struct `{}` takes {} generic arguments but {} were supplied",
+                name,
+                generic_struct.generics.len(),
+                type_args.len()
+            )
+            .into());
+        }
+        let type_arg_names = check_type_args(cur_scope, &generic_struct.generics, &type_args)?;
+        let instance_name = format!("{}<{}>", name, type_args_string(&type_args));
+        let instance_type = cur_scope.find_def_except_fn(&instance_name);
+        if !instance_type.is_unknown() {
+            return Ok(instance_type);
+        }
+        let instance = Box::pin(ItemStruct::instantiate(
+            generic_struct.template.as_ref().unwrap(),
+            generic_struct.vis(),
+            instance_name.clone(),
+            &type_arg_names,
+        )?);
+        let field_types: Vec<TypeAnnotation> = instance
+            .fields()
+            .types()
+            .into_iter()
+            .map(|(_, type_anno)| type_anno.clone())
+            .collect();
+        let instance_type = TypeInfo::from_item_struct(&instance);
+        if self.in_generic_fn {
+            self.scope_stack
+                .cur_scope_mut()
+                .add_struct_instance(&instance, type_args);
+        } else {
+            self.scope_stack
+                .file_scope_mut()
+                .add_struct_instance(&instance, type_args);
+        }
+        self.scope_stack.file_mut().struct_instances.push(instance);
+
+        // the fields may use other instances
+        for type_anno in field_types.iter() {
+            self.instantiate_type(type_anno)?;
+        }
+        let fields = match &instance_type {
+            TypeInfo::Struct { fields, .. } => unsafe { fields.as_ref() },
+            _ => unreachable!(),
+        };
+        let field_types = self.field_types(fields)?;
+        self.check_type_size(&instance_name, instance_type.clone(), field_types)?;
+        self.instantiate_generic_impls(name, &instance_type)?;
+        Ok(instance_type)
+    }
+
+    /// The generic impls of the generic struct `name` are instantiated with the instance,
+    /// unless the type args do not fit, like `impl<T: Clone> Pair<T, i32>` for `Pair<u8, u8>`.
+    /// The methods are resolved as the instances of generic functions.
+    fn instantiate_generic_impls(
+        &mut self,
+        name: &str,
+        instance_type: &TypeInfo,
+    ) -> Result<(), RccError> {
+        let cur_scope = self.scope_stack.cur_scope();
+        let mut item_impls = vec![];
+        for generic_impl in cur_scope.find_generic_impls(name) {
+            let mut type_args: Vec<(String, Option<TypeInfo>)> = generic_impl
+                .generics
+                .iter()
+                .map(|param| (param.name.clone(), None))
+                .collect();
+            let self_type = &generic_impl.self_type_anno;
+            if bind_type_params(self_type, instance_type, &mut type_args, cur_scope).is_err() {
+                continue;
+            }
+            let type_args: Vec<TypeInfo> = match type_args.into_iter().map(|(_, t)| t).collect() {
+                Some(type_args) => type_args,
+                None => continue,
+            };
+            let generics = &generic_impl.generics;
+            let type_arg_names = match check_type_args(cur_scope, generics, &type_args) {
+                Ok(type_arg_names) => type_arg_names,
+                Err(_) => continue,
+            };
+            let item_impl = ItemImpl::instantiate(
+                generic_impl.template.as_ref().unwrap(),
+                &type_arg_names,
+                &mut self.scope_count,
+            )?;
+            if item_impl.self_type == instance_type.to_string() {
+                item_impls.push(item_impl);
+            }
+        }
+
+        for mut item_impl in item_impls {
+            let mut default_methods = self.default_methods(&item_impl)?;
+            item_impl.items.append(&mut default_methods);
+            if item_impl.trait_name.is_some() {
+                self.check_trait_impl(&item_impl)?;
+            }
+            let cur_scope = self.scope_stack.cur_scope();
+            for (i, item_fn) in item_impl.items.iter().enumerate() {
+                if !cur_scope.find_fn(&item_fn.name).is_unknown()
+                    || item_impl.items[..i].iter().any(|f| f.name == item_fn.name)
+                {
+                    let name = item_fn.name.rsplit("::").next().unwrap();
+                    return Err(format!("duplicate definitions with name `{}`", name).into());
+                }
+            }
+            let item = Item::Impl(item_impl);
+            if self.in_generic_fn {
+                self.scope_stack.cur_scope_mut().add_typedef(&item);
+                continue;
+            }
+            self.scope_stack.file_scope_mut().add_typedef(&item);
+            let item_impl = match item {
+                Item::Impl(item_impl) => item_impl,
+                _ => unreachable!(),
+            };
+            for mut item_fn in item_impl.items {
+                item_fn
+                    .fn_block
+                    .scope
+                    .types
+                    .insert("Self".to_string(), instance_type.clone());
+                self.instantiate_fn_types(&item_fn)?;
+                self.instances.push(item_fn);
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_item_external_block(
+        &mut self,
+        external_block: &mut ItemExternalBlock,
+    ) -> Result<(), RccError> {
+        for item in &mut external_block.external_items {
+            match item {
+                ExternalItem::Fn(f) => {
+                    self.visit_external_item_fn(f)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_external_item_fn(
+        &mut self,
+        _external_item_fn: &mut ExternalItemFn,
+    ) -> Result<(), RccError> {
+        // do nothing
+        Ok(())
+    }
+
+    fn visit_stmt(&mut self, stmt: &mut Stmt) -> Result<(), RccError> {
+        match stmt {
+            Stmt::Semi => Ok(()),
+            Stmt::Item(Item::Const(_) | Item::Static(_)) => {
+                Err("`const` and `static` items are only supported in the file scope".into())
+            }
+            Stmt::Item(item) => self.visit_item(item),
+            Stmt::Let(let_stmt) => self.visit_let_stmt(let_stmt),
+            Stmt::ExprStmt(expr) => {
+                self.visit_expr(expr)?;
+                let t = expr.type_info();
+                let tp = t.borrow();
+                let type_info = tp.deref();
+                if expr.with_block() && type_info != &TypeInfo::Unit && !type_info.is_never() {
+                    return Err(format!(
+                        "invalid type for expr stmt: expected `()`, found {:?}",
+                        type_info
+                    )
+                    .into());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn visit_let_stmt(&mut self, let_stmt: &mut LetStmt) -> Result<(), RccError> {
+        if let Some(type_anno) = &let_stmt._type {
+            self.instantiate_type(type_anno)?;
+        }
+        let expr_type_info = if let Some(expr) = &mut let_stmt.rhs {
+            self.visit_expr(expr)?;
+            if let Some(type_anno) = &let_stmt._type {
+                let anno_type_info =
+                    TypeInfo::from_type_anno(type_anno, self.scope_stack.cur_scope());
+                self.try_determine_number_type(&anno_type_info, expr)?;
+                let t = expr.type_info();
+                if self.infer_ctx.is_inferred(&t) && !t.borrow().is(&anno_type_info) {
+                    self.infer_ctx.resolve(&t, &anno_type_info, self.cur_line)?;
+                }
+                let tp = t.borrow();
+                let expr_type_info = tp.deref();
+                if !expr_type_info.is(&anno_type_info) {
+                    return Err(format!(
+                        "invalid type in let stmt: expected `{:?}`, found `{:?}`",
+                        anno_type_info, expr_type_info
+                    )
+                    .into());
+                }
+                // `let p: *const i32 = &a;` binds `p` of the annotated type
+                if matches!(anno_type_info, TypeInfo::Ptr { .. })
+                    && expr_type_info != &anno_type_info
+                {
+                    let type_info_rc = Rc::new(RefCell::new(anno_type_info));
+                    return self.visit_let_pattern(let_stmt, type_info_rc);
+                }
+            }
+            expr.type_info()
+        } else if let Some(type_anno) = &let_stmt._type {
+            let type_info = TypeInfo::from_type_anno(type_anno, self.scope_stack.cur_scope());
+            Rc::new(RefCell::new(type_info))
+        } else {
+            // `let x;` is inferred from the assignments
+            let type_info = Rc::new(RefCell::new(Unknown));
+            self.infer_ctx.add_var(&type_info, self.cur_line);
+            type_info
+        };
+
+        self.lower_const_patterns(&mut let_stmt.pattern)?;
+        self.visit_let_pattern(let_stmt, expr_type_info)
+    }
+
+    fn visit_let_pattern(
+        &mut self,
+        let_stmt: &LetStmt,
+        type_info: Rc<RefCell<TypeInfo>>,
+    ) -> Result<(), RccError> {
+        if !let_stmt.pattern.is_irrefutable() {
+            return Err(format!(
+                "refutable pattern in local binding: {:?}",
+                let_stmt.pattern
+            )
+            .into());
+        }
+        let mode = match &let_stmt.rhs {
+            Some(rhs) => BindingMode::by_value(rhs),
+            None => BindingMode::Move,
+        };
+        let mut bindings = vec![];
+        self.visit_pattern(&let_stmt.pattern, type_info, mode, &mut bindings)?;
+        for (ident, kind, type_info) in bindings {
+            // `let x;` is initialized by assignments later
+            let kind = if let_stmt.rhs.is_none() {
+                VarKind::LocalMut
+            } else {
+                kind
+            };
+            self.scope_stack
+                .cur_scope_mut()
+                .add_variable(&ident, kind, type_info);
+        }
+        Ok(())
+    }
+
+    /// Identifiers naming constants are value patterns instead of bindings,
+    /// e.g. `X` of `match y { X => 1, _ => 2 }` is replaced with the literal of its value.
+    fn lower_const_patterns(&self, pattern: &mut Pattern) -> Result<(), RccError> {
+        match pattern {
+            Pattern::Identifier(ident_pattern)
+                if !ident_pattern.is_ref() && !ident_pattern.is_mut() =>
+            {
+                let ident = ident_pattern.ident();
+                let var_info = match self.scope_stack.cur_scope().find_variable(ident) {
+                    Some((var_info, _)) if var_info.kind() == VarKind::Const => var_info,
+                    _ => return Ok(()),
+                };
+                let lit = match (var_info.const_value(), var_info.type_info.borrow().deref()) {
+                    (Some(Operand::Bool(b)), _) => LitPattern::Bool(*b),
+                    (Some(Operand::Char(c)), _) => LitPattern::Char(*c),
+                    (Some(value), TypeInfo::LitNum(t)) if t.is_integer() => {
+                        match ir::cast_may_constant_fold(value, IRType::I128) {
+                            Some(Operand::I128(i)) => LitPattern::Int(i, *t),
+                            _ => unreachable!(),
+                        }
+                    }
+                    (_, t) => {
+                        return Err(format!(
+                            "constant `{}` of type `{:?}` cannot be used in patterns",
+                            ident, t
+                        )
+                        .into())
+                    }
+                };
+                *pattern = Pattern::Literal(lit);
+                Ok(())
+            }
+            Pattern::Ref(ref_pattern) => self.lower_const_patterns(&mut ref_pattern.pattern),
+            Pattern::Tuple(patterns)
+            | Pattern::Or(patterns)
+            | Pattern::TupleStruct(TupleStructPattern { patterns, .. }) => {
+                for p in patterns.iter_mut() {
+                    self.lower_const_patterns(p)?;
+                }
+                Ok(())
+            }
+            Pattern::Struct(struct_pattern) => {
+                for field in struct_pattern.fields.iter_mut() {
+                    self.lower_const_patterns(&mut field.pattern)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the pattern against the type of the matched value,
+    /// and collect the variables bound by the pattern.
+    /// A variable bound by a whole value shares the type with the value,
+    /// so that `let a;` can be determined by the later assignment.
+    fn visit_pattern(
+        &self,
+        pattern: &Pattern,
+        type_info: Rc<RefCell<TypeInfo>>,
+        mode: BindingMode,
+        bindings: &mut Bindings,
+    ) -> Result<(), RccError> {
+        match pattern {
+            Pattern::Identifier(ident_pattern) => {
+                self.visit_ident_pattern(ident_pattern, type_info, mode, bindings)
+            }
+            Pattern::Wildcard => Ok(()),
+            Pattern::Rest => Err("`..` patterns are not allowed here".into()),
+            Pattern::Ref(ref_pattern) => {
+                if !matches!(mode, BindingMode::Move | BindingMode::MoveFromPlace) {
+                    return Err(
+                        "reference patterns are not allowed in by-reference binding mode".into(),
+                    );
+                }
+                let inner_type = match type_info.borrow().deref() {
+                    TypeInfo::Ptr { kind, type_info }
+                        if (kind, ref_pattern.is_mut) == (&PtrKind::Ref, false)
+                            || (kind, ref_pattern.is_mut) == (&PtrKind::MutRef, true) =>
+                    {
+                        type_info.as_ref().clone()
+                    }
+                    t => {
+                        return Err(format!(
+                            "mismatched types in pattern: expected `{:?}`, found `&{}_`",
+                            t,
+                            if ref_pattern.is_mut { "mut " } else { "" }
+                        )
+                        .into())
+                    }
+                };
+                // the referenced place is mutable only through `&mut`
+                let mode = if ref_pattern.is_mut {
+                    BindingMode::Move
+                } else {
+                    BindingMode::MoveFromPlace
+                };
+                self.visit_pattern(
+                    &ref_pattern.pattern,
+                    Rc::new(RefCell::new(inner_type)),
+                    mode,
+                    bindings,
+                )
+            }
+            Pattern::Literal(_)
+            | Pattern::Range(_)
+            | Pattern::Tuple(_)
+            | Pattern::Struct(_)
+            | Pattern::TupleStruct(_)
+            | Pattern::Path(_) => {
+                // default binding mode: non-reference patterns match the referenced value
+                let (type_info, mode) = peel_refs(type_info, mode);
+                let t = type_info.borrow();
+                let tp = t.deref();
+                match pattern {
+                    Pattern::Literal(lit) => check_lit_pattern_type(lit, tp),
+                    Pattern::Range(range) => check_range_pattern_type(range, tp),
+                    Pattern::Tuple(patterns) => {
+                        self.visit_tuple_pattern(patterns, tp, mode, bindings)
+                    }
+                    Pattern::Struct(struct_pattern) => {
+                        self.visit_struct_pattern(struct_pattern, tp, mode, bindings)
+                    }
+                    Pattern::TupleStruct(tuple_struct_pattern) => {
+                        self.visit_tuple_struct_pattern(tuple_struct_pattern, tp, mode, bindings)
+                    }
+                    Pattern::Path(path) => self.visit_path_pattern(path, tp),
+                    _ => unreachable!(),
+                }
+            }
+            Pattern::Or(patterns) => {
+                for p in patterns.iter() {
+                    let mut alt_bindings = vec![];
+                    self.visit_pattern(p, type_info.clone(), mode, &mut alt_bindings)?;
+                    if let Some((ident, _, _)) = alt_bindings.first() {
+                        return Err(
+                            format!("variable `{}` is not bound in all patterns", ident).into()
+                        );
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn visit_ident_pattern(
+        &self,
+        ident_pattern: &IdentPattern,
+        type_info: Rc<RefCell<TypeInfo>>,
+        mode: BindingMode,
+        bindings: &mut Bindings,
+    ) -> Result<(), RccError> {
+        let ident = ident_pattern.ident();
+        if bindings.iter().any(|(i, _, _)| i == ident) {
+            return Err(format!(
+                "identifier `{}` is bound more than once in the same pattern",
+                ident
+            )
+            .into());
+        }
+        let ref_kind = match (mode, ident_pattern.is_ref(), ident_pattern.is_mut()) {
+            (BindingMode::Move | BindingMode::MoveFromPlace, false, _) => None,
+            (_, false, true) => {
+                return Err(format!(
+                    "binding cannot be both mutable and by-reference: `mut {}`",
+                    ident
+                )
+                .into())
+            }
+            (BindingMode::Ref, true, true) => {
+                return Err(format!(
+                    "cannot borrow data in a `&` reference as mutable: `ref mut {}`",
+                    ident
+                )
+                .into())
+            }
+            (BindingMode::MoveFromPlace, true, true) => {
+                return Err(format!(
+                    "cannot borrow immutable place as mutable: `ref mut {}`",
+                    ident
+                )
+                .into())
+            }
+            (_, true, true) | (BindingMode::RefMut, false, false) => Some(PtrKind::MutRef),
+            (_, _, false) => Some(PtrKind::Ref),
+        };
+        match ref_kind {
+            None => bindings.push((
+                ident.to_string(),
+                if ident_pattern.is_mut() {
+                    VarKind::LocalMut
+                } else {
+                    VarKind::Local
+                },
+                type_info,
+            )),
+            Some(kind) => {
+                let ptr_type = TypeInfo::Ptr {
+                    kind,
+                    type_info: Box::new(type_info.borrow().clone()),
+                };
+                bindings.push((
+                    ident.to_string(),
+                    VarKind::Local,
+                    Rc::new(RefCell::new(ptr_type)),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// `..` matches any number of elements in the middle of the tuple.
+    fn visit_tuple_pattern(
+        &self,
+        patterns: &[Pattern],
+        type_info: &TypeInfo,
+        mode: BindingMode,
+        bindings: &mut Bindings,
+    ) -> Result<(), RccError> {
+        let elem_types: Vec<TypeInfo> = match type_info {
+            TypeInfo::Unit => vec![],
+            TypeInfo::Tuple(types) => types.clone(),
+            t => {
+                return Err(
+                    format!("mismatched types in pattern: expected `{:?}`, found tuple", t).into(),
+                )
+            }
+        };
+        let (front, back) = match split_rest(patterns, elem_types.len())? {
+            Some(split) => split,
+            None => {
+                return Err(format!(
+                    "mismatched types in pattern: expected a tuple with {} elements, found one with {} elements",
+                    elem_types.len(),
+                    patterns.iter().filter(|p| p != &&Pattern::Rest).count()
+                )
+                .into())
+            }
+        };
+        self.visit_elem_patterns(patterns, &elem_types, front, back, mode, bindings)
+    }
+
+    /// Match the `front` patterns with the first elems and the `back` patterns with the last elems.
+    fn visit_elem_patterns(
+        &self,
+        patterns: &[Pattern],
+        elem_types: &[TypeInfo],
+        front: usize,
+        back: usize,
+        mode: BindingMode,
+        bindings: &mut Bindings,
+    ) -> Result<(), RccError> {
+        let back_types = &elem_types[elem_types.len() - back..];
+        for (p, t) in patterns[..front]
+            .iter()
+            .zip(elem_types.iter())
+            .chain(patterns[patterns.len() - back..].iter().zip(back_types.iter()))
+        {
+            self.visit_pattern(p, Rc::new(RefCell::new(t.clone())), mode, bindings)?;
+        }
+        Ok(())
+    }
+
+    /// Type and fields of the struct or the enum variant `path`, and the kind of the fields.
+    fn find_fields(
+        &self,
+        path: &str,
+        type_info: &TypeInfo,
+    ) -> Result<(TypeInfo, &Fields, &'static str), RccError> {
+        if let Some((enum_type, variant)) = self.find_enum_variant(path)? {
+            return Ok((enum_type, variant.fields(), "variant"));
+        }
+        let cur_scope = self.scope_stack.cur_scope();
+        // the generic struct matches its instances
+        let struct_type = if cur_scope.find_generic_struct(path).is_some() {
+            if !is_instance_of(type_info, path) {
+                return Err(format!(
+                    "mismatched types in pattern: expected `{:?}`, found `{}`",
+                    type_info, path
+                )
+                .into());
+            }
+            type_info.clone()
+        } else {
+            cur_scope.find_def_except_fn(path)
+        };
+        match &struct_type {
+            TypeInfo::Struct { fields, .. } => {
+                let fields = unsafe { fields.as_ref() };
+                Ok((struct_type, fields, "struct"))
+            }
+            _ => Err(format!("cannot find struct `{}`", path).into()),
+        }
+    }
+
+    /// `Foo(a, ..)` of tuple structs and tuple variants, which is matched like tuples.
+    fn visit_tuple_struct_pattern(
+        &self,
+        tuple_struct_pattern: &TupleStructPattern,
+        type_info: &TypeInfo,
+        mode: BindingMode,
+        bindings: &mut Bindings,
+    ) -> Result<(), RccError> {
+        let path = &tuple_struct_pattern.path;
+        let (def_type, fields, kind) = self.find_fields(path, type_info)?;
+        if !matches!(fields, Fields::Tuple(_)) {
+            return Err(format!(
+                "expected tuple struct or tuple variant, found {} `{}`",
+                kind, path
+            )
+            .into());
+        }
+        if &def_type != type_info {
+            return Err(format!(
+                "mismatched types in pattern: expected `{:?}`, found `{}`",
+                type_info, path
+            )
+            .into());
+        }
+        let elem_types = self.field_types(fields)?;
+        let patterns = &tuple_struct_pattern.patterns;
+        let (front, back) = match split_rest(patterns, elem_types.len())? {
+            Some(split) => split,
+            None => {
+                return Err(format!(
+                    "this pattern has {} fields, but the corresponding {} `{}` has {} fields",
+                    patterns.iter().filter(|p| p != &&Pattern::Rest).count(),
+                    kind,
+                    path,
+                    elem_types.len()
+                )
+                .into())
+            }
+        };
+        self.visit_elem_patterns(patterns, &elem_types, front, back, mode, bindings)
+    }
+
+    /// `Enum::Variant` of unit variants
+    fn visit_path_pattern(&self, path: &str, type_info: &TypeInfo) -> Result<(), RccError> {
+        let (enum_type, variant) = match self.find_enum_variant(path)? {
+            Some(found) => found,
+            None => return Err(format!("cannot find unit variant `{}`", path).into()),
+        };
+        if variant.fields() != &Fields::None {
+            return Err(format!(
+                "expected unit variant, found {} `{}`",
+                variant_kind(variant.fields()),
+                path
+            )
+            .into());
+        }
+        if &enum_type != type_info {
+            return Err(format!(
+                "mismatched types in pattern: expected `{:?}`, found `{}`",
+                type_info, path
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Every field must be mentioned unless the pattern ends with `..`,
+    /// the path is a struct or an enum variant with fields.
+    fn visit_struct_pattern(
+        &self,
+        struct_pattern: &StructPattern,
+        type_info: &TypeInfo,
+        mode: BindingMode,
+        bindings: &mut Bindings,
+    ) -> Result<(), RccError> {
+        let cur_scope = self.scope_stack.cur_scope();
+        let (struct_type, fields, kind) = self.find_fields(&struct_pattern.path, type_info)?;
+        if &struct_type != type_info {
+            return Err(format!(
+                "mismatched types in pattern: expected `{:?}`, found `{}`",
+                type_info, struct_pattern.path
+            )
+            .into());
+        }
+        let field_types = fields.types();
+
+        let mut mentioned = HashSet::new();
+        for field in struct_pattern.fields.iter() {
+            let type_anno = match field_types.iter().find(|(name, _)| name == &field.name) {
+                Some((_, type_anno)) => type_anno,
+                None => {
+                    return Err(format!(
+                        "{} `{}` does not have a field named `{}`",
+                        kind, struct_pattern.path, field.name
+                    )
+                    .into())
+                }
+            };
+            if !mentioned.insert(field.name.as_str()) {
+                return Err(format!(
+                    "field `{}` bound multiple times in the pattern",
+                    field.name
+                )
+                .into());
+            }
+            let field_type = TypeInfo::from_type_anno(type_anno, cur_scope);
+            self.visit_pattern(
+                &field.pattern,
+                Rc::new(RefCell::new(field_type)),
+                mode,
+                bindings,
+            )?;
+        }
+        if !struct_pattern.has_rest {
+            if let Some((name, _)) = field_types
+                .iter()
+                .find(|(name, _)| !mentioned.contains(name.as_str()))
+            {
+                return Err(format!("pattern does not mention field `{}`", name).into());
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_path_expr(&mut self, path_expr: &mut PathExpr) -> Result<(), RccError> {
+        // the type arguments of generic functions have been replaced by the instances
+        if !path_expr.type_args.is_empty() {
+            return Err(format!(
+                "`{}` takes 0 generic arguments but {} were supplied",
+                path_expr.segments.join("::"),
+                path_expr.type_args.len()
+            )
+            .into());
+        }
+        if path_expr.segments.len() == 2 {
+            return self.visit_assoc_fn_path(path_expr);
+        }
+        if let Some(ident) = path_expr.segments.last() {
+            let cur_scope = self.scope_stack.cur_scope_mut();
+            if let Some((var_info, _scope_id)) = cur_scope.find_variable(ident) {
                 path_expr.set_type_info_ref(var_info.type_info.clone());
                 path_expr.expr_kind = match var_info.kind {
-                    VarKind::Static | VarKind::LocalMut => ExprKind::MutablePlace,
-                    VarKind::Const | VarKind::Local => ExprKind::Place,
+                    VarKind::StaticMut if !self.in_unsafe => {
+                        return Err(
+                            "use of mutable static is unsafe and requires unsafe block".into()
+                        )
+                    }
+                    VarKind::StaticMut | VarKind::LocalMut => ExprKind::MutablePlace,
+                    VarKind::Static | VarKind::Local => ExprKind::Place,
+                    // constants are inlined as values
+                    VarKind::Const => ExprKind::Value,
                     VarKind::LitConst => unreachable!(),
                 };
                 Ok(())
@@ -711,6 +2132,32 @@ impl SymbolResolver {
         }
     }
 
+    /// `Type::f` of associated functions, `Self` is replaced by the implemented type.
+    fn visit_assoc_fn_path(&mut self, path_expr: &mut PathExpr) -> Result<(), RccError> {
+        let cur_scope = self.scope_stack.cur_scope();
+        let self_type = cur_scope.find_def_except_fn(&path_expr.segments[0]);
+        if self_type.is_unknown() {
+            return Err(format!(
+                "failed to resolve: use of undeclared type `{}`",
+                path_expr.segments[0]
+            )
+            .into());
+        }
+        path_expr.segments[0] = self_type.to_string();
+        let path = path_expr.segments.join("::");
+        let type_info = cur_scope.find_fn(&path);
+        if type_info.is_unknown() {
+            return Err(format!(
+                "no function named `{}` found for `{}`",
+                path_expr.segments[1], path_expr.segments[0]
+            )
+            .into());
+        }
+        path_expr.set_type_info(type_info);
+        path_expr.expr_kind = ExprKind::Value;
+        Ok(())
+    }
+
     fn visit_lit_str(&mut self, _: &str) -> Result<(), RccError> {
         // do nothing
         Ok(())
@@ -721,10 +2168,23 @@ impl SymbolResolver {
         let type_info = unary_expr.expr.type_info();
         match unary_expr.op {
             UnOp::Deref => {
-                if let TypeInfo::Ptr { kind: _, type_info } = type_info.borrow().deref() {
+                if let TypeInfo::Ptr { kind, type_info } = type_info.borrow().deref() {
+                    if kind.is_raw() && !self.in_unsafe {
+                        return Err(
+                            "dereference of raw pointer is unsafe and requires unsafe block".into(),
+                        );
+                    }
                     unary_expr.set_type_info(*type_info.clone());
-                    unary_expr.expr_kind = unary_expr.expr.kind();
+                    unary_expr.expr_kind = match kind {
+                        PtrKind::MutRef | PtrKind::MutRawPtr => ExprKind::MutablePlace,
+                        PtrKind::Ref | PtrKind::ConstRawPtr => ExprKind::Place,
+                    };
+                    let ptr_type = unary_expr.expr.type_info();
+                    let pointee = unary_expr.type_info();
+                    self.infer_ctx
+                        .add_elem(&ptr_type, &[0], &pointee, self.cur_line);
                 } else {
+                    let type_info = type_info.borrow();
                     return Err(format!("type `{:?}` can not be dereferenced", type_info).into());
                 }
             }
@@ -748,15 +2208,24 @@ impl SymbolResolver {
                     )
                 }
             },
-            UnOp::Borrow => {
+            // values are borrowed as temporaries
+            UnOp::Borrow | UnOp::BorrowMut => {
+                let kind = if unary_expr.op == UnOp::Borrow {
+                    PtrKind::Ref
+                } else if unary_expr.expr.kind() == ExprKind::Place {
+                    return Err("cannot borrow immutable place as mutable".into());
+                } else {
+                    PtrKind::MutRef
+                };
+                let pointee = type_info.borrow().deref().clone();
                 unary_expr.set_type_info(TypeInfo::Ptr {
-                    kind: PtrKind::Ref,
-                    type_info: Box::new(type_info.borrow().deref().clone()),
+                    kind,
+                    type_info: Box::new(pointee),
                 });
                 unary_expr.expr_kind = ExprKind::Value;
-            }
-            UnOp::BorrowMut => {
-                todo!("borrow mut")
+                let ptr_type = unary_expr.type_info();
+                self.infer_ctx
+                    .add_elem(&ptr_type, &[0], &type_info, self.cur_line);
             }
         }
         Ok(())
@@ -764,13 +2233,27 @@ impl SymbolResolver {
 
     fn visit_block_expr(&mut self, block_expr: &mut BlockExpr) -> Result<(), RccError> {
         self.scope_stack.enter_scope(block_expr);
+        let outer_unsafe = self.in_unsafe;
+        self.in_unsafe |= block_expr.is_unsafe;
+        self.add_default_methods(block_expr.stmts.iter_mut().filter_map(|stmt| match stmt {
+            Stmt::Item(item) => Some(item),
+            _ => None,
+        }))?;
+        self.instantiate_item_types(block_expr.stmts.iter().filter_map(|stmt| match stmt {
+            Stmt::Item(item) => Some(item),
+            _ => None,
+        }))?;
 
+        let outer_line = self.cur_line;
+        let mut lines = block_expr.lines.iter();
         for stmt in block_expr.stmts.iter_mut() {
+            self.cur_line = lines.next().copied().unwrap_or(outer_line);
             self.visit_stmt(stmt)?;
             self.scope_stack.cur_scope_mut().cur_stmt_id += 1;
         }
 
         if let Some(expr) = block_expr.last_expr.as_mut() {
+            self.cur_line = lines.next().copied().unwrap_or(outer_line);
             self.visit_expr(expr)?;
             self.scope_stack.cur_scope_mut().cur_stmt_id += 1;
             let type_info = expr.type_info();
@@ -787,6 +2270,8 @@ impl SymbolResolver {
             }
         }
 
+        self.cur_line = outer_line;
+        self.in_unsafe = outer_unsafe;
         self.scope_stack.exit_scope();
         Ok(())
     }
@@ -814,14 +2299,13 @@ impl SymbolResolver {
             ExprKind::MutablePlace => {
                 self.visit_expr(&mut assign_expr.rhs)?;
                 let l_type = assign_expr.lhs.type_info();
+                // a[i] = [1, 2]; t.0 = (1, 2);
+                let place_type = l_type.borrow().clone();
+                if let TypeInfo::Array { .. } | TypeInfo::Tuple(_) = place_type {
+                    self.try_determine_number_type(&place_type, assign_expr.rhs.as_mut())?;
+                }
                 let r_type = assign_expr.rhs.type_info();
 
-                debug_assert!(
-                    !r_type.borrow().deref().is_unknown(),
-                    "{:#?}",
-                    assign_expr.rhs
-                );
-
                 if matches!(assign_expr.assign_op, AssignOp::ShlEq | AssignOp::ShrEq) {
                     return if l_type.borrow().deref().is_integer()
                         && r_type.borrow().deref().is_integer()
@@ -832,31 +2316,23 @@ impl SymbolResolver {
                     };
                 }
 
-                // set type_info of lhs or rhs
+                // the types of variables declared without types are inferred from the rhs
                 match l_type.partial_cmp(&r_type) {
-                    Some(o) => match o {
-                        Ordering::Equal => {}
-                        // let mut a; a = 3i32;
-                        Ordering::Greater => {
-                            match &assign_expr.lhs {
-                                LhsExpr::Path(path_expr) => {
-                                    let ident = path_expr.segments.last().unwrap();
-                                    self.scope_stack
-                                        .cur_scope()
-                                        .update_variable_type(ident, r_type.clone())?;
-                                }
-                                _ => {
-                                    todo!()
-                                }
-                            }
-                            assign_expr.lhs.set_type_info_ref(r_type);
-                        }
-                        // let mut b: i32; b = 4;
-                        Ordering::Less => {
-                            self.may_update_variable_type(&*assign_expr.rhs, l_type.clone())?;
-                            assign_expr.rhs.set_type_info_ref(l_type);
-                        }
-                    },
+                    Some(Ordering::Greater) if matches!(assign_expr.lhs, LhsExpr::Deref(_)) => {
+                        return Err(POINTEE_TYPE_NEEDED.into());
+                    }
+                    // let mut a; a = 3i32;
+                    // let mut b: i32; b = 4;
+                    // let mut c = [1, 2]; c[0] = 3u8;
+                    Some(_) => self.unify(&l_type, &r_type)?,
+                    // p = &mut a; where p: *mut i32, the rhs is coerced to the type of the lhs
+                    None if r_type.borrow().is(&l_type.borrow()) => {}
+                    // the conflicting types come from different places
+                    None if self.infer_ctx.is_inferred(&l_type)
+                        || self.infer_ctx.is_inferred(&r_type) =>
+                    {
+                        self.unify(&l_type, &r_type)?
+                    }
                     None => {
                         return invalid_type_error(l_type.borrow().deref(), assign_expr);
                     }
@@ -864,8 +2340,7 @@ impl SymbolResolver {
             }
         }
         debug_assert!(
-            assign_expr.rhs.type_info().borrow().is_never()
-                || assign_expr.lhs.type_info() == assign_expr.rhs.type_info(),
+            assign_expr.rhs.type_info().borrow().is(&assign_expr.lhs.type_info().borrow()),
             "{:#?}",
             assign_expr
         );
@@ -892,19 +2367,62 @@ impl SymbolResolver {
         Ok(())
     }
 
-    fn visit_range_expr(&mut self, range_expr: &mut RangeExpr) -> Result<(), RccError> {
-        if let Some(expr) = range_expr.lhs.as_mut() {
-            self.visit_expr(expr)?;
-        }
-        if let Some(expr) = range_expr.rhs.as_mut() {
-            self.visit_expr(expr)?;
+    /// Only ranges of integers with both bounds are supported,
+    /// return the type of the bounds.
+    fn visit_range_expr(
+        &mut self,
+        range_expr: &mut RangeExpr,
+    ) -> Result<Rc<RefCell<TypeInfo>>, RccError> {
+        let (lhs, rhs) = match (range_expr.lhs.as_mut(), range_expr.rhs.as_mut()) {
+            (Some(lhs), Some(rhs)) => (lhs, rhs),
+            _ => return Err("unsupported range: expected both bounds".into()),
+        };
+        self.visit_expr(lhs)?;
+        self.visit_expr(rhs)?;
+
+        let l_type = lhs.type_info();
+        let r_type = rhs.type_info();
+        let type_info = match l_type.partial_cmp(&r_type) {
+            Some(o) => {
+                self.unify_operands(lhs, rhs)?;
+                if o == Ordering::Greater {
+                    r_type
+                } else {
+                    l_type
+                }
+            }
+            None => {
+                return Err(format!(
+                    "mismatched types in range: `{:?}` and `{:?}`",
+                    l_type.borrow().deref(),
+                    r_type.borrow().deref()
+                )
+                .into())
+            }
+        };
+        if !type_info.borrow().is_integer() {
+            return Err(format!(
+                "invalid type for range: expected integer, found `{:?}`",
+                type_info.borrow().deref()
+            )
+            .into());
         }
-        Ok(())
+        Ok(type_info)
     }
 
     fn visit_bin_op_expr(&mut self, bin_op_expr: &mut BinOpExpr) -> Result<(), RccError> {
         self.visit_expr(&mut bin_op_expr.lhs)?;
         self.visit_expr(&mut bin_op_expr.rhs)?;
+        // structs and the type params of generic functions have no operators
+        for operand in [&bin_op_expr.lhs, &bin_op_expr.rhs].iter() {
+            if let TypeInfo::Struct { name, .. } = operand.type_info().borrow().deref() {
+                return Err(format!(
+                    "binary operation `{:?}` cannot be applied to type `{}`",
+                    bin_op_expr.bin_op, name
+                )
+                .into());
+            }
+        }
 
         let t = self.primitive_bin_ops(
             &mut bin_op_expr.lhs,
@@ -934,39 +2452,462 @@ impl SymbolResolver {
         }
     }
 
+    /// The elems which are not number literals decide the type of elems,
+    /// the length of `[x; N]` is an integer literal.
     fn visit_array_expr(&mut self, array_expr: &mut ArrayExpr) -> Result<(), RccError> {
         for e in array_expr.elems.iter_mut() {
             self.visit_expr(e)?;
         }
         if let Some(expr) = array_expr.len_expr.expr.as_mut() {
             self.visit_expr(expr)?;
+            let usize_type = TypeInfo::LitNum(TypeLitNum::Usize);
+            self.try_determine_number_type(&usize_type, expr.as_mut())?;
+            let len = match const_eval::eval_const_expr(expr, self.scope_stack.cur_scope())? {
+                Some(Operand::Usize(len)) => len,
+                _ => return Err("the length of array must be a constant `usize`".into()),
+            };
+            array_expr.len_expr.set_value(len);
+        }
+
+        let elem_types: Vec<TypeInfo> = array_expr
+            .elems
+            .iter()
+            .map(|e| e.type_info().borrow().clone())
+            .collect();
+        let elem_type = elem_types
+            .iter()
+            .find(|t| !t.is_i() && !t.is_f() && !t.is_never())
+            .or_else(|| elem_types.iter().find(|t| !t.is_never()))
+            .or_else(|| elem_types.first())
+            .cloned()
+            .unwrap_or(TypeInfo::Unknown);
+        for e in array_expr.elems.iter_mut() {
+            self.expect_type(&elem_type, e, "mismatched types in array expr")?;
+        }
+        let len = *array_expr.len_expr.value().unwrap() as u32;
+        array_expr.set_type_info(TypeInfo::Array {
+            elem_type: Box::new(elem_type),
+            len,
+        });
+        let type_info = array_expr.type_info();
+        for e in array_expr.elems.iter() {
+            self.infer_ctx
+                .add_elem(&type_info, &[0], &e.type_info(), self.cur_line);
         }
         Ok(())
     }
 
+    /// Arrays are indexed by `usize`, references to arrays are dereferenced automatically.
     fn visit_array_index_expr(
         &mut self,
         array_index_expr: &mut ArrayIndexExpr,
     ) -> Result<(), RccError> {
-        todo!()
+        self.visit_expr(&mut array_index_expr.expr)?;
+        let (expr_kind, type_info) = auto_deref(array_index_expr.expr.as_ref());
+        let elem_path = auto_deref_path(array_index_expr.expr.as_ref(), 0);
+        array_index_expr.expr_kind = expr_kind;
+
+        self.visit_expr(&mut array_index_expr.index_expr)?;
+        let usize_type = TypeInfo::LitNum(TypeLitNum::Usize);
+        self.try_determine_number_type(&usize_type, array_index_expr.index_expr.as_mut())?;
+        assert_type_is(
+            array_index_expr.index_expr.as_ref(),
+            &usize_type,
+            "arrays are indexed by `usize`",
+        )?;
+
+        match type_info {
+            TypeInfo::Array { elem_type, len } => {
+                if let Expr::LitNum(lit_num) = array_index_expr.index_expr.as_ref() {
+                    let index: u128 = lit_num.value.parse()?;
+                    if index >= len as u128 {
+                        return Err(format!(
+                            "index out of bounds: the length is {} but the index is {}",
+                            len, index
+                        )
+                        .into());
+                    }
+                }
+                array_index_expr.set_type_info(*elem_type);
+                let owner = array_index_expr.expr.type_info();
+                let type_info = array_index_expr.type_info();
+                self.infer_ctx
+                    .add_elem(&owner, &elem_path, &type_info, self.cur_line);
+                Ok(())
+            }
+            t => Err(format!("cannot index into a value of type `{:?}`", t).into()),
+        }
     }
 
     fn visit_tuple_expr(&mut self, tuple_expr: &mut TupleExpr) -> Result<(), RccError> {
-        todo!()
+        let mut elem_types = vec![];
+        for elem in tuple_expr.elems.iter_mut() {
+            self.visit_expr(elem)?;
+            elem_types.push(elem.type_info().borrow().clone());
+        }
+        if elem_types.is_empty() {
+            tuple_expr.set_type_info(TypeInfo::Unit);
+        } else {
+            tuple_expr.set_type_info(TypeInfo::Tuple(elem_types));
+        }
+        let type_info = tuple_expr.type_info();
+        for (i, elem) in tuple_expr.elems.iter().enumerate() {
+            self.infer_ctx
+                .add_elem(&type_info, &[i], &elem.type_info(), self.cur_line);
+        }
+        Ok(())
     }
 
+    /// Tuple structs are indexed like tuples.
     fn visit_tuple_index_expr(
         &mut self,
         tuple_index_expr: &mut TupleIndexExpr,
     ) -> Result<(), RccError> {
-        todo!()
+        self.visit_expr(&mut tuple_index_expr.expr)?;
+        let (expr_kind, type_info) = auto_deref(tuple_index_expr.expr.as_ref());
+        tuple_index_expr.expr_kind = expr_kind;
+
+        let index = tuple_index_expr.index;
+        let elem_type = match &type_info {
+            TypeInfo::Tuple(types) => types.get(index).cloned(),
+            TypeInfo::Struct { fields, .. } => unsafe { fields.as_ref() }
+                .types()
+                .into_iter()
+                .find(|(name, _)| name == &index.to_string())
+                .map(|(_, type_anno)| {
+                    TypeInfo::from_type_anno(type_anno, self.scope_stack.cur_scope())
+                }),
+            _ => None,
+        };
+        match elem_type {
+            Some(elem_type) => {
+                tuple_index_expr.set_type_info(elem_type);
+                let owner = tuple_index_expr.expr.type_info();
+                let elem_path = auto_deref_path(tuple_index_expr.expr.as_ref(), index);
+                let type_info = tuple_index_expr.type_info();
+                self.infer_ctx
+                    .add_elem(&owner, &elem_path, &type_info, self.cur_line);
+                Ok(())
+            }
+            None => Err(format!("no field `{}` on type `{:?}`", index, type_info).into()),
+        }
     }
 
+    /// Every field must be initialized unless the struct expr has a base.
     fn visit_struct_expr(&mut self, struct_expr: &mut StructExpr) -> Result<(), RccError> {
-        todo!()
+        let cur_scope = self.scope_stack.cur_scope();
+        let is_generic = cur_scope.find_generic_struct(&struct_expr.path).is_some();
+        // the fields and the base have been visited to infer the type arguments
+        let visited = is_generic || !struct_expr.type_args.is_empty();
+        if visited {
+            self.instantiate_struct_expr(struct_expr)?;
+        }
+        let struct_type = self
+            .scope_stack
+            .cur_scope()
+            .find_def_except_fn(&struct_expr.path);
+        let fields = match &struct_type {
+            TypeInfo::Struct { fields, .. } => unsafe { fields.as_ref() },
+            _ => return Err(format!("cannot find struct `{}`", struct_expr.path).into()),
+        };
+        if visited {
+            self.check_field_exprs(&struct_expr.path, "struct", fields, &mut struct_expr.fields)?;
+        } else {
+            self.visit_field_exprs(&struct_expr.path, "struct", fields, &mut struct_expr.fields)?;
+        }
+
+        match struct_expr.base.as_mut() {
+            Some(base) => {
+                if !visited {
+                    self.visit_expr(base)?;
+                }
+                assert_type_is(base.as_ref(), &struct_type, "mismatched types in struct base")?;
+            }
+            None => check_missing_fields(&struct_expr.path, fields, &struct_expr.fields)?,
+        }
+        struct_expr.set_type_info(struct_type);
+        Ok(())
+    }
+
+    /// Replace the generic struct `Pair` of the struct expr by its instance `Pair<T>`,
+    /// the type arguments are inferred from the fields or the base if they are not given.
+    fn instantiate_struct_expr(&mut self, struct_expr: &mut StructExpr) -> Result<(), RccError> {
+        for field in struct_expr.fields.iter_mut() {
+            self.visit_expr(&mut field.expr)?;
+        }
+        if let Some(base) = struct_expr.base.as_mut() {
+            self.visit_expr(base)?;
+        }
+        let type_args = if !struct_expr.type_args.is_empty() {
+            self.explicit_type_args(&struct_expr.type_args)?
+        } else {
+            let cur_scope = self.scope_stack.cur_scope();
+            let generic_struct = cur_scope.find_generic_struct(&struct_expr.path).unwrap();
+            if let Some(base) = &struct_expr.base {
+                let base_type = base.type_info().borrow().clone();
+                if is_instance_of(&base_type, &struct_expr.path) {
+                    struct_expr.path = base_type.type_name().unwrap().to_string();
+                    return Ok(());
+                }
+            }
+            let field_types = generic_struct.fields().types();
+            let mut params = vec![];
+            for field in struct_expr.fields.iter() {
+                let field_type = field_types.iter().find(|(name, _)| name == &field.name);
+                if let Some((_, type_anno)) = field_type {
+                    params.push((*type_anno, &field.expr));
+                }
+            }
+            infer_type_args(&generic_struct.generics, &params, cur_scope)?
+        };
+        let instance_type = self.instantiate_generic_struct(&struct_expr.path, type_args)?;
+        struct_expr.path = instance_type.type_name().unwrap().to_string();
+        Ok(())
+    }
+
+    /// Visit and check the fields initialized in the struct or variant `path`
+    /// which has the fields `def`.
+    fn visit_field_exprs(
+        &mut self,
+        path: &str,
+        kind: &str,
+        def: &Fields,
+        fields: &mut [StructExprField],
+    ) -> Result<(), RccError> {
+        for field in fields.iter_mut() {
+            self.visit_expr(&mut field.expr)?;
+        }
+        self.check_field_exprs(path, kind, def, fields)
+    }
+
+    fn check_field_exprs(
+        &mut self,
+        path: &str,
+        kind: &str,
+        def: &Fields,
+        fields: &mut [StructExprField],
+    ) -> Result<(), RccError> {
+        let field_types = def.types();
+        let mut initialized = HashSet::new();
+        for field in fields.iter_mut() {
+            let type_anno = match field_types.iter().find(|(name, _)| name == &field.name) {
+                Some((_, type_anno)) => type_anno,
+                None => {
+                    return Err(format!(
+                        "{} `{}` has no field named `{}`",
+                        kind, path, field.name
+                    )
+                    .into())
+                }
+            };
+            if !initialized.insert(field.name.as_str()) {
+                return Err(format!("field `{}` specified more than once", field.name).into());
+            }
+            let field_type = TypeInfo::from_type_anno(type_anno, self.scope_stack.cur_scope());
+            let err_msg = format!("mismatched types in {} expr", kind);
+            self.expect_type(&field_type, &mut field.expr, &err_msg)?;
+        }
+        Ok(())
+    }
+
+    /// The enum and the variant named by the path `Enum::Variant`,
+    /// `None` if the path does not start with an enum.
+    fn find_enum_variant(
+        &self,
+        path: &str,
+    ) -> Result<Option<(TypeInfo, &EnumVariant)>, RccError> {
+        let (enum_name, variant_name) = match path.split_once("::") {
+            Some(names) => names,
+            None => return Ok(None),
+        };
+        let enum_type = self.scope_stack.cur_scope().find_def_except_fn(enum_name);
+        let type_enum = match &enum_type {
+            TypeInfo::Enum(type_enum) => unsafe { type_enum.as_ref() },
+            _ => return Ok(None),
+        };
+        match type_enum.variant(variant_name) {
+            Some((_, variant)) => Ok(Some((enum_type, variant))),
+            None => Err(format!(
+                "no variant named `{}` found for enum `{}`",
+                variant_name, enum_name
+            )
+            .into()),
+        }
+    }
+
+    /// `Enum::Variant`, `Enum::Variant(..)` and `Enum::Variant { .. }` are parsed as
+    /// path, call and struct exprs, which are replaced by enum variant exprs.
+    fn resolve_enum_variant(&self, expr: &mut Expr) -> Result<(), RccError> {
+        let variant_expr = match expr {
+            Expr::Path(path_expr) => {
+                let path = path_expr.segments.join("::");
+                match self.find_enum_variant(&path)? {
+                    Some((_, variant)) => {
+                        if variant.fields() != &Fields::None {
+                            return Err(format!(
+                                "expected value, found {} `{}`",
+                                variant_kind(variant.fields()),
+                                path
+                            )
+                            .into());
+                        }
+                        new_enum_variant_expr(&path, vec![])
+                    }
+                    None => return Ok(()),
+                }
+            }
+            Expr::Call(call_expr) => {
+                let path = match call_expr.expr.as_ref() {
+                    Expr::Path(path_expr) => path_expr.segments.join("::"),
+                    _ => return Ok(()),
+                };
+                match self.find_enum_variant(&path)? {
+                    Some((_, variant)) => {
+                        let field_count = match variant.fields() {
+                            Fields::Tuple(tuple_fields) => tuple_fields.len(),
+                            fields => {
+                                return Err(format!(
+                                    "expected function, found {} `{}`",
+                                    variant_kind(fields),
+                                    path
+                                )
+                                .into())
+                            }
+                        };
+                        if call_expr.call_params.len() != field_count {
+                            return Err(format!(
+                                "this enum variant takes {} fields but {} fields were supplied",
+                                field_count,
+                                call_expr.call_params.len()
+                            )
+                            .into());
+                        }
+                        let fields = std::mem::take(&mut call_expr.call_params)
+                            .into_iter()
+                            .enumerate()
+                            .map(|(i, expr)| StructExprField::new(i.to_string(), expr))
+                            .collect();
+                        new_enum_variant_expr(&path, fields)
+                    }
+                    None => return Ok(()),
+                }
+            }
+            Expr::Struct(struct_expr) => match self.find_enum_variant(&struct_expr.path)? {
+                Some(_) => {
+                    if struct_expr.base.is_some() {
+                        return Err("functional record update syntax requires a struct".into());
+                    }
+                    let fields = std::mem::take(&mut struct_expr.fields);
+                    new_enum_variant_expr(&struct_expr.path, fields)
+                }
+                None => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+        *expr = Expr::EnumVariant(variant_expr);
+        Ok(())
+    }
+
+    /// `Struct` and `Struct(..)` of unit and tuple structs are parsed as path and call exprs,
+    /// which are replaced by struct exprs.
+    fn resolve_struct_ctor(&self, expr: &mut Expr) -> Result<(), RccError> {
+        let (path_expr, args) = match expr {
+            Expr::Path(path_expr) => (&mut *path_expr, None),
+            Expr::Call(call_expr) => match call_expr.expr.as_mut() {
+                Expr::Path(path_expr) => (path_expr, Some(&mut call_expr.call_params)),
+                _ => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+        let name = match path_expr.segments.as_slice() {
+            [name] => name.clone(),
+            _ => return Ok(()),
+        };
+        let cur_scope = self.scope_stack.cur_scope();
+        if cur_scope.find_variable(&name).is_some() {
+            return Ok(());
+        }
+        let fields = match cur_scope.find_generic_struct(&name) {
+            Some(item_struct) => item_struct.fields(),
+            None => match cur_scope.find_def_except_fn(&name) {
+                TypeInfo::Struct { fields, .. } => unsafe { fields.as_ref() },
+                _ => return Ok(()),
+            },
+        };
+        let fields = match (fields, args) {
+            (Fields::None, None) => vec![],
+            (Fields::Tuple(tuple_fields), Some(args)) => {
+                if args.len() != tuple_fields.len() {
+                    return Err(format!(
+                        "this struct takes {} fields but {} fields were supplied",
+                        tuple_fields.len(),
+                        args.len()
+                    )
+                    .into());
+                }
+                std::mem::take(args)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, expr)| StructExprField::new(i.to_string(), expr))
+                    .collect()
+            }
+            (fields, None) => {
+                return Err(
+                    format!("expected value, found {} `{}`", struct_kind(fields), name).into(),
+                )
+            }
+            (fields, Some(_)) => {
+                return Err(format!(
+                    "expected function, found {} `{}`",
+                    struct_kind(fields),
+                    name
+                )
+                .into())
+            }
+        };
+        let type_args = std::mem::take(&mut path_expr.type_args);
+        *expr = Expr::Struct(StructExpr::new(name, fields, None).type_args(type_args));
+        Ok(())
+    }
+
+    /// Every field of the variant must be initialized.
+    fn visit_enum_variant_expr(
+        &mut self,
+        enum_variant_expr: &mut EnumVariantExpr,
+    ) -> Result<(), RccError> {
+        let enum_type = self
+            .scope_stack
+            .cur_scope()
+            .find_def_except_fn(&enum_variant_expr.enum_name);
+        let type_enum = match &enum_type {
+            TypeInfo::Enum(type_enum) => unsafe { type_enum.as_ref() },
+            t => unreachable!("enum variant of {:?}", t),
+        };
+        let (_, variant) = type_enum.variant(&enum_variant_expr.variant).unwrap();
+        let path = format!("{}::{}", enum_variant_expr.enum_name, enum_variant_expr.variant);
+        self.visit_field_exprs(&path, "variant", variant.fields(), &mut enum_variant_expr.fields)?;
+        check_missing_fields(&path, variant.fields(), &enum_variant_expr.fields)?;
+        enum_variant_expr.set_type_info(enum_type);
+        Ok(())
     }
 
     fn visit_call_expr(&mut self, call_expr: &mut CallExpr) -> Result<(), RccError> {
+        let generic_fn = match call_expr.expr.as_ref() {
+            Expr::Path(path_expr) if path_expr.segments.len() == 1 => {
+                let cur_scope = self.scope_stack.cur_scope();
+                let ident = &path_expr.segments[0];
+                cur_scope.find_variable(ident).is_none()
+                    && cur_scope.find_generic_fn(ident).is_some()
+            }
+            _ => false,
+        };
+        if generic_fn {
+            // the type arguments are inferred from the params
+            for expr in call_expr.call_params.iter_mut() {
+                self.visit_expr(expr)?;
+            }
+            self.instantiate_generic_fn(call_expr)?;
+        }
         self.visit_expr(&mut call_expr.expr)?;
         if !call_expr.expr.is_callable() {
             return Err("expr is not callable".into());
@@ -976,7 +2917,7 @@ impl SymbolResolver {
         let type_info = tp.deref();
         let type_fn_ptr = match type_info {
             TypeInfo::FnPtr(fn_ptr) => fn_ptr,
-            TypeInfo::Fn { vis: _, inner } => inner,
+            TypeInfo::Fn { inner, .. } => inner,
             _ => unreachable!("callable type can only be fn_ptr or fn"),
         };
 
@@ -988,45 +2929,239 @@ impl SymbolResolver {
             )
             .into());
         }
-        for (expr, param) in call_expr
-            .call_params
-            .iter_mut()
-            .zip(type_fn_ptr.params.iter())
-        {
+        if generic_fn {
+            self.check_call_params(&mut call_expr.call_params, &type_fn_ptr.params)?;
+        } else {
+            self.visit_call_params(&mut call_expr.call_params, &type_fn_ptr.params)?;
+        }
+        call_expr.set_type_info(TypeInfo::from_type_anno(
+            &type_fn_ptr.ret_type,
+            self.scope_stack.cur_scope(),
+        ));
+        Ok(())
+    }
+
+    fn visit_call_params(
+        &mut self,
+        call_params: &mut [Expr],
+        params: &[TypeAnnotation],
+    ) -> Result<(), RccError> {
+        for expr in call_params.iter_mut() {
             self.visit_expr(expr)?;
+        }
+        self.check_call_params(call_params, params)
+    }
+
+    fn check_call_params(
+        &mut self,
+        call_params: &mut [Expr],
+        params: &[TypeAnnotation],
+    ) -> Result<(), RccError> {
+        for (expr, param) in call_params.iter_mut().zip(params.iter()) {
             let excepted_info = TypeInfo::from_type_anno(param, self.scope_stack.cur_scope());
 
-            Self::try_determine_number_type(&excepted_info, expr);
-            assert_type_is(expr, &excepted_info, "invalid type for call expr")?;
+            self.expect_type(&excepted_info, expr, "invalid type for call expr")?;
+        }
+        Ok(())
+    }
+
+    /// Replace the generic function `f` called by its instance `f::<T1, T2>`,
+    /// the type arguments are inferred from the params if they are not given,
+    /// and must implement the traits bounding the type params.
+    /// The instance is created at the first call, and resolved after the items of the file.
+    fn instantiate_generic_fn(&mut self, call_expr: &mut CallExpr) -> Result<(), RccError> {
+        let path_expr = match call_expr.expr.as_mut() {
+            Expr::Path(path_expr) => path_expr,
+            _ => unreachable!(),
+        };
+        let explicit_type_args = self.explicit_type_args(&path_expr.type_args)?;
+        let cur_scope = self.scope_stack.cur_scope();
+        let generic_fn = cur_scope.find_generic_fn(&path_expr.segments[0]).unwrap();
+        let params = generic_fn.fn_params.type_annotations();
+        if call_expr.call_params.len() != params.len() {
+            return Err(format!(
+                "This function takes {} parameters but {} parameters was supplied",
+                params.len(),
+                call_expr.call_params.len(),
+            )
+            .into());
+        }
+        let type_args = if path_expr.type_args.is_empty() {
+            let params: Vec<(&TypeAnnotation, &Expr)> =
+                params.iter().zip(call_expr.call_params.iter()).collect();
+            infer_type_args(&generic_fn.generics, &params, cur_scope)?
+        } else if explicit_type_args.len() == generic_fn.generics.len() {
+            explicit_type_args
+        } else {
+            return Err(format!(
+                "function takes {} generic arguments but {} were supplied",
+                generic_fn.generics.len(),
+                explicit_type_args.len()
+            )
+            .into());
+        };
+
+        let type_arg_names = check_type_args(cur_scope, &generic_fn.generics, &type_args)?;
+        let instance_name = format!("{}::<{}>", generic_fn.name, type_args_string(&type_args));
+        if cur_scope.find_fn(&instance_name).is_unknown() {
+            let instance = ItemFn::instantiate(
+                generic_fn.template.as_ref().unwrap(),
+                generic_fn.vis(),
+                instance_name.clone(),
+                &type_arg_names,
+                &mut self.scope_count,
+            )?;
+            self.instantiate_fn_types(&instance)?;
+            if self.in_generic_fn {
+                self.scope_stack.cur_scope_mut().add_type_fn(&instance);
+            } else {
+                self.scope_stack.file_scope_mut().add_type_fn(&instance);
+                self.instances.push(instance);
+            }
+        }
+        path_expr.segments = vec![instance_name];
+        path_expr.type_args.clear();
+        Ok(())
+    }
+
+    /// `receiver.method(params)` of the implemented types is replaced by the call expr
+    /// `Type::method(receiver, params)`, where the receiver is borrowed or dereferenced
+    /// automatically to the type of `self`.
+    fn visit_method_call(&mut self, expr: &mut Expr) -> Result<(), RccError> {
+        let method_call_expr = match expr {
+            Expr::MethodCall(method_call_expr) => method_call_expr,
+            _ => unreachable!(),
+        };
+        self.visit_expr(&mut method_call_expr.receiver)?;
+        let (_, receiver_type) = auto_deref(&method_call_expr.receiver);
+        let receiver_type = receiver_type.default_number_type();
+        self.try_determine_number_type(&receiver_type, method_call_expr.receiver.as_mut())?;
+        let type_name = receiver_type.to_string();
+        let method = &method_call_expr.method;
+        let path = format!("{}::{}", type_name, method);
+        let fn_type = self.scope_stack.cur_scope().find_fn(&path);
+        // primitive types only have the methods of traits
+        if fn_type.is_unknown() && receiver_type.type_name().is_none() {
+            return self.visit_method_call_expr(method_call_expr);
+        }
+        let type_fn_ptr = match &fn_type {
+            TypeInfo::Fn {
+                inner,
+                is_method: true,
+                ..
+            } => inner.clone(),
+            TypeInfo::Fn { .. } => {
+                return Err(format!("`{}` is an associated function, not a method", method).into())
+            }
+            _ => {
+                return Err(
+                    format!("no method named `{}` found for `{}`", method, type_name).into(),
+                )
+            }
+        };
+        if method_call_expr.call_params.len() + 1 != type_fn_ptr.params.len() {
+            return Err(format!(
+                "This function takes {} parameters but {} parameters was supplied",
+                type_fn_ptr.params.len() - 1,
+                method_call_expr.call_params.len(),
+            )
+            .into());
         }
+
+        let self_type = TypeInfo::from_type_anno(&type_fn_ptr.params[0], self.scope_stack.cur_scope());
+        let receiver = std::mem::replace(method_call_expr.receiver.as_mut(), Expr::LitBool(false));
+        let mut call_params = vec![adjust_receiver(receiver, &self_type)?];
+        call_params.append(&mut method_call_expr.call_params);
+        self.visit_call_params(&mut call_params[1..], &type_fn_ptr.params[1..])?;
+
+        let mut path_expr = PathExpr::new();
+        path_expr.segments = vec![type_name, method_call_expr.method.clone()];
+        path_expr.set_type_info(fn_type);
+        path_expr.expr_kind = ExprKind::Value;
+        let mut call_expr = CallExpr::new(Expr::Path(path_expr)).call_params(call_params);
         call_expr.set_type_info(TypeInfo::from_type_anno(
             &type_fn_ptr.ret_type,
             self.scope_stack.cur_scope(),
         ));
+        *expr = Expr::Call(call_expr);
+        Ok(())
+    }
+
+    /// Only the methods of raw pointers are supported, which move the pointer by
+    /// `count` elems: `add(count: usize)`, `sub(count: usize)` and `offset(count: isize)`.
+    fn visit_method_call_expr(
+        &mut self,
+        method_call_expr: &mut MethodCallExpr,
+    ) -> Result<(), RccError> {
+        let receiver_type = method_call_expr.receiver.type_info().borrow().clone();
+        let param_type = match (&receiver_type, method_call_expr.method.as_str()) {
+            (TypeInfo::Ptr { kind, .. }, "add" | "sub") if kind.is_raw() => TypeLitNum::Usize,
+            (TypeInfo::Ptr { kind, .. }, "offset") if kind.is_raw() => TypeLitNum::Isize,
+            (t, method) => {
+                return Err(format!("no method named `{}` found for `{:?}`", method, t).into())
+            }
+        };
+        if method_call_expr.call_params.len() != 1 {
+            return Err(format!(
+                "This function takes 1 parameters but {} parameters was supplied",
+                method_call_expr.call_params.len(),
+            )
+            .into());
+        }
+        let param_type = TypeInfo::LitNum(param_type);
+        for expr in method_call_expr.call_params.iter_mut() {
+            self.visit_expr(expr)?;
+            self.expect_type(&param_type, expr, "invalid type for call expr")?;
+        }
+        method_call_expr.set_type_info(receiver_type);
         Ok(())
     }
 
+    /// References to structs are dereferenced automatically,
+    /// the field is a place of the struct or the referenced struct.
     fn visit_field_access_expr(
         &mut self,
         field_access_expr: &mut FieldAccessExpr,
     ) -> Result<(), RccError> {
-        Ok(())
+        self.visit_expr(&mut field_access_expr.lhs)?;
+        let (expr_kind, type_info) = auto_deref(field_access_expr.lhs.as_ref());
+        field_access_expr.expr_kind = expr_kind;
+
+        let field = &field_access_expr.field;
+        let type_anno = match &type_info {
+            TypeInfo::Struct { fields, .. } => unsafe { fields.as_ref() }
+                .types()
+                .into_iter()
+                .find(|(name, _)| name == field)
+                .map(|(_, type_anno)| type_anno),
+            _ => None,
+        };
+        match type_anno {
+            Some(type_anno) => {
+                let field_type = TypeInfo::from_type_anno(type_anno, self.scope_stack.cur_scope());
+                field_access_expr.set_type_info(field_type);
+                Ok(())
+            }
+            None if matches!(type_info, TypeInfo::Struct { .. }) => {
+                Err(format!("no field `{}` on struct", field).into())
+            }
+            None => Err(format!("no field `{}` on type `{:?}`", field, type_info).into()),
+        }
     }
 
     fn visit_while_expr(&mut self, while_expr: &mut WhileExpr) -> Result<(), RccError> {
-        self.visit_expr(&mut while_expr.0)?;
+        self.visit_expr(&mut while_expr.cond)?;
         // store loop kind
-        self.loop_kind_stack.push(self.loop_kind);
-        self.loop_kind = LoopKind::While;
+        self.enter_loop(LoopKind::While, while_expr.label.clone());
         assert_type_is(
-            &*while_expr.0,
+            &*while_expr.cond,
             &TypeInfo::Bool,
             "invalid type in while condition",
         )?;
 
-        self.visit_block_expr(&mut while_expr.1)?;
+        self.visit_block_expr(&mut while_expr.block)?;
         assert_type_is(
-            &*while_expr.1,
+            &*while_expr.block,
             &TypeInfo::Unit,
             "invalid type in while block",
         )?;
@@ -1037,8 +3172,7 @@ impl SymbolResolver {
     }
 
     fn visit_loop_expr(&mut self, loop_expr: &mut LoopExpr) -> Result<(), RccError> {
-        self.loop_kind_stack.push(self.loop_kind);
-        self.loop_kind = LoopKind::Loop(loop_expr);
+        self.enter_loop(LoopKind::Loop(loop_expr), loop_expr.label.clone());
         self.visit_block_expr(&mut loop_expr.expr)?;
         // never return, example: `let a = loop {};`
         let t = loop_expr.type_info();
@@ -1051,6 +3185,36 @@ impl SymbolResolver {
         Ok(())
     }
 
+    fn visit_for_expr(&mut self, for_expr: &mut ForExpr) -> Result<(), RccError> {
+        let type_info = match for_expr.expr.as_mut() {
+            Expr::Range(range_expr) => self.visit_range_expr(range_expr)?,
+            e => return Err(format!("invalid iterator in for expr: {:?}", e).into()),
+        };
+        // the pattern is bound in the scope of the loop block
+        if !for_expr.pattern.is_irrefutable() {
+            return Err(format!(
+                "refutable pattern in `for` loop binding: {:?}",
+                for_expr.pattern
+            )
+            .into());
+        }
+        let mut bindings = vec![];
+        self.visit_pattern(&for_expr.pattern, type_info, BindingMode::Move, &mut bindings)?;
+        for (ident, kind, type_info) in bindings {
+            for_expr.block.scope.add_variable(&ident, kind, type_info);
+        }
+
+        self.enter_loop(LoopKind::For, for_expr.label.clone());
+        self.visit_block_expr(&mut for_expr.block)?;
+        assert_type_is(
+            &*for_expr.block,
+            &TypeInfo::Unit,
+            "invalid type in for block",
+        )?;
+        self.exit_loop();
+        Ok(())
+    }
+
     fn visit_if_expr(&mut self, if_expr: &mut IfExpr) -> Result<(), RccError> {
         debug_assert!(
             if_expr.conditions.len() == if_expr.blocks.len()
@@ -1076,32 +3240,99 @@ impl SymbolResolver {
             }
         }
 
-        let mut block_type = TypeInfo::Unknown;
+        let mut if_type: Option<Rc<RefCell<TypeInfo>>> = None;
         for block in if_expr.blocks.iter_mut() {
             self.visit_block_expr(block)?;
             let type_info = block.type_info();
-            let t = type_info.borrow();
-            let tp = t.deref();
             debug_assert_ne!(&TypeInfo::Unknown, type_info.borrow().deref());
+            if type_info.borrow().is_never() {
+                continue;
+            }
+            match &if_type {
+                Some(if_type) => {
+                    self.unify_branches(if_type, &type_info, "different type of if block")?
+                }
+                None => if_type = Some(type_info),
+            }
+        }
 
-            if block_type != TypeInfo::Unknown && !block_type.eq_or_never(tp) {
-                return Err(format!(
-                    "different type of if block: `{:?}`, `{:?}`",
-                    block_type, type_info
-                )
-                .into());
+        match if_type {
+            Some(if_type) => if_expr.set_type_info_ref(if_type),
+            None => if_expr.set_type_info(TypeInfo::Never),
+        }
+        Ok(())
+    }
+
+    fn visit_match_expr(&mut self, match_expr: &mut MatchExpr) -> Result<(), RccError> {
+        self.visit_expr(&mut match_expr.expr)?;
+        let scrutinee_type = match_expr.expr.type_info();
+        for arm in match_expr.arms.iter_mut() {
+            self.lower_const_patterns(&mut arm.pattern)?;
+        }
+
+        // the suffix of an integer pattern determines the type of the scrutinee
+        if scrutinee_type.borrow().is_i() {
+            let suffix_type = match_expr
+                .arms
+                .iter()
+                .flat_map(|arm| arm.pattern.literals())
+                .find_map(|lit| match lit {
+                    LitPattern::Int(_, t) if t != &TypeLitNum::I => Some(*t),
+                    _ => None,
+                });
+            if let Some(t) = suffix_type {
+                self.try_determine_number_type(&TypeInfo::LitNum(t), match_expr.expr.as_mut())?;
+            }
+        }
+
+        let mut match_type: Option<Rc<RefCell<TypeInfo>>> = None;
+        for arm in match_expr.arms.iter_mut() {
+            let mut bindings = vec![];
+            self.visit_pattern(
+                &arm.pattern,
+                scrutinee_type.clone(),
+                BindingMode::by_value(&match_expr.expr),
+                &mut bindings,
+            )?;
+
+            self.scope_stack.enter(&mut arm.scope);
+            for (ident, kind, type_info) in bindings {
+                self.scope_stack
+                    .cur_scope_mut()
+                    .add_variable(&ident, kind, type_info);
             }
+            if let Some(guard) = arm.guard.as_mut() {
+                self.visit_expr(guard)?;
+                assert_type_is(guard.as_ref(), &TypeInfo::Bool, "invalid type of match guard")?;
+            }
+            self.visit_expr(&mut arm.expr)?;
+            self.scope_stack.exit_scope();
 
-            if tp != &TypeInfo::Never {
-                block_type = tp.clone();
+            // arms of number literals without suffix take the type of other arms
+            let type_info = arm.expr.type_info();
+            if type_info.borrow().is_never() {
+                continue;
+            }
+            match &match_type {
+                Some(match_type) => {
+                    self.unify_branches(match_type, &type_info, "different type of match arm")?
+                }
+                None => match_type = Some(type_info),
             }
         }
 
-        if_expr.set_type_info(if block_type == TypeInfo::Unknown {
-            TypeInfo::Never
-        } else {
-            block_type
-        });
+        let (value_type, _) = peel_refs(scrutinee_type, BindingMode::Move);
+        let field_types = |fields: &Fields| self.field_types(fields);
+        let mut value_space = ValueSpace::new(value_type.borrow().deref(), &field_types);
+        for arm in match_expr.arms.iter() {
+            value_space.cover(&arm.pattern, arm.guard.is_some())?;
+        }
+        value_space.check_exhaustive()?;
+
+        match match_type {
+            Some(match_type) => match_expr.set_type_info_ref(match_type),
+            None => match_expr.set_type_info(TypeInfo::Never),
+        }
         Ok(())
     }
 
@@ -1109,7 +3340,8 @@ impl SymbolResolver {
         match return_expr.0.as_mut() {
             Some(expr) => {
                 self.visit_expr(expr)?;
-                Self::try_determine_number_type(&self.cur_fn_ret_type, expr.as_mut());
+                let ret_type = self.cur_fn_ret_type.clone();
+                self.try_determine_number_type(&ret_type, expr.as_mut())?;
                 let type_info = expr.type_info();
                 let t = type_info.borrow();
                 let tp = t.deref();
@@ -1120,53 +3352,449 @@ impl SymbolResolver {
     }
 
     fn visit_break_expr(&mut self, break_expr: &mut BreakExpr) -> Result<(), RccError> {
-        fn try_set_type_info(
-            loop_expr: *mut LoopExpr,
-            type_info: Rc<RefCell<TypeInfo>>,
-        ) -> Result<(), RccError> {
-            let tp = type_info.borrow();
-            let t = tp.deref();
-            let loop_expr = unsafe { &mut *loop_expr };
-            let l = loop_expr.type_info();
-            let lt = l.borrow();
-            let loop_type_info = lt.deref();
-            if loop_type_info.is_unknown() {
-                loop_expr.set_type_info_ref(type_info.clone());
-                Ok(())
-            } else if !t.is(loop_type_info) {
-                Err(format!(
-                    "invalid type for break expr: expected `{:?}`, found {:?}",
-                    loop_type_info, t
-                )
-                .into())
-            } else {
-                Ok(())
-            }
-        }
-
-        if !self.loop_kind.is_in_loop() {
-            return Err("break expr can not be out of loop block".into());
-        }
-
-        if let Some(expr) = break_expr.0.as_mut() {
-            return match self.loop_kind {
+        let loop_kind = self.find_loop(&break_expr.label, "break")?;
+        if let Some(expr) = break_expr.expr.as_mut() {
+            return match loop_kind {
                 LoopKind::Loop(loop_expr) => {
                     self.visit_expr(expr)?;
-                    Self::try_determine_number_type(
-                        unsafe { (*loop_expr).type_info().borrow().deref() },
-                        expr.as_mut(),
-                    );
-                    try_set_type_info(loop_expr, expr.type_info())
+                    let loop_type = unsafe { (*loop_expr).type_info().borrow().clone() };
+                    self.try_determine_number_type(&loop_type, expr.as_mut())?;
+                    self.set_loop_type(loop_expr, expr.type_info())
                 }
                 _ => Err("only loop can return values".into()),
             };
-        } else if let LoopKind::Loop(loop_expr) = self.loop_kind {
-            return try_set_type_info(loop_expr, Rc::new(RefCell::new(TypeInfo::Unit)));
+        } else if let LoopKind::Loop(loop_expr) = loop_kind {
+            return self.set_loop_type(loop_expr, Rc::new(RefCell::new(TypeInfo::Unit)));
+        }
+        Ok(())
+    }
+
+    /// The type of `loop` is the type of its first `break`, and the other `break`s must be
+    /// of the same type.
+    fn set_loop_type(
+        &mut self,
+        loop_expr: *mut LoopExpr,
+        type_info: Rc<RefCell<TypeInfo>>,
+    ) -> Result<(), RccError> {
+        let loop_expr = unsafe { &mut *loop_expr };
+        let loop_type = loop_expr.type_info();
+        if loop_type.borrow().is_unknown() {
+            loop_expr.set_type_info_ref(type_info);
+            Ok(())
+        } else if type_info.borrow().is(&loop_type.borrow()) {
+            Ok(())
+        } else if loop_type.partial_cmp(&type_info).is_some()
+            || self.infer_ctx.is_inferred(&loop_type)
+            || self.infer_ctx.is_inferred(&type_info)
+        {
+            self.unify(&loop_type, &type_info)
+        } else {
+            Err(format!(
+                "invalid type for break expr: expected `{:?}`, found {:?}",
+                loop_type.borrow().deref(),
+                type_info.borrow().deref()
+            )
+            .into())
+        }
+    }
+
+    /// Numbers can be cast to each other, `bool` and `char` to integers, `u8` to `char`,
+    /// fieldless enums to integers which hold all the discriminants,
+    /// references to raw pointers, and raw pointers to each other and to and from integers.
+    fn visit_cast_expr(&mut self, cast_expr: &mut CastExpr) -> Result<(), RccError> {
+        self.visit_expr(&mut cast_expr.expr)?;
+        self.instantiate_type(&cast_expr.type_anno)?;
+        let target_type = TypeInfo::from_type_anno(&cast_expr.type_anno, self.scope_stack.cur_scope());
+        if target_type.is_unknown() {
+            return Err(format!("cannot find type `{:?}`", cast_expr.type_anno).into());
+        }
+        // `300 as u8` is `300u8` and `&x as *const u8` is a coercion,
+        // but the types of number variables are not inferred from casts
+        if matches!(cast_expr.expr.as_ref(), Expr::LitNum(_))
+            || matches!(target_type, TypeInfo::Ptr { .. })
+        {
+            self.try_determine_number_type(&target_type, cast_expr.expr.as_mut())?;
+        }
+        let type_info = cast_expr.expr.type_info();
+        match (type_info.borrow().deref(), &target_type) {
+            (TypeInfo::Enum(type_enum), TypeInfo::LitNum(lit_num)) if lit_num.is_integer() => {
+                let type_enum = unsafe { type_enum.as_ref() };
+                if !type_enum.is_fieldless() {
+                    return Err(format!(
+                        "non-primitive cast: `{}` as `{}`",
+                        type_enum.name(),
+                        target_type
+                    )
+                    .into());
+                }
+                let (low, high) = integer_bounds(*lit_num);
+                if type_enum.discriminants().iter().any(|&d| d < low || d > high) {
+                    return Err(format!(
+                        "casting `{}` as `{}` truncates the discriminants, which is not supported yet",
+                        type_enum.name(),
+                        target_type
+                    )
+                    .into());
+                }
+            }
+            (t, target) if t.is(target) => {}
+            // truncation, sign or zero extension, rounding and saturation
+            (TypeInfo::LitNum(_), TypeInfo::LitNum(_)) => {}
+            (TypeInfo::Bool | TypeInfo::Char, TypeInfo::LitNum(lit_num))
+                if lit_num.is_integer() => {}
+            (TypeInfo::LitNum(TypeLitNum::U8), TypeInfo::Char) => {}
+            (t, TypeInfo::Char) if t.is_number() => {
+                return Err(format!("only `u8` can be cast as `char`, not `{}`", t).into());
+            }
+            // `&a as *const i32 as *const u8`
+            (TypeInfo::Ptr { kind, .. }, TypeInfo::Ptr { kind: target_kind, .. })
+                if kind.is_raw() && target_kind.is_raw() => {}
+            // the addresses of raw pointers
+            (TypeInfo::Ptr { kind, .. }, TypeInfo::LitNum(lit_num))
+            | (TypeInfo::LitNum(lit_num), TypeInfo::Ptr { kind, .. })
+                if kind.is_raw() && lit_num.is_integer() => {}
+            (t, target) => {
+                return Err(format!("casting `{}` as `{}` is invalid", t, target).into())
+            }
         }
+        cast_expr.set_type_info(target_type);
+        Ok(())
+    }
+
+    fn visit_continue_expr(&mut self, continue_expr: &mut ContinueExpr) -> Result<(), RccError> {
+        self.find_loop(&continue_expr.0, "continue")?;
         Ok(())
     }
 }
 
+/// How the variables in a pattern are bound.
+/// Matching a reference with a non-reference pattern binds the variables by reference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BindingMode {
+    Move,
+    /// by value from an immutable place, which cannot be borrowed by `ref mut`
+    MoveFromPlace,
+    Ref,
+    RefMut,
+}
+
+impl BindingMode {
+    fn by_value(scrutinee: &Expr) -> BindingMode {
+        if scrutinee.kind() == ExprKind::Place {
+            BindingMode::MoveFromPlace
+        } else {
+            BindingMode::Move
+        }
+    }
+}
+
+/// Variables bound by a pattern
+type Bindings = Vec<(String, VarKind, Rc<RefCell<TypeInfo>>)>;
+
+/// Dereference the type of the matched value for a non-reference pattern.
+/// `&mut` becomes `&` once a shared reference is passed.
+fn peel_refs(
+    type_info: Rc<RefCell<TypeInfo>>,
+    mut mode: BindingMode,
+) -> (Rc<RefCell<TypeInfo>>, BindingMode) {
+    let mut type_info = type_info;
+    loop {
+        let inner_type = match type_info.borrow().deref() {
+            TypeInfo::Ptr {
+                kind: PtrKind::Ref,
+                type_info,
+            } => {
+                mode = BindingMode::Ref;
+                type_info.as_ref().clone()
+            }
+            TypeInfo::Ptr {
+                kind: PtrKind::MutRef,
+                type_info,
+            } => {
+                if mode != BindingMode::Ref {
+                    mode = BindingMode::RefMut;
+                }
+                type_info.as_ref().clone()
+            }
+            _ => break,
+        };
+        type_info = Rc::new(RefCell::new(inner_type));
+    }
+    (type_info, mode)
+}
+
+/// Kind and type of the place referred by `expr` through references.
+fn auto_deref(expr: &Expr) -> (ExprKind, TypeInfo) {
+    let mut expr_kind = expr.kind();
+    let mut type_info = expr.type_info().borrow().clone();
+    while let TypeInfo::Ptr {
+        kind: kind @ (PtrKind::Ref | PtrKind::MutRef),
+        type_info: inner,
+    } = type_info
+    {
+        expr_kind = if kind == PtrKind::MutRef {
+            ExprKind::MutablePlace
+        } else {
+            ExprKind::Place
+        };
+        type_info = *inner;
+    }
+    (expr_kind, type_info)
+}
+
+/// The path of the elem `index` of the value `auto_deref` of `expr` in the type of `expr`,
+/// see `InferCtx::add_elem`.
+fn auto_deref_path(expr: &Expr, index: usize) -> Vec<usize> {
+    let mut path = vec![];
+    let mut type_info = expr.type_info().borrow().clone();
+    while let TypeInfo::Ptr {
+        kind: PtrKind::Ref | PtrKind::MutRef,
+        type_info: inner,
+    } = type_info
+    {
+        path.push(0);
+        type_info = *inner;
+    }
+    path.push(index);
+    path
+}
+
+/// `&receiver`, `&mut receiver` or `*receiver` of the type of `self`,
+/// the receiver is kept if it is of the type already.
+fn adjust_receiver(receiver: Expr, self_type: &TypeInfo) -> Result<Expr, RccError> {
+    let receiver_type = receiver.type_info().borrow().clone();
+    if receiver_type.is(self_type) {
+        return Ok(receiver);
+    }
+    let (op, expr_kind) = match (&receiver_type, self_type) {
+        (_, TypeInfo::Ptr { kind, type_info }) if type_info.as_ref() == &receiver_type => {
+            match kind {
+                PtrKind::Ref => (UnOp::Borrow, ExprKind::Value),
+                PtrKind::MutRef if receiver.kind() == ExprKind::Place => {
+                    return Err("cannot borrow immutable place as mutable".into())
+                }
+                PtrKind::MutRef => (UnOp::BorrowMut, ExprKind::Value),
+                _ => unreachable!("raw pointer `self`"),
+            }
+        }
+        (TypeInfo::Ptr { kind, type_info }, _) if !kind.is_raw() && type_info.is(self_type) => {
+            match kind {
+                PtrKind::MutRef => (UnOp::Deref, ExprKind::MutablePlace),
+                _ => (UnOp::Deref, ExprKind::Place),
+            }
+        }
+        _ => {
+            return Err(format!(
+                "invalid type for self: expected {:?}, found {:?}",
+                self_type, receiver_type
+            )
+            .into())
+        }
+    };
+    let mut unary_expr = UnAryExpr::new(op, receiver);
+    unary_expr.set_type_info(self_type.clone());
+    unary_expr.expr_kind = expr_kind;
+    Ok(Expr::Unary(unary_expr))
+}
+
+/// `i32, Point` of the type arguments in the names of the instances.
+fn type_args_string(type_args: &[TypeInfo]) -> String {
+    type_args
+        .iter()
+        .map(|t| t.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Whether `type_info` is an instance `Pair<T>` of the generic struct `Pair`.
+fn is_instance_of(type_info: &TypeInfo, generic_name: &str) -> bool {
+    match type_info.type_name() {
+        Some(name) => name.starts_with(generic_name) && name[generic_name.len()..].starts_with('<'),
+        None => false,
+    }
+}
+
+/// The type arguments must implement the traits bounding the type params `generics`,
+/// return the pairs of the names of the params and the arguments.
+fn check_type_args(
+    scope: &Scope,
+    generics: &[GenericParam],
+    type_args: &[TypeInfo],
+) -> Result<Vec<(String, String)>, RccError> {
+    let mut type_arg_names = vec![];
+    for (generic_param, type_arg) in generics.iter().zip(type_args.iter()) {
+        for bound in generic_param.bounds.iter() {
+            if scope.find_trait(bound).is_none() {
+                return Err(format!("cannot find trait `{}` in this scope", bound).into());
+            }
+            if !scope.implements(&type_arg.to_string(), bound) {
+                return Err(
+                    format!("the trait bound `{}: {}` is not satisfied", type_arg, bound).into(),
+                );
+            }
+        }
+        type_arg_names.push((generic_param.name.clone(), type_arg.to_string()));
+    }
+    Ok(type_arg_names)
+}
+
+/// Infer the type params `generics` from the exprs given to the params of the types,
+/// number literals without suffixes are bound at last.
+fn infer_type_args(
+    generics: &[GenericParam],
+    params: &[(&TypeAnnotation, &Expr)],
+    scope: &Scope,
+) -> Result<Vec<TypeInfo>, RccError> {
+    let mut type_args: Vec<(String, Option<TypeInfo>)> = generics
+        .iter()
+        .map(|param| (param.name.clone(), None))
+        .collect();
+    for number_literal in [false, true] {
+        for (param, expr) in params.iter() {
+            let arg = expr.type_info().borrow().clone();
+            if arg.has_number_literal() == number_literal {
+                bind_type_params(param, &arg, &mut type_args, scope)?;
+            }
+        }
+    }
+    type_args
+        .into_iter()
+        .map(|(param, type_arg)| match type_arg {
+            Some(type_arg) => Ok(type_arg),
+            None => Err(format!(
+                "type annotations needed: cannot infer type for type parameter `{}`",
+                param
+            )
+            .into()),
+        })
+        .collect()
+}
+
+/// Bind the type params in the type annotation `param` to the parts of the type `arg`,
+/// the bound types must be the same.
+fn bind_type_params(
+    param: &TypeAnnotation,
+    arg: &TypeInfo,
+    type_args: &mut [(String, Option<TypeInfo>)],
+    scope: &Scope,
+) -> Result<(), RccError> {
+    match (param, arg) {
+        (TypeAnnotation::Identifier(name), _) => {
+            if let Some((_, type_arg)) = type_args.iter_mut().find(|(p, _)| p == name) {
+                match type_arg {
+                    None => *type_arg = Some(arg.default_number_type()),
+                    Some(t) if t == arg || t.can_determine(arg) => {}
+                    Some(t) => {
+                        return Err(format!(
+                            "mismatched types for type parameter `{}`: expected `{}`, found `{}`",
+                            name, t, arg
+                        )
+                        .into())
+                    }
+                }
+            }
+        }
+        (TypeAnnotation::Ptr(type_ptr), TypeInfo::Ptr { type_info, .. }) => {
+            bind_type_params(&type_ptr.type_anno, type_info, type_args, scope)?
+        }
+        (TypeAnnotation::Array(type_array), TypeInfo::Array { elem_type, .. }) => {
+            bind_type_params(&type_array._type, elem_type, type_args, scope)?
+        }
+        (TypeAnnotation::Tuple(types), TypeInfo::Tuple(infos)) if types.len() == infos.len() => {
+            for (t, info) in types.iter().zip(infos.iter()) {
+                bind_type_params(t, info, type_args, scope)?;
+            }
+        }
+        (TypeAnnotation::Generic(type_generic), TypeInfo::Struct { name, .. })
+            if is_instance_of(arg, &type_generic.name) =>
+        {
+            if let Some(instance_args) = scope.find_instance_args(name) {
+                for (t, instance_arg) in type_generic.args.iter().zip(instance_args.iter()) {
+                    bind_type_params(t, instance_arg, type_args, scope)?;
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Positions `(front, back)` of the patterns before and after `..` in tuple patterns
+/// matching `len` elems, `None` if the number of the patterns does not match.
+fn split_rest(patterns: &[Pattern], len: usize) -> Result<Option<(usize, usize)>, RccError> {
+    let rest_positions: Vec<usize> = patterns
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p == &&Pattern::Rest)
+        .map(|(i, _)| i)
+        .collect();
+    if rest_positions.len() > 1 {
+        return Err("`..` can only be used once per tuple pattern".into());
+    }
+    let (front, back) = match rest_positions.first() {
+        Some(&i) => (i, patterns.len() - i - 1),
+        None => (patterns.len(), 0),
+    };
+    if front + back > len || (rest_positions.is_empty() && front != len) {
+        Ok(None)
+    } else {
+        Ok(Some((front, back)))
+    }
+}
+
+/// Fields of struct or variant exprs without base must all be initialized.
+fn check_missing_fields(path: &str, def: &Fields, fields: &[StructExprField]) -> Result<(), RccError> {
+    match def
+        .types()
+        .into_iter()
+        .find(|(name, _)| fields.iter().all(|f| &f.name != name))
+    {
+        Some((name, _)) => {
+            Err(format!("missing field `{}` in initializer of `{}`", name, path).into())
+        }
+        None => Ok(()),
+    }
+}
+
+fn variant_kind(fields: &Fields) -> &'static str {
+    match fields {
+        Fields::Struct(_) => "struct variant",
+        Fields::Tuple(_) => "tuple variant",
+        Fields::None => "unit variant",
+    }
+}
+
+fn struct_kind(fields: &Fields) -> &'static str {
+    match fields {
+        Fields::Struct(_) => "struct",
+        Fields::Tuple(_) => "tuple struct",
+        Fields::None => "unit struct",
+    }
+}
+
+/// The enum variant expr of the path `Enum::Variant`
+fn new_enum_variant_expr(path: &str, fields: Vec<StructExprField>) -> EnumVariantExpr {
+    let (enum_name, variant) = path.split_once("::").unwrap();
+    EnumVariantExpr::new(enum_name.to_string(), variant.to_string(), fields)
+}
+
+/// The associated functions of a type are named by their paths,
+/// so they must be unique across all the impls of the type.
+fn check_duplicate_assoc_fns(items: &[Item]) -> Result<(), RccError> {
+    let mut paths = HashSet::new();
+    for item in items {
+        if let Item::Impl(item_impl) = item {
+            for item_fn in item_impl.items.iter() {
+                if !paths.insert(item_fn.name.as_str()) {
+                    let name = item_fn.name.rsplit("::").next().unwrap();
+                    return Err(format!("duplicate definitions with name `{}`", name).into());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 pub(super) fn assert_type_is<T: ExprVisit>(
     expr: &T,
     expected_type: &TypeInfo,
@@ -9,10 +9,10 @@ mod scope_test;
 fn get_ast_file(input: &str) -> Result<File, RccError> {
     // lex
     let mut lexer = Lexer::new(input);
-    let token_stream = lexer.tokenize();
+    let (token_stream, token_lines) = lexer.tokenize_with_lines();
 
     // parse
-    let mut cursor = ParseCursor::new(token_stream);
+    let mut cursor = ParseCursor::new(token_stream).with_lines(token_lines);
     let ast_file = File::parse(&mut cursor)?;
     Ok(ast_file)
 }
\ No newline at end of file
@@ -271,6 +271,59 @@ fn loop_test() {
     );
 }
 
+#[test]
+fn label_test() {
+    file_validate(
+        &[
+            r#"
+        fn foo() -> i32 {
+            'outer: loop {
+                let mut i = 0;
+                while i < 10 {
+                    if i == 5 {
+                        break 'outer i;
+                    }
+                    i += 1;
+                    continue;
+                }
+                continue 'outer;
+            }
+        }
+    "#,
+            r#"
+        fn foo() {
+            'a: for i in 0..3 {
+                loop {
+                    break 'b;
+                }
+            }
+        }
+    "#,
+            r#"
+        fn foo() {
+            'a: while true {
+                loop {
+                    break 'a 3;
+                }
+            }
+        }
+    "#,
+            r#"
+        fn foo() {
+            let a = 3;
+            continue;
+        }
+    "#,
+        ],
+        &[
+            Ok(()),
+            Err("use of undeclared label `'b`".into()),
+            Err("only loop can return values".into()),
+            Err("continue expr can not be out of loop block".into()),
+        ],
+    );
+}
+
 #[test]
 fn return_test() {
     file_validate(
@@ -487,6 +540,1473 @@ fn unknown_type_test() {
         b = 88;
         b = loop {};
     }
-    "#], &[Ok(()), Err("invalid type `LitNum(i128)` for `=`".into()), Ok(())]);
+    "#, r#"
+    fn foo() {
+        let x;
+    }
+    "#, r#"
+    fn foo() {
+        let x: i64;
+        let mut y;
+        x = 5;
+        y = 6;
+        let z: i32 = x;
+    }
+    "#], &[
+        Ok(()),
+        Err("mismatched types: expected `i128` (line 5), found `i32` (line 6)".into()),
+        Ok(()),
+        Err("type annotations needed: cannot infer the type at line 3".into()),
+        Err("invalid type in let stmt: expected `LitNum(i32)`, found `LitNum(i64)`".into()),
+    ]);
+}
+
+#[test]
+fn match_test() {
+    file_validate(
+        &[
+            r#"
+        fn foo(x: i32, c: char, b: bool) -> i32 {
+            let y = match x {
+                0 | 1 => 1,
+                2..=10 if b => 2,
+                n => n,
+            };
+            match c {
+                'a'..='z' => y,
+                _ => match b {
+                    true => 3,
+                    false => 4,
+                },
+            }
+        }
+    "#,
+            r#"
+        fn foo(x: u8) -> i32 {
+            match x {
+                0..=100 => 1,
+                200..=255 => 2,
+            }
+        }
+    "#,
+            r#"
+        fn foo(b: bool) -> i32 {
+            match b {
+                true => 1,
+                false if b => 2,
+            }
+        }
+    "#,
+            r#"
+        fn foo(x: i32) -> i32 {
+            match x {
+                0..=5 => 1,
+                3 | 6 => 2,
+                _ => 3,
+            }
+        }
+    "#,
+            r#"
+        fn foo(c: char) -> i32 {
+            match c {
+                'a' | 'b' => 1,
+                'a'..'c' => 2,
+                _ => 3,
+            }
+        }
+    "#,
+            r#"
+        fn foo(x: i32) -> i32 {
+            match x {
+                'a' => 1,
+                _ => 2,
+            }
+        }
+    "#,
+            r#"
+        fn foo(x: u8) -> i32 {
+            match x {
+                -1 => 1,
+                _ => 2,
+            }
+        }
+    "#,
+            r#"
+        fn foo(x: i32) {
+            match x {
+                0 => 1,
+                _ => {}
+            }
+        }
+    "#,
+            r#"
+        const X: u8 = 255;
+        const C: char = 'c';
+        fn foo(y: u8, c: char) -> i32 {
+            let z = match y {
+                X => 1,
+                0..=254 => 2,
+            };
+            match (c, y) {
+                (C, X) => z,
+                _ => 3,
+            }
+        }
+    "#,
+            r#"
+        const X: u8 = 255;
+        fn foo(y: u8) -> i32 {
+            match y {
+                X => 1,
+            }
+        }
+    "#,
+            r#"
+        const X: u8 = 255;
+        fn foo(y: u8) -> i32 {
+            match y {
+                X => 1,
+                255 => 2,
+                _ => 3,
+            }
+        }
+    "#,
+            r#"
+        const X: f64 = 2.5;
+        fn foo(y: f64) -> i32 {
+            match y {
+                X => 1,
+                _ => 2,
+            }
+        }
+    "#,
+        ],
+        &[
+            Ok(()),
+            Err("non-exhaustive patterns: `101..=199` not covered".into()),
+            Err("non-exhaustive patterns: `false` not covered".into()),
+            Err("unreachable pattern: `3`".into()),
+            Err("unreachable pattern: `'a'..'c'`".into()),
+            Err("mismatched types in pattern: expected `LitNum(i32)`, found `Char('a')`".into()),
+            Err("literal out of range for `LitNum(u8)`: -1".into()),
+            Err("different type of match arm: `LitNum(#i)`, `Unit`".into()),
+            Ok(()),
+            Err("non-exhaustive patterns: `0..=254` not covered".into()),
+            Err("unreachable pattern: `255`".into()),
+            Err("constant `X` of type `LitNum(f64)` cannot be used in patterns".into()),
+        ],
+    );
+}
+
+#[test]
+fn pattern_test() {
+    file_validate(
+        &[
+            r#"
+        struct Foo {
+            a: i32,
+            b: char,
+        }
+        fn foo(Foo { a, b: _ }: Foo, r: &Foo, _: bool) -> i32 {
+            let () = bar(r);
+            let Foo { a: c, .. } = r;
+            let &Foo { ref b, .. } = r;
+            let _ = c;
+            a
+        }
+        fn bar(_: &Foo) {}
+        fn baz(x: &i32) -> i32 {
+            match x {
+                &0 => 1,
+                1 | 2 => 2,
+                _ => 3,
+            }
+        }
+        fn qux(r: &mut (i32, i32)) {
+            let mut t = (1, 2);
+            let (ref mut a, _) = t;
+            let (ref mut b, _) = (3, 4);
+            let &mut (ref mut c, _) = r;
+            let (d, _) = r;
+            *a = 5;
+            *b = 6;
+            *c = 7;
+            *d = 8;
+        }
+    "#,
+            r#"
+        fn foo(x: i32) {
+            let 1 = x;
+        }
+    "#,
+            r#"
+        const X: u8 = 255;
+        fn foo(y: u8) {
+            let X = y;
+        }
+    "#,
+            r#"
+        fn foo(x: &i32) {
+            let &mut y = x;
+        }
+    "#,
+            r#"
+        struct Foo {
+            a: i32,
+        }
+        fn foo(r: &Foo) {
+            let Foo { mut a } = r;
+        }
+    "#,
+            r#"
+        struct Foo {
+            a: i32,
+        }
+        fn foo(r: &Foo) {
+            let Foo { ref mut a } = r;
+        }
+    "#,
+            r#"
+        struct Foo {
+            a: i32,
+            b: i32,
+        }
+        fn foo() {
+            let t = (1, 2);
+            let (ref mut a, b) = t;
+            *a = 3;
+        }
+    "#,
+            r#"
+        fn foo(x: i32) {
+            match x {
+                ref mut y => *y = 1,
+            }
+        }
+    "#,
+            r#"
+        fn foo(r: &(i32, i32)) {
+            let &(ref mut a, _) = r;
+        }
+    "#,
+            r#"
+        struct Foo {
+            a: i32,
+            b: i32,
+        }
+        fn foo(f: Foo) {
+            let Foo { a } = f;
+        }
+    "#,
+            r#"
+        struct Foo {
+            a: i32,
+        }
+        fn foo(f: Foo) {
+            let Foo { c, .. } = f;
+        }
+    "#,
+            r#"
+        struct Foo {
+            a: i32,
+            b: i32,
+        }
+        fn foo(Foo { a, b: a }: Foo) {}
+    "#,
+            r#"
+        fn foo() {
+            let (.., ..) = foo();
+        }
+    "#,
+            r#"
+        fn foo(x: i32) -> i32 {
+            match x {
+                0 | y => 1,
+            }
+        }
+    "#,
+            r#"
+        fn foo(x: i32) {
+            for 0 in 0..x {}
+        }
+    "#,
+        ],
+        &[
+            Ok(()),
+            Err("refutable pattern in local binding: Literal(Int(1, #i))".into()),
+            Err("refutable pattern in local binding: Literal(Int(255, u8))".into()),
+            Err("mismatched types in pattern: expected `Ptr { kind: Ref, type_info: LitNum(i32) }`, found `&mut _`".into()),
+            Err("binding cannot be both mutable and by-reference: `mut a`".into()),
+            Err("cannot borrow data in a `&` reference as mutable: `ref mut a`".into()),
+            Err("cannot borrow immutable place as mutable: `ref mut a`".into()),
+            Err("cannot borrow immutable place as mutable: `ref mut y`".into()),
+            Err("cannot borrow immutable place as mutable: `ref mut a`".into()),
+            Err("pattern does not mention field `b`".into()),
+            Err("struct `Foo` does not have a field named `c`".into()),
+            Err("identifier `a` is bound more than once in the same pattern".into()),
+            Err("`..` can only be used once per tuple pattern".into()),
+            Err("variable `y` is not bound in all patterns".into()),
+            Err("refutable pattern in `for` loop binding: Literal(Int(0, #i))".into()),
+        ],
+    );
+}
+
+#[test]
+fn struct_test() {
+    file_validate(
+        &[
+            r#"
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+        struct Line {
+            a: Point,
+            b: Point,
+        }
+        fn foo(l: &mut Line, p: &Point) -> i32 {
+            let mut q = Point { y: 2, ..*p };
+            q.x = p.y;
+            l.a = q;
+            l.b.x += 1;
+            let r = &mut q;
+            r.y = 3;
+            (&l.a).x + p.x
+        }
+    "#,
+            r#"
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+        fn foo() {
+            let p = Point { x: 1 };
+        }
+    "#,
+            r#"
+        struct Point {
+            x: i32,
+        }
+        fn foo() {
+            let p = Point { x: 1, x: 2 };
+        }
+    "#,
+            r#"
+        struct Point {
+            x: i32,
+        }
+        fn foo() {
+            let p = Point { x: 1, z: 2 };
+        }
+    "#,
+            r#"
+        struct Point {
+            x: i32,
+        }
+        fn foo() {
+            let p = Point { x: true };
+        }
+    "#,
+            r#"
+        struct Point {
+            x: i32,
+        }
+        fn foo(p: Point) -> i32 {
+            p.z
+        }
+    "#,
+            r#"
+        struct Point {
+            x: i32,
+        }
+        fn foo(p: Point) {
+            p.x = 2;
+        }
+    "#,
+            r#"
+        struct Point {
+            x: i32,
+        }
+        fn foo(p: &Point) {
+            p.x = 2;
+        }
+    "#,
+            r#"
+        fn foo() {
+            let p = Point { x: 1 };
+        }
+    "#,
+            r#"
+        struct List {
+            next: List,
+        }
+    "#,
+            r#"
+        struct Point {
+            x: Foo,
+        }
+    "#,
+            r#"
+        struct Node {
+            data: *mut i32,
+        }
+        fn foo(n: &Node, m: &mut Node) {
+            let mut p: *const i32 = n.data;
+            p = n.data;
+            p = if true { m.data } else { n.data };
+            m.data = n.data;
+        }
+    "#,
+            r#"
+        struct Unit;
+        struct Point(i32, i32);
+        fn foo() -> i32 {
+            let u = Unit;
+            let e = Unit {};
+            let p = Point(1, 2);
+            p.0
+        }
+    "#,
+            r#"
+        struct Point(i32, i32);
+        fn foo() {
+            let p = Point(1);
+        }
+    "#,
+            r#"
+        struct Point(i32, i32);
+        fn foo() {
+            let p = Point;
+        }
+    "#,
+            r#"
+        struct Unit;
+        fn foo() {
+            let u = Unit();
+        }
+    "#,
+        ],
+        &[
+            Ok(()),
+            Err("missing field `y` in initializer of `Point`".into()),
+            Err("field `x` specified more than once".into()),
+            Err("struct `Point` has no field named `z`".into()),
+            Err("mismatched types in struct expr: expected LitNum(i32), found Bool".into()),
+            Err("no field `z` on struct".into()),
+            Err("lhs is not mutable".into()),
+            Err("lhs is not mutable".into()),
+            Err("cannot find struct `Point`".into()),
+            Err("recursive type `List` has infinite size".into()),
+            Err("cannot find type `Foo`".into()),
+            Ok(()),
+            Ok(()),
+            Err("this struct takes 2 fields but 1 fields were supplied".into()),
+            Err("expected value, found tuple struct `Point`".into()),
+            Err("expected function, found unit struct `Unit`".into()),
+        ],
+    );
+}
 
-}
\ No newline at end of file
+#[test]
+fn array_test() {
+    file_validate(
+        &[
+            r#"
+        fn foo(a: &mut [[i64; 2]; 3], i: usize) -> i64 {
+            let mut b = [[0i64; 2]; 3];
+            b[i] = [1, 2];
+            a[0][i] += b[2][1];
+            let e: [u8; 0] = [];
+            a[i][1]
+        }
+    "#,
+            r#"
+        fn foo() {
+            let a = [1, true];
+        }
+    "#,
+            r#"
+        fn foo(i: i32) -> i32 {
+            let a = [1, 2];
+            a[i]
+        }
+    "#,
+            r#"
+        fn foo() -> i32 {
+            let a = [1, 2];
+            a[2]
+        }
+    "#,
+            r#"
+        fn foo(a: i32) -> i32 {
+            a[0]
+        }
+    "#,
+            r#"
+        fn foo(n: usize) {
+            let a = [0; n];
+        }
+    "#,
+            r#"
+        fn foo(a: [i32; 2]) {
+            a[0] = 1;
+        }
+    "#,
+            r#"
+        fn foo(x: i64) -> i64 {
+            let a = [1, 2];
+            x + a[0]
+        }
+    "#,
+            r#"
+        fn foo(x: i32, p: *mut i32) -> i32 {
+            let a = [1, 2];
+            let b = [p];
+            let c: *const i32 = b[0];
+            x + a[0]
+        }
+    "#,
+            r#"
+        fn foo() -> i64 {
+            let a = [1, 2];
+            let x = a[0];
+            x
+        }
+    "#,
+            r#"
+        fn foo(x: i64, y: u8) -> u8 {
+            let a = [1, 2];
+            let b = x + a[0];
+            y + a[1]
+        }
+    "#,
+        ],
+        &[
+            Ok(()),
+            Err("mismatched types in array expr: expected Bool, found LitNum(#i)".into()),
+            Err("arrays are indexed by `usize`: expected LitNum(usize), found LitNum(i32)".into()),
+            Err("index out of bounds: the length is 2 but the index is 2".into()),
+            Err("cannot index into a value of type `LitNum(i32)`".into()),
+            Err("attempt to use a non-constant value `n` in a constant".into()),
+            Err("lhs is not mutable".into()),
+            Ok(()),
+            Ok(()),
+            Ok(()),
+            Err("invalid operand type `LitNum(u8)` and `LitNum(i64)` for `+`".into()),
+        ],
+    );
+}
+
+#[test]
+fn tuple_test() {
+    file_validate(
+        &[
+            r#"
+        struct Pair(u8, bool);
+        fn foo(p: &mut Pair, t: (i64, (bool, u8))) -> (i64, u8) {
+            let mut a: (i64, (bool, u8)) = (1, (true, 2));
+            a.1 = t.1;
+            a.0 += 3;
+            p.0 = (a.1).1;
+            let (x, .., y) = (1u8, 'c', 3i64);
+            let u: () = ();
+            (t.0 + a.0, p.0)
+        }
+    "#,
+            r#"
+        fn foo(t: (i32, bool)) -> bool {
+            t.2
+        }
+    "#,
+            r#"
+        fn foo(t: (i32, bool)) {
+            t.0 = 1;
+        }
+    "#,
+            r#"
+        fn foo() -> (i32, bool) {
+            (true, 1)
+        }
+    "#,
+            r#"
+        fn foo(x: i64) -> i64 {
+            let t = (1, 2);
+            x + t.0
+        }
+    "#,
+            r#"
+        fn foo() {
+            let (a, b) = (1, 2, 3);
+        }
+    "#,
+            r#"
+        fn foo(x: &mut i32) -> i32 {
+            let t = (1, (2, 3));
+            let u: (i32, i32) = t.1;
+            let p = (x, 0);
+            let q: &i32 = p.0;
+            t.0 + *q
+        }
+    "#,
+            r#"
+        fn foo() {
+            let t = ((1, 2), 3);
+            let u: (i64, i64) = t.0;
+        }
+    "#,
+            r#"
+        fn foo(p: &(i64, bool)) -> u8 {
+            let t = &mut (1, (2, 3));
+            (*t).1 = (4u8, 5);
+            t.0 = p.0;
+            (t.1).0 + (t.1).1
+        }
+    "#,
+            r#"
+        fn foo() -> bool {
+            let mut t = (1, 2);
+            t.0 = 3i64;
+            let u: (i64, u8) = t;
+            u.1 == 2u8
+        }
+    "#,
+            r#"
+        fn foo(x: u8) -> i64 {
+            let t = (1, 2);
+            let s = t;
+            s.0 + x
+        }
+    "#,
+        ],
+        &[
+            Ok(()),
+            Err("no field `2` on type `Tuple([LitNum(i32), Bool])`".into()),
+            Err("lhs is not mutable".into()),
+            Err("invalid return type: excepted `Tuple([LitNum(i32), Bool])`, found `Tuple([Bool, LitNum(#i)])`".into()),
+            Ok(()),
+            Err("mismatched types in pattern: expected a tuple with 3 elements, found one with 2 elements".into()),
+            Ok(()),
+            Ok(()),
+            Ok(()),
+            Ok(()),
+            Err("invalid return type: excepted `LitNum(i64)`, found `LitNum(u8)`".into()),
+        ],
+    );
+}
+
+#[test]
+fn enum_test() {
+    file_validate(
+        &[
+            r#"
+        #[repr(u8)]
+        enum Color {
+            Red = 1,
+            Green,
+        }
+        enum Shape {
+            Empty,
+            Circle(i32),
+            Rect { w: i32, h: i32 },
+        }
+        fn foo(s: Shape, c: Color) -> i32 {
+            let t = Shape::Rect { w: 1, h: 2 };
+            let n = match t {
+                Shape::Rect { w: 0, .. } | Shape::Empty => 0,
+                Shape::Rect { w, h } => w * h,
+                Shape::Circle(r) => r,
+            };
+            match s {
+                Shape::Circle(r) => r + n,
+                _ => c as i32,
+            }
+        }
+    "#,
+            r#"
+        enum E {
+            A = 1,
+            B = 0,
+            C,
+        }
+    "#,
+            r#"
+        #[repr(i8)]
+        enum E {
+            A = 127,
+            B,
+        }
+    "#,
+            r#"
+        enum E {
+            A(i32) = 1,
+        }
+    "#,
+            r#"
+        enum E {
+            A(i32),
+        }
+        fn foo() -> E {
+            E::A(1, 2)
+        }
+    "#,
+            r#"
+        enum E {
+            A,
+        }
+        fn foo() -> E {
+            E::B
+        }
+    "#,
+            r#"
+        enum E {
+            A(i32),
+        }
+        fn foo(e: E) -> i32 {
+            e as i32
+        }
+    "#,
+            r#"
+        enum E {
+            A = 300,
+        }
+        fn foo(e: E) -> u8 {
+            e as u8
+        }
+    "#,
+            r#"
+        enum E {
+            A,
+            B(bool),
+            C { x: i32 },
+        }
+        fn foo(e: E) -> i32 {
+            match e {
+                E::A => 0,
+                E::B(true) => 1,
+            }
+        }
+    "#,
+            r#"
+        enum List {
+            Nil,
+            Cons(i32, List),
+        }
+    "#,
+            r#"
+        enum E {
+            A(bool),
+            B { x: u8, y: bool },
+        }
+        fn foo(e: &E, t: (bool, E)) -> i32 {
+            let a = match e {
+                E::A(true) => 1,
+                E::A(false) => 2,
+                E::B { x: 0..=127, .. } => 3,
+                E::B { x: 128..=255, y: true } => 4,
+                E::B { y: false, .. } => 5,
+            };
+            match t {
+                (true, E::A(_)) | (false, E::A(true)) => a,
+                (_, E::B { .. }) => 6,
+                (false, E::A(false)) => 7,
+            }
+        }
+    "#,
+            r#"
+        enum E {
+            A(bool),
+            B { x: u8, y: bool },
+        }
+        fn foo(e: E) -> i32 {
+            match e {
+                E::A(true) | E::A(false) => 1,
+                E::B { x: 0..=127, .. } => 3,
+                E::B { x: 128..=255, y: true } => 4,
+            }
+        }
+    "#,
+            r#"
+        fn foo(t: (bool, u8)) -> i32 {
+            match t {
+                (true, _) => 1,
+                (false, 0..=9) => 2,
+                (_, 9) => 3,
+                _ => 4,
+            }
+        }
+    "#,
+        ],
+        &[
+            Ok(()),
+            Err("discriminant value `1` assigned more than once".into()),
+            Err("discriminant value `128` is out of range for `i8`".into()),
+            Err("`#[repr(inttype)]` must be specified for enum `E` with fields and explicit discriminants".into()),
+            Err("this enum variant takes 1 fields but 2 fields were supplied".into()),
+            Err("no variant named `B` found for enum `E`".into()),
+            Err("non-primitive cast: `E` as `i32`".into()),
+            Err("casting `E` as `u8` truncates the discriminants, which is not supported yet".into()),
+            Err("non-exhaustive patterns: `E::B(..)`, `E::C { .. }` not covered".into()),
+            Err("recursive type `List` has infinite size".into()),
+            Ok(()),
+            Err("non-exhaustive patterns: `E::B { .. }` not covered".into()),
+            Err("unreachable pattern: `(_, 9)`".into()),
+        ],
+    );
+}
+
+#[test]
+fn pointer_test() {
+    file_validate(
+        &[
+            r#"
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+        fn get(p: &Point) -> &i32 {
+            &p.y
+        }
+        fn inc(x: &mut i32) {
+            *x += 1;
+        }
+        fn foo(p: &mut Point) -> i32 {
+            let mut a = 1;
+            inc(&mut a);
+            let r: &i32 = &mut a;
+            let raw: *const i32 = r;
+            let m = p as *mut Point;
+            let addr = raw as usize;
+            unsafe {
+                (*p).x = *get(p) + *(addr as *const i32);
+                *raw.add(1) + *raw.sub(1) + *raw.offset(-1)
+            }
+        }
+    "#,
+            r#"
+        fn foo(x: &i32) {
+            *x = 1;
+        }
+    "#,
+            r#"
+        fn foo(x: i32) -> i32 {
+            *x
+        }
+    "#,
+            r#"
+        fn foo(x: &i32) -> &mut i32 {
+            x
+        }
+    "#,
+            r#"
+        fn foo(x: &i32) -> &i32 {
+            x.add(1)
+        }
+    "#,
+            r#"
+        fn foo(x: *const i32) -> *const i32 {
+            x.add(1, 2)
+        }
+    "#,
+            r#"
+        fn foo(x: *const i32) -> *const i32 {
+            x.offset(1u8)
+        }
+    "#,
+            r#"
+        fn foo(x: *const i32) -> &i32 {
+            x as &i32
+        }
+    "#,
+            r#"
+        fn foo() -> i32 {
+            let x = 1;
+            let p: *const i32 = &x;
+            *p
+        }
+    "#,
+        ],
+        &[
+            Ok(()),
+            Err("lhs is not mutable".into()),
+            Err("type `LitNum(i32)` can not be dereferenced".into()),
+            Err("invalid return type: excepted `Ptr { kind: MutRef, type_info: LitNum(i32) }`, found `Ptr { kind: Ref, type_info: LitNum(i32) }`".into()),
+            Err("no method named `add` found for `Ptr { kind: Ref, type_info: LitNum(i32) }`".into()),
+            Err("This function takes 1 parameters but 2 parameters was supplied".into()),
+            Err("invalid type for call expr: expected LitNum(isize), found LitNum(u8)".into()),
+            Err("casting `*const i32` as `&i32` is invalid".into()),
+            Err("dereference of raw pointer is unsafe and requires unsafe block".into()),
+        ],
+    );
+}
+
+#[test]
+fn cast_test() {
+    file_validate(
+        &[
+            r#"
+        fn foo(a: i64, b: f32, c: char, d: bool, e: u8, p: *const u8) -> usize {
+            let x = a as u8 as i16 as f64 as f32 as u32;
+            let y = b as u8 as char as u32 + c as u32 + d as u32 + x;
+            let q = (p as usize + 1) as *const i32;
+            let z = 300 as u16;
+            (e as char as u8 + z as u8 + y as u8) as usize + q as usize
+        }
+    "#,
+            r#"
+        fn foo() -> u8 {
+            let x = 300;
+            let y = x as u8;
+            let z: i32 = x;
+            y
+        }
+    "#,
+            r#"
+        fn foo(x: u32) -> char {
+            x as char
+        }
+    "#,
+            r#"
+        fn foo(x: bool) -> f64 {
+            x as f64
+        }
+    "#,
+            r#"
+        fn foo(x: f64) -> *const u8 {
+            x as *const u8
+        }
+    "#,
+        ],
+        &[
+            Ok(()),
+            Ok(()),
+            Err("only `u8` can be cast as `char`, not `u32`".into()),
+            Err("casting `bool` as `f64` is invalid".into()),
+            Err("casting `f64` as `*const u8` is invalid".into()),
+        ],
+    );
+}
+
+#[test]
+fn impl_test() {
+    file_validate(
+        &[
+            r#"
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+        impl Point {
+            fn new(x: i32, y: i32) -> Self {
+                Self { x, y }
+            }
+            fn sum(&self) -> i32 {
+                self.x + self.y
+            }
+            fn scale(&mut self, k: i32) {
+                self.x *= k;
+            }
+            fn moved(self) -> Point {
+                self
+            }
+            fn origin() -> Self {
+                Self::new(0, 0)
+            }
+        }
+        fn foo() -> i32 {
+            let mut p = Point::new(1, 2);
+            p.scale(2);
+            let r = &mut p;
+            r.scale(3);
+            let q = p.moved();
+            let s = &q;
+            s.sum() + Point::origin().sum()
+        }
+    "#,
+            r#"
+        impl Point {}
+    "#,
+            r#"
+        impl i32 {}
+    "#,
+            r#"
+        struct Point { x: i32 }
+        impl Point {
+            fn new() -> Self { Point { x: 0 } }
+        }
+        fn foo() {
+            let p = Point::new();
+            p.new();
+        }
+    "#,
+            r#"
+        struct Point { x: i32 }
+        fn foo() {
+            let p = Point { x: 0 };
+            p.sum();
+        }
+    "#,
+            r#"
+        struct Point { x: i32 }
+        fn foo() {
+            Point::new();
+        }
+    "#,
+            r#"
+        fn foo() {
+            Point::new();
+        }
+    "#,
+            r#"
+        struct Point { x: i32 }
+        impl Point {
+            fn set(&mut self) {}
+        }
+        fn foo() {
+            let p = Point { x: 0 };
+            p.set();
+        }
+    "#,
+            r#"
+        struct Point { x: i32 }
+        impl Point {
+            fn add(&self, n: i32) {}
+        }
+        fn foo() {
+            let p = Point { x: 0 };
+            p.add(1, 2);
+        }
+    "#,
+            r#"
+        struct Point { x: i32 }
+        impl Point {
+            fn get(&self) -> i32 { self.x }
+            fn get(&self) -> i32 { 0 }
+        }
+    "#,
+            r#"
+        struct Point { x: i32 }
+        impl Point {
+            fn new() -> Self { Point { x: 0 } }
+        }
+        impl Point {
+            fn new() -> Self { Point { x: 1 } }
+        }
+    "#,
+        ],
+        &[
+            Ok(()),
+            Err("cannot find type `Point` in this scope".into()),
+            Err("cannot define inherent `impl` for the primitive type `i32`".into()),
+            Err("`new` is an associated function, not a method".into()),
+            Err("no method named `sum` found for `Point`".into()),
+            Err("no function named `new` found for `Point`".into()),
+            Err("failed to resolve: use of undeclared type `Point`".into()),
+            Err("cannot borrow immutable place as mutable".into()),
+            Err("This function takes 1 parameters but 2 parameters was supplied".into()),
+            Err("duplicate definitions with name `get`".into()),
+            Err("duplicate definitions with name `new`".into()),
+        ],
+    );
+}
+
+#[test]
+fn trait_test() {
+    let shape = r#"
+        trait Shape {
+            fn area(&self) -> i32;
+            fn double(&self) -> i32 {
+                self.area() * 2
+            }
+        }
+        struct Square {
+            side: i32,
+        }
+    "#;
+    file_validate(
+        &[
+            &format!(
+                "{}{}",
+                shape,
+                r#"
+        impl Shape for Square {
+            fn area(&self) -> i32 {
+                self.side * self.side
+            }
+        }
+        impl Shape for i32 {
+            fn area(&self) -> i32 {
+                *self
+            }
+            fn double(&self) -> Self {
+                0
+            }
+        }
+        fn total<T: Shape, U: Shape>(a: T, b: &U) -> i32 {
+            a.area() + b.double()
+        }
+        fn id<T>(x: T) -> T {
+            x
+        }
+        fn sum_of<S: Shape>(a: S, b: &S) -> i32 {
+            total(a, b) + id(b).area() + id::<&S>(b).double()
+        }
+        fn foo() -> i32 {
+            let s = Square { side: 2 };
+            let a: u8 = id(1u8);
+            total(s, &3) + total(4, &id(Square { side: 1 })) + 5.area()
+        }
+    "#
+            ),
+            &format!("{}{}", shape, "impl Shape for Square {}"),
+            &format!(
+                "{}{}",
+                shape,
+                "impl Shape for Square { fn area(&self) -> i32 { 1 } fn side(&self) {} }"
+            ),
+            &format!(
+                "{}{}",
+                shape,
+                "impl Shape for Square { fn area(self) -> i32 { 1 } }"
+            ),
+            "impl Shape for i32 {}",
+            "trait A {} impl A for B {}",
+            "trait A { fn f(); fn f(); }",
+            "fn f<T: Shape>(x: T) {}",
+            &format!(
+                "{}{}",
+                shape,
+                "fn f<T: Shape>(x: T) -> i32 { x.area() } fn g() -> i32 { f(1) }"
+            ),
+            "fn f<T>() {} fn g() { f(); }",
+            "fn f<T>(a: T, b: T) {} fn g() { f(1u8, true); }",
+            "fn f<T>(a: T) {} fn g() { f(1, 2); }",
+            &format!(
+                "{}{}",
+                shape,
+                "trait S { fn s(&self); } fn f<T: S>(x: &T) -> i32 { x.s(); x.area() }"
+            ),
+            "fn f<T>(x: T) -> T { x + 1 }",
+        ],
+        &[
+            Ok(()),
+            Err("not all trait items implemented, missing: `area`".into()),
+            Err("method `side` is not a member of trait `Shape`".into()),
+            Err("method `area` has an incompatible type for trait `Shape`".into()),
+            Err("cannot find trait `Shape` in this scope".into()),
+            Err("cannot find type `B` in this scope".into()),
+            Err("the name `f` is defined multiple times in trait `A`".into()),
+            Err("cannot find trait `Shape` in this scope".into()),
+            Err("the trait bound `i32: Shape` is not satisfied".into()),
+            Err("type annotations needed: cannot infer type for type parameter `T`".into()),
+            Err("mismatched types for type parameter `T`: expected `u8`, found `bool`".into()),
+            Err("This function takes 1 parameters but 2 parameters was supplied".into()),
+            Err("no method named `area` found for `T`".into()),
+            Err("binary operation `+` cannot be applied to type `T`".into()),
+        ],
+    );
+}
+
+#[test]
+fn generic_struct_test() {
+    file_validate(
+        &[
+            r#"
+        trait Tr {}
+        impl Tr for u8 {}
+        struct Pair<T> {
+            a: T,
+            b: T,
+        }
+        struct Bounded<T: Tr> {
+            t: T,
+        }
+        struct Node<T> {
+            value: T,
+            next: *const Node<T>,
+        }
+        fn make<T>(a: T, b: T) -> Pair<T> {
+            Pair { a: a, b: b }
+        }
+        fn first<T>(p: Pair<T>) -> T {
+            p.a
+        }
+        fn foo(p: Pair<Pair<bool>>, n: *const Node<Bounded<u8>>) -> i32 {
+            let q: Pair<i32> = Pair::<i32> { a: 1, b: 2 };
+            let Pair { a, b } = make(3, 4);
+            let n: *const Node<Bounded<u8>> = &Node { value: Bounded { t: 5u8 }, next: n };
+            first(q) + first::<i32>(Pair { a: a, ..q }) + b
+        }
+    "#,
+            "struct P<T> { a: i32 }",
+            "struct P<T> { a: T } fn f(p: P) {}",
+            "struct P<T> { a: T } fn f(p: P<i32, u8>) {}",
+            "fn f(p: Q<i32>) {}",
+            "struct S { a: i32 } fn f(p: S<i32>) {}",
+            "trait A {} struct P<T: A> { a: T } fn f() { let p = P { a: 1 }; }",
+            "struct P<T> { a: T, b: T } fn f() { let p = P { a: 1u8, b: true }; }",
+            "struct L<T> { a: T, next: L<T> } fn f(l: &L<i32>) {}",
+            "fn id<T>(x: T) -> T { x } fn f() { id::<i32, u8>(1); }",
+            "fn g() {} fn f() { g::<i32>(); }",
+        ],
+        &[
+            Ok(()),
+            Err("parameter `T` is never used".into()),
+            Err("missing generics for struct `P`".into()),
+            Err("struct `P` takes 1 generic arguments but 2 were supplied".into()),
+            Err("cannot find type `Q` in this scope".into()),
+            Err("type `S` takes 0 generic arguments but 1 were supplied".into()),
+            Err("the trait bound `i32: A` is not satisfied".into()),
+            Err("mismatched types for type parameter `T`: expected `u8`, found `bool`".into()),
+            Err("recursive type `L<i32>` has infinite size".into()),
+            Err("function takes 1 generic arguments but 2 were supplied".into()),
+            Err("`g` takes 0 generic arguments but 1 were supplied".into()),
+        ],
+    );
+}
+
+#[test]
+fn generic_impl_test() {
+    file_validate(
+        &[
+            r#"
+        trait Tr {
+            fn get(&self) -> i32;
+        }
+        impl Tr for u8 {
+            fn get(&self) -> i32 {
+                1
+            }
+        }
+        struct Pair<T> {
+            a: T,
+            b: T,
+        }
+        impl<T> Pair<T> {
+            fn make(a: T, b: T) -> Self {
+                Pair { a: a, b: b }
+            }
+            fn first(&self) -> T {
+                self.a
+            }
+            fn swap(&self) -> Pair<T> {
+                Self::make(self.b, self.a)
+            }
+        }
+        impl<T: Tr> Pair<T> {
+            fn sum(&self) -> i32 {
+                self.a.get() + self.swap().first().get()
+            }
+        }
+        impl Pair<bool> {
+            fn both(&self) -> bool {
+                self.a && self.b
+            }
+        }
+        fn foo(p: Pair<u8>, q: Pair<bool>) -> i32 {
+            let r: Pair<i32> = Pair { a: 1, b: 2 };
+            if q.swap().both() {
+                r.first() + p.sum()
+            } else {
+                0
+            }
+        }
+    "#,
+            "struct P<T> { a: T } impl<T> P<T> { fn f(&self) -> i32 { self.a + 1 } }",
+            "struct P<T> { a: T } impl<T> P<T> { fn f(&self) {} } impl P<i32> { fn f(&self) {} }",
+            "struct P<T> { a: T } impl<T> P<T> { fn f(&self) {} } impl<U> P<U> { fn f(&self) {} }",
+            "struct P<T> { a: T } impl<T, U> P<T> {}",
+            "impl<T> P<T> {}",
+            "struct P<T> { a: T } impl P {}",
+            "trait A { fn f(&self); } struct P<T> { a: T } impl<T> A for P<T> {}",
+            "trait A {} struct P<T> { a: T } impl<T: A> P<T> { fn f(&self) {} } \
+             fn g(p: P<i32>) { p.f(); }",
+        ],
+        &[
+            Ok(()),
+            Err("binary operation `+` cannot be applied to type `T`".into()),
+            Err("duplicate definitions with name `f`".into()),
+            Err("duplicate definitions with name `f`".into()),
+            Err(
+                "the type parameter `U` is not constrained by the impl trait, self type, \
+                 or predicates"
+                    .into(),
+            ),
+            Err("cannot find type `P` in this scope".into()),
+            Err("missing generics for struct `P`".into()),
+            Err("not all trait items implemented, missing: `f`".into()),
+            Err("no method named `f` found for `P<i32>`".into()),
+        ],
+    );
+}
+
+#[test]
+fn type_inference_test() {
+    file_validate(
+        &[
+            r#"
+        fn id(x: u64) -> u64 {
+            x
+        }
+        fn foo(c: bool) -> u8 {
+            let x;
+            if c {
+                x = 2;
+            } else {
+                x = 1u8;
+            }
+            let y = if c { 3 } else { 4u64 };
+            let z = match y {
+                0 => 5,
+                _ => id(y),
+            };
+            let w = loop {
+                if c {
+                    break 6;
+                }
+                break z;
+            };
+            x
+        }
+    "#,
+            r#"
+        fn foo(c: bool) {
+            let x;
+            if c {
+                x = 1u8;
+            } else {
+                x = true;
+            }
+        }
+    "#,
+            r#"
+        fn foo() {
+            let x;
+            x = 1;
+            let y: f64 = x;
+        }
+    "#,
+            r#"
+        fn foo() {
+            let x = 2;
+            let y: u8 = x;
+            let z: i64 = x;
+        }
+    "#,
+            r#"
+        fn foo(c: bool) -> i32 {
+            let x = 1;
+            if c { x } else { true }
+        }
+    "#,
+            r#"
+        fn foo() {
+            let x;
+            let y;
+            x = y;
+        }
+    "#,
+        ],
+        &[
+            Ok(()),
+            Err("mismatched types: expected `u8` (line 5), found `bool` (line 7)".into()),
+            Err("mismatched types: expected `f64` (line 5), found `{integer}` (line 4)".into()),
+            Err("mismatched types: expected `i64` (line 5), found `u8` (line 4)".into()),
+            Err("different type of if block: `LitNum(#i)`, `Bool`".into()),
+            Err("type annotations needed: cannot infer the type at line 5".into()),
+        ],
+    );
+}
+
+#[test]
+fn const_static_test() {
+    file_validate(
+        &[
+            r#"
+        const LEN: usize = HALF * 2 + 1;
+        const HALF: usize = 1 << 2;
+        static mut TABLE: [i32; 9] = [0; LEN];
+        static GREETING: [u8; 2] = [104, 105];
+        fn foo() -> i32 {
+            let a = [1u8; HALF];
+            unsafe {
+                TABLE[0] = GREETING[1] as i32;
+                TABLE[LEN - 1]
+            }
+        }
+    "#,
+            r#"
+        static mut COUNT: u32 = 0;
+        fn foo() {
+            COUNT += 1;
+        }
+    "#,
+            r#"
+        static COUNT: u32 = 0;
+        fn foo() {
+            unsafe {
+                COUNT = 1;
+            }
+        }
+    "#,
+            r#"
+        const A: i32 = B;
+        const B: i32 = A + 1;
+    "#,
+            r#"
+        const A: u8 = 1 + 2i32;
+    "#,
+            r#"
+        const A: u8 = 200 + 100;
+    "#,
+            r#"
+        const A: i32 = 1 / 0;
+    "#,
+            r#"
+        const A: u8 = 5;
+        const B: u8 = 7 % (A - 5);
+    "#,
+            r#"
+        fn foo() {
+            const A: i32 = 1;
+        }
+    "#,
+            r#"
+        static A: i32 = 1;
+        const B: i32 = A;
+    "#,
+            r#"
+        const A: i32 = 1;
+        const A: i32 = 2;
+    "#,
+            r#"
+        const A: usize = 4294967295 + 1;
+    "#,
+            r#"
+        const A: isize = 1 << 31;
+        const B: isize = 1 << 32;
+    "#,
+            r#"
+        const A: usize = !0;
+        const B: usize = A as u64 as usize;
+        const C: usize = A - 4294967293;
+        static D: [u8; 2] = [0; C];
+    "#,
+        ],
+        &[
+            Ok(()),
+            Err("use of mutable static is unsafe and requires unsafe block".into()),
+            Err("lhs is not mutable".into()),
+            Err("cycle detected when evaluating constant `A`".into()),
+            Err("mismatched types in `A`: expected LitNum(u8), found LitNum(i32)".into()),
+            Err("add overflow".into()),
+            Err("attempt to divide by zero".into()),
+            Err("attempt to divide by zero".into()),
+            Err("`const` and `static` items are only supported in the file scope".into()),
+            Err("attempt to use a non-constant value `A` in a constant".into()),
+            Err("the name `A` is defined multiple times".into()),
+            Err("add overflow".into()),
+            Err("shl overflow".into()),
+            Ok(()),
+        ],
+    );
+}
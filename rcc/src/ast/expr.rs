@@ -1,8 +1,9 @@
 use crate::analyser::scope::Scope;
 use crate::analyser::sym_resolver::TypeInfo;
 use crate::ast::expr::Expr::Path;
+use crate::ast::pattern::Pattern;
 use crate::ast::stmt::Stmt;
-use crate::ast::types::TypeLitNum;
+use crate::ast::types::{TypeAnnotation, TypeLitNum};
 use crate::ast::{FromToken, TokenStart};
 use crate::from_token;
 use crate::lexer::token::Token;
@@ -59,17 +60,19 @@ pub enum Expr {
     Tuple(TupleExpr),
     TupleIndex(TupleIndexExpr),
     Struct(StructExpr),
-    EnumVariant,
+    EnumVariant(EnumVariantExpr),
     Call(CallExpr),
-    MethodCall,
+    MethodCall(MethodCallExpr),
     FieldAccess(FieldAccessExpr),
     While(WhileExpr),
     Loop(LoopExpr),
-    For,
+    For(ForExpr),
     If(IfExpr),
-    Match,
+    Match(MatchExpr),
     Return(ReturnExpr),
     Break(BreakExpr),
+    Continue(ContinueExpr),
+    Cast(CastExpr),
 }
 
 impl Expr {
@@ -77,18 +80,19 @@ impl Expr {
         matches!(
             self,
             Self::Block(_)
-                | Self::Struct(_)
                 | Self::While(_)
                 | Self::Loop(_)
                 | Self::If(_)
-                | Self::Match
-                | Self::For
+                | Self::Match(_)
+                | Self::For(_)
         )
     }
     pub fn is_with_block_token_start(tk: &Token) -> bool {
         matches!(
             tk,
             Token::LeftCurlyBraces
+                | Token::Unsafe
+                | Token::Lifetime(_)
                 | Token::While
                 | Token::Loop
                 | Token::For
@@ -118,18 +122,24 @@ impl ExprVisit for Expr {
             // Self::Range(e) => e.ret_type(),
             Self::BinOp(e) => e.type_info(),
             Self::Grouped(e) => e.type_info(),
-            // Self::Array(e) => e.ret_type(),
-            // Self::ArrayIndex(e) => e.ret_type(),
-            // Self::Tuple(e) => e.ret_type(),
-            // Self::TupleIndex(e) => e.ret_type(),
-            // Self::Struct(e) => e.ret_type(),
+            Self::Array(e) => e.type_info(),
+            Self::ArrayIndex(e) => e.type_info(),
+            Self::Tuple(e) => e.type_info(),
+            Self::TupleIndex(e) => e.type_info(),
+            Self::Struct(e) => e.type_info(),
+            Self::EnumVariant(e) => e.type_info(),
             Self::Call(e) => e.type_info(),
-            // Self::FieldAccess(e) => e.ret_type(),
+            Self::MethodCall(e) => e.type_info(),
+            Self::FieldAccess(e) => e.type_info(),
             Self::While(e) => e.type_info(),
             Self::Loop(e) => e.type_info(),
+            Self::For(e) => e.type_info(),
             Self::If(e) => e.type_info(),
+            Self::Match(e) => e.type_info(),
             Self::Return(e) => e.type_info(),
             Self::Break(e) => e.type_info(),
+            Self::Continue(e) => e.type_info(),
+            Self::Cast(e) => e.type_info(),
             _ => unimplemented!("{:?}", self),
         }
     }
@@ -145,12 +155,24 @@ impl ExprVisit for Expr {
             Self::Assign(a) => a.kind(),
             Self::BinOp(b) => b.kind(),
             Self::Grouped(e) => e.kind(),
+            Self::Array(a) => a.kind(),
+            Self::ArrayIndex(a) => a.kind(),
+            Self::Tuple(t) => t.kind(),
+            Self::TupleIndex(t) => t.kind(),
+            Self::Struct(s) => s.kind(),
+            Self::EnumVariant(e) => e.kind(),
             Self::Call(c) => c.kind(),
+            Self::MethodCall(c) => c.kind(),
+            Self::FieldAccess(f) => f.kind(),
             Self::While(w) => w.kind(),
             Self::Loop(l) => l.kind(),
+            Self::For(f) => f.kind(),
             Self::If(i) => i.kind(),
+            Self::Match(m) => m.kind(),
             Self::Return(r) => r.kind(),
             Self::Break(b) => b.kind(),
+            Self::Continue(c) => c.kind(),
+            Self::Cast(c) => c.kind(),
             _ => unimplemented!("{:?}", self),
         }
     }
@@ -167,6 +189,11 @@ impl TypeInfoSetter for Expr {
             }
             Self::Unary(u) => u.set_type_info(type_info),
             Self::BinOp(b) => b.set_type_info(type_info),
+            Self::Array(a) => a.set_type_info(type_info),
+            Self::ArrayIndex(a) => a.set_type_info(type_info),
+            Self::Tuple(t) => t.set_type_info(type_info),
+            Self::TupleIndex(t) => t.set_type_info(type_info),
+            Self::Match(m) => m.set_type_info(type_info),
             e => unimplemented!("set type_info on {:?}", e),
         }
     }
@@ -177,6 +204,8 @@ impl TypeInfoSetter for Expr {
                 l.set_type_info_ref(type_info);
             }
             Self::Unary(u) => u.set_type_info_ref(type_info),
+            Self::ArrayIndex(a) => a.set_type_info_ref(type_info),
+            Self::TupleIndex(t) => t.set_type_info_ref(type_info),
             e => unimplemented!("set type_info on {:?}", e),
         }
     }
@@ -188,7 +217,7 @@ pub enum LhsExpr {
     ArrayIndex(ArrayIndexExpr),
     TupleIndex(TupleIndexExpr),
     FieldAccess(FieldAccessExpr),
-    Deref(Box<Expr>),
+    Deref(UnAryExpr),
 }
 
 impl LhsExpr {
@@ -197,7 +226,7 @@ impl LhsExpr {
             Expr::Path(p) => Ok(LhsExpr::Path(p)),
             Expr::Unary(u) => {
                 if u.op == UnOp::Deref {
-                    Ok(LhsExpr::Deref(u.expr))
+                    Ok(LhsExpr::Deref(u))
                 } else {
                     Err("invalid lhs expr".into())
                 }
@@ -218,7 +247,7 @@ impl LhsExpr {
             Self::ArrayIndex(a) => unimplemented!("set array index type info"),
             Self::TupleIndex(t) => unimplemented!("set tuple index type info"),
             Self::FieldAccess(f) => unimplemented!("set tuple field type info"),
-            Self::Deref(e) => unimplemented!("set tuple deref type info"),
+            Self::Deref(e) => e.set_type_info(type_info),
         }
     }
 
@@ -234,14 +263,20 @@ impl ExprVisit for LhsExpr {
     fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
         match self {
             LhsExpr::Path(expr) => expr.type_info(),
-            _ => todo!(),
+            LhsExpr::ArrayIndex(expr) => expr.type_info(),
+            LhsExpr::TupleIndex(expr) => expr.type_info(),
+            LhsExpr::FieldAccess(expr) => expr.type_info(),
+            LhsExpr::Deref(expr) => expr.type_info(),
         }
     }
 
     fn kind(&self) -> ExprKind {
         match self {
             LhsExpr::Path(expr) => expr.kind(),
-            _ => todo!(),
+            LhsExpr::ArrayIndex(expr) => expr.kind(),
+            LhsExpr::TupleIndex(expr) => expr.kind(),
+            LhsExpr::FieldAccess(expr) => expr.kind(),
+            LhsExpr::Deref(expr) => expr.kind(),
         }
     }
 }
@@ -266,6 +301,15 @@ impl<V> ConstantExpr<V> {
             const_value: None,
         }
     }
+
+    /// The value is known after the expr is evaluated at compile time
+    pub fn value(&self) -> Option<&V> {
+        self.const_value.as_ref()
+    }
+
+    pub fn set_value(&mut self, value: V) {
+        self.const_value = Some(value);
+    }
 }
 
 impl TokenStart for Expr {
@@ -273,12 +317,15 @@ impl TokenStart for Expr {
         matches!(
             tk,
             Token::Identifier(_)
+                | Token::SelfValue
+                | Token::SelfType
                 | Token::Literal { .. }
                 | Token::LitString(_)
                 | Token::True
                 | Token::False
                 | Token::DotDot
                 | Token::LeftCurlyBraces
+                | Token::Unsafe
                 | Token::LeftParen
                 | Token::LeftSquareBrackets
                 | Token::For
@@ -287,7 +334,9 @@ impl TokenStart for Expr {
                 | Token::If
                 | Token::Match
                 | Token::Break
+                | Token::Continue
                 | Token::Return
+                | Token::Lifetime(_)
         ) || UnAryExpr::is_token_start(tk)
             || RangeExpr::is_token_start(tk)
     }
@@ -297,6 +346,13 @@ pub struct BlockExpr {
     pub stmts: Vec<Stmt>,
     pub last_expr: Option<Box<Expr>>,
     pub scope: Scope,
+    /// Source line of `{`, 0 if the block is not parsed from source code
+    pub line: u32,
+    /// Source line of each statement, followed by the line of `last_expr`.
+    /// Empty if the block is not parsed from source code.
+    pub lines: Vec<u32>,
+    /// `unsafe { .. }`, mutable statics are only accessed in unsafe blocks
+    pub is_unsafe: bool,
     type_info: Rc<RefCell<TypeInfo>>,
 }
 
@@ -306,10 +362,18 @@ impl BlockExpr {
             stmts: vec![],
             last_expr: None,
             scope: Scope::new(scope_id),
+            line: 0,
+            lines: vec![],
+            is_unsafe: false,
             type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
         }
     }
 
+    pub fn unsafe_block(mut self) -> Self {
+        self.is_unsafe = true;
+        self
+    }
+
     pub fn expr_without_block(mut self, expr: Expr) -> Self {
         debug_assert!(!expr.with_block());
         self.last_expr = Some(Box::new(expr));
@@ -357,6 +421,9 @@ impl TypeInfoSetter for BlockExpr {
 
 impl Debug for BlockExpr {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_unsafe {
+            write!(f, "unsafe ")?;
+        }
         match &self.last_expr {
             Some(expr) => write!(f, "{{ {:?} {:?} }}", self.stmts, expr),
             None => write!(f, "{{ {:?} }}", self.stmts),
@@ -366,7 +433,9 @@ impl Debug for BlockExpr {
 
 impl PartialEq for BlockExpr {
     fn eq(&self, other: &Self) -> bool {
-        self.stmts.eq(&other.stmts) && self.last_expr.eq(&other.last_expr)
+        self.stmts.eq(&other.stmts)
+            && self.last_expr.eq(&other.last_expr)
+            && self.is_unsafe == other.is_unsafe
     }
 }
 
@@ -376,6 +445,9 @@ impl From<Vec<Stmt>> for BlockExpr {
             stmts,
             last_expr: None,
             scope: Scope::new(0),
+            line: 0,
+            lines: vec![],
+            is_unsafe: false,
             type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
         }
     }
@@ -407,7 +479,7 @@ impl LitNumExpr {
         self
     }
 
-    pub fn get_lit_type(&mut self) -> TypeLitNum {
+    pub fn get_lit_type(&self) -> TypeLitNum {
         if let TypeInfo::LitNum(t) = self.type_info.borrow().deref() {
             return t.clone();
         }
@@ -452,6 +524,8 @@ impl From<i32> for LitNumExpr {
 #[derive(PartialEq, Debug)]
 pub struct PathExpr {
     pub segments: Vec<String>,
+    /// `f::<i32>`, the type arguments of the last segment
+    pub type_args: Vec<TypeAnnotation>,
     type_info: Rc<RefCell<TypeInfo>>,
     pub expr_kind: ExprKind,
 }
@@ -460,6 +534,7 @@ impl PathExpr {
     pub fn new() -> Self {
         PathExpr {
             segments: vec![],
+            type_args: vec![],
             type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
             expr_kind: ExprKind::Unknown,
         }
@@ -490,6 +565,7 @@ impl From<Vec<String>> for PathExpr {
     fn from(segments: Vec<String>) -> Self {
         PathExpr {
             segments,
+            type_args: vec![],
             type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
             expr_kind: ExprKind::Unknown,
         }
@@ -500,6 +576,7 @@ impl From<Vec<&str>> for PathExpr {
     fn from(segments: Vec<&str>) -> Self {
         PathExpr {
             segments: segments.iter().map(|s| s.to_string()).collect(),
+            type_args: vec![],
             type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
             expr_kind: ExprKind::Unknown,
         }
@@ -510,6 +587,7 @@ impl From<&str> for PathExpr {
     fn from(s: &str) -> Self {
         PathExpr {
             segments: s.split("::").map(|s| s.to_string()).collect(),
+            type_args: vec![],
             type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
             expr_kind: ExprKind::Unknown,
         }
@@ -555,7 +633,18 @@ impl TokenStart for UnAryExpr {
 }
 
 impl TypeInfoSetter for UnAryExpr {
+    /// The types of number literals borrowed by `&` are determined as well,
+    /// the type is kept if the borrowed expr can not be determined.
     fn set_type_info(&mut self, type_info: TypeInfo) {
+        if let (UnOp::Borrow | UnOp::BorrowMut, TypeInfo::Ptr { type_info: pointee, .. }) =
+            (&self.op, &type_info)
+        {
+            if matches!(self.expr.as_ref(), Expr::Path(_) | Expr::LitNum(_) | Expr::Unary(_))
+                && pointee.can_determine(&self.expr.type_info().borrow())
+            {
+                self.expr.set_type_info(pointee.as_ref().clone());
+            }
+        }
         self.type_info.replace(type_info);
     }
 
@@ -721,7 +810,7 @@ impl TokenStart for RangeExpr {
 }
 
 from_token! {
-    #[derive(StrEnum, Debug, PartialEq)]
+    #[derive(StrEnum, Debug, PartialEq, Clone, Copy)]
     pub enum RangeOp {
         /// Range operators
         #[strenum("..")]
@@ -911,30 +1000,70 @@ impl Precedence {
 /// GroupExpr -> `(` Expr `)`
 pub type GroupedExpr = Box<Expr>;
 
+/// `[a, b, c]`, or `[x; N]` which has one elem and the length expr
 #[derive(Debug, PartialEq)]
 pub struct ArrayExpr {
     pub elems: Vec<Expr>,
     pub len_expr: ConstantExpr<usize>,
+    type_info: Rc<RefCell<TypeInfo>>,
 }
 
 impl ArrayExpr {
     pub fn new(elems: Vec<Expr>, len_expr: ConstantExpr<usize>) -> Self {
-        ArrayExpr { elems, len_expr }
+        ArrayExpr {
+            elems,
+            len_expr,
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+        }
     }
 
     pub fn elems(elems: Vec<Expr>) -> ArrayExpr {
         let length = elems.len();
-        ArrayExpr {
-            elems,
-            len_expr: ConstantExpr::<usize>::const_value(length),
+        ArrayExpr::new(elems, ConstantExpr::<usize>::const_value(length))
+    }
+
+    /// Whether the array is `[x; N]`
+    pub fn is_repeat(&self) -> bool {
+        self.len_expr.expr.is_some()
+    }
+}
+
+impl ExprVisit for ArrayExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
+
+impl TypeInfoSetter for ArrayExpr {
+    /// The types of number literals in the elems are determined as well.
+    fn set_type_info(&mut self, type_info: TypeInfo) {
+        if let TypeInfo::Array { elem_type, .. } = &type_info {
+            for elem in self.elems.iter_mut() {
+                let is_determined = elem_type.can_determine(&elem.type_info().borrow());
+                if is_determined {
+                    elem.set_type_info(elem_type.as_ref().clone());
+                }
+            }
         }
+        self.type_info.replace(type_info);
+    }
+
+    fn set_type_info_ref(&mut self, type_info: Rc<RefCell<TypeInfo>>) {
+        self.type_info = type_info;
     }
 }
 
+/// `expr[index_expr]`
 #[derive(Debug, PartialEq)]
 pub struct ArrayIndexExpr {
     pub expr: Box<Expr>,
     pub index_expr: Box<Expr>,
+    type_info: Rc<RefCell<TypeInfo>>,
+    pub expr_kind: ExprKind,
 }
 
 impl ArrayIndexExpr {
@@ -942,20 +1071,204 @@ impl ArrayIndexExpr {
         ArrayIndexExpr {
             expr: Box::new(expr),
             index_expr: Box::new(index_expr),
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            expr_kind: ExprKind::Unknown,
         }
     }
+
+    pub fn set_type_info(&mut self, type_info: TypeInfo) {
+        self.type_info.replace(type_info);
+    }
+
+    pub fn set_type_info_ref(&mut self, type_info: Rc<RefCell<TypeInfo>>) {
+        self.type_info = type_info;
+    }
+}
+
+impl ExprVisit for ArrayIndexExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        self.expr_kind
+    }
 }
 
+/// `(a, b)`, `(a,)`, or `()` which is the unit value
 #[derive(Debug, PartialEq)]
-pub struct TupleExpr(pub Vec<Expr>);
+pub struct TupleExpr {
+    pub elems: Vec<Expr>,
+    type_info: Rc<RefCell<TypeInfo>>,
+}
+
+impl TupleExpr {
+    pub fn new(elems: Vec<Expr>) -> TupleExpr {
+        TupleExpr {
+            elems,
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+        }
+    }
+}
+
+impl ExprVisit for TupleExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
+
+impl TypeInfoSetter for TupleExpr {
+    /// The types of number literals in the elems are determined as well.
+    fn set_type_info(&mut self, type_info: TypeInfo) {
+        if let TypeInfo::Tuple(elem_types) = &type_info {
+            for (elem, elem_type) in self.elems.iter_mut().zip(elem_types.iter()) {
+                let is_determined = elem_type.can_determine(&elem.type_info().borrow());
+                if is_determined {
+                    elem.set_type_info(elem_type.clone());
+                }
+            }
+        }
+        self.type_info.replace(type_info);
+    }
+
+    fn set_type_info_ref(&mut self, type_info: Rc<RefCell<TypeInfo>>) {
+        self.type_info = type_info;
+    }
+}
 
+/// `expr.0`
 #[derive(Debug, PartialEq)]
 pub struct TupleIndexExpr {
-    // TODO
+    pub expr: Box<Expr>,
+    pub index: usize,
+    type_info: Rc<RefCell<TypeInfo>>,
+    pub expr_kind: ExprKind,
+}
+
+impl TupleIndexExpr {
+    pub fn new(expr: Expr, index: usize) -> TupleIndexExpr {
+        TupleIndexExpr {
+            expr: Box::new(expr),
+            index,
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            expr_kind: ExprKind::Unknown,
+        }
+    }
+
+    pub fn set_type_info(&mut self, type_info: TypeInfo) {
+        self.type_info.replace(type_info);
+    }
+
+    pub fn set_type_info_ref(&mut self, type_info: Rc<RefCell<TypeInfo>>) {
+        self.type_info = type_info;
+    }
+}
+
+impl ExprVisit for TupleIndexExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        self.expr_kind
+    }
+}
+
+/// `Foo { a: 1, b, ..base }`
+#[derive(Debug, PartialEq)]
+pub struct StructExpr {
+    pub path: String,
+    /// `Pair::<i32> { .. }`, the type arguments of generic structs
+    pub type_args: Vec<TypeAnnotation>,
+    pub fields: Vec<StructExprField>,
+    /// the fields which are not mentioned are moved from the base
+    pub base: Option<Box<Expr>>,
+    type_info: Rc<RefCell<TypeInfo>>,
+}
+
+impl StructExpr {
+    pub fn new(path: String, fields: Vec<StructExprField>, base: Option<Expr>) -> StructExpr {
+        StructExpr {
+            path,
+            type_args: vec![],
+            fields,
+            base: base.map(Box::new),
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+        }
+    }
+
+    pub fn type_args(mut self, type_args: Vec<TypeAnnotation>) -> StructExpr {
+        self.type_args = type_args;
+        self
+    }
+
+    pub fn set_type_info(&mut self, type_info: TypeInfo) {
+        self.type_info.replace(type_info);
+    }
+}
+
+impl ExprVisit for StructExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
+
+/// `Enum::Variant`, `Enum::Variant(a, b)` or `Enum::Variant { x: a }`,
+/// which are parsed as path, call and struct exprs and replaced when the path is resolved.
+/// The fields of tuple variants are named by their indexes.
+#[derive(Debug, PartialEq)]
+pub struct EnumVariantExpr {
+    pub enum_name: String,
+    pub variant: String,
+    pub fields: Vec<StructExprField>,
+    type_info: Rc<RefCell<TypeInfo>>,
+}
+
+impl EnumVariantExpr {
+    pub fn new(enum_name: String, variant: String, fields: Vec<StructExprField>) -> EnumVariantExpr {
+        EnumVariantExpr {
+            enum_name,
+            variant,
+            fields,
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+        }
+    }
+
+    pub fn set_type_info(&mut self, type_info: TypeInfo) {
+        self.type_info.replace(type_info);
+    }
+}
+
+impl ExprVisit for EnumVariantExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
 }
 
+/// `name: expr`, or `name` for `name: name`
 #[derive(Debug, PartialEq)]
-pub struct StructExpr;
+pub struct StructExprField {
+    pub name: String,
+    pub expr: Expr,
+}
+
+impl StructExprField {
+    pub fn new(name: String, expr: Expr) -> StructExprField {
+        StructExprField { name, expr }
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub struct ReturnExpr(pub Option<Box<Expr>>);
@@ -971,7 +1284,19 @@ impl ExprVisit for ReturnExpr {
 }
 
 #[derive(Debug, PartialEq)]
-pub struct BreakExpr(pub Option<Box<Expr>>);
+pub struct BreakExpr {
+    pub label: Option<String>,
+    pub expr: Option<Box<Expr>>,
+}
+
+impl BreakExpr {
+    pub fn new(label: Option<String>, expr: Option<Expr>) -> BreakExpr {
+        BreakExpr {
+            label,
+            expr: expr.map(Box::new),
+        }
+    }
+}
 
 impl ExprVisit for BreakExpr {
     fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
@@ -983,6 +1308,19 @@ impl ExprVisit for BreakExpr {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub struct ContinueExpr(pub Option<String>);
+
+impl ExprVisit for ContinueExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        Rc::new(RefCell::new(TypeInfo::Never))
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct CallExpr {
     pub expr: Box<Expr>,
@@ -1021,19 +1359,72 @@ impl ExprVisit for CallExpr {
     }
 }
 
+/// `receiver.method(params)`
+#[derive(Debug, PartialEq)]
+pub struct MethodCallExpr {
+    pub receiver: Box<Expr>,
+    pub method: String,
+    pub call_params: CallParams,
+    type_info: Rc<RefCell<TypeInfo>>,
+}
+
+impl MethodCallExpr {
+    pub fn new(receiver: Expr, method: String, call_params: CallParams) -> Self {
+        MethodCallExpr {
+            receiver: Box::new(receiver),
+            method,
+            call_params,
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+        }
+    }
+
+    pub fn set_type_info(&mut self, type_info: TypeInfo) {
+        self.type_info.replace(type_info);
+    }
+}
+
+impl ExprVisit for MethodCallExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
+
+/// `lhs.field`, `lhs` is dereferenced automatically if it is a pointer
 #[derive(Debug, PartialEq)]
 pub struct FieldAccessExpr {
     pub lhs: Box<Expr>,
-    pub rhs: Box<Expr>,
+    pub field: String,
+    type_info: Rc<RefCell<TypeInfo>>,
+    pub expr_kind: ExprKind,
 }
 
 impl FieldAccessExpr {
-    pub fn new(lhs: Expr, rhs: Expr) -> Self {
+    pub fn new(lhs: Expr, field: String) -> Self {
         FieldAccessExpr {
             lhs: Box::new(lhs),
-            rhs: Box::new(rhs),
+            field,
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+            expr_kind: ExprKind::Unknown,
         }
     }
+
+    pub fn set_type_info(&mut self, type_info: TypeInfo) {
+        self.type_info.replace(type_info);
+    }
+}
+
+impl ExprVisit for FieldAccessExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        self.expr_kind
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -1099,7 +1490,21 @@ impl TypeInfoSetter for IfExpr {
 }
 
 #[derive(Debug, PartialEq)]
-pub struct WhileExpr(pub Box<Expr>, pub Box<BlockExpr>);
+pub struct WhileExpr {
+    pub label: Option<String>,
+    pub cond: Box<Expr>,
+    pub block: Box<BlockExpr>,
+}
+
+impl WhileExpr {
+    pub fn new(cond: Expr, block: BlockExpr) -> WhileExpr {
+        WhileExpr {
+            label: None,
+            cond: Box::new(cond),
+            block: Box::new(block),
+        }
+    }
+}
 
 impl ExprVisit for WhileExpr {
     fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
@@ -1113,6 +1518,7 @@ impl ExprVisit for WhileExpr {
 
 #[derive(Debug, PartialEq)]
 pub struct LoopExpr {
+    pub label: Option<String>,
     pub expr: Box<BlockExpr>,
     type_info: Rc<RefCell<TypeInfo>>,
 }
@@ -1120,6 +1526,7 @@ pub struct LoopExpr {
 impl LoopExpr {
     pub fn new(expr: BlockExpr) -> LoopExpr {
         LoopExpr {
+            label: None,
             expr: Box::new(expr),
             type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
         }
@@ -1145,3 +1552,145 @@ impl TypeInfoSetter for LoopExpr {
         self.type_info = type_info;
     }
 }
+
+#[derive(Debug, PartialEq)]
+pub struct ForExpr {
+    pub label: Option<String>,
+    pub pattern: Pattern,
+    pub expr: Box<Expr>,
+    pub block: Box<BlockExpr>,
+}
+
+impl ForExpr {
+    pub fn new(pattern: Pattern, expr: Expr, block: BlockExpr) -> ForExpr {
+        ForExpr {
+            label: None,
+            pattern,
+            expr: Box::new(expr),
+            block: Box::new(block),
+        }
+    }
+}
+
+impl ExprVisit for ForExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        Rc::new(RefCell::new(TypeInfo::Unit))
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MatchExpr {
+    pub expr: Box<Expr>,
+    pub arms: Vec<MatchArm>,
+    type_info: Rc<RefCell<TypeInfo>>,
+}
+
+impl MatchExpr {
+    pub fn new(expr: Expr, arms: Vec<MatchArm>) -> MatchExpr {
+        MatchExpr {
+            expr: Box::new(expr),
+            arms,
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+        }
+    }
+}
+
+impl ExprVisit for MatchExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
+
+impl TypeInfoSetter for MatchExpr {
+    fn set_type_info(&mut self, type_info: TypeInfo) {
+        self.type_info.replace(type_info.clone());
+        for arm in self.arms.iter_mut() {
+            let tp = arm.expr.type_info();
+            let t = tp.borrow();
+            if !t.is_never() && t.deref() != &type_info {
+                std::mem::drop(t);
+                arm.expr.set_type_info(type_info.clone());
+            }
+        }
+    }
+
+    fn set_type_info_ref(&mut self, type_info: Rc<RefCell<TypeInfo>>) {
+        self.type_info = type_info;
+    }
+}
+
+/// The variable bound by the pattern is in the scope of the arm
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Box<Expr>>,
+    pub expr: Box<Expr>,
+    pub scope: Scope,
+}
+
+impl MatchArm {
+    pub fn new(scope_id: u64, pattern: Pattern, guard: Option<Expr>, expr: Expr) -> MatchArm {
+        MatchArm {
+            pattern,
+            guard: guard.map(Box::new),
+            expr: Box::new(expr),
+            scope: Scope::new(scope_id),
+        }
+    }
+}
+
+impl Debug for MatchArm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.guard {
+            Some(guard) => write!(f, "{:?} if {:?} => {:?}", self.pattern, guard, self.expr),
+            None => write!(f, "{:?} => {:?}", self.pattern, self.expr),
+        }
+    }
+}
+
+impl PartialEq for MatchArm {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern.eq(&other.pattern)
+            && self.guard.eq(&other.guard)
+            && self.expr.eq(&other.expr)
+    }
+}
+
+/// `expr as Type`
+#[derive(Debug, PartialEq)]
+pub struct CastExpr {
+    pub expr: Box<Expr>,
+    pub type_anno: TypeAnnotation,
+    type_info: Rc<RefCell<TypeInfo>>,
+}
+
+impl CastExpr {
+    pub fn new(expr: Expr, type_anno: TypeAnnotation) -> CastExpr {
+        CastExpr {
+            expr: Box::new(expr),
+            type_anno,
+            type_info: Rc::new(RefCell::new(TypeInfo::Unknown)),
+        }
+    }
+
+    pub fn set_type_info(&mut self, type_info: TypeInfo) {
+        self.type_info.replace(type_info);
+    }
+}
+
+impl ExprVisit for CastExpr {
+    fn type_info(&self) -> Rc<RefCell<TypeInfo>> {
+        self.type_info.clone()
+    }
+
+    fn kind(&self) -> ExprKind {
+        ExprKind::Value
+    }
+}
@@ -1,11 +1,18 @@
-use crate::ast::item::Item;
+use crate::ast::item::{Item, ItemFn, ItemStruct};
 use crate::analyser::scope::Scope;
 use crate::analyser::scope::BULITIN_SCOPE;
 use std::fmt::{Debug, Formatter};
+use std::pin::Pin;
 
 pub struct File {
     pub items: Vec<Item>,
     pub scope: Scope,
+    /// instances of the generic functions, pinned since they are resolved before moved
+    pub instances: Vec<Pin<Box<ItemFn>>>,
+    /// instances of the generic structs, pinned since the scopes point to their fields
+    pub struct_instances: Vec<Pin<Box<ItemStruct>>>,
+    /// number of the scopes, the scopes of the instances are numbered after them
+    pub scope_count: u64,
 }
 
 impl File {
@@ -14,7 +21,10 @@ impl File {
         file_scope.set_father_as_builtin_scope();
         File {
             items: vec![],
-            scope: file_scope
+            scope: file_scope,
+            instances: vec![],
+            struct_instances: vec![],
+            scope_count: scope_id + 1,
         }
     }
 
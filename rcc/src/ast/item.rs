@@ -1,6 +1,6 @@
-use crate::ast::expr::BlockExpr;
+use crate::ast::expr::{BlockExpr, Expr};
 use crate::ast::pattern::Pattern;
-use crate::ast::types::TypeAnnotation;
+use crate::ast::types::{TypeAnnotation, TypeLitNum};
 use crate::ast::{NamedASTNode, TokenStart, Visibility};
 use crate::lexer::token::Token;
 use crate::rcc::RccError;
@@ -20,13 +20,16 @@ pub enum Item {
     Type,
 
     /// const A: i32 = 2;
-    Const,
+    Const(ItemConst),
 
     /// static B: i32 = 3;
-    Static,
+    Static(ItemStatic),
 
     /// impl Foo { ... }
-    Impl,
+    Impl(ItemImpl),
+
+    /// trait Foo { ... }
+    Trait(ItemTrait),
 
     /// extern "C" {}
     ExternalBlock(ItemExternalBlock),
@@ -37,6 +40,7 @@ impl TokenStart for Item {
         matches!(
             tk,
             Token::Pub
+                | Token::Pound
                 | Token::Priv
                 | Token::Fn
                 | Token::Const
@@ -44,6 +48,7 @@ impl TokenStart for Item {
                 | Token::Struct
                 | Token::Enum
                 | Token::Impl
+                | Token::Trait
         )
     }
 }
@@ -52,6 +57,8 @@ impl NamedASTNode for Item {
     fn ident_name(&self) -> &str {
         match self {
             Self::Fn(item_fn) => item_fn.ident_name(),
+            Self::Const(item_const) => &item_const.name,
+            Self::Static(item_static) => &item_static.name,
             _ => unimplemented!(),
         }
     }
@@ -62,15 +69,20 @@ pub trait FnSignature {
     fn name(&self) -> String;
     fn params(&self) -> Vec<TypeAnnotation>;
     fn ret_type(&self) -> TypeAnnotation;
+    /// Whether the first param is `self`, such functions are methods.
+    fn has_self(&self) -> bool;
 }
 
 #[derive(Debug, PartialEq)]
 pub struct ItemFn {
     vis: Visibility,
     pub name: String,
+    pub generics: Vec<GenericParam>,
     pub fn_params: FnParams,
     pub ret_type: TypeAnnotation,
     pub fn_block: BlockExpr,
+    /// generic functions are not resolved, but instantiated from the template
+    pub template: Option<Template>,
 }
 
 impl ItemFn {
@@ -84,11 +96,57 @@ impl ItemFn {
         ItemFn {
             vis,
             name,
+            generics: vec![],
             fn_params,
             ret_type,
             fn_block,
+            template: None,
         }
     }
+
+    pub fn generics(mut self, generics: Vec<GenericParam>, template: Template) -> Self {
+        self.generics = generics;
+        self.template = Some(template);
+        self
+    }
+
+    pub fn is_generic(&self) -> bool {
+        !self.generics.is_empty()
+    }
+}
+
+/// `T: Display + Clone` in `fn foo<T: Display + Clone>(x: T) {}`
+#[derive(Debug, PartialEq, Clone)]
+pub struct GenericParam {
+    pub name: String,
+    pub bounds: Vec<String>,
+}
+
+impl GenericParam {
+    pub fn new(name: String, bounds: Vec<String>) -> Self {
+        GenericParam { name, bounds }
+    }
+}
+
+/// Source tokens of an item, which is parsed again for each instance
+/// with the type parameters (and `Self`) replaced by the type arguments.
+#[derive(Debug, PartialEq)]
+pub struct Template {
+    pub tokens: Vec<String>,
+}
+
+impl Template {
+    /// Source text with the params in `type_args` replaced by the types.
+    pub fn substitute(&self, type_args: &[(String, String)]) -> String {
+        self.tokens
+            .iter()
+            .map(|tk| match type_args.iter().find(|(param, _)| param == tk) {
+                Some((_, type_arg)) => type_arg.as_str(),
+                None => tk.as_str(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
 }
 
 impl FnSignature for ItemFn {
@@ -107,6 +165,10 @@ impl FnSignature for ItemFn {
     fn ret_type(&self) -> TypeAnnotation {
         self.ret_type.clone()
     }
+
+    fn has_self(&self) -> bool {
+        self.fn_params.params.first().is_some_and(FnParam::is_self)
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -147,11 +209,16 @@ impl FnParam {
     pub fn new(pattern: Pattern, _type: TypeAnnotation) -> Self {
         FnParam { pattern, _type }
     }
+
+    /// `self`, `mut self`, `&self`, `&mut self` or `self: Type`
+    pub fn is_self(&self) -> bool {
+        matches!(&self.pattern, Pattern::Identifier(p) if p.ident() == "self")
+    }
 }
 
 impl TokenStart for FnParam {
     fn is_token_start(tk: &Token) -> bool {
-        Pattern::is_token_start(tk)
+        Pattern::is_token_start(tk) || matches!(tk, Token::SelfValue)
     }
 }
 
@@ -161,6 +228,157 @@ impl NamedASTNode for ItemFn {
     }
 }
 
+/// `const MAX: usize = 2 * 8;`, the value is evaluated at compile time
+/// and inlined where the constant is used.
+#[derive(Debug, PartialEq)]
+pub struct ItemConst {
+    vis: Visibility,
+    pub name: String,
+    pub type_anno: TypeAnnotation,
+    pub expr: Expr,
+}
+
+impl ItemConst {
+    pub fn new(vis: Visibility, name: String, type_anno: TypeAnnotation, expr: Expr) -> Self {
+        ItemConst {
+            vis,
+            name,
+            type_anno,
+            expr,
+        }
+    }
+}
+
+/// `static mut COUNT: u32 = 0;`, the initial value is evaluated at compile time
+/// and the variable lives in the data sections for the whole program.
+#[derive(Debug, PartialEq)]
+pub struct ItemStatic {
+    vis: Visibility,
+    pub name: String,
+    pub is_mut: bool,
+    pub type_anno: TypeAnnotation,
+    pub expr: Expr,
+}
+
+impl ItemStatic {
+    pub fn new(
+        vis: Visibility,
+        name: String,
+        is_mut: bool,
+        type_anno: TypeAnnotation,
+        expr: Expr,
+    ) -> Self {
+        ItemStatic {
+            vis,
+            name,
+            is_mut,
+            type_anno,
+            expr,
+        }
+    }
+}
+
+/// `impl Point { fn new() -> Self { .. } fn x(&self) -> i32 { .. } }`,
+/// the associated functions are named by their paths, like `Point::new`,
+/// and `Self` in their signatures is replaced by the implemented type.
+#[derive(Debug, PartialEq)]
+pub struct ItemImpl {
+    /// name of the implemented type, like `Pair<i32>` for the instances of generic structs
+    pub self_type: String,
+    pub self_type_anno: TypeAnnotation,
+    /// `impl Trait for Type`
+    pub trait_name: Option<String>,
+    pub generics: Vec<GenericParam>,
+    pub items: Vec<ItemFn>,
+    /// generic impls are instantiated from the template for the instances of the struct
+    pub template: Option<Template>,
+}
+
+impl ItemImpl {
+    pub fn new(self_type_anno: TypeAnnotation, items: Vec<ItemFn>) -> ItemImpl {
+        ItemImpl {
+            self_type: self_type_anno.to_string(),
+            self_type_anno,
+            trait_name: None,
+            generics: vec![],
+            items,
+            template: None,
+        }
+    }
+
+    pub fn trait_name(mut self, trait_name: String) -> ItemImpl {
+        self.trait_name = Some(trait_name);
+        self
+    }
+
+    pub fn generics(mut self, generics: Vec<GenericParam>, template: Template) -> Self {
+        self.generics = generics;
+        self.template = Some(template);
+        self
+    }
+
+    pub fn is_generic(&self) -> bool {
+        !self.generics.is_empty()
+    }
+
+    /// The name of the generic struct implemented by the generic impl.
+    pub fn generic_name(&self) -> Option<&str> {
+        match &self.self_type_anno {
+            TypeAnnotation::Generic(type_generic) => Some(&type_generic.name),
+            _ => None,
+        }
+    }
+}
+
+/// `trait Shape { fn area(&self) -> i32; fn double(&self) -> i32 { self.area() * 2 } }`,
+/// traits are only used by static dispatch.
+#[derive(Debug, PartialEq)]
+pub struct ItemTrait {
+    vis: Visibility,
+    pub name: String,
+    pub items: Vec<TraitItemFn>,
+}
+
+impl ItemTrait {
+    pub fn new(vis: Visibility, name: String, items: Vec<TraitItemFn>) -> ItemTrait {
+        ItemTrait { vis, name, items }
+    }
+
+    pub fn vis(&self) -> Visibility {
+        self.vis
+    }
+
+    pub fn find_fn(&self, name: &str) -> Option<&TraitItemFn> {
+        self.items.iter().find(|item| item.name == name)
+    }
+}
+
+/// A method declared in the trait, the default method is copied
+/// into the impls of the trait without the method.
+#[derive(Debug, PartialEq)]
+pub struct TraitItemFn {
+    pub name: String,
+    pub fn_params: FnParams,
+    pub ret_type: TypeAnnotation,
+    pub default: Option<Template>,
+}
+
+impl TraitItemFn {
+    pub fn new(name: String, fn_params: FnParams, ret_type: TypeAnnotation) -> TraitItemFn {
+        TraitItemFn {
+            name,
+            fn_params,
+            ret_type,
+            default: None,
+        }
+    }
+
+    pub fn default(mut self, template: Template) -> TraitItemFn {
+        self.default = Some(template);
+        self
+    }
+}
+
 /// # Examples
 /// `struct Student { name: String, age: u32 }`
 /// `pub struct Teacher(String, u32);`
@@ -168,7 +386,10 @@ impl NamedASTNode for ItemFn {
 pub struct ItemStruct {
     vis: Visibility,
     name: String,
+    pub generics: Vec<GenericParam>,
     fields: Fields,
+    /// generic structs are instantiated from the template for each type arguments
+    pub template: Option<Template>,
 }
 
 impl ItemStruct {
@@ -176,10 +397,26 @@ impl ItemStruct {
         ItemStruct {
             vis,
             name,
+            generics: vec![],
             fields: Fields::None,
+            template: None,
         }
     }
 
+    pub fn generics(mut self, generics: Vec<GenericParam>, template: Template) -> Self {
+        self.generics = generics;
+        self.template = Some(template);
+        self
+    }
+
+    pub fn is_generic(&self) -> bool {
+        !self.generics.is_empty()
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
     pub fn struct_fields(mut self, struct_fields: Vec<StructField>) -> Self {
         self.fields = Fields::Struct(struct_fields);
         self
@@ -203,6 +440,27 @@ impl ItemStruct {
     }
 }
 
+/// `#[name]` or `#[name(arg, ...)]`
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Attribute {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+impl Attribute {
+    pub fn new(name: String, args: Vec<String>) -> Attribute {
+        Attribute { name, args }
+    }
+}
+
+/// `#[repr(C)]`, `#[repr(u8)]` or `#[repr(C, u8)]` of enums
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct EnumRepr {
+    pub c: bool,
+    /// type of the discriminant
+    pub int_type: Option<TypeLitNum>,
+}
+
 /// enum Identity {
 ///     Student { name: String },
 ///     Teacher(String),
@@ -212,13 +470,96 @@ impl ItemStruct {
 pub struct TypeEnum {
     vis: Visibility,
     name: String,
+    repr: EnumRepr,
     enum_items: Vec<EnumVariant>,
 }
 
+impl TypeEnum {
+    pub fn new(vis: Visibility, name: String, repr: EnumRepr, enum_items: Vec<EnumVariant>) -> Self {
+        TypeEnum {
+            vis,
+            name,
+            repr,
+            enum_items,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn repr(&self) -> EnumRepr {
+        self.repr
+    }
+
+    pub fn variants(&self) -> &[EnumVariant] {
+        &self.enum_items
+    }
+
+    /// Index and definition of the variant named `name`
+    pub fn variant(&self, name: &str) -> Option<(usize, &EnumVariant)> {
+        self.enum_items
+            .iter()
+            .enumerate()
+            .find(|(_, v)| v.name == name)
+    }
+
+    /// Whether no variant has fields, such enums can be cast to integers
+    pub fn is_fieldless(&self) -> bool {
+        self.enum_items.iter().all(|v| v.fields == Fields::None)
+    }
+
+    /// Type of the discriminant, `isize` by default and `i32` for `#[repr(C)]`
+    pub fn discriminant_type(&self) -> TypeLitNum {
+        match self.repr.int_type {
+            Some(t) => t,
+            None if self.repr.c => TypeLitNum::I32,
+            None => TypeLitNum::Isize,
+        }
+    }
+
+    /// Discriminants of the variants, a variant without an explicit discriminant
+    /// takes the discriminant of the previous variant plus one, and the first one is 0.
+    pub fn discriminants(&self) -> Vec<i128> {
+        let mut next = 0;
+        self.enum_items
+            .iter()
+            .map(|v| {
+                let d = v.discriminant.unwrap_or(next);
+                next = d + 1;
+                d
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
 pub struct EnumVariant {
     name: String,
     fields: Fields,
+    discriminant: Option<i128>,
+}
+
+impl EnumVariant {
+    pub fn new(name: String, fields: Fields, discriminant: Option<i128>) -> Self {
+        EnumVariant {
+            name,
+            fields,
+            discriminant,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn fields(&self) -> &Fields {
+        &self.fields
+    }
+
+    pub fn discriminant(&self) -> Option<i128> {
+        self.discriminant
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
@@ -231,6 +572,24 @@ pub enum Fields {
     None,
 }
 
+impl Fields {
+    /// Names and types of the fields, tuple fields are named by their indexes.
+    pub fn types(&self) -> Vec<(String, &TypeAnnotation)> {
+        match self {
+            Fields::Struct(struct_fields) => struct_fields
+                .iter()
+                .map(|f| (f.name.clone(), &f._type))
+                .collect(),
+            Fields::Tuple(tuple_fields) => tuple_fields
+                .iter()
+                .enumerate()
+                .map(|(i, f)| (i.to_string(), &f._type))
+                .collect(),
+            Fields::None => vec![],
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct StructField {
     pub vis: Visibility,
@@ -317,4 +676,8 @@ impl FnSignature for ExternalItemFn {
     fn ret_type(&self) -> TypeAnnotation {
         self.ret_type.clone()
     }
+
+    fn has_self(&self) -> bool {
+        false
+    }
 }
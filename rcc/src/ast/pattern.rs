@@ -1,35 +1,134 @@
+use crate::ast::expr::RangeOp;
+use crate::ast::types::TypeLitNum;
 use crate::ast::TokenStart;
 use crate::lexer::token::Token;
 
 #[derive(Debug, PartialEq)]
 pub enum Pattern {
     Identifier(IdentPattern),
+    /// `_`
+    Wildcard,
+    /// `..`, only in tuple patterns
+    Rest,
+    Literal(LitPattern),
+    Range(RangePattern),
+    /// `&pattern` or `&mut pattern`
+    Ref(RefPattern),
+    /// `()`, `(a,)`, `(a, .., b)`
+    Tuple(Vec<Pattern>),
+    Struct(StructPattern),
+    /// `Foo(a, ..)` or `Enum::Variant(a, b)`
+    TupleStruct(TupleStructPattern),
+    /// `Enum::Variant` of unit variants
+    Path(String),
+    /// Alternatives separated by `|`, only at the top level of match arms
+    Or(Vec<Pattern>),
+}
+
+impl Pattern {
+    /// Whether the pattern matches any value,
+    /// paths with `::` are enum variants which are refutable.
+    pub fn is_irrefutable(&self) -> bool {
+        match self {
+            Pattern::Identifier(_) | Pattern::Wildcard | Pattern::Rest => true,
+            Pattern::Literal(_) | Pattern::Range(_) | Pattern::Path(_) => false,
+            Pattern::Ref(ref_pattern) => ref_pattern.pattern.is_irrefutable(),
+            Pattern::Tuple(patterns) => patterns.iter().all(|p| p.is_irrefutable()),
+            Pattern::Struct(struct_pattern) => {
+                !is_variant_path(&struct_pattern.path)
+                    && struct_pattern
+                        .fields
+                        .iter()
+                        .all(|f| f.pattern.is_irrefutable())
+            }
+            Pattern::TupleStruct(tuple_struct_pattern) => {
+                !is_variant_path(&tuple_struct_pattern.path)
+                    && tuple_struct_pattern
+                        .patterns
+                        .iter()
+                        .all(|p| p.is_irrefutable())
+            }
+            Pattern::Or(patterns) => patterns.iter().any(|p| p.is_irrefutable()),
+        }
+    }
+
+    /// All literals in the pattern, including the bounds of ranges
+    pub fn literals(&self) -> Vec<&LitPattern> {
+        match self {
+            Pattern::Identifier(_) | Pattern::Wildcard | Pattern::Rest | Pattern::Path(_) => {
+                vec![]
+            }
+            Pattern::Literal(lit) => vec![lit],
+            Pattern::Range(range) => vec![&range.lhs, &range.rhs],
+            Pattern::Ref(ref_pattern) => ref_pattern.pattern.literals(),
+            Pattern::Tuple(patterns)
+            | Pattern::Or(patterns)
+            | Pattern::TupleStruct(TupleStructPattern { patterns, .. }) => {
+                patterns.iter().flat_map(|p| p.literals()).collect()
+            }
+            Pattern::Struct(struct_pattern) => struct_pattern
+                .fields
+                .iter()
+                .flat_map(|f| f.pattern.literals())
+                .collect(),
+        }
+    }
+}
+
+/// Whether the path of a pattern is `Enum::Variant`
+pub fn is_variant_path(path: &str) -> bool {
+    path.contains("::")
 }
 
 impl TokenStart for Pattern {
     fn is_token_start(tk: &Token) -> bool {
         IdentPattern::is_token_start(tk)
+            || matches!(
+                tk,
+                Token::Mut
+                    | Token::Literal { .. }
+                    | Token::Minus
+                    | Token::True
+                    | Token::False
+                    | Token::DotDot
+                    | Token::And
+                    | Token::AndAnd
+                    | Token::LeftParen
+            )
     }
 }
 
+/// `ref`? `mut`? identifier
 #[derive(Debug, PartialEq)]
 pub struct IdentPattern {
     ident: String,
     is_mut: bool,
+    is_ref: bool,
 }
 
 impl IdentPattern {
     pub fn new_mut(ident: String) -> Self {
         IdentPattern {
             ident,
-            is_mut: true
+            is_mut: true,
+            is_ref: false,
         }
     }
 
     pub fn new_const(ident: String) -> Self {
         IdentPattern {
             ident,
-            is_mut: false
+            is_mut: false,
+            is_ref: false,
+        }
+    }
+
+    /// `ref ident` or `ref mut ident`, the variable is bound to a reference of the value
+    pub fn new_ref(ident: String, is_mut: bool) -> Self {
+        IdentPattern {
+            ident,
+            is_mut,
+            is_ref: true,
         }
     }
 
@@ -37,6 +136,10 @@ impl IdentPattern {
         self.is_mut
     }
 
+    pub fn is_ref(&self) -> bool {
+        self.is_ref
+    }
+
     pub fn ident(&self) -> &str {
         &self.ident
     }
@@ -44,6 +147,108 @@ impl IdentPattern {
 
 impl TokenStart for IdentPattern {
     fn is_token_start(tk: &Token) -> bool {
-        matches!(tk, Token::Identifier(_)) 
+        matches!(tk, Token::Identifier(_) | Token::Ref)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RefPattern {
+    pub is_mut: bool,
+    pub pattern: Box<Pattern>,
+}
+
+impl RefPattern {
+    pub fn new(is_mut: bool, pattern: Pattern) -> RefPattern {
+        RefPattern {
+            is_mut,
+            pattern: Box::new(pattern),
+        }
+    }
+}
+
+/// `Foo { a, b: 0, ref mut c, .. }`
+#[derive(Debug, PartialEq)]
+pub struct StructPattern {
+    pub path: String,
+    pub fields: Vec<FieldPattern>,
+    /// whether the fields not mentioned are ignored by `..`
+    pub has_rest: bool,
+}
+
+impl StructPattern {
+    pub fn new(path: String, fields: Vec<FieldPattern>, has_rest: bool) -> StructPattern {
+        StructPattern {
+            path,
+            fields,
+            has_rest,
+        }
+    }
+}
+
+/// `Foo(a, ..)`, the patterns are matched like the patterns in tuple patterns
+#[derive(Debug, PartialEq)]
+pub struct TupleStructPattern {
+    pub path: String,
+    pub patterns: Vec<Pattern>,
+}
+
+impl TupleStructPattern {
+    pub fn new(path: String, patterns: Vec<Pattern>) -> TupleStructPattern {
+        TupleStructPattern { path, patterns }
     }
-}
\ No newline at end of file
+}
+
+/// `name: pattern`, or `name` alone which is short for `name: name`
+#[derive(Debug, PartialEq)]
+pub struct FieldPattern {
+    pub name: String,
+    pub pattern: Pattern,
+}
+
+impl FieldPattern {
+    pub fn new(name: String, pattern: Pattern) -> FieldPattern {
+        FieldPattern { name, pattern }
+    }
+}
+
+/// Literals of integers, `bool` and `char` in patterns
+#[derive(Debug, PartialEq, Clone)]
+pub enum LitPattern {
+    Bool(bool),
+    Char(char),
+    /// `-`? INTEGER_LITERAL, the type is `TypeLitNum::I` without suffix
+    Int(i128, TypeLitNum),
+}
+
+impl LitPattern {
+    /// Value of the literal as an integer, `true` is 1 and chars are their code points
+    pub fn value(&self) -> i128 {
+        match self {
+            LitPattern::Bool(b) => *b as i128,
+            LitPattern::Char(c) => *c as i128,
+            LitPattern::Int(i, _) => *i,
+        }
+    }
+}
+
+/// `a..=b` or `a..b`
+#[derive(Debug, PartialEq)]
+pub struct RangePattern {
+    pub lhs: LitPattern,
+    pub range_op: RangeOp,
+    pub rhs: LitPattern,
+}
+
+impl RangePattern {
+    pub fn new(lhs: LitPattern, range_op: RangeOp, rhs: LitPattern) -> RangePattern {
+        RangePattern { lhs, range_op, rhs }
+    }
+
+    /// The smallest and the largest values in the range
+    pub fn bounds(&self) -> (i128, i128) {
+        match self.range_op {
+            RangeOp::DotDotEq => (self.lhs.value(), self.rhs.value()),
+            RangeOp::DotDot => (self.lhs.value(), self.rhs.value() - 1),
+        }
+    }
+}
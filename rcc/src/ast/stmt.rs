@@ -75,6 +75,7 @@ impl LetStmt {
     pub fn is_mut(&self) -> bool {
         match &self.pattern {
             Pattern::Identifier(i) => i.is_mut(),
+            _ => false,
         }
     }
 }
@@ -1,6 +1,6 @@
 use crate::ast::item::{ExternalItemFn, ItemFn, FnSignature};
 use crate::ast::types::TypeAnnotation::{Identifier, Tuple};
-use std::fmt::{Debug, Formatter};
+use std::fmt::{Debug, Display, Formatter};
 use strenum::StrEnum;
 
 #[derive(PartialEq, Clone, Eq, Hash)]
@@ -24,6 +24,9 @@ pub enum TypeAnnotation {
 
     Ptr(TypePtr),
 
+    /// `Pair<i32>`, the instances of generic structs
+    Generic(TypeGeneric),
+
     /// !
     Never,
     Unit,
@@ -55,6 +58,7 @@ impl Debug for TypeAnnotation {
             Self::Slice(ts) => write!(f, "[{:?}]", ts),
             Self::FnPtr(fptr) => write!(f, "{:?}", fptr),
             Self::Ptr(ptr) => write!(f, "{:?}", ptr),
+            Self::Generic(tg) => write!(f, "{:?}", tg),
             Self::Never => write!(f, "!"),
             Self::Unit => write!(f, "()"),
             Self::Bool => write!(f, "bool"),
@@ -65,13 +69,104 @@ impl Debug for TypeAnnotation {
     }
 }
 
+/// The type in the syntax of Rust, the same as the name of the resolved type,
+/// e.g. `Pair<&i32>` is the name of the instance of the generic struct `Pair`.
+impl Display for TypeAnnotation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Identifier(s) => f.write_str(s),
+            Self::Tuple(types) => {
+                let types: Vec<String> = types.iter().map(|t| t.to_string()).collect();
+                match types.len() {
+                    1 => write!(f, "({},)", types[0]),
+                    _ => write!(f, "({})", types.join(", ")),
+                }
+            }
+            Self::Array(ta) => write!(f, "[{}; {}]", ta._type, ta.len),
+            Self::Slice(ts) => write!(f, "[{}]", ts),
+            Self::FnPtr(fptr) => write!(f, "fn({:?}) -> {:?}", fptr.params, fptr.ret_type),
+            Self::Ptr(ptr) => {
+                let kind = match ptr.ptr_kind {
+                    PtrKind::Ref => "&",
+                    PtrKind::MutRef => "&mut ",
+                    PtrKind::ConstRawPtr => "*const ",
+                    PtrKind::MutRawPtr => "*mut ",
+                };
+                write!(f, "{}{}", kind, ptr.type_anno)
+            }
+            Self::Generic(tg) => {
+                let args: Vec<String> = tg.args.iter().map(|t| t.to_string()).collect();
+                write!(f, "{}<{}>", tg.name, args.join(", "))
+            }
+            Self::Never => f.write_str("!"),
+            Self::Unit => f.write_str("()"),
+            Self::Bool => f.write_str("bool"),
+            Self::Str => f.write_str("str"),
+            Self::Char => f.write_str("char"),
+            Self::Unknown => f.write_str("_"),
+        }
+    }
+}
+
+impl TypeAnnotation {
+    /// Replace `Self` in the type by `self_type`.
+    pub fn replace_self_type(&mut self, self_type: &TypeAnnotation) {
+        match self {
+            Identifier(s) if s == "Self" => *self = self_type.clone(),
+            Tuple(types) => types
+                .iter_mut()
+                .for_each(|t| t.replace_self_type(self_type)),
+            Self::Array(ta) => ta._type.replace_self_type(self_type),
+            Self::Slice(ts) => ts.replace_self_type(self_type),
+            Self::FnPtr(fn_ptr) => {
+                fn_ptr
+                    .params
+                    .iter_mut()
+                    .for_each(|t| t.replace_self_type(self_type));
+                fn_ptr.ret_type.replace_self_type(self_type);
+            }
+            Self::Ptr(ptr) => ptr.type_anno.replace_self_type(self_type),
+            Self::Generic(tg) => tg
+                .args
+                .iter_mut()
+                .for_each(|t| t.replace_self_type(self_type)),
+            _ => {}
+        }
+    }
+
+    /// Whether the type param `name` is used in the type.
+    pub fn mentions(&self, name: &str) -> bool {
+        match self {
+            Identifier(s) => s == name,
+            Tuple(types) => types.iter().any(|t| t.mentions(name)),
+            Self::Array(ta) => ta._type.mentions(name),
+            Self::Slice(ts) => ts.mentions(name),
+            Self::FnPtr(fn_ptr) => {
+                fn_ptr.params.iter().any(|t| t.mentions(name)) || fn_ptr.ret_type.mentions(name)
+            }
+            Self::Ptr(ptr) => ptr.type_anno.mentions(name),
+            Self::Generic(tg) => tg.args.iter().any(|t| t.mentions(name)),
+            _ => false,
+        }
+    }
+}
+
 pub type TypeTuple = Vec<TypeAnnotation>;
 pub type TypeSlice = Box<TypeAnnotation>;
 
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
 pub struct TypeArray {
-    _type: Box<TypeAnnotation>,
-    len: u32,
+    pub _type: Box<TypeAnnotation>,
+    pub len: u32,
+}
+
+impl TypeArray {
+    pub fn new(_type: TypeAnnotation, len: u32) -> TypeArray {
+        TypeArray {
+            _type: Box::new(_type),
+            len,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
@@ -106,6 +201,23 @@ pub enum PtrKind {
     ConstRawPtr,
 }
 
+impl PtrKind {
+    pub fn is_raw(self) -> bool {
+        matches!(self, PtrKind::MutRawPtr | PtrKind::ConstRawPtr)
+    }
+
+    /// Whether pointers of this kind can be used as pointers of `kind`,
+    /// `&mut T` coerces into `&T`, `*mut T` and `*const T`, `&T` and `*mut T` into `*const T`.
+    pub fn coerces_to(self, kind: PtrKind) -> bool {
+        use PtrKind::*;
+        self == kind
+            || matches!(
+                (self, kind),
+                (MutRef, Ref | MutRawPtr | ConstRawPtr) | (Ref | MutRawPtr, ConstRawPtr)
+            )
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct TypePtr {
     pub ptr_kind: PtrKind,
@@ -121,6 +233,31 @@ impl TypePtr {
     }
 }
 
+#[derive(PartialEq, Eq, Hash, Clone)]
+pub struct TypeGeneric {
+    pub name: String,
+    pub args: Vec<TypeAnnotation>,
+}
+
+impl TypeGeneric {
+    pub fn new(name: String, args: Vec<TypeAnnotation>) -> TypeGeneric {
+        TypeGeneric { name, args }
+    }
+}
+
+impl Debug for TypeGeneric {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}<", self.name)?;
+        for (i, arg) in self.args.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{:?}", arg)?;
+        }
+        write!(f, ">")
+    }
+}
+
 #[derive(StrEnum, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum TypeLitNum {
     F32,
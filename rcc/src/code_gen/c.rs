@@ -0,0 +1,701 @@
+//! Portable C99 backend.
+//!
+//! Every function of `CFGIR` is translated into a C function whose locals
+//! are plain C variables and whose basic blocks are labels connected by `goto`.
+use crate::analyser::sym_resolver::VarKind;
+use crate::ast::expr::BinOperator;
+use crate::code_gen::mangle;
+use crate::ir::cfg::{BasicBlockId, CFG, CFGIR};
+use crate::ir::linear_ir::ExternFn;
+use crate::ir::{IRInst, IRType, Jump, Operand, Place};
+use crate::rcc::RccError;
+use std::collections::HashSet;
+use std::io::{BufWriter, Write};
+
+/// Size of `intptr_t` is decided by the C compiler, `addr_size` is only used
+/// for choosing the C types of fixed size integers.
+pub(crate) const C_ADDR_SIZE: u32 = 64;
+
+pub struct CCodeGen<'w, W: Write> {
+    cfg_ir: CFGIR,
+    output: &'w mut BufWriter<W>,
+}
+
+impl<'w, W: 'w + Write> CCodeGen<'w, W> {
+    pub fn new(cfg_ir: CFGIR, output: &'w mut BufWriter<W>) -> CCodeGen<'w, W> {
+        CCodeGen { cfg_ir, output }
+    }
+
+    pub fn run(&mut self) -> Result<(), RccError> {
+        writeln!(self.output, "#include <stdbool.h>")?;
+        writeln!(self.output, "#include <stdint.h>")?;
+        writeln!(self.output, "#include <string.h>")?;
+        self.gen_read_only_local_str()?;
+        self.gen_statics()?;
+        self.gen_prototypes()?;
+        self.gen_functions()?;
+        Ok(())
+    }
+
+    fn gen_read_only_local_str(&mut self) -> Result<(), RccError> {
+        let mut strs: Vec<(&String, &String)> = self.cfg_ir.ro_local_strs.iter().collect();
+        strs.sort();
+        if !strs.is_empty() {
+            writeln!(self.output)?;
+        }
+        for (label, s) in strs {
+            writeln!(
+                self.output,
+                "static const char {}[] = \"{}\";",
+                str_name(label),
+                s
+            )?;
+        }
+        Ok(())
+    }
+
+    /// `extern "C"` items and forward declarations of all the functions,
+    /// so that functions can be called before they are defined.
+    /// Statics are arrays of their flattened values, which are accessed by their addresses.
+    fn gen_statics(&mut self) -> Result<(), RccError> {
+        if !self.cfg_ir.statics.is_empty() {
+            writeln!(self.output)?;
+        }
+        for static_data in self.cfg_ir.statics.iter() {
+            let values = static_data
+                .values
+                .iter()
+                .map(operand)
+                .collect::<Result<Vec<String>, RccError>>()?;
+            writeln!(
+                self.output,
+                "static {}{} {}[{}] = {{{}}};",
+                if static_data.is_mut { "" } else { "const " },
+                c_type(&static_data.elem_type),
+                var_name(&static_data.label),
+                values.len().max(1),
+                if values.is_empty() {
+                    "0".to_string()
+                } else {
+                    values.join(", ")
+                }
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Aggregates of `extern "C"` functions are structs of their scalar fields,
+    /// which are laid out and passed the same as the C structs.
+    fn gen_prototypes(&mut self) -> Result<(), RccError> {
+        writeln!(self.output)?;
+        for extern_fn in self.cfg_ir.extern_fns.iter() {
+            let mut params = vec![];
+            for (i, (ir_type, fields)) in extern_fn
+                .param_types
+                .iter()
+                .zip(extern_fn.param_fields.iter())
+                .enumerate()
+            {
+                if ir_type.is_aggregate() {
+                    let name = format!("{}_arg{}", extern_fn.name, i);
+                    writeln!(self.output, "{}", c_struct(&name, fields))?;
+                    params.push(format!("struct {}", name));
+                } else {
+                    params.push(c_type(ir_type).to_string());
+                }
+            }
+            let ret_type = if extern_fn.ret_type.is_aggregate() {
+                let name = format!("{}_ret", extern_fn.name);
+                writeln!(self.output, "{}", c_struct(&name, &extern_fn.ret_fields))?;
+                format!("struct {}", name)
+            } else {
+                c_type(&extern_fn.ret_type).to_string()
+            };
+            writeln!(
+                self.output,
+                "{} {}({});",
+                ret_type,
+                extern_fn.name,
+                param_list(params.iter().map(String::as_str).collect())
+            )?;
+        }
+        for cfg in self.cfg_ir.cfgs.iter() {
+            writeln!(self.output, "{};", fn_signature(cfg))?;
+        }
+        Ok(())
+    }
+
+    fn gen_functions(&mut self) -> Result<(), RccError> {
+        for cfg in self.cfg_ir.cfgs.iter() {
+            let mut func_gen = CFuncCodeGen::new(cfg, &self.cfg_ir, self.output);
+            func_gen.gen_function()?;
+        }
+        Ok(())
+    }
+}
+
+struct CFuncCodeGen<'w: 'codegen, 'codegen, W: Write> {
+    cfg: &'codegen CFG,
+    cfg_ir: &'codegen CFGIR,
+    output: &'w mut BufWriter<W>,
+}
+
+impl<'w: 'codegen, 'codegen, W: Write> CFuncCodeGen<'w, 'codegen, W> {
+    fn new(
+        cfg: &'codegen CFG,
+        cfg_ir: &'codegen CFGIR,
+        output: &'w mut BufWriter<W>,
+    ) -> CFuncCodeGen<'w, 'codegen, W> {
+        CFuncCodeGen {
+            cfg,
+            cfg_ir,
+            output,
+        }
+    }
+
+    fn gen_function(&mut self) -> Result<(), RccError> {
+        writeln!(self.output)?;
+        writeln!(self.output, "{} {{", fn_signature(self.cfg))?;
+        self.gen_local_variables()?;
+        self.gen_instructions()?;
+        writeln!(self.output, "}}")?;
+        Ok(())
+    }
+
+    fn gen_local_variables(&mut self) -> Result<(), RccError> {
+        let mut locals: Vec<(&usize, &String, &IRType)> = self
+            .cfg
+            .local_variables
+            .iter()
+            .filter(|(name, _)| !self.cfg.fn_args_local_var.contains(name))
+            .map(|(name, (id, ir_type))| (id, name, ir_type))
+            .collect();
+        locals.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+        for (_, name, ir_type) in locals {
+            if let IRType::Aggregate { size, .. } = ir_type {
+                // aligned for any field
+                writeln!(
+                    self.output,
+                    "\tuint64_t {}[{}];",
+                    var_name(name),
+                    size.div_ceil(8).max(1)
+                )?;
+            } else if !matches!(ir_type, IRType::Unit | IRType::Never) {
+                writeln!(self.output, "\t{} {};", c_type(ir_type), var_name(name))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn gen_instructions(&mut self) -> Result<(), RccError> {
+        let jump_targets: HashSet<BasicBlockId> = self
+            .cfg
+            .basic_blocks
+            .iter()
+            .flat_map(|bb| bb.instructions.iter())
+            .flat_map(|inst| match inst {
+                IRInst::Jump { label }
+                | IRInst::JumpIfCond { label, .. }
+                | IRInst::JumpIf { label, .. }
+                | IRInst::JumpIfNot { label, .. } => vec![*label],
+                IRInst::JumpTable {
+                    labels, default, ..
+                } => labels.iter().copied().chain(Some(*default)).collect(),
+                _ => vec![],
+            })
+            .collect();
+        for bb in self.cfg.basic_blocks.iter() {
+            if jump_targets.contains(&bb.id) {
+                writeln!(self.output, "{}:;", block_name(bb.id))?;
+            }
+            let mut iter = bb.instructions.iter().peekable();
+            while let Some(inst) = iter.next() {
+                if let IRInst::Call { callee, args } = inst {
+                    // `dest = f(args)` is split into `call f(args)` and `dest = FnRetPlace`.
+                    if let Some(IRInst::LoadData {
+                        dest,
+                        src: Operand::FnRetPlace(_),
+                    }) = iter.peek()
+                    {
+                        let call = self.call(callee, args)?;
+                        self.assign(dest, &call)?;
+                        iter.next();
+                        continue;
+                    }
+                }
+                self.gen_instruction(inst)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn gen_instruction(&mut self, inst: &IRInst) -> Result<(), RccError> {
+        match inst {
+            IRInst::Ret(o) => {
+                if o.is_unit_or_never() || self.cfg.ret_type == IRType::Unit {
+                    writeln!(self.output, "\treturn;")?;
+                } else {
+                    writeln!(self.output, "\treturn {};", operand(o)?)?;
+                }
+            }
+            IRInst::LoadData { dest, src } => match src {
+                Operand::Unit | Operand::Never => {}
+                Operand::Place(p) if dest.ir_type.is_aggregate() => {
+                    self.copy(&pointer_to(dest), &pointer_to(p), &dest.ir_type)?
+                }
+                Operand::FnRetPlace(_) => {
+                    return Err("error in c code gen: function return value is lost".into())
+                }
+                _ => self.assign(dest, &operand(src)?)?,
+            },
+            IRInst::Cast { dest, src } => {
+                let s = match (operand_ir_type(src), dest.ir_type) {
+                    (IRType::F32 | IRType::F64, IRType::F32 | IRType::F64) => operand(src)?,
+                    (IRType::F32 | IRType::F64, ir_type) => float_to_int(src, &ir_type)?,
+                    _ => operand(src)?,
+                };
+                self.assign(dest, &s)?;
+            }
+            IRInst::Neg { dest, src } => {
+                let s = format!("(-{})", operand(src)?);
+                self.assign(dest, &s)?;
+            }
+            IRInst::LoadAddr { dest, symbol } => {
+                let s = format!("(uintptr_t)&{}", operand(symbol)?);
+                self.assign(dest, &s)?;
+            }
+            IRInst::LoadMem { dest, base, offset } => {
+                let src = mem_addr(base, *offset)?;
+                self.copy(&pointer_to(dest), &src, &dest.ir_type)?;
+            }
+            IRInst::StoreMem { base, offset, src } => {
+                let dest = mem_addr(base, *offset)?;
+                match src {
+                    Operand::Unit | Operand::Never => {}
+                    Operand::Place(p) if p.kind != VarKind::LitConst => {
+                        self.copy(&dest, &pointer_to(p), &p.ir_type)?
+                    }
+                    _ => {
+                        let ir_type = operand_ir_type(src);
+                        let src = format!("&({}){{{}}}", c_type(&ir_type), operand(src)?);
+                        self.copy(&dest, &src, &ir_type)?;
+                    }
+                }
+            }
+            IRInst::BinOp {
+                op,
+                dest,
+                src1,
+                src2,
+            } => {
+                let (lhs, rhs) = (operand(src1)?, operand(src2)?);
+                let s = match wrapping_type(op, &operand_ir_type(src1)) {
+                    Some(unsigned) => {
+                        let rhs = match op {
+                            BinOperator::Shl => rhs,
+                            _ => format!("({}){}", unsigned, rhs),
+                        };
+                        format!("(({}){} {} {})", unsigned, lhs, bin_op(op), rhs)
+                    }
+                    None => format!("({} {} {})", lhs, bin_op(op), rhs),
+                };
+                self.assign(dest, &s)?;
+            }
+            IRInst::Call { callee, args } => {
+                let call = self.call(callee, args)?;
+                writeln!(self.output, "\t{};", call)?;
+            }
+            IRInst::Jump { label } => {
+                writeln!(self.output, "\tgoto {};", block_name(*label))?;
+            }
+            IRInst::JumpIfCond {
+                cond,
+                src1,
+                src2,
+                label,
+            } => {
+                let cmp = match cond {
+                    Jump::JEq => "==",
+                    Jump::JNe => "!=",
+                    Jump::JLt => "<",
+                    Jump::JGe => ">=",
+                };
+                writeln!(
+                    self.output,
+                    "\tif ({} {} {}) goto {};",
+                    operand(src1)?,
+                    cmp,
+                    operand(src2)?,
+                    block_name(*label)
+                )?;
+            }
+            IRInst::JumpIf { cond, label } => {
+                writeln!(
+                    self.output,
+                    "\tif ({}) goto {};",
+                    operand(cond)?,
+                    block_name(*label)
+                )?;
+            }
+            IRInst::JumpIfNot { cond, label } => {
+                writeln!(
+                    self.output,
+                    "\tif (!{}) goto {};",
+                    operand(cond)?,
+                    block_name(*label)
+                )?;
+            }
+            IRInst::JumpTable {
+                src,
+                low,
+                labels,
+                default,
+            } => {
+                writeln!(self.output, "\tswitch ({}) {{", operand(src)?)?;
+                for (i, label) in labels.iter().enumerate() {
+                    writeln!(
+                        self.output,
+                        "\tcase {}: goto {};",
+                        low + i as i64,
+                        block_name(*label)
+                    )?;
+                }
+                writeln!(self.output, "\tdefault: goto {};", block_name(*default))?;
+                writeln!(self.output, "\t}}")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn assign(&mut self, dest: &Place, rhs: &str) -> Result<(), RccError> {
+        if matches!(dest.ir_type, IRType::Unit | IRType::Never) {
+            writeln!(self.output, "\t{};", rhs)?;
+        } else {
+            writeln!(
+                self.output,
+                "\t{} = ({}){};",
+                var_name(&dest.label),
+                c_type(&dest.ir_type),
+                rhs
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Values are copied by `memcpy` between the memory of aggregates and other values.
+    fn copy(&mut self, dest: &str, src: &str, ir_type: &IRType) -> Result<(), RccError> {
+        let size = ir_type.byte_size(C_ADDR_SIZE);
+        if size != 0 {
+            writeln!(self.output, "\tmemcpy({}, {}, {});", dest, src, size)?;
+        }
+        Ok(())
+    }
+
+    fn call(&self, callee: &Operand, args: &[Operand]) -> Result<String, RccError> {
+        let fn_name = match callee {
+            Operand::FnLabel(fn_name) => fn_name,
+            _ => unreachable!(),
+        };
+        if let Some(extern_fn) = self.cfg_ir.extern_fns.iter().find(|f| &f.name == fn_name) {
+            return c_call(extern_fn, args);
+        }
+        let mut c_args = vec![];
+        for arg in args.iter().filter(|a| !a.is_unit_or_never()) {
+            c_args.push(operand(arg)?);
+        }
+        debug_assert!(
+            self.cfg_ir.cfgs.iter().any(|c| &c.func_name == fn_name)
+                || self.cfg_ir.extern_fns.iter().any(|f| &f.name == fn_name)
+        );
+        Ok(format!("{}({})", mangle(fn_name), c_args.join(", ")))
+    }
+}
+
+/// Call of the C function `extern_fn`, the aggregates are passed and returned by value
+/// instead of the addresses of them.
+fn c_call(extern_fn: &ExternFn, args: &[Operand]) -> Result<String, RccError> {
+    let mut args = args.iter();
+    let ret_addr = if extern_fn.ret_type.is_aggregate() {
+        Some(operand(args.next().unwrap())?)
+    } else {
+        None
+    };
+    let mut c_args = vec![];
+    for (i, (arg, ir_type)) in args.zip(extern_fn.param_types.iter()).enumerate() {
+        if ir_type.is_aggregate() {
+            c_args.push(format!(
+                "*(struct {}_arg{} *){}",
+                extern_fn.name,
+                i,
+                operand(arg)?
+            ));
+        } else if !arg.is_unit_or_never() {
+            c_args.push(operand(arg)?);
+        }
+    }
+    let call = format!("{}({})", mangle(&extern_fn.name), c_args.join(", "));
+    Ok(match ret_addr {
+        Some(addr) => format!("*(struct {}_ret *){} = {}", extern_fn.name, addr, call),
+        None => call,
+    })
+}
+
+/// C struct `name` of the scalar fields, which are named by their offsets.
+fn c_struct(name: &str, fields: &[(u32, IRType)]) -> String {
+    let mut s = format!("struct {} {{\n", name);
+    for (offset, ir_type) in fields.iter() {
+        s += &format!("\t{} f{};\n", c_type(ir_type), offset);
+    }
+    s + "};"
+}
+
+/// C type of `ir_type`, integers are mapped to the exact-width types of `<stdint.h>`.
+pub fn c_type(ir_type: &IRType) -> &'static str {
+    match ir_type {
+        IRType::Unit | IRType::Never => "void",
+        IRType::Bool => "bool",
+        IRType::F32 => "float",
+        IRType::F64 => "double",
+        IRType::Isize => "intptr_t",
+        IRType::Usize | IRType::Addr => "uintptr_t",
+        IRType::Aggregate { .. } => unreachable!("aggregates are declared as arrays"),
+        IRType::I8 | IRType::I16 | IRType::I32 | IRType::I64 | IRType::I128 => {
+            match ir_type.byte_size(C_ADDR_SIZE) {
+                1 => "int8_t",
+                2 => "int16_t",
+                4 => "int32_t",
+                8 => "int64_t",
+                _ => "__int128",
+            }
+        }
+        IRType::Char | IRType::U8 | IRType::U16 | IRType::U32 | IRType::U64 | IRType::U128 => {
+            match ir_type.byte_size(C_ADDR_SIZE) {
+                1 => "uint8_t",
+                2 => "uint16_t",
+                4 => "uint32_t",
+                8 => "uint64_t",
+                _ => "unsigned __int128",
+            }
+        }
+    }
+}
+
+fn param_list(params: Vec<&str>) -> String {
+    let params: Vec<&str> = params.into_iter().filter(|p| p != &"void").collect();
+    if params.is_empty() {
+        "void".to_string()
+    } else {
+        params.join(", ")
+    }
+}
+
+fn fn_signature(cfg: &CFG) -> String {
+    let mut params = vec![];
+    for (i, (_, ir_type)) in cfg.fn_args.iter().enumerate() {
+        if !matches!(ir_type, IRType::Unit | IRType::Never) {
+            params.push(format!(
+                "{} {}",
+                c_type(ir_type),
+                var_name(&cfg.fn_args_local_var[i])
+            ));
+        }
+    }
+    format!(
+        "{}{} {}({})",
+        if cfg.func_is_global { "" } else { "static " },
+        c_type(&cfg.ret_type),
+        mangle(&cfg.func_name),
+        if params.is_empty() {
+            "void".to_string()
+        } else {
+            params.join(", ")
+        }
+    )
+}
+
+/// Pointer to the value of `place`, aggregates are arrays which are converted to pointers.
+fn pointer_to(place: &Place) -> String {
+    if place.ir_type.is_aggregate() {
+        var_name(&place.label)
+    } else {
+        format!("&{}", var_name(&place.label))
+    }
+}
+
+/// `base + offset` as a pointer, `base` is an address or an aggregate
+fn mem_addr(base: &Operand, offset: u32) -> Result<String, RccError> {
+    Ok(format!("(char *){} + {}", operand(base)?, offset))
+}
+
+/// `src as ir_type` of a floating-point number is saturated and `NaN` is `0`,
+/// the conversion of C is undefined if the value is out of the range of the integer type.
+fn float_to_int(src: &Operand, ir_type: &IRType) -> Result<String, RccError> {
+    let (min, max) = int_bounds(ir_type);
+    Ok(format!(
+        "({0} != {0} ? 0 : {0} <= {1} ? {1} : {0} >= {2} ? {2} : ({3}){0})",
+        operand(src)?,
+        min,
+        max,
+        c_type(ir_type)
+    ))
+}
+
+/// Minimum and maximum of the C type of an integer type
+fn int_bounds(ir_type: &IRType) -> (&'static str, &'static str) {
+    match c_type(ir_type) {
+        "int8_t" => ("INT8_MIN", "INT8_MAX"),
+        "int16_t" => ("INT16_MIN", "INT16_MAX"),
+        "int32_t" => ("INT32_MIN", "INT32_MAX"),
+        "int64_t" => ("INT64_MIN", "INT64_MAX"),
+        "intptr_t" => ("INTPTR_MIN", "INTPTR_MAX"),
+        "__int128" => (
+            "(-(__int128)(~(unsigned __int128)0 >> 1) - 1)",
+            "(__int128)(~(unsigned __int128)0 >> 1)",
+        ),
+        "uint8_t" => ("0", "UINT8_MAX"),
+        "uint16_t" => ("0", "UINT16_MAX"),
+        "uint32_t" => ("0", "UINT32_MAX"),
+        "uint64_t" => ("0", "UINT64_MAX"),
+        "uintptr_t" => ("0", "UINTPTR_MAX"),
+        "unsigned __int128" => ("0", "~(unsigned __int128)0"),
+        t => unreachable!("not an integer type: {}", t),
+    }
+}
+
+/// IR type of a value which is not a place
+fn operand_ir_type(operand: &Operand) -> IRType {
+    match operand {
+        Operand::F32(_) => IRType::F32,
+        Operand::F64(_) => IRType::F64,
+        Operand::Bool(_) => IRType::Bool,
+        Operand::Char(_) => IRType::Char,
+        Operand::I8(_) => IRType::I8,
+        Operand::I16(_) => IRType::I16,
+        Operand::I32(_) => IRType::I32,
+        Operand::I64(_) => IRType::I64,
+        Operand::I128(_) => IRType::I128,
+        Operand::Isize(_) => IRType::Isize,
+        Operand::U8(_) => IRType::U8,
+        Operand::U16(_) => IRType::U16,
+        Operand::U32(_) => IRType::U32,
+        Operand::U64(_) => IRType::U64,
+        Operand::U128(_) => IRType::U128,
+        Operand::Usize(_) => IRType::Usize,
+        Operand::Place(p) => p.ir_type,
+        Operand::FnRetPlace(ir_type) => *ir_type,
+        Operand::FnLabel(_) => IRType::Addr,
+        Operand::Unit => IRType::Unit,
+        Operand::Never => IRType::Never,
+    }
+}
+
+/// `a_2` -> `v_a_2`, `$0_2` -> `t_0_2`
+fn var_name(label: &str) -> String {
+    match label.strip_prefix('$') {
+        Some(temp) => format!("t_{}", temp),
+        None => format!("v_{}", label),
+    }
+}
+
+/// `.LC0` -> `LC0`
+fn str_name(label: &str) -> &str {
+    label.trim_start_matches('.')
+}
+
+fn block_name(bb_id: usize) -> String {
+    format!("bb{}", bb_id)
+}
+
+/// Signed overflow is undefined in C, and narrow unsigned operands are promoted to `int`,
+/// so `+`, `-`, `*` and `<<` are computed in an unsigned type of at least 32 bits and
+/// converted back by `assign`.
+fn wrapping_type(op: &BinOperator, ir_type: &IRType) -> Option<&'static str> {
+    match op {
+        BinOperator::Plus | BinOperator::Minus | BinOperator::Star | BinOperator::Shl => {}
+        _ => return None,
+    }
+    match ir_type {
+        IRType::I8 | IRType::I16 | IRType::I32 | IRType::U8 | IRType::U16 => Some("uint32_t"),
+        IRType::I64 | IRType::I128 | IRType::Isize => Some(match ir_type.byte_size(C_ADDR_SIZE) {
+            4 => "uint32_t",
+            8 => "uint64_t",
+            _ => "unsigned __int128",
+        }),
+        _ => None,
+    }
+}
+
+fn bin_op(op: &BinOperator) -> &'static str {
+    match op {
+        BinOperator::Plus => "+",
+        BinOperator::Minus => "-",
+        BinOperator::Star => "*",
+        BinOperator::Slash => "/",
+        BinOperator::Percent => "%",
+        BinOperator::Caret => "^",
+        BinOperator::And => "&",
+        BinOperator::Or => "|",
+        BinOperator::Shl => "<<",
+        BinOperator::Shr => ">>",
+        BinOperator::AndAnd => "&&",
+        BinOperator::OrOr => "||",
+        BinOperator::EqEq => "==",
+        BinOperator::Ne => "!=",
+        BinOperator::Gt => ">",
+        BinOperator::Lt => "<",
+        BinOperator::Ge => ">=",
+        BinOperator::Le => "<=",
+        BinOperator::As => unreachable!(),
+    }
+}
+
+fn operand(operand: &Operand) -> Result<String, RccError> {
+    Ok(match operand {
+        Operand::Bool(b) => (if *b { "1" } else { "0" }).to_string(),
+        Operand::Char(c) => (*c as u32).to_string(),
+        Operand::I8(i) => i.to_string(),
+        Operand::I16(i) => i.to_string(),
+        Operand::I32(i) => i.to_string(),
+        Operand::I64(i) => format!("INT64_C({})", i),
+        Operand::Isize(i) => format!("(intptr_t){}", i),
+        Operand::U8(i) => i.to_string(),
+        Operand::U16(i) => i.to_string(),
+        Operand::U32(i) => format!("{}u", i),
+        Operand::U64(i) => format!("UINT64_C({})", i),
+        Operand::Usize(i) => format!("(uintptr_t){}u", i),
+        Operand::I128(i) => format!(
+            "(__int128)(((unsigned __int128)UINT64_C({}) << 64) | UINT64_C({}))",
+            (*i as u128 >> 64) as u64,
+            *i as u64
+        ),
+        Operand::U128(i) => format!(
+            "(((unsigned __int128)UINT64_C({}) << 64) | UINT64_C({}))",
+            (*i >> 64) as u64,
+            *i as u64
+        ),
+        Operand::F32(f) if f.is_finite() => format!("{:?}f", f),
+        Operand::F64(f) if f.is_finite() => format!("{:?}", f),
+        Operand::F32(f) => format!("(float){}", non_finite(*f as f64)),
+        Operand::F64(f) => non_finite(*f),
+        Operand::Place(p) => match p.kind {
+            VarKind::LitConst => format!("(uintptr_t){}", str_name(&p.label)),
+            _ => var_name(&p.label),
+        },
+        Operand::FnLabel(fn_name) => mangle(fn_name),
+        Operand::Unit | Operand::Never | Operand::FnRetPlace(_) => {
+            return Err(format!("error in c code gen: invalid operand {:?}", operand).into())
+        }
+    })
+}
+
+/// C has no literals of `NaN` and infinities, they are constant expressions.
+fn non_finite(f: f64) -> String {
+    let numerator = if f.is_nan() {
+        "0.0"
+    } else if f > 0.0 {
+        "1.0"
+    } else {
+        "-1.0"
+    };
+    format!("({} / 0.0)", numerator)
+}
@@ -0,0 +1,469 @@
+//! ELF32 little endian relocatable object file.
+//!
+//! [ELF specification]: https://refspecs.linuxfoundation.org/elf/elf.pdf
+//! [RISC-V ELF psABI specification]: https://github.com/riscv/riscv-elf-psabi-doc/blob/master/riscv-elf.md
+use crate::rcc::RccError;
+use std::io::Write;
+
+pub const EM_RISCV: u16 = 243;
+
+pub const ET_REL: u16 = 1;
+pub const ET_EXEC: u16 = 2;
+
+pub const PT_LOAD: u32 = 1;
+
+pub const PF_X: u32 = 0x1;
+pub const PF_W: u32 = 0x2;
+pub const PF_R: u32 = 0x4;
+
+pub const SHT_PROGBITS: u32 = 1;
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_STRTAB: u32 = 3;
+pub const SHT_RELA: u32 = 4;
+pub const SHT_NOBITS: u32 = 8;
+
+pub const SHF_WRITE: u32 = 0x1;
+pub const SHF_ALLOC: u32 = 0x2;
+pub const SHF_EXECINSTR: u32 = 0x4;
+pub const SHF_INFO_LINK: u32 = 0x40;
+
+pub const STB_LOCAL: u8 = 0;
+pub const STB_GLOBAL: u8 = 1;
+
+pub const STT_NOTYPE: u8 = 0;
+pub const STT_FUNC: u8 = 2;
+pub const STT_SECTION: u8 = 3;
+
+pub const SHN_UNDEF: u16 = 0;
+
+pub const R_RISCV_32: u32 = 1;
+pub const R_RISCV_BRANCH: u32 = 16;
+pub const R_RISCV_JAL: u32 = 17;
+pub const R_RISCV_CALL_PLT: u32 = 19;
+pub const R_RISCV_PCREL_HI20: u32 = 23;
+pub const R_RISCV_PCREL_LO12_I: u32 = 24;
+pub const R_RISCV_HI20: u32 = 26;
+pub const R_RISCV_LO12_I: u32 = 27;
+pub const R_RISCV_LO12_S: u32 = 28;
+
+pub const ELF_HEADER_SIZE: u32 = 52;
+pub const PROGRAM_HEADER_SIZE: u32 = 32;
+pub const SECTION_HEADER_SIZE: u32 = 40;
+pub const SYMBOL_SIZE: u32 = 16;
+const RELA_SIZE: u32 = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SectionKind {
+    Text,
+    Data,
+    ROData,
+    Bss,
+}
+
+impl SectionKind {
+    pub fn from_name(name: &str) -> Option<SectionKind> {
+        match name {
+            ".text" => Some(SectionKind::Text),
+            ".data" => Some(SectionKind::Data),
+            ".rodata" => Some(SectionKind::ROData),
+            ".bss" => Some(SectionKind::Bss),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SectionKind::Text => ".text",
+            SectionKind::Data => ".data",
+            SectionKind::ROData => ".rodata",
+            SectionKind::Bss => ".bss",
+        }
+    }
+
+    pub fn sh_type(&self) -> u32 {
+        match self {
+            SectionKind::Bss => SHT_NOBITS,
+            _ => SHT_PROGBITS,
+        }
+    }
+
+    pub fn sh_flags(&self) -> u32 {
+        match self {
+            SectionKind::Text => SHF_ALLOC | SHF_EXECINSTR,
+            SectionKind::Data | SectionKind::Bss => SHF_ALLOC | SHF_WRITE,
+            SectionKind::ROData => SHF_ALLOC,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Section {
+    pub kind: SectionKind,
+    /// contents of the section, `.bss` is zero-filled and not written to the file
+    pub data: Vec<u8>,
+    pub align: u32,
+    pub relocations: Vec<Relocation>,
+}
+
+impl Section {
+    pub fn new(kind: SectionKind) -> Section {
+        Section {
+            kind,
+            data: vec![],
+            align: if kind == SectionKind::Text { 4 } else { 1 },
+            relocations: vec![],
+        }
+    }
+
+    pub fn size(&self) -> u32 {
+        self.data.len() as u32
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    /// `None` if the symbol is undefined
+    pub section: Option<usize>,
+    pub value: u32,
+    pub size: u32,
+    pub binding: u8,
+    pub sym_type: u8,
+}
+
+impl Symbol {
+    pub fn is_global(&self) -> bool {
+        self.binding == STB_GLOBAL
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relocation {
+    pub offset: u32,
+    /// index of `ObjectFile::symbols`
+    pub symbol: usize,
+    pub r_type: u32,
+    pub addend: i32,
+}
+
+/// In-memory relocatable object, the section symbols are generated in `write`.
+#[derive(Debug, Default)]
+pub struct ObjectFile {
+    pub sections: Vec<Section>,
+    pub symbols: Vec<Symbol>,
+}
+
+impl ObjectFile {
+    pub fn new() -> ObjectFile {
+        ObjectFile::default()
+    }
+
+    pub fn find_symbol(&self, name: &str) -> Option<usize> {
+        self.symbols.iter().position(|s| s.name == name)
+    }
+
+    /// Layout:
+    ///
+    /// ```
+    /// +-----------------+
+    /// |   ELF header    |
+    /// +-----------------+
+    /// |    sections     |
+    /// +-----------------+
+    /// |  .rela.<sec>... |
+    /// +-----------------+
+    /// |     .symtab     |
+    /// +-----------------+
+    /// |     .strtab     |
+    /// +-----------------+
+    /// |    .shstrtab    |
+    /// +-----------------+
+    /// | section headers |
+    /// +-----------------+
+    /// ```
+    pub fn write<W: Write>(&self, output: &mut W) -> Result<(), RccError> {
+        // section header index of `self.sections[i]` is `i + 1`
+        let mut shstrtab = StrTab::new();
+        let mut strtab = StrTab::new();
+
+        // symbol table: null, section symbols, local symbols, global symbols
+        let mut sym_entries: Vec<(u32, u32, u32, u8, u16)> = vec![(0, 0, 0, 0, 0)];
+        let mut sym_index = vec![0; self.symbols.len()];
+        for i in 0..self.sections.len() {
+            sym_entries.push((0, 0, 0, STT_SECTION, (i + 1) as u16));
+        }
+        let locals = self
+            .symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| !s.is_global());
+        let globals = self
+            .symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.is_global());
+        let first_global = sym_entries.len() + locals.clone().count();
+        for (i, sym) in locals.chain(globals) {
+            sym_index[i] = sym_entries.len();
+            let shndx = sym.section.map_or(SHN_UNDEF, |s| (s + 1) as u16);
+            sym_entries.push((
+                strtab.add(&sym.name),
+                sym.value,
+                sym.size,
+                (sym.binding << 4) | sym.sym_type,
+                shndx,
+            ));
+        }
+
+        let mut headers = vec![SectionHeader::default()];
+        let mut offset = ELF_HEADER_SIZE;
+        for section in self.sections.iter() {
+            offset = align_to(offset, section.align);
+            headers.push(SectionHeader {
+                name: shstrtab.add(section.kind.name()),
+                sh_type: section.kind.sh_type(),
+                flags: section.kind.sh_flags(),
+                offset,
+                size: section.size(),
+                align: section.align,
+                ..SectionHeader::default()
+            });
+            if section.kind != SectionKind::Bss {
+                offset += section.size();
+            }
+        }
+        let symtab_index = (self.sections.len()
+            + self
+                .sections
+                .iter()
+                .filter(|s| !s.relocations.is_empty())
+                .count()
+            + 1) as u32;
+        for (i, section) in self.sections.iter().enumerate() {
+            if section.relocations.is_empty() {
+                continue;
+            }
+            offset = align_to(offset, 4);
+            let size = section.relocations.len() as u32 * RELA_SIZE;
+            headers.push(SectionHeader {
+                name: shstrtab.add(&format!(".rela{}", section.kind.name())),
+                sh_type: SHT_RELA,
+                flags: SHF_INFO_LINK,
+                offset,
+                size,
+                link: symtab_index,
+                info: (i + 1) as u32,
+                align: 4,
+                entsize: RELA_SIZE,
+                ..SectionHeader::default()
+            });
+            offset += size;
+        }
+        offset = align_to(offset, 4);
+        let symtab_offset = offset;
+        headers.push(SectionHeader {
+            name: shstrtab.add(".symtab"),
+            sh_type: SHT_SYMTAB,
+            offset,
+            size: sym_entries.len() as u32 * SYMBOL_SIZE,
+            link: symtab_index + 1,
+            info: first_global as u32,
+            align: 4,
+            entsize: SYMBOL_SIZE,
+            ..SectionHeader::default()
+        });
+        offset += sym_entries.len() as u32 * SYMBOL_SIZE;
+        headers.push(SectionHeader {
+            name: shstrtab.add(".strtab"),
+            sh_type: SHT_STRTAB,
+            offset,
+            size: strtab.data.len() as u32,
+            align: 1,
+            ..SectionHeader::default()
+        });
+        offset += strtab.data.len() as u32;
+        let shstrtab_name = shstrtab.add(".shstrtab");
+        headers.push(SectionHeader {
+            name: shstrtab_name,
+            sh_type: SHT_STRTAB,
+            offset,
+            size: shstrtab.data.len() as u32,
+            align: 1,
+            ..SectionHeader::default()
+        });
+        offset += shstrtab.data.len() as u32;
+        let shoff = align_to(offset, 4);
+
+        let mut buf = Vec::with_capacity(shoff as usize);
+        write_elf_header(&mut buf, ET_REL, 0, 0, shoff, 0, headers.len() as u16);
+        for (i, section) in self.sections.iter().enumerate() {
+            if section.kind != SectionKind::Bss {
+                pad_to(&mut buf, headers[i + 1].offset);
+                buf.extend_from_slice(&section.data);
+            }
+        }
+        for section in self.sections.iter() {
+            if section.relocations.is_empty() {
+                continue;
+            }
+            let aligned = align_to(buf.len() as u32, 4);
+            pad_to(&mut buf, aligned);
+            for rela in section.relocations.iter() {
+                let r_info = ((sym_index[rela.symbol] as u32) << 8) | rela.r_type;
+                push_u32(&mut buf, rela.offset);
+                push_u32(&mut buf, r_info);
+                push_u32(&mut buf, rela.addend as u32);
+            }
+        }
+        pad_to(&mut buf, symtab_offset);
+        for (name, value, size, info, shndx) in sym_entries {
+            push_u32(&mut buf, name);
+            push_u32(&mut buf, value);
+            push_u32(&mut buf, size);
+            buf.push(info);
+            buf.push(0);
+            push_u16(&mut buf, shndx);
+        }
+        buf.extend_from_slice(&strtab.data);
+        buf.extend_from_slice(&shstrtab.data);
+        pad_to(&mut buf, shoff);
+        for header in headers.iter() {
+            header.write(&mut buf);
+        }
+        output.write_all(&buf)?;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct SectionHeader {
+    pub name: u32,
+    pub sh_type: u32,
+    pub flags: u32,
+    pub addr: u32,
+    pub offset: u32,
+    pub size: u32,
+    pub link: u32,
+    pub info: u32,
+    pub align: u32,
+    pub entsize: u32,
+}
+
+impl SectionHeader {
+    pub fn write(&self, buf: &mut Vec<u8>) {
+        for field in [
+            self.name,
+            self.sh_type,
+            self.flags,
+            self.addr,
+            self.offset,
+            self.size,
+            self.link,
+            self.info,
+            self.align,
+            self.entsize,
+        ] {
+            push_u32(buf, field);
+        }
+    }
+}
+
+pub struct ProgramHeader {
+    pub p_type: u32,
+    pub offset: u32,
+    pub vaddr: u32,
+    pub file_size: u32,
+    pub mem_size: u32,
+    pub flags: u32,
+    pub align: u32,
+}
+
+impl ProgramHeader {
+    pub fn write(&self, buf: &mut Vec<u8>) {
+        for field in [
+            self.p_type,
+            self.offset,
+            self.vaddr,
+            // p_paddr
+            self.vaddr,
+            self.file_size,
+            self.mem_size,
+            self.flags,
+            self.align,
+        ] {
+            push_u32(buf, field);
+        }
+    }
+}
+
+/// String table, starts with an empty string.
+pub struct StrTab {
+    pub data: Vec<u8>,
+}
+
+impl StrTab {
+    pub fn new() -> StrTab {
+        StrTab { data: vec![0] }
+    }
+
+    /// Return the offset of `s`.
+    pub fn add(&mut self, s: &str) -> u32 {
+        if s.is_empty() {
+            return 0;
+        }
+        let offset = self.data.len() as u32;
+        self.data.extend_from_slice(s.as_bytes());
+        self.data.push(0);
+        offset
+    }
+}
+
+pub fn write_elf_header(
+    buf: &mut Vec<u8>,
+    e_type: u16,
+    entry: u32,
+    phoff: u32,
+    shoff: u32,
+    phnum: u16,
+    shnum: u16,
+) {
+    // magic, ELFCLASS32, ELFDATA2LSB, EV_CURRENT, ELFOSABI_NONE
+    buf.extend_from_slice(&[0x7f, b'E', b'L', b'F', 1, 1, 1, 0]);
+    buf.extend_from_slice(&[0; 8]);
+    push_u16(buf, e_type);
+    push_u16(buf, EM_RISCV);
+    push_u32(buf, 1);
+    push_u32(buf, entry);
+    push_u32(buf, phoff);
+    push_u32(buf, shoff);
+    // e_flags: soft float ABI, no RVC
+    push_u32(buf, 0);
+    push_u16(buf, ELF_HEADER_SIZE as u16);
+    push_u16(buf, if phnum == 0 { 0 } else { PROGRAM_HEADER_SIZE as u16 });
+    push_u16(buf, phnum);
+    push_u16(buf, SECTION_HEADER_SIZE as u16);
+    push_u16(buf, shnum);
+    // e_shstrndx: `.shstrtab` is always the last section
+    push_u16(buf, if shnum == 0 { 0 } else { shnum - 1 });
+}
+
+pub fn align_to(offset: u32, align: u32) -> u32 {
+    if align <= 1 {
+        offset
+    } else {
+        offset.div_ceil(align) * align
+    }
+}
+
+pub fn pad_to(buf: &mut Vec<u8>, offset: u32) {
+    debug_assert!(buf.len() <= offset as usize);
+    buf.resize(offset as usize, 0);
+}
+
+pub fn push_u16(buf: &mut Vec<u8>, v: u16) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+pub fn push_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
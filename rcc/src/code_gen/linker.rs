@@ -0,0 +1,497 @@
+//! Static linker of RISC-V ELF32 relocatable objects produced by `riscv32_asm`.
+//!
+//! All `.text`, `.rodata`, `.data` and `.bss` sections are merged into one
+//! read-execute segment (`.text` and `.rodata`) and one read-write segment
+//! (`.data` and `.bss`), the entry point is `_start` of the bundled crt0.
+use crate::code_gen::elf::*;
+use crate::code_gen::riscv32_asm::{self, b_imm, j_imm};
+use crate::rcc::RccError;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Virtual address of the first segment, the same as GNU ld for RISC-V.
+pub const BASE_ADDR: u32 = 0x10000;
+const PAGE_SIZE: u32 = 0x1000;
+const ENTRY: &str = "_start";
+
+const CRT0: &str = include_str!("runtime/crt0.S");
+const LIBC: &str = include_str!("runtime/libc.S");
+const LIBGCC: &str = include_str!("runtime/libgcc.S");
+const SOFTFP: &str = include_str!("runtime/softfp.S");
+
+const SECTION_ORDER: [SectionKind; 4] = [
+    SectionKind::Text,
+    SectionKind::ROData,
+    SectionKind::Data,
+    SectionKind::Bss,
+];
+
+/// Link `obj` with crt0, the libc shim and 64-bit arithmetic of libgcc.
+pub fn link_with_runtime(obj: ObjectFile) -> Result<Executable, RccError> {
+    let crt0 = riscv32_asm::assemble(CRT0)?;
+    let libc = riscv32_asm::assemble(LIBC)?;
+    let libgcc = riscv32_asm::assemble(LIBGCC)?;
+    let softfp = riscv32_asm::assemble(SOFTFP)?;
+    link(vec![crt0, obj], vec![libc, libgcc, softfp])
+}
+
+/// Objects of `libs` are linked only if they define an undefined symbol,
+/// like the members of a static library.
+pub fn link(mut objects: Vec<ObjectFile>, libs: Vec<ObjectFile>) -> Result<Executable, RccError> {
+    let mut libs: Vec<Option<ObjectFile>> = libs.into_iter().map(Some).collect();
+    loop {
+        let undefined = undefined_symbols(&objects);
+        let pos = libs.iter().position(|lib| match lib {
+            Some(lib) => lib
+                .symbols
+                .iter()
+                .any(|s| s.is_global() && s.section.is_some() && undefined.contains(&s.name)),
+            None => false,
+        });
+        match pos {
+            Some(pos) => objects.push(libs[pos].take().unwrap()),
+            None => break,
+        }
+    }
+    Linker::new(objects).link()
+}
+
+fn undefined_symbols(objects: &[ObjectFile]) -> Vec<String> {
+    let defined: Vec<&String> = objects
+        .iter()
+        .flat_map(|o| o.symbols.iter())
+        .filter(|s| s.is_global() && s.section.is_some())
+        .map(|s| &s.name)
+        .collect();
+    objects
+        .iter()
+        .flat_map(|o| o.symbols.iter())
+        .filter(|s| s.section.is_none() && !defined.contains(&&s.name))
+        .map(|s| s.name.clone())
+        .collect()
+}
+
+pub struct OutputSection {
+    pub kind: SectionKind,
+    pub addr: u32,
+    pub offset: u32,
+    pub align: u32,
+    pub data: Vec<u8>,
+}
+
+pub struct Executable {
+    pub entry: u32,
+    pub sections: Vec<OutputSection>,
+    /// <name, address, is global>
+    pub symbols: Vec<(String, u32, bool)>,
+    pub program_headers: Vec<ProgramHeader>,
+}
+
+struct Linker {
+    objects: Vec<ObjectFile>,
+    output: Vec<OutputSection>,
+    /// <(object, section), (output section, offset in output section)>
+    placements: HashMap<(usize, usize), (usize, u32)>,
+    globals: HashMap<String, u32>,
+}
+
+impl Linker {
+    fn new(objects: Vec<ObjectFile>) -> Linker {
+        Linker {
+            objects,
+            output: vec![],
+            placements: HashMap::new(),
+            globals: HashMap::new(),
+        }
+    }
+
+    fn link(mut self) -> Result<Executable, RccError> {
+        self.merge_sections();
+        let program_headers = self.layout();
+        self.resolve_globals()?;
+        self.relocate()?;
+
+        let entry = match self.globals.get(ENTRY) {
+            Some(entry) => *entry,
+            None => return Err(format!("undefined entry symbol `{}`", ENTRY).into()),
+        };
+        let mut symbols = vec![];
+        for (obj_idx, obj) in self.objects.iter().enumerate() {
+            for sym in obj.symbols.iter().filter(|s| !s.name.starts_with(".L")) {
+                if let Some(sec_idx) = sym.section {
+                    let addr = self.section_addr(obj_idx, sec_idx) + sym.value;
+                    symbols.push((sym.name.clone(), addr, sym.is_global()));
+                }
+            }
+        }
+        Ok(Executable {
+            entry,
+            sections: self.output,
+            symbols,
+            program_headers,
+        })
+    }
+
+    /// Concatenate the sections of the same kind.
+    fn merge_sections(&mut self) {
+        for kind in SECTION_ORDER.iter() {
+            let mut out = OutputSection {
+                kind: *kind,
+                addr: 0,
+                offset: 0,
+                align: 1,
+                data: vec![],
+            };
+            for (obj_idx, obj) in self.objects.iter().enumerate() {
+                for (sec_idx, section) in obj.sections.iter().enumerate() {
+                    if section.kind != *kind {
+                        continue;
+                    }
+                    let offset = align_to(out.data.len() as u32, section.align);
+                    out.data.resize(offset as usize, 0);
+                    out.data.extend_from_slice(&section.data);
+                    out.align = out.align.max(section.align);
+                    self.placements
+                        .insert((obj_idx, sec_idx), (self.output.len(), offset));
+                }
+            }
+            if !out.data.is_empty() {
+                self.output.push(out);
+            }
+        }
+    }
+
+    /// Assign addresses and file offsets, the first segment maps the ELF header too.
+    fn layout(&mut self) -> Vec<ProgramHeader> {
+        let has_rw = self
+            .output
+            .iter()
+            .any(|s| matches!(s.kind, SectionKind::Data | SectionKind::Bss));
+        let phnum = if has_rw { 2 } else { 1 };
+        let mut offset = ELF_HEADER_SIZE + PROGRAM_HEADER_SIZE * phnum;
+        let mut headers = vec![];
+        let mut rx_end = offset;
+        let mut rw: Option<(u32, u32, u32)> = None;
+        for section in self.output.iter_mut() {
+            offset = align_to(offset, section.align);
+            section.offset = offset;
+            match section.kind {
+                SectionKind::Text | SectionKind::ROData => {
+                    section.addr = BASE_ADDR + offset;
+                    offset += section.data.len() as u32;
+                    rx_end = offset;
+                }
+                SectionKind::Data | SectionKind::Bss => {
+                    // the address is congruent to the file offset modulo the page size
+                    let (rw_offset, rw_addr, _) = *rw.get_or_insert_with(|| {
+                        let addr = align_to(BASE_ADDR + offset, PAGE_SIZE) + offset % PAGE_SIZE;
+                        (offset, addr, 0)
+                    });
+                    section.addr = rw_addr + (offset - rw_offset);
+                    if section.kind == SectionKind::Data {
+                        offset += section.data.len() as u32;
+                    }
+                    let end = section.addr + section.data.len() as u32;
+                    rw.as_mut().unwrap().2 = end;
+                }
+            }
+        }
+        headers.push(ProgramHeader {
+            p_type: PT_LOAD,
+            offset: 0,
+            vaddr: BASE_ADDR,
+            file_size: rx_end,
+            mem_size: rx_end,
+            flags: PF_R | PF_X,
+            align: PAGE_SIZE,
+        });
+        if let Some((rw_offset, rw_addr, rw_end)) = rw {
+            let file_size = self
+                .output
+                .iter()
+                .filter(|s| s.kind == SectionKind::Data)
+                .map(|s| s.offset + s.data.len() as u32 - rw_offset)
+                .next()
+                .unwrap_or(0);
+            headers.push(ProgramHeader {
+                p_type: PT_LOAD,
+                offset: rw_offset,
+                vaddr: rw_addr,
+                file_size,
+                mem_size: rw_end - rw_addr,
+                flags: PF_R | PF_W,
+                align: PAGE_SIZE,
+            });
+        }
+        headers
+    }
+
+    fn section_addr(&self, obj_idx: usize, sec_idx: usize) -> u32 {
+        let (out_idx, offset) = self.placements[&(obj_idx, sec_idx)];
+        self.output[out_idx].addr + offset
+    }
+
+    fn resolve_globals(&mut self) -> Result<(), RccError> {
+        for (obj_idx, obj) in self.objects.iter().enumerate() {
+            for sym in obj.symbols.iter() {
+                if let (true, Some(sec_idx)) = (sym.is_global(), sym.section) {
+                    let addr = self.section_addr(obj_idx, sec_idx) + sym.value;
+                    if self.globals.insert(sym.name.clone(), addr).is_some() {
+                        return Err(format!("multiple definition of `{}`", sym.name).into());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn symbol_addr(&self, obj_idx: usize, symbol: &Symbol) -> Result<u32, RccError> {
+        match symbol.section {
+            Some(sec_idx) => Ok(self.section_addr(obj_idx, sec_idx) + symbol.value),
+            None => match self.globals.get(&symbol.name) {
+                Some(addr) => Ok(*addr),
+                None => Err(format!("undefined reference to `{}`", symbol.name).into()),
+            },
+        }
+    }
+
+    fn relocate(&mut self) -> Result<(), RccError> {
+        for obj_idx in 0..self.objects.len() {
+            // pc-relative offsets of `R_RISCV_PCREL_HI20`, <address of auipc, offset>
+            let mut pcrel_hi: HashMap<u32, i32> = HashMap::new();
+            let mut patches = vec![];
+            for (sec_idx, section) in self.objects[obj_idx].sections.iter().enumerate() {
+                let (out_idx, out_offset) = self.placements[&(obj_idx, sec_idx)];
+                for rela in section.relocations.iter() {
+                    let symbol = &self.objects[obj_idx].symbols[rela.symbol];
+                    let s = self.symbol_addr(obj_idx, symbol)?;
+                    let p = self.output[out_idx].addr + out_offset + rela.offset;
+                    let value = s.wrapping_add(rela.addend as u32);
+                    if rela.r_type == R_RISCV_PCREL_HI20 {
+                        pcrel_hi.insert(p, value.wrapping_sub(p) as i32);
+                    }
+                    patches.push((
+                        out_idx,
+                        out_offset + rela.offset,
+                        rela.r_type,
+                        p,
+                        value,
+                        symbol.name.clone(),
+                    ));
+                }
+            }
+            for (out_idx, offset, r_type, p, value, name) in patches {
+                let data = &mut self.output[out_idx].data;
+                let pc_offset = value.wrapping_sub(p) as i32;
+                let inst = read_u32(data, offset);
+                match r_type {
+                    R_RISCV_32 => write_u32(data, offset, value),
+                    R_RISCV_BRANCH => {
+                        if !(-4096..4096).contains(&pc_offset) {
+                            return Err(format!("branch to `{}` is out of range", name).into());
+                        }
+                        write_u32(data, offset, (inst & 0x01fff07f) | b_imm(pc_offset));
+                    }
+                    R_RISCV_JAL => {
+                        if !(-(1 << 20)..1 << 20).contains(&pc_offset) {
+                            return Err(format!("jump to `{}` is out of range", name).into());
+                        }
+                        write_u32(data, offset, (inst & 0xfff) | j_imm(pc_offset));
+                    }
+                    R_RISCV_CALL_PLT => {
+                        let (hi, lo) = split_hi_lo(pc_offset);
+                        write_u32(data, offset, patch_u(inst, hi));
+                        let jalr = read_u32(data, offset + 4);
+                        write_u32(data, offset + 4, patch_i(jalr, lo));
+                    }
+                    R_RISCV_PCREL_HI20 => {
+                        write_u32(data, offset, patch_u(inst, split_hi_lo(pc_offset).0));
+                    }
+                    R_RISCV_PCREL_LO12_I => {
+                        // `value` is the address of the `auipc`
+                        let hi_offset = match pcrel_hi.get(&value) {
+                            Some(hi_offset) => *hi_offset,
+                            None => {
+                                return Err(format!("`{}` is not a R_RISCV_PCREL_HI20", name).into())
+                            }
+                        };
+                        write_u32(data, offset, patch_i(inst, split_hi_lo(hi_offset).1));
+                    }
+                    R_RISCV_HI20 => {
+                        write_u32(data, offset, patch_u(inst, split_hi_lo(value as i32).0));
+                    }
+                    R_RISCV_LO12_I => {
+                        write_u32(data, offset, patch_i(inst, split_hi_lo(value as i32).1));
+                    }
+                    R_RISCV_LO12_S => {
+                        write_u32(data, offset, patch_s(inst, split_hi_lo(value as i32).1));
+                    }
+                    _ => return Err(format!("unsupported relocation type {}", r_type).into()),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Executable {
+    /// Layout:
+    ///
+    /// ```
+    /// +-----------------+ <---- BASE_ADDR
+    /// |   ELF header    |
+    /// +-----------------+
+    /// | program headers |
+    /// +-----------------+
+    /// |  .text .rodata  |
+    /// +-----------------+
+    /// |      .data      |
+    /// +-----------------+
+    /// |     .symtab     |
+    /// +-----------------+
+    /// |     .strtab     |
+    /// +-----------------+
+    /// |    .shstrtab    |
+    /// +-----------------+
+    /// | section headers |
+    /// +-----------------+
+    /// ```
+    pub fn write<W: Write>(&self, output: &mut W) -> Result<(), RccError> {
+        let mut shstrtab = StrTab::new();
+        let mut strtab = StrTab::new();
+        let mut headers = vec![SectionHeader::default()];
+        let mut buf = vec![];
+        // section headers, symbols and program headers are written later
+        pad_to(
+            &mut buf,
+            ELF_HEADER_SIZE + PROGRAM_HEADER_SIZE * self.program_headers.len() as u32,
+        );
+        for section in self.sections.iter() {
+            headers.push(SectionHeader {
+                name: shstrtab.add(section.kind.name()),
+                sh_type: section.kind.sh_type(),
+                flags: section.kind.sh_flags(),
+                addr: section.addr,
+                offset: section.offset,
+                size: section.data.len() as u32,
+                align: section.align,
+                ..SectionHeader::default()
+            });
+            if section.kind != SectionKind::Bss {
+                pad_to(&mut buf, section.offset);
+                buf.extend_from_slice(&section.data);
+            }
+        }
+
+        let mut symbols: Vec<&(String, u32, bool)> = self.symbols.iter().filter(|s| !s.2).collect();
+        let first_global = symbols.len() + 1;
+        symbols.extend(self.symbols.iter().filter(|s| s.2));
+        let aligned = align_to(buf.len() as u32, 4);
+        pad_to(&mut buf, aligned);
+        headers.push(SectionHeader {
+            name: shstrtab.add(".symtab"),
+            sh_type: SHT_SYMTAB,
+            offset: aligned,
+            size: (symbols.len() as u32 + 1) * SYMBOL_SIZE,
+            link: headers.len() as u32 + 1,
+            info: first_global as u32,
+            align: 4,
+            entsize: SYMBOL_SIZE,
+            ..SectionHeader::default()
+        });
+        buf.extend_from_slice(&[0; SYMBOL_SIZE as usize]);
+        for (name, addr, is_global) in symbols {
+            let shndx = self
+                .sections
+                .iter()
+                .position(|s| s.addr <= *addr && *addr <= s.addr + s.data.len() as u32)
+                .map_or(SHN_UNDEF, |i| (i + 1) as u16);
+            push_u32(&mut buf, strtab.add(name));
+            push_u32(&mut buf, *addr);
+            push_u32(&mut buf, 0);
+            buf.push(if *is_global {
+                STB_GLOBAL << 4
+            } else {
+                STB_LOCAL << 4
+            });
+            buf.push(0);
+            push_u16(&mut buf, shndx);
+        }
+        headers.push(SectionHeader {
+            name: shstrtab.add(".strtab"),
+            sh_type: SHT_STRTAB,
+            offset: buf.len() as u32,
+            size: strtab.data.len() as u32,
+            align: 1,
+            ..SectionHeader::default()
+        });
+        buf.extend_from_slice(&strtab.data);
+        let name = shstrtab.add(".shstrtab");
+        headers.push(SectionHeader {
+            name,
+            sh_type: SHT_STRTAB,
+            offset: buf.len() as u32,
+            size: shstrtab.data.len() as u32,
+            align: 1,
+            ..SectionHeader::default()
+        });
+        buf.extend_from_slice(&shstrtab.data);
+        let shoff = align_to(buf.len() as u32, 4);
+        pad_to(&mut buf, shoff);
+        for header in headers.iter() {
+            header.write(&mut buf);
+        }
+
+        let mut elf_header = vec![];
+        write_elf_header(
+            &mut elf_header,
+            ET_EXEC,
+            self.entry,
+            ELF_HEADER_SIZE,
+            shoff,
+            self.program_headers.len() as u16,
+            headers.len() as u16,
+        );
+        for ph in self.program_headers.iter() {
+            ph.write(&mut elf_header);
+        }
+        buf[..elf_header.len()].copy_from_slice(&elf_header);
+        output.write_all(&buf)?;
+        Ok(())
+    }
+}
+
+/// Split `value` to the upper 20 bits and the lower 12 bits, the lower bits are sign-extended.
+fn split_hi_lo(value: i32) -> (u32, i32) {
+    let lo = (value << 20) >> 20;
+    let hi = (value as u32).wrapping_sub(lo as u32) >> 12;
+    (hi, lo)
+}
+
+fn patch_u(inst: u32, hi: u32) -> u32 {
+    (inst & 0xfff) | hi << 12
+}
+
+fn patch_i(inst: u32, lo: i32) -> u32 {
+    (inst & 0x000fffff) | ((lo as u32) & 0xfff) << 20
+}
+
+fn patch_s(inst: u32, lo: i32) -> u32 {
+    let lo = lo as u32;
+    (inst & 0x01fff07f) | ((lo >> 5) & 0x7f) << 25 | (lo & 0x1f) << 7
+}
+
+fn read_u32(data: &[u8], offset: u32) -> u32 {
+    let offset = offset as usize;
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn write_u32(data: &mut [u8], offset: u32, value: u32) {
+    let offset = offset as usize;
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
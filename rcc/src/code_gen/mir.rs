@@ -0,0 +1,475 @@
+//! Machine IR of RISC-V: instructions with typed opcodes in machine basic blocks.
+//!
+//! `FuncCodeGen` lowers the CFG IR into machine IR with virtual registers,
+//! `reg_alloc` replaces them by physical registers, then the peephole optimiser
+//! rewrites the instructions before they are printed as assembly language or
+//! encoded into an object file.
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use strenum::StrEnum;
+
+/// RISC-V integer register `x0` - `x31`, or floating-point register `f0` - `f31` numbered from 32
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Reg(pub u8);
+
+const ABI_NAMES: [&str; 64] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6", "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7", "fs0", "fs1", "fa0",
+    "fa1", "fa2", "fa3", "fa4", "fa5", "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7",
+    "fs8", "fs9", "fs10", "fs11", "ft8", "ft9", "ft10", "ft11",
+];
+
+impl Reg {
+    pub const ZERO: Reg = Reg(0);
+    pub const RA: Reg = Reg(1);
+    pub const SP: Reg = Reg(2);
+    /// s0, the frame pointer
+    pub const FP: Reg = Reg(8);
+    pub const A0: Reg = Reg(10);
+    pub const A4: Reg = Reg(14);
+    pub const A5: Reg = Reg(15);
+    pub const FA0: Reg = Reg(42);
+    pub const FA4: Reg = Reg(46);
+    pub const FA5: Reg = Reg(47);
+
+    /// Argument register `a{i}`
+    pub fn arg(i: usize) -> Reg {
+        debug_assert!(i < 8);
+        Reg(10 + i as u8)
+    }
+
+    /// Floating-point argument register `fa{i}`
+    pub fn fp_arg(i: usize) -> Reg {
+        debug_assert!(i < 8);
+        Reg(42 + i as u8)
+    }
+
+    /// Registers which are not preserved across calls
+    pub fn is_caller_saved(&self) -> bool {
+        matches!(self.0, 1 | 5..=7 | 10..=17 | 28..=39 | 42..=49 | 60..=63)
+    }
+}
+
+impl Display for Reg {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", ABI_NAMES[self.0 as usize])
+    }
+}
+
+/// Physical register or virtual register which is not allocated yet
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MReg {
+    Phys(Reg),
+    Virt(u32),
+}
+
+impl From<Reg> for MReg {
+    fn from(reg: Reg) -> Self {
+        MReg::Phys(reg)
+    }
+}
+
+impl Display for MReg {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MReg::Phys(reg) => write!(f, "{}", reg),
+            MReg::Virt(id) => write!(f, "%{}", id),
+        }
+    }
+}
+
+#[derive(StrEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LoadOp {
+    Lb,
+    Lh,
+    Lw,
+    Lbu,
+    Lhu,
+    Flw,
+    Fld,
+}
+
+#[derive(StrEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StoreOp {
+    Sb,
+    Sh,
+    Sw,
+    Fsw,
+    Fsd,
+}
+
+/// Register-register operations of RV32IM
+#[derive(StrEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AluOp {
+    Add,
+    Sub,
+    Mul,
+    Mulh,
+    Mulhsu,
+    Mulhu,
+    Div,
+    Divu,
+    Rem,
+    Remu,
+    And,
+    Or,
+    Xor,
+    Sll,
+    Srl,
+    Sra,
+    Slt,
+    Sltu,
+}
+
+/// Register-immediate operations of RV32I
+#[derive(StrEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AluImmOp {
+    Addi,
+    Andi,
+    Ori,
+    Xori,
+    Slli,
+    Srli,
+    Srai,
+    Slti,
+    Sltiu,
+}
+
+/// Formats of floating-point instructions: single, double, signed word and unsigned word
+#[derive(StrEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FpFmt {
+    S,
+    D,
+    W,
+    Wu,
+}
+
+/// Register-register operations of RV32F and RV32D,
+/// `feq`, `flt` and `fle` write integer registers.
+#[derive(StrEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FpOp {
+    Fadd,
+    Fsub,
+    Fmul,
+    Fdiv,
+    Fsgnj,
+    Fsgnjn,
+    Fsgnjx,
+    Fmin,
+    Fmax,
+    Feq,
+    Flt,
+    Fle,
+}
+
+/// Conditional branches, `bgt`, `ble`, `bgtu` and `bleu` are pseudo instructions
+/// with swapped operands.
+#[derive(StrEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BranchOp {
+    Beq,
+    Bne,
+    Blt,
+    Bge,
+    Bltu,
+    Bgeu,
+    Bgt,
+    Ble,
+    Bgtu,
+    Bleu,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MachineInst {
+    Li {
+        rd: MReg,
+        imm: i64,
+    },
+    Mv {
+        rd: MReg,
+        rs: MReg,
+    },
+    Load {
+        op: LoadOp,
+        rd: MReg,
+        offset: i32,
+        base: MReg,
+    },
+    Store {
+        op: StoreOp,
+        rs: MReg,
+        offset: i32,
+        base: MReg,
+    },
+    Op {
+        op: AluOp,
+        rd: MReg,
+        rs1: MReg,
+        rs2: MReg,
+    },
+    OpImm {
+        op: AluImmOp,
+        rd: MReg,
+        rs1: MReg,
+        imm: i64,
+    },
+    /// Load the address of a symbol
+    La {
+        rd: MReg,
+        symbol: String,
+    },
+    FOp {
+        op: FpOp,
+        fmt: FpFmt,
+        rd: MReg,
+        rs1: MReg,
+        rs2: MReg,
+    },
+    /// `fmv.s` and `fmv.d`
+    FMv {
+        fmt: FpFmt,
+        rd: MReg,
+        rs: MReg,
+    },
+    /// Convert `rs` of format `from` to `rd` of format `to`,
+    /// conversions to integers round towards zero.
+    FCvt {
+        to: FpFmt,
+        from: FpFmt,
+        rd: MReg,
+        rs: MReg,
+    },
+    Branch {
+        op: BranchOp,
+        rs1: MReg,
+        rs2: MReg,
+        target: String,
+    },
+    J(String),
+    /// `jr rs` followed by the table of jumps to `targets` at `label`,
+    /// `rs` is the address of an entry in the table
+    JumpTable {
+        rs: MReg,
+        label: String,
+        targets: Vec<String>,
+    },
+    /// The first `arg_count` argument registers and `fp_arg_count` floating-point
+    /// argument registers are read by the callee
+    Call {
+        callee: String,
+        arg_count: usize,
+        fp_arg_count: usize,
+    },
+    Ret,
+    /// Assembler directive like `.loc\t1 2 0`, it is not an instruction
+    Directive(String),
+}
+
+impl MachineInst {
+    /// Register written by this instruction, registers clobbered by calls are not included.
+    pub fn def(&self) -> Option<MReg> {
+        match self {
+            Self::Li { rd, .. }
+            | Self::Mv { rd, .. }
+            | Self::Load { rd, .. }
+            | Self::Op { rd, .. }
+            | Self::OpImm { rd, .. }
+            | Self::La { rd, .. }
+            | Self::FOp { rd, .. }
+            | Self::FMv { rd, .. }
+            | Self::FCvt { rd, .. } => Some(*rd),
+            _ => None,
+        }
+    }
+
+    /// Registers read by this instruction in the order of operands
+    pub fn uses(&self) -> Vec<MReg> {
+        match self {
+            Self::Mv { rs, .. } | Self::FMv { rs, .. } | Self::FCvt { rs, .. } => vec![*rs],
+            Self::Load { base, .. } => vec![*base],
+            Self::Store { rs, base, .. } => vec![*rs, *base],
+            Self::Op { rs1, rs2, .. }
+            | Self::FOp { rs1, rs2, .. }
+            | Self::Branch { rs1, rs2, .. } => vec![*rs1, *rs2],
+            Self::OpImm { rs1, .. } | Self::JumpTable { rs: rs1, .. } => vec![*rs1],
+            Self::Call {
+                arg_count,
+                fp_arg_count,
+                ..
+            } => (0..(*arg_count).min(8))
+                .map(|i| MReg::Phys(Reg::arg(i)))
+                .chain((0..(*fp_arg_count).min(8)).map(|i| MReg::Phys(Reg::fp_arg(i))))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Apply `f` to all registers of this instruction.
+    pub fn map_regs<F: FnMut(MReg) -> MReg>(&mut self, mut f: F) {
+        match self {
+            Self::Li { rd, .. } | Self::La { rd, .. } => *rd = f(*rd),
+            Self::Mv { rd, rs } | Self::FMv { rd, rs, .. } | Self::FCvt { rd, rs, .. } => {
+                *rd = f(*rd);
+                *rs = f(*rs);
+            }
+            Self::Load { rd, base, .. } => {
+                *rd = f(*rd);
+                *base = f(*base);
+            }
+            Self::Store { rs, base, .. } => {
+                *rs = f(*rs);
+                *base = f(*base);
+            }
+            Self::Op { rd, rs1, rs2, .. } | Self::FOp { rd, rs1, rs2, .. } => {
+                *rd = f(*rd);
+                *rs1 = f(*rs1);
+                *rs2 = f(*rs2);
+            }
+            Self::OpImm { rd, rs1, .. } => {
+                *rd = f(*rd);
+                *rs1 = f(*rs1);
+            }
+            Self::Branch { rs1, rs2, .. } => {
+                *rs1 = f(*rs1);
+                *rs2 = f(*rs2);
+            }
+            Self::JumpTable { rs, .. } => *rs = f(*rs),
+            Self::J(_) | Self::Call { .. } | Self::Ret | Self::Directive(_) => {}
+        }
+    }
+
+    pub fn is_directive(&self) -> bool {
+        matches!(self, Self::Directive(_))
+    }
+}
+
+impl Display for MachineInst {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Li { rd, imm } => write!(f, "\tli\t{},{}", rd, imm),
+            Self::Mv { rd, rs } => write!(f, "\tmv\t{},{}", rd, rs),
+            Self::Load {
+                op,
+                rd,
+                offset,
+                base,
+            } => write!(f, "\t{}\t{},{}({})", op, rd, offset, base),
+            Self::Store {
+                op,
+                rs,
+                offset,
+                base,
+            } => write!(f, "\t{}\t{},{}({})", op, rs, offset, base),
+            Self::Op { op, rd, rs1, rs2 } => write!(f, "\t{}\t{},{},{}", op, rd, rs1, rs2),
+            Self::OpImm { op, rd, rs1, imm } => write!(f, "\t{}\t{},{},{}", op, rd, rs1, imm),
+            Self::La { rd, symbol } => write!(f, "\tla\t{},{}", rd, symbol),
+            Self::FOp {
+                op,
+                fmt,
+                rd,
+                rs1,
+                rs2,
+            } => write!(f, "\t{}.{}\t{},{},{}", op, fmt, rd, rs1, rs2),
+            Self::FMv { fmt, rd, rs } => write!(f, "\tfmv.{}\t{},{}", fmt, rd, rs),
+            Self::FCvt { to, from, rd, rs } => match to {
+                FpFmt::W | FpFmt::Wu => write!(f, "\tfcvt.{}.{}\t{},{},rtz", to, from, rd, rs),
+                _ => write!(f, "\tfcvt.{}.{}\t{},{}", to, from, rd, rs),
+            },
+            Self::Branch {
+                op,
+                rs1,
+                rs2,
+                target,
+            } => write!(f, "\t{}\t{},{},{}", op, rs1, rs2, target),
+            Self::J(target) => write!(f, "\tj\t{}", target),
+            Self::JumpTable { rs, label, targets } => {
+                write!(f, "\tjr\t{}\n{}:", rs, label)?;
+                for target in targets.iter() {
+                    write!(f, "\n\tj\t{}", target)?;
+                }
+                Ok(())
+            }
+            Self::Call { callee, .. } => write!(f, "\tcall\t{}", callee),
+            Self::Ret => write!(f, "\tret"),
+            Self::Directive(d) => write!(f, "\t{}", d),
+        }
+    }
+}
+
+/// Machine basic block, the label is only set if it is the target of jumps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MachineBlock {
+    pub label: Option<String>,
+    pub insts: Vec<MachineInst>,
+}
+
+impl MachineBlock {
+    pub fn new(label: Option<String>) -> MachineBlock {
+        MachineBlock {
+            label,
+            insts: vec![],
+        }
+    }
+}
+
+impl Display for MachineBlock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(label) = &self.label {
+            writeln!(f, "{}:", label)?;
+        }
+        for inst in self.insts.iter() {
+            writeln!(f, "{}", inst)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MachineFunction {
+    pub name: String,
+    pub blocks: Vec<MachineBlock>,
+    vreg_count: u32,
+    /// virtual registers which are allocated to floating-point registers
+    float_vregs: HashSet<u32>,
+}
+
+impl MachineFunction {
+    pub fn new(name: String) -> MachineFunction {
+        MachineFunction {
+            name,
+            blocks: vec![],
+            vreg_count: 0,
+            float_vregs: HashSet::new(),
+        }
+    }
+
+    pub fn new_vreg(&mut self) -> MReg {
+        self.vreg_count += 1;
+        MReg::Virt(self.vreg_count - 1)
+    }
+
+    pub fn new_float_vreg(&mut self) -> MReg {
+        self.vreg_count += 1;
+        self.float_vregs.insert(self.vreg_count - 1);
+        MReg::Virt(self.vreg_count - 1)
+    }
+
+    pub fn is_float_vreg(&self, id: u32) -> bool {
+        self.float_vregs.contains(&id)
+    }
+
+    /// Append an instruction to the last block.
+    pub fn push(&mut self, inst: MachineInst) {
+        self.blocks.last_mut().unwrap().insts.push(inst);
+    }
+}
+
+impl Display for MachineFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:", self.name)?;
+        for block in self.blocks.iter() {
+            write!(f, "{}", block)?;
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,13 @@
+pub mod c;
+pub mod elf;
+pub mod linker;
+pub mod mir;
+pub mod peephole;
+pub mod reg_alloc;
 pub mod riscv32;
+pub mod riscv32_asm;
+#[cfg(test)]
+mod tests;
 pub(crate) mod simple_allocator;
 
 use strenum::StrEnum;
@@ -7,9 +16,54 @@ use crate::rcc::OptimizeLevel;
 use crate::code_gen::simple_allocator::SimpleAllocator;
 use crate::ir::IRType;
 
-#[derive(StrEnum)]
+#[derive(StrEnum, Copy, Clone)]
 pub enum TargetPlatform {
-    Riscv32
+    Riscv32,
+    C,
+}
+
+impl TargetPlatform {
+    /// Size of addresses in bits
+    pub fn addr_size(&self) -> u32 {
+        match self {
+            TargetPlatform::Riscv32 => riscv32::RISCV32_ADDR_SIZE,
+            TargetPlatform::C => c::C_ADDR_SIZE,
+        }
+    }
+}
+
+/// Symbol name of the function, the paths of associated functions like `Point::new`
+/// are mangled to `_ZN5Point3newE`, which are valid in both assembly and C.
+/// The characters other than letters, digits and `_` in the segments,
+/// like the type arguments in `max::<&i32>`, are escaped as `_` and their hex codes.
+pub fn mangle(fn_name: &str) -> String {
+    if !fn_name.contains("::") {
+        return fn_name.to_string();
+    }
+    let mut symbol = "_ZN".to_string();
+    let mut segment = String::new();
+    let mut depth = 0;
+    let mut chars = fn_name.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ':' if depth == 0 && chars.peek() == Some(&':') => {
+                chars.next();
+                symbol.push_str(&format!("{}{}", segment.len(), segment));
+                segment.clear();
+                continue;
+            }
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            _ => {}
+        }
+        if c.is_ascii_alphanumeric() || c == '_' {
+            segment.push(c);
+        } else {
+            segment.push_str(&format!("_{:02x}", c as u32));
+        }
+    }
+    symbol.push_str(&format!("{}{}E", segment.len(), segment));
+    symbol
 }
 
 pub trait Allocator {
@@ -21,7 +75,7 @@ pub trait Allocator {
 
 pub fn create_allocator<'cfg>(opt_level: OptimizeLevel, cfg: &'cfg CFG, addr_size: u32) -> Box<dyn Allocator + 'cfg>  {
     match opt_level {
-        OptimizeLevel::Zero => Box::new(SimpleAllocator::new(cfg, addr_size)),
-        OptimizeLevel::One => todo!()
+        // TODO: register allocation for `OptimizeLevel::One`
+        OptimizeLevel::Zero | OptimizeLevel::One => Box::new(SimpleAllocator::new(cfg, addr_size)),
     }
 }
\ No newline at end of file
@@ -0,0 +1,243 @@
+//! Peephole optimisation over the machine IR of a function after register allocation.
+//!
+//! Rewrites are applied until nothing changes:
+//! - loading a stack slot whose value is still in a register is replaced by `mv`,
+//!   e.g. `sw a5,-20(s0); lw a4,-20(s0)` => `sw a5,-20(s0); mv a4,a5`
+//! - a store overwritten by the next store to the same slot is removed
+//! - `li a5,1` followed by `mv a0,a5` => `li a0,1` if a5 is dead
+//! - `li a4,3` followed by `add a5,a5,a4` => `addi a5,a5,3` if a4 is dead,
+//!   multiplications by a power of 2 are folded into `slli`
+//! - `mv a5,a5` is removed
+//! - jumps and branches to the next block are removed
+use crate::code_gen::mir::{
+    AluImmOp, AluOp, LoadOp, MReg, MachineBlock, MachineFunction, MachineInst, Reg, StoreOp,
+};
+use std::collections::HashMap;
+
+const IMM12_RANGE: std::ops::RangeInclusive<i64> = -2048..=2047;
+const FP: MReg = MReg::Phys(Reg::FP);
+
+pub fn optimize(func: &mut MachineFunction) {
+    loop {
+        let forwarded = forward_stack_values(func);
+        if !optimize_once(func) && !forwarded {
+            break;
+        }
+    }
+}
+
+/// Replace loads of stack slots by `mv` if the stored values are still in registers,
+/// return false if nothing changes.
+fn forward_stack_values(func: &mut MachineFunction) -> bool {
+    // offset from fp => register holding the word of the stack slot
+    let mut slots: HashMap<i32, MReg> = HashMap::new();
+    let mut changed = false;
+    for block in func.blocks.iter_mut() {
+        // the block may be reached by jumps
+        if block.label.is_some() {
+            slots.clear();
+        }
+        for inst in block.insts.iter_mut() {
+            match inst {
+                MachineInst::Call { .. } => slots.clear(),
+                MachineInst::Store {
+                    op: StoreOp::Sw,
+                    rs,
+                    offset,
+                    base: FP,
+                } => {
+                    let (rs, offset) = (*rs, *offset);
+                    slots.retain(|o, _| (*o - offset).abs() >= 4);
+                    slots.insert(offset, rs);
+                }
+                // other stores may overlap with the slots
+                MachineInst::Store { .. } => slots.clear(),
+                MachineInst::Load {
+                    op: LoadOp::Lw,
+                    rd,
+                    offset,
+                    base: FP,
+                } => {
+                    let (rd, offset) = (*rd, *offset);
+                    if let Some(rs) = slots.get(&offset) {
+                        *inst = MachineInst::Mv { rd, rs: *rs };
+                        changed = true;
+                    }
+                    slots.retain(|_, r| *r != rd);
+                    slots.insert(offset, rd);
+                }
+                _ => match inst.def() {
+                    Some(FP) => slots.clear(),
+                    Some(rd) => slots.retain(|_, r| *r != rd),
+                    None => {}
+                },
+            }
+        }
+    }
+    changed
+}
+
+/// Apply the first matched rewrite, return false if nothing changes.
+fn optimize_once(func: &mut MachineFunction) -> bool {
+    for b in 0..func.blocks.len() {
+        if remove_jump_to_next(&mut func.blocks, b) {
+            return true;
+        }
+        for i in 0..func.blocks[b].insts.len() {
+            let insts = &func.blocks[b].insts;
+            if let MachineInst::Mv { rd, rs } = &insts[i] {
+                if rd == rs {
+                    func.blocks[b].insts.remove(i);
+                    return true;
+                }
+            }
+            let j = match next_inst(insts, i) {
+                Some(j) => j,
+                None => continue,
+            };
+            if let Some(new_insts) = rewrite_pair(&func.blocks, b, i, j) {
+                let insts = &mut func.blocks[b].insts;
+                // directives between the pair are kept before the new instructions
+                insts.splice(j..=j, new_insts);
+                insts.remove(i);
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Index of the next instruction in the block except directives
+fn next_inst(insts: &[MachineInst], i: usize) -> Option<usize> {
+    (i + 1..insts.len()).find(|j| !insts[*j].is_directive())
+}
+
+/// Instructions to replace `insts[i]` and `insts[j]` of the block `blocks[b]`,
+/// None if the pair can not be rewritten.
+fn rewrite_pair(blocks: &[MachineBlock], b: usize, i: usize, j: usize) -> Option<Vec<MachineInst>> {
+    use MachineInst::*;
+
+    let insts = &blocks[b].insts;
+    match (&insts[i], &insts[j]) {
+        (
+            Store {
+                op, offset, base, ..
+            },
+            Store {
+                op: next_op,
+                offset: next_offset,
+                base: next_base,
+                ..
+            },
+        ) if op == next_op && offset == next_offset && base == next_base => {
+            Some(vec![insts[j].clone()])
+        }
+        (Li { rd, imm }, Mv { rd: mv_rd, rs }) if rd == rs && is_dead_after(blocks, b, j, *rd) => {
+            Some(vec![Li {
+                rd: *mv_rd,
+                imm: *imm,
+            }])
+        }
+        (
+            Li { rd, imm },
+            Op {
+                op,
+                rd: op_rd,
+                rs1,
+                rs2,
+            },
+        ) if (rd == rs1) != (rd == rs2) && is_dead_after(blocks, b, j, *rd) => {
+            let src = if rd == rs2 { *rs1 } else { *rs2 };
+            let (op_imm, imm) = match (*op, *imm) {
+                (AluOp::Add, imm) if IMM12_RANGE.contains(&imm) => (AluImmOp::Addi, imm),
+                (AluOp::Sub, imm) if rd == rs2 && IMM12_RANGE.contains(&-imm) => {
+                    (AluImmOp::Addi, -imm)
+                }
+                (AluOp::Mul, imm) if imm > 0 && imm & (imm - 1) == 0 => {
+                    (AluImmOp::Slli, imm.trailing_zeros() as i64)
+                }
+                _ => return None,
+            };
+            Some(vec![OpImm {
+                op: op_imm,
+                rd: *op_rd,
+                rs1: src,
+                imm,
+            }])
+        }
+        _ => None,
+    }
+}
+
+/// Remove the last jump or branch of `blocks[b]` if it targets one of the following
+/// blocks which are reached by falling through, return false if nothing changes.
+fn remove_jump_to_next(blocks: &mut [MachineBlock], b: usize) -> bool {
+    let insts = &blocks[b].insts;
+    let last = match insts.iter().rposition(|inst| !inst.is_directive()) {
+        Some(last) => last,
+        None => return false,
+    };
+    let target = match &insts[last] {
+        MachineInst::J(target) | MachineInst::Branch { target, .. } => target,
+        _ => return false,
+    };
+    for block in blocks[b + 1..].iter() {
+        if block.label.as_ref() == Some(target) {
+            blocks[b].insts.remove(last);
+            return true;
+        }
+        if block.insts.iter().any(|inst| !inst.is_directive()) {
+            break;
+        }
+    }
+    false
+}
+
+/// Whether the value of `reg` is never read after `blocks[b].insts[i]`.
+/// Jumps, branches and labelled blocks are treated as reading all registers.
+fn is_dead_after(blocks: &[MachineBlock], b: usize, i: usize, reg: MReg) -> bool {
+    let rest = blocks[b].insts[i + 1..].iter().chain(
+        blocks[b + 1..]
+            .iter()
+            .take_while(|block| block.label.is_none())
+            .flat_map(|block| block.insts.iter()),
+    );
+    for inst in rest {
+        match inst {
+            MachineInst::Directive(_) => {}
+            MachineInst::J(_) | MachineInst::Branch { .. } | MachineInst::JumpTable { .. } => {
+                return false
+            }
+            MachineInst::Ret => return is_dead_at_return(reg),
+            _ => {
+                if inst.uses().contains(&reg) {
+                    return false;
+                }
+                if inst.def() == Some(reg) {
+                    return true;
+                }
+                if let (MachineInst::Call { .. }, MReg::Phys(r)) = (inst, reg) {
+                    // clobbered by the callee
+                    if r.is_caller_saved() {
+                        return true;
+                    }
+                }
+            }
+        }
+    }
+    // reached a labelled block or the end of the function
+    match blocks[b + 1..].iter().find(|block| block.label.is_some()) {
+        Some(_) => false,
+        None => is_dead_at_return(reg),
+    }
+}
+
+/// Return values are in a0, a1 and fa0
+fn is_dead_at_return(reg: MReg) -> bool {
+    match reg {
+        MReg::Phys(r) => {
+            r != Reg::A0 && r != Reg::arg(1) && r != Reg::FA0 && r.is_caller_saved()
+        }
+        MReg::Virt(_) => true,
+    }
+}
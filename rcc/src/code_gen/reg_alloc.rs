@@ -0,0 +1,122 @@
+//! Local register allocation of the machine IR.
+//!
+//! Virtual registers are not live across machine blocks, so each block is allocated
+//! bottom-up: a register is taken at the last use of a virtual register and freed at its def.
+//! Floating-point virtual registers are allocated from their own pool.
+use crate::code_gen::mir::{MReg, MachineFunction, MachineInst, Reg};
+use crate::rcc::RccError;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+/// Registers for virtual registers, all of them are caller-saved
+const POOL: [Reg; 9] = [
+    Reg::A5,
+    Reg::A4,
+    Reg(5),
+    Reg(6),
+    Reg(7),
+    Reg(28),
+    Reg(29),
+    Reg(30),
+    Reg(31),
+];
+
+/// Floating-point registers for virtual registers, all of them are caller-saved
+const FP_POOL: [Reg; 14] = [
+    Reg::FA5,
+    Reg::FA4,
+    Reg(32),
+    Reg(33),
+    Reg(34),
+    Reg(35),
+    Reg(36),
+    Reg(37),
+    Reg(38),
+    Reg(39),
+    Reg(60),
+    Reg(61),
+    Reg(62),
+    Reg(63),
+];
+
+pub fn allocate(func: &mut MachineFunction) -> Result<(), RccError> {
+    let pool = |id: u32| -> &'static [Reg] {
+        if func.is_float_vreg(id) {
+            &FP_POOL
+        } else {
+            &POOL
+        }
+    };
+    let mut assigned_blocks = vec![];
+    for block in func.blocks.iter() {
+        assigned_blocks.push(allocate_block(&block.insts, &pool)?);
+    }
+    for (block, assigned) in func.blocks.iter_mut().zip(assigned_blocks) {
+        for inst in block.insts.iter_mut() {
+            inst.map_regs(|reg| match reg {
+                MReg::Virt(id) => MReg::Phys(assigned[&id]),
+                phys => phys,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn allocate_block(
+    insts: &[MachineInst],
+    pool: impl Fn(u32) -> &'static [Reg],
+) -> Result<HashMap<u32, Reg>, RccError> {
+    let mut assigned: HashMap<u32, Reg> = HashMap::new();
+    // registers holding a value which is read later
+    let mut live: HashSet<Reg> = HashSet::new();
+    for inst in insts.iter().rev() {
+        match inst.def() {
+            Some(MReg::Virt(id)) => match assigned.get(&id) {
+                Some(reg) => {
+                    live.remove(reg);
+                }
+                // the value is never read
+                None => {
+                    assigned.insert(id, free_reg(&live, pool(id))?);
+                }
+            },
+            Some(MReg::Phys(reg)) => {
+                live.remove(&reg);
+            }
+            None => {}
+        }
+        if let MachineInst::Call { callee, .. } = inst {
+            // the pools are caller-saved, values are kept in stack slots across calls
+            if live
+                .iter()
+                .any(|reg| POOL.contains(reg) || FP_POOL.contains(reg))
+            {
+                return Err(
+                    format!("virtual register is live across the call of {}", callee).into(),
+                );
+            }
+        }
+        for reg in inst.uses().into_iter().rev() {
+            match reg {
+                MReg::Virt(id) => {
+                    if let Entry::Vacant(entry) = assigned.entry(id) {
+                        let reg = free_reg(&live, pool(id))?;
+                        entry.insert(reg);
+                        live.insert(reg);
+                    }
+                }
+                MReg::Phys(reg) => {
+                    live.insert(reg);
+                }
+            }
+        }
+    }
+    Ok(assigned)
+}
+
+fn free_reg(live: &HashSet<Reg>, pool: &[Reg]) -> Result<Reg, RccError> {
+    pool.iter()
+        .find(|reg| !live.contains(reg))
+        .copied()
+        .ok_or_else(|| "run out of registers".into())
+}
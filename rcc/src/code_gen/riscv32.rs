@@ -3,81 +3,396 @@
 //! w(word): 32bit
 use crate::analyser::sym_resolver::VarKind;
 use crate::ast::expr::BinOperator;
-use crate::code_gen::{create_allocator, Allocator};
+use crate::code_gen::elf::SectionKind;
+use crate::code_gen::mir::{
+    AluImmOp, AluOp, BranchOp, FpFmt, FpOp, LoadOp, MReg, MachineBlock, MachineFunction,
+    MachineInst, Reg, StoreOp,
+};
+use crate::code_gen::{create_allocator, mangle, peephole, reg_alloc, Allocator};
 use crate::ir::cfg::{CFG, CFGIR};
-use crate::ir::var_name::{branch_name, FP, RA};
+use crate::ir::linear_ir::ExternFn;
+use crate::ir::var_name::{branch_name, jump_table_name, FP, RA};
 use crate::ir::{IRInst, IRType, Jump, Operand, Place};
 use crate::rcc::{OptimizeLevel, RccError};
 use std::io::{BufWriter, Write};
 
-const RISCV32_ADDR_SIZE: u32 = 32;
-pub struct Riscv32CodeGen<'w, W: Write> {
-    cfg_ir: CFGIR,
+pub(crate) const RISCV32_ADDR_SIZE: u32 = 32;
+
+/// Receiver of the sections, data and machine functions generated by `Riscv32CodeGen`,
+/// they are printed as assembly language by `AsmWriter`,
+/// or encoded into an object file by `Riscv32Assembler`.
+pub trait Riscv32Emitter {
+    /// Source file of the debug information
+    fn file(&mut self, src_file: &str) -> Result<(), RccError>;
+    fn section(&mut self, kind: SectionKind) -> Result<(), RccError>;
+    /// Align to `2^p2` bytes
+    fn align(&mut self, p2: u32) -> Result<(), RccError>;
+    fn label(&mut self, label: &str) -> Result<(), RccError>;
+    /// Null-terminated string, `s` contains the escapes of the source code
+    fn string(&mut self, s: &str) -> Result<(), RccError>;
+    fn byte(&mut self, byte: u8) -> Result<(), RccError>;
+    fn word(&mut self, word: u32) -> Result<(), RccError>;
+    fn zero(&mut self, size: u32) -> Result<(), RccError>;
+    fn function(
+        &mut self,
+        mfunc: &MachineFunction,
+        is_global: bool,
+        debug_info: bool,
+    ) -> Result<(), RccError>;
+}
+
+/// Print GAS text.
+pub struct AsmWriter<'w, W: Write> {
     output: &'w mut BufWriter<W>,
+}
+
+impl<'w, W: Write> AsmWriter<'w, W> {
+    pub fn new(output: &'w mut BufWriter<W>) -> AsmWriter<'w, W> {
+        AsmWriter { output }
+    }
+}
+
+impl<'w, W: Write> Riscv32Emitter for AsmWriter<'w, W> {
+    fn file(&mut self, src_file: &str) -> Result<(), RccError> {
+        writeln!(self.output, "\t.file\t\"{}\"", src_file)?;
+        writeln!(self.output, "\t.file\t1 \"{}\"", src_file)?;
+        Ok(())
+    }
+
+    fn section(&mut self, kind: SectionKind) -> Result<(), RccError> {
+        match kind {
+            SectionKind::Text => writeln!(self.output, "\t.text")?,
+            _ => writeln!(self.output, "\t.section\t{}", kind.name())?,
+        }
+        Ok(())
+    }
+
+    fn align(&mut self, p2: u32) -> Result<(), RccError> {
+        writeln!(self.output, "\t.align\t{}", p2)?;
+        Ok(())
+    }
+
+    fn label(&mut self, label: &str) -> Result<(), RccError> {
+        writeln!(self.output, "{}:", label)?;
+        Ok(())
+    }
+
+    fn string(&mut self, s: &str) -> Result<(), RccError> {
+        writeln!(self.output, "\t.string \"{}\"", s)?;
+        Ok(())
+    }
+
+    fn byte(&mut self, byte: u8) -> Result<(), RccError> {
+        writeln!(self.output, "\t.byte\t{}", byte)?;
+        Ok(())
+    }
+
+    fn word(&mut self, word: u32) -> Result<(), RccError> {
+        writeln!(self.output, "\t.word\t{}", word)?;
+        Ok(())
+    }
+
+    fn zero(&mut self, size: u32) -> Result<(), RccError> {
+        writeln!(self.output, "\t.zero\t{}", size)?;
+        Ok(())
+    }
+
+    fn function(
+        &mut self,
+        mfunc: &MachineFunction,
+        is_global: bool,
+        debug_info: bool,
+    ) -> Result<(), RccError> {
+        if is_global {
+            writeln!(self.output, "\t.globl  {}", mfunc.name)?;
+        }
+        if debug_info {
+            writeln!(self.output, "\t.type\t{}, @function", mfunc.name)?;
+        }
+        write!(self.output, "{}", mfunc)?;
+        if debug_info {
+            writeln!(self.output, "\t.size\t{0}, .-{0}", mfunc.name)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Riscv32CodeGen<'w> {
+    cfg_ir: CFGIR,
+    output: &'w mut dyn Riscv32Emitter,
     opt_level: OptimizeLevel,
+    /// source file name, emit `.loc` and `.cfi_*` directives if it is set
+    debug_file: Option<String>,
+    /// lower floating-point operations into calls of the runtime instead of the F/D extensions
+    soft_float: bool,
+    literal_pool: LiteralPool,
 }
 
-impl<'w, W: 'w + Write> Riscv32CodeGen<'w, W> {
+impl<'w> Riscv32CodeGen<'w> {
     pub fn new(
         cfg_ir: CFGIR,
-        output: &'w mut BufWriter<W>,
+        output: &'w mut dyn Riscv32Emitter,
         opt_level: OptimizeLevel,
-    ) -> Riscv32CodeGen<W> {
+    ) -> Riscv32CodeGen<'w> {
+        // labels of the constants follow the labels of the strings
+        let literal_pool = LiteralPool::new(cfg_ir.ro_local_strs.len());
         Riscv32CodeGen {
             cfg_ir,
             output,
             opt_level,
+            debug_file: None,
+            soft_float: false,
+            literal_pool,
         }
     }
 
+    pub fn with_debug_info(mut self, src_file: &str) -> Self {
+        self.debug_file = Some(src_file.to_string());
+        self
+    }
+
+    /// Use the soft-float ABI: floating-point numbers are kept in integer registers
+    /// and operated by the runtime functions of libgcc.
+    pub fn with_soft_float(mut self) -> Self {
+        self.soft_float = true;
+        self
+    }
+
     pub fn run(&mut self) -> Result<(), RccError> {
+        let statics = self.cfg_ir.statics.iter().map(|s| &s.elem_type);
+        if statics
+            .chain(self.cfg_ir.cfgs.iter().flat_map(ir_types))
+            .any(is_int128)
+        {
+            return Err("128-bit integers are not supported by target riscv32".into());
+        }
+        if let Some(src_file) = &self.debug_file {
+            self.output.file(src_file)?;
+        }
         self.gen_read_only_local_str()?;
+        self.gen_statics()?;
         self.gen_functions()?;
+        self.gen_literal_pool()?;
         Ok(())
     }
 
     fn gen_read_only_local_str(&mut self) -> Result<(), RccError> {
         if !self.cfg_ir.ro_local_strs.is_empty() {
-            writeln!(self.output, "\t.text")?;
-            writeln!(self.output, "\t.section\t.rodata")?;
+            self.output.section(SectionKind::Text)?;
+            self.output.section(SectionKind::ROData)?;
             for s in self.cfg_ir.ro_local_strs.iter() {
-                writeln!(self.output, "{}:", s.0)?;
-                writeln!(self.output, "\t.string \"{}\"", s.1)?;
+                self.output.label(s.0)?;
+                self.output.string(s.1)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Immutable statics are placed in `.rodata`, mutable ones in `.data`,
+    /// or in `.bss` if they are initialized with zeros.
+    fn gen_statics(&mut self) -> Result<(), RccError> {
+        for static_data in self.cfg_ir.statics.iter() {
+            let size = static_data.elem_type.byte_size(RISCV32_ADDR_SIZE) as usize;
+            let bytes: Vec<u8> = static_data
+                .values
+                .iter()
+                .flat_map(|value| imm_bits(value).to_le_bytes()[..size].to_vec())
+                .collect();
+            let section = if !static_data.is_mut {
+                SectionKind::ROData
+            } else if bytes.iter().all(|b| *b == 0) {
+                SectionKind::Bss
+            } else {
+                SectionKind::Data
+            };
+            self.output.section(section)?;
+            self.output.align(size.max(1).trailing_zeros().min(3))?;
+            self.output.label(&static_data.label)?;
+            if section == SectionKind::Bss {
+                self.output.zero(bytes.len().max(1) as u32)?;
+            } else {
+                for word in bytes.chunks(4) {
+                    match word {
+                        [b0, b1, b2, b3] => {
+                            self.output.word(u32::from_le_bytes([*b0, *b1, *b2, *b3]))?;
+                        }
+                        _ => {
+                            for b in word {
+                                self.output.byte(*b)?;
+                            }
+                        }
+                    }
+                }
             }
         }
         Ok(())
     }
 
     fn gen_functions(&mut self) -> Result<(), RccError> {
-        writeln!(self.output, "\t.text")?;
-        for cfg in self.cfg_ir.cfgs.iter() {
-            let mut func_gen = FuncCodeGen::new(cfg, self.output, self.opt_level);
-            func_gen.gen_function()?;
+        self.output.section(SectionKind::Text)?;
+        for cfg in self.cfg_ir.cfgs.iter_mut() {
+            // ra is saved if operations are lowered into calls of the runtime
+            cfg.is_leaf = cfg.is_leaf && !calls_runtime(cfg, self.soft_float);
+            let mut func_gen = FuncCodeGen::new(cfg, self.opt_level, &mut self.literal_pool);
+            func_gen.debug_info = self.debug_file.is_some();
+            func_gen.soft_float = self.soft_float;
+            func_gen.extern_fns = &self.cfg_ir.extern_fns;
+            let mfunc = func_gen.gen_function()?;
+            self.output
+                .function(&mfunc, cfg.func_is_global, self.debug_file.is_some())?;
+        }
+        Ok(())
+    }
+
+    fn gen_literal_pool(&mut self) -> Result<(), RccError> {
+        if self.literal_pool.consts.is_empty() {
+            return Ok(());
+        }
+        self.output.section(SectionKind::ROData)?;
+        for (i, (bits, size)) in self.literal_pool.consts.iter().enumerate() {
+            self.output.align(if *size == 8 { 3 } else { 2 })?;
+            self.output.label(&self.literal_pool.label_of(i))?;
+            // little endian
+            self.output.word(*bits as u32)?;
+            if *size == 8 {
+                self.output.word((*bits >> 32) as u32)?;
+            }
         }
         Ok(())
     }
 }
 
-struct FuncCodeGen<'w: 'codegen, 'codegen, W: Write> {
+/// Floating-point constants in `.rodata`, they are loaded by their labels.
+struct LiteralPool {
+    /// index of the label of the first constant
+    first_label: usize,
+    /// bits and byte size of the constants
+    consts: Vec<(u64, u32)>,
+}
+
+impl LiteralPool {
+    fn new(first_label: usize) -> LiteralPool {
+        LiteralPool {
+            first_label,
+            consts: vec![],
+        }
+    }
+
+    fn label_of(&self, idx: usize) -> String {
+        format!(".LC{}", self.first_label + idx)
+    }
+
+    /// Label of the constant, the same constants share one label.
+    fn label(&mut self, bits: u64, size: u32) -> String {
+        let idx = match self.consts.iter().position(|c| *c == (bits, size)) {
+            Some(idx) => idx,
+            None => {
+                self.consts.push((bits, size));
+                self.consts.len() - 1
+            }
+        };
+        self.label_of(idx)
+    }
+}
+
+/// Location of an argument by the ILP32 calling convention
+enum ArgLoc {
+    /// floating-point argument register `fa{i}`
+    Float(usize),
+    /// words of an integer in argument registers or on the stack
+    Int(Vec<ArgWord>),
+}
+
+/// Argument of a call, the aggregates of at most 8 bytes passed to C functions are
+/// loaded from their addresses and passed like integers of their sizes.
+enum CallArg<'a> {
+    Value(&'a Operand),
+    Packed {
+        addr: &'a Operand,
+        size: u32,
+        align: u32,
+    },
+}
+
+/// Location of a word of an argument
+#[derive(Clone, Copy)]
+enum ArgWord {
+    /// argument register `a{i}`
+    Reg(usize),
+    /// offset from sp of the caller, which is fp of the callee
+    Stack(i32),
+}
+
+/// Assign the arguments of `(byte size, whether kept in floating-point registers)` to
+/// registers and the stack, return the locations and the byte size of the stack arguments.
+///
+/// Integers of 2 words are passed by a pair of registers, they are split between `a7` and the
+/// stack if only one register is left, and aligned to 8 bytes if they are passed by the stack.
+/// Floating-point numbers are passed as integers when the floating-point registers are used up.
+fn arg_locations(args: impl Iterator<Item = (u32, bool)>) -> (Vec<ArgLoc>, u32) {
+    let (mut reg_idx, mut fp_reg_idx, mut stack_size) = (0, 0, 0);
+    let mut locations = vec![];
+    for (size, is_float) in args {
+        if is_float && fp_reg_idx < 8 {
+            locations.push(ArgLoc::Float(fp_reg_idx));
+            fp_reg_idx += 1;
+            continue;
+        }
+        let word_count = if size == 8 { 2 } else { 1 };
+        if word_count == 2 && reg_idx == 8 {
+            stack_size = u32::next_multiple_of(stack_size, 8);
+        }
+        let words = (0..word_count)
+            .map(|_| {
+                if reg_idx < 8 {
+                    reg_idx += 1;
+                    ArgWord::Reg(reg_idx - 1)
+                } else {
+                    stack_size += 4;
+                    ArgWord::Stack(stack_size as i32 - 4)
+                }
+            })
+            .collect();
+        locations.push(ArgLoc::Int(words));
+    }
+    (locations, stack_size)
+}
+
+struct FuncCodeGen<'codegen> {
     cfg: &'codegen CFG,
-    output: &'w mut BufWriter<W>,
     allocator: Box<dyn Allocator + 'codegen>,
     frame_size: u32,
+    opt_level: OptimizeLevel,
+    /// machine IR of the function, emitted after register allocation
+    mfunc: MachineFunction,
+    literal_pool: &'codegen mut LiteralPool,
+    debug_info: bool,
+    soft_float: bool,
+    /// functions declared in `extern "C"` blocks
+    extern_fns: &'codegen [ExternFn],
+    /// line of the last `.loc` directive
+    cur_line: u32,
 }
 
-impl<'w: 'codegen, 'codegen, W: Write> FuncCodeGen<'w, 'codegen, W> {
+impl<'codegen> FuncCodeGen<'codegen> {
     fn new(
         cfg: &'codegen CFG,
-        output: &'w mut BufWriter<W>,
         opt_level: OptimizeLevel,
-    ) -> FuncCodeGen<'w, 'codegen, W> {
+        literal_pool: &'codegen mut LiteralPool,
+    ) -> FuncCodeGen<'codegen> {
         let allocator = create_allocator(opt_level, cfg, RISCV32_ADDR_SIZE);
         let frame_size = allocator.get_frame_size();
         FuncCodeGen {
             cfg,
-            output,
             allocator,
             frame_size,
+            opt_level,
+            mfunc: MachineFunction::new(mangle(&cfg.func_name)),
+            literal_pool,
+            debug_info: false,
+            soft_float: false,
+            extern_fns: &[],
+            cur_line: 0,
         }
     }
 
@@ -124,70 +439,187 @@ impl<'w: 'codegen, 'codegen, W: Write> FuncCodeGen<'w, 'codegen, W> {
     ///
     /// Low Address
     /// ```
-    fn gen_function(&mut self) -> Result<(), RccError> {
-        if self.cfg.func_is_global {
-            writeln!(self.output, "\t.globl  {}", self.cfg.func_name)?;
-        }
-        writeln!(self.output, "{}:", self.cfg.func_name)?;
+    fn gen_function(mut self) -> Result<MachineFunction, RccError> {
+        self.mfunc.blocks.push(MachineBlock::new(None));
+        self.gen_cfi("startproc")?;
+        self.gen_loc(self.cfg.func_line)?;
         if !self.cfg.basic_blocks.is_empty() {
             self.gen_function_entry()?;
             self.gen_save_args()?;
             self.gen_instructions()?;
+            self.mfunc.blocks.push(MachineBlock::new(None));
             self.gen_exit_function()?;
         }
-        writeln!(self.output, "\tret")?;
+        self.emit(MachineInst::Ret);
+        self.gen_cfi("endproc")?;
+
+        reg_alloc::allocate(&mut self.mfunc)?;
+        if let OptimizeLevel::One = self.opt_level {
+            peephole::optimize(&mut self.mfunc);
+        }
+        Ok(self.mfunc)
+    }
+
+    /// Append an instruction to the current machine block.
+    fn emit(&mut self, inst: MachineInst) {
+        self.mfunc.push(inst);
+    }
+
+    /// `.cfi_*` directive for unwinding the stack frame, only emitted with debug info
+    fn gen_cfi(&mut self, directive: &str) -> Result<(), RccError> {
+        if self.debug_info {
+            self.emit(MachineInst::Directive(format!(".cfi_{}", directive)));
+        }
+        Ok(())
+    }
+
+    /// `.loc` directive of the source line, only emitted with debug info
+    fn gen_loc(&mut self, line: u32) -> Result<(), RccError> {
+        if self.debug_info && line != 0 && line != self.cur_line {
+            self.emit(MachineInst::Directive(format!(".loc\t1 {} 0", line)));
+            self.cur_line = line;
+        }
         Ok(())
     }
 
     fn gen_function_entry(&mut self) -> Result<(), RccError> {
         debug_assert!(self.frame_size >= 8);
         // set sp
-        writeln!(self.output, "\taddi\tsp,sp,-{}", self.frame_size)?;
+        self.emit(MachineInst::OpImm {
+            op: AluImmOp::Addi,
+            rd: Reg::SP.into(),
+            rs1: Reg::SP.into(),
+            imm: -(self.frame_size as i64),
+        });
+        self.gen_cfi(&format!("def_cfa_offset {}", self.frame_size))?;
         if !self.cfg.is_leaf {
             // save ra
             let offset = self.allocator.get_fp_offset(RA, &IRType::Addr);
             debug_assert_eq!(4, offset);
-            writeln!(self.output, "\tsw\tra,{}(sp)", self.frame_size - 4)?;
+            self.store_data(
+                4,
+                Reg::RA.into(),
+                (self.frame_size - 4) as i32,
+                Reg::SP.into(),
+            )?;
+            self.gen_cfi("offset ra, -4")?;
         }
         // save old fp(s0)
         let offset = self.allocator.get_fp_offset(FP, &IRType::Addr);
-        writeln!(self.output, "\tsw\ts0,{}(sp)", self.frame_size - offset)?;
+        self.store_data(
+            4,
+            Reg::FP.into(),
+            (self.frame_size - offset) as i32,
+            Reg::SP.into(),
+        )?;
+        self.gen_cfi(&format!("offset s0, -{}", offset))?;
         // set fp
-        writeln!(self.output, "\taddi\ts0,sp,{}", self.frame_size)?;
+        self.emit(MachineInst::OpImm {
+            op: AluImmOp::Addi,
+            rd: Reg::FP.into(),
+            rs1: Reg::SP.into(),
+            imm: self.frame_size as i64,
+        });
+        self.gen_cfi("def_cfa s0, 0")?;
         Ok(())
     }
 
     fn gen_exit_function(&mut self) -> Result<(), RccError> {
+        // sp is not changed in the function body, so the CFA can be computed from sp again
+        self.gen_cfi(&format!("def_cfa sp, {}", self.frame_size))?;
         if !self.cfg.is_leaf {
             // restore ra
             let offset = self.allocator.get_fp_offset(RA, &IRType::Addr);
             debug_assert_eq!(4, offset);
-            writeln!(self.output, "\tlw\tra,{}(sp)", self.frame_size - offset)?;
+            self.emit(MachineInst::Load {
+                op: LoadOp::Lw,
+                rd: Reg::RA.into(),
+                offset: (self.frame_size - offset) as i32,
+                base: Reg::SP.into(),
+            });
+            self.gen_cfi("restore ra")?;
         }
         // restore old fp
         let offset = self.allocator.get_fp_offset(FP, &IRType::Addr);
-        writeln!(self.output, "\tlw\ts0,{}(sp)", self.frame_size - offset)?;
+        self.emit(MachineInst::Load {
+            op: LoadOp::Lw,
+            rd: Reg::FP.into(),
+            offset: (self.frame_size - offset) as i32,
+            base: Reg::SP.into(),
+        });
+        self.gen_cfi("restore s0")?;
         // restore sp
-        writeln!(self.output, "\taddi\tsp,sp,{}", self.frame_size)?;
+        self.emit(MachineInst::OpImm {
+            op: AluImmOp::Addi,
+            rd: Reg::SP.into(),
+            rs1: Reg::SP.into(),
+            imm: self.frame_size as i64,
+        });
+        self.gen_cfi("def_cfa_offset 0")?;
         Ok(())
     }
 
     fn gen_save_args(&mut self) -> Result<(), RccError> {
-        for i in 0..self.cfg.fn_args.len().min(8) {
-            let arg_name = self.cfg.get_name_of_fn_arg(i).unwrap();
-            let (_, ir_type) = self.cfg.local_variables.get(&arg_name).unwrap();
-            let offset = self.allocator.get_fp_offset(&arg_name, ir_type);
-            writeln!(self.output, "\tsw\ta{},-{}(s0)", i, offset)?;
+        let arg_types: Vec<(String, IRType)> = (0..self.cfg.fn_args.len())
+            .map(|i| {
+                let arg_name = self.cfg.get_name_of_fn_arg(i).unwrap();
+                let (_, ir_type) = self.cfg.local_variables.get(&arg_name).unwrap();
+                (arg_name, *ir_type)
+            })
+            .collect();
+        let (locations, _) = arg_locations(arg_types.iter().map(|(_, ir_type)| {
+            (
+                ir_type.byte_size(RISCV32_ADDR_SIZE),
+                self.hard_float_fmt(ir_type).is_some(),
+            )
+        }));
+        for ((arg_name, ir_type), location) in arg_types.iter().zip(locations) {
+            let offset = -(self.allocator.get_fp_offset(arg_name, ir_type) as i32);
+            let size = ir_type.byte_size(RISCV32_ADDR_SIZE);
+            match location {
+                ArgLoc::Float(idx) => {
+                    let fmt = self.hard_float_fmt(ir_type).unwrap();
+                    self.store_float(fmt, Reg::fp_arg(idx).into(), offset)?;
+                }
+                ArgLoc::Int(words) => {
+                    let regs: Vec<MReg> = words
+                        .iter()
+                        .map(|word| match *word {
+                            ArgWord::Reg(idx) => Reg::arg(idx).into(),
+                            // the stack arguments are at the bottom of the frame of the caller
+                            ArgWord::Stack(stack_offset) => {
+                                let reg = self.mfunc.new_vreg();
+                                self.emit(MachineInst::Load {
+                                    op: LoadOp::Lw,
+                                    rd: reg,
+                                    offset: stack_offset,
+                                    base: Reg::FP.into(),
+                                });
+                                reg
+                            }
+                        })
+                        .collect();
+                    if size == 8 {
+                        self.store_pair(regs[0], regs[1], offset)?;
+                    } else {
+                        self.store_data(size, regs[0], offset, Reg::FP.into())?;
+                    }
+                }
+            }
         }
         Ok(())
     }
 
     fn gen_instructions(&mut self) -> Result<(), RccError> {
         for bb in self.cfg.basic_blocks.iter() {
-            if !bb.predecessors.is_empty() {
-                writeln!(self.output, "{}:", branch_name(self.cfg.func_scope_id, bb.id))?;
-            }
-            for inst in bb.instructions.iter() {
+            let label = if bb.predecessors.is_empty() {
+                None
+            } else {
+                Some(branch_name(self.cfg.func_scope_id, bb.id))
+            };
+            self.mfunc.blocks.push(MachineBlock::new(label));
+            for (inst, line) in bb.instructions.iter().zip(self.cfg.lines[bb.id].iter()) {
+                self.gen_loc(*line)?;
                 self.gen_instruction(inst)?;
             }
         }
@@ -196,13 +628,55 @@ impl<'w: 'codegen, 'codegen, W: Write> FuncCodeGen<'w, 'codegen, W> {
 
     fn gen_instruction(&mut self, inst: &IRInst) -> Result<(), RccError> {
         match inst {
-            IRInst::Ret(o) => self.load_data("a0", o)?,
+            IRInst::Ret(o) if self.is_hard_float(o) => self.load_float(Reg::FA0.into(), o)?,
+            IRInst::Ret(o) if is_wide(o) => {
+                self.load_pair(Reg::A0.into(), Reg::arg(1).into(), o)?
+            }
+            IRInst::Ret(o) => self.load_data(Reg::A0.into(), o)?,
             IRInst::LoadData { dest, src } => match dest.kind {
+                VarKind::Local | VarKind::LocalMut if dest.ir_type.is_aggregate() => {
+                    if let Operand::Place(p) = src {
+                        let (src, dest) = (self.slot(p), self.slot(dest));
+                        self.copy_memory(&p.ir_type, src, dest);
+                    }
+                }
+                VarKind::Local | VarKind::LocalMut if self.is_hard_float(src) => {
+                    let offset = self.allocator.get_fp_offset(&dest.label, &dest.ir_type);
+                    let reg = self.mfunc.new_float_vreg();
+                    self.load_float(reg, src)?;
+                    self.store_float(fp_fmt(src), reg, -(offset as i32))?;
+                }
+                VarKind::Local | VarKind::LocalMut if is_wide(src) => {
+                    let offset = self.allocator.get_fp_offset(&dest.label, &dest.ir_type);
+                    let (lo, hi) = (self.mfunc.new_vreg(), self.mfunc.new_vreg());
+                    self.load_pair(lo, hi, src)?;
+                    self.store_pair(lo, hi, -(offset as i32))?;
+                }
+                // integers are extended to the size of `dest`
+                VarKind::Local | VarKind::LocalMut
+                    if dest.ir_type.byte_size(RISCV32_ADDR_SIZE) == 8 =>
+                {
+                    let offset = self.allocator.get_fp_offset(&dest.label, &dest.ir_type);
+                    let (lo, hi) = (self.mfunc.new_vreg(), self.mfunc.new_vreg());
+                    self.load_data(lo, src)?;
+                    if is_unsigned(src) {
+                        self.emit(MachineInst::Li { rd: hi, imm: 0 });
+                    } else {
+                        self.emit(MachineInst::OpImm {
+                            op: AluImmOp::Srai,
+                            rd: hi,
+                            rs1: lo,
+                            imm: 31,
+                        });
+                    }
+                    self.store_pair(lo, hi, -(offset as i32))?;
+                }
                 VarKind::Local | VarKind::LocalMut => {
                     let offset = self.allocator.get_fp_offset(&dest.label, &dest.ir_type);
-                    self.load_data("a5", src)?;
-                    let size = src.byte_size(RISCV32_ADDR_SIZE);
-                    self.store_data(size, "a5", -(offset as i32), "s0")?;
+                    let reg = self.mfunc.new_vreg();
+                    self.load_data(reg, src)?;
+                    let size = dest.ir_type.byte_size(RISCV32_ADDR_SIZE);
+                    self.store_data(size, reg, -(offset as i32), Reg::FP.into())?;
                 }
                 _ => unimplemented!(),
             },
@@ -212,25 +686,98 @@ impl<'w: 'codegen, 'codegen, W: Write> FuncCodeGen<'w, 'codegen, W> {
                 src1,
                 src2,
             } => {
-                debug_assert!(!src1.is_imm());
+                if is_float(src1) {
+                    return self.bin_op_float(op, dest, src1, src2);
+                }
+                let unsigned = is_unsigned(src1) || is_unsigned(src2);
+                if is_wide(src1) {
+                    return self.bin_op_wide(op, dest, src1, src2, unsigned);
+                }
+                let reg_src1 = self.mfunc.new_vreg();
+                self.load_data(reg_src1, src1)?;
                 if src2.is_imm() {
-                    self.load_data("a5", src1)?;
-                    self.bin_op_imm(op, dest, "a5", src2)?;
+                    self.bin_op_imm(op, dest, reg_src1, src2, unsigned)?;
                 } else {
-                    self.load_data("a4", src1)?;
-                    self.load_data("a5", src2)?;
-                    self.bin_op(op, dest, "a4", "a5")?;
+                    let reg_src2 = self.mfunc.new_vreg();
+                    self.load_data(reg_src2, src2)?;
+                    self.bin_op(op, dest, reg_src1, reg_src2, unsigned)?;
                 }
             }
             IRInst::Call { callee, args } => match callee {
                 Operand::FnLabel(fn_name) => {
-                    self.pass_fn_args(args)?;
-                    writeln!(self.output, "\tcall\t{}", fn_name)?;
+                    let extern_fns = self.extern_fns;
+                    let (call_args, packed_ret) =
+                        match extern_fns.iter().find(|f| &f.name == fn_name) {
+                            Some(extern_fn) => self.c_call_args(extern_fn, args)?,
+                            None => (args.iter().map(CallArg::Value).collect(), None),
+                        };
+                    let (arg_count, fp_arg_count, stack_size) = self.pass_fn_args(&call_args)?;
+                    self.emit(MachineInst::Call {
+                        callee: mangle(fn_name),
+                        arg_count,
+                        fp_arg_count,
+                    });
+                    if stack_size > 0 {
+                        self.emit(MachineInst::OpImm {
+                            op: AluImmOp::Addi,
+                            rd: Reg::SP.into(),
+                            rs1: Reg::SP.into(),
+                            imm: stack_size as i64,
+                        });
+                    }
+                    if let Some(CallArg::Packed { addr, size, align }) = packed_ret {
+                        self.store_packed_ret(addr, size, align)?;
+                    }
                 }
                 _ => unreachable!(),
             },
             IRInst::Jump { label } => {
-                writeln!(self.output, "\tj\t{}", branch_name(self.cfg.func_scope_id, *label))?;
+                self.emit(MachineInst::J(branch_name(self.cfg.func_scope_id, *label)));
+            }
+            IRInst::JumpIfCond {
+                cond,
+                src1,
+                src2,
+                label,
+            } if is_float(src1) => {
+                // only `!=` is true if any operand is NaN
+                let (cmp_op, op) = match cond {
+                    Jump::JEq => (BinOperator::EqEq, BranchOp::Bne),
+                    Jump::JNe => (BinOperator::EqEq, BranchOp::Beq),
+                    Jump::JLt => (BinOperator::Lt, BranchOp::Bne),
+                    Jump::JGe => (BinOperator::Ge, BranchOp::Bne),
+                };
+                let reg = self.compare_float(&cmp_op, src1, src2)?;
+                self.emit(MachineInst::Branch {
+                    op,
+                    rs1: reg,
+                    rs2: Reg::ZERO.into(),
+                    target: branch_name(self.cfg.func_scope_id, *label),
+                });
+            }
+            IRInst::JumpIfCond {
+                cond,
+                src1,
+                src2,
+                label,
+            } if is_wide(src1) => {
+                let unsigned = is_unsigned(src1) || is_unsigned(src2);
+                let a = self.load_wide(src1)?;
+                let b = self.load_wide(src2)?;
+                // branch on the result of `==` or `<`
+                let (cmp_op, op) = match cond {
+                    Jump::JEq => (BinOperator::EqEq, BranchOp::Bne),
+                    Jump::JNe => (BinOperator::EqEq, BranchOp::Beq),
+                    Jump::JLt => (BinOperator::Lt, BranchOp::Bne),
+                    Jump::JGe => (BinOperator::Lt, BranchOp::Beq),
+                };
+                let reg = self.compare_wide(&cmp_op, a, b, unsigned);
+                self.emit(MachineInst::Branch {
+                    op,
+                    rs1: reg,
+                    rs2: Reg::ZERO.into(),
+                    target: branch_name(self.cfg.func_scope_id, *label),
+                });
             }
             IRInst::JumpIfCond {
                 cond,
@@ -238,60 +785,545 @@ impl<'w: 'codegen, 'codegen, W: Write> FuncCodeGen<'w, 'codegen, W> {
                 src2,
                 label,
             } => {
-                self.load_data("a4", src1)?;
-                self.load_data("a5", src2)?;
-                let inst = match cond {
-                    Jump::JEq => "beq",
-                    Jump::JGe => "ble",
-                    Jump::JLt => "bgt",
-                    Jump::JNe => "beq",
+                let reg_src1 = self.mfunc.new_vreg();
+                let reg_src2 = self.mfunc.new_vreg();
+                self.load_data(reg_src1, src1)?;
+                self.load_data(reg_src2, src2)?;
+                let unsigned = is_unsigned(src1) || is_unsigned(src2);
+                let op = match (cond, unsigned) {
+                    (Jump::JEq, _) => BranchOp::Beq,
+                    (Jump::JNe, _) => BranchOp::Bne,
+                    (Jump::JLt, false) => BranchOp::Blt,
+                    (Jump::JLt, true) => BranchOp::Bltu,
+                    (Jump::JGe, false) => BranchOp::Bge,
+                    (Jump::JGe, true) => BranchOp::Bgeu,
+                };
+                self.emit(MachineInst::Branch {
+                    op,
+                    rs1: reg_src1,
+                    rs2: reg_src2,
+                    target: branch_name(self.cfg.func_scope_id, *label),
+                });
+            }
+            IRInst::JumpIf { cond, label } | IRInst::JumpIfNot { cond, label } => {
+                let reg = self.mfunc.new_vreg();
+                self.load_data(reg, cond)?;
+                // booleans are 0 or 1
+                let op = match inst {
+                    IRInst::JumpIf { .. } => BranchOp::Bne,
+                    _ => BranchOp::Beq,
                 };
-                writeln!(self.output, "\t{}\ta5,a4,{}", inst, branch_name(self.cfg.func_scope_id, *label))?;
+                self.emit(MachineInst::Branch {
+                    op,
+                    rs1: reg,
+                    rs2: Reg::ZERO.into(),
+                    target: branch_name(self.cfg.func_scope_id, *label),
+                });
+            }
+            IRInst::JumpTable {
+                src,
+                low,
+                labels,
+                default,
+            } => {
+                let scope_id = self.cfg.func_scope_id;
+                let value = self.mfunc.new_vreg();
+                self.load_data(value, src)?;
+                // index = src - low, values less than `low` become large unsigned indexes
+                let index = self.mfunc.new_vreg();
+                if (-2047..=2048).contains(low) {
+                    self.emit(MachineInst::OpImm {
+                        op: AluImmOp::Addi,
+                        rd: index,
+                        rs1: value,
+                        imm: -low,
+                    });
+                } else {
+                    let reg_low = self.mfunc.new_vreg();
+                    self.emit(MachineInst::Li {
+                        rd: reg_low,
+                        imm: *low,
+                    });
+                    self.emit(MachineInst::Op {
+                        op: AluOp::Sub,
+                        rd: index,
+                        rs1: value,
+                        rs2: reg_low,
+                    });
+                }
+                let len = self.mfunc.new_vreg();
+                self.emit(MachineInst::Li {
+                    rd: len,
+                    imm: labels.len() as i64,
+                });
+                self.emit(MachineInst::Branch {
+                    op: BranchOp::Bgeu,
+                    rs1: index,
+                    rs2: len,
+                    target: branch_name(scope_id, *default),
+                });
+
+                // each entry of the table is a `j` instruction of 4 bytes
+                let offset = self.mfunc.new_vreg();
+                self.emit(MachineInst::OpImm {
+                    op: AluImmOp::Slli,
+                    rd: offset,
+                    rs1: index,
+                    imm: 2,
+                });
+                let table = jump_table_name(scope_id, self.mfunc.blocks.len() - 1);
+                let base = self.mfunc.new_vreg();
+                self.emit(MachineInst::La {
+                    rd: base,
+                    symbol: table.clone(),
+                });
+                let addr = self.mfunc.new_vreg();
+                self.emit(MachineInst::Op {
+                    op: AluOp::Add,
+                    rd: addr,
+                    rs1: base,
+                    rs2: offset,
+                });
+                self.emit(MachineInst::JumpTable {
+                    rs: addr,
+                    label: table,
+                    targets: labels
+                        .iter()
+                        .map(|label| branch_name(scope_id, *label))
+                        .collect(),
+                });
+            }
+            IRInst::Cast { dest, src } => self.cast(dest, src)?,
+            IRInst::Neg { dest, src } => self.neg_float(dest, src)?,
+            IRInst::LoadAddr { dest, symbol } => {
+                let reg = self.mfunc.new_vreg();
+                match symbol {
+                    // statics are addressed by their labels
+                    Operand::Place(p) if matches!(p.kind, VarKind::Static | VarKind::StaticMut) => {
+                        self.emit(MachineInst::La {
+                            rd: reg,
+                            symbol: p.label.clone(),
+                        });
+                    }
+                    Operand::Place(p) => {
+                        let (base, offset) = self.slot(p);
+                        self.emit(MachineInst::OpImm {
+                            op: AluImmOp::Addi,
+                            rd: reg,
+                            rs1: base,
+                            imm: offset as i64,
+                        });
+                    }
+                    o => unimplemented!("address of {:?}", o),
+                }
+                let (base, offset) = self.slot(dest);
+                self.store_data(4, reg, offset, base)?;
+            }
+            IRInst::LoadMem { dest, base, offset } => {
+                let src = self.mem_addr(base, *offset)?;
+                let dest_slot = self.slot(dest);
+                self.copy_memory(&dest.ir_type, src, dest_slot);
             }
-            IRInst::JumpIfNot { cond, label } => {
-                self.load_data("a5", cond)?;
-                // writeln!(self.output, "\t")?;
-                todo!()
+            IRInst::StoreMem { base, offset, src } => {
+                let (base, offset) = self.mem_addr(base, *offset)?;
+                match src {
+                    Operand::Place(p) if matches!(p.kind, VarKind::Local | VarKind::LocalMut) => {
+                        let src = self.slot(p);
+                        self.copy_memory(&p.ir_type, src, (base, offset));
+                    }
+                    Operand::Unit | Operand::Never => {}
+                    src if self.is_hard_float(src) => {
+                        let fmt = fp_fmt(src);
+                        let reg = self.mfunc.new_float_vreg();
+                        self.load_float(reg, src)?;
+                        self.emit(MachineInst::Store {
+                            op: if fmt == FpFmt::S { StoreOp::Fsw } else { StoreOp::Fsd },
+                            rs: reg,
+                            offset,
+                            base,
+                        });
+                    }
+                    src if is_wide(src) => {
+                        let (lo, hi) = self.load_wide(src)?;
+                        self.store_data(4, lo, offset, base)?;
+                        self.store_data(4, hi, offset + 4, base)?;
+                    }
+                    src => {
+                        let reg = self.mfunc.new_vreg();
+                        self.load_data(reg, src)?;
+                        self.store_data(src.byte_size(RISCV32_ADDR_SIZE), reg, offset, base)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Base register and offset of the stack slot of a local variable.
+    fn slot(&mut self, place: &Place) -> (MReg, i32) {
+        let offset = self.allocator.get_fp_offset(&place.label, &place.ir_type);
+        (Reg::FP.into(), -(offset as i32))
+    }
+
+    /// Base register and offset of the memory at `base + offset`,
+    /// `base` is an address or an aggregate on the stack.
+    fn mem_addr(&mut self, base: &Operand, offset: u32) -> Result<(MReg, i32), RccError> {
+        match base {
+            Operand::Place(p) if p.ir_type.is_aggregate() => {
+                let (reg, slot_offset) = self.slot(p);
+                Ok((reg, slot_offset + offset as i32))
             }
             _ => {
-                todo!()
+                let reg = self.mfunc.new_vreg();
+                self.load_data(reg, base)?;
+                Ok((reg, offset as i32))
+            }
+        }
+    }
+
+    /// Copy a value of `ir_type` between memory, by the widest loads and stores its alignment allows.
+    /// Values on the stack are kept in the same representation as in memory.
+    fn copy_memory(&mut self, ir_type: &IRType, src: (MReg, i32), dest: (MReg, i32)) {
+        let (chunk, load_op, store_op) = match ir_type.align(RISCV32_ADDR_SIZE) {
+            1 => (1, LoadOp::Lbu, StoreOp::Sb),
+            2 => (2, LoadOp::Lhu, StoreOp::Sh),
+            _ => (4, LoadOp::Lw, StoreOp::Sw),
+        };
+        for i in (0..ir_type.byte_size(RISCV32_ADDR_SIZE) as i32).step_by(chunk) {
+            let reg = self.mfunc.new_vreg();
+            self.emit(MachineInst::Load {
+                op: load_op,
+                rd: reg,
+                offset: src.1 + i,
+                base: src.0,
+            });
+            self.emit(MachineInst::Store {
+                op: store_op,
+                rs: reg,
+                offset: dest.1 + i,
+                base: dest.0,
+            });
+        }
+    }
+
+    /// Pass the arguments by registers and the stack, return the number of argument registers,
+    /// floating-point argument registers and the bytes reserved on the stack.
+    fn pass_fn_args(&mut self, args: &[CallArg]) -> Result<(usize, usize, u32), RccError> {
+        let (locations, stack_size) = arg_locations(args.iter().map(|arg| match arg {
+            CallArg::Value(arg) => (arg.byte_size(RISCV32_ADDR_SIZE), self.is_hard_float(arg)),
+            CallArg::Packed { size, .. } => (if *size <= 4 { 4 } else { 8 }, false),
+        }));
+        // sp is kept aligned to 16 bytes
+        let stack_size = stack_size.next_multiple_of(16);
+        if stack_size > 0 {
+            self.emit(MachineInst::OpImm {
+                op: AluImmOp::Addi,
+                rd: Reg::SP.into(),
+                rs1: Reg::SP.into(),
+                imm: -(stack_size as i64),
+            });
+        }
+        let (mut reg_count, mut fp_reg_count) = (0, 0);
+        for (arg, location) in args.iter().zip(locations) {
+            let arg = match (arg, &location) {
+                (CallArg::Value(arg), _) => *arg,
+                (CallArg::Packed { addr, size, align }, ArgLoc::Int(words)) => {
+                    let regs = self.arg_regs(words);
+                    self.load_packed(&regs, addr, *size, *align)?;
+                    reg_count += self.store_stack_args(words, regs)?;
+                    continue;
+                }
+                (CallArg::Packed { .. }, ArgLoc::Float(_)) => unreachable!(),
+            };
+            match location {
+                ArgLoc::Float(idx) => {
+                    self.load_float(Reg::fp_arg(idx).into(), arg)?;
+                    fp_reg_count += 1;
+                }
+                ArgLoc::Int(words) => {
+                    let regs = self.arg_regs(&words);
+                    // floating-point numbers are loaded as the bits of their representation
+                    if is_wide(arg) {
+                        self.load_pair(regs[0], regs[1], arg)?;
+                    } else {
+                        self.load_data(regs[0], arg)?;
+                    }
+                    reg_count += self.store_stack_args(&words, regs)?;
+                }
+            }
+        }
+        Ok((reg_count, fp_reg_count, stack_size))
+    }
+
+    /// Registers of the words of an argument, the words passed by the stack are loaded
+    /// into virtual registers.
+    fn arg_regs(&mut self, words: &[ArgWord]) -> Vec<MReg> {
+        words
+            .iter()
+            .map(|word| match word {
+                ArgWord::Reg(idx) => Reg::arg(*idx).into(),
+                ArgWord::Stack(_) => self.mfunc.new_vreg(),
+            })
+            .collect()
+    }
+
+    /// Store the words passed by the stack, return the number of argument registers.
+    fn store_stack_args(&mut self, words: &[ArgWord], regs: Vec<MReg>) -> Result<usize, RccError> {
+        let mut reg_count = 0;
+        for (word, reg) in words.iter().zip(regs) {
+            match word {
+                ArgWord::Reg(_) => reg_count += 1,
+                ArgWord::Stack(offset) => self.store_data(4, reg, *offset, Reg::SP.into())?,
+            }
+        }
+        Ok(reg_count)
+    }
+
+    /// Arguments of a call of the C function `extern_fn` by the integer calling convention
+    /// of ILP32, and the aggregate returned in registers.
+    ///
+    /// Aggregates of at most 8 bytes are passed in registers like integers and returned in
+    /// `a0` and `a1`, larger aggregates are passed by the addresses of their copies and
+    /// returned by storing to the address passed as the hidden first argument,
+    /// the same as the other functions.
+    fn c_call_args<'a>(
+        &self,
+        extern_fn: &ExternFn,
+        args: &'a [Operand],
+    ) -> Result<(Vec<CallArg<'a>>, Option<CallArg<'a>>), RccError> {
+        let mut args = args.iter();
+        let mut call_args = vec![];
+        let mut packed_ret = None;
+        if let IRType::Aggregate { size, align } = extern_fn.ret_type {
+            self.check_c_aggregate(&extern_fn.name, &extern_fn.ret_fields)?;
+            let addr = args.next().unwrap();
+            if size <= 8 {
+                packed_ret = Some(CallArg::Packed { addr, size, align });
+            } else {
+                call_args.push(CallArg::Value(addr));
+            }
+        }
+        let params = extern_fn
+            .param_types
+            .iter()
+            .zip(extern_fn.param_fields.iter());
+        for (arg, (ir_type, fields)) in args.zip(params) {
+            call_args.push(match *ir_type {
+                IRType::Aggregate { size, align } if size <= 8 => {
+                    self.check_c_aggregate(&extern_fn.name, fields)?;
+                    CallArg::Packed {
+                        addr: arg,
+                        size,
+                        align,
+                    }
+                }
+                IRType::Aggregate { .. } => {
+                    self.check_c_aggregate(&extern_fn.name, fields)?;
+                    CallArg::Value(arg)
+                }
+                _ => CallArg::Value(arg),
+            });
+        }
+        Ok((call_args, packed_ret))
+    }
+
+    /// The hard-float ABI passes the aggregates of a floating-point number, two floating-point
+    /// numbers, or a floating-point number and an integer of at most 4 bytes, in the
+    /// floating-point registers, which is not supported.
+    fn check_c_aggregate(&self, fn_name: &str, fields: &[(u32, IRType)]) -> Result<(), RccError> {
+        let is_float = |t: &IRType| matches!(t, IRType::F32 | IRType::F64);
+        let in_fp_regs = fields.len() <= 2
+            && fields.iter().any(|(_, t)| is_float(t))
+            && fields
+                .iter()
+                .all(|(_, t)| is_float(t) || t.byte_size(RISCV32_ADDR_SIZE) <= 4);
+        if !self.soft_float && in_fp_regs {
+            return Err(format!(
+                "aggregates of floating-point numbers cannot be passed to `{}` by the hard-float \
+                 ABI",
+                fn_name
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Load the `size` bytes at the address `addr` into the registers of its words,
+    /// by the loads of the alignment of the aggregate.
+    fn load_packed(
+        &mut self,
+        regs: &[MReg],
+        addr: &Operand,
+        size: u32,
+        align: u32,
+    ) -> Result<(), RccError> {
+        let base = self.mfunc.new_vreg();
+        self.load_data(base, addr)?;
+        let (op, step) = match align {
+            1 => (LoadOp::Lbu, 1),
+            2 => (LoadOp::Lhu, 2),
+            _ => (LoadOp::Lw, 4),
+        };
+        for (i, &reg) in regs.iter().enumerate() {
+            let word_offset = 4 * i as u32;
+            for offset in (0..(size - word_offset).min(4)).step_by(step as usize) {
+                let rd = if offset == 0 {
+                    reg
+                } else {
+                    self.mfunc.new_vreg()
+                };
+                self.emit(MachineInst::Load {
+                    op,
+                    rd,
+                    offset: (word_offset + offset) as i32,
+                    base,
+                });
+                if offset != 0 {
+                    self.emit(MachineInst::OpImm {
+                        op: AluImmOp::Slli,
+                        rd,
+                        rs1: rd,
+                        imm: 8 * offset as i64,
+                    });
+                    self.emit(MachineInst::Op {
+                        op: AluOp::Or,
+                        rd: reg,
+                        rs1: reg,
+                        rs2: rd,
+                    });
+                }
             }
         }
         Ok(())
     }
 
-    fn pass_fn_args(&mut self, args: &[Operand]) -> Result<(), RccError> {
-        for (i, arg) in args.iter().enumerate() {
-            // pass by registers
-            if i <= 7 {
-                self.load_data(&format!("a{}", i), arg)?;
+    /// Store the aggregate of `size` bytes returned in `a0` and `a1` to the address `addr`,
+    /// by the stores of the alignment of the aggregate.
+    fn store_packed_ret(&mut self, addr: &Operand, size: u32, align: u32) -> Result<(), RccError> {
+        let mut words = vec![];
+        for i in 0..size.div_ceil(4) {
+            let word = self.mfunc.new_vreg();
+            self.emit(MachineInst::Mv {
+                rd: word,
+                rs: Reg::arg(i as usize).into(),
+            });
+            words.push(word);
+        }
+        let base = self.mfunc.new_vreg();
+        self.load_data(base, addr)?;
+        let step = align.min(4);
+        for (i, word) in words.into_iter().enumerate() {
+            let word_offset = 4 * i as u32;
+            for offset in (0..(size - word_offset).min(4)).step_by(step as usize) {
+                let rs = if offset == 0 {
+                    word
+                } else {
+                    let rs = self.mfunc.new_vreg();
+                    self.emit(MachineInst::OpImm {
+                        op: AluImmOp::Srli,
+                        rd: rs,
+                        rs1: word,
+                        imm: 8 * offset as i64,
+                    });
+                    rs
+                };
+                self.store_data(step, rs, (word_offset + offset) as i32, base)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Load a 64-bit integer into `lo` and `hi`.
+    fn load_pair(&mut self, lo: MReg, hi: MReg, operand: &Operand) -> Result<(), RccError> {
+        let asm_operand = AsmOperand::from_operand(operand, &mut *self.allocator);
+        match asm_operand {
+            AsmOperand::Imm64(low, high) => {
+                self.emit(MachineInst::Li {
+                    rd: lo,
+                    imm: low.parse()?,
+                });
+                self.emit(MachineInst::Li {
+                    rd: hi,
+                    imm: high.parse()?,
+                });
+            }
+            AsmOperand::FpOffset(offset) => {
+                // little endian
+                for (rd, offset) in [(lo, -(offset as i32)), (hi, 4 - offset as i32)] {
+                    self.emit(MachineInst::Load {
+                        op: LoadOp::Lw,
+                        rd,
+                        offset,
+                        base: Reg::FP.into(),
+                    });
+                }
+            }
+            AsmOperand::FnRet(_) => {
+                for (rd, rs) in [(lo, Reg::A0), (hi, Reg::arg(1))] {
+                    if rd != MReg::Phys(rs) {
+                        self.emit(MachineInst::Mv { rd, rs: rs.into() });
+                    }
+                }
             }
+            AsmOperand::Never | AsmOperand::Unit => {}
+            _ => unimplemented!("{:?}", asm_operand),
         }
         Ok(())
     }
 
-    fn load_data(&mut self, reg_name: &str, operand: &Operand) -> Result<(), RccError> {
+    fn load_wide(&mut self, operand: &Operand) -> Result<(MReg, MReg), RccError> {
+        let (lo, hi) = (self.mfunc.new_vreg(), self.mfunc.new_vreg());
+        self.load_pair(lo, hi, operand)?;
+        Ok((lo, hi))
+    }
+
+    /// Store a 64-bit integer to the stack slot at `offset` from fp.
+    fn store_pair(&mut self, lo: MReg, hi: MReg, offset: i32) -> Result<(), RccError> {
+        self.store_data(4, lo, offset, Reg::FP.into())?;
+        self.store_data(4, hi, offset + 4, Reg::FP.into())
+    }
+
+    fn load_data(&mut self, reg: MReg, operand: &Operand) -> Result<(), RccError> {
         let asm_operand = AsmOperand::from_operand(operand, &mut *self.allocator);
         let size = operand.byte_size(RISCV32_ADDR_SIZE);
         match asm_operand {
             AsmOperand::Imm(s) => {
-                writeln!(self.output, "\tli\t{},{}", reg_name, s)?;
+                self.emit(MachineInst::Li {
+                    rd: reg,
+                    imm: s.parse()?,
+                });
             }
+            // zero sized places like the return value of `fn foo() {}`
+            AsmOperand::FpOffset(_) if size == 0 => {}
             AsmOperand::FpOffset(offset) => {
-                let inst = match size {
-                    4 => "lw",
-                    _ => todo!(),
+                let op = match (size, is_unsigned(operand)) {
+                    (1, false) => LoadOp::Lb,
+                    (1, true) => LoadOp::Lbu,
+                    (2, false) => LoadOp::Lh,
+                    (2, true) => LoadOp::Lhu,
+                    (4, _) => LoadOp::Lw,
+                    _ => return Err(format!("cannot load {} bytes into a register", size).into()),
                 };
-                writeln!(self.output, "\t{}\t{},-{}(s0)", inst, reg_name, offset)?;
+                self.emit(MachineInst::Load {
+                    op,
+                    rd: reg,
+                    offset: -(offset as i32),
+                    base: Reg::FP.into(),
+                });
             }
             AsmOperand::Never | AsmOperand::Unit => {}
             AsmOperand::FnRet(_ir_type) => match size {
-                4 => {
-                    if reg_name != "a0" {
-                        writeln!(self.output, "\tmv\t{},a0", reg_name)?;
+                0 => {}
+                // the callee has extended the value to 32 bits
+                1 | 2 | 4 => {
+                    if reg != MReg::Phys(Reg::A0) {
+                        self.emit(MachineInst::Mv {
+                            rd: reg,
+                            rs: Reg::A0.into(),
+                        });
                     }
                 }
-                _ => todo!(),
+                _ => return Err(format!("cannot load {} bytes into a register", size).into()),
             },
             _ => unimplemented!("{:?}", asm_operand),
         }
@@ -302,21 +1334,716 @@ impl<'w: 'codegen, 'codegen, W: Write> FuncCodeGen<'w, 'codegen, W> {
     fn store_data(
         &mut self,
         src_byte_size: u32,
-        src_reg_name: &str,
+        src_reg: MReg,
         offset: i32,
-        tar_reg_name: &str,
+        tar_reg: MReg,
     ) -> Result<(), RccError> {
-        let inst = match src_byte_size {
-            1 => "sb",
-            2 => "sh",
-            4 => "sw",
-            _ => todo!(),
+        let op = match src_byte_size {
+            1 => StoreOp::Sb,
+            2 => StoreOp::Sh,
+            4 => StoreOp::Sw,
+            _ => {
+                return Err(format!("cannot store {} bytes from a register", src_byte_size).into())
+            }
         };
-        writeln!(
-            self.output,
-            "\t{}\t{},{}({})",
-            inst, src_reg_name, offset, tar_reg_name
-        )?;
+        self.emit(MachineInst::Store {
+            op,
+            rs: src_reg,
+            offset,
+            base: tar_reg,
+        });
+        Ok(())
+    }
+
+    /// Whether the operand is kept in a floating-point register
+    fn is_hard_float(&self, operand: &Operand) -> bool {
+        !self.soft_float && is_float(operand)
+    }
+
+    /// Format of the type if it is kept in floating-point registers
+    fn hard_float_fmt(&self, ir_type: &IRType) -> Option<FpFmt> {
+        match ir_type {
+            IRType::F32 if !self.soft_float => Some(FpFmt::S),
+            IRType::F64 if !self.soft_float => Some(FpFmt::D),
+            _ => None,
+        }
+    }
+
+    /// Load a floating-point number into a floating-point register.
+    fn load_float(&mut self, reg: MReg, operand: &Operand) -> Result<(), RccError> {
+        let fmt = fp_fmt(operand);
+        match operand {
+            Operand::F32(f) => return self.load_float_const(reg, f.to_bits() as u64, fmt),
+            Operand::F64(f) => return self.load_float_const(reg, f.to_bits(), fmt),
+            _ => {}
+        }
+        match AsmOperand::from_operand(operand, &mut *self.allocator) {
+            AsmOperand::FpOffset(offset) => {
+                self.emit(MachineInst::Load {
+                    op: if fmt == FpFmt::S { LoadOp::Flw } else { LoadOp::Fld },
+                    rd: reg,
+                    offset: -(offset as i32),
+                    base: Reg::FP.into(),
+                });
+            }
+            AsmOperand::FnRet(_) => {
+                if reg != MReg::Phys(Reg::FA0) {
+                    self.emit(MachineInst::FMv {
+                        fmt,
+                        rd: reg,
+                        rs: Reg::FA0.into(),
+                    });
+                }
+            }
+            AsmOperand::Never | AsmOperand::Unit => {}
+            asm_operand => unimplemented!("{:?}", asm_operand),
+        }
+        Ok(())
+    }
+
+    /// `0.0` is converted from `zero`, other constants are loaded from the literal pool.
+    fn load_float_const(&mut self, reg: MReg, bits: u64, fmt: FpFmt) -> Result<(), RccError> {
+        if bits == 0 {
+            self.emit(MachineInst::FCvt {
+                to: fmt,
+                from: FpFmt::W,
+                rd: reg,
+                rs: Reg::ZERO.into(),
+            });
+            return Ok(());
+        }
+        let (op, size) = match fmt {
+            FpFmt::S => (LoadOp::Flw, 4),
+            _ => (LoadOp::Fld, 8),
+        };
+        let addr = self.mfunc.new_vreg();
+        let symbol = self.literal_pool.label(bits, size);
+        self.emit(MachineInst::La { rd: addr, symbol });
+        self.emit(MachineInst::Load {
+            op,
+            rd: reg,
+            offset: 0,
+            base: addr,
+        });
+        Ok(())
+    }
+
+    /// Store a floating-point register to the stack slot at `offset` from fp.
+    fn store_float(&mut self, fmt: FpFmt, reg: MReg, offset: i32) -> Result<(), RccError> {
+        self.emit(MachineInst::Store {
+            op: if fmt == FpFmt::S { StoreOp::Fsw } else { StoreOp::Fsd },
+            rs: reg,
+            offset,
+            base: Reg::FP.into(),
+        });
+        Ok(())
+    }
+
+    /// `dest = src as dest.ir_type`, integers on the stack are kept in the representation of
+    /// memory, so they are truncated by the stores and extended by the loads of their types.
+    fn cast(&mut self, dest: &Place, src: &Operand) -> Result<(), RccError> {
+        let offset = -(self.allocator.get_fp_offset(&dest.label, &dest.ir_type) as i32);
+        let size = dest.ir_type.byte_size(RISCV32_ADDR_SIZE);
+        match (is_float(src), matches!(dest.ir_type, IRType::F32 | IRType::F64)) {
+            (false, false) if is_wide(src) && size <= 4 => {
+                let (lo, _) = self.load_wide(src)?;
+                self.store_data(size, lo, offset, Reg::FP.into())
+            }
+            (false, false) => self.gen_instruction(&IRInst::load_data(dest.clone(), src.clone())),
+            (true, true) => self.cast_float(dest.ir_type, src, offset),
+            (false, true) if self.soft_float => self.soft_int_to_float(dest.ir_type, src, offset),
+            (true, false) if self.soft_float => self.soft_float_to_int(&dest.ir_type, src, offset),
+            (false, true) if is_wide(src) => self.wide_to_float(dest.ir_type, src, offset),
+            (true, false) if size == 8 => {
+                let (lo, hi) = self.float_to_wide(&dest.ir_type, src)?;
+                self.store_pair(lo, hi, offset)
+            }
+            (false, true) => {
+                let fmt = if dest.ir_type == IRType::F32 { FpFmt::S } else { FpFmt::D };
+                // extended to 32 bits by the load
+                let rs = self.mfunc.new_vreg();
+                self.load_data(rs, src)?;
+                let rd = self.mfunc.new_float_vreg();
+                self.emit(MachineInst::FCvt {
+                    to: fmt,
+                    from: if is_unsigned(src) { FpFmt::Wu } else { FpFmt::W },
+                    rd,
+                    rs,
+                });
+                self.store_float(fmt, rd, offset)
+            }
+            (true, false) => {
+                let rd = self.float_to_int(&dest.ir_type, src)?;
+                self.store_data(size, rd, offset, Reg::FP.into())
+            }
+        }
+    }
+
+    /// Convert between `f32` and `f64`, they are calls of the runtime with soft-float.
+    fn cast_float(&mut self, ir_type: IRType, src: &Operand, offset: i32) -> Result<(), RccError> {
+        let from = fp_fmt(src);
+        let to = if ir_type == IRType::F32 { FpFmt::S } else { FpFmt::D };
+        if self.soft_float {
+            if to == FpFmt::D {
+                self.load_data(Reg::A0.into(), src)?;
+                self.emit(MachineInst::Call {
+                    callee: "__extendsfdf2".to_string(),
+                    arg_count: 1,
+                    fp_arg_count: 0,
+                });
+            } else {
+                self.load_pair(Reg::A0.into(), Reg::arg(1).into(), src)?;
+                self.emit(MachineInst::Call {
+                    callee: "__truncdfsf2".to_string(),
+                    arg_count: 2,
+                    fp_arg_count: 0,
+                });
+            }
+            return self.store_soft_float_ret(ir_type, offset);
+        }
+        let rs = self.mfunc.new_float_vreg();
+        self.load_float(rs, src)?;
+        let rd = self.mfunc.new_float_vreg();
+        self.emit(MachineInst::FCvt { to, from, rd, rs });
+        self.store_float(to, rd, offset)
+    }
+
+    /// Convert a floating-point number to an integer of `ir_type` rounding towards zero,
+    /// `fcvt` saturates to 32-bit integers and narrower integers are clamped before it,
+    /// `NaN` is converted to the maximum by `fcvt` so it is masked to `0`.
+    fn float_to_int(&mut self, ir_type: &IRType, src: &Operand) -> Result<MReg, RccError> {
+        let fmt = fp_fmt(src);
+        let value = self.mfunc.new_float_vreg();
+        self.load_float(value, src)?;
+        let mut rs = value;
+        if let Some((min, max)) = narrow_bounds(ir_type) {
+            for (op, bound) in [(FpOp::Fmax, min as f64), (FpOp::Fmin, max as f64)] {
+                let bits = match fmt {
+                    FpFmt::S => (bound as f32).to_bits() as u64,
+                    _ => bound.to_bits(),
+                };
+                let reg = self.mfunc.new_float_vreg();
+                self.load_float_const(reg, bits, fmt)?;
+                let rd = self.mfunc.new_float_vreg();
+                self.emit(MachineInst::FOp {
+                    op,
+                    fmt,
+                    rd,
+                    rs1: rs,
+                    rs2: reg,
+                });
+                rs = rd;
+            }
+        }
+        let int = self.mfunc.new_vreg();
+        self.emit(MachineInst::FCvt {
+            to: if matches!(ir_type, IRType::U32 | IRType::Usize) {
+                FpFmt::Wu
+            } else {
+                FpFmt::W
+            },
+            from: fmt,
+            rd: int,
+            rs,
+        });
+        // mask = -(value == value)
+        let eq = self.mfunc.new_vreg();
+        self.emit(MachineInst::FOp {
+            op: FpOp::Feq,
+            fmt,
+            rd: eq,
+            rs1: value,
+            rs2: value,
+        });
+        let mask = self.mfunc.new_vreg();
+        self.emit(MachineInst::Op {
+            op: AluOp::Sub,
+            rd: mask,
+            rs1: Reg::ZERO.into(),
+            rs2: eq,
+        });
+        let rd = self.mfunc.new_vreg();
+        self.emit(MachineInst::Op {
+            op: AluOp::And,
+            rd,
+            rs1: int,
+            rs2: mask,
+        });
+        Ok(rd)
+    }
+
+    /// Convert a 64-bit integer to a floating-point number, `hi * 2^32 + lo` is exact before
+    /// the rounding of the addition. A `f32` would be rounded twice, so an integer of more than
+    /// 53 bits is narrowed first: its low 12 bits are replaced with a sticky bit 11, then the
+    /// double is exact and rounded to the same `f32` as the integer.
+    fn wide_to_float(
+        &mut self,
+        ir_type: IRType,
+        src: &Operand,
+        offset: i32,
+    ) -> Result<(), RccError> {
+        let unsigned = is_unsigned(src);
+        let (mut lo, hi) = self.load_wide(src)?;
+        if ir_type == IRType::F32 {
+            // mask = -(|hi| >= 2^21)
+            let magnitude = if unsigned {
+                hi
+            } else {
+                let sign = self.emit_op_imm(AluImmOp::Srai, hi, 31);
+                self.emit_op(AluOp::Xor, hi, sign)
+            };
+            let bound = self.mfunc.new_vreg();
+            self.emit(MachineInst::Li {
+                rd: bound,
+                imm: 0x1fffff,
+            });
+            let wide = self.emit_op(AluOp::Sltu, bound, magnitude);
+            let mask = self.emit_op(AluOp::Sub, Reg::ZERO.into(), wide);
+            // lo & 0xfff
+            let low_bits = self.emit_op_imm(AluImmOp::Slli, lo, 20);
+            let low_bits = self.emit_op_imm(AluImmOp::Srli, low_bits, 20);
+            let sticky = self.emit_op(AluOp::Sltu, Reg::ZERO.into(), low_bits);
+            let sticky = self.emit_op_imm(AluImmOp::Slli, sticky, 11);
+            let narrowed = self.emit_op(AluOp::Xor, lo, low_bits);
+            let narrowed = self.emit_op(AluOp::Or, narrowed, sticky);
+            let diff = self.emit_op(AluOp::Xor, lo, narrowed);
+            let diff = self.emit_op(AluOp::And, diff, mask);
+            lo = self.emit_op(AluOp::Xor, lo, diff);
+        }
+        let from = if unsigned { FpFmt::Wu } else { FpFmt::W };
+        let high = self.emit_fcvt(FpFmt::D, from, hi);
+        let low = self.emit_fcvt(FpFmt::D, FpFmt::Wu, lo);
+        let scale = self.mfunc.new_float_vreg();
+        self.load_float_const(scale, 2f64.powi(32).to_bits(), FpFmt::D)?;
+        let high = self.emit_fp_op(FpOp::Fmul, high, scale);
+        let value = self.emit_fp_op(FpOp::Fadd, high, low);
+        if ir_type == IRType::F32 {
+            let rd = self.emit_fcvt(FpFmt::S, FpFmt::D, value);
+            self.store_float(FpFmt::S, rd, offset)
+        } else {
+            self.store_float(FpFmt::D, value, offset)
+        }
+    }
+
+    /// Convert a floating-point number to a 64-bit integer rounding towards zero. The magnitude
+    /// `m` (clamped to 2^63 if signed) is split into `hi = m / 2^32` and `lo = m - hi * 2^32`
+    /// by `fcvt`, which saturates both words if `m` is out of range. A signed magnitude of 2^63
+    /// is decreased to the maximum if positive, then it is negated if negative. `NaN` is masked
+    /// to `0` like `float_to_int`.
+    fn float_to_wide(&mut self, ir_type: &IRType, src: &Operand) -> Result<(MReg, MReg), RccError> {
+        let value = self.mfunc.new_float_vreg();
+        self.load_float(value, src)?;
+        // the conversions are exact in double precision
+        let value = if fp_fmt(src) == FpFmt::S {
+            self.emit_fcvt(FpFmt::D, FpFmt::S, value)
+        } else {
+            value
+        };
+        let zero = self.mfunc.new_float_vreg();
+        self.load_float_const(zero, 0, FpFmt::D)?;
+        let signed = *ir_type == IRType::I64;
+        let magnitude = if signed {
+            let abs = self.emit_fp_op(FpOp::Fsgnjx, value, value);
+            let bound = self.mfunc.new_float_vreg();
+            self.load_float_const(bound, 2f64.powi(63).to_bits(), FpFmt::D)?;
+            self.emit_fp_op(FpOp::Fmin, abs, bound)
+        } else {
+            // negative numbers and `NaN` are converted to `0`
+            self.emit_fp_op(FpOp::Fmax, value, zero)
+        };
+        let scale = self.mfunc.new_float_vreg();
+        self.load_float_const(scale, 2f64.powi(-32).to_bits(), FpFmt::D)?;
+        let high = self.emit_fp_op(FpOp::Fmul, magnitude, scale);
+        let hi = self.emit_fcvt(FpFmt::Wu, FpFmt::D, high);
+        let high = self.emit_fcvt(FpFmt::D, FpFmt::Wu, hi);
+        let unit = self.mfunc.new_float_vreg();
+        self.load_float_const(unit, 2f64.powi(32).to_bits(), FpFmt::D)?;
+        let high = self.emit_fp_op(FpOp::Fmul, high, unit);
+        let low = self.emit_fp_op(FpOp::Fsub, magnitude, high);
+        let lo = self.emit_fcvt(FpFmt::Wu, FpFmt::D, low);
+        if !signed {
+            return Ok((lo, hi));
+        }
+        let neg = self.emit_fp_op(FpOp::Flt, value, zero);
+        // (lo, hi) -= (hi >> 31) & !neg
+        let pos = self.emit_op_imm(AluImmOp::Xori, neg, 1);
+        let overflow = self.emit_op_imm(AluImmOp::Srli, hi, 31);
+        let overflow = self.emit_op(AluOp::And, overflow, pos);
+        let lo = self.emit_op(AluOp::Sub, lo, overflow);
+        let hi = self.emit_op(AluOp::Sub, hi, overflow);
+        // (lo, hi) = (-lo, -hi - (lo != 0)) if neg
+        let mask = self.emit_op(AluOp::Sub, Reg::ZERO.into(), neg);
+        let borrow = self.emit_op(AluOp::Sltu, Reg::ZERO.into(), lo);
+        let borrow = self.emit_op(AluOp::And, borrow, neg);
+        let lo = self.emit_op(AluOp::Xor, lo, mask);
+        let lo = self.emit_op(AluOp::Sub, lo, mask);
+        let hi = self.emit_op(AluOp::Xor, hi, mask);
+        let hi = self.emit_op(AluOp::Sub, hi, mask);
+        let hi = self.emit_op(AluOp::Sub, hi, borrow);
+        // mask = -(value == value)
+        let eq = self.emit_fp_op(FpOp::Feq, value, value);
+        let mask = self.emit_op(AluOp::Sub, Reg::ZERO.into(), eq);
+        let lo = self.emit_op(AluOp::And, lo, mask);
+        let hi = self.emit_op(AluOp::And, hi, mask);
+        Ok((lo, hi))
+    }
+
+    /// Convert an integer to a floating-point number by the soft-float runtime.
+    fn soft_int_to_float(
+        &mut self,
+        ir_type: IRType,
+        src: &Operand,
+        offset: i32,
+    ) -> Result<(), RccError> {
+        let (int, arg_count) = if is_wide(src) {
+            self.load_pair(Reg::A0.into(), Reg::arg(1).into(), src)?;
+            ("di", 2)
+        } else {
+            // extended to 32 bits by the load
+            self.load_data(Reg::A0.into(), src)?;
+            ("si", 1)
+        };
+        let unsigned = if is_unsigned(src) { "un" } else { "" };
+        let float = if ir_type == IRType::F32 { "sf" } else { "df" };
+        self.emit(MachineInst::Call {
+            callee: format!("__float{}{}{}", unsigned, int, float),
+            arg_count,
+            fp_arg_count: 0,
+        });
+        self.store_soft_float_ret(ir_type, offset)
+    }
+
+    /// Convert a floating-point number to an integer by the soft-float runtime,
+    /// integers narrower than 32 bits are clamped after the conversion to `i32`.
+    fn soft_float_to_int(
+        &mut self,
+        ir_type: &IRType,
+        src: &Operand,
+        offset: i32,
+    ) -> Result<(), RccError> {
+        let (float, arg_count) = if fp_fmt(src) == FpFmt::S {
+            self.load_data(Reg::A0.into(), src)?;
+            ("sf", 1)
+        } else {
+            self.load_pair(Reg::A0.into(), Reg::arg(1).into(), src)?;
+            ("df", 2)
+        };
+        let (unsigned, int) = match ir_type {
+            IRType::I64 => ("", "di"),
+            IRType::U64 => ("uns", "di"),
+            IRType::U32 | IRType::Usize => ("uns", "si"),
+            _ => ("", "si"),
+        };
+        self.emit(MachineInst::Call {
+            callee: format!("__fix{}{}{}", unsigned, float, int),
+            arg_count,
+            fp_arg_count: 0,
+        });
+        let ret = Operand::FnRetPlace(*ir_type);
+        if int == "di" {
+            let (lo, hi) = self.load_wide(&ret)?;
+            return self.store_pair(lo, hi, offset);
+        }
+        let mut rd = self.mfunc.new_vreg();
+        self.load_data(rd, &ret)?;
+        if let Some((min, max)) = narrow_bounds(ir_type) {
+            rd = self.clamp_int(rd, min, max);
+        }
+        let size = ir_type.byte_size(RISCV32_ADDR_SIZE);
+        self.store_data(size, rd, offset, Reg::FP.into())
+    }
+
+    /// Store the floating-point number returned by the soft-float runtime
+    /// to the stack slot at `offset` from fp.
+    fn store_soft_float_ret(&mut self, ir_type: IRType, offset: i32) -> Result<(), RccError> {
+        if ir_type == IRType::F64 {
+            let (lo, hi) = self.load_wide(&Operand::FnRetPlace(ir_type))?;
+            self.store_pair(lo, hi, offset)
+        } else {
+            let rd = self.mfunc.new_vreg();
+            self.load_data(rd, &Operand::FnRetPlace(ir_type))?;
+            self.store_data(4, rd, offset, Reg::FP.into())
+        }
+    }
+
+    /// Clamp the integer `reg` to `[min, max]`, the bound is selected by the mask of
+    /// the comparison, e.g. `reg ^ ((reg ^ min) & -(reg < min))`.
+    fn clamp_int(&mut self, reg: MReg, min: i32, max: i32) -> MReg {
+        let mut rd = reg;
+        for (bound, is_min) in [(min, true), (max, false)] {
+            let bound_reg = self.mfunc.new_vreg();
+            self.emit(MachineInst::Li {
+                rd: bound_reg,
+                imm: bound as i64,
+            });
+            let out = if is_min {
+                self.emit_op(AluOp::Slt, rd, bound_reg)
+            } else {
+                self.emit_op(AluOp::Slt, bound_reg, rd)
+            };
+            let mask = self.emit_op(AluOp::Sub, Reg::ZERO.into(), out);
+            let diff = self.emit_op(AluOp::Xor, rd, bound_reg);
+            let diff = self.emit_op(AluOp::And, diff, mask);
+            rd = self.emit_op(AluOp::Xor, rd, diff);
+        }
+        rd
+    }
+
+    /// `op rd, rs1, rs2` with a new register `rd`
+    fn emit_op(&mut self, op: AluOp, rs1: MReg, rs2: MReg) -> MReg {
+        let rd = self.mfunc.new_vreg();
+        self.emit(MachineInst::Op { op, rd, rs1, rs2 });
+        rd
+    }
+
+    /// `op rd, rs1, imm` with a new register `rd`
+    fn emit_op_imm(&mut self, op: AluImmOp, rs1: MReg, imm: i64) -> MReg {
+        let rd = self.mfunc.new_vreg();
+        self.emit(MachineInst::OpImm { op, rd, rs1, imm });
+        rd
+    }
+
+    /// Double-precision `op rd, rs1, rs2` with a new register `rd`,
+    /// it is an integer register for comparisons.
+    fn emit_fp_op(&mut self, op: FpOp, rs1: MReg, rs2: MReg) -> MReg {
+        let rd = match op {
+            FpOp::Feq | FpOp::Flt | FpOp::Fle => self.mfunc.new_vreg(),
+            _ => self.mfunc.new_float_vreg(),
+        };
+        self.emit(MachineInst::FOp {
+            op,
+            fmt: FpFmt::D,
+            rd,
+            rs1,
+            rs2,
+        });
+        rd
+    }
+
+    /// `fcvt` from `rs` to a new register `rd`
+    fn emit_fcvt(&mut self, to: FpFmt, from: FpFmt, rs: MReg) -> MReg {
+        let rd = match to {
+            FpFmt::W | FpFmt::Wu => self.mfunc.new_vreg(),
+            _ => self.mfunc.new_float_vreg(),
+        };
+        self.emit(MachineInst::FCvt { to, from, rd, rs });
+        rd
+    }
+
+    /// Operations of floating-point numbers, they are calls of the runtime with soft-float.
+    fn bin_op_float(
+        &mut self,
+        op: &BinOperator,
+        dest: &Place,
+        src1: &Operand,
+        src2: &Operand,
+    ) -> Result<(), RccError> {
+        let offset = match dest.kind {
+            VarKind::LocalMut | VarKind::Local => {
+                -(self.allocator.get_fp_offset(&dest.label, &dest.ir_type) as i32)
+            }
+            _ => unimplemented!(),
+        };
+        let fp_op = match op {
+            BinOperator::Lt
+            | BinOperator::Gt
+            | BinOperator::Le
+            | BinOperator::Ge
+            | BinOperator::EqEq
+            | BinOperator::Ne => {
+                let rd = self.compare_float(op, src1, src2)?;
+                let size = dest.ir_type.byte_size(RISCV32_ADDR_SIZE);
+                return self.store_data(size, rd, offset, Reg::FP.into());
+            }
+            BinOperator::Plus => FpOp::Fadd,
+            BinOperator::Minus => FpOp::Fsub,
+            BinOperator::Star => FpOp::Fmul,
+            BinOperator::Slash => FpOp::Fdiv,
+            _ => {
+                return Err(format!("operator `{}` of floating-point numbers is not supported", op).into())
+            }
+        };
+        let fmt = fp_fmt(src1);
+        if self.soft_float {
+            self.call_soft_float(soft_float_fn(op, fmt).unwrap(), fmt, src1, src2)?;
+            return if fmt == FpFmt::S {
+                let rd = self.mfunc.new_vreg();
+                self.load_data(rd, &Operand::FnRetPlace(dest.ir_type))?;
+                self.store_data(4, rd, offset, Reg::FP.into())
+            } else {
+                let (lo, hi) = (self.mfunc.new_vreg(), self.mfunc.new_vreg());
+                self.load_pair(lo, hi, &Operand::FnRetPlace(dest.ir_type))?;
+                self.store_pair(lo, hi, offset)
+            };
+        }
+        let rs1 = self.mfunc.new_float_vreg();
+        self.load_float(rs1, src1)?;
+        let rs2 = self.mfunc.new_float_vreg();
+        self.load_float(rs2, src2)?;
+        let rd = self.mfunc.new_float_vreg();
+        self.emit(MachineInst::FOp {
+            op: fp_op,
+            fmt,
+            rd,
+            rs1,
+            rs2,
+        });
+        self.store_float(fmt, rd, offset)
+    }
+
+    /// `fneg` flips the sign bit, which is the highest bit of the high word with soft-float.
+    fn neg_float(&mut self, dest: &Place, src: &Operand) -> Result<(), RccError> {
+        let offset = -(self.allocator.get_fp_offset(&dest.label, &dest.ir_type) as i32);
+        let fmt = fp_fmt(src);
+        if !self.soft_float {
+            let rs = self.mfunc.new_float_vreg();
+            self.load_float(rs, src)?;
+            let rd = self.mfunc.new_float_vreg();
+            self.emit(MachineInst::FOp {
+                op: FpOp::Fsgnjn,
+                fmt,
+                rd,
+                rs1: rs,
+                rs2: rs,
+            });
+            return self.store_float(fmt, rd, offset);
+        }
+        let sign = self.mfunc.new_vreg();
+        self.emit(MachineInst::Li {
+            rd: sign,
+            imm: i32::MIN as i64,
+        });
+        if fmt == FpFmt::S {
+            let rs = self.mfunc.new_vreg();
+            self.load_data(rs, src)?;
+            let rd = self.emit_op(AluOp::Xor, rs, sign);
+            self.store_data(4, rd, offset, Reg::FP.into())
+        } else {
+            let (lo, hi) = self.load_wide(src)?;
+            let hi = self.emit_op(AluOp::Xor, hi, sign);
+            self.store_pair(lo, hi, offset)
+        }
+    }
+
+    /// Compare two floating-point numbers, set the result register to 1 if the comparison is true.
+    /// The comparisons except `!=` are false if any operand is NaN.
+    fn compare_float(
+        &mut self,
+        op: &BinOperator,
+        src1: &Operand,
+        src2: &Operand,
+    ) -> Result<MReg, RccError> {
+        let fmt = fp_fmt(src1);
+        let rd = self.mfunc.new_vreg();
+        if self.soft_float {
+            // the sign of the result of the runtime function is the result of the comparison
+            self.call_soft_float(soft_float_fn(op, fmt).unwrap(), fmt, src1, src2)?;
+            let res = self.mfunc.new_vreg();
+            self.load_data(res, &Operand::FnRetPlace(IRType::I32))?;
+            let zero = Reg::ZERO.into();
+            let inst = match op {
+                BinOperator::Lt => MachineInst::Op {
+                    op: AluOp::Slt,
+                    rd,
+                    rs1: res,
+                    rs2: zero,
+                },
+                BinOperator::Le => MachineInst::OpImm {
+                    op: AluImmOp::Slti,
+                    rd,
+                    rs1: res,
+                    imm: 1,
+                },
+                BinOperator::Gt => MachineInst::Op {
+                    op: AluOp::Slt,
+                    rd,
+                    rs1: zero,
+                    rs2: res,
+                },
+                BinOperator::Ge => {
+                    let lt = self.mfunc.new_vreg();
+                    self.emit(MachineInst::Op {
+                        op: AluOp::Slt,
+                        rd: lt,
+                        rs1: res,
+                        rs2: zero,
+                    });
+                    MachineInst::OpImm {
+                        op: AluImmOp::Xori,
+                        rd,
+                        rs1: lt,
+                        imm: 1,
+                    }
+                }
+                BinOperator::EqEq => MachineInst::OpImm {
+                    op: AluImmOp::Sltiu,
+                    rd,
+                    rs1: res,
+                    imm: 1,
+                },
+                _ => MachineInst::Op {
+                    op: AluOp::Sltu,
+                    rd,
+                    rs1: zero,
+                    rs2: res,
+                },
+            };
+            self.emit(inst);
+            return Ok(rd);
+        }
+        let a = self.mfunc.new_float_vreg();
+        self.load_float(a, src1)?;
+        let b = self.mfunc.new_float_vreg();
+        self.load_float(b, src2)?;
+        // `a > b` is `b < a`, `a >= b` is `b <= a`
+        let (fp_op, rs1, rs2) = match op {
+            BinOperator::Lt => (FpOp::Flt, a, b),
+            BinOperator::Gt => (FpOp::Flt, b, a),
+            BinOperator::Le => (FpOp::Fle, a, b),
+            BinOperator::Ge => (FpOp::Fle, b, a),
+            _ => (FpOp::Feq, a, b),
+        };
+        let eq = self.mfunc.new_vreg();
+        self.emit(MachineInst::FOp {
+            op: fp_op,
+            fmt,
+            rd: if let BinOperator::Ne = op { eq } else { rd },
+            rs1,
+            rs2,
+        });
+        if let BinOperator::Ne = op {
+            self.emit(MachineInst::OpImm {
+                op: AluImmOp::Xori,
+                rd,
+                rs1: eq,
+                imm: 1,
+            });
+        }
+        Ok(rd)
+    }
+
+    /// Call the soft-float runtime function with the operands in integer registers.
+    fn call_soft_float(
+        &mut self,
+        callee: &str,
+        fmt: FpFmt,
+        src1: &Operand,
+        src2: &Operand,
+    ) -> Result<(), RccError> {
+        let arg_count = if fmt == FpFmt::S {
+            self.load_data(Reg::arg(0).into(), src1)?;
+            self.load_data(Reg::arg(1).into(), src2)?;
+            2
+        } else {
+            self.load_pair(Reg::arg(0).into(), Reg::arg(1).into(), src1)?;
+            self.load_pair(Reg::arg(2).into(), Reg::arg(3).into(), src2)?;
+            4
+        };
+        self.emit(MachineInst::Call {
+            callee: callee.to_string(),
+            arg_count,
+            fp_arg_count: 0,
+        });
         Ok(())
     }
 
@@ -324,30 +2051,53 @@ impl<'w: 'codegen, 'codegen, W: Write> FuncCodeGen<'w, 'codegen, W> {
         &mut self,
         op: &BinOperator,
         dest: &Place,
-        reg_src1: &str,
-        reg_src2: &str,
+        reg_src1: MReg,
+        reg_src2: MReg,
+        unsigned: bool,
     ) -> Result<(), RccError> {
         match dest.kind {
             VarKind::LocalMut | VarKind::Local => {
                 let offset = self.allocator.get_fp_offset(&dest.label, &dest.ir_type);
-                let inst = match op {
-                    BinOperator::Plus => "add",
-                    BinOperator::Star => "mul",
-                    BinOperator::Minus => "sub",
-                    BinOperator::Slash => "div",
-                    BinOperator::Percent => match dest.ir_type {
-                        IRType::I8 | IRType::I16 | IRType::I32 => "rem",
-                        IRType::U8 | IRType::U16 | IRType::U32 => "remu",
-                        _ => unimplemented!(),
-                    },
-                    _ => todo!(),
-                };
-                writeln!(self.output, "\t{}\ta5,{},{}", inst, reg_src1, reg_src2)?;
+                let rd = self.mfunc.new_vreg();
+                match op {
+                    BinOperator::Lt
+                    | BinOperator::Gt
+                    | BinOperator::Le
+                    | BinOperator::Ge
+                    | BinOperator::EqEq
+                    | BinOperator::Ne => {
+                        self.compare(op, rd, reg_src1, reg_src2, unsigned);
+                    }
+                    _ => {
+                        let alu_op = match (op, unsigned) {
+                            (BinOperator::Plus, _) => AluOp::Add,
+                            (BinOperator::Star, _) => AluOp::Mul,
+                            (BinOperator::Minus, _) => AluOp::Sub,
+                            (BinOperator::Slash, false) => AluOp::Div,
+                            (BinOperator::Slash, true) => AluOp::Divu,
+                            (BinOperator::Percent, false) => AluOp::Rem,
+                            (BinOperator::Percent, true) => AluOp::Remu,
+                            (BinOperator::And, _) => AluOp::And,
+                            (BinOperator::Or, _) => AluOp::Or,
+                            (BinOperator::Caret, _) => AluOp::Xor,
+                            (BinOperator::Shl, _) => AluOp::Sll,
+                            (BinOperator::Shr, false) => AluOp::Sra,
+                            (BinOperator::Shr, true) => AluOp::Srl,
+                            _ => return Err(format!("operator `{}` is not supported", op).into()),
+                        };
+                        self.emit(MachineInst::Op {
+                            op: alu_op,
+                            rd,
+                            rs1: reg_src1,
+                            rs2: reg_src2,
+                        });
+                    }
+                }
                 self.store_data(
                     dest.ir_type.byte_size(RISCV32_ADDR_SIZE),
-                    "a5",
+                    rd,
                     -(offset as i32),
-                    "s0",
+                    Reg::FP.into(),
                 )?;
             }
             _ => unimplemented!(),
@@ -355,49 +2105,271 @@ impl<'w: 'codegen, 'codegen, W: Write> FuncCodeGen<'w, 'codegen, W> {
         Ok(())
     }
 
+    /// Set `rd` to 1 if the comparison is true, otherwise 0.
+    fn compare(
+        &mut self,
+        op: &BinOperator,
+        rd: MReg,
+        reg_src1: MReg,
+        reg_src2: MReg,
+        unsigned: bool,
+    ) {
+        let slt = if unsigned { AluOp::Sltu } else { AluOp::Slt };
+        // `a <= b` is `!(b < a)`, `a >= b` is `!(a < b)`
+        let (rs1, rs2) = match op {
+            BinOperator::Gt | BinOperator::Le => (reg_src2, reg_src1),
+            _ => (reg_src1, reg_src2),
+        };
+        match op {
+            BinOperator::Lt | BinOperator::Gt => {
+                self.emit(MachineInst::Op {
+                    op: slt,
+                    rd,
+                    rs1,
+                    rs2,
+                });
+            }
+            BinOperator::Le | BinOperator::Ge => {
+                let lt = self.mfunc.new_vreg();
+                self.emit(MachineInst::Op {
+                    op: slt,
+                    rd: lt,
+                    rs1,
+                    rs2,
+                });
+                self.emit(MachineInst::OpImm {
+                    op: AluImmOp::Xori,
+                    rd,
+                    rs1: lt,
+                    imm: 1,
+                });
+            }
+            BinOperator::EqEq | BinOperator::Ne => {
+                let diff = self.mfunc.new_vreg();
+                self.emit(MachineInst::Op {
+                    op: AluOp::Xor,
+                    rd: diff,
+                    rs1,
+                    rs2,
+                });
+                // seqz and snez
+                if let BinOperator::EqEq = op {
+                    self.emit(MachineInst::OpImm {
+                        op: AluImmOp::Sltiu,
+                        rd,
+                        rs1: diff,
+                        imm: 1,
+                    });
+                } else {
+                    self.emit(MachineInst::Op {
+                        op: AluOp::Sltu,
+                        rd,
+                        rs1: Reg::ZERO.into(),
+                        rs2: diff,
+                    });
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
     fn bin_op_imm(
         &mut self,
         op: &BinOperator,
         dest: &Place,
-        reg_src1: &str,
+        reg_src1: MReg,
         src2: &Operand,
+        unsigned: bool,
     ) -> Result<(), RccError> {
         let asm_src2 = AsmOperand::from_operand(src2, &mut *self.allocator);
         match asm_src2 {
             AsmOperand::Imm(s) => match dest.kind {
                 VarKind::LocalMut | VarKind::Local => {
                     let offset = self.allocator.get_fp_offset(&dest.label, &dest.ir_type);
-                    match op {
-                        BinOperator::Plus => {
-                            writeln!(self.output, "\taddi\ta5,{},{}", reg_src1, s)?;
-                            self.store_data(
-                                dest.ir_type.byte_size(RISCV32_ADDR_SIZE),
-                                "a5",
-                                -(offset as i32),
-                                "s0",
-                            )?;
-                        }
-                        BinOperator::Minus => {
-                            writeln!(self.output, "\taddi\ta5,{},-{}", reg_src1, s)?;
+                    let imm: i64 = s.parse()?;
+                    let imm = match op {
+                        BinOperator::Plus => Some(imm),
+                        BinOperator::Minus => Some(-imm),
+                        _ => None,
+                    };
+                    match imm {
+                        // addi only accepts 12-bit immediates
+                        Some(imm) if (-2048..2048).contains(&imm) => {
+                            let rd = self.mfunc.new_vreg();
+                            self.emit(MachineInst::OpImm {
+                                op: AluImmOp::Addi,
+                                rd,
+                                rs1: reg_src1,
+                                imm,
+                            });
                             self.store_data(
                                 dest.ir_type.byte_size(RISCV32_ADDR_SIZE),
-                                "a5",
+                                rd,
                                 -(offset as i32),
-                                "s0",
+                                Reg::FP.into(),
                             )?;
                         }
                         _ => {
-                            self.load_data("a4", &src2)?;
-                            self.bin_op(op, dest, reg_src1, "a4")?;
+                            let reg_src2 = self.mfunc.new_vreg();
+                            self.load_data(reg_src2, src2)?;
+                            self.bin_op(op, dest, reg_src1, reg_src2, unsigned)?;
                         }
                     }
                 }
                 _ => unimplemented!(),
             },
-            _ => todo!(),
+            o => return Err(format!("invalid immediate operand {:?}", o).into()),
         }
         Ok(())
     }
+
+    /// Operations of 64-bit integers, `lo` and `hi` words are kept in two registers.
+    /// Division, remainder and shifts are calls of the runtime functions of libgcc.
+    fn bin_op_wide(
+        &mut self,
+        op: &BinOperator,
+        dest: &Place,
+        src1: &Operand,
+        src2: &Operand,
+        unsigned: bool,
+    ) -> Result<(), RccError> {
+        let offset = match dest.kind {
+            VarKind::LocalMut | VarKind::Local => {
+                -(self.allocator.get_fp_offset(&dest.label, &dest.ir_type) as i32)
+            }
+            _ => unimplemented!(),
+        };
+        if let Some(callee) = runtime_fn(op, unsigned) {
+            self.load_pair(Reg::arg(0).into(), Reg::arg(1).into(), src1)?;
+            let arg_count = match op {
+                // the shift amount is an `int`
+                BinOperator::Shl | BinOperator::Shr if is_wide(src2) => {
+                    let unused = self.mfunc.new_vreg();
+                    self.load_pair(Reg::arg(2).into(), unused, src2)?;
+                    3
+                }
+                BinOperator::Shl | BinOperator::Shr => {
+                    self.load_data(Reg::arg(2).into(), src2)?;
+                    3
+                }
+                _ => {
+                    self.load_pair(Reg::arg(2).into(), Reg::arg(3).into(), src2)?;
+                    4
+                }
+            };
+            self.emit(MachineInst::Call {
+                callee: callee.to_string(),
+                arg_count,
+                fp_arg_count: 0,
+            });
+            let (lo, hi) = (self.mfunc.new_vreg(), self.mfunc.new_vreg());
+            self.load_pair(lo, hi, &Operand::FnRetPlace(dest.ir_type))?;
+            return self.store_pair(lo, hi, offset);
+        }
+
+        let (a_lo, a_hi) = self.load_wide(src1)?;
+        let (b_lo, b_hi) = self.load_wide(src2)?;
+        let op3 = |gen: &mut Self, op, rs1, rs2| {
+            let rd = gen.mfunc.new_vreg();
+            gen.emit(MachineInst::Op { op, rd, rs1, rs2 });
+            rd
+        };
+        let (lo, hi) = match op {
+            BinOperator::Plus => {
+                let lo = op3(self, AluOp::Add, a_lo, b_lo);
+                let carry = op3(self, AluOp::Sltu, lo, a_lo);
+                let hi = op3(self, AluOp::Add, a_hi, b_hi);
+                (lo, op3(self, AluOp::Add, hi, carry))
+            }
+            BinOperator::Minus => {
+                let borrow = op3(self, AluOp::Sltu, a_lo, b_lo);
+                let lo = op3(self, AluOp::Sub, a_lo, b_lo);
+                let hi = op3(self, AluOp::Sub, a_hi, b_hi);
+                (lo, op3(self, AluOp::Sub, hi, borrow))
+            }
+            BinOperator::Star => {
+                let lo = op3(self, AluOp::Mul, a_lo, b_lo);
+                let carry = op3(self, AluOp::Mulhu, a_lo, b_lo);
+                let cross1 = op3(self, AluOp::Mul, a_lo, b_hi);
+                let cross2 = op3(self, AluOp::Mul, a_hi, b_lo);
+                let hi = op3(self, AluOp::Add, carry, cross1);
+                (lo, op3(self, AluOp::Add, hi, cross2))
+            }
+            BinOperator::And | BinOperator::Or | BinOperator::Caret => {
+                let alu_op = match op {
+                    BinOperator::And => AluOp::And,
+                    BinOperator::Or => AluOp::Or,
+                    _ => AluOp::Xor,
+                };
+                (op3(self, alu_op, a_lo, b_lo), op3(self, alu_op, a_hi, b_hi))
+            }
+            BinOperator::Lt
+            | BinOperator::Gt
+            | BinOperator::Le
+            | BinOperator::Ge
+            | BinOperator::EqEq
+            | BinOperator::Ne => {
+                let rd = self.compare_wide(op, (a_lo, a_hi), (b_lo, b_hi), unsigned);
+                let size = dest.ir_type.byte_size(RISCV32_ADDR_SIZE);
+                return self.store_data(size, rd, offset, Reg::FP.into());
+            }
+            _ => {
+                return Err(format!("operator `{}` of 64-bit integers is not supported", op).into())
+            }
+        };
+        self.store_pair(lo, hi, offset)
+    }
+
+    /// Compare two 64-bit integers, set the result register to 1 if the comparison is true.
+    fn compare_wide(
+        &mut self,
+        op: &BinOperator,
+        a: (MReg, MReg),
+        b: (MReg, MReg),
+        unsigned: bool,
+    ) -> MReg {
+        let op3 = |gen: &mut Self, op, rs1, rs2| {
+            let rd = gen.mfunc.new_vreg();
+            gen.emit(MachineInst::Op { op, rd, rs1, rs2 });
+            rd
+        };
+        let op_imm = |gen: &mut Self, op, rs1, imm| {
+            let rd = gen.mfunc.new_vreg();
+            gen.emit(MachineInst::OpImm { op, rd, rs1, imm });
+            rd
+        };
+        // `a <= b` is `!(b < a)`, `a >= b` is `!(a < b)`
+        let (x, y) = match op {
+            BinOperator::Gt | BinOperator::Le => (b, a),
+            _ => (a, b),
+        };
+        match op {
+            BinOperator::EqEq | BinOperator::Ne => {
+                let diff_lo = op3(self, AluOp::Xor, x.0, y.0);
+                let diff_hi = op3(self, AluOp::Xor, x.1, y.1);
+                let diff = op3(self, AluOp::Or, diff_lo, diff_hi);
+                if let BinOperator::EqEq = op {
+                    op_imm(self, AluImmOp::Sltiu, diff, 1)
+                } else {
+                    op3(self, AluOp::Sltu, Reg::ZERO.into(), diff)
+                }
+            }
+            _ => {
+                // x.hi < y.hi || (x.hi == y.hi && x.lo <u y.lo)
+                let slt = if unsigned { AluOp::Sltu } else { AluOp::Slt };
+                let hi_lt = op3(self, slt, x.1, y.1);
+                let diff_hi = op3(self, AluOp::Xor, x.1, y.1);
+                let hi_eq = op_imm(self, AluImmOp::Sltiu, diff_hi, 1);
+                let lo_lt = op3(self, AluOp::Sltu, x.0, y.0);
+                let lo_lt = op3(self, AluOp::And, hi_eq, lo_lt);
+                let lt = op3(self, AluOp::Or, hi_lt, lo_lt);
+                match op {
+                    BinOperator::Le | BinOperator::Ge => op_imm(self, AluImmOp::Xori, lt, 1),
+                    _ => lt,
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -415,13 +2387,25 @@ pub enum AsmOperand {
 impl AsmOperand {
     pub fn from_operand(operand: &Operand, allocator: &mut dyn Allocator) -> AsmOperand {
         match operand {
+            Operand::Bool(b) => Self::Imm((*b as u8).to_string()),
             Operand::Char(c) => Self::Imm((*c as u8).to_string()),
             Operand::I8(i) => Self::Imm(i.to_string()),
             Operand::I16(i) => Self::Imm(i.to_string()),
             Operand::I32(i) => Self::Imm(i.to_string()),
+            Operand::I64(i) => Self::Imm64((*i as i32).to_string(), ((*i >> 32) as i32).to_string()),
             Operand::U8(i) => Self::Imm(i.to_string()),
             Operand::U16(i) => Self::Imm(i.to_string()),
             Operand::U32(i) => Self::Imm(i.to_string()),
+            Operand::U64(i) => Self::Imm64((*i as i32).to_string(), ((*i >> 32) as i32).to_string()),
+            // addresses are 32 bits
+            Operand::Isize(i) => Self::Imm((*i as i32).to_string()),
+            Operand::Usize(i) => Self::Imm((*i as i32).to_string()),
+            // bits of floating-point numbers
+            Operand::F32(f) => Self::Imm((f.to_bits() as i32).to_string()),
+            Operand::F64(f) => {
+                let bits = f.to_bits();
+                Self::Imm64((bits as i32).to_string(), ((bits >> 32) as i32).to_string())
+            }
             Operand::Place(p) => {
                 match p.kind {
                     VarKind::Local | VarKind::LocalMut => {
@@ -438,3 +2422,181 @@ impl AsmOperand {
         }
     }
 }
+
+/// Bits of the immediate in little endian, the higher bits are truncated by the size
+fn imm_bits(operand: &Operand) -> u128 {
+    match operand {
+        Operand::Bool(b) => *b as u128,
+        Operand::Char(c) => *c as u128,
+        Operand::I8(i) => *i as u128,
+        Operand::I16(i) => *i as u128,
+        Operand::I32(i) => *i as u128,
+        Operand::I64(i) => *i as u128,
+        Operand::I128(i) => *i as u128,
+        Operand::Isize(i) => *i as u128,
+        Operand::U8(i) => *i as u128,
+        Operand::U16(i) => *i as u128,
+        Operand::U32(i) => *i as u128,
+        Operand::U64(i) => *i as u128,
+        Operand::U128(i) => *i,
+        Operand::Usize(i) => *i as u128,
+        Operand::F32(f) => f.to_bits() as u128,
+        Operand::F64(f) => f.to_bits() as u128,
+        o => unreachable!("{:?} is not an immediate", o),
+    }
+}
+
+/// Whether the operand is compared, divided and extended as an unsigned integer
+fn is_unsigned(operand: &Operand) -> bool {
+    let ir_type = match operand {
+        Operand::Place(p) => &p.ir_type,
+        Operand::FnRetPlace(ir_type) => ir_type,
+        Operand::Bool(_)
+        | Operand::Char(_)
+        | Operand::U8(_)
+        | Operand::U16(_)
+        | Operand::U32(_)
+        | Operand::U64(_)
+        | Operand::Usize(_) => return true,
+        _ => return false,
+    };
+    matches!(
+        ir_type,
+        IRType::Bool
+            | IRType::Char
+            | IRType::U8
+            | IRType::U16
+            | IRType::U32
+            | IRType::U64
+            | IRType::Usize
+            | IRType::Addr
+    )
+}
+
+fn is_float(operand: &Operand) -> bool {
+    match operand {
+        Operand::Place(p) => matches!(p.ir_type, IRType::F32 | IRType::F64),
+        Operand::FnRetPlace(ir_type) => matches!(ir_type, IRType::F32 | IRType::F64),
+        Operand::F32(_) | Operand::F64(_) => true,
+        _ => false,
+    }
+}
+
+fn fp_fmt(operand: &Operand) -> FpFmt {
+    if operand.byte_size(RISCV32_ADDR_SIZE) == 4 {
+        FpFmt::S
+    } else {
+        FpFmt::D
+    }
+}
+
+/// Bounds of the integer types narrower than 32 bits
+fn narrow_bounds(ir_type: &IRType) -> Option<(i32, i32)> {
+    match ir_type {
+        IRType::I8 => Some((i8::MIN as i32, i8::MAX as i32)),
+        IRType::I16 => Some((i16::MIN as i32, i16::MAX as i32)),
+        IRType::U8 => Some((0, u8::MAX as i32)),
+        IRType::U16 => Some((0, u16::MAX as i32)),
+        _ => None,
+    }
+}
+
+/// 64-bit integers (and doubles with soft-float) are kept in a pair of registers
+fn is_wide(operand: &Operand) -> bool {
+    operand.byte_size(RISCV32_ADDR_SIZE) == 8
+}
+
+/// Runtime function of the 64-bit operation, the names are the same as libgcc.
+fn runtime_fn(op: &BinOperator, unsigned: bool) -> Option<&'static str> {
+    match (op, unsigned) {
+        (BinOperator::Slash, false) => Some("__divdi3"),
+        (BinOperator::Slash, true) => Some("__udivdi3"),
+        (BinOperator::Percent, false) => Some("__moddi3"),
+        (BinOperator::Percent, true) => Some("__umoddi3"),
+        (BinOperator::Shl, _) => Some("__ashldi3"),
+        (BinOperator::Shr, false) => Some("__ashrdi3"),
+        (BinOperator::Shr, true) => Some("__lshrdi3"),
+        _ => None,
+    }
+}
+
+/// Soft-float runtime function of the operation, the names are the same as libgcc.
+fn soft_float_fn(op: &BinOperator, fmt: FpFmt) -> Option<&'static str> {
+    let double = fmt == FpFmt::D;
+    Some(match (op, double) {
+        (BinOperator::Plus, false) => "__addsf3",
+        (BinOperator::Plus, true) => "__adddf3",
+        (BinOperator::Minus, false) => "__subsf3",
+        (BinOperator::Minus, true) => "__subdf3",
+        (BinOperator::Star, false) => "__mulsf3",
+        (BinOperator::Star, true) => "__muldf3",
+        (BinOperator::Slash, false) => "__divsf3",
+        (BinOperator::Slash, true) => "__divdf3",
+        (BinOperator::EqEq, false) => "__eqsf2",
+        (BinOperator::EqEq, true) => "__eqdf2",
+        (BinOperator::Ne, false) => "__nesf2",
+        (BinOperator::Ne, true) => "__nedf2",
+        (BinOperator::Lt, false) => "__ltsf2",
+        (BinOperator::Lt, true) => "__ltdf2",
+        (BinOperator::Le, false) => "__lesf2",
+        (BinOperator::Le, true) => "__ledf2",
+        (BinOperator::Gt, false) => "__gtsf2",
+        (BinOperator::Gt, true) => "__gtdf2",
+        (BinOperator::Ge, false) => "__gesf2",
+        (BinOperator::Ge, true) => "__gedf2",
+        _ => return None,
+    })
+}
+
+fn is_int128(ir_type: &IRType) -> bool {
+    matches!(ir_type, IRType::I128 | IRType::U128)
+}
+
+/// Types of the arguments, the return value, the local variables and the operands of `cfg`.
+fn ir_types(cfg: &CFG) -> impl Iterator<Item = &IRType> {
+    let operands = cfg.basic_blocks.iter().flat_map(|bb| {
+        bb.instructions.iter().flat_map(|inst| match inst {
+            IRInst::BinOp { src1, src2, .. } | IRInst::JumpIfCond { src1, src2, .. } => {
+                vec![src1, src2]
+            }
+            IRInst::LoadData { src, .. }
+            | IRInst::Cast { src, .. }
+            | IRInst::Neg { src, .. }
+            | IRInst::StoreMem { src, .. }
+            | IRInst::Ret(src) => vec![src],
+            IRInst::Call { args, .. } => args.iter().collect(),
+            _ => vec![],
+        })
+    });
+    let operand_types = operands.filter_map(|operand| match operand {
+        Operand::I128(_) => Some(&IRType::I128),
+        Operand::U128(_) => Some(&IRType::U128),
+        Operand::Place(p) => Some(&p.ir_type),
+        Operand::FnRetPlace(ir_type) => Some(ir_type),
+        _ => None,
+    });
+    let var_types = cfg.local_variables.values().map(|(_, ir_type)| ir_type);
+    let arg_types = cfg.fn_args.iter().map(|(_, ir_type)| ir_type);
+    std::iter::once(&cfg.ret_type).chain(arg_types).chain(var_types).chain(operand_types)
+}
+
+fn calls_runtime(cfg: &CFG, soft_float: bool) -> bool {
+    cfg.basic_blocks.iter().any(|bb| {
+        bb.instructions.iter().any(|inst| match inst {
+            IRInst::BinOp { src1, .. }
+            | IRInst::JumpIfCond { src1, .. }
+            | IRInst::Cast { src: src1, .. }
+                if is_float(src1) =>
+            {
+                soft_float
+            }
+            IRInst::Cast { dest, .. } if matches!(dest.ir_type, IRType::F32 | IRType::F64) => {
+                soft_float
+            }
+            IRInst::BinOp { op, src1, src2, .. } => {
+                is_wide(src1) && runtime_fn(op, is_unsigned(src1) || is_unsigned(src2)).is_some()
+            }
+            _ => false,
+        })
+    })
+}
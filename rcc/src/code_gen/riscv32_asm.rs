@@ -0,0 +1,1278 @@
+//! Integrated assembler of RV32IMFD.
+//!
+//! Encode the machine functions generated by `Riscv32CodeGen` into an ELF32 relocatable object,
+//! the GAS text of the runtime is parsed into the same instructions.
+//! Branches and jumps to labels in the same section are resolved directly,
+//! other references to symbols are left to the linker as `R_RISCV_*` relocations.
+//!
+//! [RISC-V Assembly Programmer's Manual]: https://github.com/riscv/riscv-asm-manual/blob/master/riscv-asm.md
+use crate::code_gen::elf::*;
+use crate::code_gen::mir::{
+    AluImmOp, AluOp, BranchOp, FpFmt, FpOp, LoadOp, MReg, MachineFunction, MachineInst, StoreOp,
+};
+use crate::code_gen::riscv32::Riscv32Emitter;
+use crate::rcc::RccError;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+const OP_LUI: u32 = 0x37;
+const OP_AUIPC: u32 = 0x17;
+const OP_JAL: u32 = 0x6f;
+const OP_JALR: u32 = 0x67;
+const OP_BRANCH: u32 = 0x63;
+const OP_LOAD: u32 = 0x03;
+const OP_STORE: u32 = 0x23;
+const OP_IMM: u32 = 0x13;
+const OP_REG: u32 = 0x33;
+const OP_SYSTEM: u32 = 0x73;
+const OP_LOAD_FP: u32 = 0x07;
+const OP_STORE_FP: u32 = 0x27;
+const OP_FP: u32 = 0x53;
+
+/// Round towards zero
+const RM_RTZ: u32 = 1;
+/// Dynamic rounding mode of `frm`
+const RM_DYN: u32 = 7;
+
+const ZERO: u32 = 0;
+const RA: u32 = 1;
+const T1: u32 = 6;
+
+pub fn assemble(input: &str) -> Result<ObjectFile, RccError> {
+    let mut assembler = Riscv32Assembler::new();
+    for (line_no, line) in input.lines().enumerate() {
+        assembler
+            .assemble_line(line)
+            .map_err(|e| RccError::from(format!("asm line {}: {}", line_no + 1, e)))?;
+    }
+    assembler.finish()
+}
+
+/// Where a label is referenced.
+enum FixupKind {
+    Branch,
+    Jal,
+    /// `auipc` + `jalr` pair
+    Call,
+    /// `auipc` of `auipc` + `addi`, the `addi` refers to the hidden label of this `auipc`
+    PcrelHi,
+    PcrelLo,
+    Hi,
+    LoI,
+    LoS,
+    Word,
+}
+
+struct Fixup {
+    section: usize,
+    offset: u32,
+    symbol: String,
+    kind: FixupKind,
+}
+
+#[derive(Debug, PartialEq)]
+enum Arg {
+    Reg(u32),
+    /// floating-point register
+    FReg(u32),
+    Imm(i64),
+    Sym(String),
+    /// `%hi(sym)`
+    Hi(String),
+    /// `%lo(sym)`
+    Lo(String),
+    /// offset(reg)
+    Mem(Box<Arg>, u32),
+}
+
+pub struct Riscv32Assembler {
+    obj: ObjectFile,
+    cur_section: usize,
+    /// <label, (section, offset)>
+    labels: HashMap<String, (usize, u32)>,
+    globals: HashSet<String>,
+    functions: HashSet<String>,
+    fixups: Vec<Fixup>,
+    pcrel_hi_count: usize,
+}
+
+impl Riscv32Assembler {
+    pub fn new() -> Riscv32Assembler {
+        let mut obj = ObjectFile::new();
+        obj.sections.push(Section::new(SectionKind::Text));
+        Riscv32Assembler {
+            obj,
+            cur_section: 0,
+            labels: HashMap::new(),
+            globals: HashSet::new(),
+            functions: HashSet::new(),
+            fixups: vec![],
+            pcrel_hi_count: 0,
+        }
+    }
+
+    fn offset(&self) -> u32 {
+        self.obj.sections[self.cur_section].size()
+    }
+
+    fn switch_section(&mut self, kind: SectionKind) {
+        match self.obj.sections.iter().position(|s| s.kind == kind) {
+            Some(idx) => self.cur_section = idx,
+            None => {
+                self.obj.sections.push(Section::new(kind));
+                self.cur_section = self.obj.sections.len() - 1;
+            }
+        }
+    }
+
+    fn assemble_line(&mut self, line: &str) -> Result<(), RccError> {
+        let mut line = strip_comment(line).trim();
+        // labels
+        while let Some(pos) = label_end(line) {
+            let label = line[..pos].trim();
+            self.define_label(label)?;
+            line = line[pos + 1..].trim();
+        }
+        if line.is_empty() {
+            return Ok(());
+        }
+        let (mnemonic, rest) = match line.find(char::is_whitespace) {
+            Some(pos) => (&line[..pos], line[pos..].trim()),
+            None => (line, ""),
+        };
+        if mnemonic.starts_with('.') {
+            self.directive(mnemonic, rest)
+        } else {
+            let mut args = split_args(rest);
+            let target = if has_symbol_target(mnemonic) {
+                args.pop()
+            } else {
+                None
+            };
+            let mut args = args
+                .iter()
+                .map(|a| parse_arg(a))
+                .collect::<Result<Vec<Arg>, RccError>>()?;
+            if let Some(target) = target {
+                args.push(parse_symbol_arg(&target)?);
+            }
+            self.instruction(mnemonic, &args)
+        }
+    }
+
+    fn define_label(&mut self, label: &str) -> Result<(), RccError> {
+        if label.is_empty() || !label.chars().all(is_symbol_char) {
+            return Err(format!("invalid label `{}`", label).into());
+        }
+        if self.labels.contains_key(label) {
+            return Err(format!("symbol `{}` is already defined", label).into());
+        }
+        self.labels
+            .insert(label.to_string(), (self.cur_section, self.offset()));
+        Ok(())
+    }
+
+    fn directive(&mut self, directive: &str, rest: &str) -> Result<(), RccError> {
+        match directive {
+            ".text" | ".data" | ".rodata" | ".bss" => {
+                self.switch_section(SectionKind::from_name(directive).unwrap());
+            }
+            ".section" => {
+                let name = rest.split(',').next().unwrap_or("").trim();
+                match SectionKind::from_name(name) {
+                    Some(kind) => self.switch_section(kind),
+                    None => return Err(format!("unsupported section `{}`", name).into()),
+                }
+            }
+            ".globl" | ".global" => {
+                self.globals.insert(rest.trim().to_string());
+            }
+            ".type" => {
+                let args = split_args(rest);
+                if args.len() == 2 && args[1] == "@function" {
+                    self.functions.insert(args[0].to_string());
+                }
+            }
+            ".string" | ".asciz" => {
+                let mut bytes = parse_string(rest)?;
+                bytes.push(0);
+                self.emit_bytes(&bytes);
+            }
+            ".ascii" => {
+                let bytes = parse_string(rest)?;
+                self.emit_bytes(&bytes);
+            }
+            ".byte" | ".half" | ".word" => {
+                let size = match directive {
+                    ".byte" => 1,
+                    ".half" => 2,
+                    _ => 4,
+                };
+                for arg in split_args(rest) {
+                    match parse_arg(&arg)? {
+                        Arg::Imm(i) => self.emit_bytes(&(i as u32).to_le_bytes()[..size]),
+                        Arg::Sym(s) if size == 4 => {
+                            self.add_fixup(s, FixupKind::Word);
+                            self.emit_bytes(&[0; 4]);
+                        }
+                        _ => return Err(format!("invalid {} `{}`", directive, arg).into()),
+                    }
+                }
+            }
+            ".zero" => match parse_arg(rest)? {
+                Arg::Imm(i) if i >= 0 => self.emit_bytes(&vec![0; i as usize]),
+                _ => return Err(format!("invalid .zero `{}`", rest).into()),
+            },
+            // `.align` is the same as `.p2align` on RISC-V
+            ".align" | ".p2align" => match parse_arg(rest)? {
+                Arg::Imm(i) if (0..16).contains(&i) => self.align(i as u32),
+                _ => return Err(format!("invalid {} `{}`", directive, rest).into()),
+            },
+            ".file" | ".ident" | ".size" | ".option" | ".attribute" => {}
+            // debug information is not generated by the integrated assembler
+            ".loc" => {}
+            d if d.starts_with(".cfi_") => {}
+            _ => return Err(format!("unknown directive `{}`", directive).into()),
+        }
+        Ok(())
+    }
+
+    /// Align the current offset to `2^p2` bytes, the padding of `.text` is `nop`s.
+    fn align(&mut self, p2: u32) {
+        let align = 1 << p2;
+        let section = &mut self.obj.sections[self.cur_section];
+        section.align = section.align.max(align);
+        let padding = align_to(section.size(), align) - section.size();
+        if section.kind == SectionKind::Text {
+            for _ in 0..padding / 4 {
+                self.emit_inst(i_type(OP_IMM, 0, ZERO, ZERO, 0));
+            }
+        } else {
+            self.emit_bytes(&vec![0; padding as usize]);
+        }
+    }
+
+    fn emit_bytes(&mut self, bytes: &[u8]) {
+        self.obj.sections[self.cur_section]
+            .data
+            .extend_from_slice(bytes);
+    }
+
+    fn emit_inst(&mut self, inst: u32) {
+        self.emit_bytes(&inst.to_le_bytes());
+    }
+
+    /// Reference `symbol` from the current offset.
+    fn add_fixup(&mut self, symbol: String, kind: FixupKind) {
+        self.fixups.push(Fixup {
+            section: self.cur_section,
+            offset: self.offset(),
+            symbol,
+            kind,
+        });
+    }
+
+    fn instruction(&mut self, mnemonic: &str, args: &[Arg]) -> Result<(), RccError> {
+        if self.obj.sections[self.cur_section].kind != SectionKind::Text {
+            return Err(format!("instruction `{}` is not in .text", mnemonic).into());
+        }
+        use Arg::*;
+        match (mnemonic, args) {
+            ("ecall", []) => self.emit_inst(OP_SYSTEM),
+            ("ebreak", []) => self.emit_inst(1 << 20 | OP_SYSTEM),
+            ("nop", []) => self.emit_inst(i_type(OP_IMM, 0, ZERO, ZERO, 0)),
+            ("li", [Reg(rd), Imm(imm)]) => self.load_imm(*rd, *imm)?,
+            ("mv", [Reg(rd), Reg(rs)]) => self.emit_inst(i_type(OP_IMM, 0, *rd, *rs, 0)),
+            ("not", [Reg(rd), Reg(rs)]) => self.emit_inst(i_type(OP_IMM, 4, *rd, *rs, -1)),
+            ("neg", [Reg(rd), Reg(rs)]) => self.emit_inst(r_type(0x20, 0, *rd, ZERO, *rs)),
+            ("seqz", [Reg(rd), Reg(rs)]) => self.emit_inst(i_type(OP_IMM, 3, *rd, *rs, 1)),
+            ("snez", [Reg(rd), Reg(rs)]) => self.emit_inst(r_type(0, 3, *rd, ZERO, *rs)),
+            ("sltz", [Reg(rd), Reg(rs)]) => self.emit_inst(r_type(0, 2, *rd, *rs, ZERO)),
+            ("sgtz", [Reg(rd), Reg(rs)]) => self.emit_inst(r_type(0, 2, *rd, ZERO, *rs)),
+            ("lui", [Reg(rd), Imm(imm)]) => {
+                check_imm(*imm, 0, 0xfffff, mnemonic)?;
+                self.emit_inst(u_type(OP_LUI, *rd, (*imm as u32) << 12));
+            }
+            ("lui", [Reg(rd), Hi(sym)]) => {
+                self.add_fixup(sym.clone(), FixupKind::Hi);
+                self.emit_inst(u_type(OP_LUI, *rd, 0));
+            }
+            ("auipc", [Reg(rd), Imm(imm)]) => {
+                check_imm(*imm, 0, 0xfffff, mnemonic)?;
+                self.emit_inst(u_type(OP_AUIPC, *rd, (*imm as u32) << 12));
+            }
+            ("la", [Reg(rd), Sym(sym)]) | ("lla", [Reg(rd), Sym(sym)]) => self.la(*rd, sym)?,
+            ("j", [Sym(label)]) => self.jal(ZERO, label),
+            ("jal", [Sym(label)]) => self.jal(RA, label),
+            ("jal", [Reg(rd), Sym(label)]) => self.jal(*rd, label),
+            ("jr", [Reg(rs)]) => self.emit_inst(i_type(OP_JALR, 0, ZERO, *rs, 0)),
+            ("jalr", [Reg(rs)]) => self.emit_inst(i_type(OP_JALR, 0, RA, *rs, 0)),
+            ("jalr", [Reg(rd), Mem(imm, rs)]) => {
+                let imm = imm12(imm, mnemonic)?;
+                self.emit_inst(i_type(OP_JALR, 0, *rd, *rs, imm));
+            }
+            ("ret", []) => self.emit_inst(i_type(OP_JALR, 0, ZERO, RA, 0)),
+            ("call", [Sym(sym)]) => self.call(RA, sym),
+            ("tail", [Sym(sym)]) => self.call(ZERO, sym),
+            _ => {
+                if let Some((op, cmp_zero)) = branch_op(mnemonic) {
+                    match (cmp_zero, args) {
+                        (true, [Reg(rs), Sym(label)]) => self.branch(op, *rs, ZERO, label),
+                        (false, [Reg(rs1), Reg(rs2), Sym(label)]) => {
+                            self.branch(op, *rs1, *rs2, label)
+                        }
+                        _ => return Err(invalid_operands(mnemonic)),
+                    }
+                    return Ok(());
+                }
+                if let Ok(op) = LoadOp::from_str(mnemonic) {
+                    return match (is_fp_load(op), args) {
+                        (false, [Reg(rd), Mem(offset, base)])
+                        | (true, [FReg(rd), Mem(offset, base)]) => {
+                            let imm = self.lo12(offset, FixupKind::LoI, mnemonic)?;
+                            self.load(op, *rd, imm, *base);
+                            Ok(())
+                        }
+                        _ => Err(invalid_operands(mnemonic)),
+                    };
+                }
+                if let Ok(op) = StoreOp::from_str(mnemonic) {
+                    return match (is_fp_store(op), args) {
+                        (false, [Reg(rs), Mem(offset, base)])
+                        | (true, [FReg(rs), Mem(offset, base)]) => {
+                            let imm = self.lo12(offset, FixupKind::LoS, mnemonic)?;
+                            self.store(op, *rs, imm, *base);
+                            Ok(())
+                        }
+                        _ => Err(invalid_operands(mnemonic)),
+                    };
+                }
+                if let Ok(op) = AluOp::from_str(mnemonic) {
+                    return match args {
+                        [Reg(rd), Reg(rs1), Reg(rs2)] => {
+                            self.op(op, *rd, *rs1, *rs2);
+                            Ok(())
+                        }
+                        _ => Err(invalid_operands(mnemonic)),
+                    };
+                }
+                if let Ok(op) = AluImmOp::from_str(mnemonic) {
+                    return match (op, args) {
+                        (
+                            AluImmOp::Slli | AluImmOp::Srli | AluImmOp::Srai,
+                            [Reg(rd), Reg(rs1), Imm(shamt)],
+                        ) => self.op_imm(op, *rd, *rs1, *shamt),
+                        (AluImmOp::Slli | AluImmOp::Srli | AluImmOp::Srai, _) => {
+                            Err(invalid_operands(mnemonic))
+                        }
+                        (_, [Reg(rd), Reg(rs1), imm]) => {
+                            let imm = self.lo12(imm, FixupKind::LoI, mnemonic)?;
+                            self.op_imm(op, *rd, *rs1, imm as i64)
+                        }
+                        _ => Err(invalid_operands(mnemonic)),
+                    };
+                }
+                if mnemonic.starts_with('f') {
+                    return self.fp_instruction(mnemonic, args);
+                }
+                return Err(format!("unknown instruction `{}`", mnemonic).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Instructions of the F and D extensions except loads and stores,
+    /// `fmv`, `fneg` and `fabs` are pseudo instructions of sign injection.
+    fn fp_instruction(&mut self, mnemonic: &str, args: &[Arg]) -> Result<(), RccError> {
+        use Arg::*;
+        match (mnemonic, args) {
+            ("fmv.x.w", [Reg(rd), FReg(rs)]) => {
+                self.emit_inst(fp_type(0x1c, 0, 0, *rd, *rs, 0));
+                return Ok(());
+            }
+            ("fmv.w.x", [FReg(rd), Reg(rs)]) => {
+                self.emit_inst(fp_type(0x1e, 0, 0, *rd, *rs, 0));
+                return Ok(());
+            }
+            _ => {}
+        }
+        let unknown = || RccError::from(format!("unknown instruction `{}`", mnemonic));
+        let (op, fmts) = mnemonic.split_once('.').ok_or_else(unknown)?;
+        // the rounding mode is the optional last operand
+        let (args, rm) = match args {
+            [args @ .., Sym(rm)] => (args, Some(rounding_mode(rm)?)),
+            _ => (args, None),
+        };
+        if op == "fcvt" {
+            let (to, from) = match fmts.split_once('.') {
+                Some((to, from)) => match (FpFmt::from_str(to), FpFmt::from_str(from)) {
+                    (Ok(to), Ok(from)) => (to, from),
+                    _ => return Err(unknown()),
+                },
+                None => return Err(unknown()),
+            };
+            let (rd, rs) = match (is_int_fmt(to), is_int_fmt(from), args) {
+                (true, false, [Reg(rd), FReg(rs)])
+                | (false, true, [FReg(rd), Reg(rs)])
+                | (false, false, [FReg(rd), FReg(rs)]) => (*rd, *rs),
+                _ => return Err(invalid_operands(mnemonic)),
+            };
+            let inst = fcvt(to, from, rd, rs, rm).ok_or_else(|| invalid_operands(mnemonic))?;
+            self.emit_inst(inst);
+            return Ok(());
+        }
+        let fmt = match FpFmt::from_str(fmts) {
+            Ok(fmt) if !is_int_fmt(fmt) => fmt,
+            _ => return Err(unknown()),
+        };
+        if let Ok(op) = FpOp::from_str(op) {
+            return match (fp_op_writes_int(op), args) {
+                (false, [FReg(rd), FReg(rs1), FReg(rs2)])
+                | (true, [Reg(rd), FReg(rs1), FReg(rs2)]) => {
+                    self.emit_inst(fp_op(op, fmt, *rd, *rs1, *rs2, rm));
+                    Ok(())
+                }
+                _ => Err(invalid_operands(mnemonic)),
+            };
+        }
+        let inst = match (op, args) {
+            ("fsqrt", [FReg(rd), FReg(rs)]) => {
+                fp_type(0x0b, fmt_bits(fmt), rm.unwrap_or(RM_DYN), *rd, *rs, 0)
+            }
+            ("fmv", [FReg(rd), FReg(rs)]) => fp_type(0x04, fmt_bits(fmt), 0, *rd, *rs, *rs),
+            ("fneg", [FReg(rd), FReg(rs)]) => fp_type(0x04, fmt_bits(fmt), 1, *rd, *rs, *rs),
+            ("fabs", [FReg(rd), FReg(rs)]) => fp_type(0x04, fmt_bits(fmt), 2, *rd, *rs, *rs),
+            _ => return Err(invalid_operands(mnemonic)),
+        };
+        self.emit_inst(inst);
+        Ok(())
+    }
+
+    /// Encode the machine function after register allocation, the directives of
+    /// debug information are skipped.
+    fn machine_function(&mut self, mfunc: &MachineFunction) -> Result<(), RccError> {
+        self.define_label(&mfunc.name)?;
+        for block in mfunc.blocks.iter() {
+            if let Some(label) = &block.label {
+                self.define_label(label)?;
+            }
+            for inst in block.insts.iter() {
+                self.machine_inst(inst)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn machine_inst(&mut self, inst: &MachineInst) -> Result<(), RccError> {
+        match inst {
+            MachineInst::Li { rd, imm } => self.load_imm(reg_bits(rd)?, *imm)?,
+            MachineInst::Mv { rd, rs } => {
+                self.emit_inst(i_type(OP_IMM, 0, reg_bits(rd)?, reg_bits(rs)?, 0))
+            }
+            MachineInst::Load {
+                op,
+                rd,
+                offset,
+                base,
+            } => {
+                check_imm(*offset as i64, -2048, 2047, &op.to_string())?;
+                self.load(*op, reg_bits(rd)?, *offset, reg_bits(base)?);
+            }
+            MachineInst::Store {
+                op,
+                rs,
+                offset,
+                base,
+            } => {
+                check_imm(*offset as i64, -2048, 2047, &op.to_string())?;
+                self.store(*op, reg_bits(rs)?, *offset, reg_bits(base)?);
+            }
+            MachineInst::Op { op, rd, rs1, rs2 } => {
+                self.op(*op, reg_bits(rd)?, reg_bits(rs1)?, reg_bits(rs2)?)
+            }
+            MachineInst::OpImm { op, rd, rs1, imm } => {
+                self.op_imm(*op, reg_bits(rd)?, reg_bits(rs1)?, *imm)?
+            }
+            MachineInst::La { rd, symbol } => self.la(reg_bits(rd)?, symbol)?,
+            MachineInst::FOp {
+                op,
+                fmt,
+                rd,
+                rs1,
+                rs2,
+            } => {
+                let (rd, rs1, rs2) = (reg_bits(rd)?, reg_bits(rs1)?, reg_bits(rs2)?);
+                self.emit_inst(fp_op(*op, *fmt, rd, rs1, rs2, None));
+            }
+            MachineInst::FMv { fmt, rd, rs } => {
+                let (rd, rs) = (reg_bits(rd)?, reg_bits(rs)?);
+                self.emit_inst(fp_type(0x04, fmt_bits(*fmt), 0, rd, rs, rs));
+            }
+            MachineInst::FCvt { to, from, rd, rs } => {
+                // conversions to integers round towards zero
+                let rm = if is_int_fmt(*to) { Some(RM_RTZ) } else { None };
+                match fcvt(*to, *from, reg_bits(rd)?, reg_bits(rs)?, rm) {
+                    Some(inst) => self.emit_inst(inst),
+                    None => return Err(format!("invalid operands of `{}`", inst).into()),
+                }
+            }
+            MachineInst::Branch {
+                op,
+                rs1,
+                rs2,
+                target,
+            } => self.branch(*op, reg_bits(rs1)?, reg_bits(rs2)?, target),
+            MachineInst::J(target) => self.jal(ZERO, target),
+            MachineInst::JumpTable { rs, label, targets } => {
+                self.emit_inst(i_type(OP_JALR, 0, ZERO, reg_bits(rs)?, 0));
+                self.define_label(label)?;
+                for target in targets.iter() {
+                    self.jal(ZERO, target);
+                }
+            }
+            MachineInst::Call { callee, .. } => self.call(RA, callee),
+            MachineInst::Ret => self.emit_inst(i_type(OP_JALR, 0, ZERO, RA, 0)),
+            // debug information is not generated by the integrated assembler
+            MachineInst::Directive(_) => {}
+        }
+        Ok(())
+    }
+
+    /// `li` is `addi` for 12-bit immediates, otherwise `lui` + `addi`.
+    fn load_imm(&mut self, rd: u32, imm: i64) -> Result<(), RccError> {
+        check_imm(imm, i32::MIN as i64, u32::MAX as i64, "li")?;
+        let imm = imm as u32 as i32;
+        if (-2048..2048).contains(&imm) {
+            self.emit_inst(i_type(OP_IMM, 0, rd, ZERO, imm));
+        } else {
+            let lo = (imm << 20) >> 20;
+            let hi = (imm as u32).wrapping_sub(lo as u32);
+            self.emit_inst(u_type(OP_LUI, rd, hi));
+            if lo != 0 {
+                self.emit_inst(i_type(OP_IMM, 0, rd, rd, lo));
+            }
+        }
+        Ok(())
+    }
+
+    /// Immediate of I-type or S-type instructions, `%lo(sym)` is relocated.
+    fn lo12(&mut self, arg: &Arg, kind: FixupKind, mnemonic: &str) -> Result<i32, RccError> {
+        match arg {
+            Arg::Lo(sym) => {
+                self.add_fixup(sym.clone(), kind);
+                Ok(0)
+            }
+            _ => imm12(arg, mnemonic),
+        }
+    }
+
+    fn jal(&mut self, rd: u32, label: &str) {
+        self.add_fixup(label.to_string(), FixupKind::Jal);
+        self.emit_inst(rd << 7 | OP_JAL);
+    }
+
+    fn call(&mut self, rd: u32, sym: &str) {
+        // `call` saves the return address in `ra`, `tail` uses `t1` as the scratch register
+        let tmp = if rd == ZERO { T1 } else { RA };
+        self.add_fixup(sym.to_string(), FixupKind::Call);
+        self.emit_inst(u_type(OP_AUIPC, tmp, 0));
+        self.emit_inst(i_type(OP_JALR, 0, rd, tmp, 0));
+    }
+
+    fn pcrel_hi_label(&mut self) -> Result<String, RccError> {
+        let label = format!(".Lpcrel_hi{}", self.pcrel_hi_count);
+        self.pcrel_hi_count += 1;
+        self.define_label(&label)?;
+        Ok(label)
+    }
+
+    /// `bgt`, `ble`, `bgtu` and `bleu` swap the operands of `blt`, `bge`, `bltu` and `bgeu`.
+    fn branch(&mut self, op: BranchOp, rs1: u32, rs2: u32, label: &str) {
+        let (funct3, swap) = branch_funct3(op);
+        let (rs1, rs2) = if swap { (rs2, rs1) } else { (rs1, rs2) };
+        self.add_fixup(label.to_string(), FixupKind::Branch);
+        self.emit_inst(rs2 << 20 | rs1 << 15 | funct3 << 12 | OP_BRANCH);
+    }
+
+    fn la(&mut self, rd: u32, sym: &str) -> Result<(), RccError> {
+        let hi_label = self.pcrel_hi_label()?;
+        self.add_fixup(sym.to_string(), FixupKind::PcrelHi);
+        self.emit_inst(u_type(OP_AUIPC, rd, 0));
+        self.add_fixup(hi_label, FixupKind::PcrelLo);
+        self.emit_inst(i_type(OP_IMM, 0, rd, rd, 0));
+        Ok(())
+    }
+
+    fn load(&mut self, op: LoadOp, rd: u32, imm: i32, base: u32) {
+        let (opcode, funct3) = load_funct3(op);
+        self.emit_inst(i_type(opcode, funct3, rd, base, imm));
+    }
+
+    fn store(&mut self, op: StoreOp, rs: u32, imm: i32, base: u32) {
+        let (opcode, funct3) = store_funct3(op);
+        self.emit_inst(s_type(opcode, funct3, base, rs, imm));
+    }
+
+    fn op(&mut self, op: AluOp, rd: u32, rs1: u32, rs2: u32) {
+        let (funct7, funct3) = alu_funct(op);
+        self.emit_inst(r_type(funct7, funct3, rd, rs1, rs2));
+    }
+
+    /// Register-immediate instructions, the immediates of shifts are the shift amounts.
+    fn op_imm(&mut self, op: AluImmOp, rd: u32, rs1: u32, imm: i64) -> Result<(), RccError> {
+        let (funct7, funct3) = alu_imm_funct(op);
+        let imm = match op {
+            AluImmOp::Slli | AluImmOp::Srli | AluImmOp::Srai => {
+                check_imm(imm, 0, 31, &op.to_string())?;
+                imm as i32 | (funct7 << 5) as i32
+            }
+            _ => {
+                check_imm(imm, -2048, 2047, &op.to_string())?;
+                imm as i32
+            }
+        };
+        self.emit_inst(i_type(OP_IMM, funct3, rd, rs1, imm));
+        Ok(())
+    }
+
+    /// Resolve the local references and generate symbols and relocations.
+    pub fn finish(mut self) -> Result<ObjectFile, RccError> {
+        // symbols except the local labels start with `.L`
+        let mut names: Vec<(&String, &(usize, u32))> = self
+            .labels
+            .iter()
+            .filter(|(name, _)| !name.starts_with(".L"))
+            .collect();
+        names.sort_by_key(|(name, (section, offset))| (*section, *offset, name.as_str()));
+        for (name, (section, offset)) in names {
+            let sym_type = if self.functions.contains(name) {
+                STT_FUNC
+            } else {
+                STT_NOTYPE
+            };
+            self.obj.symbols.push(Symbol {
+                name: name.clone(),
+                section: Some(*section),
+                value: *offset,
+                size: 0,
+                binding: if self.globals.contains(name) {
+                    STB_GLOBAL
+                } else {
+                    STB_LOCAL
+                },
+                sym_type,
+            });
+        }
+        let mut undefined_globals: Vec<&String> = self
+            .globals
+            .iter()
+            .filter(|g| !self.labels.contains_key(*g))
+            .collect();
+        undefined_globals.sort();
+        for name in undefined_globals {
+            self.obj.symbols.push(undefined_symbol(name));
+        }
+
+        let fixups = std::mem::take(&mut self.fixups);
+        for fixup in fixups {
+            let local = self
+                .labels
+                .get(&fixup.symbol)
+                .filter(|(section, _)| *section == fixup.section)
+                .map(|(_, offset)| *offset as i64 - fixup.offset as i64);
+            match (&fixup.kind, local) {
+                (FixupKind::Branch, Some(pc_offset)) => {
+                    if !(-4096..4096).contains(&pc_offset) {
+                        return Err(format!("branch to `{}` is out of range", fixup.symbol).into());
+                    }
+                    self.patch(&fixup, b_imm(pc_offset as i32));
+                }
+                (FixupKind::Jal, Some(pc_offset)) => {
+                    if !(-(1 << 20)..1 << 20).contains(&pc_offset) {
+                        return Err(format!("jump to `{}` is out of range", fixup.symbol).into());
+                    }
+                    self.patch(&fixup, j_imm(pc_offset as i32));
+                }
+                (FixupKind::PcrelLo, _) => {
+                    // relative to the `auipc` which is always defined in this section
+                    let symbol = self.symbol_index(&fixup.symbol)?;
+                    self.add_relocation(&fixup, symbol, R_RISCV_PCREL_LO12_I, 0);
+                }
+                (kind, _) => {
+                    let r_type = match kind {
+                        FixupKind::Branch => R_RISCV_BRANCH,
+                        FixupKind::Jal => R_RISCV_JAL,
+                        FixupKind::Call => R_RISCV_CALL_PLT,
+                        FixupKind::PcrelHi => R_RISCV_PCREL_HI20,
+                        FixupKind::Hi => R_RISCV_HI20,
+                        FixupKind::LoI => R_RISCV_LO12_I,
+                        FixupKind::LoS => R_RISCV_LO12_S,
+                        FixupKind::Word => R_RISCV_32,
+                        FixupKind::PcrelLo => unreachable!(),
+                    };
+                    let symbol = self.symbol_index(&fixup.symbol)?;
+                    self.add_relocation(&fixup, symbol, r_type, 0);
+                }
+            }
+        }
+        Ok(self.obj)
+    }
+
+    fn patch(&mut self, fixup: &Fixup, bits: u32) {
+        let data = &mut self.obj.sections[fixup.section].data;
+        let offset = fixup.offset as usize;
+        let mut inst = [0; 4];
+        inst.copy_from_slice(&data[offset..offset + 4]);
+        let inst = u32::from_le_bytes(inst) | bits;
+        data[offset..offset + 4].copy_from_slice(&inst.to_le_bytes());
+    }
+
+    fn add_relocation(&mut self, fixup: &Fixup, symbol: usize, r_type: u32, addend: i32) {
+        self.obj.sections[fixup.section]
+            .relocations
+            .push(Relocation {
+                offset: fixup.offset,
+                symbol,
+                r_type,
+                addend,
+            });
+    }
+
+    /// Index of `name` in symbols, local labels and undefined symbols are added on demand.
+    fn symbol_index(&mut self, name: &str) -> Result<usize, RccError> {
+        if let Some(idx) = self.obj.find_symbol(name) {
+            return Ok(idx);
+        }
+        let symbol = match self.labels.get(name) {
+            Some((section, offset)) => Symbol {
+                name: name.to_string(),
+                section: Some(*section),
+                value: *offset,
+                size: 0,
+                binding: STB_LOCAL,
+                sym_type: STT_NOTYPE,
+            },
+            None if name.starts_with(".L") => {
+                return Err(format!("undefined local label `{}`", name).into())
+            }
+            None => undefined_symbol(name),
+        };
+        self.obj.symbols.push(symbol);
+        Ok(self.obj.symbols.len() - 1)
+    }
+}
+
+/// The code generator emits the machine functions and data into the object file directly.
+impl Riscv32Emitter for Riscv32Assembler {
+    fn file(&mut self, _src_file: &str) -> Result<(), RccError> {
+        Ok(())
+    }
+
+    fn section(&mut self, kind: SectionKind) -> Result<(), RccError> {
+        self.switch_section(kind);
+        Ok(())
+    }
+
+    fn align(&mut self, p2: u32) -> Result<(), RccError> {
+        Riscv32Assembler::align(self, p2);
+        Ok(())
+    }
+
+    fn label(&mut self, label: &str) -> Result<(), RccError> {
+        self.define_label(label)
+    }
+
+    fn string(&mut self, s: &str) -> Result<(), RccError> {
+        let mut bytes = parse_string(&format!("\"{}\"", s))?;
+        bytes.push(0);
+        self.emit_bytes(&bytes);
+        Ok(())
+    }
+
+    fn byte(&mut self, byte: u8) -> Result<(), RccError> {
+        self.emit_bytes(&[byte]);
+        Ok(())
+    }
+
+    fn word(&mut self, word: u32) -> Result<(), RccError> {
+        self.emit_bytes(&word.to_le_bytes());
+        Ok(())
+    }
+
+    fn zero(&mut self, size: u32) -> Result<(), RccError> {
+        self.emit_bytes(&vec![0; size as usize]);
+        Ok(())
+    }
+
+    fn function(
+        &mut self,
+        mfunc: &MachineFunction,
+        is_global: bool,
+        debug_info: bool,
+    ) -> Result<(), RccError> {
+        if is_global {
+            self.globals.insert(mfunc.name.clone());
+        }
+        if debug_info {
+            self.functions.insert(mfunc.name.clone());
+        }
+        self.machine_function(mfunc)
+    }
+}
+
+fn undefined_symbol(name: &str) -> Symbol {
+    Symbol {
+        name: name.to_string(),
+        section: None,
+        value: 0,
+        size: 0,
+        binding: STB_GLOBAL,
+        sym_type: STT_NOTYPE,
+    }
+}
+
+fn invalid_operands(mnemonic: &str) -> RccError {
+    format!("invalid operands of `{}`", mnemonic).into()
+}
+
+fn check_imm(imm: i64, min: i64, max: i64, mnemonic: &str) -> Result<(), RccError> {
+    if imm < min || imm > max {
+        Err(format!("immediate {} of `{}` is out of range", imm, mnemonic).into())
+    } else {
+        Ok(())
+    }
+}
+
+fn imm12(arg: &Arg, mnemonic: &str) -> Result<i32, RccError> {
+    match arg {
+        Arg::Imm(i) => {
+            check_imm(*i, -2048, 2047, mnemonic)?;
+            Ok(*i as i32)
+        }
+        _ => Err(invalid_operands(mnemonic)),
+    }
+}
+
+fn r_type(funct7: u32, funct3: u32, rd: u32, rs1: u32, rs2: u32) -> u32 {
+    funct7 << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | OP_REG
+}
+
+fn i_type(opcode: u32, funct3: u32, rd: u32, rs1: u32, imm: i32) -> u32 {
+    ((imm as u32) & 0xfff) << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | opcode
+}
+
+fn s_type(opcode: u32, funct3: u32, rs1: u32, rs2: u32, imm: i32) -> u32 {
+    let imm = imm as u32;
+    ((imm >> 5) & 0x7f) << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | (imm & 0x1f) << 7 | opcode
+}
+
+/// R-type instructions of OP-FP, `fmt` is 0 for single and 1 for double,
+/// `funct3` is the rounding mode of arithmetic instructions.
+fn fp_type(funct5: u32, fmt: u32, funct3: u32, rd: u32, rs1: u32, rs2: u32) -> u32 {
+    (funct5 << 2 | fmt) << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | OP_FP
+}
+
+fn rounding_mode(rm: &str) -> Result<u32, RccError> {
+    Ok(match rm {
+        "rne" => 0,
+        "rtz" => RM_RTZ,
+        "rdn" => 2,
+        "rup" => 3,
+        "rmm" => 4,
+        "dyn" => RM_DYN,
+        _ => return Err(format!("invalid rounding mode `{}`", rm).into()),
+    })
+}
+
+/// `imm` is the upper 20 bits at bit 12..=31
+fn u_type(opcode: u32, rd: u32, imm: u32) -> u32 {
+    (imm & 0xfffff000) | rd << 7 | opcode
+}
+
+/// Immediate bits of B-type instructions.
+pub(crate) fn b_imm(offset: i32) -> u32 {
+    let imm = offset as u32;
+    ((imm >> 12) & 1) << 31
+        | ((imm >> 5) & 0x3f) << 25
+        | ((imm >> 1) & 0xf) << 8
+        | ((imm >> 11) & 1) << 7
+}
+
+/// Immediate bits of J-type instructions.
+pub(crate) fn j_imm(offset: i32) -> u32 {
+    let imm = offset as u32;
+    ((imm >> 20) & 1) << 31
+        | ((imm >> 1) & 0x3ff) << 21
+        | ((imm >> 11) & 1) << 20
+        | ((imm >> 12) & 0xff) << 12
+}
+
+/// `beqz`, `bnez`, `bltz`, `bgez`, `blez` and `bgtz` compare `rs` with `zero`.
+fn branch_op(mnemonic: &str) -> Option<(BranchOp, bool)> {
+    match mnemonic.strip_suffix('z') {
+        Some(op @ ("beq" | "bne" | "blt" | "bge" | "ble" | "bgt")) => {
+            BranchOp::from_str(op).ok().map(|op| (op, true))
+        }
+        _ => BranchOp::from_str(mnemonic).ok().map(|op| (op, false)),
+    }
+}
+
+/// funct3 of the branch, and whether the operands are swapped
+fn branch_funct3(op: BranchOp) -> (u32, bool) {
+    match op {
+        BranchOp::Beq => (0, false),
+        BranchOp::Bne => (1, false),
+        BranchOp::Blt => (4, false),
+        BranchOp::Bge => (5, false),
+        BranchOp::Bltu => (6, false),
+        BranchOp::Bgeu => (7, false),
+        BranchOp::Bgt => (4, true),
+        BranchOp::Ble => (5, true),
+        BranchOp::Bgtu => (6, true),
+        BranchOp::Bleu => (7, true),
+    }
+}
+
+fn is_fp_load(op: LoadOp) -> bool {
+    matches!(op, LoadOp::Flw | LoadOp::Fld)
+}
+
+fn is_fp_store(op: StoreOp) -> bool {
+    matches!(op, StoreOp::Fsw | StoreOp::Fsd)
+}
+
+/// (opcode, funct3) of loads
+fn load_funct3(op: LoadOp) -> (u32, u32) {
+    match op {
+        LoadOp::Lb => (OP_LOAD, 0),
+        LoadOp::Lh => (OP_LOAD, 1),
+        LoadOp::Lw => (OP_LOAD, 2),
+        LoadOp::Lbu => (OP_LOAD, 4),
+        LoadOp::Lhu => (OP_LOAD, 5),
+        LoadOp::Flw => (OP_LOAD_FP, 2),
+        LoadOp::Fld => (OP_LOAD_FP, 3),
+    }
+}
+
+/// (opcode, funct3) of stores
+fn store_funct3(op: StoreOp) -> (u32, u32) {
+    match op {
+        StoreOp::Sb => (OP_STORE, 0),
+        StoreOp::Sh => (OP_STORE, 1),
+        StoreOp::Sw => (OP_STORE, 2),
+        StoreOp::Fsw => (OP_STORE_FP, 2),
+        StoreOp::Fsd => (OP_STORE_FP, 3),
+    }
+}
+
+/// (funct7, funct3) of RV32I and RV32M register-register instructions
+fn alu_funct(op: AluOp) -> (u32, u32) {
+    match op {
+        AluOp::Add => (0, 0),
+        AluOp::Sub => (0x20, 0),
+        AluOp::Sll => (0, 1),
+        AluOp::Slt => (0, 2),
+        AluOp::Sltu => (0, 3),
+        AluOp::Xor => (0, 4),
+        AluOp::Srl => (0, 5),
+        AluOp::Sra => (0x20, 5),
+        AluOp::Or => (0, 6),
+        AluOp::And => (0, 7),
+        AluOp::Mul => (1, 0),
+        AluOp::Mulh => (1, 1),
+        AluOp::Mulhsu => (1, 2),
+        AluOp::Mulhu => (1, 3),
+        AluOp::Div => (1, 4),
+        AluOp::Divu => (1, 5),
+        AluOp::Rem => (1, 6),
+        AluOp::Remu => (1, 7),
+    }
+}
+
+/// (funct7, funct3) of register-immediate instructions, funct7 is only used by shifts
+fn alu_imm_funct(op: AluImmOp) -> (u32, u32) {
+    match op {
+        AluImmOp::Addi => (0, 0),
+        AluImmOp::Slli => (0, 1),
+        AluImmOp::Slti => (0, 2),
+        AluImmOp::Sltiu => (0, 3),
+        AluImmOp::Xori => (0, 4),
+        AluImmOp::Srli => (0, 5),
+        AluImmOp::Srai => (0x20, 5),
+        AluImmOp::Ori => (0, 6),
+        AluImmOp::Andi => (0, 7),
+    }
+}
+
+fn is_int_fmt(fmt: FpFmt) -> bool {
+    matches!(fmt, FpFmt::W | FpFmt::Wu)
+}
+
+/// `fmt` field of OP-FP instructions, 0 for single and 1 for double
+fn fmt_bits(fmt: FpFmt) -> u32 {
+    (fmt == FpFmt::D) as u32
+}
+
+/// `feq`, `flt` and `fle` write integer registers.
+fn fp_op_writes_int(op: FpOp) -> bool {
+    matches!(op, FpOp::Feq | FpOp::Flt | FpOp::Fle)
+}
+
+/// Register-register floating-point instruction, the rounding mode `rm` is only used
+/// by arithmetic instructions.
+fn fp_op(op: FpOp, fmt: FpFmt, rd: u32, rs1: u32, rs2: u32, rm: Option<u32>) -> u32 {
+    let (funct5, funct3) = match op {
+        FpOp::Fadd => (0x00, rm.unwrap_or(RM_DYN)),
+        FpOp::Fsub => (0x01, rm.unwrap_or(RM_DYN)),
+        FpOp::Fmul => (0x02, rm.unwrap_or(RM_DYN)),
+        FpOp::Fdiv => (0x03, rm.unwrap_or(RM_DYN)),
+        FpOp::Fsgnj => (0x04, 0),
+        FpOp::Fsgnjn => (0x04, 1),
+        FpOp::Fsgnjx => (0x04, 2),
+        FpOp::Fmin => (0x05, 0),
+        FpOp::Fmax => (0x05, 1),
+        FpOp::Feq => (0x14, 2),
+        FpOp::Flt => (0x14, 1),
+        FpOp::Fle => (0x14, 0),
+    };
+    fp_type(funct5, fmt_bits(fmt), funct3, rd, rs1, rs2)
+}
+
+/// `fcvt.{to}.{from}`, `None` if the formats are the same or both integers.
+/// Conversions without rounding use `rne`.
+fn fcvt(to: FpFmt, from: FpFmt, rd: u32, rs: u32, rm: Option<u32>) -> Option<u32> {
+    Some(match (is_int_fmt(to), is_int_fmt(from)) {
+        // float to integer
+        (true, false) => {
+            let rs2 = (to == FpFmt::Wu) as u32;
+            fp_type(0x18, fmt_bits(from), rm.unwrap_or(RM_DYN), rd, rs, rs2)
+        }
+        // integer to float
+        (false, true) => {
+            let rs2 = (from == FpFmt::Wu) as u32;
+            let exact = if to == FpFmt::D { 0 } else { RM_DYN };
+            fp_type(0x1a, fmt_bits(to), rm.unwrap_or(exact), rd, rs, rs2)
+        }
+        // double to single and single to double
+        (false, false) if to == FpFmt::S && from == FpFmt::D => {
+            fp_type(0x08, 0, rm.unwrap_or(RM_DYN), rd, rs, 1)
+        }
+        (false, false) if to == FpFmt::D && from == FpFmt::S => {
+            fp_type(0x08, 1, rm.unwrap_or(0), rd, rs, 0)
+        }
+        _ => return None,
+    })
+}
+
+/// Number of the physical register in its register file.
+fn reg_bits(reg: &MReg) -> Result<u32, RccError> {
+    match reg {
+        MReg::Phys(reg) => Ok(reg.0 as u32 % 32),
+        MReg::Virt(id) => Err(format!("virtual register `%{}` is not allocated", id).into()),
+    }
+}
+
+pub fn freg_number(name: &str) -> Option<u32> {
+    let abi_names = [
+        "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7", "fs0", "fs1", "fa0", "fa1", "fa2",
+        "fa3", "fa4", "fa5", "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7", "fs8", "fs9",
+        "fs10", "fs11", "ft8", "ft9", "ft10", "ft11",
+    ];
+    if let Some(pos) = abi_names.iter().position(|n| *n == name) {
+        return Some(pos as u32);
+    }
+    match name.strip_prefix('f').map(|n| n.parse::<u32>()) {
+        Some(Ok(n)) if n < 32 => Some(n),
+        _ => None,
+    }
+}
+
+pub fn reg_number(name: &str) -> Option<u32> {
+    let abi_names = [
+        "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+        "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+        "t5", "t6",
+    ];
+    if let Some(pos) = abi_names.iter().position(|n| *n == name) {
+        return Some(pos as u32);
+    }
+    if name == "fp" {
+        return Some(8);
+    }
+    match name.strip_prefix('x').map(|n| n.parse::<u32>()) {
+        Some(Ok(n)) if n < 32 => Some(n),
+        _ => None,
+    }
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$'
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_str = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '\\' if in_str => {
+                escaped = !escaped;
+                continue;
+            }
+            '"' if !escaped => in_str = !in_str,
+            '#' if !in_str => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+/// Position of `:` if the line starts with a label.
+fn label_end(line: &str) -> Option<usize> {
+    let pos = line.find(':')?;
+    if line[..pos].trim().chars().all(is_symbol_char) && !line[..pos].trim().is_empty() {
+        Some(pos)
+    } else {
+        None
+    }
+}
+
+fn split_args(s: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut cur = String::new();
+    let mut in_str = false;
+    let mut depth = 0;
+    for c in s.chars() {
+        match c {
+            '"' => in_str = !in_str,
+            '(' if !in_str => depth += 1,
+            ')' if !in_str => depth -= 1,
+            ',' if !in_str && depth == 0 => {
+                args.push(cur.trim().to_string());
+                cur.clear();
+                continue;
+            }
+            _ => {}
+        }
+        cur.push(c);
+    }
+    if !cur.trim().is_empty() {
+        args.push(cur.trim().to_string());
+    }
+    args
+}
+
+/// The last operand of jumps, branches and `la` is a symbol,
+/// even if it is spelled like a register, e.g. `call s1`.
+fn has_symbol_target(mnemonic: &str) -> bool {
+    matches!(mnemonic, "call" | "tail" | "j" | "jal" | "la" | "lla")
+        || branch_op(mnemonic).is_some()
+}
+
+fn parse_symbol_arg(s: &str) -> Result<Arg, RccError> {
+    let s = s.trim();
+    let is_symbol = s.chars().all(is_symbol_char);
+    if !s.is_empty() && !s.starts_with(|c: char| c.is_ascii_digit()) && is_symbol {
+        Ok(Arg::Sym(s.to_string()))
+    } else {
+        parse_arg(s)
+    }
+}
+
+fn parse_arg(s: &str) -> Result<Arg, RccError> {
+    let s = s.trim();
+    if let Some(reg) = reg_number(s) {
+        return Ok(Arg::Reg(reg));
+    }
+    if let Some(reg) = freg_number(s) {
+        return Ok(Arg::FReg(reg));
+    }
+    for (prefix, ctor) in [("%hi(", Arg::Hi as fn(String) -> Arg), ("%lo(", Arg::Lo)] {
+        if let Some(rest) = s.strip_prefix(prefix) {
+            if let Some(pos) = rest.find(')') {
+                let sym = rest[..pos].trim().to_string();
+                let tail = rest[pos + 1..].trim();
+                return if tail.is_empty() {
+                    Ok(ctor(sym))
+                } else {
+                    parse_mem(ctor(sym), tail, s)
+                };
+            }
+        }
+    }
+    if let Some(pos) = s.find('(') {
+        let offset = if pos == 0 {
+            Arg::Imm(0)
+        } else {
+            Arg::Imm(parse_int(&s[..pos])?)
+        };
+        return parse_mem(offset, &s[pos..], s);
+    }
+    if s.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+') {
+        return Ok(Arg::Imm(parse_int(s)?));
+    }
+    if !s.is_empty() && s.chars().all(is_symbol_char) {
+        return Ok(Arg::Sym(s.to_string()));
+    }
+    Err(format!("invalid operand `{}`", s).into())
+}
+
+/// `(reg)` after the offset of a memory operand.
+fn parse_mem(offset: Arg, tail: &str, s: &str) -> Result<Arg, RccError> {
+    match tail
+        .strip_prefix('(')
+        .and_then(|t| t.strip_suffix(')'))
+        .and_then(|r| reg_number(r.trim()))
+    {
+        Some(reg) => Ok(Arg::Mem(Box::new(offset), reg)),
+        None => Err(format!("invalid operand `{}`", s).into()),
+    }
+}
+
+fn parse_int(s: &str) -> Result<i64, RccError> {
+    let s = s.trim();
+    let (neg, digits) = match s.strip_prefix('-') {
+        Some(d) => (true, d),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let value = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        i64::from_str_radix(hex, 16)?
+    } else if let Some(bin) = digits.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2)?
+    } else {
+        digits.parse::<i64>()?
+    };
+    Ok(if neg { -value } else { value })
+}
+
+/// Contents of a quoted string with C-style escapes.
+fn parse_string(s: &str) -> Result<Vec<u8>, RccError> {
+    let s = s.trim();
+    let inner = s
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .ok_or_else(|| RccError::from(format!("invalid string {}", s)))?;
+    let mut bytes = vec![];
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('r') => bytes.push(b'\r'),
+            Some('0') => bytes.push(0),
+            Some('\\') => bytes.push(b'\\'),
+            Some('"') => bytes.push(b'"'),
+            Some('\'') => bytes.push(b'\''),
+            _ => return Err(format!("invalid escape in string {}", s).into()),
+        }
+    }
+    Ok(bytes)
+}
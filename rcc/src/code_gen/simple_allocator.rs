@@ -20,6 +20,7 @@ impl<'cfg> SimpleAllocator<'cfg> {
             var_offsets: HashMap::new(),
         }
     }
+
 }
 
 impl<'cfg> Allocator for SimpleAllocator<'cfg> {
@@ -30,8 +31,21 @@ impl<'cfg> Allocator for SimpleAllocator<'cfg> {
             frame_size *= 2;
         }
         // locals
-        for (_id, ir_type) in self.cfg.local_variables.values() {
-            frame_size += ir_type.byte_size(self.addr_size);
+        let layouts: Vec<(u32, u32)> = self
+            .cfg
+            .local_variables
+            .values()
+            .map(|(_id, ir_type)| (ir_type.byte_size(self.addr_size), ir_type.align(self.addr_size)))
+            .filter(|(size, _)| *size != 0)
+            .collect();
+        frame_size += layouts.iter().map(|(size, _)| size).sum::<u32>();
+        // padding for alignment, offsets are always multiples of the largest power of two
+        // dividing all the sizes
+        if let Some(granule) = layouts.iter().map(|(size, _)| 1 << size.trailing_zeros()).min() {
+            frame_size += layouts
+                .iter()
+                .map(|(_, align)| align.saturating_sub(granule))
+                .sum::<u32>();
         }
         if frame_size % 8 == 0 {
             frame_size
@@ -45,7 +59,8 @@ impl<'cfg> Allocator for SimpleAllocator<'cfg> {
             Some(offset) => *offset,
             None => {
                 let size = ir_type.byte_size(self.addr_size);
-                self.offset += size;
+                let align = ir_type.align(self.addr_size);
+                self.offset = (self.offset + size).div_ceil(align) * align;
                 self.var_offsets.insert(var_name.to_string(), self.offset);
                 self.offset
             }
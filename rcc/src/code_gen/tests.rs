@@ -0,0 +1,544 @@
+use crate::code_gen::elf::*;
+use crate::code_gen::linker::{link, link_with_runtime};
+use crate::code_gen::peephole;
+use crate::code_gen::mir::{MachineInst::*, *};
+use crate::code_gen::reg_alloc;
+use crate::code_gen::riscv32_asm::assemble;
+use crate::tests::rv32_sim::run_elf;
+
+fn text_words(obj: &ObjectFile) -> Vec<u32> {
+    obj.sections[0]
+        .data
+        .chunks(4)
+        .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+        .collect()
+}
+
+#[test]
+fn encode_test() {
+    let obj = assemble(
+        r#"
+	addi	sp,sp,-24
+	sw	s0,20(sp)
+	lw	a5,-16(s0)
+	lb	a0,0(a1)
+	lbu	a0,-1(a1)
+	lh	t0,2(t1)
+	lhu	t2,4(s1)
+	sb	a5,-1(s0)
+	sh	a5,6(s0)
+	add	a5,a4,a5
+	sub	a5,a4,a5
+	mul	a5,a4,a5
+	div	a5,a4,a5
+	divu	a5,a4,a5
+	rem	a5,a4,a5
+	remu	a5,a4,a5
+	and	a0,a1,a2
+	or	a0,a1,a2
+	xor	a0,a1,a2
+	sll	a0,a1,a2
+	srl	a0,a1,a2
+	sra	a0,a1,a2
+	slt	a0,a1,a2
+	sltu	a0,a1,a2
+	slli	a0,a1,3
+	srli	a0,a1,31
+	srai	a0,a1,7
+	andi	a0,a1,-1
+	ori	a0,a1,255
+	xori	a0,a1,-1
+	slti	a0,a1,10
+	sltiu	a0,a1,1
+	li	a5,2047
+	li	a5,-2048
+	li	a5,2048
+	li	a5,-44
+	li	a5,305419896
+	li	a5,-2147483648
+	li	a5,4096
+	mv	a5,a0
+	not	a0,a1
+	neg	a0,a1
+	seqz	a0,a1
+	snez	a0,a1
+	ret
+	nop
+	jr	t0
+	lui	a0,74565
+	auipc	a0,1
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        vec![
+            0xfe810113, 0x00812a23, 0xff042783, 0x00058503, 0xfff5c503, 0x00231283, 0x0044d383,
+            0xfef40fa3, 0x00f41323, 0x00f707b3, 0x40f707b3, 0x02f707b3, 0x02f747b3, 0x02f757b3,
+            0x02f767b3, 0x02f777b3, 0x00c5f533, 0x00c5e533, 0x00c5c533, 0x00c59533, 0x00c5d533,
+            0x40c5d533, 0x00c5a533, 0x00c5b533, 0x00359513, 0x01f5d513, 0x4075d513, 0xfff5f513,
+            0x0ff5e513, 0xfff5c513, 0x00a5a513, 0x0015b513, 0x7ff00793, 0x80000793, 0x000017b7,
+            0x80078793, 0xfd400793, 0x123457b7, 0x67878793, 0x800007b7, 0x000017b7, 0x00050793,
+            0xfff5c513, 0x40b00533, 0x0015b513, 0x00b03533, 0x00008067, 0x00000013, 0x00028067,
+            0x12345537, 0x00001517,
+        ],
+        text_words(&obj)
+    );
+    assert!(obj.sections[0].relocations.is_empty());
+}
+
+#[test]
+fn encode_fp_test() {
+    let obj = assemble(
+        r#"
+	flw	fa5,-12(s0)
+	fld	ft0,8(a5)
+	fsw	fa5,-12(s0)
+	fsd	ft11,2040(sp)
+	fadd.s	fa5,fa4,fa5
+	fsub.d	fa5,fa4,fa5
+	fmul.d	fa0,fa1,fa2
+	fdiv.s	ft1,ft2,ft3
+	fsqrt.d	fa0,fa1
+	fsgnj.s	fa0,fa1,fa2
+	fsgnjn.d	fa0,fa1,fa2
+	fsgnjx.d	fa0,fa1,fa2
+	fmin.s	fa0,fa1,fa2
+	fmax.d	fa0,fa1,fa2
+	feq.s	a5,fa4,fa5
+	flt.d	a5,fa4,fa5
+	fle.d	a0,fa4,fa5
+	fmv.s	fa5,fa0
+	fmv.d	fa5,fa0
+	fneg.d	fa5,fa0
+	fabs.s	fa5,fa0
+	fcvt.w.s	a0,fa0,rtz
+	fcvt.wu.d	a0,fa0,rtz
+	fcvt.w.d	a0,fa0
+	fcvt.s.w	fa0,a0
+	fcvt.d.w	fa0,zero
+	fcvt.d.wu	fa0,a1
+	fcvt.s.wu	fa0,a1
+	fcvt.s.d	fa0,fa1
+	fcvt.d.s	fa0,fa1
+	fmv.x.w	a0,fa0
+	fmv.w.x	fa0,a0
+	fadd.d	fa0,fa1,fa2,rne
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        vec![
+            0xff442787, 0x0087b007, 0xfef42a27, 0x7ff13c27, 0x00f777d3, 0x0af777d3, 0x12c5f553,
+            0x183170d3, 0x5a05f553, 0x20c58553, 0x22c59553, 0x22c5a553, 0x28c58553, 0x2ac59553,
+            0xa0f727d3, 0xa2f717d3, 0xa2f70553, 0x20a507d3, 0x22a507d3, 0x22a517d3, 0x20a527d3,
+            0xc0051553, 0xc2151553, 0xc2057553, 0xd0057553, 0xd2000553, 0xd2158553, 0xd015f553,
+            0x4015f553, 0x42058553, 0xe0050553, 0xf0050553, 0x02c58553,
+        ],
+        text_words(&obj)
+    );
+}
+
+#[test]
+fn branch_test() {
+    let obj = assemble(
+        r#"
+f:
+	beq	a5,a4,.L1
+	bne	a5,a4,.L1
+	blt	a5,a4,.L1
+	bge	a5,a4,.L1
+	bltu	a5,a4,.L1
+	bgeu	a5,a4,.L1
+	bgt	a5,a4,.L1
+	ble	a5,a4,.L1
+	beqz	a5,.L1
+	bnez	a5,.L1
+.L1:
+	j	f
+	j	.L1
+	jal	f
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        vec![
+            0x02e78463, 0x02e79263, 0x02e7c063, 0x00e7de63, 0x00e7ec63, 0x00e7fa63, 0x00f74863,
+            0x00f75663, 0x00078463, 0x00079263, 0xfd9ff06f, 0xffdff06f, 0xfd1ff0ef,
+        ],
+        text_words(&obj)
+    );
+    // `.L1` is not a symbol
+    assert_eq!(1, obj.symbols.len());
+    assert_eq!("f", obj.symbols[0].name);
+    assert!(!obj.symbols[0].is_global());
+}
+
+/// Functions may be named like registers.
+#[test]
+fn register_named_symbol_test() {
+    let obj = assemble(
+        r#"
+s1:
+	ret
+	.globl  a0
+a0:
+	call	s1
+	tail	a0
+	la	s1,ra
+	beqz	s1,s1
+	j	s1
+	jal	ra,a0
+ra:
+"#,
+    )
+    .unwrap();
+    let names: Vec<&str> = obj.symbols.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(vec!["s1", "a0", "ra", ".Lpcrel_hi0"], names);
+    let relocations: Vec<(u32, usize, u32)> = obj.sections[0]
+        .relocations
+        .iter()
+        .map(|r| (r.offset, r.symbol, r.r_type))
+        .collect();
+    assert_eq!(
+        vec![
+            (4, 0, R_RISCV_CALL_PLT),
+            (12, 1, R_RISCV_CALL_PLT),
+            (20, 2, R_RISCV_PCREL_HI20),
+            (24, 3, R_RISCV_PCREL_LO12_I),
+        ],
+        relocations
+    );
+    // `la s1,ra` loads into `s1`
+    assert_eq!(0x00000497, text_words(&obj)[5]);
+}
+
+#[test]
+fn relocation_test() {
+    let obj = assemble(
+        r#"
+	.text
+	.section	.rodata
+.LC0:
+	.string "hi\n"
+	.text
+	.globl  main
+main:
+	la	a0,.LC0
+	call	puts
+	tail	main
+"#,
+    )
+    .unwrap();
+    assert_eq!(2, obj.sections.len());
+    assert_eq!(SectionKind::ROData, obj.sections[1].kind);
+    assert_eq!(b"hi\n\0".to_vec(), obj.sections[1].data);
+    assert_eq!(
+        vec![0x00000517, 0x00050513, 0x00000097, 0x000080e7, 0x00000317, 0x00030067],
+        text_words(&obj)
+    );
+
+    let names: Vec<&str> = obj.symbols.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(vec!["main", ".LC0", ".Lpcrel_hi0", "puts"], names);
+    assert!(obj.symbols[0].is_global());
+    assert_eq!(None, obj.symbols[3].section);
+    let relocations: Vec<(u32, usize, u32)> = obj.sections[0]
+        .relocations
+        .iter()
+        .map(|r| (r.offset, r.symbol, r.r_type))
+        .collect();
+    assert_eq!(
+        vec![
+            (0, 1, R_RISCV_PCREL_HI20),
+            (4, 2, R_RISCV_PCREL_LO12_I),
+            (8, 3, R_RISCV_CALL_PLT),
+            (16, 0, R_RISCV_CALL_PLT),
+        ],
+        relocations
+    );
+}
+
+#[test]
+fn asm_error_test() {
+    assert_eq!(
+        Err("asm line 1: unknown instruction `foo`".into()),
+        assemble("foo a0").map(|_| ())
+    );
+    assert_eq!(
+        Err("asm line 2: immediate 2048 of `addi` is out of range".into()),
+        assemble("\n addi a0,a0,2048").map(|_| ())
+    );
+    assert_eq!(
+        Err("asm line 2: symbol `f` is already defined".into()),
+        assemble("f:\nf:").map(|_| ())
+    );
+    assert_eq!(
+        Err("undefined local label `.L3`".into()),
+        assemble("j .L3").map(|_| ())
+    );
+}
+
+#[test]
+fn write_elf_test() {
+    let obj = assemble("\t.globl  main\nmain:\n\tcall\tputchar\n\tret\n").unwrap();
+    let mut buf = vec![];
+    obj.write(&mut buf).unwrap();
+    assert_eq!(b"\x7fELF\x01\x01\x01", &buf[..7]);
+    // e_type, e_machine
+    assert_eq!([1, 0, 243, 0], buf[16..20]);
+    let shoff = u32::from_le_bytes([buf[32], buf[33], buf[34], buf[35]]) as usize;
+    let shnum = u16::from_le_bytes([buf[48], buf[49]]) as usize;
+    // null, .text, .rela.text, .symtab, .strtab, .shstrtab
+    assert_eq!(6, shnum);
+    assert_eq!(shoff + shnum * 40, buf.len());
+}
+
+#[test]
+fn link_test() {
+    let obj = assemble(
+        r#"
+	.section	.rodata
+.LC0:
+	.string "hi\n"
+	.data
+	.align	2
+count:
+	.word	0
+	.text
+	.globl  main
+main:
+	addi	sp,sp,-16
+	sw	ra,12(sp)
+	sw	s0,8(sp)
+	la	s0,.LC0
+.L1:
+	lbu	a0,0(s0)
+	beqz	a0,.L2
+	call	putchar
+	lui	a5,%hi(count)
+	lw	a4,%lo(count)(a5)
+	addi	a4,a4,1
+	sw	a4,%lo(count)(a5)
+	addi	s0,s0,1
+	j	.L1
+.L2:
+	lui	a5,%hi(count)
+	lw	a0,%lo(count)(a5)
+	lw	ra,12(sp)
+	lw	s0,8(sp)
+	addi	sp,sp,16
+	ret
+"#,
+    )
+    .unwrap();
+    let mut exe = vec![];
+    link_with_runtime(obj).unwrap().write(&mut exe).unwrap();
+    let res = run_elf(&exe).unwrap();
+    assert_eq!(b"hi\n".to_vec(), res.stdout);
+    assert_eq!(3, res.exit_code);
+}
+
+#[test]
+fn link_error_test() {
+    let obj = || assemble("\t.globl  _start\n_start:\n\tcall\tfoo\n").unwrap();
+    assert_eq!(
+        Err("undefined reference to `foo`".into()),
+        link(vec![obj()], vec![]).map(|_| ())
+    );
+    let foo = || assemble("\t.globl  foo\nfoo:\n\tret\n").unwrap();
+    assert!(link(vec![obj()], vec![foo()]).is_ok());
+    assert_eq!(
+        Err("multiple definition of `foo`".into()),
+        link(vec![obj(), foo(), foo()], vec![]).map(|_| ())
+    );
+    assert_eq!(
+        Err("undefined entry symbol `_start`".into()),
+        link(vec![foo()], vec![]).map(|_| ())
+    );
+}
+
+fn func(blocks: Vec<(Option<&str>, Vec<MachineInst>)>) -> MachineFunction {
+    let mut func = MachineFunction::new("f".into());
+    for (label, insts) in blocks {
+        let mut block = MachineBlock::new(label.map(|l| l.to_string()));
+        block.insts = insts;
+        func.blocks.push(block);
+    }
+    func
+}
+
+fn peephole_asm(blocks: Vec<(Option<&str>, Vec<MachineInst>)>) -> String {
+    let mut func = func(blocks);
+    peephole::optimize(&mut func);
+    func.to_string()
+}
+
+fn sw(rs: Reg, offset: i32) -> MachineInst {
+    Store {
+        op: StoreOp::Sw,
+        rs: rs.into(),
+        offset,
+        base: Reg::FP.into(),
+    }
+}
+
+fn lw(rd: Reg, offset: i32) -> MachineInst {
+    Load {
+        op: LoadOp::Lw,
+        rd: rd.into(),
+        offset,
+        base: Reg::FP.into(),
+    }
+}
+
+#[test]
+fn reg_alloc_test() {
+    let mut f = func(vec![(None, vec![])]);
+    let (v0, v1, v2, v3) = (f.new_vreg(), f.new_vreg(), f.new_vreg(), f.new_vreg());
+    f.blocks[0].insts = vec![
+        Load {
+            op: LoadOp::Lw,
+            rd: v0,
+            offset: -20,
+            base: Reg::FP.into(),
+        },
+        Load {
+            op: LoadOp::Lw,
+            rd: v1,
+            offset: -24,
+            base: Reg::FP.into(),
+        },
+        Op {
+            op: AluOp::Add,
+            rd: v2,
+            rs1: v0,
+            rs2: v1,
+        },
+        OpImm {
+            op: AluImmOp::Addi,
+            rd: v3,
+            rs1: v2,
+            imm: 1,
+        },
+        Mv {
+            rd: Reg::A0.into(),
+            rs: v3,
+        },
+        Ret,
+    ];
+    assert_eq!(
+        "f:\n\tlw\t%0,-20(s0)\n\tlw\t%1,-24(s0)\n\tadd\t%2,%0,%1\n\taddi\t%3,%2,1\n\tmv\ta0,%3\n\tret\n",
+        f.to_string()
+    );
+    reg_alloc::allocate(&mut f).unwrap();
+    assert_eq!(
+        "f:\n\tlw\ta4,-20(s0)\n\tlw\ta5,-24(s0)\n\tadd\ta5,a4,a5\n\taddi\ta5,a5,1\n\tmv\ta0,a5\n\tret\n",
+        f.to_string()
+    );
+}
+
+#[test]
+fn peephole_load_store_test() {
+    let blocks = vec![
+        (
+            None,
+            vec![
+                sw(Reg::A5, -20),
+                lw(Reg::A5, -20),
+                lw(Reg::A4, -20),
+                Call {
+                    callee: "foo".into(),
+                    arg_count: 0,
+                    fp_arg_count: 0,
+                },
+                lw(Reg::A4, -20),
+                sw(Reg::A4, -12),
+                sw(Reg::A5, -12),
+            ],
+        ),
+        (Some(".L1"), vec![lw(Reg::A0, -12), Ret]),
+    ];
+    assert_eq!(
+        "f:\n\tsw\ta5,-20(s0)\n\tmv\ta4,a5\n\tcall\tfoo\n\tlw\ta4,-20(s0)\n\tsw\ta5,-12(s0)\n\
+         .L1:\n\tlw\ta0,-12(s0)\n\tret\n",
+        peephole_asm(blocks)
+    );
+}
+
+#[test]
+fn peephole_imm_test() {
+    let op = |op, rs2: Reg| Op {
+        op,
+        rd: Reg::A5.into(),
+        rs1: Reg::A5.into(),
+        rs2: rs2.into(),
+    };
+    let li = |rd: Reg, imm| Li { rd: rd.into(), imm };
+    let insts = vec![
+        li(Reg::A4, 3),
+        op(AluOp::Add, Reg::A4),
+        li(Reg::A4, 8),
+        op(AluOp::Mul, Reg::A4),
+        li(Reg::A4, 5),
+        op(AluOp::Sub, Reg::A4),
+        // a4 is still used
+        li(Reg::A4, 7),
+        op(AluOp::Add, Reg::A4),
+        Mv {
+            rd: Reg::A0.into(),
+            rs: Reg::A4.into(),
+        },
+        Call {
+            callee: "foo".into(),
+            arg_count: 1,
+            fp_arg_count: 0,
+        },
+        li(Reg::A5, 1),
+        Mv {
+            rd: Reg::A0.into(),
+            rs: Reg::A5.into(),
+        },
+    ];
+    assert_eq!(
+        "f:\n\taddi\ta5,a5,3\n\tslli\ta5,a5,3\n\taddi\ta5,a5,-5\n\tli\ta4,7\n\tadd\ta5,a5,a4\n\
+         \tmv\ta0,a4\n\tcall\tfoo\n\tli\ta0,1\n\tret\n",
+        peephole_asm(vec![(None, insts), (None, vec![Ret])])
+    );
+}
+
+#[test]
+fn peephole_jump_test() {
+    let blocks = vec![
+        (
+            None,
+            vec![J(".L1".into()), Directive(".loc\t1 2 0".into())],
+        ),
+        (Some(".L0"), vec![]),
+        (
+            Some(".L1"),
+            vec![
+                Branch {
+                    op: BranchOp::Beq,
+                    rs1: Reg::A5.into(),
+                    rs2: Reg::A4.into(),
+                    target: ".L2".into(),
+                },
+                J(".L1".into()),
+            ],
+        ),
+        (Some(".L2"), vec![Ret]),
+    ];
+    assert_eq!(
+        "f:\n\t.loc\t1 2 0\n.L0:\n.L1:\n\tbeq\ta5,a4,.L2\n\tj\t.L1\n.L2:\n\tret\n",
+        peephole_asm(blocks)
+    );
+}
+
+#[test]
+fn mangle_test() {
+    use crate::code_gen::mangle;
+    assert_eq!("main", mangle("main"));
+    assert_eq!("_ZN5Point3newE", mangle("Point::new"));
+    assert_eq!("_ZN1A11bcdefghijklE", mangle("A::bcdefghijkl"));
+    assert_eq!("_ZN3max12_3c_26i32_3eE", mangle("max::<&i32>"));
+    assert_eq!("_ZN4pair20_3ci32_2c_20Point_3eE", mangle("pair::<i32, Point>"));
+}
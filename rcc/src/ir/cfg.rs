@@ -1,6 +1,7 @@
-use crate::ir::linear_ir::{Func, LinearIR};
+use crate::analyser::sym_resolver::VarKind;
+use crate::ir::linear_ir::{ExternFn, Func, LinearIR, StaticData};
 use crate::ir::var_name::local_var;
-use crate::ir::{IRInst, IRType};
+use crate::ir::{IRInst, IRType, Operand, Place};
 use std::collections::{BTreeSet, HashMap, LinkedList};
 use crate::rcc::RccError;
 use crate::ir::dataflow::reaching_definitions::ReachingDefinitionsAnalysis;
@@ -11,6 +12,11 @@ pub struct CFGIR {
 
     /// read only local strings, <label, value>
     pub ro_local_strs: HashMap<String, String>,
+
+    /// functions declared in `extern "C"` blocks
+    pub extern_fns: Vec<ExternFn>,
+
+    pub statics: Vec<StaticData>,
 }
 
 impl CFGIR {
@@ -19,6 +25,8 @@ impl CFGIR {
         CFGIR {
             cfgs,
             ro_local_strs: linear_ir.ro_local_strs,
+            extern_fns: linear_ir.extern_fns,
+            statics: linear_ir.statics,
         }
     }
 
@@ -43,14 +51,20 @@ pub struct CFG {
     pub func_name: String,
     pub func_scope_id: u64,
     pub func_is_global: bool,
+    pub func_line: u32,
     pub fn_args: Vec<(String, IRType)>,
     pub fn_args_local_var: Vec<String>,
+    pub ret_type: IRType,
     pub is_leaf: bool,
+
+    /// Source line of each instruction in `basic_blocks[i].instructions`, 0 if unknown
+    pub lines: Vec<Vec<u32>>,
 }
 
 pub type BasicBlockId = usize;
 
-/// number of successors less equal than 2 (the next leader or goto label)
+/// number of successors less equal than 2 (the next leader or goto label),
+/// except for blocks ending with a jump table
 #[derive(Debug)]
 pub struct BasicBlock {
     /// start from 0
@@ -71,6 +85,7 @@ impl CFG {
         let mut leader = 1usize;
 
         let mut inst_id = 1;
+        let mut lines = Vec::with_capacity(leaders.len());
         let mut basic_blocks: Vec<BasicBlock> = leaders
             .iter()
             .enumerate()
@@ -79,8 +94,10 @@ impl CFG {
                 let mut inst_count = next_leader - leader;
                 leader = *next_leader;
                 let mut bb = LinkedList::new();
+                let mut bb_lines = vec![];
                 while inst_count > 0 {
                     let inst = func.insts.pop_front().unwrap();
+                    let line = func.lines.pop_front().unwrap_or(0);
                     match inst {
                         // delete instructions like `(n) if cond goto n+1`
                         IRInst::Jump { label }
@@ -89,15 +106,18 @@ impl CFG {
                         | IRInst::JumpIfCond { label, .. } => {
                             if inst_id + 1 != label {
                                 bb.push_back(inst);
+                                bb_lines.push(line);
                             }
                         }
                         _ => {
                             bb.push_back(inst);
+                            bb_lines.push(line);
                         }
                     }
                     inst_count -= 1;
                     inst_id += 1;
                 }
+                lines.push(bb_lines);
                 BasicBlock::new(i, bb)
             })
             .collect();
@@ -123,6 +143,15 @@ impl CFG {
                             Some(vec![*label])
                         }
                     }
+                    IRInst::JumpTable {
+                        labels, default, ..
+                    } => {
+                        for label in labels.iter_mut() {
+                            *label = *label_map.get(label).unwrap();
+                        }
+                        *default = *label_map.get(default).unwrap();
+                        Some(jump_table_targets(labels, *default))
+                    }
                     _ => {
                         if i < last_bb_id {
                             Some(vec![i + 1])
@@ -152,9 +181,12 @@ impl CFG {
             func_name: func.name,
             func_scope_id: func.block_scope_id,
             func_is_global: func.is_global,
+            func_line: func.line,
             fn_args: func.fn_args,
             fn_args_local_var,
+            ret_type: func.ret_type,
             is_leaf,
+            lines,
         }
     }
 
@@ -171,6 +203,9 @@ impl CFG {
             .unwrap()
         {
             IRInst::Jump { label } => vec![*label],
+            IRInst::JumpTable {
+                labels, default, ..
+            } => jump_table_targets(labels, *default),
 
             IRInst::JumpIf { label, .. }
             | IRInst::JumpIfNot { label, .. }
@@ -215,6 +250,13 @@ fn get_leaders_and_is_leaf(func: &Func) -> (BTreeSet<usize>, bool) {
                     insert_leaders!(leaders, label, i + 2);
                 }
             }
+            IRInst::JumpTable {
+                labels, default, ..
+            } => {
+                leaders.extend(labels.iter());
+                leaders.insert(*default);
+                leaders.insert(i + 2);
+            }
             IRInst::Call { .. } => {
                 is_leaf = false;
             }
@@ -226,6 +268,15 @@ fn get_leaders_and_is_leaf(func: &Func) -> (BTreeSet<usize>, bool) {
     (leaders, is_leaf)
 }
 
+/// Distinct targets of a jump table, the default target is the last one.
+fn jump_table_targets(labels: &[usize], default: usize) -> Vec<usize> {
+    let mut targets: Vec<usize> = labels.iter().copied().filter(|l| *l != default).collect();
+    targets.sort_unstable();
+    targets.dedup();
+    targets.push(default);
+    targets
+}
+
 fn get_local_variables(func: &Func) -> HashMap<String, (usize, IRType)> {
     let mut local_variables = HashMap::new();
     let mut next_id: usize = 0;
@@ -234,16 +285,33 @@ fn get_local_variables(func: &Func) -> HashMap<String, (usize, IRType)> {
         local_variables.insert(var_name, (next_id, arg.1));
     }
 
+    let mut add_variable = |place: &Place| {
+        if !local_variables.contains_key(&place.label) {
+            local_variables.insert(place.label.clone(), (next_id, place.ir_type));
+            next_id += 1;
+        }
+    };
     for inst in func.insts.iter() {
         match inst {
             IRInst::BinOp { dest, .. }
             | IRInst::LoadData { dest, .. }
-            | IRInst::LoadAddr { dest, .. } => {
-                if !local_variables.contains_key(&dest.label) {
-                    local_variables.insert(dest.label.clone(), (next_id, dest.ir_type));
-                    next_id += 1;
+            | IRInst::Cast { dest, .. }
+            | IRInst::Neg { dest, .. }
+            | IRInst::LoadMem { dest, .. } => add_variable(dest),
+            // aggregates may be only written through their memory or their addresses
+            IRInst::LoadAddr { dest, symbol } => {
+                add_variable(dest);
+                match symbol {
+                    Operand::Place(p) if matches!(p.kind, VarKind::Local | VarKind::LocalMut) => {
+                        add_variable(p)
+                    }
+                    _ => {}
                 }
             }
+            IRInst::StoreMem {
+                base: Operand::Place(p),
+                ..
+            } if p.ir_type.is_aggregate() => add_variable(p),
             _ => {}
         }
     }
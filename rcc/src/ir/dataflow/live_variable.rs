@@ -99,10 +99,21 @@ impl<'cfg> LiveVariableAnalysis<'cfg> {
         let in_state = self.in_states.get_mut(bb_id).unwrap();
         in_state.clone_from(out_state);
         match inst {
-            IRInst::LoadAddr { .. } => {
-                todo!()
+            IRInst::LoadAddr { dest, symbol } => {
+                gen!(self, dest, in_state);
+                kill!(self, symbol, in_state);
+            }
+            IRInst::LoadMem { dest, base, .. } => {
+                gen!(self, dest, in_state);
+                kill!(self, base, in_state);
+            }
+            IRInst::StoreMem { base, src, .. } => {
+                kill!(self, base, in_state);
+                kill!(self, src, in_state);
             }
-            IRInst::LoadData { dest, src } => {
+            IRInst::LoadData { dest, src }
+            | IRInst::Cast { dest, src }
+            | IRInst::Neg { dest, src } => {
                 gen!(self, dest, in_state);
                 kill!(self, src, in_state);
             }
@@ -113,7 +124,9 @@ impl<'cfg> LiveVariableAnalysis<'cfg> {
                 kill!(self, src1, in_state);
                 kill!(self, src2, in_state);
             }
-            IRInst::JumpIf { cond, .. } | IRInst::JumpIfNot { cond, .. } => {
+            IRInst::JumpIf { cond, .. }
+            | IRInst::JumpIfNot { cond, .. }
+            | IRInst::JumpTable { src: cond, .. } => {
                 kill!(self, cond, in_state);
             }
             IRInst::JumpIfCond { src1, src2, .. } => {
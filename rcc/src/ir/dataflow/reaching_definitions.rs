@@ -62,8 +62,42 @@ impl<'cfg> ReachingDefinitionsAnalysis<'cfg> {
                         bb_id,
                         inst_id as isize,
                     ),
-                    IRInst::LoadData { dest, .. } => add_definitions(
-                        dest,
+                    IRInst::LoadData { dest, .. }
+                    | IRInst::Cast { dest, .. }
+                    | IRInst::Neg { dest, .. }
+                    | IRInst::LoadMem { dest, .. } => {
+                        add_definitions(
+                            dest,
+                            &mut definitions,
+                            &mut next_definition_id,
+                            bb_id,
+                            inst_id as isize,
+                        )
+                    }
+                    // the place may be written through its address
+                    IRInst::LoadAddr { dest, symbol } => {
+                        add_definitions(
+                            dest,
+                            &mut definitions,
+                            &mut next_definition_id,
+                            bb_id,
+                            inst_id as isize,
+                        );
+                        if let Operand::Place(p) = symbol {
+                            add_definitions(
+                                p,
+                                &mut definitions,
+                                &mut next_definition_id,
+                                bb_id,
+                                inst_id as isize,
+                            );
+                        }
+                    }
+                    IRInst::StoreMem {
+                        base: Operand::Place(p),
+                        ..
+                    } if p.ir_type.is_aggregate() => add_definitions(
+                        p,
                         &mut definitions,
                         &mut next_definition_id,
                         bb_id,
@@ -161,10 +195,31 @@ impl<'cfg> ReachingDefinitionsAnalysis<'cfg> {
                     self.valid(bb_id, src2)?;
                     self.gen_kill(dest, bb_id, inst_id as isize);
                 }
-                IRInst::LoadData { dest, src } => {
+                IRInst::LoadData { dest, src }
+                | IRInst::Cast { dest, src }
+                | IRInst::Neg { dest, src } => {
                     self.valid(bb_id, src)?;
                     self.gen_kill(dest, bb_id, inst_id as isize);
                 }
+                IRInst::LoadMem { dest, base, .. } => {
+                    self.valid(bb_id, base)?;
+                    self.gen_kill(dest, bb_id, inst_id as isize);
+                }
+                IRInst::LoadAddr { dest, symbol } => {
+                    self.gen_kill(dest, bb_id, inst_id as isize);
+                    if let Operand::Place(p) = symbol {
+                        self.gen_kill(p, bb_id, inst_id as isize);
+                    }
+                }
+                IRInst::StoreMem { base, src, .. } => {
+                    self.valid(bb_id, src)?;
+                    match base {
+                        Operand::Place(p) if p.ir_type.is_aggregate() => {
+                            self.gen_kill(p, bb_id, inst_id as isize)
+                        }
+                        _ => self.valid(bb_id, base)?,
+                    }
+                }
                 _ => {}
             }
         }
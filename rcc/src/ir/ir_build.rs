@@ -1,36 +1,84 @@
+use crate::analyser::const_eval;
 use crate::analyser::scope::ScopeStack;
 use crate::analyser::sym_resolver::{TypeInfo, VarKind};
 use crate::ast::expr::{
     ArrayExpr, ArrayIndexExpr, AssignExpr, AssignOp, BinOpExpr, BinOperator, BlockExpr, BreakExpr,
-    CallExpr, Expr, ExprKind, ExprVisit, FieldAccessExpr, GroupedExpr, IfExpr, LhsExpr, LitNumExpr,
-    LoopExpr, PathExpr, RangeExpr, ReturnExpr, StructExpr, TupleExpr, TupleIndexExpr, UnAryExpr,
+    CallExpr, CastExpr, ContinueExpr, EnumVariantExpr, Expr, ExprKind, ExprVisit, FieldAccessExpr, ForExpr, GroupedExpr, IfExpr, LhsExpr, LitNumExpr,
+    LoopExpr, MatchExpr, MethodCallExpr, PathExpr, RangeExpr, RangeOp, ReturnExpr, StructExpr, TupleExpr, TupleIndexExpr, UnAryExpr,
     UnOp, WhileExpr,
 };
 use crate::ast::file::File;
-use crate::ast::item::{Item, ItemFn, ItemStruct};
-use crate::ast::pattern::{IdentPattern, Pattern};
+use crate::ast::item::{Item, ItemFn, ItemStatic, ItemStruct, TypeEnum};
+use crate::ast::pattern::{is_variant_path, Pattern, StructPattern, TupleStructPattern};
 use crate::ast::stmt::{LetStmt, Stmt};
-use crate::ast::types::TypeLitNum;
+use crate::ast::types::{PtrKind, TypeLitNum};
 use crate::ast::AST;
 use crate::ir;
-use crate::ir::linear_ir::LinearIR;
+use crate::ir::linear_ir::{ExternFn, LinearIR, StaticData};
+use crate::ir::layout::{ir_type_of, EnumLayout, FieldLayout, StructLayout};
+use crate::ir::var_name::local_var;
 use crate::ir::Jump::*;
 use crate::ir::{IRInst, IRType, Jump, Operand, Place};
 use crate::rcc::{OptimizeLevel, RccError};
 use std::cell::RefCell;
 use std::ops::Deref;
+use std::ptr::NonNull;
 use std::rc::Rc;
 
+/// Value matched by a pattern
+#[derive(Clone)]
+enum Matched {
+    /// a scalar operand or an aggregate place
+    Value(Operand),
+    /// the value in memory at `base + offset`, addressed like `IRInst::load_mem`
+    Mem(Operand, u32),
+}
+
+impl Matched {
+    /// The field at `offset` of the matched aggregate.
+    fn field(&self, offset: u32) -> Matched {
+        match self {
+            Matched::Value(operand) => Matched::Mem(operand.clone(), offset),
+            Matched::Mem(base, base_offset) => Matched::Mem(base.clone(), base_offset + offset),
+        }
+    }
+}
+
+/// Loop being visited
+struct LoopVar {
+    label: Option<String>,
+    /// place = loop expr
+    place: Option<Place>,
+    break_link: usize,
+    continue_link: usize,
+}
+
+impl LoopVar {
+    fn new(label: Option<String>, place: Option<Place>, break_link: usize) -> LoopVar {
+        LoopVar {
+            label,
+            place,
+            break_link,
+            continue_link: 0,
+        }
+    }
+}
+
 pub struct IRBuilder {
     ir_output: LinearIR,
     fn_ret_temp_var: Vec<Place>,
 
     scope_stack: ScopeStack,
 
-    // (place = loop expr, break link)
-    loop_var_stack: Vec<(Option<Place>, usize)>,
+    loop_var_stack: Vec<LoopVar>,
 
     optimize_level: OptimizeLevel,
+
+    /// size of addresses in bits, which decides the layout of structs
+    addr_size: u32,
+
+    /// whether indexing arrays with non-constant indices aborts when out of bounds
+    bounds_check: bool,
 }
 
 impl IRBuilder {
@@ -41,9 +89,22 @@ impl IRBuilder {
             scope_stack: ScopeStack::new(),
             loop_var_stack: vec![],
             optimize_level,
+            addr_size: 32,
+            bounds_check: true,
         }
     }
 
+    pub fn with_addr_size(mut self, addr_size: u32) -> Self {
+        debug_assert!(addr_size == 32 || addr_size == 64);
+        self.addr_size = addr_size;
+        self
+    }
+
+    pub fn with_no_bounds_check(mut self) -> Self {
+        self.bounds_check = false;
+        self
+    }
+
     pub(crate) fn generate_ir(&mut self, ast: &mut AST) -> Result<LinearIR, RccError> {
         self.visit_file(&mut ast.file)?;
         let mut output = LinearIR::new();
@@ -51,10 +112,60 @@ impl IRBuilder {
         Ok(output)
     }
 
+    fn ir_type(&self, type_info: &TypeInfo) -> Result<IRType, RccError> {
+        ir_type_of(type_info, self.scope_stack.cur_scope(), self.addr_size)
+    }
+
+    fn struct_layout(&self, type_info: &TypeInfo) -> Result<StructLayout, RccError> {
+        match type_info {
+            TypeInfo::Struct { fields, .. } => StructLayout::new(
+                unsafe { fields.as_ref() },
+                self.scope_stack.cur_scope(),
+                self.addr_size,
+            ),
+            TypeInfo::Tuple(types) => {
+                StructLayout::tuple(types, self.scope_stack.cur_scope(), self.addr_size)
+            }
+            t => Err(format!("expected struct, found `{:?}`", t).into()),
+        }
+    }
+
+    fn enum_layout(&self, type_enum: &TypeEnum) -> Result<EnumLayout, RccError> {
+        EnumLayout::new(type_enum, self.scope_stack.cur_scope(), self.addr_size)
+    }
+
+    /// The enum and the index of the variant if `path` is an enum variant.
+    fn find_variant(&self, path: &str) -> Option<(NonNull<TypeEnum>, usize)> {
+        if !is_variant_path(path) {
+            return None;
+        }
+        let (enum_name, variant) = path.split_once("::")?;
+        match self.scope_stack.cur_scope().find_def_except_fn(enum_name) {
+            TypeInfo::Enum(type_enum) => {
+                let (index, _) = unsafe { type_enum.as_ref() }.variant(variant)?;
+                Some((type_enum, index))
+            }
+            _ => None,
+        }
+    }
+
+    /// Layout of the fields of the struct or enum variant `path`.
+    /// Layout of the variant `path`, or the struct `type_info` matched by the path,
+    /// which may be an instance of the generic struct `path`.
+    fn fields_layout(&self, path: &str, type_info: &TypeInfo) -> Result<StructLayout, RccError> {
+        match self.find_variant(path) {
+            Some((type_enum, index)) => {
+                let mut layout = self.enum_layout(unsafe { type_enum.as_ref() })?;
+                Ok(layout.variants.swap_remove(index))
+            }
+            None => self.struct_layout(type_info),
+        }
+    }
+
     fn gen_temp_var(&mut self, type_info: Rc<RefCell<TypeInfo>>) -> Place {
         let t = type_info.borrow();
         let tp = t.deref();
-        let ir_type = IRType::from_type_info(tp).unwrap();
+        let ir_type = self.ir_type(tp).unwrap();
         std::mem::drop(t);
         let label = self
             .scope_stack
@@ -65,7 +176,7 @@ impl IRBuilder {
 
     fn gen_variable(&mut self, ident: &str, var_kind: VarKind) -> Place {
         let res = self.scope_stack.cur_scope().find_variable(ident).unwrap();
-        let ir_type = IRType::from_var_info(res.0).unwrap();
+        let ir_type = self.ir_type(&res.0.type_info.borrow()).unwrap();
         Place::variable(ident, res.1, var_kind, ir_type)
     }
 
@@ -74,46 +185,150 @@ impl IRBuilder {
         for item in file.items.iter_mut() {
             self.visit_item(item)?;
         }
+        for instance in file.instances.iter_mut() {
+            self.visit_item_fn(instance)?;
+        }
         Ok(())
     }
 
     fn visit_item(&mut self, item: &mut Item) -> Result<(), RccError> {
         match item {
+            // only the instances of generic functions and impls are generated
+            Item::Fn(item_fn) if item_fn.is_generic() => Ok(()),
+            Item::Impl(item_impl) if item_impl.is_generic() => Ok(()),
             Item::Fn(item_fn) => self.visit_item_fn(item_fn),
+            Item::Trait(_) => Ok(()),
             Item::Struct(item_struct) => self.visit_item_struct(item_struct),
-            Item::ExternalBlock(item_block) => {
-                // do nothing
+            // enums are laid out where they are used
+            Item::Enum(_) => Ok(()),
+            Item::ExternalBlock(item_block) => self.ir_output.add_extern_block(
+                item_block,
+                self.scope_stack.cur_scope(),
+                self.addr_size,
+            ),
+            Item::Impl(item_impl) => {
+                for item_fn in item_impl.items.iter_mut() {
+                    self.visit_item_fn(item_fn)?;
+                }
                 Ok(())
             }
+            // constants are inlined where they are used
+            Item::Const(_) => Ok(()),
+            Item::Static(item_static) => self.visit_item_static(item_static),
             _ => unimplemented!(),
         }
     }
 
-    fn visit_item_fn(&mut self, item_fn: &mut ItemFn) -> Result<(), RccError> {
-        self.ir_output.add_func(item_fn)?;
+    fn visit_item_static(&mut self, item_static: &ItemStatic) -> Result<(), RccError> {
+        let cur_scope = self.scope_stack.cur_scope();
+        let (var, scope_id) = cur_scope.find_variable(&item_static.name).unwrap();
+        let mut elem_type = var.type_info.borrow().clone();
+        while let TypeInfo::Array { elem_type: t, .. } = elem_type {
+            elem_type = *t;
+        }
+        let static_data = StaticData {
+            label: local_var(&item_static.name, scope_id),
+            elem_type: self.ir_type(&elem_type)?,
+            values: const_eval::eval_static_init(&item_static.expr, cur_scope)?,
+            is_mut: item_static.is_mut,
+        };
+        self.ir_output.statics.push(static_data);
+        Ok(())
+    }
+
+    /// The symbol of the static `path_expr`, `None` if it is not a static.
+    fn static_symbol(&self, path_expr: &PathExpr) -> Option<Place> {
+        let ident = &path_expr.segments.join("::");
+        let (var, scope_id) = self.scope_stack.cur_scope().find_variable(ident)?;
+        match var.kind() {
+            VarKind::Static | VarKind::StaticMut => {
+                let ir_type = self.ir_type(&var.type_info.borrow()).ok()?;
+                Some(Place::variable(ident, scope_id, var.kind(), ir_type))
+            }
+            _ => None,
+        }
+    }
+
+    /// The address of the static `path_expr` in a new temporary.
+    fn static_addr(&mut self, path_expr: &PathExpr) -> Operand {
+        let symbol = self.static_symbol(path_expr).unwrap();
+        self.gen_addr_of(Operand::Place(symbol), path_expr.type_info())
+    }
 
+    fn visit_item_fn(&mut self, item_fn: &mut ItemFn) -> Result<(), RccError> {
         let info = self.scope_stack.cur_scope().find_fn(&item_fn.name);
         assert_eq!(info, TypeInfo::from_fn_signature(item_fn));
 
         let ret_info = TypeInfo::from_type_anno(&item_fn.ret_type, self.scope_stack.cur_scope());
+        let ret_type = self.ir_type(&ret_info)?;
+        self.ir_output.add_func(item_fn, ret_type, self.addr_size)?;
         // visit function block
         let dest = self.gen_temp_var(Rc::new(RefCell::new(ret_info)));
         self.fn_ret_temp_var.push(dest.clone());
 
+        // copy the structs passed by addresses, and bind the variables
+        // in the patterns of params which are not identifiers
+        self.scope_stack.enter(&mut item_fn.fn_block.scope);
+        let scope_id = item_fn.fn_block.scope.scope_id;
+        let first_param = self.ir_output.cur_func_mut().fn_args.len() - item_fn.fn_params.params.len();
+        for (i, param) in item_fn.fn_params.params.iter().enumerate() {
+            let (name, ir_type) = self.ir_output.cur_func_mut().fn_args[first_param + i].clone();
+            let arg = Operand::Place(Place::local(local_var(&name, scope_id), ir_type));
+            let type_info = TypeInfo::from_type_anno(&param._type, self.scope_stack.cur_scope());
+            let is_aggregate = self.ir_type(&type_info)?.is_aggregate();
+            match &param.pattern {
+                Pattern::Identifier(p) if !p.is_ref() && is_aggregate => {
+                    let var_kind = if p.is_mut() {
+                        VarKind::LocalMut
+                    } else {
+                        VarKind::Local
+                    };
+                    let var = self.gen_variable(p.ident(), var_kind);
+                    self.ir_output
+                        .add_instructions(IRInst::load_mem(var, arg, 0));
+                }
+                Pattern::Identifier(p) if !p.is_ref() => {}
+                pattern if is_aggregate => {
+                    let temp = self.gen_temp_var(Rc::new(RefCell::new(type_info.clone())));
+                    self.ir_output
+                        .add_instructions(IRInst::load_mem(temp.clone(), arg, 0));
+                    let src = Matched::Value(Operand::Place(temp));
+                    self.visit_pattern(pattern, src, &type_info, false)?;
+                }
+                pattern => self.visit_pattern(pattern, Matched::Value(arg), &type_info, false)?,
+            }
+        }
+        self.scope_stack.exit_scope();
         let operand = self.visit_block_expr(&mut item_fn.fn_block, Some(dest), false)?;
 
         if item_fn.fn_block.last_expr.is_none() && item_fn.fn_block.stmts.is_empty() {
             self.ir_output.add_instructions(IRInst::Ret(Operand::Unit));
         } else if !item_fn.fn_block.last_stmt_is_return() {
-            self.ir_output.add_instructions(IRInst::Ret(operand));
+            self.gen_ret(operand);
         }
 
         self.fn_ret_temp_var.pop();
         Ok(())
     }
 
+    /// Structs are laid out when they are used.
     fn visit_item_struct(&mut self, item_struct: &mut ItemStruct) -> Result<(), RccError> {
-        unimplemented!()
+        Ok(())
+    }
+
+    /// Return `operand`, structs are stored to the address of the hidden argument.
+    fn gen_ret(&mut self, operand: Operand) {
+        match operand {
+            Operand::Place(p) if p.ir_type.is_aggregate() => {
+                let func = self.ir_output.cur_func_mut();
+                let (name, ir_type) = func.fn_args[0].clone();
+                let ret = Place::local(local_var(&name, func.block_scope_id), ir_type);
+                self.ir_output
+                    .add_instructions(IRInst::store_mem(Operand::Place(ret), 0, Operand::Place(p)));
+                self.ir_output.add_instructions(IRInst::Ret(Operand::Unit));
+            }
+            operand => self.ir_output.add_instructions(IRInst::Ret(operand)),
+        }
     }
 
     fn visit_stmt(&mut self, stmt: &mut Stmt) -> Result<(), RccError> {
@@ -133,7 +348,7 @@ impl IRBuilder {
         let is_mut = let_stmt.is_mut();
         if let Some(rhs) = &mut let_stmt.rhs {
             match &let_stmt.pattern {
-                Pattern::Identifier(ident_pattern) => {
+                Pattern::Identifier(ident_pattern) if !ident_pattern.is_ref() => {
                     let ident = ident_pattern.ident();
                     let dest = self.gen_variable(
                         ident,
@@ -145,6 +360,17 @@ impl IRBuilder {
                     );
                     let rhs = self.visit_expr(rhs, Some(dest), false)?;
                 }
+                pattern => {
+                    let type_info = rhs.type_info();
+                    let ir_type = self.ir_type(&type_info.borrow())?;
+                    let src = if matches!(ir_type, IRType::Unit | IRType::Never) {
+                        self.visit_expr(rhs, None, false)?
+                    } else {
+                        let temp = self.gen_temp_var(type_info.clone());
+                        self.visit_expr(rhs, Some(temp), true)?
+                    };
+                    self.visit_pattern(pattern, Matched::Value(src), &type_info.borrow(), false)?;
+                }
             }
         }
         Ok(())
@@ -168,18 +394,34 @@ impl IRBuilder {
             // Expr::Range(range_expr) => self.visit_range_expr(range_expr),
             Expr::BinOp(bin_op_expr) => self.visit_bin_op_expr(bin_op_expr, dest),
             Expr::Grouped(grouped_expr) => self.visit_grouped_expr(grouped_expr, dest, remain_temp),
-            // Expr::Array(array_expr) => self.visit_array_expr(array_expr),
-            // Expr::ArrayIndex(array_index_expr) => self.visit_array_index_expr(array_index_expr),
-            // Expr::Tuple(tuple_expr) => self.visit_tuple_expr(tuple_expr),
-            // Expr::TupleIndex(tuple_index_expr) => self.visit_tuple_index_expr(tuple_index_expr),
-            // Expr::Struct(struct_expr) => self.visit_struct_expr(struct_expr),
+            Expr::Array(array_expr) => self.visit_array_expr(array_expr, dest),
+            Expr::ArrayIndex(array_index_expr) => {
+                self.visit_array_index_expr(array_index_expr, dest)
+            }
+            Expr::Tuple(tuple_expr) => self.visit_tuple_expr(tuple_expr, dest),
+            Expr::TupleIndex(tuple_index_expr) => {
+                self.visit_tuple_index_expr(tuple_index_expr, dest)
+            }
+            Expr::Struct(struct_expr) => self.visit_struct_expr(struct_expr, dest),
+            Expr::EnumVariant(variant_expr) => {
+                self.visit_enum_variant_expr(variant_expr, dest, remain_temp)
+            }
+            Expr::Cast(cast_expr) => self.visit_cast_expr(cast_expr, dest, remain_temp),
             Expr::Call(call_expr) => self.visit_call_expr(call_expr, dest),
-            // Expr::FieldAccess(field_access_expr) => self.visit_field_access_expr(field_access_expr),
+            Expr::MethodCall(method_call_expr) => {
+                self.visit_method_call_expr(method_call_expr, dest)
+            }
+            Expr::FieldAccess(field_access_expr) => {
+                self.visit_field_access_expr(field_access_expr, dest)
+            }
             Expr::While(while_expr) => self.visit_while_expr(while_expr),
             Expr::Loop(loop_expr) => self.visit_loop_expr(loop_expr, dest),
+            Expr::For(for_expr) => self.visit_for_expr(for_expr),
             Expr::If(if_expr) => self.visit_if_expr(if_expr, dest),
+            Expr::Match(match_expr) => self.visit_match_expr(match_expr, dest),
             Expr::Return(return_expr) => self.visit_return_expr(return_expr, dest),
             Expr::Break(break_expr) => self.visit_break_expr(break_expr, dest),
+            Expr::Continue(continue_expr) => self.visit_continue_expr(continue_expr, dest),
             _ => unimplemented!(),
         };
         debug_assert_ne!(
@@ -208,15 +450,162 @@ impl IRBuilder {
         self.visit_expr(grouped_expr, dest, remain_temp)
     }
 
-    fn visit_pattern(&mut self, pattern: &mut Pattern) -> Result<Operand, RccError> {
-        unimplemented!()
+    /// Bind the variables in `pattern` to the matched value `src` of type `type_info`,
+    /// by reference if `by_ref`. The pattern has been tested if it is refutable.
+    fn visit_pattern(
+        &mut self,
+        pattern: &Pattern,
+        src: Matched,
+        type_info: &TypeInfo,
+        by_ref: bool,
+    ) -> Result<(), RccError> {
+        match pattern {
+            Pattern::Identifier(ident_pattern) => {
+                let var_kind = if ident_pattern.is_mut() {
+                    VarKind::LocalMut
+                } else {
+                    VarKind::Local
+                };
+                let var = self.gen_variable(ident_pattern.ident(), var_kind);
+                if ident_pattern.is_ref() || by_ref {
+                    return self.gen_matched_addr(var, src, type_info);
+                }
+                let value = self.gen_load_matched(&src, type_info)?;
+                if !value.is_unit_or_never() {
+                    self.ir_output
+                        .add_instructions(IRInst::load_data(var, value));
+                }
+                Ok(())
+            }
+            // literal, range and alternative patterns bind no variables
+            Pattern::Wildcard | Pattern::Literal(_) | Pattern::Range(_) | Pattern::Or(_) => Ok(()),
+            // unit variants
+            Pattern::Path(_) => Ok(()),
+            Pattern::Ref(ref_pattern) => {
+                let (value, value_type) = self.deref_matched(&src, type_info)?;
+                self.visit_pattern(&ref_pattern.pattern, value, &value_type, false)
+            }
+            // default binding mode: the referenced value is matched and bound by reference
+            _ if matches!(type_info, TypeInfo::Ptr { .. }) => {
+                let (value, value_type) = self.deref_matched(&src, type_info)?;
+                self.visit_pattern(pattern, value, &value_type, true)
+            }
+            // `()` and `(..)` of unit
+            Pattern::Tuple(patterns) if patterns.iter().all(|p| p == &Pattern::Rest) => Ok(()),
+            Pattern::Tuple(patterns) => {
+                let layout = self.struct_layout(type_info)?;
+                for (elem, pattern, elem_type) in
+                    matched_elems(patterns, &src, &layout, is_wildcard)
+                {
+                    self.visit_pattern(pattern, elem, &elem_type, by_ref)?;
+                }
+                Ok(())
+            }
+            Pattern::TupleStruct(tuple_struct_pattern) => {
+                let layout = self.fields_layout(&tuple_struct_pattern.path, type_info)?;
+                let patterns = &tuple_struct_pattern.patterns;
+                for (elem, pattern, elem_type) in
+                    matched_elems(patterns, &src, &layout, is_wildcard)
+                {
+                    self.visit_pattern(pattern, elem, &elem_type, by_ref)?;
+                }
+                Ok(())
+            }
+            Pattern::Struct(struct_pattern) => {
+                for (field, pattern, field_type) in
+                    self.matched_fields(struct_pattern, &src, type_info, is_wildcard)?
+                {
+                    self.visit_pattern(pattern, field, &field_type, by_ref)?;
+                }
+                Ok(())
+            }
+            p => Err(format!("unsupported pattern yet: {:?}", p).into()),
+        }
+    }
+
+    /// The fields of the struct or enum variant `src` of type `type_info`
+    /// except those whose patterns are skipped by `skip`,
+    /// with their patterns and types.
+    fn matched_fields<'p>(
+        &mut self,
+        struct_pattern: &'p StructPattern,
+        src: &Matched,
+        type_info: &TypeInfo,
+        skip: fn(&Pattern) -> bool,
+    ) -> Result<Vec<(Matched, &'p Pattern, TypeInfo)>, RccError> {
+        let layout = self.fields_layout(&struct_pattern.path, type_info)?;
+        let mut fields = vec![];
+        for field_pattern in struct_pattern.fields.iter() {
+            if skip(&field_pattern.pattern) {
+                continue;
+            }
+            let field = layout.field(&field_pattern.name).unwrap();
+            fields.push((
+                src.field(field.offset),
+                &field_pattern.pattern,
+                field.type_info.clone(),
+            ));
+        }
+        Ok(fields)
     }
 
-    fn visit_ident_pattern(
+    /// The matched value `src` of type `type_info`,
+    /// the value in memory is loaded to a new temporary.
+    fn gen_load_matched(
         &mut self,
-        ident_pattern: &mut IdentPattern,
+        src: &Matched,
+        type_info: &TypeInfo,
     ) -> Result<Operand, RccError> {
-        unimplemented!()
+        match src {
+            Matched::Value(operand) => Ok(operand.clone()),
+            Matched::Mem(base, offset) => {
+                let type_info = Rc::new(RefCell::new(type_info.clone()));
+                let temp = self.gen_temp_var(type_info.clone());
+                self.gen_load_mem(base.clone(), *offset, type_info, Some(temp))
+            }
+        }
+    }
+
+    /// dest = &src, `type_info` is the type of the matched value `src`.
+    /// Immediates are borrowed as temporaries.
+    fn gen_matched_addr(
+        &mut self,
+        dest: Place,
+        src: Matched,
+        type_info: &TypeInfo,
+    ) -> Result<(), RccError> {
+        let place = match src {
+            Matched::Mem(base, offset) => return self.gen_mem_addr(dest, base, offset),
+            Matched::Value(Operand::Place(p)) if p.ir_type.is_aggregate() => {
+                return self.gen_mem_addr(dest, Operand::Place(p), 0);
+            }
+            Matched::Value(Operand::Place(p)) => p,
+            Matched::Value(operand) => {
+                let temp = self.gen_temp_var(Rc::new(RefCell::new(type_info.clone())));
+                if !operand.is_unit_or_never() {
+                    self.ir_output
+                        .add_instructions(IRInst::load_data(temp.clone(), operand));
+                }
+                temp
+            }
+        };
+        self.ir_output
+            .add_instructions(IRInst::load_addr(dest, Operand::Place(place)));
+        Ok(())
+    }
+
+    /// The value referenced by the matched pointer `src` of type `type_info`, and its type.
+    fn deref_matched(
+        &mut self,
+        src: &Matched,
+        type_info: &TypeInfo,
+    ) -> Result<(Matched, TypeInfo), RccError> {
+        let value_type = match type_info {
+            TypeInfo::Ptr { type_info, .. } => type_info.as_ref().clone(),
+            t => return Err(format!("expected reference, found `{:?}`", t).into()),
+        };
+        let ptr = self.gen_load_matched(src, type_info)?;
+        Ok((Matched::Mem(ptr, 0), value_type))
     }
 
     fn visit_path_expr(
@@ -225,13 +614,26 @@ impl IRBuilder {
         dest: Option<Place>,
         remain_temp: bool,
     ) -> Result<Operand, RccError> {
-        // TODO path segmentation
-        let ident = path_expr.segments.last().unwrap();
+        // associated functions are named by their paths
+        let ident = &path_expr.segments.join("::");
 
+        // statics are loaded from the memory at their addresses
+        if self.static_symbol(path_expr).is_some() {
+            let addr = self.static_addr(path_expr);
+            return self.gen_load_mem(addr, 0, path_expr.type_info(), dest);
+        }
         let cur_scope = self.scope_stack.cur_scope();
         if let Some((var, scope_id)) = cur_scope.find_variable(ident) {
-            let ir_type = IRType::from_var_info(var)?;
-            let operand = Operand::Place(Place::variable(ident, scope_id, var.kind(), ir_type));
+            let ir_type = self.ir_type(&var.type_info.borrow())?;
+            // variables of `()` are not stored
+            if ir_type == IRType::Unit {
+                return Ok(Operand::Unit);
+            }
+            let operand = match var.const_value() {
+                // constants are inlined
+                Some(value) => value.clone(),
+                None => Operand::Place(Place::variable(ident, scope_id, var.kind(), ir_type)),
+            };
             if let Some(d) = dest {
                 if !d.is_temp() || remain_temp {
                     self.ir_output
@@ -240,7 +642,7 @@ impl IRBuilder {
             }
             Ok(operand)
         } else if !cur_scope.find_fn(ident).is_unknown() {
-            Ok(Operand::FnLabel(ident.clone()))
+            Ok(Operand::FnLabel(ident.to_string()))
         } else {
             Err("error in visit path expr: ident not found".into())
         }
@@ -270,23 +672,7 @@ impl IRBuilder {
         dest: Option<Place>,
         remain_temp: bool,
     ) -> Result<Operand, RccError> {
-        let t = lit_num_expr.get_lit_type();
-        let operand = match t {
-            TypeLitNum::I8 => Operand::I8(lit_num_expr.value.parse()?),
-            TypeLitNum::I16 => Operand::I16(lit_num_expr.value.parse()?),
-            TypeLitNum::I | TypeLitNum::I32 => Operand::I32(lit_num_expr.value.parse()?),
-            TypeLitNum::I64 => Operand::I64(lit_num_expr.value.parse()?),
-            TypeLitNum::I128 => Operand::I128(lit_num_expr.value.parse()?),
-            TypeLitNum::Isize => Operand::Isize(lit_num_expr.value.parse()?),
-            TypeLitNum::U8 => Operand::U8(lit_num_expr.value.parse()?),
-            TypeLitNum::U16 => Operand::U16(lit_num_expr.value.parse()?),
-            TypeLitNum::U32 => Operand::U32(lit_num_expr.value.parse()?),
-            TypeLitNum::U64 => Operand::U64(lit_num_expr.value.parse()?),
-            TypeLitNum::U128 => Operand::U128(lit_num_expr.value.parse()?),
-            TypeLitNum::Usize => Operand::Usize(lit_num_expr.value.parse()?),
-            TypeLitNum::F32 => Operand::F32(lit_num_expr.value.parse()?),
-            TypeLitNum::F | TypeLitNum::F64 => Operand::F64(lit_num_expr.value.parse()?),
-        };
+        let operand = ir::lit_num_operand(lit_num_expr)?;
         self.lit(operand, dest, remain_temp)
     }
 
@@ -330,17 +716,158 @@ impl IRBuilder {
                 let temp_dest = self.gen_temp_var(unary_expr.expr.type_info());
                 let operand = self.visit_expr(&mut unary_expr.expr, Some(temp_dest), false)?;
                 let operand = match operand {
-                    Operand::I8(i) => Operand::I8(-i),
-                    Operand::I16(i) => Operand::I16(-i),
-                    Operand::I32(i) => Operand::I32(-i),
-                    _ => todo!(),
+                    Operand::I8(i) => Operand::I8(i.wrapping_neg()),
+                    Operand::I16(i) => Operand::I16(i.wrapping_neg()),
+                    Operand::I32(i) => Operand::I32(i.wrapping_neg()),
+                    Operand::I64(i) => Operand::I64(i.wrapping_neg()),
+                    Operand::I128(i) => Operand::I128(i.wrapping_neg()),
+                    Operand::Isize(i) => Operand::Isize(i.wrapping_neg()),
+                    Operand::F32(f) => Operand::F32(-f),
+                    Operand::F64(f) => Operand::F64(-f),
+                    Operand::Place(p) => {
+                        let d = match dest {
+                            Some(d) => d,
+                            None => return Ok(Operand::Unit),
+                        };
+                        // -a = 0 - a, wrapping like the other arithmetic operators
+                        let zero = match p.ir_type {
+                            IRType::I8 => Operand::I8(0),
+                            IRType::I16 => Operand::I16(0),
+                            IRType::I32 => Operand::I32(0),
+                            IRType::I64 => Operand::I64(0),
+                            IRType::I128 => Operand::I128(0),
+                            IRType::Isize => Operand::Isize(0),
+                            IRType::F32 | IRType::F64 => {
+                                self.ir_output
+                                    .add_instructions(IRInst::neg(d.clone(), Operand::Place(p)));
+                                return Ok(Operand::Place(d));
+                            }
+                            t => {
+                                return Err(
+                                    format!("cannot apply unary operator `-` to `{:?}`", t).into()
+                                )
+                            }
+                        };
+                        return self.bin_op(zero, Operand::Place(p), BinOperator::Minus, d);
+                    }
+                    o => {
+                        return Err(format!("cannot apply unary operator `-` to `{:?}`", o).into())
+                    }
+                };
+                self.lit(operand, dest, remain_temp)
+            }
+            UnOp::Not => {
+                let temp_dest = self.gen_temp_var(unary_expr.expr.type_info());
+                let operand = self.visit_expr(&mut unary_expr.expr, Some(temp_dest), false)?;
+                let operand = match operand {
+                    Operand::Bool(b) => Operand::Bool(!b),
+                    Operand::I8(i) => Operand::I8(!i),
+                    Operand::I16(i) => Operand::I16(!i),
+                    Operand::I32(i) => Operand::I32(!i),
+                    Operand::I64(i) => Operand::I64(!i),
+                    Operand::U8(i) => Operand::U8(!i),
+                    Operand::U16(i) => Operand::U16(!i),
+                    Operand::U32(i) => Operand::U32(!i),
+                    Operand::U64(i) => Operand::U64(!i),
+                    Operand::Place(p) => {
+                        // !a = a ^ 0b11...1
+                        let all_ones = match p.ir_type {
+                            IRType::Bool => Operand::Bool(true),
+                            IRType::I8 => Operand::I8(-1),
+                            IRType::I16 => Operand::I16(-1),
+                            IRType::I32 => Operand::I32(-1),
+                            IRType::I64 => Operand::I64(-1),
+                            IRType::U8 => Operand::U8(u8::MAX),
+                            IRType::U16 => Operand::U16(u16::MAX),
+                            IRType::U32 => Operand::U32(u32::MAX),
+                            IRType::U64 => Operand::U64(u64::MAX),
+                            t => {
+                                return Err(
+                                    format!("cannot apply unary operator `!` to `{:?}`", t).into()
+                                )
+                            }
+                        };
+                        return match dest {
+                            Some(d) => {
+                                self.bin_op(Operand::Place(p), all_ones, BinOperator::Caret, d)
+                            }
+                            None => Ok(Operand::Unit),
+                        };
+                    }
+                    o => {
+                        return Err(format!("cannot apply unary operator `!` to `{:?}`", o).into())
+                    }
                 };
                 self.lit(operand, dest, remain_temp)
             }
-            _ => todo!(),
+            UnOp::Borrow | UnOp::BorrowMut => {
+                let dest = match dest {
+                    Some(d) => d,
+                    None => self.gen_temp_var(unary_expr.type_info()),
+                };
+                self.visit_borrow(&mut unary_expr.expr, dest)
+            }
+            UnOp::Deref => {
+                let (base, offset) = self.visit_deref_place(unary_expr)?;
+                self.gen_load_mem(base, offset, unary_expr.type_info(), dest)
+            }
         }
     }
 
+    /// dest = &expr, values are borrowed as temporaries.
+    fn visit_borrow(&mut self, expr: &mut Expr, dest: Place) -> Result<Operand, RccError> {
+        let (base, offset) = match expr {
+            Expr::Grouped(expr) => return self.visit_borrow(expr, dest),
+            Expr::FieldAccess(field_access_expr) => self.visit_field_place(field_access_expr)?,
+            Expr::ArrayIndex(array_index_expr) => self.visit_index_place(array_index_expr)?,
+            Expr::TupleIndex(tuple_index_expr) => self.visit_tuple_index_place(tuple_index_expr)?,
+            // `&*p` is the value of `p`
+            Expr::Unary(unary_expr) if unary_expr.op == UnOp::Deref => {
+                self.visit_deref_place(unary_expr)?
+            }
+            Expr::Path(path_expr) if self.static_symbol(path_expr).is_some() => {
+                let symbol = Operand::Place(self.static_symbol(path_expr).unwrap());
+                self.ir_output
+                    .add_instructions(IRInst::load_addr(dest.clone(), symbol));
+                return Ok(Operand::Place(dest));
+            }
+            expr => {
+                let place = match expr {
+                    // constants are borrowed as temporaries
+                    Expr::Path(path_expr) if path_expr.expr_kind != ExprKind::Value => {
+                        self.visit_path_expr(path_expr, None, false)?
+                    }
+                    expr => {
+                        let temp = self.gen_temp_var(expr.type_info());
+                        self.visit_expr(expr, Some(temp.clone()), true)?;
+                        Operand::Place(temp)
+                    }
+                };
+                // zero-sized values have no memory, any aligned address is valid
+                let zero_sized = match &place {
+                    Operand::Place(p) => matches!(p.ir_type, IRType::Unit | IRType::Never),
+                    operand => operand.is_unit_or_never(),
+                };
+                if zero_sized {
+                    self.ir_output
+                        .add_instructions(IRInst::load_data(dest.clone(), Operand::Usize(1)));
+                    return Ok(Operand::Place(dest));
+                }
+                // the address of a variable or a temporary
+                if let Operand::Place(p) = &place {
+                    if !p.ir_type.is_aggregate() {
+                        self.ir_output
+                            .add_instructions(IRInst::load_addr(dest.clone(), place));
+                        return Ok(Operand::Place(dest));
+                    }
+                }
+                (place, 0)
+            }
+        };
+        self.gen_mem_addr(dest.clone(), base, offset)?;
+        Ok(Operand::Place(dest))
+    }
+
     fn visit_block_expr(
         &mut self,
         block_expr: &mut BlockExpr,
@@ -348,11 +875,17 @@ impl IRBuilder {
         remain_temp: bool,
     ) -> Result<Operand, RccError> {
         self.scope_stack.enter_scope(block_expr);
-        for stmt in block_expr.stmts.iter_mut() {
+        for (i, stmt) in block_expr.stmts.iter_mut().enumerate() {
+            if let Some(line) = block_expr.lines.get(i) {
+                self.ir_output.set_line(*line);
+            }
             self.visit_stmt(stmt)?;
         }
 
         let result = Ok(if let Some(expr) = &mut block_expr.last_expr {
+            if let Some(line) = block_expr.lines.get(block_expr.stmts.len()) {
+                self.ir_output.set_line(*line);
+            }
             let is_none = dest.is_none();
             let res = self.visit_expr(&mut *expr, dest, remain_temp)?;
             if is_none && !res.is_unit_or_never() {
@@ -371,44 +904,93 @@ impl IRBuilder {
     }
 
     fn visit_assign_expr(&mut self, assign_expr: &mut AssignExpr) -> Result<Operand, RccError> {
+        // fields and elems are stored to the memory of structs, tuples and arrays,
+        // `*p = v` is stored to the memory at the address `p`, and `S = v` at the address of `S`
+        let is_static = matches!(
+            &assign_expr.lhs,
+            LhsExpr::Path(lhs) if self.static_symbol(lhs).is_some()
+        );
+        if is_static
+            || matches!(
+                assign_expr.lhs,
+                LhsExpr::FieldAccess(_)
+                    | LhsExpr::ArrayIndex(_)
+                    | LhsExpr::TupleIndex(_)
+                    | LhsExpr::Deref(_)
+            )
+        {
+            let type_info = assign_expr.lhs.type_info();
+            let rhs_dest = self.gen_temp_var(type_info.clone());
+            let rhs = self.visit_expr(&mut assign_expr.rhs, Some(rhs_dest), false)?;
+            let (base, offset) = match &mut assign_expr.lhs {
+                LhsExpr::FieldAccess(lhs) => self.visit_field_place(lhs)?,
+                LhsExpr::ArrayIndex(lhs) => self.visit_index_place(lhs)?,
+                LhsExpr::TupleIndex(lhs) => self.visit_tuple_index_place(lhs)?,
+                LhsExpr::Deref(lhs) => self.visit_deref_place(lhs)?,
+                LhsExpr::Path(lhs) => (self.static_addr(lhs), 0),
+            };
+            let src = match assign_op_to_bin_op(&assign_expr.assign_op) {
+                Some(op) => {
+                    let temp = self.gen_temp_var(type_info);
+                    self.ir_output
+                        .add_instructions(IRInst::load_mem(temp.clone(), base.clone(), offset));
+                    self.bin_op(Operand::Place(temp.clone()), rhs, op, temp)?
+                }
+                None => rhs,
+            };
+            if !src.is_unit_or_never() {
+                self.ir_output
+                    .add_instructions(IRInst::store_mem(base, offset, src));
+            }
+            return Ok(Operand::Unit);
+        }
+
         let operand = self.visit_lhs_expr(&mut assign_expr.lhs)?;
         let p = match operand {
             Operand::Place(p) => p,
             _ => unimplemented!(),
         };
 
-        macro_rules! add_inst {
-            ($bin_op:path) => {{
+        match assign_op_to_bin_op(&assign_expr.assign_op) {
+            Some(op) => {
                 let rhs_dest = self.gen_temp_var(assign_expr.lhs.type_info());
                 let rhs = self.visit_expr(&mut assign_expr.rhs, Some(rhs_dest), false)?;
                 self.ir_output.add_instructions(IRInst::bin_op(
-                    $bin_op,
+                    op,
                     p.clone(),
                     Operand::Place(p),
-                    rhs.clone(),
+                    rhs,
                 ))
-            }};
-        }
-        match assign_expr.assign_op {
-            AssignOp::Eq => {
-                let rhs = self.visit_expr(&mut assign_expr.rhs, Some(p.clone()), false)?;
-            }
-            AssignOp::ShrEq => add_inst!(BinOperator::Shr),
-            AssignOp::ShlEq => add_inst!(BinOperator::Shl),
-            AssignOp::PlusEq => add_inst!(BinOperator::Plus),
-            AssignOp::MinusEq => add_inst!(BinOperator::Minus),
-            AssignOp::StarEq => add_inst!(BinOperator::Star),
-            AssignOp::SlashEq => add_inst!(BinOperator::Slash),
-            AssignOp::PercentEq => add_inst!(BinOperator::Percent),
-            AssignOp::AndEq => add_inst!(BinOperator::And),
-            AssignOp::OrEq => add_inst!(BinOperator::Or),
-            AssignOp::CaretEq => add_inst!(BinOperator::Caret),
+            }
+            None => {
+                self.visit_expr(&mut assign_expr.rhs, Some(p), false)?;
+            }
         }
         Ok(Operand::Unit)
     }
 
-    fn visit_range_expr(&mut self, range_expr: &mut RangeExpr) -> Result<Operand, RccError> {
-        unimplemented!()
+    /// Evaluate the bounds of the range once, return (counter = start, end).
+    fn visit_range_expr(
+        &mut self,
+        range_expr: &mut RangeExpr,
+    ) -> Result<(Place, Operand), RccError> {
+        let (lhs, rhs) = match (range_expr.lhs.as_mut(), range_expr.rhs.as_mut()) {
+            (Some(lhs), Some(rhs)) => (lhs, rhs),
+            _ => return Err("unsupported range: expected both bounds".into()),
+        };
+        let counter = self.gen_temp_var(lhs.type_info());
+        self.visit_expr(lhs, Some(counter.clone()), true)?;
+        let end = self.gen_temp_var(rhs.type_info());
+        let end = match self.visit_expr(rhs, Some(end.clone()), false)? {
+            // the variable may be changed in the loop
+            Operand::Place(p) if !p.is_temp() => {
+                self.ir_output
+                    .add_instructions(IRInst::load_data(end.clone(), Operand::Place(p)));
+                Operand::Place(end)
+            }
+            operand => operand,
+        };
+        Ok((counter, end))
     }
 
     fn bin_op(
@@ -428,6 +1010,19 @@ impl IRBuilder {
         bin_op_expr: &mut BinOpExpr,
         dest: Option<Place>,
     ) -> Result<Operand, RccError> {
+        if matches!(bin_op_expr.bin_op, BinOperator::AndAnd | BinOperator::OrOr) {
+            return match dest {
+                Some(d) => self.visit_logic_bin_expr(bin_op_expr, d),
+                None => {
+                    let mut true_link = 0;
+                    let mut false_link = 0;
+                    self.gen_logic_cond(bin_op_expr, &mut true_link, &mut false_link, true)?;
+                    self.back_patch(true_link);
+                    self.back_patch(false_link);
+                    Ok(Operand::Unit)
+                }
+            };
+        }
         let d = self.gen_temp_var(bin_op_expr.lhs.type_info());
         let lhs = self.visit_expr(&mut bin_op_expr.lhs, Some(d), false)?;
         let d = self.gen_temp_var(bin_op_expr.rhs.type_info());
@@ -492,94 +1087,632 @@ impl IRBuilder {
             bin_op_expr.bin_op,
             BinOperator::AndAnd | BinOperator::OrOr
         ));
-        // let lhs = self.visit_expr(&mut bin_op_expr.lhs, dest)?;
-        // let if_inst = if bin_op_expr.bin_op == BinOperator::AndAnd {
-        //     IRInst::jump_if_not(lhs)
-        // } else {
-        //     IRInst::jump_if(lhs)
-        // };
-        // let if_idx = self.ir_output.instructions.len();
-        // self.ir_output.add_instructions(if_inst);
-        // let rhs = self.visit_expr(
-        //     &mut bin_op_expr.rhs,
-        //     dest.clone(),
-        // );
-        todo!()
+        // `a = b || a` reads `a` after the lhs is evaluated
+        let res = if dest.is_temp() {
+            dest.clone()
+        } else {
+            self.gen_temp_var(bin_op_expr.type_info())
+        };
+        let d = self.gen_temp_var(bin_op_expr.lhs.type_info());
+        let lhs = self.visit_expr(&mut bin_op_expr.lhs, Some(d), false)?;
+        self.ir_output
+            .add_instructions(IRInst::load_data(res.clone(), lhs));
+
+        // the rhs is not evaluated if the lhs decides the result
+        let operand = Operand::Place(res.clone());
+        let mut end_link = 0;
+        if bin_op_expr.bin_op == BinOperator::AndAnd {
+            self.add_jump(IRInst::jump_if_not(operand.clone(), 0), &mut end_link);
+        } else {
+            self.add_jump(IRInst::jump_if(operand.clone(), 0), &mut end_link);
+        }
+        let d = self.gen_temp_var(bin_op_expr.rhs.type_info());
+        let rhs = self.visit_expr(&mut bin_op_expr.rhs, Some(d), false)?;
+        self.ir_output.add_instructions(IRInst::load_data(res, rhs));
+        self.back_patch(end_link);
+        if dest.is_temp() {
+            Ok(operand)
+        } else {
+            self.ir_output
+                .add_instructions(IRInst::load_data(dest.clone(), operand));
+            Ok(Operand::Place(dest))
+        }
+    }
+
+    fn visit_array_expr(
+        &mut self,
+        array_expr: &mut ArrayExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        let type_info = array_expr.type_info();
+        let (elem_type, len) = match type_info.borrow().deref() {
+            TypeInfo::Array { elem_type, len } => (elem_type.as_ref().clone(), *len as usize),
+            t => unreachable!("array expr of {:?}", t),
+        };
+        let elem_type = Rc::new(RefCell::new(elem_type));
+        let elem_size = self.ir_type(&elem_type.borrow())?.byte_size(self.addr_size);
+        let place = match &dest {
+            Some(d) if d.is_temp() => d.clone(),
+            _ => self.gen_temp_var(type_info.clone()),
+        };
+        let base = Operand::Place(place.clone());
+
+        if array_expr.is_repeat() {
+            let temp = self.gen_temp_var(elem_type.clone());
+            let src = self.visit_expr(&mut array_expr.elems[0], Some(temp), false)?;
+            if !src.is_unit_or_never() && len > 0 {
+                self.gen_repeat(base.clone(), src, elem_size, len, elem_type)?;
+            }
+        } else {
+            for (i, elem) in array_expr.elems.iter_mut().enumerate() {
+                let temp = self.gen_temp_var(elem.type_info());
+                let src = self.visit_expr(elem, Some(temp), false)?;
+                if !src.is_unit_or_never() {
+                    self.ir_output.add_instructions(IRInst::store_mem(
+                        base.clone(),
+                        i as u32 * elem_size,
+                        src,
+                    ));
+                }
+            }
+        }
+
+        match dest {
+            Some(d) if d == place => Ok(base),
+            Some(d) => {
+                self.ir_output.add_instructions(IRInst::load_data(d.clone(), base));
+                Ok(Operand::Place(d))
+            }
+            None => Ok(Operand::Unit),
+        }
     }
 
-    fn visit_array_expr(&mut self, array_expr: &mut ArrayExpr) -> Result<Operand, RccError> {
-        unimplemented!()
+    /// Stores `src` to the `len` elems of the array `base` in a loop.
+    fn gen_repeat(
+        &mut self,
+        base: Operand,
+        src: Operand,
+        elem_size: u32,
+        len: usize,
+        elem_type: Rc<RefCell<TypeInfo>>,
+    ) -> Result<(), RccError> {
+        let ptr = TypeInfo::Ptr {
+            kind: PtrKind::Ref,
+            type_info: Box::new(elem_type.borrow().clone()),
+        };
+        let ptr = Rc::new(RefCell::new(ptr));
+        let p = self.gen_temp_var(ptr.clone());
+        let end = self.gen_temp_var(ptr);
+        self.gen_mem_addr(p.clone(), base, 0)?;
+        let size = Operand::Usize(len * elem_size as usize);
+        self.bin_op(Operand::Place(p.clone()), size, BinOperator::Plus, end.clone())?;
+
+        let start = self.ir_output.next_inst_id();
+        self.ir_output
+            .add_instructions(IRInst::store_mem(Operand::Place(p.clone()), 0, src));
+        let size = Operand::Usize(elem_size as usize);
+        self.bin_op(Operand::Place(p.clone()), size, BinOperator::Plus, p.clone())?;
+        self.ir_output.add_instructions(IRInst::jump_if_cond(
+            JNe,
+            Operand::Place(p),
+            Operand::Place(end),
+            start,
+        ));
+        Ok(())
     }
 
     fn visit_array_index_expr(
         &mut self,
         array_index_expr: &mut ArrayIndexExpr,
+        dest: Option<Place>,
     ) -> Result<Operand, RccError> {
-        unimplemented!()
+        let (base, offset) = self.visit_index_place(array_index_expr)?;
+        self.gen_load_mem(base, offset, array_index_expr.type_info(), dest)
     }
 
-    fn visit_tuple_expr(&mut self, tuple_expr: &mut TupleExpr) -> Result<Operand, RccError> {
-        unimplemented!()
+    fn visit_tuple_expr(
+        &mut self,
+        tuple_expr: &mut TupleExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        let type_info = tuple_expr.type_info();
+        if tuple_expr.elems.is_empty() {
+            return Ok(Operand::Unit);
+        }
+        let layout = self.struct_layout(&type_info.borrow())?;
+        let place = match &dest {
+            Some(d) if d.is_temp() => d.clone(),
+            _ => self.gen_temp_var(type_info),
+        };
+        let base = Operand::Place(place.clone());
+
+        for (elem, elem_layout) in tuple_expr.elems.iter_mut().zip(layout.fields.iter()) {
+            let temp = self.gen_temp_var(elem.type_info());
+            let src = self.visit_expr(elem, Some(temp), false)?;
+            if !src.is_unit_or_never() {
+                self.ir_output.add_instructions(IRInst::store_mem(
+                    base.clone(),
+                    elem_layout.offset,
+                    src,
+                ));
+            }
+        }
+
+        match dest {
+            Some(d) if d == place => Ok(base),
+            Some(d) => {
+                self.ir_output.add_instructions(IRInst::load_data(d.clone(), base));
+                Ok(Operand::Place(d))
+            }
+            None => Ok(Operand::Unit),
+        }
     }
 
     fn visit_tuple_index_expr(
         &mut self,
         tuple_index_expr: &mut TupleIndexExpr,
+        dest: Option<Place>,
     ) -> Result<Operand, RccError> {
-        unimplemented!()
+        let (base, offset) = self.visit_tuple_index_place(tuple_index_expr)?;
+        self.gen_load_mem(base, offset, tuple_index_expr.type_info(), dest)
     }
 
-    fn visit_struct_expr(&mut self, struct_expr: &mut StructExpr) -> Result<Operand, RccError> {
-        unimplemented!()
-    }
-
-    fn visit_call_expr(
+    /// The struct is built in a temporary, since the fields may
+    /// refer to the variable being assigned.
+    fn visit_struct_expr(
         &mut self,
-        call_expr: &mut CallExpr,
+        struct_expr: &mut StructExpr,
         dest: Option<Place>,
     ) -> Result<Operand, RccError> {
-        let callee_place = self.gen_temp_var(call_expr.type_info());
-        let callee = self.visit_expr(&mut call_expr.expr, Some(callee_place), false)?;
+        let type_info = struct_expr.type_info();
+        // zero-sized structs are values like `()`, the exprs are evaluated for their effects
+        if self.ir_type(&type_info.borrow())? == IRType::Unit {
+            for field in struct_expr.fields.iter_mut() {
+                let temp = self.gen_temp_var(field.expr.type_info());
+                self.visit_expr(&mut field.expr, Some(temp), false)?;
+            }
+            if let Some(base_expr) = struct_expr.base.as_mut() {
+                let temp = self.gen_temp_var(base_expr.type_info());
+                self.visit_expr(base_expr, Some(temp), false)?;
+            }
+            return Ok(Operand::Unit);
+        }
+        let layout = self.struct_layout(&type_info.borrow())?;
+        let place = match &dest {
+            Some(d) if d.is_temp() => d.clone(),
+            _ => self.gen_temp_var(type_info),
+        };
+        let base = Operand::Place(place.clone());
 
-        let mut params = vec![];
-        for e in call_expr.call_params.iter_mut() {
-            let param_place = self.gen_temp_var(e.type_info());
-            params.push(self.visit_expr(e, Some(param_place), false)?);
+        for field in struct_expr.fields.iter_mut() {
+            let offset = layout.field(&field.name).unwrap().offset;
+            let temp = self.gen_temp_var(field.expr.type_info());
+            let src = self.visit_expr(&mut field.expr, Some(temp), false)?;
+            if !src.is_unit_or_never() {
+                self.ir_output
+                    .add_instructions(IRInst::store_mem(base.clone(), offset, src));
+            }
         }
-        self.ir_output
-            .add_instructions(IRInst::call(callee, params));
+        // copy the other fields from `..base`
+        if let Some(base_expr) = struct_expr.base.as_mut() {
+            let temp = self.gen_temp_var(base_expr.type_info());
+            let src = self.visit_expr(base_expr, Some(temp), false)?;
+            for field in layout.fields.iter() {
+                if struct_expr.fields.iter().any(|f| f.name == field.name) {
+                    continue;
+                }
+                let temp = self.gen_temp_var(Rc::new(RefCell::new(field.type_info.clone())));
+                self.ir_output.add_instructions(IRInst::load_mem(
+                    temp.clone(),
+                    src.clone(),
+                    field.offset,
+                ));
+                self.ir_output.add_instructions(IRInst::store_mem(
+                    base.clone(),
+                    field.offset,
+                    Operand::Place(temp),
+                ));
+            }
+        }
+
         match dest {
+            Some(d) if d == place => Ok(base),
             Some(d) => {
-                self.ir_output
-                    .add_instructions(IRInst::load_data(d.clone(), Operand::FnRetPlace(d.ir_type)));
+                self.ir_output.add_instructions(IRInst::load_data(d.clone(), base));
                 Ok(Operand::Place(d))
             }
             None => Ok(Operand::Unit),
         }
     }
 
-    fn visit_field_access_expr(
+    /// Fieldless enums are their discriminants, other enums are built like structs
+    /// with the discriminant at offset 0.
+    fn visit_enum_variant_expr(
         &mut self,
-        field_access_expr: &mut FieldAccessExpr,
+        variant_expr: &mut EnumVariantExpr,
+        dest: Option<Place>,
+        remain_temp: bool,
     ) -> Result<Operand, RccError> {
-        unimplemented!()
-    }
+        let type_info = variant_expr.type_info();
+        let type_enum = match type_info.borrow().deref() {
+            TypeInfo::Enum(type_enum) => unsafe { type_enum.as_ref() },
+            t => return Err(format!("expected enum, found `{:?}`", t).into()),
+        };
+        let layout = self.enum_layout(type_enum)?;
+        let (index, _) = type_enum.variant(&variant_expr.variant).unwrap();
+        let discriminant = int_operand(layout.discriminants[index], layout.discriminant_type);
+        if type_enum.is_fieldless() {
+            return self.lit(discriminant, dest, remain_temp);
+        }
 
-    fn visit_loop_block(
-        &mut self,
+        let place = match &dest {
+            Some(d) if d.is_temp() => d.clone(),
+            _ => self.gen_temp_var(type_info),
+        };
+        let base = Operand::Place(place.clone());
+        self.ir_output
+            .add_instructions(IRInst::store_mem(base.clone(), 0, discriminant));
+        let variant_layout = &layout.variants[index];
+        for field in variant_expr.fields.iter_mut() {
+            let offset = variant_layout.field(&field.name).unwrap().offset;
+            let temp = self.gen_temp_var(field.expr.type_info());
+            let src = self.visit_expr(&mut field.expr, Some(temp), false)?;
+            if !src.is_unit_or_never() {
+                self.ir_output
+                    .add_instructions(IRInst::store_mem(base.clone(), offset, src));
+            }
+        }
+
+        match dest {
+            Some(d) if d == place => Ok(base),
+            Some(d) => {
+                self.ir_output.add_instructions(IRInst::load_data(d.clone(), base));
+                Ok(Operand::Place(d))
+            }
+            None => Ok(Operand::Unit),
+        }
+    }
+
+    /// Casts of constants are folded, and values of other IR types are converted by `Cast`.
+    fn visit_cast_expr(
+        &mut self,
+        cast_expr: &mut CastExpr,
+        dest: Option<Place>,
+        remain_temp: bool,
+    ) -> Result<Operand, RccError> {
+        let ir_type = self.ir_type(&cast_expr.type_info().borrow())?;
+        let temp = self.gen_temp_var(cast_expr.expr.type_info());
+        let src = self.visit_expr(&mut cast_expr.expr, Some(temp), false)?;
+        if let Some(o) = ir::cast_may_constant_fold(&src, ir_type) {
+            return self.lit(o, dest, remain_temp);
+        }
+        match src {
+            Operand::Place(p) if p.ir_type == ir_type => {
+                self.lit(Operand::Place(p), dest, remain_temp)
+            }
+            o if o.is_imm() || matches!(o, Operand::Place(_)) => match dest {
+                Some(d) => {
+                    self.ir_output.add_instructions(IRInst::cast(d.clone(), o));
+                    Ok(Operand::Place(d))
+                }
+                None => Ok(Operand::Unit),
+            },
+            o => self.lit(o, dest, remain_temp),
+        }
+    }
+
+    fn visit_call_expr(
+        &mut self,
+        call_expr: &mut CallExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        let callee_place = self.gen_temp_var(call_expr.type_info());
+        let callee = self.visit_expr(&mut call_expr.expr, Some(callee_place), false)?;
+
+        let mut params = vec![];
+        // the returned struct is stored to the first argument
+        let ret_type = self.ir_type(&call_expr.type_info().borrow())?;
+        let ret_place = if ret_type.is_aggregate() {
+            let place = match &dest {
+                Some(d) if d.is_temp() => d.clone(),
+                _ => self.gen_temp_var(call_expr.type_info()),
+            };
+            params.push(self.gen_addr_of(Operand::Place(place.clone()), call_expr.type_info()));
+            Some(place)
+        } else {
+            None
+        };
+        // C functions may modify the aggregates passed to them
+        let is_extern = match &callee {
+            Operand::FnLabel(name) => self.ir_output.extern_fns.iter().any(|f| &f.name == name),
+            _ => false,
+        };
+        for e in call_expr.call_params.iter_mut() {
+            let param_place = self.gen_temp_var(e.type_info());
+            let param = self.visit_expr(e, Some(param_place.clone()), false)?;
+            // structs are copied by the callee
+            params.push(match param {
+                Operand::Place(p) if p.ir_type.is_aggregate() && is_extern && !p.is_temp() => {
+                    let data = Operand::Place(p);
+                    self.ir_output
+                        .add_instructions(IRInst::load_data(param_place.clone(), data));
+                    self.gen_addr_of(Operand::Place(param_place), e.type_info())
+                }
+                Operand::Place(p) if p.ir_type.is_aggregate() => {
+                    self.gen_addr_of(Operand::Place(p), e.type_info())
+                }
+                param => param,
+            });
+        }
+        self.ir_output
+            .add_instructions(IRInst::call(callee, params));
+        match (dest, ret_place) {
+            (Some(d), Some(place)) if d == place => Ok(Operand::Place(d)),
+            (Some(d), Some(place)) => {
+                self.ir_output
+                    .add_instructions(IRInst::load_data(d.clone(), Operand::Place(place)));
+                Ok(Operand::Place(d))
+            }
+            // `()` is not stored
+            (Some(_), None) if ret_type == IRType::Unit => Ok(Operand::Unit),
+            (Some(d), None) => {
+                self.ir_output
+                    .add_instructions(IRInst::load_data(d.clone(), Operand::FnRetPlace(d.ir_type)));
+                Ok(Operand::Place(d))
+            }
+            (None, _) => Ok(Operand::Unit),
+        }
+    }
+
+    fn visit_field_access_expr(
+        &mut self,
+        field_access_expr: &mut FieldAccessExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        let (base, offset) = self.visit_field_place(field_access_expr)?;
+        self.gen_load_mem(base, offset, field_access_expr.type_info(), dest)
+    }
+
+    /// dest = *(base + offset)
+    fn gen_load_mem(
+        &mut self,
+        base: Operand,
+        offset: u32,
+        type_info: Rc<RefCell<TypeInfo>>,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        if matches!(self.ir_type(&type_info.borrow())?, IRType::Unit | IRType::Never) {
+            return Ok(Operand::Unit);
+        }
+        match dest {
+            Some(d) => {
+                self.ir_output
+                    .add_instructions(IRInst::load_mem(d.clone(), base, offset));
+                Ok(Operand::Place(d))
+            }
+            None => Ok(Operand::Unit),
+        }
+    }
+
+    /// `ptr.add(count)`, `ptr.sub(count)` and `ptr.offset(count)` of raw pointers,
+    /// the count is scaled by the size of the pointee.
+    fn visit_method_call_expr(
+        &mut self,
+        method_call_expr: &mut MethodCallExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        let ptr_place = self.gen_temp_var(method_call_expr.type_info());
+        let ptr = self.visit_expr(&mut method_call_expr.receiver, Some(ptr_place), false)?;
+        let count_expr = &mut method_call_expr.call_params[0];
+        let count_type = count_expr.type_info();
+        let count_place = self.gen_temp_var(count_type.clone());
+        let count = self.visit_expr(count_expr, Some(count_place), false)?;
+
+        let size = match &*method_call_expr.type_info().borrow() {
+            TypeInfo::Ptr { type_info, .. } => {
+                self.ir_type(type_info.as_ref())?.byte_size(self.addr_size)
+            }
+            t => unreachable!("method call on {:?}", t),
+        };
+        let count = match count {
+            Operand::Usize(n) => Operand::Usize(n * size as usize),
+            Operand::Isize(n) => Operand::Isize(n * size as isize),
+            count if size == 1 => count,
+            count => {
+                let size = match count {
+                    Operand::Place(Place {
+                        ir_type: IRType::Isize,
+                        ..
+                    }) => Operand::Isize(size as isize),
+                    _ => Operand::Usize(size as usize),
+                };
+                let temp = self.gen_temp_var(count_type);
+                self.bin_op(count, size, BinOperator::Star, temp)?
+            }
+        };
+        let dest = match dest {
+            Some(d) => d,
+            None => return Ok(Operand::Unit),
+        };
+        let op = match method_call_expr.method.as_str() {
+            "sub" => BinOperator::Minus,
+            _ => BinOperator::Plus,
+        };
+        self.bin_op(ptr, count, op, dest)
+    }
+
+    /// Memory of the struct or array `expr` as `(base, offset)` of `IRInst::LoadMem`,
+    /// references are dereferenced automatically.
+    fn visit_memory_place(&mut self, expr: &mut Expr) -> Result<(Operand, u32), RccError> {
+        let mut type_info = expr.type_info().borrow().clone();
+        let is_ptr = matches!(type_info, TypeInfo::Ptr { .. });
+        let (mut base, mut offset) = match expr {
+            Expr::Grouped(expr) => return self.visit_memory_place(expr),
+            Expr::FieldAccess(expr) if !is_ptr => self.visit_field_place(expr)?,
+            Expr::ArrayIndex(expr) if !is_ptr => self.visit_index_place(expr)?,
+            Expr::TupleIndex(expr) if !is_ptr => self.visit_tuple_index_place(expr)?,
+            Expr::Unary(expr) if expr.op == UnOp::Deref => self.visit_deref_place(expr)?,
+            Expr::Path(expr) if self.static_symbol(expr).is_some() => (self.static_addr(expr), 0),
+            expr => {
+                let temp = self.gen_temp_var(expr.type_info());
+                (self.visit_expr(expr, Some(temp), false)?, 0)
+            }
+        };
+        while let TypeInfo::Ptr {
+            type_info: pointee, ..
+        } = type_info
+        {
+            // the address of a reference to reference is loaded
+            if let TypeInfo::Ptr { .. } = pointee.as_ref() {
+                let temp = self.gen_temp_var(Rc::new(RefCell::new(pointee.as_ref().clone())));
+                self.ir_output
+                    .add_instructions(IRInst::load_mem(temp.clone(), base, offset));
+                base = Operand::Place(temp);
+                offset = 0;
+            }
+            type_info = *pointee;
+        }
+        Ok((base, offset))
+    }
+
+    /// Memory of `*expr` as `(base, offset)` of `IRInst::LoadMem`,
+    /// the base is the value of the pointer.
+    fn visit_deref_place(
+        &mut self,
+        unary_expr: &mut UnAryExpr,
+    ) -> Result<(Operand, u32), RccError> {
+        let temp = self.gen_temp_var(unary_expr.expr.type_info());
+        Ok((self.visit_expr(&mut unary_expr.expr, Some(temp), false)?, 0))
+    }
+
+    /// Memory of the field as `(base, offset)` of `IRInst::LoadMem`.
+    fn visit_field_place(
+        &mut self,
+        field_access_expr: &mut FieldAccessExpr,
+    ) -> Result<(Operand, u32), RccError> {
+        let (base, offset) = self.visit_memory_place(&mut field_access_expr.lhs)?;
+        let type_info = pointee_type(&field_access_expr.lhs.type_info().borrow());
+        let layout = self.struct_layout(&type_info)?;
+        let field = layout.field(&field_access_expr.field).unwrap();
+        Ok((base, offset + field.offset))
+    }
+
+    /// Memory of the elem of tuple (or the field of tuple struct)
+    /// as `(base, offset)` of `IRInst::LoadMem`.
+    fn visit_tuple_index_place(
+        &mut self,
+        tuple_index_expr: &mut TupleIndexExpr,
+    ) -> Result<(Operand, u32), RccError> {
+        let (base, offset) = self.visit_memory_place(&mut tuple_index_expr.expr)?;
+        let type_info = pointee_type(&tuple_index_expr.expr.type_info().borrow());
+        let layout = self.struct_layout(&type_info)?;
+        let elem = layout.field(&tuple_index_expr.index.to_string()).unwrap();
+        Ok((base, offset + elem.offset))
+    }
+
+    /// Memory of the elem as `(base, offset)` of `IRInst::LoadMem`,
+    /// the address of the elem is computed if the index is not a constant.
+    fn visit_index_place(
+        &mut self,
+        array_index_expr: &mut ArrayIndexExpr,
+    ) -> Result<(Operand, u32), RccError> {
+        let (base, offset) = self.visit_memory_place(&mut array_index_expr.expr)?;
+        let elem_type = array_index_expr.type_info();
+        let elem_size = self.ir_type(&elem_type.borrow())?.byte_size(self.addr_size);
+        let len = match pointee_type(&array_index_expr.expr.type_info().borrow()) {
+            TypeInfo::Array { len, .. } => len,
+            t => unreachable!("index into {:?}", t),
+        };
+
+        let index_temp = self.gen_temp_var(array_index_expr.index_expr.type_info());
+        match self.visit_expr(&mut array_index_expr.index_expr, Some(index_temp), false)? {
+            Operand::Usize(index) if index >= len as usize => Err(format!(
+                "index out of bounds: the length is {} but the index is {}",
+                len, index
+            )
+            .into()),
+            Operand::Usize(index) => Ok((base, offset + index as u32 * elem_size)),
+            index => {
+                if self.bounds_check {
+                    self.gen_bounds_check(index.clone(), len);
+                }
+                let ptr = TypeInfo::Ptr {
+                    kind: PtrKind::Ref,
+                    type_info: Box::new(elem_type.borrow().clone()),
+                };
+                let addr = self.gen_temp_var(Rc::new(RefCell::new(ptr)));
+                self.gen_mem_addr(addr.clone(), base, offset)?;
+                let index = if elem_size == 1 {
+                    index
+                } else {
+                    let temp = self.gen_temp_var(array_index_expr.index_expr.type_info());
+                    let size = Operand::Usize(elem_size as usize);
+                    self.bin_op(index, size, BinOperator::Star, temp)?
+                };
+                self.bin_op(Operand::Place(addr.clone()), index, BinOperator::Plus, addr)
+                    .map(|addr| (addr, 0))
+            }
+        }
+    }
+
+    /// Abort if `index` is not less than `len`.
+    fn gen_bounds_check(&mut self, index: Operand, len: u32) {
+        let mut in_bounds_link = 0;
+        let inst = IRInst::jump_if_cond(JLt, index, Operand::Usize(len as usize), 0);
+        self.add_jump(inst, &mut in_bounds_link);
+        let abort = self.ir_output.add_extern_fn(ExternFn {
+            name: "abort".to_string(),
+            param_types: vec![],
+            ret_type: IRType::Unit,
+            param_fields: vec![],
+            ret_fields: vec![],
+        });
+        self.ir_output.add_instructions(IRInst::call(abort, vec![]));
+        self.back_patch(in_bounds_link);
+    }
+
+    /// dest = base + offset, the address of an aggregate place is loaded.
+    fn gen_mem_addr(&mut self, dest: Place, base: Operand, offset: u32) -> Result<(), RccError> {
+        let base = match base {
+            Operand::Place(p) if p.ir_type.is_aggregate() => {
+                self.ir_output
+                    .add_instructions(IRInst::load_addr(dest.clone(), Operand::Place(p)));
+                Operand::Place(dest.clone())
+            }
+            base => base,
+        };
+        if offset != 0 {
+            let offset = Operand::Usize(offset as usize);
+            self.bin_op(base, offset, BinOperator::Plus, dest)?;
+        } else if base != Operand::Place(dest.clone()) {
+            self.ir_output.add_instructions(IRInst::load_data(dest, base));
+        }
+        Ok(())
+    }
+
+    /// dest = &place in a new temporary, `type_info` is the type of the place.
+    fn gen_addr_of(&mut self, place: Operand, type_info: Rc<RefCell<TypeInfo>>) -> Operand {
+        let ptr = TypeInfo::Ptr {
+            kind: PtrKind::Ref,
+            type_info: Box::new(type_info.borrow().clone()),
+        };
+        let dest = self.gen_temp_var(Rc::new(RefCell::new(ptr)));
+        self.ir_output
+            .add_instructions(IRInst::load_addr(dest.clone(), place));
+        Operand::Place(dest)
+    }
+
+    fn visit_loop_block(
+        &mut self,
         loop_block: &mut BlockExpr,
         loop_start_id: usize,
     ) -> Result<(), RccError> {
         let operand = self.visit_block_expr(loop_block, None, false)?;
         assert!(operand.is_unit_or_never());
         self.ir_output.add_instructions(IRInst::jump(loop_start_id));
-        let (d, mut link) = self.loop_var_stack.pop().unwrap();
-        let next_id = self.ir_output.next_inst_id();
-        while link != 0 {
-            let inst = self.ir_output.get_inst_by_id(link);
-            link = inst.jump_label();
-            inst.set_jump_label(next_id);
-        }
+        let loop_var = self.loop_var_stack.pop().unwrap();
+        self.patch(loop_var.continue_link, loop_start_id);
+        self.back_patch(loop_var.break_link);
         Ok(())
     }
 
@@ -587,55 +1720,14 @@ impl IRBuilder {
     fn visit_while_expr(&mut self, while_expr: &mut WhileExpr) -> Result<Operand, RccError> {
         let loop_start_id = self.ir_output.next_inst_id();
 
-        let mut next_back_patch_link = 0;
-        // while condition
-        match while_expr.0.as_mut() {
-            Expr::BinOp(e) => match e.bin_op {
-                BinOperator::AndAnd => {
-                    todo!()
-                }
-                BinOperator::OrOr => {
-                    todo!()
-                }
-                BinOperator::Ne => {
-                    self.gen_jump_cond(e, JEq, &mut next_back_patch_link)?;
-                }
-                BinOperator::EqEq => {
-                    self.gen_jump_cond(e, JNe, &mut next_back_patch_link)?;
-                }
-                BinOperator::Le => {
-                    self.gen_jump_cond_reverse(e, JLt, &mut next_back_patch_link)?;
-                }
-                BinOperator::Lt => {
-                    self.gen_jump_cond(e, JGe, &mut next_back_patch_link)?;
-                }
-                BinOperator::Gt => {
-                    self.gen_jump_cond_reverse(e, JGe, &mut next_back_patch_link)?;
-                }
-                BinOperator::Ge => {
-                    self.gen_jump_cond(e, JLt, &mut next_back_patch_link)?;
-                }
-                _ => {
-                    let d = self.gen_temp_var(e.type_info());
-                    let operand = self.visit_bin_op_expr(e, Some(d))?;
-
-                    next_back_patch_link = self.ir_output.next_inst_id();
-                    let ir_inst = IRInst::jump_if_not(operand, 0);
-                    self.ir_output.add_instructions(ir_inst);
-                }
-            },
-            // todo: unary expr, lit bool
-            e => {
-                let d = self.gen_temp_var(e.type_info());
-                let operand = self.visit_expr(e, Some(d), false)?;
-
-                next_back_patch_link = self.ir_output.next_inst_id();
-                let ir_inst = IRInst::jump_if_not(operand, 0);
-                self.ir_output.add_instructions(ir_inst);
-            }
-        }
-        self.loop_var_stack.push((None, next_back_patch_link));
-        self.visit_loop_block(&mut while_expr.1, loop_start_id)?;
+        let mut true_link = 0;
+        let mut false_link = 0;
+        self.gen_cond(&mut while_expr.cond, &mut true_link, &mut false_link, true)?;
+        self.back_patch(true_link);
+        // jumps out of the loop are back patched like `break`
+        let label = while_expr.label.clone();
+        self.loop_var_stack.push(LoopVar::new(label, None, false_link));
+        self.visit_loop_block(&mut while_expr.block, loop_start_id)?;
         Ok(Operand::Unit)
     }
 
@@ -645,7 +1737,8 @@ impl IRBuilder {
         dest: Option<Place>,
     ) -> Result<Operand, RccError> {
         let loop_start_id = self.ir_output.next_inst_id();
-        self.loop_var_stack.push((dest.clone(), 0));
+        let label = loop_expr.label.clone();
+        self.loop_var_stack.push(LoopVar::new(label, dest.clone(), 0));
         self.visit_loop_block(&mut loop_expr.expr, loop_start_id)?;
         match dest {
             Some(p) => Ok(Operand::Place(p)),
@@ -653,6 +1746,96 @@ impl IRBuilder {
         }
     }
 
+    /// For Expr always values ()
+    ///
+    /// for i in a..b {
+    ///     ... // BLOCK
+    /// }
+    /// ... // NEXT
+    ///
+    ///     counter = a
+    ///     end = b
+    /// START:
+    ///     if counter >= end goto NEXT
+    ///     i = counter
+    ///     BLOCK
+    ///     counter = counter + 1
+    ///     goto START
+    ///
+    /// `a..=b` is checked once before the loop, and exits after `counter == end`
+    /// so that the counter never overflows:
+    ///
+    ///     if end < counter goto NEXT
+    /// START:
+    ///     i = counter
+    ///     BLOCK
+    ///     if counter == end goto NEXT
+    ///     counter = counter + 1
+    ///     goto START
+    fn visit_for_expr(&mut self, for_expr: &mut ForExpr) -> Result<Operand, RccError> {
+        let (range_op, (counter, end), counter_type) = match for_expr.expr.as_mut() {
+            Expr::Range(range_expr) => {
+                let bounds = self.visit_range_expr(range_expr)?;
+                let counter_type = range_expr.lhs.as_ref().unwrap().type_info();
+                (range_expr.range_op, bounds, counter_type)
+            }
+            e => return Err(format!("invalid iterator in for expr: {:?}", e).into()),
+        };
+        let one = match counter.ir_type {
+            IRType::I8 => Operand::I8(1),
+            IRType::I16 => Operand::I16(1),
+            IRType::I32 => Operand::I32(1),
+            IRType::I64 => Operand::I64(1),
+            IRType::I128 => Operand::I128(1),
+            IRType::Isize => Operand::Isize(1),
+            IRType::U8 => Operand::U8(1),
+            IRType::U16 => Operand::U16(1),
+            IRType::U32 => Operand::U32(1),
+            IRType::U64 => Operand::U64(1),
+            IRType::U128 => Operand::U128(1),
+            IRType::Usize => Operand::Usize(1),
+            t => return Err(format!("invalid type for range: {:?}", t).into()),
+        };
+        let mut exit_link = 0;
+        if range_op == RangeOp::DotDotEq {
+            let inst = IRInst::jump_if_cond(JLt, end.clone(), Operand::Place(counter.clone()), 0);
+            self.add_jump(inst, &mut exit_link);
+        }
+        let loop_start_id = self.ir_output.next_inst_id();
+        if range_op == RangeOp::DotDot {
+            let inst = IRInst::jump_if_cond(JGe, Operand::Place(counter.clone()), end.clone(), 0);
+            self.add_jump(inst, &mut exit_link);
+        }
+        // the pattern is bound in the scope of the loop block
+        self.scope_stack.enter(&mut for_expr.block.scope);
+        let src = Matched::Value(Operand::Place(counter.clone()));
+        self.visit_pattern(&for_expr.pattern, src, &counter_type.borrow(), false)?;
+        self.scope_stack.exit_scope();
+
+        // jumps out of the loop are back patched like `break`
+        let label = for_expr.label.clone();
+        self.loop_var_stack.push(LoopVar::new(label, None, exit_link));
+        let operand = self.visit_block_expr(&mut for_expr.block, None, false)?;
+        assert!(operand.is_unit_or_never());
+        let loop_var = self.loop_var_stack.pop().unwrap();
+        self.back_patch(loop_var.continue_link);
+        let mut exit_link = loop_var.break_link;
+
+        if range_op == RangeOp::DotDotEq {
+            let inst = IRInst::jump_if_cond(JEq, Operand::Place(counter.clone()), end, 0);
+            self.add_jump(inst, &mut exit_link);
+        }
+        self.bin_op(
+            Operand::Place(counter.clone()),
+            one,
+            BinOperator::Plus,
+            counter,
+        )?;
+        self.ir_output.add_instructions(IRInst::jump(loop_start_id));
+        self.back_patch(exit_link);
+        Ok(Operand::Unit)
+    }
+
     /// ## Examples for translating `if` and logical condition expressions
     ///
     /// ### Primitive condition
@@ -758,138 +1941,398 @@ impl IRBuilder {
         dest: Option<Place>,
     ) -> Result<Operand, RccError> {
         let mut direct_jump_link = 0usize;
-        let mut last_cond_jump = 0usize;
 
-        macro_rules! visit_block {
-            ($i:ident, $ir_inst:ident) => {
-                self.visit_block_expr(if_expr.blocks.get_mut($i).unwrap(), dest.clone(), true)?;
-                if $i != if_expr.blocks.len() - 1 {
-                    self.ir_output
-                        .add_instructions(IRInst::jump(direct_jump_link));
-                    direct_jump_link = self.ir_output.next_inst_id() - 1;
+        for (i, cond) in if_expr.conditions.iter_mut().enumerate() {
+            let mut true_link = 0;
+            let mut false_link = 0;
+            self.gen_cond(cond, &mut true_link, &mut false_link, true)?;
+            self.back_patch(true_link);
+            self.visit_block_expr(if_expr.blocks.get_mut(i).unwrap(), dest.clone(), true)?;
+            if i != if_expr.blocks.len() - 1 {
+                self.add_jump(IRInst::jump(0), &mut direct_jump_link);
+            }
+            // the next condition or the else block
+            self.back_patch(false_link);
+        }
+
+        // visit else block
+        if if_expr.blocks.len() == if_expr.conditions.len() + 1 {
+            self.visit_block_expr(if_expr.blocks.last_mut().unwrap(), dest.clone(), true)?;
+        }
+
+        // back patch all the direct jump
+        self.back_patch(direct_jump_link);
+
+        match dest {
+            Some(d) => Ok(Operand::Place(d)),
+            None => Ok(Operand::Unit),
+        }
+    }
+
+    /// The arms are tested in order, and the last arm is not tested
+    /// because the patterns are exhaustive:
+    ///
+    ///     if src does not match pattern_1 goto arm_2
+    ///     if not guard_1 goto arm_2
+    ///     dest = expr_1
+    ///     goto end
+    /// arm_2:
+    ///     ...
+    /// arm_n:
+    ///     dest = expr_n
+    /// end:
+    ///
+    /// A jump table is used instead of the tests if there are enough literal patterns
+    /// with dense values and no guards.
+    fn visit_match_expr(
+        &mut self,
+        match_expr: &mut MatchExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        let type_info = match_expr.expr.type_info();
+        let scrutinee_type = type_info.borrow().clone();
+        let ir_type = self.ir_type(&scrutinee_type)?;
+        let temp = self.gen_temp_var(type_info);
+        let src = match self.visit_expr(&mut match_expr.expr, Some(temp.clone()), false)? {
+            o if o.is_imm() => {
+                self.ir_output
+                    .add_instructions(IRInst::load_data(temp.clone(), o));
+                Operand::Place(temp)
+            }
+            o => o,
+        };
+
+        let jump_table = match jump_table_entries(match_expr, ir_type) {
+            Some((low, entries, default)) => {
+                let id = self.ir_output.next_inst_id();
+                let inst = IRInst::jump_table(src.clone(), low, entries, default);
+                self.ir_output.add_instructions(inst);
+                Some(id)
+            }
+            None => None,
+        };
+
+        let src = Matched::Value(src);
+        let mut arm_start_ids = vec![];
+        let mut end_link = 0;
+        let arm_count = match_expr.arms.len();
+        for (i, arm) in match_expr.arms.iter_mut().enumerate() {
+            let is_last = i == arm_count - 1;
+            let mut next_arm_link = 0;
+            if jump_table.is_none() && !is_last {
+                self.gen_pattern_test(&arm.pattern, &src, &scrutinee_type, &mut next_arm_link)?;
+            }
+            arm_start_ids.push(self.ir_output.next_inst_id());
+
+            self.scope_stack.enter(&mut arm.scope);
+            self.visit_pattern(&arm.pattern, src.clone(), &scrutinee_type, false)?;
+            if let Some(guard) = arm.guard.as_mut() {
+                let mut true_link = 0;
+                self.gen_cond(guard, &mut true_link, &mut next_arm_link, true)?;
+                self.back_patch(true_link);
+            }
+            self.visit_expr(&mut arm.expr, dest.clone(), true)?;
+            self.scope_stack.exit_scope();
+
+            if !is_last {
+                self.add_jump(IRInst::jump(0), &mut end_link);
+            }
+            self.back_patch(next_arm_link);
+        }
+        self.back_patch(end_link);
+
+        // the labels of the jump table are the indexes of arms before this
+        if let Some(id) = jump_table {
+            if let IRInst::JumpTable {
+                labels, default, ..
+            } = self.ir_output.get_inst_by_id(id)
+            {
+                for label in labels.iter_mut() {
+                    *label = arm_start_ids[*label];
                 }
-            };
+                *default = arm_start_ids[*default];
+            }
         }
 
-        for (i, cond) in if_expr.conditions.iter_mut().enumerate() {
-            match cond {
-                Expr::BinOp(e) => match e.bin_op {
-                    BinOperator::AndAnd => {
-                        todo!()
-                    }
-                    BinOperator::OrOr => {
-                        todo!()
-                    }
-                    BinOperator::Ne => {
-                        self.gen_jump_cond(e, JEq, &mut last_cond_jump)?;
-                        visit_block!(i, ir_inst);
-                    }
-                    BinOperator::EqEq => {
-                        self.gen_jump_cond(e, JNe, &mut last_cond_jump)?;
-                        visit_block!(i, ir_inst);
-                    }
-                    BinOperator::Le => {
-                        self.gen_jump_cond_reverse(e, JLt, &mut last_cond_jump)?;
-                        visit_block!(i, ir_inst);
-                    }
-                    BinOperator::Lt => {
-                        self.gen_jump_cond(e, JGe, &mut last_cond_jump)?;
-                        visit_block!(i, ir_inst);
+        match dest {
+            Some(d) => Ok(Operand::Place(d)),
+            None => Ok(Operand::Unit),
+        }
+    }
+
+    /// Add jumps to `miss_link` taken if `src` does not match the `pattern`,
+    /// the next instruction is reached if it matches.
+    fn gen_pattern_test(
+        &mut self,
+        pattern: &Pattern,
+        src: &Matched,
+        type_info: &TypeInfo,
+        miss_link: &mut usize,
+    ) -> Result<(), RccError> {
+        if pattern.is_irrefutable() {
+            return Ok(());
+        }
+        let ir_type = self.ir_type(type_info)?;
+        let is_ptr = matches!(type_info, TypeInfo::Ptr { .. });
+        // scalars are loaded once for all alternatives
+        let src = if ir_type.is_aggregate() {
+            src.clone()
+        } else {
+            Matched::Value(self.gen_load_matched(src, type_info)?)
+        };
+        let alternatives = match pattern {
+            Pattern::Or(patterns) => patterns.iter().collect(),
+            p => vec![p],
+        };
+        let mut hit_link = 0;
+        for (i, p) in alternatives.iter().enumerate() {
+            let is_last = i == alternatives.len() - 1;
+            match p {
+                Pattern::Literal(lit) if ir_type == IRType::Bool => {
+                    let src = self.gen_load_matched(&src, type_info)?;
+                    // jump if not matched for the last alternative, otherwise if matched
+                    let inst = if (lit.value() != 0) == is_last {
+                        IRInst::jump_if_not(src, 0)
+                    } else {
+                        IRInst::jump_if(src, 0)
+                    };
+                    self.add_jump(inst, if is_last { miss_link } else { &mut hit_link });
+                }
+                Pattern::Literal(lit) if !is_ptr => {
+                    let src = self.gen_load_matched(&src, type_info)?;
+                    let value = int_operand(lit.value(), ir_type);
+                    if is_last {
+                        let inst = IRInst::jump_if_cond(JNe, src, value, 0);
+                        self.add_jump(inst, miss_link);
+                    } else {
+                        let inst = IRInst::jump_if_cond(JEq, src, value, 0);
+                        self.add_jump(inst, &mut hit_link);
                     }
-                    BinOperator::Gt => {
-                        self.gen_jump_cond_reverse(e, JGe, &mut last_cond_jump)?;
-                        visit_block!(i, ir_inst);
+                }
+                Pattern::Range(range) if !is_ptr => {
+                    let src = self.gen_load_matched(&src, type_info)?;
+                    // miss if src < low || high < src
+                    let (low, high) = range.bounds();
+                    let mut range_miss_link = 0;
+                    let link = if is_last {
+                        &mut *miss_link
+                    } else {
+                        &mut range_miss_link
+                    };
+                    let low = int_operand(low, ir_type);
+                    let high = int_operand(high, ir_type);
+                    self.add_jump(IRInst::jump_if_cond(JLt, src.clone(), low, 0), link);
+                    self.add_jump(IRInst::jump_if_cond(JLt, high, src, 0), link);
+                    if !is_last {
+                        self.add_jump(IRInst::jump(0), &mut hit_link);
+                        self.back_patch(range_miss_link);
                     }
-                    BinOperator::Ge => {
-                        self.gen_jump_cond(e, JLt, &mut last_cond_jump)?;
-                        visit_block!(i, ir_inst);
+                }
+                p => {
+                    let mut alt_miss_link = 0;
+                    let link = if is_last {
+                        &mut *miss_link
+                    } else {
+                        &mut alt_miss_link
+                    };
+                    match p {
+                        Pattern::Ref(ref_pattern) => {
+                            let (value, value_type) = self.deref_matched(&src, type_info)?;
+                            self.gen_pattern_test(&ref_pattern.pattern, &value, &value_type, link)?;
+                        }
+                        Pattern::Or(_) => self.gen_pattern_test(p, &src, type_info, link)?,
+                        // default binding mode: non-reference patterns test the referenced value
+                        p if is_ptr => {
+                            let (value, value_type) = self.deref_matched(&src, type_info)?;
+                            self.gen_pattern_test(p, &value, &value_type, link)?;
+                        }
+                        p => self.gen_fields_test(p, &src, type_info, link)?,
                     }
-                    _ => {
-                        let d = self.gen_temp_var(e.type_info());
-                        let operand = self.visit_bin_op_expr(e, Some(d))?;
-                        let ir_inst = IRInst::jump_if_not(operand, last_cond_jump);
-                        self.ir_output.add_instructions(ir_inst);
-                        visit_block!(i, ir_inst);
+                    if !is_last {
+                        self.add_jump(IRInst::jump(0), &mut hit_link);
+                        self.back_patch(alt_miss_link);
                     }
-                },
-                // todo: unary expr, lit bool
-                e => {
-                    let d = self.gen_temp_var(e.type_info());
-                    let operand = self.visit_expr(e, Some(d), false)?;
-                    let ir_inst = IRInst::jump_if_not(operand, last_cond_jump);
-                    last_cond_jump = self.ir_output.next_inst_id();
-                    self.ir_output.add_instructions(ir_inst);
-                    visit_block!(i, ir_inst);
                 }
             }
         }
+        self.back_patch(hit_link);
+        Ok(())
+    }
 
-        // back patch the last jump condition
-        if last_cond_jump != 0 {
-            let jump_label = self.ir_output.next_inst_id();
-            let inst_to_backpatch = self.ir_output.get_inst_by_id(last_cond_jump);
-            inst_to_backpatch.set_jump_label(jump_label);
+    /// Add jumps to `miss_link` taken if `src` is not the enum variant of `pattern`
+    /// or its fields do not match their refutable patterns.
+    fn gen_fields_test(
+        &mut self,
+        pattern: &Pattern,
+        src: &Matched,
+        type_info: &TypeInfo,
+        miss_link: &mut usize,
+    ) -> Result<(), RccError> {
+        let variant = match pattern {
+            Pattern::Path(path)
+            | Pattern::Struct(StructPattern { path, .. })
+            | Pattern::TupleStruct(TupleStructPattern { path, .. }) => self.find_variant(path),
+            _ => None,
+        };
+        if let Some((type_enum, index)) = variant {
+            let type_enum = unsafe { type_enum.as_ref() };
+            let layout = self.enum_layout(type_enum)?;
+            // fieldless enums are their discriminants
+            let tag = if self.ir_type(type_info)? == layout.discriminant_type {
+                self.gen_load_matched(src, type_info)?
+            } else {
+                let tag_type = TypeInfo::LitNum(type_enum.discriminant_type());
+                self.gen_load_matched(&src.field(0), &tag_type)?
+            };
+            let value = int_operand(layout.discriminants[index], layout.discriminant_type);
+            self.add_jump(IRInst::jump_if_cond(JNe, tag, value, 0), miss_link);
         }
 
-        // visit else block
-        if if_expr.blocks.len() == if_expr.conditions.len() + 1 {
-            self.visit_block_expr(if_expr.blocks.last_mut().unwrap(), dest.clone(), true)?;
+        let fields = match pattern {
+            Pattern::Tuple(patterns) => {
+                let layout = self.struct_layout(type_info)?;
+                matched_elems(patterns, src, &layout, Pattern::is_irrefutable)
+            }
+            Pattern::Struct(struct_pattern) => {
+                self.matched_fields(struct_pattern, src, type_info, Pattern::is_irrefutable)?
+            }
+            Pattern::TupleStruct(tuple_struct_pattern) => {
+                let layout = self.fields_layout(&tuple_struct_pattern.path, type_info)?;
+                let patterns = &tuple_struct_pattern.patterns;
+                matched_elems(patterns, src, &layout, Pattern::is_irrefutable)
+            }
+            _ => vec![],
+        };
+        for (field, pattern, field_type) in fields {
+            self.gen_pattern_test(pattern, &field, &field_type, miss_link)?;
         }
+        Ok(())
+    }
 
+    /// Add the jump `inst` to the back patch link `link`.
+    fn add_jump(&mut self, mut inst: IRInst, link: &mut usize) {
+        inst.set_jump_label(*link);
+        *link = self.ir_output.next_inst_id();
+        self.ir_output.add_instructions(inst);
+    }
+
+    /// Set the labels of all the jumps in the back patch link to the next instruction.
+    fn back_patch(&mut self, link: usize) {
         let jump_label = self.ir_output.next_inst_id();
+        self.patch(link, jump_label);
+    }
 
-        // back patch all the direct jump
-        while direct_jump_link != 0 {
-            let inst_to_backpatch = self.ir_output.get_inst_by_id(direct_jump_link);
-            direct_jump_link = inst_to_backpatch.jump_label();
+    /// Set the labels of all the jumps in the back patch link to `jump_label`.
+    fn patch(&mut self, mut link: usize, jump_label: usize) {
+        while link != 0 {
+            let inst_to_backpatch = self.ir_output.get_inst_by_id(link);
+            link = inst_to_backpatch.jump_label();
             inst_to_backpatch.set_jump_label(jump_label);
         }
+    }
 
-        match dest {
-            Some(d) => Ok(Operand::Place(d)),
-            None => Ok(Operand::Unit),
+    /// Generate the jumps of the condition `cond`, jumps taken if it is true or false
+    /// are added to `true_link` or `false_link`,
+    /// the next instruction is reached if it equals `fall_through`.
+    fn gen_cond(
+        &mut self,
+        cond: &mut Expr,
+        true_link: &mut usize,
+        false_link: &mut usize,
+        fall_through: bool,
+    ) -> Result<(), RccError> {
+        match cond {
+            Expr::Grouped(e) => return self.gen_cond(e, true_link, false_link, fall_through),
+            Expr::Unary(e) if e.op == UnOp::Not => {
+                return self.gen_cond(&mut e.expr, false_link, true_link, !fall_through);
+            }
+            Expr::LitBool(b) => {
+                if *b != fall_through {
+                    let link = if *b { true_link } else { false_link };
+                    self.add_jump(IRInst::jump(0), link);
+                }
+                return Ok(());
+            }
+            Expr::BinOp(e) => match e.bin_op {
+                BinOperator::AndAnd | BinOperator::OrOr => {
+                    return self.gen_logic_cond(e, true_link, false_link, fall_through);
+                }
+                BinOperator::EqEq
+                | BinOperator::Ne
+                | BinOperator::Lt
+                | BinOperator::Le
+                | BinOperator::Gt
+                | BinOperator::Ge
+                    // `!(a < b)` is not `a >= b` if any operand is NaN,
+                    // so comparisons of floats are evaluated into booleans
+                    if !e.lhs.type_info().borrow().is_float() =>
+                {
+                    return self.gen_cmp_cond(e, true_link, false_link, fall_through);
+                }
+                _ => {}
+            },
+            _ => {}
         }
+        let d = self.gen_temp_var(cond.type_info());
+        let operand = self.visit_expr(cond, Some(d), false)?;
+        if fall_through {
+            self.add_jump(IRInst::jump_if_not(operand, 0), false_link);
+        } else {
+            self.add_jump(IRInst::jump_if(operand, 0), true_link);
+        }
+        Ok(())
     }
 
-    fn gen_jump_cond(
+    /// `A && B`: B is evaluated if A is true,
+    /// `A || B`: B is evaluated if A is false.
+    fn gen_logic_cond(
         &mut self,
         e: &mut BinOpExpr,
-        jump: Jump,
-        last_condition_jump: &mut usize,
+        true_link: &mut usize,
+        false_link: &mut usize,
+        fall_through: bool,
     ) -> Result<(), RccError> {
-        let d = self.gen_temp_var(e.type_info());
-        let lhs = self.visit_expr(&mut e.lhs, Some(d), false)?;
-        let d = self.gen_temp_var(e.type_info());
-        let rhs = self.visit_expr(&mut e.rhs, Some(d), false)?;
-        if *last_condition_jump != 0 {
-            let jump_label = self.ir_output.next_inst_id();
-            let inst_to_backpatch = self.ir_output.get_inst_by_id(*last_condition_jump);
-            inst_to_backpatch.set_jump_label(jump_label);
+        if e.bin_op == BinOperator::AndAnd {
+            let mut lhs_true_link = 0;
+            self.gen_cond(&mut e.lhs, &mut lhs_true_link, false_link, true)?;
+            self.back_patch(lhs_true_link);
+        } else {
+            let mut lhs_false_link = 0;
+            self.gen_cond(&mut e.lhs, true_link, &mut lhs_false_link, false)?;
+            self.back_patch(lhs_false_link);
         }
-        let ir_inst = IRInst::jump_if_cond(jump, lhs, rhs, 0);
-        *last_condition_jump = self.ir_output.next_inst_id();
-        self.ir_output.add_instructions(ir_inst);
-        Ok(())
+        self.gen_cond(&mut e.rhs, true_link, false_link, fall_through)
     }
 
-    fn gen_jump_cond_reverse(
+    fn gen_cmp_cond(
         &mut self,
         e: &mut BinOpExpr,
-        jump: Jump,
-        next_back_patch_link: &mut usize,
+        true_link: &mut usize,
+        false_link: &mut usize,
+        fall_through: bool,
     ) -> Result<(), RccError> {
-        let d = self.gen_temp_var(e.type_info());
+        let d = self.gen_temp_var(e.lhs.type_info());
         let lhs = self.visit_expr(&mut e.lhs, Some(d), false)?;
-        let d = self.gen_temp_var(e.type_info());
+        let d = self.gen_temp_var(e.rhs.type_info());
         let rhs = self.visit_expr(&mut e.rhs, Some(d), false)?;
-        if *next_back_patch_link != 0 {
-            let jump_label = self.ir_output.next_inst_id();
-            let inst_to_backpatch = self.ir_output.get_inst_by_id(*next_back_patch_link);
-            inst_to_backpatch.set_jump_label(jump_label);
+        // (jump if true, jump if false, swap operands)
+        let (if_true, if_false, reverse) = match e.bin_op {
+            BinOperator::EqEq => (JEq, JNe, false),
+            BinOperator::Ne => (JNe, JEq, false),
+            BinOperator::Lt => (JLt, JGe, false),
+            BinOperator::Ge => (JGe, JLt, false),
+            // a <= b <=> b >= a
+            BinOperator::Le => (JGe, JLt, true),
+            // a > b <=> b < a
+            BinOperator::Gt => (JLt, JGe, true),
+            _ => unreachable!(),
+        };
+        let (src1, src2) = if reverse { (rhs, lhs) } else { (lhs, rhs) };
+        if fall_through {
+            self.add_jump(IRInst::jump_if_cond(if_false, src1, src2, 0), false_link);
+        } else {
+            self.add_jump(IRInst::jump_if_cond(if_true, src1, src2, 0), true_link);
         }
-        let ir_inst = IRInst::jump_if_cond(jump, rhs, lhs, 0);
-        self.ir_output.add_instructions(ir_inst);
-        *next_back_patch_link = self.ir_output.next_inst_id() - 1;
         Ok(())
     }
 
@@ -902,7 +2345,7 @@ impl IRBuilder {
             Some(e) => {
                 let ret_place = self.fn_ret_temp_var.last().unwrap();
                 let operand = self.visit_expr(e.as_mut(), Some(ret_place.clone()), false)?;
-                self.ir_output.add_instructions(IRInst::Ret(operand));
+                self.gen_ret(operand);
             }
             None => {
                 self.ir_output.add_instructions(IRInst::Ret(Operand::Unit));
@@ -923,8 +2366,9 @@ impl IRBuilder {
         break_expr: &mut BreakExpr,
         dest: Option<Place>,
     ) -> Result<Operand, RccError> {
-        let (break_place, _) = self.loop_var_stack.last_mut().unwrap();
-        match &mut break_expr.0 {
+        let idx = self.find_loop_var(&break_expr.label);
+        let break_place = &self.loop_var_stack[idx].place;
+        match &mut break_expr.expr {
             Some(e) => {
                 if let Some(p) = break_place {
                     let p = p.clone();
@@ -941,12 +2385,29 @@ impl IRBuilder {
                 }
             }
         }
-        let jump_id = self.ir_output.next_inst_id();
+        let mut break_link = self.loop_var_stack[idx].break_link;
+        self.add_jump(IRInst::jump(0), &mut break_link);
+        self.loop_var_stack[idx].break_link = break_link;
 
-        let (_, loop_start_id) = self.loop_var_stack.last_mut().unwrap();
-        self.ir_output
-            .add_instructions(IRInst::jump(*loop_start_id));
-        *loop_start_id = jump_id;
+        match dest {
+            Some(d) => {
+                self.ir_output
+                    .add_instructions(IRInst::load_data(d.clone(), Operand::Never));
+                Ok(Operand::Place(d))
+            }
+            None => Ok(Operand::Never),
+        }
+    }
+
+    fn visit_continue_expr(
+        &mut self,
+        continue_expr: &mut ContinueExpr,
+        dest: Option<Place>,
+    ) -> Result<Operand, RccError> {
+        let idx = self.find_loop_var(&continue_expr.0);
+        let mut continue_link = self.loop_var_stack[idx].continue_link;
+        self.add_jump(IRInst::jump(0), &mut continue_link);
+        self.loop_var_stack[idx].continue_link = continue_link;
 
         match dest {
             Some(d) => {
@@ -957,4 +2418,178 @@ impl IRBuilder {
             None => Ok(Operand::Never),
         }
     }
+
+    /// Return the index of the loop which `break` or `continue` with `label` refers to
+    fn find_loop_var(&self, label: &Option<String>) -> usize {
+        match label {
+            None => self.loop_var_stack.len() - 1,
+            Some(_) => self
+                .loop_var_stack
+                .iter()
+                .rposition(|loop_var| &loop_var.label == label)
+                .expect("error in ir_builder: label not found"),
+        }
+    }
+}
+
+/// Minimum number of literal and range patterns to use a jump table
+const JUMP_TABLE_MIN_CASES: usize = 4;
+const JUMP_TABLE_MAX_SIZE: i128 = 256;
+
+/// Entries of the jump table of `match_expr` if it is worth using one,
+/// return the smallest value in the table, the arm index of each value in the table
+/// and the arm index of values out of the table.
+///
+/// A jump table is used for integers of at most 32 bits and `char` without guards,
+/// if at least half of the values in the table are matched by literal and range patterns.
+/// The operator of compound assignment, `None` for `=`
+fn assign_op_to_bin_op(assign_op: &AssignOp) -> Option<BinOperator> {
+    match assign_op {
+        AssignOp::Eq => None,
+        AssignOp::ShrEq => Some(BinOperator::Shr),
+        AssignOp::ShlEq => Some(BinOperator::Shl),
+        AssignOp::PlusEq => Some(BinOperator::Plus),
+        AssignOp::MinusEq => Some(BinOperator::Minus),
+        AssignOp::StarEq => Some(BinOperator::Star),
+        AssignOp::SlashEq => Some(BinOperator::Slash),
+        AssignOp::PercentEq => Some(BinOperator::Percent),
+        AssignOp::AndEq => Some(BinOperator::And),
+        AssignOp::OrEq => Some(BinOperator::Or),
+        AssignOp::CaretEq => Some(BinOperator::Caret),
+    }
+}
+
+/// Type referred by `type_info` through pointers
+fn pointee_type(type_info: &TypeInfo) -> TypeInfo {
+    match type_info {
+        TypeInfo::Ptr { type_info, .. } => pointee_type(type_info),
+        t => t.clone(),
+    }
+}
+
+fn jump_table_entries(
+    match_expr: &MatchExpr,
+    ir_type: IRType,
+) -> Option<(i64, Vec<usize>, usize)> {
+    use IRType::*;
+    if !matches!(ir_type, I8 | I16 | I32 | Isize | U8 | U16 | U32 | Usize | Char)
+        || match_expr.arms.iter().any(|arm| arm.guard.is_some())
+    {
+        return None;
+    }
+
+    // (low, high, arm index) of literal and range patterns
+    let mut cases = vec![];
+    for (i, arm) in match_expr.arms.iter().enumerate() {
+        let alternatives = match &arm.pattern {
+            Pattern::Or(patterns) => patterns.iter().collect(),
+            p => vec![p],
+        };
+        for p in alternatives {
+            match p {
+                Pattern::Literal(lit) => cases.push((lit.value(), lit.value(), i)),
+                Pattern::Range(range) => {
+                    let (low, high) = range.bounds();
+                    cases.push((low, high, i));
+                }
+                _ => {}
+            }
+        }
+    }
+    if cases.len() < JUMP_TABLE_MIN_CASES {
+        return None;
+    }
+    let low = cases.iter().map(|(l, _, _)| *l).min()?;
+    let high = cases.iter().map(|(_, h, _)| *h).max()?;
+    let size = high - low + 1;
+    let matched: i128 = cases.iter().map(|(l, h, _)| h - l + 1).sum();
+    if size > JUMP_TABLE_MAX_SIZE || size > 2 * matched {
+        return None;
+    }
+
+    // values not in any case are matched by the first irrefutable arm
+    let default = match match_expr.arms.iter().position(|arm| arm.pattern.is_irrefutable()) {
+        Some(i) => i,
+        None => cases.iter().find(|(l, _, _)| *l == low)?.2,
+    };
+    let mut entries = vec![None; size as usize];
+    // the first arm matching the value wins
+    for (l, h, i) in cases {
+        for entry in entries[(l - low) as usize..=(h - low) as usize].iter_mut() {
+            entry.get_or_insert(i);
+        }
+    }
+    let entries = entries.into_iter().map(|e| e.unwrap_or(default)).collect();
+    Some((low as i64, entries, default))
+}
+
+fn is_wildcard(pattern: &Pattern) -> bool {
+    pattern == &Pattern::Wildcard
+}
+
+/// The elems of the tuple `src` with the `layout` like `IRBuilder::matched_fields`,
+/// the elems after `..` are matched from the end.
+fn matched_elems<'p>(
+    patterns: &'p [Pattern],
+    src: &Matched,
+    layout: &StructLayout,
+    skip: fn(&Pattern) -> bool,
+) -> Vec<(Matched, &'p Pattern, TypeInfo)> {
+    let rest = patterns.iter().position(|p| p == &Pattern::Rest);
+    let mut elems = vec![];
+    for (i, pattern) in patterns.iter().enumerate() {
+        if pattern == &Pattern::Rest || skip(pattern) {
+            continue;
+        }
+        let index = match rest {
+            Some(rest) if i > rest => layout.fields.len() - (patterns.len() - i),
+            _ => i,
+        };
+        let elem = &layout.fields[index];
+        elems.push((src.field(elem.offset), pattern, elem.type_info.clone()));
+    }
+    elems
+}
+
+/// Value of the integer immediate `operand`.
+fn imm_value(operand: &Operand) -> i128 {
+    match *operand {
+        Operand::Bool(b) => b as i128,
+        Operand::Char(c) => c as i128,
+        Operand::I8(v) => v as i128,
+        Operand::I16(v) => v as i128,
+        Operand::I32(v) => v as i128,
+        Operand::I64(v) => v as i128,
+        Operand::I128(v) => v,
+        Operand::Isize(v) => v as i128,
+        Operand::U8(v) => v as i128,
+        Operand::U16(v) => v as i128,
+        Operand::U32(v) => v as i128,
+        Operand::U64(v) => v as i128,
+        Operand::U128(v) => v as i128,
+        Operand::Usize(v) => v as i128,
+        ref o => unreachable!("not an integer: {:?}", o),
+    }
+}
+
+/// Immediate operand of the integer `value` with type `ir_type`,
+/// `bool` and `char` are represented by integers in patterns.
+fn int_operand(value: i128, ir_type: IRType) -> Operand {
+    match ir_type {
+        IRType::Bool => Operand::Bool(value != 0),
+        IRType::Char => Operand::Char(char::from_u32(value as u32).unwrap()),
+        IRType::I8 => Operand::I8(value as i8),
+        IRType::I16 => Operand::I16(value as i16),
+        IRType::I32 => Operand::I32(value as i32),
+        IRType::I64 => Operand::I64(value as i64),
+        IRType::I128 => Operand::I128(value),
+        IRType::Isize => Operand::Isize(value as isize),
+        IRType::U8 => Operand::U8(value as u8),
+        IRType::U16 => Operand::U16(value as u16),
+        IRType::U32 => Operand::U32(value as u32),
+        IRType::U64 => Operand::U64(value as u64),
+        IRType::U128 => Operand::U128(value as u128),
+        IRType::Usize => Operand::Usize(value as usize),
+        t => unreachable!("invalid type of pattern: {:?}", t),
+    }
 }
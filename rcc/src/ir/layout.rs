@@ -0,0 +1,252 @@
+//! Memory layout of structs, tuples and enums.
+use crate::analyser::scope::Scope;
+use crate::analyser::sym_resolver::TypeInfo;
+use crate::ast::item::{Fields, TypeEnum};
+use crate::ir::IRType;
+use crate::rcc::RccError;
+
+#[derive(Debug, PartialEq)]
+pub struct FieldLayout {
+    pub name: String,
+    /// offset from the start of the struct
+    pub offset: u32,
+    pub type_info: TypeInfo,
+    pub ir_type: IRType,
+}
+
+/// Fields are laid out in the declared order like `#[repr(C)]`:
+/// every field is aligned to its alignment, and the size is
+/// rounded up to the largest alignment of the fields.
+/// Tuples are laid out like tuple structs, so they have the same layout as C structs.
+#[derive(Debug, PartialEq)]
+pub struct StructLayout {
+    pub fields: Vec<FieldLayout>,
+    pub size: u32,
+    pub align: u32,
+}
+
+impl StructLayout {
+    /// The types of the fields are found in `scope`.
+    pub fn new(fields: &Fields, scope: &Scope, addr_size: u32) -> Result<StructLayout, RccError> {
+        let fields = fields
+            .types()
+            .into_iter()
+            .map(|(name, type_anno)| (name, TypeInfo::from_type_anno(type_anno, scope)));
+        StructLayout::from_fields(fields, scope, addr_size)
+    }
+
+    /// The elems of the tuple are named by their indexes.
+    pub fn tuple(types: &[TypeInfo], scope: &Scope, addr_size: u32) -> Result<StructLayout, RccError> {
+        let fields = types
+            .iter()
+            .enumerate()
+            .map(|(i, type_info)| (i.to_string(), type_info.clone()));
+        StructLayout::from_fields(fields, scope, addr_size)
+    }
+
+    fn from_fields(
+        fields: impl Iterator<Item = (String, TypeInfo)>,
+        scope: &Scope,
+        addr_size: u32,
+    ) -> Result<StructLayout, RccError> {
+        StructLayout::from_fields_after(0, 1, fields, scope, addr_size)
+    }
+
+    /// The fields are laid out after the first `size` bytes aligned to `align`.
+    fn from_fields_after(
+        size: u32,
+        align: u32,
+        fields: impl Iterator<Item = (String, TypeInfo)>,
+        scope: &Scope,
+        addr_size: u32,
+    ) -> Result<StructLayout, RccError> {
+        let mut layout = StructLayout {
+            fields: vec![],
+            size,
+            align,
+        };
+        for (name, type_info) in fields {
+            let ir_type = ir_type_of(&type_info, scope, addr_size)?;
+            let align = ir_type.align(addr_size);
+            let offset = layout.size.div_ceil(align) * align;
+            layout.size = offset + ir_type.byte_size(addr_size);
+            layout.align = layout.align.max(align);
+            layout.fields.push(FieldLayout {
+                name,
+                offset,
+                type_info,
+                ir_type,
+            });
+        }
+        layout.size = layout.size.div_ceil(layout.align) * layout.align;
+        Ok(layout)
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldLayout> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+}
+
+/// Enums are tagged unions, the discriminant of the variant is stored at offset 0.
+/// The fields of `#[repr(inttype)]` enums are laid out after the discriminant
+/// like structs for each variant, otherwise the fields of all variants start
+/// at the same offset like `#[repr(C)]` which puts them in a union.
+/// Fieldless enums are their discriminants.
+#[derive(Debug, PartialEq)]
+pub struct EnumLayout {
+    pub discriminant_type: IRType,
+    pub discriminants: Vec<i128>,
+    /// fields of the variants, the offsets are from the start of the enum
+    pub variants: Vec<StructLayout>,
+    pub size: u32,
+    pub align: u32,
+}
+
+impl EnumLayout {
+    pub fn new(type_enum: &TypeEnum, scope: &Scope, addr_size: u32) -> Result<EnumLayout, RccError> {
+        let tag_type = TypeInfo::LitNum(type_enum.discriminant_type());
+        let discriminant_type = IRType::from_type_info(&tag_type)?;
+        let tag_size = discriminant_type.byte_size(addr_size);
+        let tag_align = discriminant_type.align(addr_size);
+        let fields = |fields: &Fields| {
+            fields
+                .types()
+                .into_iter()
+                .map(|(name, type_anno)| (name, TypeInfo::from_type_anno(type_anno, scope)))
+                .collect::<Vec<_>>()
+        };
+
+        let mut variants = vec![];
+        let repr = type_enum.repr();
+        if repr.int_type.is_some() && !repr.c {
+            for variant in type_enum.variants() {
+                let layout = StructLayout::from_fields_after(
+                    tag_size,
+                    tag_align,
+                    fields(variant.fields()).into_iter(),
+                    scope,
+                    addr_size,
+                )?;
+                variants.push(layout);
+            }
+        } else {
+            for variant in type_enum.variants() {
+                let layout = StructLayout::from_fields(
+                    fields(variant.fields()).into_iter(),
+                    scope,
+                    addr_size,
+                )?;
+                variants.push(layout);
+            }
+            let union_align = variants.iter().map(|v| v.align).max().unwrap_or(1);
+            let union_start = tag_size.div_ceil(union_align) * union_align;
+            for layout in variants.iter_mut() {
+                for field in layout.fields.iter_mut() {
+                    field.offset += union_start;
+                }
+                layout.size += union_start;
+                layout.align = layout.align.max(tag_align);
+            }
+        }
+
+        let align = variants.iter().map(|v| v.align).fold(tag_align, u32::max);
+        let size = variants.iter().map(|v| v.size).fold(tag_size, u32::max);
+        Ok(EnumLayout {
+            discriminant_type,
+            discriminants: type_enum.discriminants(),
+            variants,
+            size: size.div_ceil(align) * align,
+            align,
+        })
+    }
+}
+
+/// IR type of `type_info`, structs, tuples, arrays and enums with fields are aggregates,
+/// the fields of structs are found in `scope`.
+pub fn ir_type_of(type_info: &TypeInfo, scope: &Scope, addr_size: u32) -> Result<IRType, RccError> {
+    match type_info {
+        TypeInfo::Struct { fields, .. } => {
+            let layout = StructLayout::new(unsafe { fields.as_ref() }, scope, addr_size)?;
+            // zero-sized structs are values like `()`
+            if layout.size == 0 {
+                return Ok(IRType::Unit);
+            }
+            Ok(IRType::Aggregate {
+                size: layout.size,
+                align: layout.align,
+            })
+        }
+        TypeInfo::Tuple(types) => {
+            let layout = StructLayout::tuple(types, scope, addr_size)?;
+            Ok(IRType::Aggregate {
+                size: layout.size,
+                align: layout.align,
+            })
+        }
+        TypeInfo::Array { elem_type, len } => {
+            let elem = ir_type_of(elem_type, scope, addr_size)?;
+            Ok(IRType::Aggregate {
+                size: elem.byte_size(addr_size) * len,
+                align: elem.align(addr_size),
+            })
+        }
+        TypeInfo::Enum(type_enum) => {
+            let type_enum = unsafe { type_enum.as_ref() };
+            if type_enum.variants().is_empty() {
+                return Ok(IRType::Never);
+            }
+            let layout = EnumLayout::new(type_enum, scope, addr_size)?;
+            if type_enum.is_fieldless() {
+                Ok(layout.discriminant_type)
+            } else {
+                Ok(IRType::Aggregate {
+                    size: layout.size,
+                    align: layout.align,
+                })
+            }
+        }
+        t => IRType::from_type_info(t),
+    }
+}
+
+/// The scalar fields of `type_info` and their offsets, flattened from the nested structs,
+/// tuples and arrays, which decide how the aggregate is passed to C functions.
+pub fn scalar_fields(
+    type_info: &TypeInfo,
+    scope: &Scope,
+    addr_size: u32,
+) -> Result<Vec<(u32, IRType)>, RccError> {
+    let layout = match type_info {
+        TypeInfo::Struct { fields, .. } => {
+            StructLayout::new(unsafe { fields.as_ref() }, scope, addr_size)?
+        }
+        TypeInfo::Tuple(types) => StructLayout::tuple(types, scope, addr_size)?,
+        TypeInfo::Array { elem_type, len } => {
+            let elem_fields = scalar_fields(elem_type, scope, addr_size)?;
+            let elem_size = ir_type_of(elem_type, scope, addr_size)?.byte_size(addr_size);
+            let fields = (0..*len).flat_map(|i| {
+                let offset = i * elem_size;
+                elem_fields.iter().map(move |(o, t)| (offset + o, *t))
+            });
+            return Ok(fields.collect());
+        }
+        t => {
+            return match ir_type_of(t, scope, addr_size)? {
+                IRType::Unit | IRType::Never => Ok(vec![]),
+                IRType::Aggregate { .. } => Err(format!(
+                    "enums with fields cannot be passed to C functions: `{}`",
+                    t
+                )
+                .into()),
+                ir_type => Ok(vec![(0, ir_type)]),
+            };
+        }
+    };
+    let mut fields = vec![];
+    for field in layout.fields.iter() {
+        for (offset, ir_type) in scalar_fields(&field.type_info, scope, addr_size)? {
+            fields.push((field.offset + offset, ir_type));
+        }
+    }
+    Ok(fields)
+}
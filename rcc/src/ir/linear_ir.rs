@@ -1,6 +1,11 @@
-use crate::ast::item::{ItemFn, FnSignature};
+use crate::analyser::scope::Scope;
+use crate::analyser::sym_resolver::TypeInfo;
+use crate::ast::item::{ExternalItem, FnSignature, ItemExternalBlock, ItemFn};
 use crate::ast::pattern::Pattern;
+use crate::ast::types::TypeAnnotation;
 use crate::ast::Visibility;
+use crate::ir::layout::{ir_type_of, scalar_fields};
+use crate::ir::var_name::{fn_param_var, FN_RET_VAR};
 use crate::ir::{IRInst, IRType, Operand, Place};
 use crate::rcc::RccError;
 use std::collections::{HashMap, VecDeque};
@@ -9,6 +14,11 @@ pub struct LinearIR {
     pub funcs: Vec<Func>,
     /// label, value
     pub ro_local_strs: HashMap<String, String>,
+    /// functions declared in `extern "C"` blocks
+    pub extern_fns: Vec<ExternFn>,
+    pub statics: Vec<StaticData>,
+    /// source line of the instructions being added, 0 if unknown
+    cur_line: u32,
 }
 
 impl LinearIR {
@@ -16,16 +26,78 @@ impl LinearIR {
         LinearIR {
             funcs: vec![],
             ro_local_strs: HashMap::new(),
+            extern_fns: vec![],
+            statics: vec![],
+            cur_line: 0,
         }
     }
 
+    /// Structs, tuples and arrays are passed and returned like those of other functions,
+    /// the code generators pass them by the C calling conventions with their scalar fields.
+    pub fn add_extern_block(
+        &mut self,
+        item_block: &ItemExternalBlock,
+        cur_scope: &Scope,
+        addr_size: u32,
+    ) -> Result<(), RccError> {
+        let c_type = |t: &TypeAnnotation| -> Result<(IRType, Vec<(u32, IRType)>), RccError> {
+            let type_info = TypeInfo::from_type_anno(t, cur_scope);
+            let ir_type = ir_type_of(&type_info, cur_scope, addr_size)?;
+            let fields = if ir_type.is_aggregate() {
+                scalar_fields(&type_info, cur_scope, addr_size)?
+            } else {
+                vec![]
+            };
+            Ok((ir_type, fields))
+        };
+        for item in item_block.external_items.iter() {
+            match item {
+                ExternalItem::Fn(f) => {
+                    let mut param_types = vec![];
+                    let mut param_fields = vec![];
+                    for t in f.params().iter() {
+                        let (ir_type, fields) = c_type(t)?;
+                        param_types.push(ir_type);
+                        param_fields.push(fields);
+                    }
+                    let (ret_type, ret_fields) = c_type(&f.ret_type)?;
+                    self.extern_fns.push(ExternFn {
+                        name: f.name(),
+                        param_types,
+                        ret_type,
+                        param_fields,
+                        ret_fields,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Declares `extern_fn` once for the functions provided by the runtime,
+    /// returns the callee operand.
+    pub fn add_extern_fn(&mut self, extern_fn: ExternFn) -> Operand {
+        let callee = Operand::FnLabel(extern_fn.name.clone());
+        if !self.extern_fns.iter().any(|f| f.name == extern_fn.name) {
+            self.extern_fns.push(extern_fn);
+        }
+        callee
+    }
+
     pub fn add_ro_local_str(&mut self, s: String) -> Operand {
         let label = format!(".LC{}", self.ro_local_strs.len());
         self.ro_local_strs.insert(label.clone(), s);
         Operand::Place(Place::lit_const(label, IRType::Char))
     }
 
-    pub fn add_func(&mut self, item_fn: &ItemFn) -> Result<(), RccError> {
+    /// Structs are passed by the addresses of their copies, and returned by
+    /// storing to the address passed as the hidden first argument `$ret`.
+    pub fn add_func(
+        &mut self,
+        item_fn: &ItemFn,
+        mut ret_type: IRType,
+        addr_size: u32,
+    ) -> Result<(), RccError> {
         let fn_name = item_fn.name.clone();
         let is_global = item_fn.vis() == Visibility::Pub;
 
@@ -34,17 +106,27 @@ impl LinearIR {
         debug_assert_ne!(0, scope_id);
 
         let mut fn_args = Vec::new();
-        for param in item_fn.fn_params.params.iter() {
+        if ret_type.is_aggregate() {
+            fn_args.push((FN_RET_VAR.to_string(), IRType::Addr));
+            ret_type = IRType::Unit;
+        }
+        for (i, param) in item_fn.fn_params.params.iter().enumerate() {
+            let type_info = TypeInfo::from_type_anno(&param._type, scope);
+            let ir_type = ir_type_of(&type_info, scope, addr_size)?;
             fn_args.push(match &param.pattern {
-                Pattern::Identifier(i) => {
-                    let (var_info, _) = scope.find_variable(i.ident()).unwrap();
-                    (i.ident().to_string(), IRType::from_var_info(var_info)?)
+                Pattern::Identifier(p) if !p.is_ref() && !ir_type.is_aggregate() => {
+                    (p.ident().to_string(), ir_type)
                 }
+                // the variables in other patterns are bound at the beginning of the function
+                _ if ir_type.is_aggregate() => (fn_param_var(i), IRType::Addr),
+                _ => (fn_param_var(i), ir_type),
             });
         }
 
-        self.funcs
-            .push(Func::new(fn_name, is_global, fn_args, scope_id));
+        let mut func = Func::new(fn_name, is_global, fn_args, ret_type, scope_id);
+        func.line = item_fn.fn_block.line;
+        self.funcs.push(func);
+        self.cur_line = item_fn.fn_block.line;
         Ok(())
     }
 
@@ -53,7 +135,14 @@ impl LinearIR {
     }
 
     pub fn add_instructions(&mut self, ir_inst: IRInst) {
-        self.cur_func_mut().insts.push_back(ir_inst);
+        let line = self.cur_line;
+        let func = self.cur_func_mut();
+        func.insts.push_back(ir_inst);
+        func.lines.push_back(line);
+    }
+
+    pub fn set_line(&mut self, line: u32) {
+        self.cur_line = line;
     }
 
     /// Start from 1
@@ -69,9 +158,14 @@ impl LinearIR {
 pub struct Func {
     pub name: String,
     pub insts: VecDeque<IRInst>,
+    /// source line of each instruction in `insts`, 0 if unknown
+    pub lines: VecDeque<u32>,
     pub is_global: bool,
     pub fn_args: Vec<(String, IRType)>,
+    pub ret_type: IRType,
     pub block_scope_id: u64,
+    /// source line of the function block, 0 if unknown
+    pub line: u32,
 }
 
 impl Func {
@@ -79,14 +173,41 @@ impl Func {
         name: String,
         is_global: bool,
         fn_args: Vec<(String, IRType)>,
+        ret_type: IRType,
         block_scope_id: u64,
     ) -> Func {
         Func {
             name,
             insts: VecDeque::new(),
+            lines: VecDeque::new(),
             is_global,
             fn_args,
+            ret_type,
             block_scope_id,
+            line: 0,
         }
     }
 }
+
+/// A `static` item living in the data sections, which is accessed by its address.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StaticData {
+    pub label: String,
+    /// type of the values, the elems of arrays are flattened
+    pub elem_type: IRType,
+    /// initial values evaluated at compile time
+    pub values: Vec<Operand>,
+    pub is_mut: bool,
+}
+
+/// Signature of a function defined outside of the current file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExternFn {
+    pub name: String,
+    pub param_types: Vec<IRType>,
+    pub ret_type: IRType,
+    /// scalar fields of the aggregate params at their offsets, empty for the other params
+    pub param_fields: Vec<Vec<(u32, IRType)>>,
+    /// scalar fields of the aggregate return value at their offsets
+    pub ret_fields: Vec<(u32, IRType)>,
+}
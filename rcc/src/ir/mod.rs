@@ -3,7 +3,7 @@ use std::fmt::Debug;
 use std::ops::Deref;
 
 use crate::analyser::sym_resolver::{TypeInfo, VarInfo, VarKind};
-use crate::ast::expr::BinOperator;
+use crate::ast::expr::{BinOperator, LitNumExpr, UnOp};
 use crate::ast::types::TypeLitNum;
 use crate::ir::var_name::{is_temp_var, local_var};
 use crate::rcc::RccError;
@@ -11,10 +11,15 @@ use crate::rcc::RccError;
 pub mod cfg;
 mod dataflow;
 pub mod ir_build;
-mod linear_ir;
+pub mod layout;
+pub mod linear_ir;
 pub(crate) mod tests;
 pub mod var_name;
 
+/// Conditions of `JumpIfCond`, the jump is taken if `src1 cond src2` is true.
+///
+/// Floats are compared like `==`, `!=`, `<` and `>=` of Rust, so only `JNe` is true
+/// if any operand is NaN.
 #[derive(Debug, PartialEq)]
 pub enum Jump {
     JEq,
@@ -53,8 +58,11 @@ impl Operand {
         match self {
             Self::Unit | Self::Never => 0,
             Self::Bool(_) | Self::Char(_)| Self::I8(_) | Self::U8(_) => 1,
-            Self::I32(_) | Self::U32(_) => 4,
-            Self::I64(_) | Self::U64(_) => 8,
+            Self::I16(_) | Self::U16(_) => 2,
+            Self::I32(_) | Self::U32(_) | Self::F32(_) => 4,
+            Self::I64(_) | Self::U64(_) | Self::F64(_) => 8,
+            Self::I128(_) | Self::U128(_) => 16,
+            Self::Isize(_) | Self::Usize(_) => addr_size / 8,
             Self::Place(p) => p.ir_type.byte_size(addr_size),
             Self::FnRetPlace(ir_type) => ir_type.byte_size(addr_size),
             _ => unimplemented!("{:?}", self),
@@ -152,6 +160,8 @@ pub enum IRType {
     Never,
     /// address
     Addr,
+    /// structs which are kept in memory
+    Aggregate { size: u32, align: u32 },
 }
 
 impl IRType {
@@ -167,9 +177,22 @@ impl IRType {
                 addr_size / 8
             }
             IRType::Unit | IRType::Never => 0,
+            IRType::Aggregate { size, .. } => *size,
         }
     }
 
+    /// Values are aligned to their size up to 8 bytes, like `i64` and `f64` of the psABI.
+    pub fn align(&self, addr_size: u32) -> u32 {
+        match self {
+            IRType::Aggregate { align, .. } => *align,
+            t => t.byte_size(addr_size).clamp(1, 8),
+        }
+    }
+
+    pub fn is_aggregate(&self) -> bool {
+        matches!(self, IRType::Aggregate { .. })
+    }
+
     pub fn from_type_info(type_info: &TypeInfo) -> Result<IRType, RccError> {
         let ir_type = match type_info {
             TypeInfo::LitNum(num) => match num {
@@ -238,18 +261,60 @@ pub enum IRInst {
         label: usize,
     },
 
+    /// goto labels[src - low] if low <= src < low + labels.len(), else goto default
+    JumpTable {
+        src: Operand,
+        low: i64,
+        labels: Vec<usize>,
+        default: usize,
+    },
+
     /// dest = src
     LoadData {
         dest: Place,
         src: Operand,
     },
 
-    /// dest = *symbol
+    /// dest = src as dest.ir_type
+    ///
+    /// Integers are truncated or extended by their signedness, floating-point numbers
+    /// are converted to integers rounding towards zero and saturating, `NaN` is `0`.
+    Cast {
+        dest: Place,
+        src: Operand,
+    },
+
+    /// dest = -src of floating-point numbers, only the sign bit is flipped
+    /// (integers are negated by `0 - src`)
+    Neg {
+        dest: Place,
+        src: Operand,
+    },
+
+    /// dest = &symbol
     LoadAddr {
         dest: Place,
         symbol: Operand,
     },
 
+    /// dest = *(base + offset)
+    ///
+    /// `base` is an address, or an aggregate place for accessing its own memory.
+    LoadMem {
+        dest: Place,
+        base: Operand,
+        offset: u32,
+    },
+
+    /// *(base + offset) = src
+    ///
+    /// `base` is an address, or an aggregate place for accessing its own memory.
+    StoreMem {
+        base: Operand,
+        offset: u32,
+        src: Operand,
+    },
+
     Call {
         callee: Operand,
         args: Vec<Operand>,
@@ -282,6 +347,26 @@ impl IRInst {
         IRInst::LoadData { dest, src }
     }
 
+    pub fn cast(dest: Place, src: Operand) -> IRInst {
+        IRInst::Cast { dest, src }
+    }
+
+    pub fn neg(dest: Place, src: Operand) -> IRInst {
+        IRInst::Neg { dest, src }
+    }
+
+    pub fn load_addr(dest: Place, symbol: Operand) -> IRInst {
+        IRInst::LoadAddr { dest, symbol }
+    }
+
+    pub fn load_mem(dest: Place, base: Operand, offset: u32) -> IRInst {
+        IRInst::LoadMem { dest, base, offset }
+    }
+
+    pub fn store_mem(base: Operand, offset: u32, src: Operand) -> IRInst {
+        IRInst::StoreMem { base, offset, src }
+    }
+
     pub fn jump(label: usize) -> IRInst {
         IRInst::Jump { label }
     }
@@ -303,6 +388,15 @@ impl IRInst {
         }
     }
 
+    pub fn jump_table(src: Operand, low: i64, labels: Vec<usize>, default: usize) -> IRInst {
+        IRInst::JumpTable {
+            src,
+            low,
+            labels,
+            default,
+        }
+    }
+
     pub fn call(callee: Operand, args: Vec<Operand>) -> IRInst {
         IRInst::Call { callee, args }
     }
@@ -345,6 +439,7 @@ pub enum StrKind {
 
 /// Constant fold optimization.
 /// a = 2 * 3 -> a = 6
+/// `isize` and `usize` are folded as 32-bit integers, the size of them on the target.
 /// TODO other primitive type
 pub fn bin_op_may_constant_fold(
     op: &BinOperator,
@@ -366,6 +461,9 @@ pub fn bin_op_may_constant_fold(
                     Some(res) => res,
                     None => return Err("mul overflow".into()),
                 })),
+                BinOperator::Slash | BinOperator::Percent if *$r == 0 => {
+                    return Err("attempt to divide by zero".into())
+                }
                 BinOperator::Slash => Some($i(match $l.checked_div(*$r) {
                     Some(res) => res,
                     None => return Err("div overflow".into()),
@@ -376,14 +474,6 @@ pub fn bin_op_may_constant_fold(
                 BinOperator::Ge => Some(Operand::Bool($l >= $r)),
                 BinOperator::Ne => Some(Operand::Bool($l != $r)),
                 BinOperator::EqEq => Some(Operand::Bool($l == $r)),
-                BinOperator::Shl => Some($i(match $l.checked_shl(*$r as u32) {
-                    Some(res) => res,
-                    None => return Err("shl overflow".into()),
-                })),
-                BinOperator::Shr => Some($i(match $l.checked_shr(*$r as u32) {
-                    Some(res) => res,
-                    None => return Err("shr overflow".into()),
-                })),
                 BinOperator::And => Some($i($l & $r)),
                 BinOperator::Or => Some($i($l | $r)),
                 BinOperator::Caret => Some($i($l ^ $r)),
@@ -395,10 +485,232 @@ pub fn bin_op_may_constant_fold(
             }
         };
     }
+    macro_rules! try_fold_shift {
+        ($i:path, $l:ident, $amount:ident) => {
+            match op {
+                BinOperator::Shl => $i(match $l.checked_shl($amount) {
+                    Some(res) => res,
+                    None => return Err("shl overflow".into()),
+                }),
+                _ => $i(match $l.checked_shr($amount) {
+                    Some(res) => res,
+                    None => return Err("shr overflow".into()),
+                }),
+            }
+        };
+    }
+    macro_rules! try_fold_cmp {
+        ($l:ident, $r:ident) => {
+            match op {
+                BinOperator::Lt => Some(Operand::Bool($l < $r)),
+                BinOperator::Le => Some(Operand::Bool($l <= $r)),
+                BinOperator::Gt => Some(Operand::Bool($l > $r)),
+                BinOperator::Ge => Some(Operand::Bool($l >= $r)),
+                BinOperator::Ne => Some(Operand::Bool($l != $r)),
+                BinOperator::EqEq => Some(Operand::Bool($l == $r)),
+                _ => None,
+            }
+        };
+    }
+    macro_rules! try_fold_float {
+        ($f:path, $l:ident, $r:ident) => {
+            match op {
+                BinOperator::Plus => Some($f($l + $r)),
+                BinOperator::Minus => Some($f($l - $r)),
+                BinOperator::Star => Some($f($l * $r)),
+                BinOperator::Slash => Some($f($l / $r)),
+                BinOperator::Percent => Some($f($l % $r)),
+                _ => try_fold_cmp!($l, $r),
+            }
+        };
+    }
+    // the type of the shift amount may differ from the type of the shifted value
+    if matches!(op, BinOperator::Shl | BinOperator::Shr) && is_int_imm(src1) && is_int_imm(src2) {
+        let amount = match cast_may_constant_fold(src2, IRType::I128) {
+            Some(Operand::I128(amount)) if (0..=u32::MAX as i128).contains(&amount) => amount,
+            _ => {
+                let name = match op {
+                    BinOperator::Shl => "shl",
+                    _ => "shr",
+                };
+                return Err(format!("{} overflow", name).into());
+            }
+        } as u32;
+        return Ok(Some(match src1 {
+            Operand::I8(l) => try_fold_shift!(Operand::I8, l, amount),
+            Operand::I16(l) => try_fold_shift!(Operand::I16, l, amount),
+            Operand::I32(l) => try_fold_shift!(Operand::I32, l, amount),
+            Operand::I64(l) => try_fold_shift!(Operand::I64, l, amount),
+            Operand::I128(l) => try_fold_shift!(Operand::I128, l, amount),
+            Operand::Isize(l) => {
+                let l = *l as i32;
+                from_word(try_fold_shift!(Operand::I32, l, amount))
+            }
+            Operand::U8(l) => try_fold_shift!(Operand::U8, l, amount),
+            Operand::U16(l) => try_fold_shift!(Operand::U16, l, amount),
+            Operand::U32(l) => try_fold_shift!(Operand::U32, l, amount),
+            Operand::U64(l) => try_fold_shift!(Operand::U64, l, amount),
+            Operand::U128(l) => try_fold_shift!(Operand::U128, l, amount),
+            Operand::Usize(l) => {
+                let l = *l as u32;
+                from_word(try_fold_shift!(Operand::U32, l, amount))
+            }
+            _ => unreachable!(),
+        }));
+    }
     Ok(match (src1, src2) {
+        (Operand::Bool(l), Operand::Bool(r)) => match op {
+            BinOperator::And | BinOperator::AndAnd => Some(Operand::Bool(*l && *r)),
+            BinOperator::Or | BinOperator::OrOr => Some(Operand::Bool(*l || *r)),
+            BinOperator::Caret => Some(Operand::Bool(l ^ r)),
+            _ => try_fold_cmp!(l, r),
+        },
+        (Operand::Char(l), Operand::Char(r)) => try_fold_cmp!(l, r),
+        (Operand::F32(l), Operand::F32(r)) => try_fold_float!(Operand::F32, l, r),
+        (Operand::F64(l), Operand::F64(r)) => try_fold_float!(Operand::F64, l, r),
+        (Operand::I8(l), Operand::I8(r)) => try_fold_int!(Operand::I8, l, r),
+        (Operand::I16(l), Operand::I16(r)) => try_fold_int!(Operand::I16, l, r),
         (Operand::I32(l), Operand::I32(r)) => try_fold_int!(Operand::I32, l, r),
         (Operand::I64(l), Operand::I64(r)) => try_fold_int!(Operand::I64, l, r),
         (Operand::I128(l), Operand::I128(r)) => try_fold_int!(Operand::I128, l, r),
+        (Operand::Isize(l), Operand::Isize(r)) => {
+            let (l, r) = (&(*l as i32), &(*r as i32));
+            try_fold_int!(Operand::I32, l, r).map(from_word)
+        }
+        (Operand::U8(l), Operand::U8(r)) => try_fold_int!(Operand::U8, l, r),
+        (Operand::U16(l), Operand::U16(r)) => try_fold_int!(Operand::U16, l, r),
+        (Operand::U32(l), Operand::U32(r)) => try_fold_int!(Operand::U32, l, r),
+        (Operand::U64(l), Operand::U64(r)) => try_fold_int!(Operand::U64, l, r),
+        (Operand::U128(l), Operand::U128(r)) => try_fold_int!(Operand::U128, l, r),
+        (Operand::Usize(l), Operand::Usize(r)) => {
+            let (l, r) = (&(*l as u32), &(*r as u32));
+            try_fold_int!(Operand::U32, l, r).map(from_word)
+        }
         _ => None,
     })
 }
+
+/// The `isize` or `usize` of the 32-bit result folded for it.
+fn from_word(operand: Operand) -> Operand {
+    match operand {
+        Operand::I32(v) => Operand::Isize(v as isize),
+        Operand::U32(v) => Operand::Usize(v as usize),
+        operand => operand,
+    }
+}
+
+fn is_int_imm(operand: &Operand) -> bool {
+    operand.is_imm()
+        && !matches!(
+            operand,
+            Operand::Bool(_) | Operand::Char(_) | Operand::F32(_) | Operand::F64(_)
+        )
+}
+
+/// Constant fold of unary operators.
+/// -(2) -> -2, !0u8 -> 255u8, !true -> false
+pub fn un_op_may_constant_fold(op: &UnOp, src: &Operand) -> Result<Option<Operand>, RccError> {
+    macro_rules! neg {
+        ($i:path, $v:ident) => {
+            $i(match $v.checked_neg() {
+                Some(res) => res,
+                None => return Err("neg overflow".into()),
+            })
+        };
+    }
+    Ok(match (op, src) {
+        (UnOp::Neg, Operand::I8(v)) => Some(neg!(Operand::I8, v)),
+        (UnOp::Neg, Operand::I16(v)) => Some(neg!(Operand::I16, v)),
+        (UnOp::Neg, Operand::I32(v)) => Some(neg!(Operand::I32, v)),
+        (UnOp::Neg, Operand::I64(v)) => Some(neg!(Operand::I64, v)),
+        (UnOp::Neg, Operand::I128(v)) => Some(neg!(Operand::I128, v)),
+        (UnOp::Neg, Operand::Isize(v)) => {
+            let v = *v as i32;
+            Some(from_word(neg!(Operand::I32, v)))
+        }
+        (UnOp::Neg, Operand::F32(v)) => Some(Operand::F32(-v)),
+        (UnOp::Neg, Operand::F64(v)) => Some(Operand::F64(-v)),
+        (UnOp::Not, Operand::Bool(v)) => Some(Operand::Bool(!v)),
+        (UnOp::Not, Operand::I8(v)) => Some(Operand::I8(!v)),
+        (UnOp::Not, Operand::I16(v)) => Some(Operand::I16(!v)),
+        (UnOp::Not, Operand::I32(v)) => Some(Operand::I32(!v)),
+        (UnOp::Not, Operand::I64(v)) => Some(Operand::I64(!v)),
+        (UnOp::Not, Operand::I128(v)) => Some(Operand::I128(!v)),
+        (UnOp::Not, Operand::Isize(v)) => Some(Operand::Isize(!(*v as i32) as isize)),
+        (UnOp::Not, Operand::U8(v)) => Some(Operand::U8(!v)),
+        (UnOp::Not, Operand::U16(v)) => Some(Operand::U16(!v)),
+        (UnOp::Not, Operand::U32(v)) => Some(Operand::U32(!v)),
+        (UnOp::Not, Operand::U64(v)) => Some(Operand::U64(!v)),
+        (UnOp::Not, Operand::U128(v)) => Some(Operand::U128(!v)),
+        (UnOp::Not, Operand::Usize(v)) => Some(Operand::Usize(!(*v as u32) as usize)),
+        _ => None,
+    })
+}
+
+/// The value of a number literal whose type has been determined
+pub fn lit_num_operand(lit_num_expr: &LitNumExpr) -> Result<Operand, RccError> {
+    let value = &lit_num_expr.value;
+    Ok(match lit_num_expr.get_lit_type() {
+        TypeLitNum::I8 => Operand::I8(value.parse()?),
+        TypeLitNum::I16 => Operand::I16(value.parse()?),
+        TypeLitNum::I | TypeLitNum::I32 => Operand::I32(value.parse()?),
+        TypeLitNum::I64 => Operand::I64(value.parse()?),
+        TypeLitNum::I128 => Operand::I128(value.parse()?),
+        TypeLitNum::Isize => Operand::Isize(value.parse::<i32>()? as isize),
+        TypeLitNum::U8 => Operand::U8(value.parse()?),
+        TypeLitNum::U16 => Operand::U16(value.parse()?),
+        TypeLitNum::U32 => Operand::U32(value.parse()?),
+        TypeLitNum::U64 => Operand::U64(value.parse()?),
+        TypeLitNum::U128 => Operand::U128(value.parse()?),
+        TypeLitNum::Usize => Operand::Usize(value.parse::<u32>()? as usize),
+        TypeLitNum::F32 => Operand::F32(value.parse()?),
+        TypeLitNum::F | TypeLitNum::F64 => Operand::F64(value.parse()?),
+    })
+}
+
+/// Constant fold of `src as ir_type` by the casts of Rust.
+/// 1u8 as char -> '\u{1}', 300i32 as u8 -> 44u8, -1.5f64 as u32 -> 0u32
+pub fn cast_may_constant_fold(src: &Operand, ir_type: IRType) -> Option<Operand> {
+    macro_rules! cast_num {
+        ($v:expr) => {
+            match ir_type {
+                IRType::F32 => Operand::F32($v as f32),
+                IRType::F64 => Operand::F64($v as f64),
+                IRType::I8 => Operand::I8($v as i8),
+                IRType::I16 => Operand::I16($v as i16),
+                IRType::I32 => Operand::I32($v as i32),
+                IRType::I64 => Operand::I64($v as i64),
+                IRType::I128 => Operand::I128($v as i128),
+                IRType::Isize => Operand::Isize($v as i32 as isize),
+                IRType::U8 => Operand::U8($v as u8),
+                IRType::U16 => Operand::U16($v as u16),
+                IRType::U32 => Operand::U32($v as u32),
+                IRType::U64 => Operand::U64($v as u64),
+                IRType::U128 => Operand::U128($v as u128),
+                IRType::Usize => Operand::Usize($v as u32 as usize),
+                _ => return None,
+            }
+        };
+    }
+    Some(match (src, ir_type) {
+        (Operand::U8(v), IRType::Char) => Operand::Char(*v as char),
+        (Operand::Char(_), IRType::Char) | (Operand::Bool(_), IRType::Bool) => src.clone(),
+        (Operand::Bool(v), _) => cast_num!(*v as u8),
+        (Operand::Char(v), _) => cast_num!(*v as u32),
+        (Operand::F32(v), _) => cast_num!(*v),
+        (Operand::F64(v), _) => cast_num!(*v),
+        (Operand::I8(v), _) => cast_num!(*v),
+        (Operand::I16(v), _) => cast_num!(*v),
+        (Operand::I32(v), _) => cast_num!(*v),
+        (Operand::I64(v), _) => cast_num!(*v),
+        (Operand::I128(v), _) => cast_num!(*v),
+        (Operand::Isize(v), _) => cast_num!(*v),
+        (Operand::U8(v), _) => cast_num!(*v),
+        (Operand::U16(v), _) => cast_num!(*v),
+        (Operand::U32(v), _) => cast_num!(*v),
+        (Operand::U64(v), _) => cast_num!(*v),
+        (Operand::U128(v), _) => cast_num!(*v),
+        (Operand::Usize(v), _) => cast_num!(*v),
+        _ => return None,
+    })
+}
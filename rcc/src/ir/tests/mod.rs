@@ -195,6 +195,22 @@ fn max(a: i32, b: i32) -> i32 {
     assert_pretty_fmt_eq(&expected, &ir.funcs.pop().unwrap().insts);
 }
 
+#[test]
+fn test_logic() {
+    let mut ir = ir_build(r#"
+fn f(a: i32, b: bool) -> bool {
+    if a > 0 && (b || !(a == 5)) {
+        a < 10 || b
+    } else {
+        false
+    }
+}
+
+    "#).unwrap();
+    let expected = expected_from_file("test_logic_ir.txt");
+    assert_pretty_fmt_eq(&expected, &ir.funcs.pop().unwrap().insts);
+}
+
 #[test]
 fn test_loop() {
     let mut ir = ir_build(
@@ -248,6 +264,28 @@ fn test_while() {
     assert_eq!(expected, format!("{:#?}", ir.funcs.last().unwrap().insts));
 }
 
+#[test]
+fn test_for() {
+    let ir = ir_build(
+        r#"
+        fn main() {
+            let mut a = 0;
+            for i in 0..10 {
+                if i == 5 {
+                    break;
+                }
+                a += i;
+            }
+            for j in 1..=a {
+            }
+        }
+    "#,
+    )
+    .unwrap();
+    let expected = expected_from_file("test_for_ir.txt");
+    assert_eq!(expected, format!("{:#?}", ir.funcs.last().unwrap().insts));
+}
+
 #[test]
 fn fib10_test() {
     let mut ir = ir_build(
@@ -304,3 +342,73 @@ fn fn_call_test() {
         );
     }
 }
+
+#[test]
+fn test_match() {
+    let mut ir = ir_build(
+        r#"
+        fn chain(x: i32) -> i32 {
+            match x {
+                1 | 2 => 10,
+                3..=5 if x > 4 => 20,
+                _ => 30,
+            }
+        }
+
+        fn table(x: u8) -> i32 {
+            match x {
+                1 => 1,
+                2 | 4 => 2,
+                3 => 3,
+                6 => 4,
+                _ => 0,
+            }
+        }
+
+        const LOW: u8 = 1;
+        const HIGH: u8 = 255;
+        fn consts(x: u8) -> i32 {
+            match x {
+                LOW => 1,
+                HIGH => 2,
+                _ => 3,
+            }
+        }
+    "#,
+    )
+    .unwrap();
+    // the constants are compared with the scrutinee instead of being bound
+    let consts = ir.funcs.pop().unwrap();
+    let tests: Vec<&crate::ir::Operand> = consts
+        .insts
+        .iter()
+        .filter_map(|inst| match inst {
+            IRInst::JumpIfCond {
+                cond: crate::ir::Jump::JNe,
+                src2,
+                ..
+            } => Some(src2),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(vec![&crate::ir::Operand::U8(1), &crate::ir::Operand::U8(255)], tests);
+    let table = ir.funcs.pop().unwrap();
+    match table.insts.front().unwrap() {
+        IRInst::JumpTable {
+            low,
+            labels,
+            default,
+            ..
+        } => {
+            assert_eq!(1, *low);
+            let arms: Vec<usize> = labels
+                .iter()
+                .map(|l| [2, 4, 6, 8, *default].iter().position(|a| a == l).unwrap())
+                .collect();
+            assert_eq!(vec![0, 1, 2, 1, 4, 3], arms);
+        }
+        inst => panic!("expected jump table, found {:?}", inst),
+    }
+    let expected = expected_from_file("test_match_ir.txt");
+    assert_eq!(expected, format!("{:#?}", ir.funcs.pop().unwrap().insts));
+}
@@ -9,10 +9,22 @@ pub fn temp_local_var(temp_count: u64, scope_id: u64) -> String {
     format!("${}_{}", temp_count, scope_id)
 }
 
+/// Name of the `index`th param of a function which is not bound to an identifier
+pub fn fn_param_var(index: usize) -> String {
+    format!("$param{}", index)
+}
+
+/// Name of the hidden argument of a function returning a struct,
+/// which is the address to store the returned value
+pub const FN_RET_VAR: &str = "$ret";
+
 pub fn is_temp_var(var_name: &str) -> bool {
     var_name.starts_with('$')
 }
 
 pub fn branch_name(func_scope_id: u64, bb_id: usize) -> String{
     format!(".L{}_{}",  func_scope_id,bb_id)
-}
\ No newline at end of file
+}
+pub fn jump_table_name(func_scope_id: u64, bb_id: usize) -> String {
+    format!(".LJT{}_{}", func_scope_id, bb_id)
+}
@@ -31,20 +31,35 @@ impl<'a: 'b, 'b> Lexer<'a> {
     }
 
     pub fn tokenize(&'b mut self) -> Vec<Token<'a>> {
+        self.tokenize_with_lines().0
+    }
+
+    /// Tokenize the input and return the line number (start from 1) of each token.
+    pub fn tokenize_with_lines(&'b mut self) -> (Vec<Token<'a>>, Vec<u32>) {
         let mut tokens = vec![];
+        let mut lines = vec![];
+        let mut line = 1;
+        let mut line_counted = 0;
         while !self.cursor.is_eof() {
+            let start = self.cursor.eaten_len();
+            line += self.input[line_counted..start].matches('\n').count() as u32;
+            line_counted = start;
             let token = self.advance_token();
 
             match token {
                 Unknown => {
                     tokens.push(Unknown);
+                    lines.push(line);
                     break;
                 }
                 WhiteSpace | Comment => {}
-                _ => tokens.push(token)
+                _ => {
+                    tokens.push(token);
+                    lines.push(line);
+                }
             }
         }
-        tokens
+        (tokens, lines)
     }
 
     fn advance_token(&'b mut self) -> Token<'a> {
@@ -59,7 +74,7 @@ impl<'a: 'b, 'b> Lexer<'a> {
                 Token::from_str(&c.to_string()).unwrap()
             }
             '0'..='9' => self.integer_or_float_literal(),
-            '\'' => self.char_literal(self.cursor.eaten_len()),
+            '\'' => self.char_literal_or_lifetime(self.cursor.eaten_len()),
             '"' => self.string_literal(self.cursor.eaten_len()),
             c if "+*%^!".contains(c) => {
                 static TABLE: [[Token; 5]; 2] = [
@@ -229,12 +244,8 @@ impl<'a: 'b, 'b> Lexer<'a> {
                         self.digits_with_underscore(start, radix, Self::make_integer)
                     }
 
-                    // 001 01.23
-                    '0'..='9' => self.decimal_or_float_literal_no_prefix(start),
-                    _ => Literal {
-                        literal_kind: self.make_integer(),
-                        value: "0",
-                    },
+                    // 001 01.23 0.5 0f32
+                    _ => self.decimal_or_float_literal_no_prefix(start),
                 }
             }
             '1'..='9' => self.decimal_or_float_literal_no_prefix(start),
@@ -381,10 +392,15 @@ impl<'a: 'b, 'b> Lexer<'a> {
         }
     }
 
-    fn char_literal(&'b mut self, start: usize) -> Token<'a> {
+    fn char_literal_or_lifetime(&'b mut self, start: usize) -> Token<'a> {
         debug_assert!(self.cursor.next() == '\'');
         self.cursor.bump();
 
+        // 'label, but not 'a'
+        if is_id_start(self.cursor.next()) && self.cursor.nth(1) != '\'' {
+            self.cursor.eat_id();
+            return Lifetime(&self.input[start..self.cursor.eaten_len()]);
+        }
         // ''
         if self.cursor.next() == '\'' {
             Unknown
@@ -199,6 +199,25 @@ mod lexer_tests {
         );
     }
 
+    #[test]
+    fn lifetime_test() {
+        validate_tokenize(
+            vec!["'outer: loop", "break 'a 'b'", "continue '_x;"],
+            vec![
+                vec![Lifetime("'outer"), Colon, Loop],
+                vec![
+                    Break,
+                    Lifetime("'a"),
+                    Literal {
+                        literal_kind: Char,
+                        value: "'b'",
+                    },
+                ],
+                vec![Continue, Lifetime("'_x"), Semi],
+            ],
+        );
+    }
+
     #[test]
     fn and_or_test() {
         validate_tokenize(
@@ -325,6 +344,26 @@ mod lexer_tests {
             vec![vec![Lt, Le, Shl, ShlEq, Gt, Ge, Shr, ShrEq], vec![Shl, Lt]],
         );
     }
+
+    #[test]
+    fn source_text_test() {
+        let input = r#"fn f<T: A + B>(x: &mut T) -> u8 { 'a: loop { g(1u8, 2.5f32, 0x1f, 'c', "s\n") >> 1; } }"#;
+        let tokens = Lexer::new(input).tokenize();
+        let source: Vec<String> = tokens.iter().map(Token::source_text).collect();
+        let source = source.join(" ");
+        assert_eq!(tokens, Lexer::new(&source).tokenize());
+    }
+
+    #[test]
+    fn line_test() {
+        let mut lexer = Lexer::new("fn main() {\n    /* a\n b */ let a = 1;\n\n    a\n}");
+        let (tokens, lines) = lexer.tokenize_with_lines();
+        assert_eq!(tokens.len(), lines.len());
+        assert_eq!(
+            vec![1, 1, 1, 1, 1, 3, 3, 3, 3, 3, 5, 6],
+            lines
+        );
+    }
 }
 
 mod token_tests {
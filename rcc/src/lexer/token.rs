@@ -4,6 +4,7 @@ use strenum::StrEnum;
 #[derive(Clone, Debug, PartialEq, StrEnum)]
 pub enum Token<'a> {
     /// Strict keywords
+    #[strenum("as")]
     As,
     Break,
     Const,
@@ -80,6 +81,11 @@ pub enum Token<'a> {
     #[strenum(disabled)]
     LitString(&'a str),
 
+    /// 'label
+    /// field 0 contains quote
+    #[strenum(disabled)]
+    Lifetime(&'a str),
+
     /// Symbols
     #[strenum("+")]
     Plus,
@@ -246,6 +252,19 @@ impl Token<'_> {
     pub fn is_range_op(&self) -> bool {
         matches!(self, Self::DotDot | Self::DotDotEq)
     }
+
+    /// The source code of the token, lexing it again gives the same token.
+    pub fn source_text(&self) -> String {
+        match self {
+            Self::Identifier(s) | Self::LitString(s) | Self::Lifetime(s) => s.to_string(),
+            Self::Literal {
+                literal_kind: Integer { suffix } | Float { suffix },
+                value,
+            } => format!("{}{}", value, suffix),
+            Self::Literal { value, .. } => value.to_string(),
+            tk => tk.to_string(),
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -1,6 +1,6 @@
 #![feature(map_first_last)]
 
-use crate::rcc::{OptimizeLevel, RcCompiler, RccError};
+use crate::rcc::{Emit, OptimizeLevel, RcCompiler, RccError};
 use clap::Clap;
 use code_gen::TargetPlatform;
 use std::str::FromStr;
@@ -16,7 +16,7 @@ mod tests;
 
 #[derive(Clap)]
 struct Opts {
-    /// output asm file
+    /// output asm (or C source) file instead of executable file
     #[clap(short = 'S')]
     output_asm: bool,
     /// input file
@@ -27,23 +27,78 @@ struct Opts {
     /// target platform
     #[clap(short = 't', default_value = "riscv32")]
     target: String,
+    /// kind of output: asm, obj, exe
+    #[clap(long = "emit")]
+    emit: Option<String>,
+    /// generate source line and call frame debug information, only with `-S`
+    #[clap(short = 'g')]
+    debug_info: bool,
+    /// lower floating-point operations into calls of the runtime for targets without F/D extensions
+    #[clap(long = "soft-float")]
+    soft_float: bool,
+    /// elide the bounds checks of indexing arrays, out-of-bounds indices are undefined behavior
+    #[clap(long = "no-bounds-check")]
+    no_bounds_check: bool,
+    /// optimize level: 0, 1
+    #[clap(short = 'O', default_value = "0")]
+    opt_level: String,
 }
 
 fn compile(opts: Opts) -> Result<(), RccError> {
     match TargetPlatform::from_str(&opts.target) {
         Ok(target_platform) => {
-            let input = std::fs::File::open(opts.input)?;
-            let output = std::fs::File::create(opts.output)?;
-            // TODO: set opt level
+            let emit = match &opts.emit {
+                Some(emit) => match Emit::from_str(emit) {
+                    Ok(emit) => emit,
+                    Err(_) => return Err(format!("invalid emit kind {}", emit).into()),
+                },
+                None => match target_platform {
+                    TargetPlatform::Riscv32 if !opts.output_asm => Emit::Exe,
+                    _ => Emit::Asm,
+                },
+            };
+            let opt_level = match opts.opt_level.as_str() {
+                "0" => OptimizeLevel::Zero,
+                "1" => OptimizeLevel::One,
+                _ => return Err(format!("invalid optimize level {}", opts.opt_level).into()),
+            };
+            let input = std::fs::File::open(&opts.input)?;
+            let output = std::fs::File::create(&opts.output)?;
             let mut rc_compiler =
-                RcCompiler::new(target_platform, input, output, OptimizeLevel::Zero);
+                RcCompiler::new(target_platform, input, output, opt_level).with_emit(emit);
+            if opts.debug_info {
+                rc_compiler = rc_compiler.with_debug_info(&opts.input);
+            }
+            if opts.soft_float {
+                rc_compiler = rc_compiler.with_soft_float();
+            }
+            if opts.no_bounds_check {
+                rc_compiler = rc_compiler.with_no_bounds_check();
+            }
             rc_compiler.compile()?;
+            if emit == Emit::Exe {
+                set_executable(&opts.output)?;
+            }
             Ok(())
         }
-        Err(_) => Err(format!("invalid target platform {}", opts.input).into()),
+        Err(_) => Err(format!("invalid target platform {}", opts.target).into()),
     }
 }
 
+#[cfg(unix)]
+fn set_executable(path: &str) -> Result<(), RccError> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o111);
+    std::fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &str) -> Result<(), RccError> {
+    Ok(())
+}
+
 fn main() {
     let opts = Opts::parse();
     if let Err(e) = compile(opts) {
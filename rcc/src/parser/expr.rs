@@ -14,15 +14,18 @@ impl Parse for Expr {
 
 /// Expression having precedences
 pub mod prec {
-    use crate::ast::expr::Expr::{ArrayIndex, Assign, Call, FieldAccess, Range, Unary};
+    use crate::ast::expr::Expr::{
+        ArrayIndex, Assign, Call, FieldAccess, MethodCall, Range, TupleIndex, Unary,
+    };
     use crate::ast::expr::UnOp::{Borrow, BorrowMut};
     use crate::ast::expr::{
-        ArrayIndexExpr, AssignExpr, BinOpExpr, BinOperator, CallExpr, CallParams, Expr,
-        FieldAccessExpr, LhsExpr, Precedence, RangeExpr, UnAryExpr, UnOp,
+        ArrayIndexExpr, AssignExpr, BinOpExpr, BinOperator, CallExpr, CallParams, CastExpr, Expr,
+        FieldAccessExpr, LhsExpr, MethodCallExpr, Precedence, RangeExpr, TupleIndexExpr, UnAryExpr, UnOp,
     };
+    use crate::ast::types::TypeAnnotation;
     use crate::ast::FromToken;
     use crate::ast::TokenStart;
-    use crate::lexer::token::Token;
+    use crate::lexer::token::{LiteralKind, Token};
     use crate::parser::expr::primitive::primitive_expr;
     use crate::parser::{Parse, ParseCursor};
     use crate::rcc::RccError;
@@ -87,7 +90,7 @@ pub mod prec {
     }
 
     /// Operator Precedence Parsing
-    /// as               left to right
+    /// as               left to right, the rhs is a type
     /// * / %            left to right
     /// + -              left to right
     /// << >>            left to right
@@ -120,6 +123,13 @@ pub mod prec {
         loop {
             if next_is_op {
                 match cursor.eat_token_if_from::<BinOperator>() {
+                    // `as` has the highest precedence, so it casts the last operand
+                    Some(BinOperator::As) => {
+                        let expr = exprs.pop().unwrap();
+                        let type_anno = TypeAnnotation::parse(cursor)?;
+                        exprs.push(Expr::Cast(CastExpr::new(expr, type_anno)));
+                        continue;
+                    }
                     Some(next_op) => {
                         if let Some(last_op) = bin_ops.last() {
                             // 1 + 2 * 3   <- -
@@ -184,7 +194,8 @@ pub mod prec {
     /// CallExpr -> PrimitiveExpr
     ///           | CallExpr `(` CallParams? `)`
     ///           | CallExpr ArrayIndexExpr
-    ///           | CallExpr `.` PrimitiveExpr
+    ///           | CallExpr `.` identifier
+    ///           | CallExpr `.` identifier `(` CallParams? `)`
     fn call_expr(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
         let mut expr = primitive_expr(cursor)?;
         while let Ok(tk) = cursor.next_token() {
@@ -194,20 +205,36 @@ pub mod prec {
                     let mut call_expr = CallExpr::new(expr);
 
                     if !cursor.eat_token_if_eq(Token::RightParen) {
-                        let call_params = CallParams::parse(cursor)?;
+                        let call_params = cursor.with_no_struct_expr(false, CallParams::parse)?;
                         cursor.eat_token_eq(Token::RightParen)?;
                         call_expr = call_expr.call_params(call_params);
                     }
                     Call(call_expr)
                 }
                 Token::LeftSquareBrackets => {
-                    let index_expr = ArrayIndexExpr::parse_index(cursor)?;
+                    let index_expr =
+                        cursor.with_no_struct_expr(false, ArrayIndexExpr::parse_index)?;
                     ArrayIndex(ArrayIndexExpr::new(expr, index_expr))
                 }
                 Token::Dot => {
                     cursor.bump_token()?;
-                    let rhs = primitive_expr(cursor)?;
-                    FieldAccess(FieldAccessExpr::new(expr, rhs))
+                    match cursor.next_token()? {
+                        Token::Literal { .. } => TupleIndexExpr::parse_from_lhs(cursor, expr)?,
+                        _ => {
+                            let field = cursor.eat_identifier()?.to_string();
+                            if cursor.eat_token_if_eq(Token::LeftParen) {
+                                let mut call_params = vec![];
+                                if !cursor.eat_token_if_eq(Token::RightParen) {
+                                    call_params =
+                                        cursor.with_no_struct_expr(false, CallParams::parse)?;
+                                    cursor.eat_token_eq(Token::RightParen)?;
+                                }
+                                MethodCall(MethodCallExpr::new(expr, field, call_params))
+                            } else {
+                                FieldAccess(FieldAccessExpr::new(expr, field))
+                            }
+                        }
+                    }
                 }
                 _ => return Ok(expr),
             }
@@ -228,6 +255,30 @@ pub mod prec {
         }
     }
 
+    /// TupleIndexExpr -> Expr `.` INTEGER_LITERAL,
+    /// `a.0.1` is lexed as the float literal `0.1`.
+    impl TupleIndexExpr {
+        fn parse_from_lhs(cursor: &mut ParseCursor, lhs: Expr) -> Result<Expr, RccError> {
+            let value = match cursor.eat_literal()? {
+                (LiteralKind::Integer { suffix: "" }, value) => value,
+                (LiteralKind::Float { suffix: "" }, value) => value,
+                (LiteralKind::Integer { suffix } | LiteralKind::Float { suffix }, value) => {
+                    return Err(format!("invalid tuple index `{}{}`", value, suffix).into())
+                }
+                (_, value) => return Err(format!("invalid tuple index `{}`", value).into()),
+            };
+            let mut expr = lhs;
+            for index in value.split('.') {
+                let index = match index.parse() {
+                    Ok(index) => index,
+                    Err(_) => return Err(format!("invalid tuple index `{}`", value).into()),
+                };
+                expr = TupleIndex(TupleIndexExpr::new(expr, index));
+            }
+            Ok(expr)
+        }
+    }
+
     /// ArrayIndexExpr -> `[` Expr `]`
     impl ArrayIndexExpr {
         fn parse_index(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
@@ -243,8 +294,9 @@ pub mod prec {
 pub mod primitive {
     use std::str::FromStr;
 
-    use crate::ast::expr::Expr::{Array, Block, If, LitBool, LitNum, Loop, Path, While};
+    use crate::ast::expr::Expr::{Array, Block, For, If, LitBool, LitNum, Loop, Path, While};
     use crate::ast::expr::*;
+    use crate::ast::pattern::Pattern;
     use crate::ast::stmt::Stmt;
     use crate::ast::types::TypeLitNum;
     use crate::ast::TokenStart;
@@ -252,27 +304,40 @@ pub mod primitive {
     use crate::lexer::token::Token;
     use crate::parser::expr::prec::range_expr;
     use crate::parser::stmt::{parse_stmt_or_expr_without_block, StmtOrExpr};
+    use crate::parser::types::parse_generic_args;
     use crate::parser::{Parse, ParseCursor};
     use crate::rcc::RccError;
 
     /// PrimitiveExpr -> PathExpr | LitExpr | LitChar | LitStr | LitBool | BlockExpr
-    ///                | GroupedExpr | TupleExpr | ArrayExpr
-    ///                | ReturnExpr | BreakExpr
+    ///                | GroupedExpr | TupleExpr | ArrayExpr | StructExpr
+    ///                | ReturnExpr | BreakExpr | ContinueExpr
     ///                | RangeExpr(without lhs)
     pub fn primitive_expr(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
         let expr = match cursor.next_token()? {
-            Token::Identifier(_) | Token::PathSep => Path(PathExpr::parse(cursor)?),
+            Token::Identifier(_) | Token::PathSep | Token::SelfValue | Token::SelfType => {
+                let path_expr = PathExpr::parse(cursor)?;
+                if !cursor.no_struct_expr && cursor.next_token() == Ok(&Token::LeftCurlyBraces) {
+                    Expr::Struct(StructExpr::parse_from_path(cursor, path_expr)?)
+                } else {
+                    Path(path_expr)
+                }
+            }
             Token::Literal { .. } => parse_literal(cursor)?,
             Token::LitString(_) => Expr::LitStr(parse_lit_string(cursor)?),
             Token::True | Token::False => LitBool(*cursor.bump_token()? == Token::True),
             Token::LeftCurlyBraces => Block(BlockExpr::parse(cursor)?),
+            Token::Unsafe => Block(parse_unsafe_block(cursor)?),
             Token::LeftParen => parse_grouped_or_tuple_expr(cursor)?,
-            Token::LeftSquareBrackets => Array(ArrayExpr::parse(cursor)?),
+            Token::LeftSquareBrackets => Array(cursor.with_no_struct_expr(false, ArrayExpr::parse)?),
             Token::While => While(WhileExpr::parse(cursor)?),
             Token::Loop => Loop(LoopExpr::parse(cursor)?),
+            Token::For => For(ForExpr::parse(cursor)?),
             Token::If => If(IfExpr::parse(cursor)?),
+            Token::Match => Expr::Match(MatchExpr::parse(cursor)?),
             Token::Return => Expr::Return(ReturnExpr::parse(cursor)?),
             Token::Break => Expr::Break(BreakExpr::parse(cursor)?),
+            Token::Continue => Expr::Continue(ContinueExpr::parse(cursor)?),
+            Token::Lifetime(_) => parse_labeled_loop(cursor)?,
             Token::DotDot | Token::DotDotEq => range_expr(cursor)?,
             _ => unreachable!(),
         };
@@ -281,7 +346,14 @@ pub mod primitive {
 
     /// GroupedExpr | TupleExpr
     fn parse_grouped_or_tuple_expr(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
+        cursor.with_no_struct_expr(false, parse_grouped_or_tuple_expr_inner)
+    }
+
+    fn parse_grouped_or_tuple_expr_inner(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
         cursor.eat_token_eq(Token::LeftParen)?;
+        if cursor.eat_token_if_eq(Token::RightParen) {
+            return Ok(Expr::Tuple(TupleExpr::new(vec![])));
+        }
         let expr = Expr::parse(cursor)?;
         match cursor.next_token()? {
             Token::RightParen => {
@@ -293,13 +365,13 @@ pub mod primitive {
         }
     }
 
-    ///  TupleExpr -> `(` ( Expr , )+ Expr? `)`
+    ///  TupleExpr -> `(` `)` | `(` ( Expr , )+ Expr? `)`
     impl TupleExpr {
         fn parse_from_second(cursor: &mut ParseCursor, first_expr: Expr) -> Result<Self, RccError> {
             cursor.eat_token_eq(Token::Comma)?;
-            let mut tuple_expr = TupleExpr(vec![first_expr]);
+            let mut tuple_expr = TupleExpr::new(vec![first_expr]);
             while cursor.next_token()? != &Token::RightParen {
-                tuple_expr.0.push(Expr::parse(cursor)?);
+                tuple_expr.elems.push(Expr::parse(cursor)?);
                 if !cursor.eat_token_if_eq(Token::Comma) {
                     break;
                 }
@@ -312,9 +384,45 @@ pub mod primitive {
         }
     }
 
-    /// PathExpr -> identifier (:: identifier)*
+    /// StructExpr -> PathExpr `{` ( StructExprField ( , StructExprField )* ,? )? `}`
+    ///             | PathExpr `{` ( StructExprField , )* `..` Expr `}`
+    ///
+    /// StructExprField -> identifier ( `:` Expr )?
+    impl StructExpr {
+        fn parse_from_path(cursor: &mut ParseCursor, path_expr: PathExpr) -> Result<Self, RccError> {
+            cursor.eat_token_eq(Token::LeftCurlyBraces)?;
+            cursor.with_no_struct_expr(false, |cursor| {
+                let mut fields = vec![];
+                let mut base = None;
+                while !cursor.eat_token_if_eq(Token::RightCurlyBraces) {
+                    if cursor.eat_token_if_eq(Token::DotDot) {
+                        base = Some(Expr::parse(cursor)?);
+                        cursor.eat_token_eq(Token::RightCurlyBraces)?;
+                        break;
+                    }
+                    let name = cursor.eat_identifier()?.to_string();
+                    let expr = if cursor.eat_token_if_eq(Token::Colon) {
+                        Expr::parse(cursor)?
+                    } else {
+                        Path(vec![name.clone()].into())
+                    };
+                    fields.push(StructExprField::new(name, expr));
+                    if !cursor.eat_token_if_eq(Token::Comma) {
+                        cursor.eat_token_eq(Token::RightCurlyBraces)?;
+                        break;
+                    }
+                }
+                let path = path_expr.segments.join("::");
+                Ok(StructExpr::new(path, fields, base).type_args(path_expr.type_args))
+            })
+        }
+    }
+
+    /// PathExpr -> PathSegment (:: PathSegment)* ( :: GenericArgs )?
+    ///
+    /// PathSegment -> identifier | `self` | `Self`
     /// # Examples
-    /// `a::b::c`, `a`
+    /// `a::b::c`, `a`, `Self::new`, `max::<i32>`
     impl Parse for PathExpr {
         fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
             #[derive(PartialEq)]
@@ -332,14 +440,24 @@ pub mod primitive {
                         if state == State::PathSep || state == State::Init {
                             return Err("invalid path".into());
                         }
+                        // the type arguments end the path
+                        if cursor.nth_token(1) == Ok(&Token::Lt) {
+                            cursor.bump_token()?;
+                            path_expr.type_args = parse_generic_args(cursor)?;
+                            return Ok(path_expr);
+                        }
                         state = State::PathSep;
                     }
-                    Token::Identifier(s) => {
+                    Token::Identifier(_) | Token::SelfValue | Token::SelfType => {
                         if state == State::Segment {
                             return Err("invalid path".into());
                         }
                         state = State::Segment;
-                        path_expr.segments.push(s.to_string());
+                        let segment = match tk {
+                            Token::Identifier(s) => s.to_string(),
+                            tk => tk.to_string(),
+                        };
+                        path_expr.segments.push(segment);
                     }
                     _ => break,
                 }
@@ -388,50 +506,74 @@ pub mod primitive {
     /// BlockExpr -> `{` Stmt* Expr(without block)? `}`
     impl Parse for BlockExpr {
         fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
-            cursor.eat_token_eq(Token::LeftCurlyBraces)?;
-            let mut block_expr = BlockExpr::new(cursor.scope_count);
-            cursor.scope_count += 1;
-            while cursor.next_token()? != &Token::RightCurlyBraces {
-                match parse_stmt_or_expr_without_block(cursor)? {
-                    StmtOrExpr::Stmt(stmt) => {
-                        if let crate::ast::stmt::Stmt::Item(item) = &stmt {
-                            block_expr.scope.add_typedef(item);
-                        }
-                        block_expr.stmts.push(stmt)
-                    }
-                    StmtOrExpr::Expr(expr) => {
-                        if block_expr.last_expr.is_none() {
-                            block_expr.last_expr = Some(Box::new(expr));
-                        } else {
-                            return Err("expected `;`".into());
-                        }
+            cursor.with_no_struct_expr(false, parse_block_expr)
+        }
+    }
+
+    /// UnsafeBlockExpr -> `unsafe` BlockExpr
+    pub fn parse_unsafe_block(cursor: &mut ParseCursor) -> Result<BlockExpr, RccError> {
+        cursor.eat_token_eq(Token::Unsafe)?;
+        Ok(BlockExpr::parse(cursor)?.unsafe_block())
+    }
+
+    fn parse_block_expr(cursor: &mut ParseCursor) -> Result<BlockExpr, RccError> {
+        let line = cursor.line();
+        cursor.eat_token_eq(Token::LeftCurlyBraces)?;
+        let mut block_expr = BlockExpr::new(cursor.scope_count);
+        block_expr.line = line;
+        cursor.scope_count += 1;
+        while cursor.next_token()? != &Token::RightCurlyBraces {
+            let line = cursor.line();
+            match parse_stmt_or_expr_without_block(cursor)? {
+                StmtOrExpr::Stmt(stmt) => {
+                    block_expr.stmts.push(stmt);
+                    block_expr.lines.push(line);
+                }
+                StmtOrExpr::Expr(expr) => {
+                    if block_expr.last_expr.is_none() {
+                        block_expr.last_expr = Some(Box::new(expr));
+                        block_expr.lines.push(line);
+                    } else {
+                        return Err("expected `;`".into());
                     }
                 }
             }
+        }
 
-            if block_expr.last_expr.is_none() && !block_expr.stmts.is_empty() {
-                if let Stmt::ExprStmt(e) = block_expr.stmts.last().unwrap() {
-                    if e.with_block() {
-                        block_expr.set_last_stmt_as_expr();
-                    }
+        if block_expr.last_expr.is_none() && !block_expr.stmts.is_empty() {
+            if let Stmt::ExprStmt(e) = block_expr.stmts.last().unwrap() {
+                if e.with_block() {
+                    block_expr.set_last_stmt_as_expr();
                 }
             }
-
-            cursor.eat_token_eq(Token::RightCurlyBraces)?;
-            Ok(block_expr)
         }
+        // types refer to the items, so they are added after the items are not moved
+        for stmt in block_expr.stmts.iter() {
+            if let Stmt::Item(item) = stmt {
+                block_expr.scope.add_typedef(item);
+            }
+        }
+
+        cursor.eat_token_eq(Token::RightCurlyBraces)?;
+        Ok(block_expr)
     }
 
-    /// ArrayExpr -> `[` Expression ( , Expression )* ,? `]`
+    /// ArrayExpr -> `[` ( Expression ( , Expression )* ,? )? `]`
     ///            | `[` Expression ; Expression `]`
     impl Parse for ArrayExpr {
         fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
             cursor.eat_token_eq(Token::LeftSquareBrackets)?;
+            if cursor.eat_token_if_eq(Token::RightSquareBrackets) {
+                return Ok(ArrayExpr::elems(vec![]));
+            }
             let mut elems = vec![Expr::parse(cursor)?];
             let mut last_is_comma = false;
             loop {
                 match cursor.next_token()? {
-                    Token::RightCurlyBraces => return Ok(ArrayExpr::elems(elems)),
+                    Token::RightSquareBrackets => {
+                        cursor.bump_token()?;
+                        return Ok(ArrayExpr::elems(elems));
+                    }
                     Token::Semi => {
                         cursor.bump_token()?;
                         return if elems.len() == 1 {
@@ -465,10 +607,8 @@ pub mod primitive {
     impl Parse for WhileExpr {
         fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
             cursor.eat_token_eq(Token::While)?;
-            Ok(WhileExpr(
-                Box::new(Expr::parse(cursor)?),
-                Box::new(BlockExpr::parse(cursor)?),
-            ))
+            let cond = cursor.with_no_struct_expr(true, Expr::parse)?;
+            Ok(WhileExpr::new(cond, BlockExpr::parse(cursor)?))
         }
     }
 
@@ -479,16 +619,54 @@ pub mod primitive {
         }
     }
 
+    /// LabeledLoopExpr -> LIFETIME `:` ( LoopExpr | WhileExpr | ForExpr )
+    pub fn parse_labeled_loop(cursor: &mut ParseCursor) -> Result<Expr, RccError> {
+        let label = match cursor.bump_token()? {
+            Token::Lifetime(label) => label.to_string(),
+            _ => unreachable!(),
+        };
+        cursor.eat_token_eq(Token::Colon)?;
+        match cursor.next_token()? {
+            Token::Loop => {
+                let mut loop_expr = LoopExpr::parse(cursor)?;
+                loop_expr.label = Some(label);
+                Ok(Loop(loop_expr))
+            }
+            Token::While => {
+                let mut while_expr = WhileExpr::parse(cursor)?;
+                while_expr.label = Some(label);
+                Ok(While(while_expr))
+            }
+            Token::For => {
+                let mut for_expr = ForExpr::parse(cursor)?;
+                for_expr.label = Some(label);
+                Ok(For(for_expr))
+            }
+            _ => Err("expected `loop`, `while` or `for` after label".into()),
+        }
+    }
+
+    /// ForExpr -> `for` Pattern `in` Expr BlockExpr
+    impl Parse for ForExpr {
+        fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
+            cursor.eat_token_eq(Token::For)?;
+            let pattern = Pattern::parse(cursor)?;
+            cursor.eat_token_eq(Token::In)?;
+            let expr = cursor.with_no_struct_expr(true, Expr::parse)?;
+            Ok(ForExpr::new(pattern, expr, BlockExpr::parse(cursor)?))
+        }
+    }
+
     /// IfExpr -> `if` Expr BlockExpr ( `else` (BlockExpr | IfExpr) )?
     impl Parse for IfExpr {
         fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
             cursor.eat_token_eq(Token::If)?;
             let mut if_expr = IfExpr::new();
-            if_expr.add_cond(Expr::parse(cursor)?);
+            if_expr.add_cond(cursor.with_no_struct_expr(true, Expr::parse)?);
             if_expr.add_block(BlockExpr::parse(cursor)?);
             while cursor.eat_token_if_eq(Token::Else) {
                 if cursor.eat_token_if_eq(Token::If) {
-                    if_expr.add_cond(Expr::parse(cursor)?);
+                    if_expr.add_cond(cursor.with_no_struct_expr(true, Expr::parse)?);
                 }
                 if_expr.add_block(BlockExpr::parse(cursor)?);
             }
@@ -496,6 +674,46 @@ pub mod primitive {
         }
     }
 
+    /// MatchExpr -> `match` Expr `{` MatchArm* `}`
+    ///
+    /// MatchArm -> Pattern ( `if` Expr )? `=>` ( ExprWithoutBlock `,` | ExprWithBlock `,`? )
+    ///
+    /// The comma after the last arm is optional.
+    impl Parse for MatchExpr {
+        fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
+            cursor.eat_token_eq(Token::Match)?;
+            let expr = cursor.with_no_struct_expr(true, Expr::parse)?;
+            cursor.eat_token_eq(Token::LeftCurlyBraces)?;
+            let mut arms = vec![];
+            while !cursor.eat_token_if_eq(Token::RightCurlyBraces) {
+                let scope_id = cursor.scope_count;
+                cursor.scope_count += 1;
+                let pattern = Pattern::parse(cursor)?;
+                let guard = if cursor.eat_token_if_eq(Token::If) {
+                    Some(Expr::parse(cursor)?)
+                } else {
+                    None
+                };
+                cursor.eat_token_eq(Token::FatArrow)?;
+                let arm_expr = if Expr::is_with_block_token_start(cursor.next_token()?) {
+                    let arm_expr = Expr::parse_with_block(cursor)?;
+                    cursor.eat_token_if_eq(Token::Comma);
+                    arm_expr
+                } else {
+                    let arm_expr = Expr::parse(cursor)?;
+                    if !cursor.eat_token_if_eq(Token::Comma)
+                        && cursor.next_token()? != &Token::RightCurlyBraces
+                    {
+                        return Err("expected `,` after match arm".into());
+                    }
+                    arm_expr
+                };
+                arms.push(MatchArm::new(scope_id, pattern, guard, arm_expr));
+            }
+            Ok(MatchExpr::new(expr, arms))
+        }
+    }
+
     /// ReturnExpr -> `return` Expr?
     impl Parse for ReturnExpr {
         fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
@@ -510,17 +728,36 @@ pub mod primitive {
         }
     }
 
-    /// BreakExpr -> `break` Expr?
+    fn parse_label(cursor: &mut ParseCursor) -> Option<String> {
+        if let Ok(Token::Lifetime(label)) = cursor.next_token() {
+            let label = label.to_string();
+            cursor.bump_token().unwrap();
+            Some(label)
+        } else {
+            None
+        }
+    }
+
+    /// BreakExpr -> `break` LIFETIME? Expr?
     impl Parse for BreakExpr {
         fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
             cursor.eat_token_eq(Token::Break)?;
+            let label = parse_label(cursor);
             if let Ok(tk) = cursor.next_token() {
                 if Expr::is_token_start(tk) {
                     let expr = Expr::parse(cursor)?;
-                    return Ok(BreakExpr(Some(Box::new(expr))));
+                    return Ok(BreakExpr::new(label, Some(expr)));
                 }
             }
-            Ok(BreakExpr(None))
+            Ok(BreakExpr::new(label, None))
+        }
+    }
+
+    /// ContinueExpr -> `continue` LIFETIME?
+    impl Parse for ContinueExpr {
+        fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
+            cursor.eat_token_eq(Token::Continue)?;
+            Ok(ContinueExpr(parse_label(cursor)))
         }
     }
 }
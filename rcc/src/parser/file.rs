@@ -10,9 +10,12 @@ impl Parse for File {
         let mut file = File::new(cursor.scope_count);
         cursor.scope_count += 1;
         while !cursor.is_eof() {
-            let item = Item::parse(cursor)?;
-            file.scope.add_typedef(&item);
-            file.items.push(item);
+            file.items.push(Item::parse(cursor)?);
+        }
+        file.scope_count = cursor.scope_count;
+        // types refer to the items, so they are added after the items are not moved
+        for item in file.items.iter() {
+            file.scope.add_typedef(item);
         }
         Ok(file)
     }
@@ -1,81 +1,390 @@
-use crate::ast::expr::BlockExpr;
+use crate::ast::expr::{BlockExpr, Expr};
 use crate::ast::item::{
-    ExternalItem, ExternalItemFn, FnParam, FnParams, Item, ItemExternalBlock, ItemFn, ItemStruct,
-    StructField, TupleField, TypeEnum, ABI,
+    Attribute, EnumRepr, EnumVariant, ExternalItem, ExternalItemFn, Fields, FnParam, FnParams,
+    FnSignature, GenericParam, Item, ItemConst, ItemExternalBlock, ItemFn, ItemImpl, ItemStatic,
+    ItemStruct, ItemTrait, StructField, Template, TraitItemFn, TupleField, TypeEnum, ABI,
 };
-use crate::ast::pattern::Pattern;
-use crate::ast::types::TypeAnnotation;
+use crate::ast::pattern::{IdentPattern, Pattern};
+use crate::ast::types::{PtrKind, TypeAnnotation, TypeLitNum, TypePtr};
 use crate::ast::{TokenStart, Visibility};
-use crate::lexer::token::Token;
+use crate::lexer::token::{LiteralKind, Token};
+use crate::lexer::Lexer;
 use crate::parser::expr::primitive::parse_lit_string;
 use crate::parser::{Parse, ParseCursor};
 use crate::rcc::RccError;
+use std::str::FromStr;
 use std::string::ToString;
 
+/// Item -> OuterAttribute* pub? VisItem
 impl Parse for Item {
     fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
+        let attrs = Vec::<Attribute>::parse(cursor)?;
         let vis = Visibility::parse(cursor)?;
 
-        match cursor.next_token()? {
-            Token::Fn => Ok(Self::Fn(ItemFn::parse_with_attr(cursor, vis)?)),
+        let next_token = cursor.next_token()?;
+        if next_token != &Token::Enum {
+            // structs are always laid out like `#[repr(C)]`
+            let is_repr_c = |attr: &Attribute| attr.name == "repr" && attr.args == ["C"];
+            if let Some(attr) = attrs
+                .iter()
+                .find(|attr| next_token != &Token::Struct || !is_repr_c(attr))
+            {
+                return Err(format!("unsupported attribute `{}`", attr.name).into());
+            }
+        }
+        match next_token {
+            Token::Fn => {
+                let item_fn = ItemFn::parse_with_attr(cursor, vis)?;
+                if item_fn.has_self() {
+                    return Err(
+                        "`self` parameter is only allowed in associated functions".into()
+                    );
+                }
+                Ok(Self::Fn(item_fn))
+            }
             Token::Struct => Ok(Self::Struct(ItemStruct::parse_with_attr(cursor, vis)?)),
-            Token::Enum => Ok(Self::Enum(TypeEnum::parse_with_attr(cursor, vis)?)),
-            Token::Static => unimplemented!(),
-            Token::Const => unimplemented!(),
-            Token::Impl => unimplemented!(),
+            Token::Enum => Ok(Self::Enum(TypeEnum::parse_with_attr(cursor, vis, attrs)?)),
+            Token::Static => Ok(Self::Static(ItemStatic::parse_with_attr(cursor, vis)?)),
+            Token::Const => Ok(Self::Const(ItemConst::parse_with_attr(cursor, vis)?)),
+            Token::Impl if vis == Visibility::Pub => {
+                Err("unnecessary visibility qualifier for `impl`".into())
+            }
+            Token::Impl => Ok(Self::Impl(ItemImpl::parse(cursor)?)),
+            Token::Trait => Ok(Self::Trait(ItemTrait::parse_with_attr(cursor, vis)?)),
             Token::Extern => Ok(Self::ExternalBlock(ItemExternalBlock::parse(cursor)?)),
-            _ => unreachable!("inner item must be fn, struct, enum, static, const or impl"),
+            _ => unreachable!("inner item must be fn, struct, enum, static, const, impl or trait"),
         }
     }
 }
 
+/// Impl -> `impl` Generics? ( identifier `for` )? identifier GenericArgs? `{` ( pub? ItemFn )* `}`
+impl Parse for ItemImpl {
+    fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
+        let start = cursor.token_idx;
+        cursor.eat_token_eq(Token::Impl)?;
+        let generics = if cursor.next_token()? == &Token::Lt {
+            Vec::<GenericParam>::parse(cursor)?
+        } else {
+            vec![]
+        };
+        fn parse_type(cursor: &mut ParseCursor) -> Result<TypeAnnotation, RccError> {
+            match cursor.next_token()? {
+                Token::Identifier(_) => TypeAnnotation::parse(cursor),
+                tk => Err(format!("expected a type to implement, found `{:?}`", tk).into()),
+            }
+        }
+        let mut self_type_anno = parse_type(cursor)?;
+        let trait_name = if cursor.eat_token_if_eq(Token::For) {
+            match std::mem::replace(&mut self_type_anno, parse_type(cursor)?) {
+                TypeAnnotation::Identifier(trait_name) => Some(trait_name),
+                _ => return Err("generic traits are not supported yet".into()),
+            }
+        } else {
+            None
+        };
+        let self_type = self_type_anno.to_string();
+        cursor.eat_token_eq(Token::LeftCurlyBraces)?;
+        let mut items = vec![];
+        while !cursor.eat_token_if_eq(Token::RightCurlyBraces) {
+            let vis = Visibility::parse(cursor)?;
+            if cursor.next_token()? != &Token::Fn {
+                return Err("only functions are supported in `impl` blocks".into());
+            }
+            if trait_name.is_some() && vis == Visibility::Pub {
+                return Err("unnecessary visibility qualifier in trait impls".into());
+            }
+            let mut item_fn = ItemFn::parse_with_attr(cursor, vis)?;
+            if item_fn.is_generic() {
+                return Err("generic associated functions are not supported yet".into());
+            }
+            item_fn.name = format!("{}::{}", self_type, item_fn.name);
+            for param in item_fn.fn_params.params.iter_mut() {
+                param._type.replace_self_type(&self_type_anno);
+            }
+            item_fn.ret_type.replace_self_type(&self_type_anno);
+            items.push(item_fn);
+        }
+        let mut item_impl = ItemImpl::new(self_type_anno, items);
+        if let Some(trait_name) = trait_name {
+            item_impl = item_impl.trait_name(trait_name);
+        }
+        if generics.is_empty() {
+            return Ok(item_impl);
+        }
+        let mut tokens = cursor.source_text(start);
+        let generics_end = tokens.iter().position(|tk| tk == ">").unwrap();
+        tokens.drain(1..=generics_end);
+        Ok(item_impl.generics(generics, Template { tokens }))
+    }
+}
+
+impl ItemImpl {
+    /// Parse the template of a generic impl again,
+    /// with the type params in `type_args` replaced by the types.
+    /// The scopes of the instance are numbered from `scope_count`.
+    pub fn instantiate(
+        template: &Template,
+        type_args: &[(String, String)],
+        scope_count: &mut u64,
+    ) -> Result<ItemImpl, RccError> {
+        let source = template.substitute(type_args);
+        let mut lexer = Lexer::new(&source);
+        let mut cursor = ParseCursor::new(lexer.tokenize());
+        cursor.scope_count = *scope_count;
+        let item_impl = ItemImpl::parse(&mut cursor)?;
+        debug_assert!(cursor.is_eof());
+        *scope_count = cursor.scope_count;
+        Ok(item_impl)
+    }
+}
+
+/// Trait -> `trait` identifier `{` TraitItemFn* `}`
+///
+/// TraitItemFn -> `fn` identifier `(` FnParams? `)` ( `->` Type )? ( `;` | BlockExpr )
+impl ItemTrait {
+    fn parse_with_attr(cursor: &mut ParseCursor, vis: Visibility) -> Result<Self, RccError> {
+        cursor.eat_token_eq(Token::Trait)?;
+        let name = cursor.eat_identifier()?.to_string();
+        cursor.eat_token_eq(Token::LeftCurlyBraces)?;
+        let mut items = vec![];
+        while !cursor.eat_token_if_eq(Token::RightCurlyBraces) {
+            if cursor.next_token()? != &Token::Fn {
+                return Err("only functions are supported in traits".into());
+            }
+            let start = cursor.token_idx;
+            let (fn_name, generics, fn_params, ret_type) = parse_fn_signature(cursor)?;
+            if !generics.is_empty() {
+                return Err("generic methods are not supported yet".into());
+            }
+            let item = TraitItemFn::new(fn_name, fn_params, ret_type);
+            if cursor.eat_token_if_eq(Token::Semi) {
+                items.push(item);
+            } else {
+                BlockExpr::parse(cursor)?;
+                let tokens = cursor.source_text(start);
+                items.push(item.default(Template { tokens }));
+            }
+        }
+        Ok(ItemTrait::new(vis, name, items))
+    }
+}
+
+/// `Type = Expr;` of const and static items, the type can not be omitted
+fn parse_type_and_init(cursor: &mut ParseCursor) -> Result<(TypeAnnotation, Expr), RccError> {
+    if !cursor.eat_token_if_eq(Token::Colon) {
+        return Err("missing type for `const` or `static` item".into());
+    }
+    let type_anno = TypeAnnotation::parse(cursor)?;
+    cursor.eat_token_eq(Token::Eq)?;
+    let expr = Expr::parse(cursor)?;
+    cursor.eat_token_eq(Token::Semi)?;
+    Ok((type_anno, expr))
+}
+
+/// Const -> `const` identifier `:` Type `=` Expr `;`
+impl ItemConst {
+    fn parse_with_attr(cursor: &mut ParseCursor, vis: Visibility) -> Result<Self, RccError> {
+        cursor.eat_token_eq(Token::Const)?;
+        let name = cursor.eat_identifier()?.to_string();
+        let (type_anno, expr) = parse_type_and_init(cursor)?;
+        Ok(ItemConst::new(vis, name, type_anno, expr))
+    }
+}
+
+/// Static -> `static` `mut`? identifier `:` Type `=` Expr `;`
+impl ItemStatic {
+    fn parse_with_attr(cursor: &mut ParseCursor, vis: Visibility) -> Result<Self, RccError> {
+        cursor.eat_token_eq(Token::Static)?;
+        let is_mut = cursor.eat_token_if_eq(Token::Mut);
+        let name = cursor.eat_identifier()?.to_string();
+        let (type_anno, expr) = parse_type_and_init(cursor)?;
+        Ok(ItemStatic::new(vis, name, is_mut, type_anno, expr))
+    }
+}
+
 /// Parse struct definition
-/// ItemStruct -> struct Identifier ; | TupleField ; | StructField
+/// ItemStruct -> struct Identifier Generics? ( ; | TupleField ; | StructField )
 impl ItemStruct {
     fn parse_with_attr(cursor: &mut ParseCursor, vis: Visibility) -> Result<Self, RccError> {
         debug_assert!(cursor.next_token()? == &Token::Struct);
+        let start = cursor.token_idx;
         cursor.bump_token()?;
-        let ident = cursor.bump_token()?;
-        if let Token::Identifier(struct_name) = ident {
-            let type_struct = Self::new(vis, struct_name.to_string());
-            match cursor.next_token()? {
-                // struct Foo;
-                Token::Semi => Ok(type_struct),
-                // struct Foo(i32);
-                Token::LeftParen => {
-                    let tuple_fields = Vec::<TupleField>::parse(cursor)?;
-                    // eat semicolon
-                    let tk = cursor.bump_token()?;
-                    if tk == &Token::Semi {
-                        Ok(type_struct.tuple_fields(tuple_fields))
-                    } else {
-                        Err("invalid struct definition(consider adding ';' after ')')".into())
-                    }
+        let struct_name = match cursor.bump_token()? {
+            Token::Identifier(struct_name) => struct_name.to_string(),
+            _ => return Err("no identifier for struct".into()),
+        };
+        let generics = if cursor.next_token()? == &Token::Lt {
+            Vec::<GenericParam>::parse(cursor)?
+        } else {
+            vec![]
+        };
+        let type_struct = Self::new(vis, struct_name);
+        let type_struct = match cursor.next_token()? {
+            // struct Foo;
+            Token::Semi => {
+                cursor.bump_token()?;
+                type_struct
+            }
+            // struct Foo(i32);
+            Token::LeftParen => {
+                let tuple_fields = Vec::<TupleField>::parse(cursor)?;
+                // eat semicolon
+                let tk = cursor.bump_token()?;
+                if tk == &Token::Semi {
+                    type_struct.tuple_fields(tuple_fields)
+                } else {
+                    return Err(
+                        "invalid struct definition(consider adding ';' after ')')".into()
+                    );
                 }
-                // struct Foo {id: i32}
-                Token::LeftCurlyBraces => {
-                    let struct_fields = Vec::<StructField>::parse(cursor)?;
-                    Ok(type_struct.struct_fields(struct_fields))
+            }
+            // struct Foo {id: i32}
+            Token::LeftCurlyBraces => {
+                let struct_fields = Vec::<StructField>::parse(cursor)?;
+                type_struct.struct_fields(struct_fields)
+            }
+            _ => return Err("invalid struct definition".into()),
+        };
+        if generics.is_empty() {
+            return Ok(type_struct);
+        }
+        let template = template_without_generics(cursor, start);
+        Ok(type_struct.generics(generics, template))
+    }
+
+    /// Parse the template of a generic struct again,
+    /// with the type params in `type_args` replaced by the types.
+    pub fn instantiate(
+        template: &Template,
+        vis: Visibility,
+        name: String,
+        type_args: &[(String, String)],
+    ) -> Result<ItemStruct, RccError> {
+        let source = template.substitute(type_args);
+        let mut lexer = Lexer::new(&source);
+        let mut cursor = ParseCursor::new(lexer.tokenize());
+        let mut item_struct = ItemStruct::parse_with_attr(&mut cursor, vis)?;
+        debug_assert!(cursor.is_eof());
+        item_struct.set_name(name);
+        Ok(item_struct)
+    }
+}
+
+/// The tokens of the generic item from `start`, the instances are not generic,
+/// so `Generics` after `fn name` or `struct Name` is dropped.
+fn template_without_generics(cursor: &ParseCursor, start: usize) -> Template {
+    let mut tokens = cursor.source_text(start);
+    let generics_end = tokens.iter().position(|tk| tk == ">").unwrap();
+    tokens.drain(2..=generics_end);
+    Template { tokens }
+}
+
+/// OuterAttribute -> `#` `[` identifier ( `(` identifier ( `,` identifier )* `,`? `)` )? `]`
+impl Parse for Vec<Attribute> {
+    fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
+        let mut attrs = vec![];
+        while cursor.eat_token_if_eq(Token::Pound) {
+            cursor.eat_token_eq(Token::LeftSquareBrackets)?;
+            let name = cursor.eat_identifier()?.to_string();
+            let mut args = vec![];
+            if cursor.eat_token_if_eq(Token::LeftParen) {
+                while cursor.next_token()? != &Token::RightParen {
+                    args.push(cursor.eat_identifier()?.to_string());
+                    if !cursor.eat_token_if_eq(Token::Comma) {
+                        break;
+                    }
                 }
-                _ => Err("invalid struct definition".into()),
+                cursor.eat_token_eq(Token::RightParen)?;
             }
-        } else {
-            Err("no identifier for struct".into())
+            cursor.eat_token_eq(Token::RightSquareBrackets)?;
+            attrs.push(Attribute::new(name, args));
         }
+        Ok(attrs)
     }
 }
 
+/// Enum -> `enum` identifier `{` EnumVariants? `}`
+///
+/// EnumVariants -> EnumVariant ( `,` EnumVariant )* `,`?
+///
+/// EnumVariant -> identifier ( TupleFields | StructFields | `=` `-`? INTEGER_LITERAL )?
 impl TypeEnum {
-    fn parse_with_attr(cursor: &mut ParseCursor, vis: Visibility) -> Result<Self, RccError> {
-        unimplemented!()
+    fn parse_with_attr(
+        cursor: &mut ParseCursor,
+        vis: Visibility,
+        attrs: Vec<Attribute>,
+    ) -> Result<Self, RccError> {
+        let repr = EnumRepr::from_attrs(&attrs)?;
+        cursor.eat_token_eq(Token::Enum)?;
+        let name = cursor.eat_identifier()?.to_string();
+        cursor.eat_token_eq(Token::LeftCurlyBraces)?;
+        let mut variants = vec![];
+        while cursor.next_token()? != &Token::RightCurlyBraces {
+            let variant_name = cursor.eat_identifier()?.to_string();
+            let fields = match cursor.next_token()? {
+                Token::LeftParen => Fields::Tuple(Vec::<TupleField>::parse(cursor)?),
+                Token::LeftCurlyBraces => Fields::Struct(Vec::<StructField>::parse(cursor)?),
+                _ => Fields::None,
+            };
+            let discriminant = if cursor.eat_token_if_eq(Token::Eq) {
+                Some(parse_discriminant(cursor)?)
+            } else {
+                None
+            };
+            variants.push(EnumVariant::new(variant_name, fields, discriminant));
+            if !cursor.eat_token_if_eq(Token::Comma) {
+                break;
+            }
+        }
+        cursor.eat_token_eq(Token::RightCurlyBraces)?;
+        Ok(TypeEnum::new(vis, name, repr, variants))
+    }
+}
+
+impl EnumRepr {
+    /// Only `#[repr]` is allowed on enums.
+    fn from_attrs(attrs: &[Attribute]) -> Result<EnumRepr, RccError> {
+        let mut repr = EnumRepr::default();
+        for attr in attrs {
+            if attr.name != "repr" {
+                return Err(format!("unsupported attribute `{}`", attr.name).into());
+            }
+            for arg in attr.args.iter() {
+                match TypeLitNum::from_str(arg) {
+                    _ if arg == "C" => repr.c = true,
+                    Ok(t) if t.is_integer() && repr.int_type.is_none() => repr.int_type = Some(t),
+                    Ok(t) if t.is_integer() => {
+                        return Err("conflicting representation hints".into());
+                    }
+                    _ => return Err(format!("unrecognized representation hint `{}`", arg).into()),
+                }
+            }
+        }
+        Ok(repr)
     }
 }
 
-fn parse_fn_signature(
-    cursor: &mut ParseCursor,
-) -> Result<(String, FnParams, TypeAnnotation), RccError> {
+fn parse_discriminant(cursor: &mut ParseCursor) -> Result<i128, RccError> {
+    let is_neg = cursor.eat_token_if_eq(Token::Minus);
+    match cursor.eat_literal()? {
+        (LiteralKind::Integer { .. }, value) => {
+            let value: i128 = value.parse()?;
+            Ok(if is_neg { -value } else { value })
+        }
+        _ => Err("expected integer literal for the discriminant".into()),
+    }
+}
+
+type FnSignatureParts = (String, Vec<GenericParam>, FnParams, TypeAnnotation);
+
+fn parse_fn_signature(cursor: &mut ParseCursor) -> Result<FnSignatureParts, RccError> {
     cursor.eat_token_eq(Token::Fn)?;
     let fn_name = cursor.eat_identifier()?.to_string();
+    let generics = if cursor.next_token()? == &Token::Lt {
+        Vec::<GenericParam>::parse(cursor)?
+    } else {
+        vec![]
+    };
 
     cursor.eat_token_eq(Token::LeftParen)?;
     let fn_params = if cursor.eat_token_if_eq(Token::RightParen) {
@@ -94,15 +403,68 @@ fn parse_fn_signature(
         Token::Semi | Token::LeftCurlyBraces => TypeAnnotation::Unit,
         _ => return Err("except '->' or '{'".into()),
     };
-    Ok((fn_name, fn_params, ret_type))
+    Ok((fn_name, generics, fn_params, ret_type))
+}
+
+/// Generics -> `<` ( GenericParam `,` )* GenericParam? `>`
+///
+/// GenericParam -> identifier ( `:` identifier ( `+` identifier )* )?
+impl Parse for Vec<GenericParam> {
+    fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
+        cursor.eat_token_eq(Token::Lt)?;
+        let mut generics = vec![];
+        while !cursor.eat_token_if_eq(Token::Gt) {
+            let name = cursor.eat_identifier()?.to_string();
+            let mut bounds = vec![];
+            if cursor.eat_token_if_eq(Token::Colon) {
+                bounds.push(cursor.eat_identifier()?.to_string());
+                while cursor.eat_token_if_eq(Token::Plus) {
+                    bounds.push(cursor.eat_identifier()?.to_string());
+                }
+            }
+            generics.push(GenericParam::new(name, bounds));
+            if !cursor.eat_token_if_eq(Token::Comma) {
+                cursor.eat_token_eq(Token::Gt)?;
+                break;
+            }
+        }
+        Ok(generics)
+    }
 }
 
-/// ItemFn -> vis? `fn` identifier `(` FnParams? `)` ( `->` Type )? BlockExpr
+/// ItemFn -> vis? `fn` identifier Generics? `(` FnParams? `)` ( `->` Type )? BlockExpr
 impl ItemFn {
     fn parse_with_attr(cursor: &mut ParseCursor, vis: Visibility) -> Result<Self, RccError> {
-        let (fn_name, fn_params, ret_type) = parse_fn_signature(cursor)?;
+        let start = cursor.token_idx;
+        let (fn_name, generics, fn_params, ret_type) = parse_fn_signature(cursor)?;
         let fn_block = BlockExpr::parse(cursor)?;
-        Ok(ItemFn::new(vis, fn_name, fn_params, ret_type, fn_block))
+        let item_fn = ItemFn::new(vis, fn_name, fn_params, ret_type, fn_block);
+        if generics.is_empty() {
+            return Ok(item_fn);
+        }
+        let template = template_without_generics(cursor, start);
+        Ok(item_fn.generics(generics, template))
+    }
+
+    /// Parse the template of a generic function or a default method again,
+    /// with the type params in `type_args` replaced by the types.
+    /// The scopes of the instance are numbered from `scope_count`.
+    pub fn instantiate(
+        template: &Template,
+        vis: Visibility,
+        name: String,
+        type_args: &[(String, String)],
+        scope_count: &mut u64,
+    ) -> Result<ItemFn, RccError> {
+        let source = template.substitute(type_args);
+        let mut lexer = Lexer::new(&source);
+        let mut cursor = ParseCursor::new(lexer.tokenize());
+        cursor.scope_count = *scope_count;
+        let mut item_fn = ItemFn::parse_with_attr(&mut cursor, vis)?;
+        debug_assert!(cursor.is_eof());
+        *scope_count = cursor.scope_count;
+        item_fn.name = name;
+        Ok(item_fn)
     }
 }
 
@@ -112,7 +474,11 @@ impl Parse for FnParams {
         let mut fn_params: FnParams = vec![FnParam::parse(cursor)?].into();
         while cursor.eat_token_if_eq(Token::Comma) {
             if FnParam::is_token_start(cursor.next_token()?) {
-                fn_params.push(FnParam::parse(cursor)?);
+                let fn_param = FnParam::parse(cursor)?;
+                if fn_param.is_self() {
+                    return Err("unexpected `self` parameter in function".into());
+                }
+                fn_params.push(fn_param);
             } else {
                 break;
             }
@@ -121,9 +487,14 @@ impl Parse for FnParams {
     }
 }
 
-/// FnParam -> Pattern `:` Type
+/// FnParam -> SelfParam | Pattern `:` Type
+///
+/// SelfParam -> `mut`? `self` ( `:` Type )? | `&` `mut`? `self`
 impl Parse for FnParam {
     fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
+        if let Some(self_param) = parse_self_param(cursor)? {
+            return Ok(self_param);
+        }
         let ptn = Pattern::parse(cursor)?;
         cursor.eat_token_eq(Token::Colon)?;
         let _type = TypeAnnotation::parse(cursor)?;
@@ -131,6 +502,32 @@ impl Parse for FnParam {
     }
 }
 
+/// `self` is typed `Self`, `&self` is `&Self` and `&mut self` is `&mut Self`.
+fn parse_self_param(cursor: &mut ParseCursor) -> Result<Option<FnParam>, RccError> {
+    let self_token = |n| cursor.nth_token(n) == Ok(&Token::SelfValue);
+    let mut_token = |n| cursor.nth_token(n) == Ok(&Token::Mut);
+    let (ptr_kind, is_mut) = match cursor.next_token()? {
+        Token::SelfValue => (None, false),
+        Token::Mut if self_token(1) => (None, true),
+        Token::And if self_token(1) => (Some(PtrKind::Ref), false),
+        Token::And if mut_token(1) && self_token(2) => (Some(PtrKind::MutRef), false),
+        _ => return Ok(None),
+    };
+    while cursor.bump_token()? != &Token::SelfValue {}
+    let pattern = if is_mut {
+        IdentPattern::new_mut("self".to_string())
+    } else {
+        IdentPattern::new_const("self".to_string())
+    };
+    let self_type = TypeAnnotation::from("Self");
+    let _type = match ptr_kind {
+        Some(ptr_kind) => TypeAnnotation::Ptr(TypePtr::new(ptr_kind, self_type)),
+        None if cursor.eat_token_if_eq(Token::Colon) => TypeAnnotation::parse(cursor)?,
+        None => self_type,
+    };
+    Ok(Some(FnParam::new(Pattern::Identifier(pattern), _type)))
+}
+
 /// ExternalItem -> extern ABI { ExternalItem* }
 impl Parse for ItemExternalBlock {
     fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
@@ -174,7 +571,10 @@ impl ExternalItemFn {
         cursor: &mut ParseCursor,
         vis: Visibility,
     ) -> Result<ExternalItemFn, RccError> {
-        let (fn_name, fn_params, ret_type) = parse_fn_signature(cursor)?;
+        let (fn_name, generics, fn_params, ret_type) = parse_fn_signature(cursor)?;
+        if !generics.is_empty() {
+            return Err("foreign items may not have type parameters".into());
+        }
         cursor.eat_token_eq(Token::Semi)?;
         Ok(ExternalItemFn::new(vis, fn_name, fn_params, ret_type))
     }
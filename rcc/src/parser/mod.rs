@@ -13,18 +13,23 @@
 //!
 //! File -> Item File | Item
 //! Item -> pub? VisItem | Impl
-//! VisItem -> Fn | Struct | Enum | Const | Static
+//! VisItem -> Fn | Struct | Enum | Const | Static | Trait
+//! Impl -> impl ( Ident for )? Ident `{` ( pub? Fn )* `}`
+//! Trait -> trait Ident `{` ( FnSignature ( semi | BlockExpr ) )* `}`
 //!
 //! Fn -> FnSignature BlockExpr
-//! FnSignature -> fn Ident `(` FnArgs? `)` RetType?
+//! FnSignature -> fn Ident Generics? `(` FnArgs? `)` RetType?
+//! Generics -> `<` ( Ident ( `:` Ident ( `+` Ident )* )? `,`? )* `>`
+//! GenericArgs -> `<` ( Type `,` )* Type? `>`
 //! FnArgs -> FnArg (`,` FnArg)* `,`?
 //! RetType -> r_arrow Type
-//! Type -> ident | `()` | ( left_paren (Type comma)+ Type? right_paren ) |
+//! Type -> ident GenericArgs? | `()` | ( left_paren (Type comma)+ Type? right_paren ) |
 //!         bool | char |
 //!         f32 | f64 | i8 | i16 | i32 | i64 |
 //!         i128 | isize | u8 | u16 | u32 | u64 | u128 | usize
 //!
-//! Static -> static ident TypeAnnotation eq semi
+//! Const -> const Ident `:` Type eq Expr semi
+//! Static -> static mut? Ident `:` Type eq Expr semi
 
 use crate::ast::FromToken;
 use crate::ast::{Visibility, AST};
@@ -51,6 +56,11 @@ pub struct ParseCursor<'a> {
     token_stream: Vec<Token<'a>>,
     token_idx: usize,
     scope_count: u64,
+    /// line number of each token, empty if unknown
+    token_lines: Vec<u32>,
+    /// `{` after a path starts a block instead of a struct expression,
+    /// in the conditions of `if`, `while`, the expr of `match` and the iterator of `for`
+    no_struct_expr: bool,
 }
 
 impl<'a> ParseCursor<'a> {
@@ -59,9 +69,35 @@ impl<'a> ParseCursor<'a> {
             token_stream,
             token_idx: 0,
             scope_count: 1,
+            token_lines: vec![],
+            no_struct_expr: false,
         }
     }
 
+    pub fn with_lines(mut self, token_lines: Vec<u32>) -> Self {
+        debug_assert_eq!(self.token_stream.len(), token_lines.len());
+        self.token_lines = token_lines;
+        self
+    }
+
+    /// Parse with struct expressions disallowed or allowed again,
+    /// e.g. they are allowed in the parentheses of the conditions.
+    pub fn with_no_struct_expr<T>(
+        &mut self,
+        no_struct_expr: bool,
+        parse: impl FnOnce(&mut Self) -> Result<T, RccError>,
+    ) -> Result<T, RccError> {
+        let old = std::mem::replace(&mut self.no_struct_expr, no_struct_expr);
+        let result = parse(self);
+        self.no_struct_expr = old;
+        result
+    }
+
+    /// Line number of the next token, 0 if unknown.
+    pub fn line(&self) -> u32 {
+        self.token_lines.get(self.token_idx).copied().unwrap_or(0)
+    }
+
     pub fn next_token(&self) -> Result<&Token<'a>, RccError> {
         match self.token_stream.get(self.token_idx) {
             Some(tk) => Ok(tk),
@@ -69,6 +105,14 @@ impl<'a> ParseCursor<'a> {
         }
     }
 
+    /// The `n`th token after the next token, the next token is the 0th.
+    pub fn nth_token(&self, n: usize) -> Result<&Token<'a>, RccError> {
+        match self.token_stream.get(self.token_idx + n) {
+            Some(tk) => Ok(tk),
+            None => Err("EOF token".into()),
+        }
+    }
+
     pub fn bump_token(&mut self) -> Result<&Token<'a>, RccError> {
         match self.token_stream.get(self.token_idx) {
             Some(tk) => {
@@ -147,6 +191,29 @@ impl<'a> ParseCursor<'a> {
         }
     }
 
+    /// Eat `>` closing generics, which may be the first `>` of `>>`, `>=` or `>>=`,
+    /// e.g. `Pair<Pair<i32>>`, then the rest of the token is left.
+    pub fn eat_gt(&mut self) -> bool {
+        let rest = match self.next_token() {
+            Ok(Token::Gt) => {
+                self.token_idx += 1;
+                return true;
+            }
+            Ok(Token::Shr) => Token::Gt,
+            Ok(Token::Ge) => Token::Eq,
+            Ok(Token::ShrEq) => Token::Ge,
+            _ => return false,
+        };
+        self.token_stream[self.token_idx] = rest;
+        self.token_stream.insert(self.token_idx, Token::Gt);
+        if !self.token_lines.is_empty() {
+            let line = self.token_lines[self.token_idx];
+            self.token_lines.insert(self.token_idx, line);
+        }
+        self.token_idx += 1;
+        true
+    }
+
     fn err(&self, expect: String) -> String {
         format!("error in parsing: except {}", expect)
     }
@@ -154,6 +221,14 @@ impl<'a> ParseCursor<'a> {
     pub fn is_eof(&self) -> bool {
         self.token_idx == self.token_stream.len()
     }
+
+    /// Source text of the tokens from `start` to the next token.
+    fn source_text(&self, start: usize) -> Vec<String> {
+        self.token_stream[start..self.token_idx]
+            .iter()
+            .map(Token::source_text)
+            .collect()
+    }
 }
 
 impl Parse for Visibility {
@@ -1,30 +1,214 @@
-use crate::ast::pattern::{IdentPattern, Pattern};
+use crate::ast::expr::RangeOp;
+use crate::ast::pattern::{
+    FieldPattern, IdentPattern, LitPattern, Pattern, RangePattern, RefPattern, StructPattern,
+    TupleStructPattern,
+};
+use crate::ast::types::TypeLitNum;
+use crate::lexer::token::LiteralKind;
 use crate::lexer::token::Token;
 use crate::parser::{Parse, ParseCursor};
 use crate::rcc::RccError;
+use std::str::FromStr;
 
-/// Pattern -> IdentifierPattern
-///
-/// IdentifierPattern -> `mut`? identifier
+/// Pattern -> `|`? PatternNoTopAlt ( `|` PatternNoTopAlt )*
 impl Parse for Pattern {
     fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
-        match cursor.next_token()? {
-            Token::Mut => {
+        cursor.eat_token_if_eq(Token::Or);
+        let mut patterns = vec![parse_pattern_no_top_alt(cursor)?];
+        while cursor.eat_token_if_eq(Token::Or) {
+            patterns.push(parse_pattern_no_top_alt(cursor)?);
+        }
+        if patterns.len() == 1 {
+            Ok(patterns.pop().unwrap())
+        } else {
+            Ok(Self::Or(patterns))
+        }
+    }
+}
+
+/// PatternNoTopAlt -> IdentifierPattern | WildcardPattern | RestPattern | LiteralPattern
+///                   | RangePattern | ReferencePattern | TuplePattern | GroupedPattern
+///                   | StructPattern | TupleStructPattern | PathPattern
+///
+/// IdentifierPattern -> `ref`? `mut`? identifier
+///
+/// WildcardPattern -> `_`
+///
+/// RestPattern -> `..`
+///
+/// RangePattern -> LiteralPattern ( `..=` | `..` ) LiteralPattern
+///
+/// ReferencePattern -> (`&` | `&&`) `mut`? PatternNoTopAlt
+///
+/// TuplePattern -> `(` ( Pattern `,` )* Pattern? `)`
+///
+/// GroupedPattern -> `(` Pattern `)`
+///
+/// TupleStructPattern -> PathInExpression `(` ( Pattern `,` )* Pattern? `)`
+///
+/// PathPattern -> identifier ( `::` identifier )+
+fn parse_pattern_no_top_alt(cursor: &mut ParseCursor) -> Result<Pattern, RccError> {
+    match cursor.next_token()? {
+        Token::Ref | Token::Mut => parse_ident_pattern(cursor).map(Pattern::Identifier),
+        Token::Identifier("_") => {
+            cursor.bump_token()?;
+            Ok(Pattern::Wildcard)
+        }
+        Token::Identifier(s) => {
+            let mut path = s.to_string();
+            cursor.bump_token()?;
+            let mut is_path = false;
+            while cursor.eat_token_if_eq(Token::PathSep) {
+                path.push_str("::");
+                path.push_str(cursor.eat_identifier()?);
+                is_path = true;
+            }
+            match cursor.next_token()? {
+                Token::LeftCurlyBraces => parse_struct_pattern(cursor, path).map(Pattern::Struct),
+                Token::LeftParen => {
+                    let (patterns, _) = parse_paren_patterns(cursor)?;
+                    Ok(Pattern::TupleStruct(TupleStructPattern::new(path, patterns)))
+                }
+                _ if is_path => Ok(Pattern::Path(path)),
+                _ => Ok(Pattern::Identifier(IdentPattern::new_const(path))),
+            }
+        }
+        Token::DotDot => {
+            cursor.bump_token()?;
+            Ok(Pattern::Rest)
+        }
+        Token::And | Token::AndAnd => {
+            let is_double = cursor.bump_token()? == &Token::AndAnd;
+            let is_mut = cursor.eat_token_if_eq(Token::Mut);
+            let pattern = Pattern::Ref(RefPattern::new(is_mut, parse_pattern_no_top_alt(cursor)?));
+            if is_double {
+                // `&&` is lexed as a single token
+                Ok(Pattern::Ref(RefPattern::new(false, pattern)))
+            } else {
+                Ok(pattern)
+            }
+        }
+        Token::LeftParen => parse_tuple_pattern(cursor),
+        Token::Literal { .. } | Token::Minus | Token::True | Token::False => {
+            let lhs = parse_lit_pattern(cursor)?;
+            match cursor.eat_token_if_from::<RangeOp>() {
+                Some(range_op) => {
+                    let rhs = parse_lit_pattern(cursor)?;
+                    Ok(Pattern::Range(RangePattern::new(lhs, range_op, rhs)))
+                }
+                None => Ok(Pattern::Literal(lhs)),
+            }
+        }
+        _ => Err("invalid pattern".into()),
+    }
+}
+
+fn parse_ident_pattern(cursor: &mut ParseCursor) -> Result<IdentPattern, RccError> {
+    let is_ref = cursor.eat_token_if_eq(Token::Ref);
+    let is_mut = cursor.eat_token_if_eq(Token::Mut);
+    let ident = cursor.eat_identifier()?.to_string();
+    Ok(if is_ref {
+        IdentPattern::new_ref(ident, is_mut)
+    } else if is_mut {
+        IdentPattern::new_mut(ident)
+    } else {
+        IdentPattern::new_const(ident)
+    })
+}
+
+fn parse_tuple_pattern(cursor: &mut ParseCursor) -> Result<Pattern, RccError> {
+    let (mut patterns, has_comma) = parse_paren_patterns(cursor)?;
+    // `(..)` is a tuple pattern while `(a)` is a grouped pattern
+    if patterns.len() == 1 && !has_comma && patterns[0] != Pattern::Rest {
+        Ok(patterns.pop().unwrap())
+    } else {
+        Ok(Pattern::Tuple(patterns))
+    }
+}
+
+/// Patterns separated by `,` in parentheses, and whether there is any `,`
+fn parse_paren_patterns(cursor: &mut ParseCursor) -> Result<(Vec<Pattern>, bool), RccError> {
+    cursor.eat_token_eq(Token::LeftParen)?;
+    let mut patterns = vec![];
+    let mut has_comma = false;
+    while cursor.next_token()? != &Token::RightParen {
+        patterns.push(Pattern::parse(cursor)?);
+        if cursor.eat_token_if_eq(Token::Comma) {
+            has_comma = true;
+        } else {
+            break;
+        }
+    }
+    cursor.eat_token_eq(Token::RightParen)?;
+    Ok((patterns, has_comma))
+}
+
+/// StructPattern -> PathInExpression `{` StructPatternElements? `}`
+///
+/// StructPatternElements -> StructPatternField ( `,` StructPatternField )* ( `,` `..`? )?
+///                        | `..`
+///
+/// StructPatternField -> identifier `:` Pattern | `ref`? `mut`? identifier
+fn parse_struct_pattern(cursor: &mut ParseCursor, path: String) -> Result<StructPattern, RccError> {
+    cursor.eat_token_eq(Token::LeftCurlyBraces)?;
+    let mut fields = vec![];
+    let mut has_rest = false;
+    while cursor.next_token()? != &Token::RightCurlyBraces {
+        if cursor.eat_token_if_eq(Token::DotDot) {
+            has_rest = true;
+            break;
+        }
+        let field = match cursor.next_token()? {
+            Token::Identifier(name) => {
+                let name = name.to_string();
                 cursor.bump_token()?;
-                if let Token::Identifier(s) = cursor.bump_token()? {
-                    Ok(Self::Identifier(IdentPattern::new_mut(
-                        s.to_string(),
-                    )))
+                if cursor.eat_token_if_eq(Token::Colon) {
+                    FieldPattern::new(name, Pattern::parse(cursor)?)
                 } else {
-                    Err("expect identifier".into())
+                    FieldPattern::new(
+                        name.clone(),
+                        Pattern::Identifier(IdentPattern::new_const(name)),
+                    )
                 }
             }
-            Token::Identifier(s) => {
-                let s = s.to_string();
-                cursor.bump_token()?;
-                Ok(Self::Identifier(IdentPattern::new_const(s)))
+            _ => {
+                let ident_pattern = parse_ident_pattern(cursor)?;
+                FieldPattern::new(
+                    ident_pattern.ident().to_string(),
+                    Pattern::Identifier(ident_pattern),
+                )
+            }
+        };
+        fields.push(field);
+        if !cursor.eat_token_if_eq(Token::Comma) {
+            break;
+        }
+    }
+    cursor.eat_token_eq(Token::RightCurlyBraces)?;
+    Ok(StructPattern::new(path, fields, has_rest))
+}
+
+/// LiteralPattern -> `true` | `false` | CHAR_LITERAL | `-`? INTEGER_LITERAL
+fn parse_lit_pattern(cursor: &mut ParseCursor) -> Result<LitPattern, RccError> {
+    match cursor.next_token()? {
+        Token::True | Token::False => Ok(LitPattern::Bool(cursor.bump_token()? == &Token::True)),
+        _ => {
+            let is_neg = cursor.eat_token_if_eq(Token::Minus);
+            match cursor.eat_literal()? {
+                (LiteralKind::Char, value) if !is_neg => {
+                    Ok(LitPattern::Char(value.chars().nth(1).unwrap()))
+                }
+                (LiteralKind::Integer { suffix }, value) => {
+                    let value: i128 = value.parse()?;
+                    let lit_type = if suffix.is_empty() {
+                        TypeLitNum::I
+                    } else {
+                        TypeLitNum::from_str(suffix).unwrap()
+                    };
+                    Ok(LitPattern::Int(if is_neg { -value } else { value }, lit_type))
+                }
+                _ => Err("invalid literal pattern".into()),
             }
-            _ => Err("invalid pattern".into()),
         }
     }
 }
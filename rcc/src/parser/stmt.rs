@@ -1,11 +1,12 @@
-use crate::ast::expr::Expr::{Block, For, If, Loop, While};
-use crate::ast::expr::{BlockExpr, Expr, IfExpr, LoopExpr, WhileExpr};
+use crate::ast::expr::Expr::{Block, For, If, Loop, Match, While};
+use crate::ast::expr::{BlockExpr, Expr, ForExpr, IfExpr, LoopExpr, MatchExpr, WhileExpr};
 use crate::ast::item::Item;
 use crate::ast::pattern::Pattern;
 use crate::ast::stmt::{LetStmt, Stmt};
 use crate::ast::types::TypeAnnotation;
 use crate::ast::TokenStart;
 use crate::lexer::token::Token;
+use crate::parser::expr::primitive::{parse_labeled_loop, parse_unsafe_block};
 use crate::parser::{Parse, ParseCursor};
 use crate::rcc::RccError;
 
@@ -20,11 +21,13 @@ impl Expr {
         debug_assert!(Self::is_with_block_token_start(cursor.next_token()?));
         match cursor.next_token()? {
             Token::LeftCurlyBraces => Ok(Block(BlockExpr::parse(cursor)?)),
+            Token::Unsafe => Ok(Block(parse_unsafe_block(cursor)?)),
             Token::While => Ok(While(WhileExpr::parse(cursor)?)),
             Token::Loop => Ok(Loop(LoopExpr::parse(cursor)?)),
-            Token::For => todo!("parse for expr"),
+            Token::For => Ok(For(ForExpr::parse(cursor)?)),
             Token::If => Ok(If(IfExpr::parse(cursor)?)),
-            Token::Match => todo!("parse match expr"),
+            Token::Match => Ok(Match(MatchExpr::parse(cursor)?)),
+            Token::Lifetime(_) => parse_labeled_loop(cursor),
             _ => unreachable!(),
         }
     }
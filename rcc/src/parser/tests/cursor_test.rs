@@ -6,4 +6,14 @@ fn cursor_test() {
     let mut cursor = get_parser("a - b");
     assert_eq!(Ok("a"), cursor.eat_identifier()) ;
     assert!(cursor.eat_token_in(&[Token::Minus, Token::Le]).is_ok());
-}
\ No newline at end of file
+}
+#[test]
+fn eat_gt_test() {
+    let mut cursor = get_parser(">> >= a");
+    assert!(cursor.eat_gt());
+    assert!(cursor.eat_gt());
+    assert!(cursor.eat_gt());
+    assert!(cursor.eat_token_if_eq(Token::Eq));
+    assert!(!cursor.eat_gt());
+    assert_eq!(Ok("a"), cursor.eat_identifier());
+}
@@ -2,12 +2,14 @@ use crate::ast::expr::Expr::*;
 use crate::ast::expr::RangeOp::{DotDot, DotDotEq};
 use crate::ast::expr::UnOp::{Borrow, BorrowMut};
 use crate::ast::expr::{
-    AssignExpr, AssignOp, BinOpExpr, BinOperator, BlockExpr, CallExpr, Expr, FieldAccessExpr,
-    GroupedExpr, IfExpr, LhsExpr, PathExpr, RangeExpr, ReturnExpr, TupleExpr,
+    ArrayExpr, AssignExpr, AssignOp, BinOpExpr, BinOperator, BlockExpr, BreakExpr, CallExpr, CastExpr, ContinueExpr,
+    Expr, FieldAccessExpr, ForExpr, GroupedExpr, IfExpr, LhsExpr, LoopExpr, MatchArm, MatchExpr, MethodCallExpr,
+    PathExpr, RangeExpr, ReturnExpr, StructExpr, StructExprField, TupleExpr, TupleIndexExpr,
 };
 use crate::ast::expr::{LitNumExpr, UnAryExpr, UnOp};
+use crate::ast::pattern::{IdentPattern, LitPattern, Pattern, RangePattern};
 use crate::ast::stmt::Stmt;
-use crate::ast::types::TypeLitNum;
+use crate::ast::types::{TypeAnnotation, TypeGeneric, TypeLitNum};
 use crate::parser::tests::{parse_validate, parse_input, expected_from_file};
 use crate::rcc::RccError;
 use crate::tests::assert_pretty_fmt_eq;
@@ -27,6 +29,30 @@ fn path_expr_test() {
     );
 }
 
+#[test]
+fn path_expr_type_args_test() {
+    let path_expr = |segments: Vec<&str>, type_args| {
+        let mut path_expr = PathExpr::from(segments);
+        path_expr.type_args = type_args;
+        path_expr
+    };
+    parse_validate(
+        vec!["max::<i32, Pair<u8>>", "Pair::<>", "f::<i32", "f::<i32 u8>"],
+        vec![
+            Ok(path_expr(
+                vec!["max"],
+                vec![
+                    "i32".into(),
+                    TypeAnnotation::Generic(TypeGeneric::new("Pair".into(), vec!["u8".into()])),
+                ],
+            )),
+            Ok(path_expr(vec!["Pair"], vec![])),
+            Err("expected `,` or `>` in generic arguments".into()),
+            Err("expected `,` or `>` in generic arguments".into()),
+        ],
+    );
+}
+
 #[test]
 fn lit_expr_test() {
     parse_validate::<Expr>(
@@ -70,6 +96,21 @@ fn return_expr_test() {
     );
 }
 
+#[test]
+fn unsafe_block_expr_test() {
+    parse_validate(
+        vec!["unsafe { 0 }", "unsafe 0"],
+        vec![
+            Ok(Block(
+                BlockExpr::new(0)
+                    .expr_without_block(LitNum(0.into()))
+                    .unsafe_block(),
+            )),
+            Err("error in parsing: except {".into()),
+        ],
+    );
+}
+
 #[test]
 fn borrow_expr_test() {
     parse_validate(
@@ -128,10 +169,37 @@ fn left_paren_test() {
     parse_validate(
         vec!["('1',)", "(1)", "(1,2)", "(1,22,)"],
         vec![
-            Ok(Tuple(TupleExpr(vec![LitChar('1')]))),
+            Ok(Tuple(TupleExpr::new(vec![LitChar('1')]))),
             Ok(Grouped(GroupedExpr::new(LitNum(1.into())))),
-            Ok(Tuple(TupleExpr(vec![LitNum(1.into()), LitNum(2.into())]))),
-            Ok(Tuple(TupleExpr(vec![LitNum(1.into()), LitNum(22.into())]))),
+            Ok(Tuple(TupleExpr::new(vec![LitNum(1.into()), LitNum(2.into())]))),
+            Ok(Tuple(TupleExpr::new(vec![LitNum(1.into()), LitNum(22.into())]))),
+        ],
+    );
+}
+
+#[test]
+fn tuple_index_test() {
+    parse_validate(
+        vec!["()", "a.0", "a.0.1", "a.1.x", "(a.0).2", "a.0u8"],
+        vec![
+            Ok(Tuple(TupleExpr::new(vec![]))),
+            Ok(TupleIndex(TupleIndexExpr::new("a".into(), 0))),
+            Ok(TupleIndex(TupleIndexExpr::new(
+                TupleIndex(TupleIndexExpr::new("a".into(), 0)),
+                1,
+            ))),
+            Ok(FieldAccess(FieldAccessExpr::new(
+                TupleIndex(TupleIndexExpr::new("a".into(), 1)),
+                "x".into(),
+            ))),
+            Ok(TupleIndex(TupleIndexExpr::new(
+                Grouped(GroupedExpr::new(TupleIndex(TupleIndexExpr::new(
+                    "a".into(),
+                    0,
+                )))),
+                2,
+            ))),
+            Err("invalid tuple index `0u8`".into()),
         ],
     );
 }
@@ -186,6 +254,36 @@ fn bin_op_test() {
     );
 }
 
+#[test]
+fn cast_expr_test() {
+    parse_validate(
+        vec!["a as u8", "1 + E::A as i32 * 2", "a as u8 as i64", "-a as u8"],
+        vec![
+            Ok(Cast(CastExpr::new(Path("a".into()), "u8".into()))),
+            Ok(BinOp(BinOpExpr::new(
+                LitNum(1.into()),
+                BinOperator::Plus,
+                BinOp(BinOpExpr::new(
+                    Cast(CastExpr::new(
+                        Path(PathExpr::from(vec!["E", "A"])),
+                        "i32".into(),
+                    )),
+                    BinOperator::Star,
+                    LitNum(2.into()),
+                )),
+            ))),
+            Ok(Cast(CastExpr::new(
+                Cast(CastExpr::new(Path("a".into()), "u8".into())),
+                "i64".into(),
+            ))),
+            Ok(Cast(CastExpr::new(
+                Unary(UnAryExpr::new(UnOp::Neg, Path("a".into()))),
+                "u8".into(),
+            ))),
+        ],
+    );
+}
+
 #[test]
 fn if_expr_test() {
     parse_validate(
@@ -203,13 +301,161 @@ fn if_expr_test() {
     );
 }
 
+#[test]
+fn for_expr_test() {
+    parse_validate(
+        vec!["for i in 0..n {}", "for mut i in ..=3 { i }"],
+        vec![
+            Ok(For(ForExpr::new(
+                Pattern::Identifier(IdentPattern::new_const("i".into())),
+                Range(RangeExpr::new(DotDot).lhs(LitNum(0.into())).rhs("n".into())),
+                BlockExpr::new(0),
+            ))),
+            Ok(For(ForExpr::new(
+                Pattern::Identifier(IdentPattern::new_mut("i".into())),
+                Range(RangeExpr::new(DotDotEq).rhs(LitNum(3.into()))),
+                BlockExpr::new(0).expr_without_block("i".into()),
+            ))),
+        ],
+    );
+}
+
+#[test]
+fn match_expr_test() {
+    parse_validate(
+        vec![
+            "match x { 0 | 1u8 => a, -3..=5 if b => {} n => n, _ => 'c', }",
+            "match c { 'a'..'z' => 1 _ => 2 }",
+        ],
+        vec![
+            Ok(Match(MatchExpr::new(
+                "x".into(),
+                vec![
+                    MatchArm::new(
+                        0,
+                        Pattern::Or(vec![
+                            Pattern::Literal(LitPattern::Int(0, TypeLitNum::I)),
+                            Pattern::Literal(LitPattern::Int(1, TypeLitNum::U8)),
+                        ]),
+                        None,
+                        "a".into(),
+                    ),
+                    MatchArm::new(
+                        0,
+                        Pattern::Range(RangePattern::new(
+                            LitPattern::Int(-3, TypeLitNum::I),
+                            DotDotEq,
+                            LitPattern::Int(5, TypeLitNum::I),
+                        )),
+                        Some("b".into()),
+                        Block(BlockExpr::new(0)),
+                    ),
+                    MatchArm::new(
+                        0,
+                        Pattern::Identifier(IdentPattern::new_const("n".into())),
+                        None,
+                        "n".into(),
+                    ),
+                    MatchArm::new(0, Pattern::Wildcard, None, LitChar('c')),
+                ],
+            ))),
+            Err("expected `,` after match arm".into()),
+        ],
+    );
+}
+
+#[test]
+fn label_test() {
+    let mut loop_expr = LoopExpr::new(BlockExpr::new(0).expr_without_block(Break(
+        BreakExpr::new(Some("'a".into()), Some(LitNum(1.into()))),
+    )));
+    loop_expr.label = Some("'a".into());
+    parse_validate(
+        vec!["'a: loop { break 'a 1 }", "continue 'b", "break", "'a: if true {}"],
+        vec![
+            Ok(Loop(loop_expr)),
+            Ok(Continue(ContinueExpr(Some("'b".into())))),
+            Ok(Break(BreakExpr::new(None, None))),
+            Err("expected `loop`, `while` or `for` after label".into()),
+        ],
+    );
+}
+
 #[test]
 fn call_expr_test() {
     parse_validate(
-        vec!["1.hello()()"],
-        vec![Ok(Call(CallExpr::new(Call(CallExpr::new(FieldAccess(
-            FieldAccessExpr::new(LitNum(1.into()), "hello".into()),
-        ))))))],
+        vec!["1.hello()()", "p.add(1).offset(-i)", "a.f(1).0"],
+        vec![
+            Ok(Call(CallExpr::new(MethodCall(MethodCallExpr::new(
+                LitNum(1.into()),
+                "hello".into(),
+                vec![],
+            ))))),
+            Ok(MethodCall(MethodCallExpr::new(
+                MethodCall(MethodCallExpr::new(
+                    "p".into(),
+                    "add".into(),
+                    vec![LitNum(1.into())],
+                )),
+                "offset".into(),
+                vec![Unary(UnAryExpr::new(UnOp::Neg, "i".into()))],
+            ))),
+            Ok(TupleIndex(TupleIndexExpr::new(
+                MethodCall(MethodCallExpr::new(
+                    "a".into(),
+                    "f".into(),
+                    vec![LitNum(1.into())],
+                )),
+                0,
+            ))),
+        ],
+    );
+}
+
+#[test]
+fn struct_expr_test() {
+    parse_validate(
+        vec![
+            "Point { x: 1, y, }",
+            "a::B { x: 1, ..p }",
+            "if a == B {} else {}",
+            "Point { x: 1, x }.x",
+        ],
+        vec![
+            Ok(Struct(StructExpr::new(
+                "Point".into(),
+                vec![
+                    StructExprField::new("x".into(), LitNum(1.into())),
+                    StructExprField::new("y".into(), "y".into()),
+                ],
+                None,
+            ))),
+            Ok(Struct(StructExpr::new(
+                "a::B".into(),
+                vec![StructExprField::new("x".into(), LitNum(1.into()))],
+                Some("p".into()),
+            ))),
+            // the block is not the fields of a struct expr in the condition
+            Ok(If(IfExpr::from_exprs(
+                vec![BinOp(BinOpExpr::new(
+                    "a".into(),
+                    BinOperator::EqEq,
+                    "B".into(),
+                ))],
+                vec![BlockExpr::new(0), BlockExpr::new(0)],
+            ))),
+            Ok(FieldAccess(FieldAccessExpr::new(
+                Struct(StructExpr::new(
+                    "Point".into(),
+                    vec![
+                        StructExprField::new("x".into(), LitNum(1.into())),
+                        StructExprField::new("x".into(), "x".into()),
+                    ],
+                    None,
+                )),
+                "x".into(),
+            ))),
+        ],
     );
 }
 
@@ -218,7 +464,7 @@ fn place_expr_test() {
     let expecteds: Vec<Result<Expr, RccError>> = vec![
         Err("invalid lhs expr".into()),
         Ok(Expr::Assign(AssignExpr::new(
-            LhsExpr::Deref(Box::new("a".into())),
+            LhsExpr::Deref(UnAryExpr::new(UnOp::Deref, "a".into())),
             AssignOp::Eq,
             Expr::LitNum(4.into()),
         ))),
@@ -236,3 +482,15 @@ fn array_expr_test() {
     let expected = expected_from_file("array_expr_test.txt");
     assert_pretty_fmt_eq(&expected, &result.unwrap());
 }
+
+#[test]
+fn array_elems_test() {
+    parse_validate(
+        vec!["[]", "[1, a,]", "[1, 2"],
+        vec![
+            Ok(Array(ArrayExpr::elems(vec![]))),
+            Ok(Array(ArrayExpr::elems(vec![LitNum(1.into()), "a".into()]))),
+            Err("EOF token".into()),
+        ],
+    );
+}
@@ -1,9 +1,13 @@
 use crate::ast::expr::Expr::{BinOp, LitNum};
 use crate::ast::expr::{BinOpExpr, BinOperator, BlockExpr};
-use crate::ast::item::{FnParam, FnParams, Item, ItemExternalBlock, ItemFn};
+use crate::ast::item::{
+    EnumRepr, EnumVariant, Fields, FnParam, FnParams, GenericParam, Item, ItemConst,
+    ItemExternalBlock, ItemFn, ItemImpl, ItemStatic, ItemStruct, ItemTrait, StructField, Template,
+    TraitItemFn, TupleField, TypeEnum,
+};
 use crate::ast::pattern::{IdentPattern, Pattern};
-use crate::ast::types::TypeAnnotation;
-use crate::ast::Visibility::Priv;
+use crate::ast::types::{PtrKind, TypeAnnotation, TypeArray, TypeGeneric, TypeLitNum, TypePtr};
+use crate::ast::Visibility::{Priv, Pub};
 use crate::parser::tests::{expected_from_file, parse_input, parse_validate};
 use crate::tests::assert_pretty_fmt_eq;
 
@@ -72,3 +76,403 @@ fn item_external_block_test() {
     let expected = expected_from_file("item_external_block.txt");
     assert_pretty_fmt_eq(&expected, &result.unwrap());
 }
+
+#[test]
+fn type_array_test() {
+    parse_validate(
+        vec!["[i32; 3]", "[[u8; 2]; 0]", "&[bool]", "[i32; 3u8]", "[i32, 3]"],
+        vec![
+            Ok(TypeAnnotation::Array(TypeArray::new("i32".into(), 3))),
+            Ok(TypeAnnotation::Array(TypeArray::new(
+                TypeAnnotation::Array(TypeArray::new("u8".into(), 2)),
+                0,
+            ))),
+            Ok(TypeAnnotation::Ptr(TypePtr::new(
+                PtrKind::Ref,
+                TypeAnnotation::Slice(Box::new("bool".into())),
+            ))),
+            Err("expected integer literal for the length of array".into()),
+            Err("expected `]` or `;`, found `Comma`".into()),
+        ],
+    );
+}
+
+#[test]
+fn type_tuple_test() {
+    parse_validate(
+        vec!["()", "(i32)", "(i32,)", "(i32, (bool, u8),)", "(i32 bool)"],
+        vec![
+            Ok(TypeAnnotation::Unit),
+            Ok("i32".into()),
+            Ok(TypeAnnotation::Tuple(vec!["i32".into()])),
+            Ok(TypeAnnotation::Tuple(vec![
+                "i32".into(),
+                TypeAnnotation::Tuple(vec!["bool".into(), "u8".into()]),
+            ])),
+            Err("error in parsing: except ,".into()),
+        ],
+    );
+}
+
+#[test]
+fn item_enum_test() {
+    parse_validate(
+        vec![
+            "enum Shape { Empty, Circle(f64), Rect { w: u32, h: u32 }, }",
+            "#[repr(u8)] enum Color { Red = 1, Green, Blue = -1 }",
+            "#[repr(C, i16)] enum E { A }",
+            "#[repr(u8, i32)] enum E { A }",
+            "#[repr(u7)] enum E { A }",
+            "#[derive(Debug)] fn foo() {}",
+        ],
+        vec![
+            Ok(Item::Enum(TypeEnum::new(
+                Priv,
+                "Shape".into(),
+                EnumRepr::default(),
+                vec![
+                    EnumVariant::new("Empty".into(), Fields::None, None),
+                    EnumVariant::new(
+                        "Circle".into(),
+                        Fields::Tuple(vec![TupleField {
+                            vis: Priv,
+                            _type: "f64".into(),
+                        }]),
+                        None,
+                    ),
+                    EnumVariant::new(
+                        "Rect".into(),
+                        Fields::Struct(vec![
+                            StructField {
+                                vis: Priv,
+                                name: "w".into(),
+                                _type: "u32".into(),
+                            },
+                            StructField {
+                                vis: Priv,
+                                name: "h".into(),
+                                _type: "u32".into(),
+                            },
+                        ]),
+                        None,
+                    ),
+                ],
+            ))),
+            Ok(Item::Enum(TypeEnum::new(
+                Priv,
+                "Color".into(),
+                EnumRepr {
+                    c: false,
+                    int_type: Some(TypeLitNum::U8),
+                },
+                vec![
+                    EnumVariant::new("Red".into(), Fields::None, Some(1)),
+                    EnumVariant::new("Green".into(), Fields::None, None),
+                    EnumVariant::new("Blue".into(), Fields::None, Some(-1)),
+                ],
+            ))),
+            Ok(Item::Enum(TypeEnum::new(
+                Priv,
+                "E".into(),
+                EnumRepr {
+                    c: true,
+                    int_type: Some(TypeLitNum::I16),
+                },
+                vec![EnumVariant::new("A".into(), Fields::None, None)],
+            ))),
+            Err("conflicting representation hints".into()),
+            Err("unrecognized representation hint `u7`".into()),
+            Err("unsupported attribute `derive`".into()),
+        ],
+    );
+}
+
+#[test]
+fn item_impl_test() {
+    let self_ptr = |kind| TypeAnnotation::Ptr(TypePtr::new(kind, "Point".into()));
+    parse_validate(
+        vec![
+            r#"
+            impl Point {
+                pub fn new() -> Self { 0 }
+                fn x(&self) {}
+                fn y(&mut self) {}
+                fn z(mut self, a: i32) {}
+            }
+            "#,
+            "impl &Point {}",
+            "impl Point { struct A; }",
+            "pub impl Point {}",
+            "fn foo(self) {}",
+            "impl Point { fn foo(a: i32, self) {} }",
+        ],
+        vec![
+            Ok(Item::Impl(ItemImpl::new(
+                "Point".into(),
+                vec![
+                    ItemFn::new(
+                        Pub,
+                        "Point::new".into(),
+                        FnParams::new(),
+                        "Point".into(),
+                        BlockExpr::new(0).expr_without_block(LitNum(0.into())),
+                    ),
+                    ItemFn::new(
+                        Priv,
+                        "Point::x".into(),
+                        vec![FnParam::new(
+                            Pattern::Identifier(IdentPattern::new_const("self".into())),
+                            self_ptr(PtrKind::Ref),
+                        )]
+                        .into(),
+                        TypeAnnotation::Unit,
+                        BlockExpr::new(1),
+                    ),
+                    ItemFn::new(
+                        Priv,
+                        "Point::y".into(),
+                        vec![FnParam::new(
+                            Pattern::Identifier(IdentPattern::new_const("self".into())),
+                            self_ptr(PtrKind::MutRef),
+                        )]
+                        .into(),
+                        TypeAnnotation::Unit,
+                        BlockExpr::new(2),
+                    ),
+                    ItemFn::new(
+                        Priv,
+                        "Point::z".into(),
+                        vec![
+                            FnParam::new(
+                                Pattern::Identifier(IdentPattern::new_mut("self".into())),
+                                "Point".into(),
+                            ),
+                            FnParam::new(
+                                Pattern::Identifier(IdentPattern::new_const("a".into())),
+                                "i32".into(),
+                            ),
+                        ]
+                        .into(),
+                        TypeAnnotation::Unit,
+                        BlockExpr::new(3),
+                    ),
+                ],
+            ))),
+            Err("expected a type to implement, found `And`".into()),
+            Err("only functions are supported in `impl` blocks".into()),
+            Err("unnecessary visibility qualifier for `impl`".into()),
+            Err("`self` parameter is only allowed in associated functions".into()),
+            Err("unexpected `self` parameter in function".into()),
+        ],
+    );
+}
+
+#[test]
+fn item_trait_test() {
+    let tokens = |s: &str| s.split(' ').map(|tk| tk.to_string()).collect();
+    let self_param = || {
+        FnParam::new(
+            Pattern::Identifier(IdentPattern::new_const("self".into())),
+            TypeAnnotation::Ptr(TypePtr::new(PtrKind::Ref, "Self".into())),
+        )
+    };
+    parse_validate(
+        vec![
+            "pub trait Shape { fn area(&self) -> i32; fn one(&self) -> i32 { 1 } }",
+            "trait A { struct B; }",
+            "trait A { fn f<T>(); }",
+            "extern \"C\" { fn f<T>(); }",
+        ],
+        vec![
+            Ok(Item::Trait(ItemTrait::new(
+                Pub,
+                "Shape".into(),
+                vec![
+                    TraitItemFn::new(
+                        "area".into(),
+                        vec![self_param()].into(),
+                        "i32".into(),
+                    ),
+                    TraitItemFn::new("one".into(), vec![self_param()].into(), "i32".into())
+                        .default(Template {
+                            tokens: tokens("fn one ( & self ) -> i32 { 1 }"),
+                        }),
+                ],
+            ))),
+            Err("only functions are supported in traits".into()),
+            Err("generic methods are not supported yet".into()),
+            Err("foreign items may not have type parameters".into()),
+        ],
+    );
+}
+
+#[test]
+fn item_generic_fn_test() {
+    let tokens = |s: &str| s.split(' ').map(|tk| tk.to_string()).collect();
+    parse_validate(
+        vec![
+            "fn id<T: A + B, U,>(x: T) -> T { x }",
+            "impl A for B { fn f() {} }",
+            "impl A for B { pub fn f() {} }",
+            "impl A { fn f<T>() {} }",
+            "fn f<T: &A>() {}",
+        ],
+        vec![
+            Ok(Item::Fn(
+                ItemFn::new(
+                    Priv,
+                    "id".into(),
+                    vec![FnParam::new(
+                        Pattern::Identifier(IdentPattern::new_const("x".into())),
+                        "T".into(),
+                    )]
+                    .into(),
+                    "T".into(),
+                    BlockExpr::new(0).expr_without_block("x".into()),
+                )
+                .generics(
+                    vec![
+                        GenericParam::new("T".into(), vec!["A".into(), "B".into()]),
+                        GenericParam::new("U".into(), vec![]),
+                    ],
+                    Template {
+                        tokens: tokens("fn id ( x : T ) -> T { x }"),
+                    },
+                ),
+            )),
+            Ok(Item::Impl(
+                ItemImpl::new(
+                    "B".into(),
+                    vec![ItemFn::new(
+                        Priv,
+                        "B::f".into(),
+                        FnParams::new(),
+                        TypeAnnotation::Unit,
+                        BlockExpr::new(0),
+                    )],
+                )
+                .trait_name("A".into()),
+            )),
+            Err("unnecessary visibility qualifier in trait impls".into()),
+            Err("generic associated functions are not supported yet".into()),
+            Err("error in parsing: except identifier".into()),
+        ],
+    );
+}
+
+#[test]
+fn item_generic_struct_test() {
+    let tokens = |s: &str| s.split(' ').map(|tk| tk.to_string()).collect();
+    let pair = |arg| TypeAnnotation::Generic(TypeGeneric::new("Pair".into(), vec![arg]));
+    parse_validate(
+        vec![
+            "struct Pair<T: A> { a: T, b: *const Pair<Pair<T>> }",
+            "struct P<T> fn",
+        ],
+        vec![
+            Ok(Item::Struct(
+                ItemStruct::new(Priv, "Pair".into())
+                    .struct_fields(vec![
+                        StructField {
+                            vis: Priv,
+                            name: "a".into(),
+                            _type: "T".into(),
+                        },
+                        StructField {
+                            vis: Priv,
+                            name: "b".into(),
+                            _type: TypeAnnotation::Ptr(TypePtr::new(
+                                PtrKind::ConstRawPtr,
+                                pair(pair("T".into())),
+                            )),
+                        },
+                    ])
+                    .generics(
+                        vec![GenericParam::new("T".into(), vec!["A".into()])],
+                        Template {
+                            tokens: tokens(
+                                "struct Pair { a : T , b : * const Pair < Pair < T > > }",
+                            ),
+                        },
+                    ),
+            )),
+            Err("invalid struct definition".into()),
+        ],
+    );
+}
+
+#[test]
+fn item_generic_impl_test() {
+    let tokens = |s: &str| s.split(' ').map(|tk| tk.to_string()).collect();
+    let pair = |arg| TypeAnnotation::Generic(TypeGeneric::new("Pair".into(), vec![arg]));
+    parse_validate(
+        vec![
+            "impl<T: A> Pair<T> { fn f(x: T) -> Self {} }",
+            "impl A for Pair<u8> {}",
+            "impl<T> Pair<T> for A {}",
+            "impl<T> &T {}",
+        ],
+        vec![
+            Ok(Item::Impl(
+                ItemImpl::new(
+                    pair("T".into()),
+                    vec![ItemFn::new(
+                        Priv,
+                        "Pair<T>::f".into(),
+                        vec![FnParam::new(
+                            Pattern::Identifier(IdentPattern::new_const("x".into())),
+                            "T".into(),
+                        )]
+                        .into(),
+                        pair("T".into()),
+                        BlockExpr::new(0),
+                    )],
+                )
+                .generics(
+                    vec![GenericParam::new("T".into(), vec!["A".into()])],
+                    Template {
+                        tokens: tokens("impl Pair < T > { fn f ( x : T ) -> Self { } }"),
+                    },
+                ),
+            )),
+            Ok(Item::Impl(
+                ItemImpl::new(pair("u8".into()), vec![]).trait_name("A".into()),
+            )),
+            Err("generic traits are not supported yet".into()),
+            Err("expected a type to implement, found `And`".into()),
+        ],
+    );
+}
+
+#[test]
+fn item_const_static_test() {
+    parse_validate(
+        vec![
+            "const A: i32 = 1 + 2;",
+            "pub static mut B: u8 = 0;",
+            "static C = 0;",
+            "const D: i32 = 1",
+        ],
+        vec![
+            Ok(Item::Const(ItemConst::new(
+                Priv,
+                "A".into(),
+                "i32".into(),
+                BinOp(BinOpExpr::new(
+                    LitNum(1.into()),
+                    BinOperator::Plus,
+                    LitNum(2.into()),
+                )),
+            ))),
+            Ok(Item::Static(ItemStatic::new(
+                Pub,
+                "B".into(),
+                true,
+                "u8".into(),
+                LitNum(0.into()),
+            ))),
+            Err("missing type for `const` or `static` item".into()),
+            Err("EOF token".into()),
+        ],
+    );
+}
@@ -1,8 +1,10 @@
 use crate::ast::expr::Expr::{Block, LitBool, Loop};
 use crate::ast::expr::UnOp::Borrow;
 use crate::ast::expr::{BlockExpr, Expr, LoopExpr, UnAryExpr, UnOp};
-use crate::ast::pattern::IdentPattern;
 use crate::ast::pattern::Pattern::Identifier;
+use crate::ast::pattern::{
+    FieldPattern, IdentPattern, Pattern, RefPattern, StructPattern, TupleStructPattern,
+};
 use crate::ast::stmt::Stmt::ExprStmt;
 use crate::ast::stmt::{LetStmt, Stmt};
 use crate::parser::stmt::{parse_stmt_or_expr_without_block, StmtOrExpr};
@@ -44,6 +46,104 @@ fn let_stmt_test() {
     validate(inputs, outputs);
 }
 
+#[test]
+fn let_pattern_test() {
+    let inputs = vec![
+        "let _ = a;",
+        "let (a, mut b, ..) = c;",
+        "let (a) = c;",
+        "let &&mut ref mut a = c;",
+        "let Foo { a, b: &c, ref d, .. } = e;",
+        "let () = a;",
+        "let E::B(x, ..) = a;",
+        "let Foo(_,) = a;",
+        "let E::A = a;",
+        "let E::C { x } = a;",
+    ];
+    let outputs = vec![
+        Ok(StmtOrExpr::Stmt(Stmt::Let(
+            LetStmt::new(Pattern::Wildcard).expr("a".into()),
+        ))),
+        Ok(StmtOrExpr::Stmt(Stmt::Let(
+            LetStmt::new(Pattern::Tuple(vec![
+                Identifier(IdentPattern::new_const("a".into())),
+                Identifier(IdentPattern::new_mut("b".into())),
+                Pattern::Rest,
+            ]))
+            .expr("c".into()),
+        ))),
+        Ok(StmtOrExpr::Stmt(Stmt::Let(
+            LetStmt::new(Identifier(IdentPattern::new_const("a".into()))).expr("c".into()),
+        ))),
+        Ok(StmtOrExpr::Stmt(Stmt::Let(
+            LetStmt::new(Pattern::Ref(RefPattern::new(
+                false,
+                Pattern::Ref(RefPattern::new(
+                    true,
+                    Identifier(IdentPattern::new_ref("a".into(), true)),
+                )),
+            )))
+            .expr("c".into()),
+        ))),
+        Ok(StmtOrExpr::Stmt(Stmt::Let(
+            LetStmt::new(Pattern::Struct(StructPattern::new(
+                "Foo".into(),
+                vec![
+                    FieldPattern::new(
+                        "a".into(),
+                        Identifier(IdentPattern::new_const("a".into())),
+                    ),
+                    FieldPattern::new(
+                        "b".into(),
+                        Pattern::Ref(RefPattern::new(
+                            false,
+                            Identifier(IdentPattern::new_const("c".into())),
+                        )),
+                    ),
+                    FieldPattern::new(
+                        "d".into(),
+                        Identifier(IdentPattern::new_ref("d".into(), false)),
+                    ),
+                ],
+                true,
+            )))
+            .expr("e".into()),
+        ))),
+        Ok(StmtOrExpr::Stmt(Stmt::Let(
+            LetStmt::new(Pattern::Tuple(vec![])).expr("a".into()),
+        ))),
+        Ok(StmtOrExpr::Stmt(Stmt::Let(
+            LetStmt::new(Pattern::TupleStruct(TupleStructPattern {
+                path: "E::B".into(),
+                patterns: vec![Identifier(IdentPattern::new_const("x".into())), Pattern::Rest],
+            }))
+            .expr("a".into()),
+        ))),
+        Ok(StmtOrExpr::Stmt(Stmt::Let(
+            LetStmt::new(Pattern::TupleStruct(TupleStructPattern {
+                path: "Foo".into(),
+                patterns: vec![Pattern::Wildcard],
+            }))
+            .expr("a".into()),
+        ))),
+        Ok(StmtOrExpr::Stmt(Stmt::Let(
+            LetStmt::new(Pattern::Path("E::A".into())).expr("a".into()),
+        ))),
+        Ok(StmtOrExpr::Stmt(Stmt::Let(
+            LetStmt::new(Pattern::Struct(StructPattern::new(
+                "E::C".into(),
+                vec![FieldPattern::new(
+                    "x".into(),
+                    Identifier(IdentPattern::new_const("x".into())),
+                )],
+                false,
+            )))
+            .expr("a".into()),
+        ))),
+    ];
+    validate(inputs, outputs);
+}
+
 #[test]
 fn not_end_with_semicolon() {
     validate(
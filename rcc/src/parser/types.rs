@@ -1,11 +1,13 @@
 use crate::ast::item::{StructField, TupleField, TypeEnum};
 use crate::ast::types::TypeAnnotation::Ptr;
 use crate::ast::types::{
-    PtrKind, TypeAnnotation, TypeArray, TypeFnPtr, TypePtr, TypeSlice, TypeTuple,
+    PtrKind, TypeAnnotation, TypeArray, TypeFnPtr, TypeGeneric, TypePtr, TypeSlice, TypeTuple,
 };
 use crate::ast::Visibility;
-use crate::lexer::token::Token;
-use crate::lexer::token::Token::{Comma, LeftParen, RightParen, Semi};
+use crate::lexer::token::{LiteralKind, Token};
+use crate::lexer::token::Token::{
+    Colon, Comma, LeftCurlyBraces, LeftParen, RightCurlyBraces, RightParen, Semi,
+};
 use crate::parser::{Parse, ParseCursor};
 use crate::rcc::RccError;
 
@@ -16,17 +18,44 @@ enum TypeArrayOrSlice {
     Slice(TypeSlice),
 }
 
+/// TypeArrayOrSlice -> Type `]` | Type `;` INTEGER_LITERAL `]`,
+/// `[` has been eaten
 impl Parse for TypeArrayOrSlice {
     fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
-        unimplemented!()
+        let _type = TypeAnnotation::parse(cursor)?;
+        match cursor.bump_token()? {
+            Token::RightSquareBrackets => Ok(Self::Slice(Box::new(_type))),
+            Token::Semi => {
+                let len = match cursor.eat_literal()? {
+                    (LiteralKind::Integer { suffix }, value)
+                        if suffix.is_empty() || suffix == "usize" =>
+                    {
+                        value.parse()?
+                    }
+                    _ => return Err("expected integer literal for the length of array".into()),
+                };
+                cursor.eat_token_eq(Token::RightSquareBrackets)?;
+                Ok(Self::Array(TypeArray::new(_type, len)))
+            }
+            tk => Err(format!("expected `]` or `;`, found `{:?}`", tk).into()),
+        }
     }
 }
 
 impl Parse for TypeAnnotation {
     fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
         match cursor.bump_token()? {
-            Token::Identifier(s) => Ok(Self::Identifier(s.to_string())),
-            Token::LeftParen => Ok(Self::Tuple(TypeTuple::parse(cursor)?)),
+            Token::Identifier(s) => {
+                let name = s.to_string();
+                if cursor.next_token() == Ok(&Token::Lt) {
+                    let args = parse_generic_args(cursor)?;
+                    Ok(Self::Generic(TypeGeneric::new(name, args)))
+                } else {
+                    Ok(Self::Identifier(name))
+                }
+            }
+            Token::SelfType => Ok(Self::Identifier("Self".to_string())),
+            Token::LeftParen => parse_tuple_or_grouped_type(cursor),
             Token::LeftSquareBrackets => {
                 let arr_or_slice = TypeArrayOrSlice::parse(cursor)?;
                 match arr_or_slice {
@@ -45,15 +74,53 @@ impl Parse for TypeAnnotation {
     }
 }
 
-impl Parse for TypeTuple {
-    fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
-        unimplemented!()
+/// GenericArgs -> `<` ( Type `,` )* Type? `>`
+pub fn parse_generic_args(cursor: &mut ParseCursor) -> Result<Vec<TypeAnnotation>, RccError> {
+    cursor.eat_token_eq(Token::Lt)?;
+    let mut args = vec![];
+    while !cursor.eat_gt() {
+        args.push(TypeAnnotation::parse(cursor)?);
+        if !cursor.eat_token_if_eq(Comma) {
+            if cursor.eat_gt() {
+                break;
+            }
+            return Err("expected `,` or `>` in generic arguments".into());
+        }
     }
+    Ok(args)
+}
+
+/// TupleType -> `(` `)` | `(` ( Type , )+ Type? `)`
+///
+/// ParenthesizedType -> `(` Type `)`,
+/// `(` has been eaten, `()` is the unit type
+fn parse_tuple_or_grouped_type(cursor: &mut ParseCursor) -> Result<TypeAnnotation, RccError> {
+    if cursor.eat_token_if_eq(RightParen) {
+        return Ok(TypeAnnotation::Unit);
+    }
+    let first = TypeAnnotation::parse(cursor)?;
+    if cursor.eat_token_if_eq(RightParen) {
+        return Ok(first);
+    }
+    cursor.eat_token_eq(Comma)?;
+    let mut types: TypeTuple = vec![first];
+    while !cursor.eat_token_if_eq(RightParen) {
+        types.push(TypeAnnotation::parse(cursor)?);
+        if !cursor.eat_token_if_eq(Comma) {
+            cursor.eat_token_eq(RightParen)?;
+            break;
+        }
+    }
+    Ok(TypeAnnotation::Tuple(types))
 }
 
 impl Parse for TypeArray {
     fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
-        unimplemented!()
+        cursor.eat_token_eq(Token::LeftSquareBrackets)?;
+        match TypeArrayOrSlice::parse(cursor)? {
+            TypeArrayOrSlice::Array(array) => Ok(array),
+            TypeArrayOrSlice::Slice(_) => Err("expected array type, found slice type".into()),
+        }
     }
 }
 
@@ -100,7 +167,28 @@ impl TypePtr {
 
 impl Parse for Vec<StructField> {
     fn parse(cursor: &mut ParseCursor) -> Result<Self, RccError> {
-        unimplemented!()
+        if cursor.bump_token()? != &LeftCurlyBraces {
+            return Err("invalid struct field: except '{'".into());
+        }
+
+        let mut struct_fields = vec![];
+        loop {
+            if cursor.next_token()? == &RightCurlyBraces {
+                cursor.bump_token()?;
+                break;
+            }
+            let vis = Visibility::parse(cursor)?;
+            let name = cursor.eat_identifier()?.to_string();
+            cursor.eat_token_eq(Colon)?;
+            let _type = TypeAnnotation::parse(cursor)?;
+            struct_fields.push(StructField { vis, name, _type });
+            match cursor.bump_token()? {
+                Comma => {}
+                RightCurlyBraces => break,
+                _ => return Err("invalid struct field: except ','".into()),
+            }
+        }
+        Ok(struct_fields)
     }
 }
 
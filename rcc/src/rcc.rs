@@ -1,12 +1,16 @@
 use crate::analyser::sym_resolver::SymbolResolver;
 use crate::ast::AST;
-use crate::code_gen::riscv32::Riscv32CodeGen;
+use crate::code_gen::c::CCodeGen;
+use crate::code_gen::linker;
+use crate::code_gen::riscv32::{AsmWriter, Riscv32CodeGen};
+use crate::code_gen::riscv32_asm::Riscv32Assembler;
 use crate::code_gen::TargetPlatform;
 use crate::ir::cfg::CFGIR;
 use crate::ir::ir_build::IRBuilder;
 use crate::lexer::Lexer;
 use crate::parser::{Parse, ParseCursor};
 use std::io::{BufReader, BufWriter, Read, Write};
+use strenum::StrEnum;
 
 #[derive(Copy, Clone)]
 pub enum OptimizeLevel {
@@ -14,10 +18,25 @@ pub enum OptimizeLevel {
     One,
 }
 
+/// Kind of the output file: assembly language (C source for the C target),
+/// relocatable object file or executable file linked with the runtime
+#[derive(StrEnum, Copy, Clone, PartialEq)]
+pub enum Emit {
+    Asm,
+    Obj,
+    Exe,
+}
+
 pub struct RcCompiler<R: Read, W: Write> {
     input: BufReader<R>,
     pub output: BufWriter<W>,
     opt_level: OptimizeLevel,
+    target_platform: TargetPlatform,
+    emit: Emit,
+    /// source file name for the debug information
+    debug_file: Option<String>,
+    soft_float: bool,
+    bounds_check: bool,
 }
 
 impl<R: Read, W: Write> RcCompiler<R, W> {
@@ -31,37 +50,104 @@ impl<R: Read, W: Write> RcCompiler<R, W> {
             input: BufReader::new(input),
             output: BufWriter::new(output),
             opt_level,
+            target_platform,
+            emit: Emit::Asm,
+            debug_file: None,
+            soft_float: false,
+            bounds_check: true,
         }
     }
 
+    pub fn with_emit(mut self, emit: Emit) -> Self {
+        self.emit = emit;
+        self
+    }
+
+    /// Emit source line and call frame information of `src_file`
+    /// (only for riscv32 assembly, the object files do not contain debug information)
+    pub fn with_debug_info(mut self, src_file: &str) -> Self {
+        self.debug_file = Some(src_file.to_string());
+        self
+    }
+
+    /// Lower floating-point operations into calls of the runtime (only for riscv32)
+    pub fn with_soft_float(mut self) -> Self {
+        self.soft_float = true;
+        self
+    }
+
+    /// Elide the bounds checks of indexing arrays with non-constant indices
+    pub fn with_no_bounds_check(mut self) -> Self {
+        self.bounds_check = false;
+        self
+    }
+
     pub fn compile(&mut self) -> Result<(), RccError> {
         let mut input = String::new();
         self.input.read_to_string(&mut input)?;
 
         // lex
         let mut lexer = Lexer::new(input.as_str());
-        let token_stream = lexer.tokenize();
+        let (token_stream, token_lines) = lexer.tokenize_with_lines();
 
         // parse
-        let mut cursor = ParseCursor::new(token_stream);
+        let mut cursor = ParseCursor::new(token_stream).with_lines(token_lines);
         let mut ast = AST::parse(&mut cursor)?;
 
         let mut sym_resolver = SymbolResolver::new();
         sym_resolver.visit_file(&mut ast.file)?;
 
-        let mut ir_builder = IRBuilder::new(self.opt_level);
+        let mut ir_builder =
+            IRBuilder::new(self.opt_level).with_addr_size(self.target_platform.addr_size());
+        if !self.bounds_check {
+            ir_builder = ir_builder.with_no_bounds_check();
+        }
         let linear_ir = ir_builder.generate_ir(&mut ast)?;
 
         let cfg_ir = CFGIR::new(linear_ir);
         cfg_ir.reaching_definitions_analysis()?;
 
-        match self.opt_level {
-            OptimizeLevel::Zero => {
-                let mut code_gen = Riscv32CodeGen::new(cfg_ir, &mut self.output, self.opt_level);
-                code_gen.run()?;
+        match self.target_platform {
+            TargetPlatform::Riscv32 => match self.emit {
+                Emit::Asm => {
+                    let mut asm_writer = AsmWriter::new(&mut self.output);
+                    let mut code_gen = Riscv32CodeGen::new(cfg_ir, &mut asm_writer, self.opt_level);
+                    if let Some(src_file) = &self.debug_file {
+                        code_gen = code_gen.with_debug_info(src_file);
+                    }
+                    if self.soft_float {
+                        code_gen = code_gen.with_soft_float();
+                    }
+                    code_gen.run()?;
+                }
+                Emit::Obj | Emit::Exe if self.debug_file.is_some() => {
+                    return Err(
+                        "debug information is only emitted into assembly, use `-g` with `-S`"
+                            .into(),
+                    );
+                }
+                Emit::Obj | Emit::Exe => {
+                    // the machine functions are encoded without printing assembly
+                    let mut assembler = Riscv32Assembler::new();
+                    let mut code_gen = Riscv32CodeGen::new(cfg_ir, &mut assembler, self.opt_level);
+                    if self.soft_float {
+                        code_gen = code_gen.with_soft_float();
+                    }
+                    code_gen.run()?;
+                    let obj = assembler.finish()?;
+                    if self.emit == Emit::Obj {
+                        obj.write(&mut self.output)?;
+                    } else {
+                        linker::link_with_runtime(obj)?.write(&mut self.output)?;
+                    }
+                }
+            },
+            TargetPlatform::C if self.emit != Emit::Asm => {
+                return Err(format!("emit {} is not supported by target c", self.emit).into());
             }
-            OptimizeLevel::One => {
-                todo!()
+            TargetPlatform::C => {
+                let mut code_gen = CCodeGen::new(cfg_ir, &mut self.output);
+                code_gen.run()?;
             }
         }
         Ok(())
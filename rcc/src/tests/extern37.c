@@ -0,0 +1,46 @@
+#include <stdint.h>
+
+struct pair {
+    int32_t a;
+    uint8_t b;
+};
+
+struct point {
+    int16_t x;
+    int16_t y;
+};
+
+struct big {
+    int64_t a;
+    int32_t b;
+    uint8_t c;
+};
+
+struct triple {
+    uint8_t a, b, c;
+};
+
+int32_t pair_sum(struct pair p, int64_t q) {
+    return p.a + p.b + (int32_t)q;
+}
+
+struct point point_scale(struct point p, int32_t k) {
+    struct point r = {p.x * k, p.y * k};
+    return r;
+}
+
+int64_t big_sum(struct big b) {
+    int64_t sum = b.a + b.b + b.c;
+    b.b = 0;
+    return sum;
+}
+
+struct big big_make(int32_t x) {
+    struct big b = {x, x + 1, 7};
+    return b;
+}
+
+struct triple triple_rev(struct triple t) {
+    struct triple r = {t.c, t.b, t.a};
+    return r;
+}
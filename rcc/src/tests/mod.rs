@@ -4,6 +4,8 @@ use std::io::Read;
 
 #[cfg(test)]
 mod rcc_tests;
+#[cfg(test)]
+pub(crate) mod rv32_sim;
 
 pub fn read_from_file(file_name: &str, path: &str) -> String {
     let mut file = File::open(format!("{}/{}", path, file_name)).unwrap();
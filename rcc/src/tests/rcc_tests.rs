@@ -1,5 +1,8 @@
 use crate::code_gen::TargetPlatform;
-use crate::rcc::{OptimizeLevel, RcCompiler, RccError};
+use crate::code_gen::linker::link_with_runtime;
+use crate::code_gen::riscv32_asm::assemble;
+use crate::rcc::{Emit, OptimizeLevel, RcCompiler, RccError};
+use crate::tests::rv32_sim::{self, SimResult};
 use std::io::Read;
 
 fn file_path(file_name: &str) -> String {
@@ -7,9 +10,17 @@ fn file_path(file_name: &str) -> String {
 }
 
 fn test_compile(input: &str, expected_output: &str) -> Result<(), RccError> {
+    test_compile_target(TargetPlatform::Riscv32, input, expected_output)
+}
+
+fn test_compile_target(
+    target_platform: TargetPlatform,
+    input: &str,
+    expected_output: &str,
+) -> Result<(), RccError> {
     let input = std::fs::File::open(file_path(input))?;
     let output = Vec::<u8>::new();
-    let mut rcc = RcCompiler::new(TargetPlatform::Riscv32, input, output, OptimizeLevel::Zero);
+    let mut rcc = RcCompiler::new(target_platform, input, output, OptimizeLevel::Zero);
 
     rcc.compile()?;
 
@@ -28,10 +39,283 @@ fn rcc_test_ok() {
     }
 }
 
+/// The machine functions are encoded directly, the object file is the same as
+/// the assembled output of `-S`.
+#[test]
+fn rcc_test_obj() {
+    for opt_level in [OptimizeLevel::Zero, OptimizeLevel::One] {
+        for i in (1..=5).chain(7..=31).chain(33..=38) {
+            let compile = |emit| {
+                let input = std::fs::File::open(file_path(&format!("in{}.txt", i))).unwrap();
+                let mut rcc =
+                    RcCompiler::new(TargetPlatform::Riscv32, input, Vec::<u8>::new(), opt_level)
+                        .with_emit(emit);
+                rcc.compile().unwrap();
+                rcc.output.into_inner().unwrap()
+            };
+            let asm = String::from_utf8(compile(Emit::Asm)).unwrap();
+            let mut expected = vec![];
+            assemble(&asm).unwrap().write(&mut expected).unwrap();
+            assert_eq!(expected, compile(Emit::Obj), "in{}.txt", i);
+        }
+    }
+}
+
+/// Link with the runtime and run in the simulator.
+fn run_exe(input: &str, opt_level: OptimizeLevel) -> SimResult {
+    let input = std::fs::File::open(file_path(input)).unwrap();
+    let mut rcc = RcCompiler::new(TargetPlatform::Riscv32, input, Vec::<u8>::new(), opt_level)
+        .with_emit(Emit::Exe);
+    rcc.compile().unwrap();
+    rv32_sim::run_elf(&rcc.output.into_inner().unwrap()).unwrap()
+}
+
+#[test]
+fn rcc_test_exe() {
+    for opt_level in [OptimizeLevel::Zero, OptimizeLevel::One] {
+        for (i, exit_code, stdout) in [
+            (1, 5, ""),
+            (2, 102, ""),
+            (4, 233, ""),
+            (5, 0, "a"),
+            (7, 0, "abcdefghij\n"),
+            (8, 0, "abcdefghijklmnopqrstu\n"),
+            (9, 0, "abcdefghijklmnop\n"),
+            (10, 0, "1356789\nabcdefg\nabcdefghij\n"),
+            (11, 0, "abcde\nabcdefghijkl\n"),
+            (12, 0, "13579\nabcdef\n"),
+            (13, 0, "abcdefghijklm\n"),
+            (14, 0, "abcd\n"),
+            (15, 0, "abcdefghij\n"),
+            (16, 0, "abcdefghijk\n"),
+            (18, 0, "abcdefghij\n"),
+            (19, 0, "abcdefghijklmnop\n"),
+            (20, 0, "abcdefghijklmnopq\n"),
+            (21, 0, "abcdefg\n"),
+            (22, 0, "abcdefghi\n"),
+            (23, 0, "abcdefgh\n"),
+            (24, 0, "abcdefghi\n"),
+            (25, 0, "abcdefghijklmn\n"),
+            (26, 0, "abcdehi!+*\n"),
+            (27, 0, "abcd\n"),
+            (28, 0, "abcd\n"),
+            (29, 0, "abcdefghijk\n"),
+            (30, 0, "abcdefghijklmnopq\n"),
+            (31, 0, "abcdefghi\n"),
+            (33, 0, "abcdefghijklmnopqrst\n"),
+            (34, 0, "abcdefghijkl\n"),
+            (35, 0, "abcdefg\n"),
+            (36, 0, "abcdefghi\n"),
+            (38, 0, "abcdef\n"),
+        ] {
+            let res = run_exe(&format!("in{}.txt", i), opt_level);
+            assert_eq!(exit_code, res.exit_code);
+            assert_eq!(stdout.as_bytes(), res.stdout.as_slice());
+        }
+    }
+}
+
+/// Floating-point operations are lowered into calls of the soft-float runtime.
+#[test]
+fn rcc_test_soft_float() {
+    for opt_level in [OptimizeLevel::Zero, OptimizeLevel::One] {
+        for (i, stdout) in [
+            (9, "abcdefghijklmnop\n"),
+            (28, "abcd\n"),
+            (30, "abcdefghijklmnopq\n"),
+            (33, "abcdefghijklmnopqrst\n"),
+            (34, "abcdefghijkl\n"),
+        ] {
+            let input = std::fs::File::open(file_path(&format!("in{}.txt", i))).unwrap();
+            let mut rcc =
+                RcCompiler::new(TargetPlatform::Riscv32, input, Vec::<u8>::new(), opt_level)
+                    .with_emit(Emit::Exe)
+                    .with_soft_float();
+            rcc.compile().unwrap();
+            let res = rv32_sim::run_elf(&rcc.output.into_inner().unwrap()).unwrap();
+            assert_eq!(0, res.exit_code);
+            assert_eq!(stdout.as_bytes(), res.stdout.as_slice());
+        }
+    }
+}
+
+/// Tuples and structs are passed to and returned from C functions by the
+/// ILP32 calling convention, the callees are written in assembly.
+#[test]
+fn rcc_test_extern_c() {
+    for opt_level in [OptimizeLevel::Zero, OptimizeLevel::One] {
+        let input = std::fs::File::open(file_path("in37.txt")).unwrap();
+        let mut rcc = RcCompiler::new(TargetPlatform::Riscv32, input, Vec::<u8>::new(), opt_level)
+            .with_emit(Emit::Asm);
+        rcc.compile().unwrap();
+        let mut asm = String::from_utf8(rcc.output.into_inner().unwrap()).unwrap();
+        asm.push_str(&std::fs::read_to_string(file_path("extern37.S")).unwrap());
+        let mut exe = vec![];
+        link_with_runtime(assemble(&asm).unwrap())
+            .unwrap()
+            .write(&mut exe)
+            .unwrap();
+        let res = rv32_sim::run_elf(&exe).unwrap();
+        assert_eq!(0, res.exit_code);
+        assert_eq!(b"abcdef\n", res.stdout.as_slice());
+    }
+}
+
+/// Indexing out of bounds aborts unless the bounds checks are elided.
+#[test]
+fn rcc_test_bounds_check() {
+    for opt_level in [OptimizeLevel::Zero, OptimizeLevel::One] {
+        let res = run_exe("in17.txt", opt_level);
+        assert_eq!(134, res.exit_code);
+        assert_eq!(b"abcd", res.stdout.as_slice());
+
+        let input = std::fs::File::open(file_path("in17.txt")).unwrap();
+        let mut rcc = RcCompiler::new(TargetPlatform::Riscv32, input, Vec::<u8>::new(), opt_level)
+            .with_emit(Emit::Exe)
+            .with_no_bounds_check();
+        rcc.compile().unwrap();
+        let res = rv32_sim::run_elf(rcc.output.buffer()).unwrap();
+        assert_eq!(0, res.exit_code);
+        assert_eq!(b"abcd", &res.stdout[..4]);
+        assert_eq!(5, res.stdout.len());
+    }
+}
+
+#[test]
+fn rcc_test_debug_info() {
+    let input = std::fs::File::open(file_path("in5.txt")).unwrap();
+    let mut rcc = RcCompiler::new(
+        TargetPlatform::Riscv32,
+        input,
+        Vec::<u8>::new(),
+        OptimizeLevel::Zero,
+    )
+    .with_debug_info("in5.txt");
+    rcc.compile().unwrap();
+    let asm = std::str::from_utf8(rcc.output.buffer()).unwrap();
+    assert_eq!(crate::tests::read_from_file("g_out5.txt", "./src/tests"), asm);
+
+    // debug directives are ignored by the integrated assembler
+    let expected = crate::tests::read_from_file("out5.txt", "./src/tests");
+    let expected = assemble(&expected).unwrap();
+    let obj = assemble(asm).unwrap();
+    assert_eq!(expected.sections[0].data, obj.sections[0].data);
+
+    // debug information is not dropped silently
+    for emit in [Emit::Obj, Emit::Exe] {
+        let input = std::fs::File::open(file_path("in5.txt")).unwrap();
+        let mut rcc = RcCompiler::new(
+            TargetPlatform::Riscv32,
+            input,
+            Vec::<u8>::new(),
+            OptimizeLevel::Zero,
+        )
+        .with_emit(emit)
+        .with_debug_info("in5.txt");
+        assert_eq!(
+            Err("debug information is only emitted into assembly, use `-g` with `-S`".into()),
+            rcc.compile()
+        );
+    }
+}
+
 #[test]
 fn rcc_test_error() {
-    let errors: [Result<(), RccError>; 1] = [Err("`a_5` may not have definition".into())];
-    for (i, err) in (6..=6).zip(errors) {
+    let errors: [Result<(), RccError>; 2] = [
+        Err("`a_5` may not have definition".into()),
+        Err("128-bit integers are not supported by target riscv32".into()),
+    ];
+    for (i, err) in [6, 32].iter().zip(errors) {
         assert_eq!(test_compile(&format!("in{}.txt", i), ""), err);
     }
 }
+
+#[test]
+fn rcc_test_c_ok() {
+    for i in 4..=5 {
+        test_compile_target(
+            TargetPlatform::C,
+            &format!("in{}.txt", i),
+            &format!("c_out{}.txt", i),
+        )
+        .unwrap();
+    }
+}
+
+/// Build the generated C source with the host C compiler, with and without
+/// optimisation, and check the exit code and stdout of `main`, skipped if no C compiler is found.
+#[test]
+fn rcc_test_c_run() {
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    if std::process::Command::new(&cc).arg("--version").output().is_err() {
+        return;
+    }
+    let out_dir = std::env::temp_dir();
+    for (i, exit_code, stdout) in [
+        (1, 5, ""),
+        (4, 233, ""),
+        (5, 0, "a"),
+        (16, 0, "abcdefghijk\n"),
+        (18, 0, "abcdefghij\n"),
+        (19, 0, "abcdefghijklmnop\n"),
+        // `write` bypasses the stdout buffer of `putchar` in C
+        (20, 0, "mnopq\nabcdefghijkl"),
+        (21, 0, "abcdefg\n"),
+        (22, 0, "abcdefghi\n"),
+        (23, 0, "abcdefgh\n"),
+        (24, 0, "abcdefghi\n"),
+        (25, 0, "abcdefghijklmn\n"),
+        (26, 0, "abcdehi!+*\n"),
+        (27, 0, "abcd\n"),
+        (28, 0, "abcd\n"),
+        (29, 0, "abcdefghijk\n"),
+        (30, 0, "abcdefghijklmnopq\n"),
+        (31, 0, "abcdefghi\n"),
+        // 128-bit integers are only supported by target c
+        (32, 43, ""),
+        (33, 0, "abcdefghijklmnopqrst\n"),
+        (34, 0, "abcdefghijkl\n"),
+        (35, 0, "abcdefg\n"),
+        (36, 0, "abcdefghi\n"),
+        (37, 0, "abcdef\n"),
+        (38, 0, "abcdef\n"),
+    ] {
+        // unique names, the tests of other processes may run at the same time
+        let name = format!("rcc_c_run_{}_{}", i, std::process::id());
+        let c_file = out_dir.join(format!("{}.c", name));
+        let exe_file = out_dir.join(&name);
+        let input = std::fs::File::open(file_path(&format!("in{}.txt", i))).unwrap();
+        let output = std::fs::File::create(&c_file).unwrap();
+        let mut rcc = RcCompiler::new(TargetPlatform::C, input, output, OptimizeLevel::Zero);
+        rcc.compile().unwrap();
+        drop(rcc);
+
+        // the optimiser of the C compiler exploits any undefined behavior in the output
+        for opt_flag in ["-O0", "-O2"] {
+            let mut cc_cmd = std::process::Command::new(&cc);
+            cc_cmd.arg("-std=c99").arg("-w").arg(opt_flag).arg(&c_file);
+            // the C functions called by the program
+            let extern_c = file_path(&format!("extern{}.c", i));
+            if std::path::Path::new(&extern_c).exists() {
+                cc_cmd.arg(extern_c);
+            }
+            let cc_output = cc_cmd.arg("-o").arg(&exe_file).output().unwrap();
+            assert!(
+                cc_output.status.success(),
+                "{}",
+                String::from_utf8_lossy(&cc_output.stderr)
+            );
+            let output = std::process::Command::new(&exe_file).output().unwrap();
+            std::fs::remove_file(&exe_file).unwrap();
+            assert_eq!(Some(exit_code), output.status.code(), "in{}.txt {}", i, opt_flag);
+            assert_eq!(
+                stdout.as_bytes(),
+                output.stdout.as_slice(),
+                "in{}.txt {}",
+                i,
+                opt_flag
+            );
+        }
+        std::fs::remove_file(&c_file).unwrap();
+    }
+}
@@ -0,0 +1,396 @@
+//! RV32IMFD user mode simulator for running the executables linked by rcc in tests,
+//! only `write` and `exit` system calls are supported.
+//! Floating-point operations always round to nearest, ties to even, except conversions
+//! to integers with `rtz`.
+
+const MEM_SIZE: usize = 4 << 20;
+const MAX_STEPS: usize = 10_000_000;
+
+pub struct SimResult {
+    pub exit_code: i32,
+    pub stdout: Vec<u8>,
+}
+
+pub fn run_elf(elf: &[u8]) -> Result<SimResult, String> {
+    let mut sim = Simulator::new();
+    sim.load(elf)?;
+    sim.run()
+}
+
+struct Simulator {
+    regs: [u32; 32],
+    /// single-precision values are NaN-boxed
+    fregs: [u64; 32],
+    pc: u32,
+    mem: Vec<u8>,
+    stdout: Vec<u8>,
+}
+
+fn u16_at(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ])
+}
+
+fn box_f32(bits: u32) -> u64 {
+    0xffff_ffff_0000_0000 | bits as u64
+}
+
+impl Simulator {
+    fn new() -> Simulator {
+        let mut regs = [0; 32];
+        // sp
+        regs[2] = MEM_SIZE as u32 - 16;
+        Simulator {
+            regs,
+            fregs: [0; 32],
+            pc: 0,
+            mem: vec![0; MEM_SIZE],
+            stdout: vec![],
+        }
+    }
+
+    fn load(&mut self, elf: &[u8]) -> Result<(), String> {
+        if elf.len() < 52 || &elf[..4] != b"\x7fELF" || elf[4] != 1 {
+            return Err("not an ELF32 file".into());
+        }
+        if u16_at(elf, 16) != 2 || u16_at(elf, 18) != 243 {
+            return Err("not a RISC-V executable".into());
+        }
+        self.pc = u32_at(elf, 24);
+        let phoff = u32_at(elf, 28) as usize;
+        for i in 0..u16_at(elf, 44) as usize {
+            let ph = phoff + i * 32;
+            // PT_LOAD
+            if u32_at(elf, ph) != 1 {
+                continue;
+            }
+            let offset = u32_at(elf, ph + 4) as usize;
+            let vaddr = u32_at(elf, ph + 8) as usize;
+            let file_size = u32_at(elf, ph + 16) as usize;
+            let mem_size = u32_at(elf, ph + 20) as usize;
+            if vaddr + mem_size > MEM_SIZE {
+                return Err(format!("segment at {:#x} is out of memory", vaddr));
+            }
+            self.mem[vaddr..vaddr + file_size].copy_from_slice(&elf[offset..offset + file_size]);
+        }
+        Ok(())
+    }
+
+    fn addr(&self, addr: u32, size: usize) -> Result<usize, String> {
+        let addr = addr as usize;
+        if addr + size > MEM_SIZE {
+            Err(format!(
+                "access {:#x} out of memory at pc {:#x}",
+                addr, self.pc
+            ))
+        } else {
+            Ok(addr)
+        }
+    }
+
+    fn load_mem(&self, addr: u32, size: usize) -> Result<u32, String> {
+        let a = self.addr(addr, size)?;
+        let mut bytes = [0; 4];
+        bytes[..size].copy_from_slice(&self.mem[a..a + size]);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn store_mem(&mut self, addr: u32, size: usize, value: u32) -> Result<(), String> {
+        let a = self.addr(addr, size)?;
+        self.mem[a..a + size].copy_from_slice(&value.to_le_bytes()[..size]);
+        Ok(())
+    }
+
+    fn set_reg(&mut self, rd: usize, value: u32) {
+        if rd != 0 {
+            self.regs[rd] = value;
+        }
+    }
+
+    fn run(&mut self) -> Result<SimResult, String> {
+        for _ in 0..MAX_STEPS {
+            let inst = self.load_mem(self.pc, 4)?;
+            if let Some(exit_code) = self.step(inst)? {
+                return Ok(SimResult {
+                    exit_code,
+                    stdout: std::mem::take(&mut self.stdout),
+                });
+            }
+        }
+        Err("too many steps".into())
+    }
+
+    /// Execute one instruction, return the exit code if the program exits.
+    fn step(&mut self, inst: u32) -> Result<Option<i32>, String> {
+        let opcode = inst & 0x7f;
+        let rd = ((inst >> 7) & 0x1f) as usize;
+        let funct3 = (inst >> 12) & 0x7;
+        let rs1 = self.regs[((inst >> 15) & 0x1f) as usize];
+        let rs2 = self.regs[((inst >> 20) & 0x1f) as usize];
+        let funct7 = inst >> 25;
+        let imm_i = (inst as i32) >> 20;
+        let imm_s = ((inst as i32) >> 25) << 5 | ((inst >> 7) & 0x1f) as i32;
+        let imm_b = ((inst as i32) >> 31) << 12
+            | (((inst >> 7) & 1) << 11) as i32
+            | (((inst >> 25) & 0x3f) << 5) as i32
+            | (((inst >> 8) & 0xf) << 1) as i32;
+        let imm_j = ((inst as i32) >> 31) << 20
+            | (inst & 0xff000) as i32
+            | (((inst >> 20) & 1) << 11) as i32
+            | (((inst >> 21) & 0x3ff) << 1) as i32;
+        let mut next_pc = self.pc.wrapping_add(4);
+        match opcode {
+            // lui
+            0x37 => self.set_reg(rd, inst & 0xfffff000),
+            // auipc
+            0x17 => self.set_reg(rd, self.pc.wrapping_add(inst & 0xfffff000)),
+            // jal
+            0x6f => {
+                self.set_reg(rd, next_pc);
+                next_pc = self.pc.wrapping_add(imm_j as u32);
+            }
+            // jalr
+            0x67 => {
+                let target = rs1.wrapping_add(imm_i as u32) & !1;
+                self.set_reg(rd, next_pc);
+                next_pc = target;
+            }
+            0x63 => {
+                let taken = match funct3 {
+                    0 => rs1 == rs2,
+                    1 => rs1 != rs2,
+                    4 => (rs1 as i32) < (rs2 as i32),
+                    5 => (rs1 as i32) >= (rs2 as i32),
+                    6 => rs1 < rs2,
+                    7 => rs1 >= rs2,
+                    _ => return Err(self.illegal(inst)),
+                };
+                if taken {
+                    next_pc = self.pc.wrapping_add(imm_b as u32);
+                }
+            }
+            0x03 => {
+                let addr = rs1.wrapping_add(imm_i as u32);
+                let value = match funct3 {
+                    0 => self.load_mem(addr, 1)? as i8 as i32 as u32,
+                    1 => self.load_mem(addr, 2)? as i16 as i32 as u32,
+                    2 => self.load_mem(addr, 4)?,
+                    4 => self.load_mem(addr, 1)?,
+                    5 => self.load_mem(addr, 2)?,
+                    _ => return Err(self.illegal(inst)),
+                };
+                self.set_reg(rd, value);
+            }
+            0x23 => {
+                let addr = rs1.wrapping_add(imm_s as u32);
+                match funct3 {
+                    0 => self.store_mem(addr, 1, rs2)?,
+                    1 => self.store_mem(addr, 2, rs2)?,
+                    2 => self.store_mem(addr, 4, rs2)?,
+                    _ => return Err(self.illegal(inst)),
+                }
+            }
+            0x13 => {
+                let imm = imm_i as u32;
+                let shamt = imm & 0x1f;
+                let value = match funct3 {
+                    0 => rs1.wrapping_add(imm),
+                    1 => rs1 << shamt,
+                    2 => ((rs1 as i32) < imm_i) as u32,
+                    3 => (rs1 < imm) as u32,
+                    4 => rs1 ^ imm,
+                    5 if funct7 == 0x20 => ((rs1 as i32) >> shamt) as u32,
+                    5 => rs1 >> shamt,
+                    6 => rs1 | imm,
+                    _ => rs1 & imm,
+                };
+                self.set_reg(rd, value);
+            }
+            0x33 => {
+                let value = match (funct7, funct3) {
+                    (0, 0) => rs1.wrapping_add(rs2),
+                    (0x20, 0) => rs1.wrapping_sub(rs2),
+                    (0, 1) => rs1 << (rs2 & 0x1f),
+                    (0, 2) => ((rs1 as i32) < (rs2 as i32)) as u32,
+                    (0, 3) => (rs1 < rs2) as u32,
+                    (0, 4) => rs1 ^ rs2,
+                    (0, 5) => rs1 >> (rs2 & 0x1f),
+                    (0x20, 5) => ((rs1 as i32) >> (rs2 & 0x1f)) as u32,
+                    (0, 6) => rs1 | rs2,
+                    (0, 7) => rs1 & rs2,
+                    (1, 0) => rs1.wrapping_mul(rs2),
+                    (1, 1) => ((rs1 as i32 as i64 * rs2 as i32 as i64) >> 32) as u32,
+                    (1, 2) => ((rs1 as i32 as i64 * rs2 as i64) >> 32) as u32,
+                    (1, 3) => ((rs1 as u64 * rs2 as u64) >> 32) as u32,
+                    (1, 4) => match rs2 {
+                        0 => u32::MAX,
+                        _ => (rs1 as i32).wrapping_div(rs2 as i32) as u32,
+                    },
+                    (1, 5) => rs1.checked_div(rs2).unwrap_or(u32::MAX),
+                    (1, 6) => match rs2 {
+                        0 => rs1,
+                        _ => (rs1 as i32).wrapping_rem(rs2 as i32) as u32,
+                    },
+                    (1, 7) => rs1.checked_rem(rs2).unwrap_or(rs1),
+                    _ => return Err(self.illegal(inst)),
+                };
+                self.set_reg(rd, value);
+            }
+            // flw, fld
+            0x07 => {
+                let addr = rs1.wrapping_add(imm_i as u32);
+                self.fregs[rd] = match funct3 {
+                    2 => box_f32(self.load_mem(addr, 4)?),
+                    3 => {
+                        let lo = self.load_mem(addr, 4)? as u64;
+                        let hi = self.load_mem(addr.wrapping_add(4), 4)? as u64;
+                        hi << 32 | lo
+                    }
+                    _ => return Err(self.illegal(inst)),
+                };
+            }
+            // fsw, fsd
+            0x27 => {
+                let addr = rs1.wrapping_add(imm_s as u32);
+                let value = self.fregs[((inst >> 20) & 0x1f) as usize];
+                match funct3 {
+                    2 => self.store_mem(addr, 4, value as u32)?,
+                    3 => {
+                        self.store_mem(addr, 4, value as u32)?;
+                        self.store_mem(addr.wrapping_add(4), 4, (value >> 32) as u32)?;
+                    }
+                    _ => return Err(self.illegal(inst)),
+                }
+            }
+            0x53 => self.fp_op(inst)?,
+            // ecall
+            0x73 if inst == 0x73 => match self.regs[17] {
+                // write
+                64 => {
+                    let (fd, buf, len) = (self.regs[10], self.regs[11], self.regs[12]);
+                    let a = self.addr(buf, len as usize)?;
+                    if fd == 1 {
+                        self.stdout
+                            .extend_from_slice(&self.mem[a..a + len as usize]);
+                    }
+                    self.regs[10] = len;
+                }
+                // exit, exit_group
+                93 | 94 => return Ok(Some(self.regs[10] as i32)),
+                n => return Err(format!("unsupported system call {}", n)),
+            },
+            _ => return Err(self.illegal(inst)),
+        }
+        self.pc = next_pc;
+        Ok(None)
+    }
+
+    /// Instructions of OP-FP
+    fn fp_op(&mut self, inst: u32) -> Result<(), String> {
+        let rd = ((inst >> 7) & 0x1f) as usize;
+        let funct3 = (inst >> 12) & 0x7;
+        let rs1 = ((inst >> 15) & 0x1f) as usize;
+        let rs2 = ((inst >> 20) & 0x1f) as usize;
+        let funct5 = inst >> 27;
+        let double = (inst >> 25) & 0x3 == 1;
+        // operands are computed in f64, which is exact for f32
+        let (a, b) = if double {
+            (f64::from_bits(self.fregs[rs1]), f64::from_bits(self.fregs[rs2]))
+        } else {
+            (
+                f32::from_bits(self.fregs[rs1] as u32) as f64,
+                f32::from_bits(self.fregs[rs2] as u32) as f64,
+            )
+        };
+        let float = |x: f64| {
+            if double {
+                x.to_bits()
+            } else {
+                box_f32((x as f32).to_bits())
+            }
+        };
+        match funct5 {
+            // the results of f32 operations are rounded once because f64 has enough precision
+            0x00 => self.fregs[rd] = float(a + b),
+            0x01 => self.fregs[rd] = float(a - b),
+            0x02 => self.fregs[rd] = float(a * b),
+            0x03 => self.fregs[rd] = float(a / b),
+            0x0b => self.fregs[rd] = float(a.sqrt()),
+            // sign injection
+            0x04 => {
+                let sign_bit = if double { 1 << 63 } else { 1 << 31 };
+                let sign = match funct3 {
+                    0 => self.fregs[rs2],
+                    1 => !self.fregs[rs2],
+                    2 => self.fregs[rs1] ^ self.fregs[rs2],
+                    _ => return Err(self.illegal(inst)),
+                } & sign_bit;
+                let value = (self.fregs[rs1] & !sign_bit) | sign;
+                self.fregs[rd] = if double { value } else { box_f32(value as u32) };
+            }
+            0x05 => match funct3 {
+                0 => self.fregs[rd] = float(a.min(b)),
+                1 => self.fregs[rd] = float(a.max(b)),
+                _ => return Err(self.illegal(inst)),
+            },
+            0x14 => {
+                let value = match funct3 {
+                    0 => a <= b,
+                    1 => a < b,
+                    2 => a == b,
+                    _ => return Err(self.illegal(inst)),
+                };
+                self.set_reg(rd, value as u32);
+            }
+            // float to integer, saturated, NaN is converted to the maximum
+            0x18 => {
+                let a = if funct3 == 1 {
+                    a.trunc()
+                } else {
+                    a.round_ties_even()
+                };
+                let value = match rs2 {
+                    0 if a.is_nan() => i32::MAX as u32,
+                    0 => a as i32 as u32,
+                    1 if a.is_nan() => u32::MAX,
+                    1 => a as u32,
+                    _ => return Err(self.illegal(inst)),
+                };
+                self.set_reg(rd, value);
+            }
+            // integer to float
+            0x1a => {
+                let value = match rs2 {
+                    0 => self.regs[rs1] as i32 as f64,
+                    1 => self.regs[rs1] as f64,
+                    _ => return Err(self.illegal(inst)),
+                };
+                self.fregs[rd] = float(value);
+            }
+            // fcvt.s.d, fcvt.d.s
+            0x08 => {
+                self.fregs[rd] = if double {
+                    (f32::from_bits(self.fregs[rs1] as u32) as f64).to_bits()
+                } else {
+                    box_f32((f64::from_bits(self.fregs[rs1]) as f32).to_bits())
+                };
+            }
+            0x1c if funct3 == 0 => self.set_reg(rd, self.fregs[rs1] as u32),
+            0x1e => self.fregs[rd] = box_f32(self.regs[rs1]),
+            _ => return Err(self.illegal(inst)),
+        }
+        Ok(())
+    }
+
+    fn illegal(&self, inst: u32) -> String {
+        format!("illegal instruction {:#010x} at pc {:#x}", inst, self.pc)
+    }
+}